@@ -0,0 +1,125 @@
+// Benchmarks of the setup, the prover and the verifier over circuits of 2^6 to 2^16 gates, with
+// the streaming prover and the phases that dominate the prover timed on their own: the FFTs
+// over H and over the quotient domain, the multi-scalar multiplications of the commitments, and
+// the grand products of the permutation and lookup arguments. The suite has its own `main`
+// (`harness = false`), so that it needs no benchmark framework, and prints one row per size:
+//
+// cargo bench
+// cargo bench --features parallel
+//
+// the second one comparing the parallel prover with the sequential one.
+// `BENCH_MAX_LOG` lowers the largest size, e.g. `BENCH_MAX_LOG=12 cargo bench` for a quick run.
+use blstrs::Scalar;
+use dummy_plonk::kernels::{lookup_product, permutation_product};
+use dummy_plonk::{
+    Blake2bTranscript, ComputationTrace, EvaluationDomain, PlonkCircuit, PlonkVerifier, Prover,
+    Transcript, Wire,
+};
+use ff::Field;
+use rand_chacha::ChaCha20Rng;
+use rand_core::SeedableRng;
+use std::time::{Duration, Instant};
+
+const MIN_LOG: u32 = 6;
+const MAX_LOG: u32 = 16;
+
+// Runs `f` and returns its result with the time it took.
+fn time<R>(f: impl FnOnce() -> R) -> (R, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+// Chain of `gates` doublings, padded to the next power of two, with its trace.
+fn doublings(gates: usize) -> (PlonkCircuit, ComputationTrace) {
+    let mut circuit = PlonkCircuit::init();
+    let wires = (0..gates).map(|_| circuit.add_gate()).collect::<Vec<_>>();
+    circuit.pad_next_power_of_two();
+    for (i, (left, right, out)) in wires.iter().enumerate() {
+        circuit.connect(*left, *right).unwrap();
+        if let Some((next_left, _, _)) = wires.get(i + 1) {
+            circuit.connect(*out, *next_left).unwrap();
+        }
+    }
+
+    let mut value = Scalar::one();
+    let mut trace = ComputationTrace::new(&circuit);
+    for (left, right, out) in wires.iter() {
+        trace.set(*left, value);
+        trace.set(*right, value);
+        value = value.double();
+        trace.set(*out, value);
+    }
+    (circuit, trace)
+}
+
+fn main() {
+    let max_log = std::env::var("BENCH_MAX_LOG")
+        .ok()
+        .and_then(|log| log.parse().ok())
+        .unwrap_or(MAX_LOG);
+    let mut rng = ChaCha20Rng::from_seed([0u8; 32]);
+    println!(
+        "{:>6} {:>11} {:>11} {:>11} {:>11} {:>11} {:>11} {:>11} {:>11}",
+        "n", "setup", "prove", "streaming", "verify", "fft", "coset fft", "msm", "products"
+    );
+    for log in MIN_LOG..=max_log {
+        // Leave room for the rows the circuit adds, so that it pads to 2^log.
+        let (mut circuit, trace) = doublings(3 << (log - 2));
+        let ((prover_key, verifier_key), setup) = time(|| circuit.setup(&mut rng).unwrap());
+        let n = circuit.nr_constraints;
+
+        let (proof, prove) = time(|| {
+            let mut transcript = Blake2bTranscript::new(b"benchmark");
+            Prover::prove(&[], &prover_key, &trace, &mut transcript).unwrap()
+        });
+        let (streaming_proof, streaming) = time(|| {
+            let mut transcript = Blake2bTranscript::new(b"benchmark");
+            Prover::prove_streaming(&[], &prover_key, &trace, &mut transcript).unwrap()
+        });
+        let mut transcript = Blake2bTranscript::new(b"benchmark");
+        assert!(
+            PlonkVerifier::verify(&[], &verifier_key, &streaming_proof, &mut transcript).is_ok()
+        );
+        let (result, verify) = time(|| {
+            let mut transcript = Blake2bTranscript::new(b"benchmark");
+            PlonkVerifier::verify(&[], &verifier_key, &proof, &mut transcript)
+        });
+        assert!(result.is_ok());
+
+        // A wire polynomial to ifft and fft over H, and to extend to the quotient domain, as
+        // the prover does for each of the polynomials of the quotient.
+        let column = (0..n)
+            .map(|row| trace.value(Wire::left(row)))
+            .collect::<Vec<_>>();
+        let (polynomial, ifft) = time(|| prover_key.domain.ifft(&column));
+        let (_, fft) = time(|| prover_key.domain.fft(&polynomial));
+        let quotient_domain = EvaluationDomain::new((4 * n + 7).next_power_of_two()).unwrap();
+        let (_, coset_fft) = time(|| quotient_domain.coset_fft(&polynomial));
+
+        let mut column = || (0..n).map(|_| Scalar::random(&mut rng)).collect::<Vec<_>>();
+        let random = prover_key.domain.ifft(&column());
+        let (_, msm) = time(|| prover_key.scheme.commit(&random));
+
+        let [beta, gamma, delta, epsilon] = [(); 4].map(|_| Scalar::random(&mut rng));
+        let mut column = || (0..n).map(|_| Scalar::random(&mut rng)).collect::<Vec<_>>();
+        let (queries, table) = (column(), column());
+        let (_, products) = time(|| {
+            permutation_product(&prover_key, &trace, &beta, &gamma).unwrap();
+            lookup_product(&queries, &table, &queries, &table, &delta, &epsilon).unwrap();
+        });
+
+        println!(
+            "{:>6} {:>11.2?} {:>11.2?} {:>11.2?} {:>11.2?} {:>11.2?} {:>11.2?} {:>11.2?} {:>11.2?}",
+            n,
+            setup,
+            prove,
+            streaming,
+            verify,
+            fft + ifft,
+            coset_fft,
+            msm,
+            products
+        );
+    }
+}
@@ -0,0 +1,47 @@
+/* C interface of dummy-plonk, built with `cargo build --release --features ffi`. Inputs are
+ * byte buffers in the encodings of the crate (see src/bindings.rs), and functions return a
+ * status code. Buffers and verifier keys returned by the library are released by the caller. */
+#ifndef PLONK_H
+#define PLONK_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define PLONK_OK 0
+#define PLONK_INVALID_PROOF 1
+#define PLONK_ERROR (-1)
+
+/* Bytes allocated by the library, released with plonk_buffer_free. */
+typedef struct {
+    uint8_t *data;
+    size_t len;
+} PlonkBuffer;
+
+/* Decoded verifier key, released with plonk_vk_free. */
+typedef struct PlonkVerifierKey PlonkVerifierKey;
+
+/* Message of the last error of the calling thread, valid until the next call. */
+const char *plonk_last_error(void);
+
+/* Encoded prover and verifier keys of an encoded circuit, with the SRS of a Powers of Tau
+ * response file of tau_powers_length powers in G2. */
+int32_t plonk_setup(const uint8_t *circuit, size_t circuit_len, const uint8_t *srs,
+                    size_t srs_len, size_t tau_powers_length, PlonkBuffer *pk_out,
+                    PlonkBuffer *vk_out);
+
+/* Proof that the encoded trace satisfies the circuit of the encoded prover key. */
+int32_t plonk_prove(const uint8_t *pk, size_t pk_len, const uint8_t *witness,
+                    size_t witness_len, PlonkBuffer *proof_out);
+
+/* Decodes a verifier key, returning NULL on error. */
+PlonkVerifierKey *plonk_load_vk(const uint8_t *vk, size_t vk_len);
+
+/* Checks a proof for public inputs given as concatenated 32 byte big endian scalars. */
+int32_t plonk_verify(const PlonkVerifierKey *vk, const uint8_t *proof, size_t proof_len,
+                     const uint8_t *public_inputs, size_t public_inputs_len);
+
+void plonk_vk_free(PlonkVerifierKey *vk);
+
+void plonk_buffer_free(PlonkBuffer buffer);
+
+#endif
@@ -0,0 +1,242 @@
+// Accumulation of the final check of the verifier, in the style of Halo. An accumulator is a
+// pair of points (lhs, rhs) of G1, valid if e(lhs, x * g2) = e(rhs, g2), which is exactly the
+// form of the deferred check of a proof. Accumulating a proof runs its verifier up to the
+// deferred check, and folds its points into the accumulator as acc' = new + r * acc, with r
+// drawn from a transcript over both pairs, so that the result is only valid if both were, but
+// with negligible probability. Two accumulators fold the same way, which allows trees of
+// proofs as well as chains.
+//
+// The pairings, the most expensive part of a verification, are left to `finalize`, which runs
+// them once for the whole accumulator, with the points of G2 of a trusted verifier key. The
+// accumulator does not carry them, as a decoded one could otherwise choose x * g2 = g2 and
+// lhs = rhs. An incrementally verifiable chain of proofs carries the
+// accumulator, whose size does not depend on the number of proofs it covers, and only
+// finalizes it at the end.
+use crate::error::PlonkError;
+use crate::kzg10::pairing_check;
+use crate::proof::PlonkProof;
+use crate::transcript::Transcript;
+use crate::verifier::{PlonkVerifier, VerifierKey};
+use alloc::vec::Vec;
+use blstrs::{G1Affine, G1Projective, Scalar};
+use group::prime::PrimeCurveAffine;
+use group::Curve;
+
+/// Points (lhs, rhs) of the folded checks of the accumulated proofs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Accumulator {
+    pub lhs: G1Affine,
+    pub rhs: G1Affine,
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Accumulator {
+    /// Size of the encoding of an accumulator.
+    pub const SIZE: usize = 2 * 48;
+
+    /// Empty accumulator, which is valid.
+    pub fn new() -> Self {
+        Accumulator {
+            lhs: G1Affine::identity(),
+            rhs: G1Affine::identity(),
+        }
+    }
+
+    /// Runs the verifier of `proof` with a transcript created with `label` up to the final
+    /// check, and folds the check into the accumulator. Errors are those the verifier returns
+    /// before the final check. Proofs of verifier keys of another SRS than the one of
+    /// `finalize` make it fail.
+    pub fn accumulate<T: Transcript>(
+        &mut self,
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey,
+        proof: &PlonkProof,
+        label: &'static [u8],
+    ) -> Result<(), PlonkError> {
+        let mut transcript = T::new(label);
+        let (lhs, rhs) =
+            PlonkVerifier::deferred_check(pub_in, verifier_key, proof, &mut transcript)?
+                .pairing_points(verifier_key, proof);
+        self.fold::<T>(&Accumulator { lhs, rhs });
+        Ok(())
+    }
+
+    /// Folds `other` into the accumulator, which afterwards covers the proofs of both.
+    pub fn fold<T: Transcript>(&mut self, other: &Accumulator) {
+        let mut transcript = T::new(b"accumulation");
+        transcript.append_point(b"accumulator lhs", &self.lhs);
+        transcript.append_point(b"accumulator rhs", &self.rhs);
+        transcript.append_point(b"folded lhs", &other.lhs);
+        transcript.append_point(b"folded rhs", &other.rhs);
+        let r = transcript.challenge_scalar(b"r");
+
+        let points = [
+            G1Projective::from(other.lhs) + self.lhs * r,
+            G1Projective::from(other.rhs) + self.rhs * r,
+        ];
+        let mut affine = [G1Affine::identity(); 2];
+        G1Projective::batch_normalize(&points, &mut affine);
+        [self.lhs, self.rhs] = affine;
+    }
+
+    /// Checks the accumulator with a single multi-pairing against the points of G2 of
+    /// `verifier_key`, which passes only if all the accumulated proofs are valid and of
+    /// verifier keys over the same SRS.
+    pub fn finalize(&self, verifier_key: &VerifierKey) -> Result<(), PlonkError> {
+        pairing_check(&verifier_key.scheme.powers_x_g2, &self.lhs, &self.rhs)
+    }
+
+    /// Encodes the accumulator as its compressed points, lhs and rhs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        bytes.extend_from_slice(&self.lhs.to_compressed());
+        bytes.extend_from_slice(&self.rhs.to_compressed());
+        bytes
+    }
+
+    /// Decodes an accumulator encoded with `to_bytes`, checking that all points are in the
+    /// prime order subgroups.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PlonkError> {
+        if bytes.len() != Self::SIZE {
+            return Err(PlonkError::InvalidLength {
+                expected: Self::SIZE,
+                got: bytes.len(),
+            });
+        }
+        let g1 = |chunk: &[u8]| {
+            Option::from(G1Affine::from_compressed(chunk.try_into().unwrap()))
+                .ok_or(PlonkError::InvalidPoint)
+        };
+        Ok(Accumulator {
+            lhs: g1(&bytes[..48])?,
+            rhs: g1(&bytes[48..])?,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::kzg10::Kzg10;
+    use crate::plonk::PlonkCircuit;
+    use crate::prover::Prover;
+    use crate::transcript::Blake2bTranscript;
+    use crate::witness::WitnessBuilder;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    // Proof that the public input is x * x + x, for x = `x`, with its verifier key.
+    fn prove(x: u64, srs: Kzg10) -> (VerifierKey, PlonkProof, Vec<Scalar>) {
+        let mut circuit = PlonkCircuit::init();
+        let y = circuit.public_input();
+        let (x_l, x_r, square) = circuit.mult_gate();
+        let (square_copy, x_copy, sum) = circuit.add_gate();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(x_l, x_copy).unwrap();
+        circuit.connect(square, square_copy).unwrap();
+        circuit.connect(sum, y).unwrap();
+        let (prover_key, verifier_key) = circuit.setup_with_srs(srs).unwrap();
+
+        let mut builder = WitnessBuilder::new(&circuit);
+        builder.assign(x_l, Scalar::from(x)).unwrap();
+        let trace = builder.build().unwrap();
+        let pub_in = vec![Scalar::from(x * x + x)];
+        let mut transcript = Blake2bTranscript::new(b"accumulation");
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut transcript).unwrap();
+        (verifier_key, proof, pub_in)
+    }
+
+    #[test]
+    fn test_accumulator() {
+        let srs = Kzg10::setup(16, &mut ChaCha20Rng::from_seed([0u8; 32]));
+        let proofs = (1..4).map(|x| prove(x, srs.clone())).collect::<Vec<_>>();
+        let trusted_key = &proofs[0].0;
+
+        let mut accumulator = Accumulator::new();
+        assert!(accumulator.finalize(trusted_key).is_ok());
+        for (verifier_key, proof, pub_in) in proofs.iter() {
+            accumulator
+                .accumulate::<Blake2bTranscript>(pub_in, verifier_key, proof, b"accumulation")
+                .unwrap();
+        }
+        assert!(accumulator.finalize(trusted_key).is_ok());
+        let decoded = Accumulator::from_bytes(&accumulator.to_bytes()).unwrap();
+        assert_eq!(decoded, accumulator);
+
+        // Accumulators of parts of the chain fold into one.
+        let mut left = Accumulator::new();
+        let (verifier_key, proof, pub_in) = &proofs[0];
+        left.accumulate::<Blake2bTranscript>(pub_in, verifier_key, proof, b"accumulation")
+            .unwrap();
+        left.fold::<Blake2bTranscript>(&decoded);
+        assert!(left.finalize(trusted_key).is_ok());
+
+        // A single wrong statement makes the accumulator fail from then on.
+        let (verifier_key, proof, _) = &proofs[1];
+        let mut wrong = accumulator.clone();
+        wrong
+            .accumulate::<Blake2bTranscript>(
+                &[Scalar::from(7)],
+                verifier_key,
+                proof,
+                b"accumulation",
+            )
+            .unwrap();
+        assert_eq!(
+            wrong.finalize(trusted_key),
+            Err(PlonkError::VerificationFailed)
+        );
+        let (verifier_key, proof, pub_in) = &proofs[2];
+        wrong
+            .accumulate::<Blake2bTranscript>(pub_in, verifier_key, proof, b"accumulation")
+            .unwrap();
+        assert_eq!(
+            wrong.finalize(trusted_key),
+            Err(PlonkError::VerificationFailed)
+        );
+
+        // A valid proof over another SRS is not accepted by the trusted key.
+        let mut other_srs = srs;
+        other_srs.contribute(Scalar::from(7)).unwrap();
+        let (other_key, other_proof, other_in) = prove(1, other_srs);
+        let mut other = accumulator.clone();
+        other
+            .accumulate::<Blake2bTranscript>(&other_in, &other_key, &other_proof, b"accumulation")
+            .unwrap();
+        assert!(other.finalize(&other_key).is_err());
+        assert_eq!(
+            other.finalize(trusted_key),
+            Err(PlonkError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn test_forged_accumulator() {
+        let srs = Kzg10::setup(16, &mut ChaCha20Rng::from_seed([0u8; 32]));
+        let (verifier_key, _, _) = prove(1, srs);
+
+        // lhs = rhs passes e(lhs, x * g2) = e(rhs, g2) only for x = 1, which the encoding can
+        // no longer choose.
+        let forged = Accumulator {
+            lhs: G1Affine::generator(),
+            rhs: G1Affine::generator(),
+        };
+        let decoded = Accumulator::from_bytes(&forged.to_bytes()).unwrap();
+        assert_eq!(
+            decoded.finalize(&verifier_key),
+            Err(PlonkError::VerificationFailed)
+        );
+        assert_eq!(
+            Accumulator::from_bytes(&[0u8; 2 * 48 + 2 * 96]),
+            Err(PlonkError::InvalidLength {
+                expected: Accumulator::SIZE,
+                got: 2 * 48 + 2 * 96
+            })
+        );
+    }
+}
@@ -0,0 +1,338 @@
+// Backend for ACIR, the intermediate representation that Noir compiles programs to. A program
+// is a list of opcodes over witnesses w_0, w_1, ..., whose values are solved by the ACVM before
+// proving, so the backend only lowers the opcodes to gates and fills the trace from the solved
+// witnesses. The types mirror the ones of the `acir` crate for the opcodes that we support:
+//
+// * `AssertZero` constrains sum(q_M * x * y) + sum(q_L * x) + q_c = 0, with a gate per product
+//   and per extra term of the sum,
+// * `Range` constrains a witness to `num_bits` bits, by decomposing it into boolean bits,
+// * `And` and `Xor` decompose both inputs into bits, combine them bit by bit with a gate
+//   a * b or a + b - 2 * a * b, and connect the recomposition to the output.
+//
+// Every witness has a wire: public witnesses are public inputs, in the order of the program,
+// and the others are laid out three per row of a gate without selectors.
+use crate::error::PlonkError;
+use crate::gadgets::arithmetic::{decompose, Arithmetic, Assigner, Builder, Combination};
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::{Field, PrimeField};
+
+/// Index of a witness of an ACIR program.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Witness(pub u32);
+
+/// Quadratic expression sum(q_M * x * y) + sum(q_L * x) + q_c.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Expression {
+    pub mul_terms: Vec<(Scalar, Witness, Witness)>,
+    pub linear_combinations: Vec<(Scalar, Witness)>,
+    pub q_c: Scalar,
+}
+
+/// Opcodes of ACIR supported by the backend.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Opcode {
+    /// The expression evaluates to zero.
+    AssertZero(Expression),
+    /// The input is smaller than 2^num_bits.
+    Range { input: Witness, num_bits: u32 },
+    /// The output is the bitwise AND of the `num_bits`-bit inputs.
+    And {
+        lhs: Witness,
+        rhs: Witness,
+        num_bits: u32,
+        output: Witness,
+    },
+    /// The output is the bitwise XOR of the `num_bits`-bit inputs.
+    Xor {
+        lhs: Witness,
+        rhs: Witness,
+        num_bits: u32,
+        output: Witness,
+    },
+}
+
+/// Compiled program, with witnesses w_0, ..., w_{nr_witnesses - 1}.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AcirProgram {
+    pub nr_witnesses: u32,
+    pub public_inputs: Vec<Witness>,
+    pub opcodes: Vec<Opcode>,
+}
+
+impl AcirProgram {
+    fn witnesses(&self) -> impl Iterator<Item = Witness> + '_ {
+        self.opcodes
+            .iter()
+            .flat_map(|opcode| match opcode {
+                Opcode::AssertZero(expression) => expression
+                    .mul_terms
+                    .iter()
+                    .flat_map(|(_, x, y)| [*x, *y])
+                    .chain(expression.linear_combinations.iter().map(|(_, x)| *x))
+                    .collect(),
+                Opcode::Range { input, .. } => vec![*input],
+                Opcode::And {
+                    lhs, rhs, output, ..
+                }
+                | Opcode::Xor {
+                    lhs, rhs, output, ..
+                } => vec![*lhs, *rhs, *output],
+            })
+            .chain(self.public_inputs.iter().copied())
+    }
+}
+
+/// Wires created by `acir_to_plonk`, used to fill the rows of the opcodes in the trace.
+#[derive(Clone, Debug)]
+pub struct AcirCircuit {
+    witnesses: Vec<Wire>, // wire of each witness
+    public_inputs: Vec<(Wire, Witness)>,
+    rows: Vec<usize>,
+}
+
+impl AcirCircuit {
+    /// Wire of `witness`.
+    pub fn witness(&self, witness: Witness) -> Wire {
+        self.witnesses[witness.0 as usize]
+    }
+
+    /// Public values of the proof for the solved witnesses.
+    pub fn public_inputs(&self, witnesses: &[Scalar]) -> Vec<Scalar> {
+        self.public_inputs
+            .iter()
+            .map(|(_, witness)| witnesses[witness.0 as usize])
+            .collect()
+    }
+
+    /// Assigns the wires of the witnesses and the rows of the opcodes for the values of the
+    /// witnesses solved by the ACVM.
+    pub fn assign(
+        &self,
+        trace: &mut ComputationTrace,
+        program: &AcirProgram,
+        witnesses: &[Scalar],
+    ) -> Result<(), PlonkError> {
+        if witnesses.len() != self.witnesses.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: self.witnesses.len(),
+                got: witnesses.len(),
+            });
+        }
+        for (wire, value) in self.witnesses.iter().zip(witnesses) {
+            trace.set(*wire, *value);
+        }
+        for (wire, witness) in self.public_inputs.iter() {
+            trace.set(*wire, witnesses[witness.0 as usize]);
+        }
+        let mut assigner = Assigner::new(trace, &self.rows);
+        opcodes(&mut assigner, program, witnesses)
+    }
+}
+
+/// Lowers the opcodes of `program` to gates of `circuit`, with a public input per public
+/// witness.
+pub fn acir_to_plonk(
+    circuit: &mut PlonkCircuit,
+    program: &AcirProgram,
+) -> Result<AcirCircuit, PlonkError> {
+    let nr_witnesses = program.nr_witnesses as usize;
+    if let Some(witness) = program
+        .witnesses()
+        .find(|witness| witness.0 >= program.nr_witnesses)
+    {
+        return Err(PlonkError::UnknownVariable {
+            variable: witness.0 as usize,
+            nr_variables: nr_witnesses,
+        });
+    }
+
+    let mut witnesses: Vec<Option<Wire>> = vec![None; nr_witnesses];
+    let mut public_inputs = Vec::with_capacity(program.public_inputs.len());
+    for witness in program.public_inputs.iter() {
+        let wire = circuit.public_input();
+        // A witness that is public more than once has a public input each time.
+        match witnesses[witness.0 as usize] {
+            Some(previous) => circuit.connect(previous, wire)?,
+            None => witnesses[witness.0 as usize] = Some(wire),
+        }
+        public_inputs.push((wire, *witness));
+    }
+    let nr_private = witnesses.iter().filter(|wire| wire.is_none()).count();
+    let mut private = Vec::with_capacity(nr_private);
+    for _ in 0..nr_private.div_ceil(3) {
+        let (a, b, c) = circuit.gate(Gate::new());
+        private.extend([a, b, c]);
+    }
+    let mut private = private.into_iter();
+    let witnesses = witnesses
+        .into_iter()
+        .map(|wire| match wire {
+            Some(wire) => wire,
+            None => private.next().expect("There is a wire per private witness"),
+        })
+        .collect::<Vec<_>>();
+
+    let mut builder = Builder::new(circuit);
+    opcodes(&mut builder, program, &witnesses)?;
+    Ok(AcirCircuit {
+        witnesses,
+        public_inputs,
+        rows: builder.rows,
+    })
+}
+
+// Opcodes of `program` over the values of the witnesses.
+fn opcodes<A: Arithmetic>(
+    ops: &mut A,
+    program: &AcirProgram,
+    witnesses: &[A::Value],
+) -> Result<(), PlonkError> {
+    let value = |witness: &Witness| witnesses[witness.0 as usize];
+    let max = Scalar::NUM_BITS as usize - 1;
+    let check_bits = |num_bits: u32| match num_bits as usize {
+        num_bits if num_bits > max => Err(PlonkError::RangeTooLarge { num_bits, max }),
+        num_bits => Ok(num_bits),
+    };
+
+    for opcode in program.opcodes.iter() {
+        match opcode {
+            Opcode::AssertZero(expression) => {
+                let mut sum = Combination::constant(expression.q_c);
+                for (q_m, x, y) in expression.mul_terms.iter() {
+                    let product = ops.gate(Gate::new().mul(*q_m), value(x), value(y))?;
+                    sum.terms.push((product, Scalar::one()));
+                }
+                for (q_l, x) in expression.linear_combinations.iter() {
+                    sum.terms.push((value(x), *q_l));
+                }
+                let sum = sum.evaluate(ops)?;
+                let zero = ops.constant(Scalar::zero())?;
+                ops.assert_equal(sum, zero)?;
+            }
+            Opcode::Range { input, num_bits } => {
+                decompose(ops, value(input), check_bits(*num_bits)?)?;
+            }
+            Opcode::And {
+                lhs,
+                rhs,
+                num_bits,
+                output,
+            }
+            | Opcode::Xor {
+                lhs,
+                rhs,
+                num_bits,
+                output,
+            } => {
+                let num_bits = check_bits(*num_bits)?;
+                let gate = match opcode {
+                    Opcode::And { .. } => Gate::new().mul(1),
+                    _ => Gate::new().left(1).right(1).mul(-2),
+                };
+                let lhs = decompose(ops, value(lhs), num_bits)?;
+                let rhs = decompose(ops, value(rhs), num_bits)?;
+
+                let mut result = Combination::constant(Scalar::zero());
+                let mut power = Scalar::one();
+                for (x, y) in lhs.into_iter().zip(rhs) {
+                    result.terms.push((ops.gate(gate, x, y)?, power));
+                    power = power.double();
+                }
+                let result = result.evaluate(ops)?;
+                ops.assert_equal(result, value(output))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::Prover;
+    use crate::transcript::{Blake2bTranscript, Transcript};
+    use crate::verifier::PlonkVerifier;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_acir_to_plonk() {
+        // x * x = y with y public, x < 2^4, k = 5, x ^ k = z and x & k = t.
+        let [x, y, k, z, t] = [0, 1, 2, 3, 4].map(Witness);
+        let one = Scalar::one();
+        let program = AcirProgram {
+            nr_witnesses: 5,
+            public_inputs: vec![y],
+            opcodes: vec![
+                Opcode::AssertZero(Expression {
+                    mul_terms: vec![(one, x, x)],
+                    linear_combinations: vec![(-one, y)],
+                    q_c: Scalar::zero(),
+                }),
+                Opcode::Range {
+                    input: x,
+                    num_bits: 4,
+                },
+                Opcode::AssertZero(Expression {
+                    mul_terms: vec![],
+                    linear_combinations: vec![(one, k)],
+                    q_c: -Scalar::from(5),
+                }),
+                Opcode::Xor {
+                    lhs: x,
+                    rhs: k,
+                    num_bits: 4,
+                    output: z,
+                },
+                Opcode::And {
+                    lhs: x,
+                    rhs: k,
+                    num_bits: 4,
+                    output: t,
+                },
+            ],
+        };
+
+        let mut circuit = PlonkCircuit::init();
+        let backend = acir_to_plonk(&mut circuit, &program).unwrap();
+        assert_eq!(backend.witness(y), Wire::left(0));
+        circuit.pad_next_power_of_two();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let witnesses = [3u64, 9, 5, 6, 1].map(Scalar::from);
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        backend.assign(&mut trace, &program, &witnesses).unwrap();
+        let public_inputs = backend.public_inputs(&witnesses);
+        assert_eq!(public_inputs, vec![Scalar::from(9)]);
+        let mut transcript = Blake2bTranscript::new(b"testing acir");
+        let proof = Prover::prove(&public_inputs, &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"testing acir");
+        assert!(
+            PlonkVerifier::verify(&public_inputs, &verifier_key, &proof, &mut transcript).is_ok()
+        );
+
+        // Each opcode rejects a wrong witness: a square, a XOR, an AND and an x out of range.
+        for witnesses in [
+            [3u64, 10, 5, 6, 1],
+            [3, 9, 5, 7, 1],
+            [3, 9, 5, 6, 0],
+            [19, 361, 5, 22, 1],
+        ] {
+            let mut trace = ComputationTrace::new(&prover_key.constraints);
+            let witnesses = witnesses.map(Scalar::from);
+            backend.assign(&mut trace, &program, &witnesses).unwrap();
+            assert!(prover_key.constraints.is_satisfied(&trace).is_err());
+        }
+
+        let mut invalid = program;
+        invalid.public_inputs.push(Witness(5));
+        assert_eq!(
+            acir_to_plonk(&mut PlonkCircuit::init(), &invalid).err(),
+            Some(PlonkError::UnknownVariable {
+                variable: 5,
+                nr_variables: 5
+            })
+        );
+    }
+}
@@ -0,0 +1,259 @@
+// Bridge from arkworks constraint systems. Once synthesized, an `ark-relations` constraint
+// system is described by `ConstraintSystem::to_matrices`, whose rows are lists of pairs
+// (coefficient, variable), and by its instance and witness assignments. Variables are ordered
+// as (one, instance variables, witness variables), which is the order of z in `R1cs`, so the
+// matrices convert directly into an R1CS and then into gates with `r1cs_to_plonk`.
+//
+// The field elements of arkworks are a different type, so they are converted through their
+// canonical serialization, which is 32 bytes in little endian for the BLS12-381 scalar field.
+// `ArkworksCircuit::synthesize` runs a `ConstraintSynthesizer` over that field and does all
+// of the above.
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, PlonkCircuit};
+use crate::r1cs::{r1cs_to_plonk, R1cs, R1csCircuit};
+use ark_bls12_381::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use ark_relations::r1cs::{self as ark, ConstraintSynthesizer, SynthesisError};
+use blstrs::Scalar;
+
+/// Rows of each matrix, as pairs (coefficient, index of the variable).
+pub type Matrix = Vec<Vec<(Scalar, usize)>>;
+
+/// Matrices of a constraint system, as returned by `to_matrices` in ark-relations.
+#[derive(Clone, Debug, Default)]
+pub struct ConstraintMatrices {
+    pub num_instance_variables: usize, // including the constant one
+    pub num_witness_variables: usize,
+    pub num_constraints: usize,
+    pub a: Matrix,
+    pub b: Matrix,
+    pub c: Matrix,
+}
+
+impl ConstraintMatrices {
+    /// Converts the matrices returned by `to_matrices`.
+    pub fn from_ark(matrices: &ark::ConstraintMatrices<Fr>) -> Result<Self, PlonkError> {
+        let matrix = |matrix: &ark::Matrix<Fr>| {
+            matrix
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|(coefficient, variable)| Ok((scalar(coefficient)?, *variable)))
+                        .collect()
+                })
+                .collect::<Result<Matrix, PlonkError>>()
+        };
+        Ok(ConstraintMatrices {
+            num_instance_variables: matrices.num_instance_variables,
+            num_witness_variables: matrices.num_witness_variables,
+            num_constraints: matrices.num_constraints,
+            a: matrix(&matrices.a)?,
+            b: matrix(&matrices.b)?,
+            c: matrix(&matrices.c)?,
+        })
+    }
+
+    /// Constraints as an R1CS over z = (1, instance, witness).
+    pub fn r1cs(&self) -> Result<R1cs, PlonkError> {
+        for matrix in [&self.a, &self.b, &self.c] {
+            if matrix.len() != self.num_constraints {
+                return Err(PlonkError::InvalidLength {
+                    expected: self.num_constraints,
+                    got: matrix.len(),
+                });
+            }
+        }
+        let row = |row: &Vec<(Scalar, usize)>| {
+            row.iter()
+                .map(|(coefficient, variable)| (*variable, *coefficient))
+                .collect()
+        };
+        Ok(R1cs {
+            nr_variables: self.num_instance_variables + self.num_witness_variables,
+            nr_public: self.num_instance_variables.saturating_sub(1),
+            constraints: (0..self.num_constraints)
+                .map(|i| (row(&self.a[i]), row(&self.b[i]), row(&self.c[i])))
+                .collect(),
+        })
+    }
+}
+
+/// Circuit converted from arkworks, padded to a power of two and ready for the setup.
+pub struct ArkworksCircuit {
+    pub circuit: PlonkCircuit,
+    r1cs: R1cs,
+    gadget: R1csCircuit,
+}
+
+impl ArkworksCircuit {
+    /// Converts the constraints described by `matrices` to gates.
+    pub fn new(matrices: &ConstraintMatrices) -> Result<Self, PlonkError> {
+        let r1cs = matrices.r1cs()?;
+        let mut circuit = PlonkCircuit::init();
+        let gadget = r1cs_to_plonk(&mut circuit, &r1cs)?;
+        circuit.pad_next_power_of_two();
+        Ok(ArkworksCircuit {
+            circuit,
+            r1cs,
+            gadget,
+        })
+    }
+
+    /// Synthesizes `circuit` with its assignments and converts it, returning the circuit with
+    /// the instance (starting with the constant one) and witness assignments, to be passed to
+    /// `witness`.
+    pub fn synthesize<C: ConstraintSynthesizer<Fr>>(
+        circuit: C,
+    ) -> Result<(Self, Vec<Scalar>, Vec<Scalar>), PlonkError> {
+        let cs = ark::ConstraintSystem::<Fr>::new_ref();
+        circuit
+            .generate_constraints(cs.clone())
+            .map_err(|error| match error {
+                SynthesisError::AssignmentMissing => PlonkError::AssignmentMissing,
+                error => PlonkError::Synthesis(error.to_string()),
+            })?;
+        cs.finalize();
+        let matrices = cs
+            .to_matrices()
+            .ok_or_else(|| PlonkError::Synthesis("no constraint matrices".into()))?;
+        let cs = cs
+            .into_inner()
+            .expect("the constraint system was created above");
+        let instance = cs
+            .instance_assignment
+            .iter()
+            .map(scalar)
+            .collect::<Result<_, _>>()?;
+        let witness = cs
+            .witness_assignment
+            .iter()
+            .map(scalar)
+            .collect::<Result<_, _>>()?;
+        Ok((
+            Self::new(&ConstraintMatrices::from_ark(&matrices)?)?,
+            instance,
+            witness,
+        ))
+    }
+
+    /// Public values and trace for the assignments of arkworks, where `instance` starts with
+    /// the constant one.
+    pub fn witness(
+        &self,
+        instance: &[Scalar],
+        witness: &[Scalar],
+    ) -> Result<(Vec<Scalar>, ComputationTrace), PlonkError> {
+        let z = [instance, witness].concat();
+        let mut trace = ComputationTrace::new(&self.circuit);
+        self.gadget.assign(&mut trace, &self.r1cs, &z)?;
+        Ok((self.gadget.public_inputs(&z), trace))
+    }
+}
+
+/// Converts the canonical serialization of an arkworks scalar of BLS12-381.
+pub fn scalar_from_ark(bytes: &[u8]) -> Result<Scalar, PlonkError> {
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| PlonkError::InvalidLength {
+        expected: 32,
+        got: bytes.len(),
+    })?;
+    Option::from(Scalar::from_bytes_le(&bytes)).ok_or(PlonkError::InvalidScalar)
+}
+
+fn scalar(value: &Fr) -> Result<Scalar, PlonkError> {
+    scalar_from_ark(&value.into_bigint().to_bytes_le())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::Prover;
+    use crate::transcript::{Blake2bTranscript, Transcript};
+    use crate::verifier::PlonkVerifier;
+    use ark_relations::lc;
+    use ark_relations::r1cs::{ConstraintSystemRef, Variable};
+    use ff::Field;
+    use rand_core::OsRng;
+
+    // x^3 + x + 5 = out, with out public.
+    struct Cube {
+        x: u64,
+    }
+
+    impl ConstraintSynthesizer<Fr> for Cube {
+        fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+            let x_value = Fr::from(self.x);
+            let x = cs.new_witness_variable(|| Ok(x_value))?;
+            let x_sq = cs.new_witness_variable(|| Ok(x_value * x_value))?;
+            let out =
+                cs.new_input_variable(|| Ok(x_value * x_value * x_value + x_value + Fr::from(5)))?;
+            cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + x_sq)?;
+            cs.enforce_constraint(
+                lc!() + x_sq,
+                lc!() + x,
+                lc!() + out - x - (Fr::from(5), Variable::One),
+            )
+        }
+    }
+
+    #[test]
+    fn test_constraint_synthesizer() {
+        let (mut ark, instance, witness) = ArkworksCircuit::synthesize(Cube { x: 3 }).unwrap();
+        assert_eq!(instance, vec![Scalar::one(), Scalar::from(35)]);
+        let (prover_key, verifier_key) = ark.circuit.setup(&mut OsRng).unwrap();
+        let (public_inputs, trace) = ark.witness(&instance, &witness).unwrap();
+        assert_eq!(public_inputs, vec![Scalar::from(35)]);
+        let mut transcript = Blake2bTranscript::new(b"testing arkworks");
+        let proof = Prover::prove(&public_inputs, &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"testing arkworks");
+        assert!(
+            PlonkVerifier::verify(&public_inputs, &verifier_key, &proof, &mut transcript).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_arkworks_circuit() {
+        // a * b = c with c public, and c * a = d, over (1, c, a, b, d).
+        let one = Scalar::one();
+        let matrices = ConstraintMatrices {
+            num_instance_variables: 2,
+            num_witness_variables: 3,
+            num_constraints: 2,
+            a: vec![vec![(one, 2)], vec![(one, 1)]],
+            b: vec![vec![(one, 3)], vec![(one, 2)]],
+            c: vec![vec![(one, 1)], vec![(one, 4)]],
+        };
+        let mut ark = ArkworksCircuit::new(&matrices).unwrap();
+        let (prover_key, verifier_key) = ark.circuit.setup(&mut OsRng).unwrap();
+
+        let instance = [one, Scalar::from(12)];
+        let witness = [3u64, 4, 36].map(Scalar::from);
+        let (public_inputs, trace) = ark.witness(&instance, &witness).unwrap();
+        assert_eq!(public_inputs, vec![Scalar::from(12)]);
+        let mut transcript = Blake2bTranscript::new(b"testing arkworks");
+        let proof = Prover::prove(&public_inputs, &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"testing arkworks");
+        assert!(
+            PlonkVerifier::verify(&public_inputs, &verifier_key, &proof, &mut transcript).is_ok()
+        );
+
+        let (_, trace) = ark
+            .witness(&instance, &[3u64, 4, 35].map(Scalar::from))
+            .unwrap();
+        assert!(ark.circuit.is_satisfied(&trace).is_err());
+
+        let mut invalid = matrices;
+        invalid.c.pop();
+        assert_eq!(
+            ArkworksCircuit::new(&invalid).err(),
+            Some(PlonkError::InvalidLength {
+                expected: 2,
+                got: 1
+            })
+        );
+        assert_eq!(
+            scalar_from_ark(&Scalar::from(7).to_bytes_le()),
+            Ok(Scalar::from(7))
+        );
+        assert_eq!(scalar_from_ark(&[0xff; 32]), Err(PlonkError::InvalidScalar));
+    }
+}
@@ -0,0 +1,457 @@
+// Synthesis of circuits written for bellman. The `ConstraintSystem` and `Circuit` traits have
+// the shape of the ones of bellman, so that a gadget written against them only needs to import
+// them from here instead: variables are allocated with a closure computing their value, and
+// constraints are enforced as <a, z> * <b, z> = <c, z>, with linear combinations built from
+// `zero()` by adding terms.
+//
+// `PlonkConstraintSystem` records the variables and constraints as an `R1cs`, which is then
+// converted to gates by `r1cs_to_plonk`, with the public inputs in the order in which they
+// were allocated. As in bellman, the input 0 is the constant one.
+//
+// With the `bellman` feature, `PlonkConstraintSystem` also implements the `ConstraintSystem`
+// of bellman itself over the scalars of BLS12-381, so that existing bellman circuits and
+// gadgets run on it unchanged, with `synthesize_bellman`.
+use crate::error::PlonkError;
+use crate::r1cs::{self, R1cs};
+use blstrs::Scalar;
+use ff::Field;
+use std::ops::{Add, Sub};
+
+/// Variable of a constraint system: a public input (0 being the constant one), or an
+/// auxiliary (private) variable.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Variable {
+    Input(usize),
+    Aux(usize),
+}
+
+/// Sparse linear combination of variables.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinearCombination(Vec<(Variable, Scalar)>);
+
+impl LinearCombination {
+    pub fn zero() -> Self {
+        LinearCombination(Vec::new())
+    }
+}
+
+// The operators move the terms out of the combination and wrap them again instead of pushing
+// through `mut self`: rustc 1.95 miscompiles the latter in release builds when the closures of
+// `enforce` are inlined, with the terms of one combination ending up in the next one.
+impl Add<Variable> for LinearCombination {
+    type Output = Self;
+
+    fn add(self, variable: Variable) -> Self {
+        self + (Scalar::one(), variable)
+    }
+}
+
+impl Sub<Variable> for LinearCombination {
+    type Output = Self;
+
+    fn sub(self, variable: Variable) -> Self {
+        self - (Scalar::one(), variable)
+    }
+}
+
+impl Add<(Scalar, Variable)> for LinearCombination {
+    type Output = Self;
+
+    fn add(self, (coefficient, variable): (Scalar, Variable)) -> Self {
+        let mut terms = self.0;
+        terms.push((variable, coefficient));
+        LinearCombination(terms)
+    }
+}
+
+impl Sub<(Scalar, Variable)> for LinearCombination {
+    type Output = Self;
+
+    fn sub(self, (coefficient, variable): (Scalar, Variable)) -> Self {
+        self + (-coefficient, variable)
+    }
+}
+
+impl Add<&LinearCombination> for LinearCombination {
+    type Output = Self;
+
+    fn add(self, other: &LinearCombination) -> Self {
+        let mut terms = self.0;
+        terms.extend_from_slice(&other.0);
+        LinearCombination(terms)
+    }
+}
+
+impl Sub<&LinearCombination> for LinearCombination {
+    type Output = Self;
+
+    fn sub(self, other: &LinearCombination) -> Self {
+        let mut terms = self.0;
+        terms.extend(
+            other
+                .0
+                .iter()
+                .map(|(variable, coefficient)| (*variable, -coefficient)),
+        );
+        LinearCombination(terms)
+    }
+}
+
+/// System in which circuits allocate their variables and enforce their constraints.
+pub trait ConstraintSystem: Sized {
+    /// The constant one.
+    fn one() -> Variable {
+        Variable::Input(0)
+    }
+
+    /// Allocates a private variable, whose value is computed by `f`.
+    fn alloc<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, PlonkError>
+    where
+        F: FnOnce() -> Result<Scalar, PlonkError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>;
+
+    /// Allocates a public input, whose value is computed by `f`.
+    fn alloc_input<F, A, AR>(&mut self, annotation: A, f: F) -> Result<Variable, PlonkError>
+    where
+        F: FnOnce() -> Result<Scalar, PlonkError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>;
+
+    /// Enforces a * b = c, with each combination built from zero by its closure.
+    fn enforce<A, AR, LA, LB, LC>(&mut self, annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination) -> LinearCombination,
+        LB: FnOnce(LinearCombination) -> LinearCombination,
+        LC: FnOnce(LinearCombination) -> LinearCombination;
+
+    /// Namespaces only name the constraints of a gadget, so they are all the same system.
+    fn namespace<NR: Into<String>, N: FnOnce() -> NR>(&mut self, _name: N) -> &mut Self {
+        self
+    }
+}
+
+/// Circuit that can be synthesized in any constraint system.
+pub trait Circuit {
+    fn synthesize<CS: ConstraintSystem>(self, cs: &mut CS) -> Result<(), PlonkError>;
+}
+
+/// Constraint system that records a circuit as an R1CS, with the values of its variables.
+/// Values that could not be computed (e.g. when synthesizing without a witness) are missing.
+#[derive(Clone, Debug)]
+pub struct PlonkConstraintSystem {
+    inputs: Vec<Option<Scalar>>,
+    aux: Vec<Option<Scalar>>,
+    constraints: Vec<(LinearCombination, LinearCombination, LinearCombination)>,
+}
+
+impl Default for PlonkConstraintSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlonkConstraintSystem {
+    pub fn new() -> Self {
+        PlonkConstraintSystem {
+            inputs: vec![Some(Scalar::one())],
+            aux: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Synthesizes `circuit` in a new constraint system.
+    pub fn synthesize<C: Circuit>(circuit: C) -> Result<Self, PlonkError> {
+        let mut cs = Self::new();
+        circuit.synthesize(&mut cs)?;
+        Ok(cs)
+    }
+
+    /// Synthesizes a bellman `circuit` in a new constraint system.
+    #[cfg(feature = "bellman")]
+    pub fn synthesize_bellman<C: ::bellman::Circuit<Scalar>>(
+        circuit: C,
+    ) -> Result<Self, PlonkError> {
+        let mut cs = Self::new();
+        circuit.synthesize(&mut cs).map_err(|error| match error {
+            ::bellman::SynthesisError::AssignmentMissing => PlonkError::AssignmentMissing,
+            error => PlonkError::Synthesis(error.to_string()),
+        })?;
+        Ok(cs)
+    }
+
+    // Index of the variable in z = (1, public inputs, auxiliary variables).
+    fn index(&self, variable: Variable) -> usize {
+        match variable {
+            Variable::Input(i) => i,
+            Variable::Aux(i) => self.inputs.len() + i,
+        }
+    }
+
+    /// Constraints of the circuit, over z = (1, public inputs, auxiliary variables).
+    pub fn r1cs(&self) -> R1cs {
+        let convert = |combination: &LinearCombination| -> r1cs::LinearCombination {
+            combination
+                .0
+                .iter()
+                .map(|(variable, coefficient)| (self.index(*variable), *coefficient))
+                .collect()
+        };
+        R1cs {
+            nr_variables: self.inputs.len() + self.aux.len(),
+            nr_public: self.inputs.len() - 1,
+            constraints: self
+                .constraints
+                .iter()
+                .map(|(a, b, c)| (convert(a), convert(b), convert(c)))
+                .collect(),
+        }
+    }
+
+    /// Values of z = (1, public inputs, auxiliary variables). Fails if any of them is missing.
+    pub fn assignment(&self) -> Result<Vec<Scalar>, PlonkError> {
+        self.inputs
+            .iter()
+            .chain(self.aux.iter())
+            .map(|value| value.ok_or(PlonkError::AssignmentMissing))
+            .collect()
+    }
+}
+
+impl ConstraintSystem for PlonkConstraintSystem {
+    fn alloc<F, A, AR>(&mut self, _annotation: A, f: F) -> Result<Variable, PlonkError>
+    where
+        F: FnOnce() -> Result<Scalar, PlonkError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux.push(f().ok());
+        Ok(Variable::Aux(self.aux.len() - 1))
+    }
+
+    fn alloc_input<F, A, AR>(&mut self, _annotation: A, f: F) -> Result<Variable, PlonkError>
+    where
+        F: FnOnce() -> Result<Scalar, PlonkError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(f().ok());
+        Ok(Variable::Input(self.inputs.len() - 1))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(LinearCombination) -> LinearCombination,
+        LB: FnOnce(LinearCombination) -> LinearCombination,
+        LC: FnOnce(LinearCombination) -> LinearCombination,
+    {
+        let zero = LinearCombination::zero;
+        self.constraints.push((a(zero()), b(zero()), c(zero())));
+    }
+}
+
+#[cfg(feature = "bellman")]
+impl ::bellman::ConstraintSystem<Scalar> for PlonkConstraintSystem {
+    type Root = Self;
+
+    fn alloc<F, A, AR>(
+        &mut self,
+        _annotation: A,
+        f: F,
+    ) -> Result<::bellman::Variable, ::bellman::SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, ::bellman::SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.aux.push(f().ok());
+        Ok(::bellman::Variable::new_unchecked(::bellman::Index::Aux(
+            self.aux.len() - 1,
+        )))
+    }
+
+    fn alloc_input<F, A, AR>(
+        &mut self,
+        _annotation: A,
+        f: F,
+    ) -> Result<::bellman::Variable, ::bellman::SynthesisError>
+    where
+        F: FnOnce() -> Result<Scalar, ::bellman::SynthesisError>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.inputs.push(f().ok());
+        Ok(::bellman::Variable::new_unchecked(::bellman::Index::Input(
+            self.inputs.len() - 1,
+        )))
+    }
+
+    fn enforce<A, AR, LA, LB, LC>(&mut self, _annotation: A, a: LA, b: LB, c: LC)
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+        LA: FnOnce(::bellman::LinearCombination<Scalar>) -> ::bellman::LinearCombination<Scalar>,
+        LB: FnOnce(::bellman::LinearCombination<Scalar>) -> ::bellman::LinearCombination<Scalar>,
+        LC: FnOnce(::bellman::LinearCombination<Scalar>) -> ::bellman::LinearCombination<Scalar>,
+    {
+        let convert = |combination: ::bellman::LinearCombination<Scalar>| {
+            LinearCombination(
+                combination
+                    .as_ref()
+                    .iter()
+                    .map(|(variable, coefficient)| {
+                        let variable = match variable.get_unchecked() {
+                            ::bellman::Index::Input(i) => Variable::Input(i),
+                            ::bellman::Index::Aux(i) => Variable::Aux(i),
+                        };
+                        (variable, *coefficient)
+                    })
+                    .collect(),
+            )
+        };
+        let zero = ::bellman::LinearCombination::zero;
+        self.constraints
+            .push((convert(a(zero())), convert(b(zero())), convert(c(zero()))));
+    }
+
+    // Namespaces only name the constraints of a gadget, which are not recorded.
+    fn push_namespace<NR: Into<String>, N: FnOnce() -> NR>(&mut self, _name: N) {}
+
+    fn pop_namespace(&mut self) {}
+
+    fn get_root(&mut self) -> &mut Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::{ComputationTrace, PlonkCircuit};
+    use crate::prover::Prover;
+    use crate::r1cs::r1cs_to_plonk;
+    use crate::transcript::{Blake2bTranscript, Transcript};
+    use crate::verifier::PlonkVerifier;
+    #[cfg(feature = "bellman")]
+    use ::bellman::ConstraintSystem as _;
+    use rand_core::OsRng;
+
+    // x^3 + x + 5 = out, written as for bellman.
+    struct Cube {
+        x: Option<Scalar>,
+    }
+
+    impl Circuit for Cube {
+        fn synthesize<CS: ConstraintSystem>(self, cs: &mut CS) -> Result<(), PlonkError> {
+            let value = |x: Option<Scalar>| x.ok_or(PlonkError::AssignmentMissing);
+            let x = cs.alloc(|| "x", || value(self.x))?;
+            let x_sq_value = self.x.map(|x| x.square());
+            let x_sq = cs
+                .namespace(|| "square")
+                .alloc(|| "x^2", || value(x_sq_value))?;
+            cs.enforce(|| "x * x", |lc| lc + x, |lc| lc + x, |lc| lc + x_sq);
+
+            let out_value = self.x.map(|x| x.square() * x + x + Scalar::from(5));
+            let out = cs.alloc_input(|| "out", || value(out_value))?;
+            cs.enforce(
+                || "x^2 * x",
+                |lc| lc + x_sq,
+                |lc| lc + x,
+                |lc| lc + out - x - (Scalar::from(5), CS::one()),
+            );
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bellman_circuit() {
+        // The circuit does not depend on the witness.
+        let setup = PlonkConstraintSystem::synthesize(Cube { x: None }).unwrap();
+        assert_eq!(setup.assignment(), Err(PlonkError::AssignmentMissing));
+        let r1cs = setup.r1cs();
+        assert_eq!((r1cs.nr_variables, r1cs.nr_public), (4, 1));
+
+        let mut circuit = PlonkCircuit::init();
+        let gadget = r1cs_to_plonk(&mut circuit, &r1cs).unwrap();
+        circuit.pad_next_power_of_two();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let cs = PlonkConstraintSystem::synthesize(Cube {
+            x: Some(Scalar::from(3)),
+        })
+        .unwrap();
+        assert_eq!(cs.r1cs().constraints, r1cs.constraints);
+        let z = cs.assignment().unwrap();
+        assert!(r1cs.is_satisfied(&z));
+
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        gadget.assign(&mut trace, &r1cs, &z).unwrap();
+        let public_inputs = gadget.public_inputs(&z);
+        assert_eq!(public_inputs, vec![Scalar::from(35)]);
+        let mut transcript = Blake2bTranscript::new(b"testing bellman");
+        let proof = Prover::prove(&public_inputs, &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"testing bellman");
+        assert!(
+            PlonkVerifier::verify(&public_inputs, &verifier_key, &proof, &mut transcript).is_ok()
+        );
+    }
+
+    // The same circuit, written against bellman itself.
+    #[cfg(feature = "bellman")]
+    struct BellmanCube {
+        x: Option<Scalar>,
+    }
+
+    #[cfg(feature = "bellman")]
+    impl ::bellman::Circuit<Scalar> for BellmanCube {
+        fn synthesize<CS: ::bellman::ConstraintSystem<Scalar>>(
+            self,
+            cs: &mut CS,
+        ) -> Result<(), ::bellman::SynthesisError> {
+            use ::bellman::SynthesisError::AssignmentMissing;
+            let x = cs.alloc(|| "x", || self.x.ok_or(AssignmentMissing))?;
+            let x_sq_value = self.x.map(|x| x.square());
+            let x_sq = cs
+                .namespace(|| "square")
+                .alloc(|| "x^2", || x_sq_value.ok_or(AssignmentMissing))?;
+            cs.enforce(|| "x * x", |lc| lc + x, |lc| lc + x, |lc| lc + x_sq);
+
+            let out_value = self.x.map(|x| x.square() * x + x + Scalar::from(5));
+            let out = cs.alloc_input(|| "out", || out_value.ok_or(AssignmentMissing))?;
+            cs.enforce(
+                || "x^2 * x",
+                |lc| lc + x_sq,
+                |lc| lc + x,
+                |lc| lc + out - x - (Scalar::from(5), CS::one()),
+            );
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "bellman")]
+    #[test]
+    fn test_bellman_trait() {
+        assert_eq!(
+            PlonkConstraintSystem::synthesize_bellman(BellmanCube { x: None })
+                .unwrap()
+                .assignment(),
+            Err(PlonkError::AssignmentMissing)
+        );
+        let cs = PlonkConstraintSystem::synthesize_bellman(BellmanCube {
+            x: Some(Scalar::from(3)),
+        })
+        .unwrap();
+        let mirrored = PlonkConstraintSystem::synthesize(Cube {
+            x: Some(Scalar::from(3)),
+        })
+        .unwrap();
+        assert_eq!(cs.r1cs().constraints, mirrored.r1cs().constraints);
+        let z = cs.assignment().unwrap();
+        assert_eq!(z, mirrored.assignment().unwrap());
+        assert!(cs.r1cs().is_satisfied(&z));
+    }
+}
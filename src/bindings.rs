@@ -0,0 +1,166 @@
+// Entry points over encoded inputs, shared by the bindings for other languages (`wasm` and
+// `ffi`), which only convert buffers and errors. The encodings are the ones of the crate:
+// circuits as written by `PlonkCircuit::serialize`, traces by `ComputationTrace::serialize`,
+// prover keys by `ProverKey::serialize`, verifier keys by `VerifierKey::serialize` and proofs
+// by `PlonkProof::to_bytes`. Public inputs are concatenated scalars of 32 bytes in big endian.
+//
+// The SRS of the setup is read from a Powers of Tau response file of a ceremony (compressed
+// points, see `Kzg10::from_powers_of_tau`), and checked to be well formed. The bindings never
+// generate an SRS themselves, as whoever knows its toxic waste can forge proofs. The prover
+// key is computed once and stored by the application, which ships the verifier key to the
+// verifiers.
+use crate::error::PlonkError;
+use crate::kzg10::{Kzg10, PowersOfTauFormat};
+use crate::plonk::{ComputationTrace, PlonkCircuit, ProverKey, Wire};
+use crate::proof::PlonkProof;
+use crate::prover::Prover;
+use crate::transcript::{Blake2bTranscript, Transcript};
+use crate::verifier::{PlonkVerifier, VerifierKey};
+use blstrs::Scalar;
+
+/// Label of the transcripts of the bindings.
+pub const TRANSCRIPT_LABEL: &[u8] = b"dummy-plonk bindings";
+
+/// Encoded prover key of an encoded circuit, with the SRS of a Powers of Tau response file of
+/// `tau_powers_length` powers in G2.
+pub fn prover_key(
+    circuit_bytes: &[u8],
+    srs_bytes: &[u8],
+    tau_powers_length: usize,
+) -> Result<Vec<u8>, PlonkError> {
+    let mut circuit = PlonkCircuit::deserialize(circuit_bytes)?;
+    circuit.pad_next_power_of_two();
+    let srs = Kzg10::from_powers_of_tau(
+        srs_bytes,
+        PowersOfTauFormat::Response,
+        tau_powers_length,
+        circuit.nr_constraints + 7,
+    )?;
+    let (prover_key, _) = circuit.setup_with_srs(srs)?;
+    let mut bytes = Vec::new();
+    prover_key.serialize(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Encoded verifier key of an encoded prover key.
+pub fn verifier_key(prover_key_bytes: &[u8]) -> Result<Vec<u8>, PlonkError> {
+    let prover_key = ProverKey::deserialize(prover_key_bytes)?;
+    let mut bytes = Vec::new();
+    prover_key.verifier_key.serialize(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Proves that the trace satisfies the circuit of the encoded prover key. The public inputs
+/// are read from the trace, at the rows of the public inputs of the circuit.
+pub fn prove(prover_key_bytes: &[u8], witness_bytes: &[u8]) -> Result<Vec<u8>, PlonkError> {
+    let prover_key = ProverKey::deserialize(prover_key_bytes)?;
+    let trace = ComputationTrace::deserialize(witness_bytes)?;
+    let public_inputs = prover_key
+        .constraints
+        .public_inputs
+        .iter()
+        .map(|row| trace.value(Wire::left(*row)))
+        .collect::<Vec<_>>();
+    let mut transcript = Blake2bTranscript::new(TRANSCRIPT_LABEL);
+    Ok(Prover::prove(&public_inputs, &prover_key, &trace, &mut transcript)?.to_bytes())
+}
+
+/// Checks an encoded proof against a decoded verifier key.
+pub fn verify(
+    verifier_key: &VerifierKey,
+    proof_bytes: &[u8],
+    public_inputs: &[u8],
+) -> Result<(), PlonkError> {
+    let proof = PlonkProof::from_bytes(proof_bytes)?;
+    if !public_inputs.len().is_multiple_of(32) {
+        return Err(PlonkError::InvalidLength {
+            expected: public_inputs.len().next_multiple_of(32),
+            got: public_inputs.len(),
+        });
+    }
+    let public_inputs = public_inputs
+        .chunks_exact(32)
+        .map(|chunk| {
+            Option::from(Scalar::from_bytes_be(chunk.try_into().unwrap()))
+                .ok_or(PlonkError::InvalidScalar)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut transcript = Blake2bTranscript::new(TRANSCRIPT_LABEL);
+    PlonkVerifier::verify(&public_inputs, verifier_key, &proof, &mut transcript)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::kzg10::tests::write_powers_of_tau;
+    use ff::Field;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    pub(crate) const TAU_POWERS_LENGTH: usize = 8;
+
+    // Encoded circuit x * x = y with y public, its trace for x = `x`, and a Powers of Tau
+    // response file for it.
+    pub(crate) fn square_circuit(x: u64) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let mut circuit = PlonkCircuit::init();
+        let y = circuit.public_input();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        circuit.pad_next_power_of_two();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(y, x_sq).unwrap();
+        let mut circuit_bytes = Vec::new();
+        circuit.serialize(&mut circuit_bytes).unwrap();
+
+        let mut trace = ComputationTrace::new(&circuit);
+        for (wire, value) in [(y, 9), (x_l, x), (x_r, x), (x_sq, 9)] {
+            trace.set(wire, Scalar::from(value));
+        }
+        let mut witness_bytes = Vec::new();
+        trace.serialize(&mut witness_bytes).unwrap();
+
+        let toxic_waste = Scalar::random(&mut ChaCha20Rng::from_seed([5u8; 32]));
+        let srs_bytes =
+            write_powers_of_tau(toxic_waste, PowersOfTauFormat::Response, TAU_POWERS_LENGTH);
+        (circuit_bytes, witness_bytes, srs_bytes)
+    }
+
+    #[test]
+    fn test_bindings() {
+        let (circuit_bytes, witness_bytes, srs_bytes) = square_circuit(3);
+        let pk_bytes = prover_key(&circuit_bytes, &srs_bytes, TAU_POWERS_LENGTH).unwrap();
+        let vk_bytes = verifier_key(&pk_bytes).unwrap();
+        let vk = VerifierKey::deserialize(vk_bytes.as_slice()).unwrap();
+        let proof = prove(&pk_bytes, &witness_bytes).unwrap();
+        let nine = Scalar::from(9).to_bytes_be();
+        assert_eq!(verify(&vk, &proof, &nine), Ok(()));
+        assert_eq!(
+            verify(&vk, &proof, &Scalar::from(4).to_bytes_be()),
+            Err(PlonkError::VerificationFailed)
+        );
+        assert_eq!(
+            verify(&vk, &proof, &nine[..31]),
+            Err(PlonkError::InvalidLength {
+                expected: 32,
+                got: 31
+            })
+        );
+        assert_eq!(
+            verify(&vk, &proof, &[0xff; 32]),
+            Err(PlonkError::InvalidScalar)
+        );
+
+        let (_, witness_bytes, _) = square_circuit(4);
+        assert!(prove(&pk_bytes, &witness_bytes).is_err());
+
+        // The SRS must be well formed, and large enough for the circuit.
+        let mut broken = srs_bytes.clone();
+        broken[64 + 48 + 5] ^= 1;
+        assert!(prover_key(&circuit_bytes, &broken, TAU_POWERS_LENGTH).is_err());
+        assert_eq!(
+            prover_key(&circuit_bytes, &srs_bytes[..500], TAU_POWERS_LENGTH).err(),
+            Some(PlonkError::Io("Unexpected end of file".to_string()))
+        );
+        assert!(prover_key(&circuit_bytes, &srs_bytes, 4).is_err());
+        assert!(prove(&pk_bytes[1..], &witness_bytes).is_err());
+    }
+}
@@ -0,0 +1,333 @@
+// Parsers of the binary files produced by circom: the constraint system (.r1cs) and the
+// witness (.wtns), so that circuits compiled with `circom --prime bls12381` can be converted
+// with `r1cs_to_plonk` and proven.
+//
+// Both formats start with a magic string, a version and a number of sections. Each section
+// has a type (u32) and a size (u64), followed by its data, and all integers are little endian.
+// Field elements take n8 bytes, and are stored in little endian in their canonical form.
+//
+// The header of an .r1cs file (type 1) has n8, the prime, the number of wires, of public
+// outputs, of public inputs, of private inputs, of labels (u64) and of constraints. The
+// constraints (type 2) are three linear combinations each, as a number of terms followed by
+// pairs (wire, coefficient). Wire 0 is the constant one, and it is followed by the outputs and
+// the public inputs, which are the public values of the proof. The wire map (type 3) has the
+// label (u64) of every wire. Other sections are skipped.
+//
+// The header of a .wtns file (type 1) has n8, the prime and the number of values, which are
+// in the section of type 2.
+use crate::error::PlonkError;
+use crate::r1cs::{LinearCombination, R1cs};
+use blstrs::Scalar;
+use ff::Field;
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Reads a constraint system in the .r1cs format of circom.
+pub fn read_r1cs<R: Read>(reader: R) -> Result<R1cs, PlonkError> {
+    let mut sections = read_sections(reader, b"r1cs")?;
+    let mut header = Section::new(sections.remove(&1), "header")?;
+    read_prime(&mut header)?;
+    let nr_variables = header.u32()? as u64;
+    let nr_public = header.u32()? as u64 + header.u32()? as u64;
+    let nr_private_inputs = header.u32()? as u64;
+    let _nr_labels = header.u64()?;
+    let nr_constraints = header.u32()? as u64;
+
+    // The counts are checked against the sizes of the sections, so that a corrupted header
+    // can't make the conversion allocate a circuit larger than the file: the inputs and the
+    // outputs are wires other than the constant one, every wire has a label in the map
+    // section, and every constraint has at least the numbers of terms of its combinations.
+    if nr_public + nr_private_inputs >= nr_variables {
+        return Err(invalid("More inputs and outputs than wires"));
+    }
+    let labels = Section::new(sections.remove(&3), "wire map")?;
+    if labels.data.len() as u64 != 8 * nr_variables {
+        return Err(invalid("The wire map does not have a label per wire"));
+    }
+    let mut data = Section::new(sections.remove(&2), "constraints")?;
+    if (data.data.len() as u64) < 12 * nr_constraints {
+        return Err(invalid("Constraints section too short"));
+    }
+    let mut combination = || {
+        (0..data.u32()?)
+            .map(|_| Ok((data.u32()? as usize, data.scalar()?)))
+            .collect::<Result<LinearCombination, PlonkError>>()
+    };
+    let constraints = (0..nr_constraints)
+        .map(|_| Ok((combination()?, combination()?, combination()?)))
+        .collect::<Result<Vec<_>, PlonkError>>()?;
+
+    Ok(R1cs {
+        nr_variables: nr_variables as usize,
+        nr_public: nr_public as usize,
+        constraints,
+    })
+}
+
+/// Reads a witness in the .wtns format of circom, which is the assignment z of the variables
+/// of the constraint system, starting with the constant one.
+pub fn read_wtns<R: Read>(reader: R) -> Result<Vec<Scalar>, PlonkError> {
+    let mut sections = read_sections(reader, b"wtns")?;
+    let mut header = Section::new(sections.remove(&1), "header")?;
+    read_prime(&mut header)?;
+    let nr_values = header.u32()?;
+
+    let mut data = Section::new(sections.remove(&2), "witness")?;
+    if data.data.len() as u64 != 32 * nr_values as u64 {
+        return Err(invalid(
+            "The witness section does not have a value per wire",
+        ));
+    }
+    (0..nr_values).map(|_| data.scalar()).collect()
+}
+
+fn invalid(message: &str) -> PlonkError {
+    PlonkError::InvalidFormat(message.to_string())
+}
+
+// Data of every section, by type. If there are several sections with the same type, the last
+// one is kept.
+fn read_sections<R: Read>(
+    mut reader: R,
+    magic: &[u8; 4],
+) -> Result<HashMap<u32, Vec<u8>>, PlonkError> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    if &bytes != magic {
+        return Err(invalid("Wrong magic string"));
+    }
+    reader.read_exact(&mut bytes)?; // version
+    reader.read_exact(&mut bytes)?;
+    let nr_sections = u32::from_le_bytes(bytes);
+
+    let mut sections = HashMap::new();
+    for _ in 0..nr_sections {
+        reader.read_exact(&mut bytes)?;
+        let section_type = u32::from_le_bytes(bytes);
+        let mut size = [0u8; 8];
+        reader.read_exact(&mut size)?;
+        let size = u64::from_le_bytes(size);
+
+        // The size is not trusted to allocate, so that a corrupted one fails when the data runs
+        // out.
+        let mut data = Vec::new();
+        if reader.by_ref().take(size).read_to_end(&mut data)? as u64 != size {
+            return Err(PlonkError::Io("Unexpected end of file".to_string()));
+        }
+        sections.insert(section_type, data);
+    }
+    Ok(sections)
+}
+
+// The prime must be the modulus of the scalar field of BLS12-381.
+fn read_prime(header: &mut Section) -> Result<(), PlonkError> {
+    if header.u32()? != 32 {
+        return Err(invalid("Field elements must have 32 bytes"));
+    }
+    if header.bytes(32)? != modulus() {
+        return Err(invalid(
+            "The prime is not the order of the BLS12-381 scalar field",
+        ));
+    }
+    Ok(())
+}
+
+// Little endian encoding of the modulus, computed as -1 + 1 over the encoding of -1.
+pub(crate) fn modulus() -> [u8; 32] {
+    let mut bytes = (-Scalar::one()).to_bytes_le();
+    for byte in bytes.iter_mut() {
+        let (sum, carry) = byte.overflowing_add(1);
+        *byte = sum;
+        if !carry {
+            break;
+        }
+    }
+    bytes
+}
+
+struct Section {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl Section {
+    fn new(data: Option<Vec<u8>>, name: &str) -> Result<Self, PlonkError> {
+        let data = data.ok_or_else(|| invalid(&format!("Missing {name} section")))?;
+        Ok(Section { data, position: 0 })
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&[u8], PlonkError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or_else(|| invalid("Section too short"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, PlonkError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, PlonkError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn scalar(&mut self) -> Result<Scalar, PlonkError> {
+        let bytes = self.bytes(32)?.try_into().unwrap();
+        Option::from(Scalar::from_bytes_le(&bytes)).ok_or(PlonkError::InvalidScalar)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::plonk::{ComputationTrace, PlonkCircuit};
+    use crate::r1cs::r1cs_to_plonk;
+    use crate::transcript::{Blake2bTranscript, Transcript};
+    use crate::{prover::Prover, verifier::PlonkVerifier};
+    use rand_core::OsRng;
+
+    fn file(magic: &[u8; 4], version: u32, sections: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+        let mut bytes = magic.to_vec();
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+        for (section_type, data) in sections {
+            bytes.extend_from_slice(&section_type.to_le_bytes());
+            bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&data);
+        }
+        bytes
+    }
+
+    fn field_header(values: &[u32]) -> Vec<u8> {
+        let mut bytes = 32u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&modulus());
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    // .r1cs file with the numbers of wires, outputs, public and private inputs of `counts`,
+    // and a wire map with `nr_labels` labels.
+    fn r1cs_file(
+        counts: [u32; 4],
+        nr_constraints: u32,
+        constraints: Vec<u8>,
+        nr_labels: u64,
+    ) -> Vec<u8> {
+        let mut header = field_header(&counts);
+        header.extend_from_slice(&nr_labels.to_le_bytes());
+        header.extend_from_slice(&nr_constraints.to_le_bytes());
+        let labels = (0..nr_labels).flat_map(u64::to_le_bytes).collect();
+        file(b"r1cs", 1, vec![(2, constraints), (1, header), (3, labels)])
+    }
+
+    // Files of the circuit out <== x * x * x + x + 5 compiled by circom, with x private and out
+    // public: wires (1, out, x, x^2), and constraints x * x = x^2 and x^2 * x = out - x - 5.
+    pub(crate) fn example_files() -> (Vec<u8>, Vec<u8>) {
+        let (one, minus_one) = (Scalar::one(), -Scalar::one());
+        let combinations = [
+            vec![(2, minus_one)],
+            vec![(2, one)],
+            vec![(3, minus_one)],
+            vec![(3, minus_one)],
+            vec![(2, one)],
+            vec![(0, Scalar::from(5)), (1, minus_one), (2, one)],
+        ];
+        let mut constraints = Vec::new();
+        for combination in combinations.iter() {
+            constraints.extend_from_slice(&(combination.len() as u32).to_le_bytes());
+            for (wire, value) in combination {
+                constraints.extend_from_slice(&(*wire as u32).to_le_bytes());
+                constraints.extend_from_slice(&value.to_bytes_le());
+            }
+        }
+        let r1cs_file = r1cs_file([4, 1, 0, 1], 2, constraints, 4);
+
+        let mut witness = field_header(&[4]);
+        for value in [1u64, 35, 3, 9] {
+            witness.extend_from_slice(&Scalar::from(value).to_bytes_le());
+        }
+        let wtns_file = file(
+            b"wtns",
+            2,
+            vec![(1, field_header(&[4])), (2, witness[40..].to_vec())],
+        );
+        (r1cs_file, wtns_file)
+    }
+
+    #[test]
+    fn test_circom_files() {
+        let (r1cs_file, wtns_file) = example_files();
+        let r1cs = read_r1cs(r1cs_file.as_slice()).unwrap();
+        assert_eq!((r1cs.nr_variables, r1cs.nr_public), (4, 1));
+        assert_eq!(
+            r1cs.constraints[1].2,
+            vec![
+                (0, Scalar::from(5)),
+                (1, -Scalar::one()),
+                (2, Scalar::one())
+            ]
+        );
+        let z = read_wtns(wtns_file.as_slice()).unwrap();
+        assert!(r1cs.is_satisfied(&z));
+
+        let mut circuit = PlonkCircuit::init();
+        let gadget = r1cs_to_plonk(&mut circuit, &r1cs).unwrap();
+        circuit.pad_next_power_of_two();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        gadget.assign(&mut trace, &r1cs, &z).unwrap();
+        let public_inputs = gadget.public_inputs(&z);
+        assert_eq!(public_inputs, vec![Scalar::from(35)]);
+        let mut transcript = Blake2bTranscript::new(b"testing circom");
+        let proof = Prover::prove(&public_inputs, &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"testing circom");
+        assert!(
+            PlonkVerifier::verify(&public_inputs, &verifier_key, &proof, &mut transcript).is_ok()
+        );
+
+        assert!(matches!(
+            read_r1cs(&wtns_file[..]),
+            Err(PlonkError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            read_wtns(&wtns_file[..wtns_file.len() - 1]),
+            Err(PlonkError::Io(_))
+        ));
+        let mut other_prime = wtns_file;
+        other_prime[28] ^= 1;
+        assert!(matches!(
+            read_wtns(other_prime.as_slice()),
+            Err(PlonkError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_circom_header_counts() {
+        let header_error = |counts, nr_constraints, nr_labels| {
+            matches!(
+                read_r1cs(r1cs_file(counts, nr_constraints, vec![], nr_labels).as_slice()),
+                Err(PlonkError::InvalidFormat(_))
+            )
+        };
+        assert!(read_r1cs(r1cs_file([4, 1, 1, 1], 0, vec![], 4).as_slice()).is_ok());
+        // Billions of public inputs, and inputs that leave no room for the constant wire.
+        assert!(header_error([4, u32::MAX, u32::MAX, 0], 0, 4));
+        assert!(header_error([4, 1, 1, 2], 0, 4));
+        // Billions of wires without their labels.
+        assert!(header_error([u32::MAX, 1, 0, 0], 0, 4));
+        // More constraints than the section can hold.
+        assert!(header_error([4, 1, 0, 0], u32::MAX, 4));
+
+        let (_, mut wtns_file) = example_files();
+        // Number of values of the header.
+        wtns_file[60..64].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            read_wtns(wtns_file.as_slice()),
+            Err(PlonkError::InvalidFormat(_))
+        ));
+    }
+}
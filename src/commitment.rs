@@ -0,0 +1,379 @@
+// Interface of the polynomial commitment schemes. A scheme commits to polynomials over the
+// scalar field, and opens them either at a single point, or each at its own set of points, with
+// the challenges drawn from the transcript of the protocol that uses it. Code that only needs
+// to commit and open is written against this trait, so that any scheme can be plugged in.
+//
+// The PLONK prover and verifier are written against `PlonkCommitment`, which adds what they
+// need on top: absorbing commitments, and proving the evaluations of a proof. KZG commitments
+// are points of G1, which the verifier combines homomorphically, so that the quotient is
+// linearised and most selectors are never opened (and the proofs can be checked in the EVM).
+// The other schemes open every polynomial at zeta, and those of the next row at zeta * omega,
+// and the verifier evaluates the numerator of the quotient itself.
+use crate::error::PlonkError;
+use crate::kzg10::{Kzg10, Kzg10Commitment, Kzg10Opening, LagrangeKzg10, MultiOpen};
+#[cfg(feature = "std")]
+use crate::plonk::ProverKey;
+use crate::polynomial::{EvaluationDomain, Polynomial};
+use crate::proof::{LinearisedOpening, PlonkProof};
+#[cfg(feature = "std")]
+use crate::prover::{self, ProofPolynomials};
+use crate::transcript::Transcript;
+use crate::verifier::{Challenges, PlonkVerifier, VerifierKey};
+use alloc::vec;
+use alloc::vec::Vec;
+use blstrs::Scalar;
+
+pub trait PolynomialCommitment {
+    type Commitment: Clone;
+    /// Opening of several polynomials at the same point.
+    type Opening;
+    /// Opening of several polynomials, each at its own set of points.
+    type BatchOpening;
+
+    /// Largest number of coefficients of the polynomials that can be committed.
+    fn max_size(&self) -> usize;
+
+    fn commit(&self, polynomial: &Polynomial) -> Self::Commitment;
+
+    /// Opens all `polynomials` at `point`.
+    fn open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        point: &Scalar,
+        transcript: &mut T,
+    ) -> Self::Opening;
+
+    /// Evaluations of the polynomials claimed by an opening.
+    fn evaluations(opening: &Self::Opening) -> &[Scalar];
+
+    /// Checks an opening produced by `open` of the polynomials committed in `commitments`.
+    fn check<T: Transcript>(
+        &self,
+        commitments: &[Self::Commitment],
+        point: &Scalar,
+        opening: &Self::Opening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError>;
+
+    /// Opens the i-th polynomial at the points of `points[i]`.
+    fn batch_open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        points: &[Vec<Scalar>],
+        transcript: &mut T,
+    ) -> Self::BatchOpening;
+
+    /// Evaluations of the polynomials claimed by a batch opening, `[i][j]` being the one of the
+    /// i-th polynomial at its j-th point.
+    fn batch_evaluations(opening: &Self::BatchOpening) -> &[Vec<Scalar>];
+
+    /// Checks an opening produced by `batch_open` of the polynomials committed in
+    /// `commitments`, at the same sets of points.
+    fn check_batch<T: Transcript>(
+        &self,
+        commitments: &[Self::Commitment],
+        points: &[Vec<Scalar>],
+        opening: &Self::BatchOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError>;
+}
+
+impl PolynomialCommitment for Kzg10 {
+    type Commitment = Kzg10Commitment;
+    type Opening = Kzg10Opening;
+    type BatchOpening = MultiOpen;
+
+    fn max_size(&self) -> usize {
+        self.powers_x_g1.len()
+    }
+
+    fn commit(&self, polynomial: &Polynomial) -> Kzg10Commitment {
+        Kzg10::commit(self, polynomial)
+    }
+
+    fn open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        point: &Scalar,
+        transcript: &mut T,
+    ) -> Kzg10Opening {
+        self.open_batch(polynomials, point, transcript)
+    }
+
+    fn evaluations(opening: &Kzg10Opening) -> &[Scalar] {
+        &opening.evaluations
+    }
+
+    fn check<T: Transcript>(
+        &self,
+        commitments: &[Kzg10Commitment],
+        point: &Scalar,
+        opening: &Kzg10Opening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        Kzg10::check_batch(self, commitments, point, opening, transcript)
+    }
+
+    fn batch_open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        points: &[Vec<Scalar>],
+        transcript: &mut T,
+    ) -> MultiOpen {
+        self.multi_open(polynomials, points, transcript)
+    }
+
+    fn batch_evaluations(opening: &MultiOpen) -> &[Vec<Scalar>] {
+        &opening.evaluations
+    }
+
+    fn check_batch<T: Transcript>(
+        &self,
+        commitments: &[Kzg10Commitment],
+        points: &[Vec<Scalar>],
+        opening: &MultiOpen,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        self.check_multi_open(commitments, points, opening, transcript)
+    }
+}
+
+/// Commitment schemes with which PLONK proofs are created and checked.
+pub trait PlonkCommitment: PolynomialCommitment<Commitment: Send> + Clone + Sync {
+    /// Precomputation with which the prover commits to the wires from their values over the
+    /// domain, `()` for the schemes that commit to their coefficients.
+    type LagrangeSet: Clone;
+    /// Proof of the evaluations of a PLONK proof.
+    type ProofOpening: Clone;
+
+    /// What the verifier keys keep of the scheme.
+    fn verifier_part(&self) -> Self;
+
+    fn lagrange_set(&self, domain: &EvaluationDomain) -> Result<Self::LagrangeSet, PlonkError>;
+
+    fn append_commitment<T: Transcript>(
+        transcript: &mut T,
+        label: &'static [u8],
+        commitment: &Self::Commitment,
+    );
+
+    /// Encoding of a commitment, hashed in the fingerprint of the verifier keys.
+    fn commitment_bytes(commitment: &Self::Commitment) -> Vec<u8>;
+
+    /// Encoding of the verifier part of the scheme, hashed in the fingerprint of the verifier
+    /// keys.
+    fn verifier_bytes(&self) -> Vec<u8>;
+
+    /// Commitment to the wire `polynomial`, which takes the `values` over the domain and is
+    /// blinded with the polynomial of coefficients `blinding` times Z_H(X).
+    #[cfg(feature = "std")]
+    fn commit_wire(
+        prover_key: &ProverKey<Self>,
+        _values: &[Scalar],
+        _blinding: &[Scalar],
+        polynomial: &Polynomial,
+    ) -> Self::Commitment {
+        prover_key.scheme.commit(polynomial)
+    }
+
+    /// Proves the evaluations of a proof, once they are absorbed by the transcript.
+    #[cfg(feature = "std")]
+    fn open_proof<T: Transcript>(
+        polynomials: &ProofPolynomials<Self>,
+        transcript: &mut T,
+    ) -> Self::ProofOpening;
+
+    /// Checks the evaluations of a proof and its quotient, once the transcript has absorbed
+    /// them and drawn `challenges`.
+    fn check_proof<T: Transcript>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey<Self>,
+        proof: &PlonkProof<Self>,
+        challenges: &Challenges,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError>;
+}
+
+impl PlonkCommitment for Kzg10 {
+    type LagrangeSet = LagrangeKzg10;
+    type ProofOpening = LinearisedOpening;
+
+    fn verifier_part(&self) -> Self {
+        Kzg10 {
+            powers_x_g1: vec![self.powers_x_g1[0]],
+            powers_x_g2: self.powers_x_g2,
+        }
+    }
+
+    fn lagrange_set(&self, domain: &EvaluationDomain) -> Result<LagrangeKzg10, PlonkError> {
+        self.lagrange_basis(domain)
+    }
+
+    fn append_commitment<T: Transcript>(
+        transcript: &mut T,
+        label: &'static [u8],
+        commitment: &Kzg10Commitment,
+    ) {
+        transcript.append_point(label, &commitment.0);
+    }
+
+    fn commitment_bytes(commitment: &Kzg10Commitment) -> Vec<u8> {
+        commitment.to_bytes().to_vec()
+    }
+
+    fn verifier_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.powers_x_g1[0].to_compressed().to_vec();
+        for point in self.powers_x_g2.iter() {
+            bytes.extend_from_slice(&point.to_compressed());
+        }
+        bytes
+    }
+
+    // The wires are committed from the trace with the SRS in the lagrange basis, and their
+    // blinding terms with the few powers they use.
+    #[cfg(feature = "std")]
+    fn commit_wire(
+        prover_key: &ProverKey,
+        values: &[Scalar],
+        blinding: &[Scalar],
+        _polynomial: &Polynomial,
+    ) -> Kzg10Commitment {
+        prover::commit_wire(prover_key, values, blinding)
+    }
+
+    #[cfg(feature = "std")]
+    fn open_proof<T: Transcript>(
+        polynomials: &ProofPolynomials<Self>,
+        transcript: &mut T,
+    ) -> LinearisedOpening {
+        prover::linearised_opening(polynomials, transcript)
+    }
+
+    fn check_proof<T: Transcript>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey,
+        proof: &PlonkProof,
+        challenges: &Challenges,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        PlonkVerifier::linearised_check(pub_in, verifier_key, proof, challenges, transcript)?
+            .verify(verifier_key, proof)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::transcript::Blake2bTranscript;
+    use ff::Field;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    // Commits, opens and checks through the trait only, so that it can be run for every scheme.
+    pub(crate) fn check_scheme<P: PolynomialCommitment>(scheme: &P) {
+        let mut rng = ChaCha20Rng::from_seed([5u8; 32]);
+        let size = scheme.max_size().min(8);
+        let polynomials = (0..3)
+            .map(|_| Polynomial((0..size).map(|_| Scalar::random(&mut rng)).collect()))
+            .collect::<Vec<_>>();
+        let commitments = polynomials
+            .iter()
+            .map(|poly| scheme.commit(poly))
+            .collect::<Vec<_>>();
+        let point = Scalar::from(11);
+
+        let mut transcript = Blake2bTranscript::new(b"commitment");
+        let opening = scheme.open(&polynomials, &point, &mut transcript);
+        let expected = polynomials
+            .iter()
+            .map(|poly| poly.eval(&point))
+            .collect::<Vec<_>>();
+        assert_eq!(P::evaluations(&opening), expected.as_slice());
+        let mut transcript = Blake2bTranscript::new(b"commitment");
+        assert!(scheme
+            .check(&commitments, &point, &opening, &mut transcript)
+            .is_ok());
+        // Constant polynomials do take the same values at another point.
+        if size > 1 {
+            let mut transcript = Blake2bTranscript::new(b"commitment");
+            assert!(scheme
+                .check(&commitments, &Scalar::from(12), &opening, &mut transcript)
+                .is_err());
+        }
+
+        let points = vec![
+            vec![Scalar::from(1)],
+            vec![Scalar::from(2), Scalar::from(3)],
+            vec![Scalar::from(1), Scalar::from(4)],
+        ];
+        let mut transcript = Blake2bTranscript::new(b"commitment");
+        let opening = scheme.batch_open(&polynomials, &points, &mut transcript);
+        assert_eq!(
+            P::batch_evaluations(&opening)[1],
+            vec![
+                polynomials[1].eval(&points[1][0]),
+                polynomials[1].eval(&points[1][1])
+            ]
+        );
+        let mut transcript = Blake2bTranscript::new(b"commitment");
+        assert!(scheme
+            .check_batch(&commitments, &points, &opening, &mut transcript)
+            .is_ok());
+        let mut swapped = commitments.clone();
+        swapped.swap(0, 1);
+        let mut transcript = Blake2bTranscript::new(b"commitment");
+        assert!(scheme
+            .check_batch(&swapped, &points, &opening, &mut transcript)
+            .is_err());
+    }
+
+    // Proves and checks a circuit with a public input and a lookup with the keys of `scheme`,
+    // and checks that a wrong public input or a wrong evaluation is rejected.
+    #[cfg(feature = "std")]
+    pub(crate) fn check_plonk<P: PlonkCommitment>(scheme: P) {
+        use crate::plonk::PlonkCircuit;
+        use crate::prover::Prover;
+        use crate::witness::WitnessBuilder;
+
+        // x^2 is public, and (x XOR y) is looked up in a table.
+        let mut circuit = PlonkCircuit::init();
+        let pi = circuit.public_input();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        let (y, _, _) = circuit.add_gate();
+        circuit.connect(pi, x_sq).unwrap();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.xor(x_l, y, 2).unwrap();
+        let (prover_key, verifier_key) = circuit.setup_with_srs(scheme).unwrap();
+
+        let mut witness = WitnessBuilder::new(&prover_key.constraints);
+        witness.assign(x_l, Scalar::from(3)).unwrap();
+        witness.assign(y, Scalar::from(1)).unwrap();
+        let trace = witness.build().unwrap();
+        let pub_in = [Scalar::from(9)];
+
+        let mut transcript = Blake2bTranscript::new(b"plonk");
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"plonk");
+        assert!(PlonkVerifier::verify(&pub_in, &verifier_key, &proof, &mut transcript).is_ok());
+
+        let mut transcript = Blake2bTranscript::new(b"plonk");
+        assert_eq!(
+            PlonkVerifier::verify(&[Scalar::from(4)], &verifier_key, &proof, &mut transcript),
+            Err(PlonkError::VerificationFailed)
+        );
+        let mut wrong_proof = proof;
+        wrong_proof.a_eval += Scalar::one();
+        let mut transcript = Blake2bTranscript::new(b"plonk");
+        assert_eq!(
+            PlonkVerifier::verify(&pub_in, &verifier_key, &wrong_proof, &mut transcript),
+            Err(PlonkError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn test_kzg10() {
+        check_scheme(&Kzg10::setup(10, &mut ChaCha20Rng::from_seed([0u8; 32])));
+        #[cfg(feature = "std")]
+        check_plonk(Kzg10::setup(32, &mut ChaCha20Rng::from_seed([0u8; 32])));
+    }
+}
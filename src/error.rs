@@ -0,0 +1,173 @@
+#[cfg(feature = "std")]
+use crate::plonk::UnsatisfiedGate;
+use crate::wire::Wire;
+use alloc::string::String;
+use core::fmt;
+
+/// Errors returned when building, proving or verifying a circuit.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PlonkError {
+    /// A wire position is not part of the circuit.
+    WireOutOfBounds { wire: usize, nr_wires: usize },
+    /// A wire handle does not belong to the circuit.
+    UnknownWire(Wire),
+    /// The domain size must be a non-zero power of two fitting the two-adicity of the field.
+    InvalidDomainSize(usize),
+    /// The circuit has more constraints than what the SRS supports.
+    CircuitTooLarge { nr_constraints: usize, max: usize },
+    /// The number of public values does not match the public inputs of the circuit.
+    PublicInputLength { expected: usize, got: usize },
+    /// The computation trace does not satisfy a copy constraint.
+    CopyConstraintUnsatisfied { wire: Wire, connected: Wire },
+    /// The quotient of the prover is not a polynomial. The prover first checks the gates, the
+    /// copy constraints and the lookups on the trace alone, which return `GateFailed`,
+    /// `CopyConstraintUnsatisfied` and `LookupFailed`, so this is returned when the public
+    /// inputs given to the prover do not match the rows of the trace that carry them.
+    PublicInputsUnsatisfied,
+    /// The computation trace does not satisfy the gate at `row`, named `label` if it has one.
+    /// Returned by the checks of the trace that locate the gate, such as the prover's.
+    GateFailed { row: usize, label: Option<String> },
+    /// The columns of the computation trace do not have the same number of rows.
+    TraceShape { a: usize, b: usize, c: usize },
+    /// We tried to invert zero, which only happens with degenerate inputs.
+    NonInvertible,
+    /// The polynomial is not a multiple of the divisor.
+    NotDivisible,
+    /// The proof is not valid.
+    VerificationFailed,
+    /// Reading or writing data failed.
+    Io(String),
+    /// The bytes do not encode a valid point of the prime order subgroup.
+    InvalidPoint,
+    /// The structured reference string is not well formed.
+    InvalidSrs,
+    /// The secret of a contribution to the SRS is zero, which would erase its toxic waste.
+    ZeroSecret,
+    /// The bytes do not encode a canonical scalar.
+    InvalidScalar,
+    /// The encoding does not have the expected length.
+    InvalidLength { expected: usize, got: usize },
+    /// The wires of the lookup gate at `row` are not a row of the lookup table.
+    LookupFailed { row: usize },
+    /// There is no lookup table with this identifier in the circuit.
+    UnknownLookupTable(usize),
+    /// The lookup tables have more rows than the circuit has constraints.
+    LookupTableTooLarge {
+        table_size: usize,
+        nr_constraints: usize,
+    },
+    /// A range check over more bits than the ones that fit in a scalar without wrapping.
+    RangeTooLarge { num_bits: usize, max: usize },
+    /// A constraint system refers to a variable that it does not have.
+    UnknownVariable {
+        variable: usize,
+        nr_variables: usize,
+    },
+    /// A circuit was synthesized without the value of one of its variables.
+    AssignmentMissing,
+    /// A bellman or arkworks circuit failed to synthesize for another reason than a missing
+    /// value.
+    Synthesis(String),
+    /// The JSON encoding of a circuit is not valid.
+    InvalidJson(String),
+    /// The file does not follow the expected binary format.
+    InvalidFormat(String),
+}
+
+impl fmt::Display for PlonkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlonkError::WireOutOfBounds { wire, nr_wires } => write!(
+                f,
+                "The circuit does not have enough wires. Max {nr_wires}, got {wire}"
+            ),
+            PlonkError::UnknownWire(wire) => write!(f, "Wire {wire:?} is not part of the circuit"),
+            PlonkError::InvalidDomainSize(size) => write!(f, "Invalid domain size {size}"),
+            PlonkError::CircuitTooLarge {
+                nr_constraints,
+                max,
+            } => write!(
+                f,
+                "Circuit with {nr_constraints} constraints, but the SRS supports at most {max}"
+            ),
+            PlonkError::PublicInputLength { expected, got } => {
+                write!(f, "Expected {expected} public inputs, got {got}")
+            }
+            PlonkError::CopyConstraintUnsatisfied { wire, connected } => write!(
+                f,
+                "Wires {wire:?} and {connected:?} are connected but have different values"
+            ),
+            PlonkError::PublicInputsUnsatisfied => {
+                write!(f, "The public inputs do not match the computation trace")
+            }
+            PlonkError::GateFailed { row, label } => match label {
+                Some(label) => write!(f, "The gate \"{label}\" at row {row} is not satisfied"),
+                None => write!(f, "The gate at row {row} is not satisfied"),
+            },
+            PlonkError::TraceShape { a, b, c } => write!(
+                f,
+                "The columns of the trace have different lengths: a {a}, b {b}, c {c}"
+            ),
+            PlonkError::NonInvertible => write!(f, "Tried to invert zero"),
+            PlonkError::NotDivisible => write!(f, "The polynomial is not divisible"),
+            PlonkError::VerificationFailed => write!(f, "Proof verification failed"),
+            PlonkError::Io(error) => write!(f, "I/O error: {error}"),
+            PlonkError::ZeroSecret => write!(f, "The secret of the contribution is zero"),
+            PlonkError::InvalidPoint => write!(f, "Invalid point encoding"),
+            PlonkError::InvalidSrs => write!(f, "The SRS is not well formed"),
+            PlonkError::InvalidScalar => write!(f, "Invalid scalar encoding"),
+            PlonkError::InvalidLength { expected, got } => {
+                write!(f, "Expected {expected} bytes, got {got}")
+            }
+            PlonkError::LookupFailed { row } => {
+                write!(f, "The lookup gate at row {row} is not in the lookup table")
+            }
+            PlonkError::UnknownLookupTable(id) => {
+                write!(f, "There is no lookup table with identifier {id}")
+            }
+            PlonkError::LookupTableTooLarge {
+                table_size,
+                nr_constraints,
+            } => write!(
+                f,
+                "Lookup tables with {table_size} rows, but the circuit only has {nr_constraints} constraints"
+            ),
+            PlonkError::RangeTooLarge { num_bits, max } => {
+                write!(f, "Range checks support at most {max} bits, got {num_bits}")
+            }
+            PlonkError::UnknownVariable {
+                variable,
+                nr_variables,
+            } => write!(
+                f,
+                "Variable {variable} is not one of the {nr_variables} variables of the system"
+            ),
+            PlonkError::AssignmentMissing => write!(f, "The value of a variable is missing"),
+            PlonkError::Synthesis(error) => write!(f, "Synthesis failed: {error}"),
+            PlonkError::InvalidJson(error) => write!(f, "Invalid JSON circuit: {error}"),
+            PlonkError::InvalidFormat(error) => write!(f, "Invalid file format: {error}"),
+        }
+    }
+}
+
+impl core::error::Error for PlonkError {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for PlonkError {
+    fn from(error: std::io::Error) -> Self {
+        PlonkError::Io(error.to_string())
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<UnsatisfiedGate> for PlonkError {
+    fn from(error: UnsatisfiedGate) -> Self {
+        match error {
+            UnsatisfiedGate::CopyConstraint {
+                wire, connected, ..
+            } => PlonkError::CopyConstraintUnsatisfied { wire, connected },
+            UnsatisfiedGate::Gate { row, label, .. } => PlonkError::GateFailed { row, label },
+            UnsatisfiedGate::Lookup { row, .. } => PlonkError::LookupFailed { row },
+        }
+    }
+}
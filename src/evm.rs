@@ -0,0 +1,56 @@
+// Encodings of the EVM, shared by the Solidity verifier, the Keccak transcript and the
+// calldata of proofs.
+use crate::keccak::keccak256;
+use blstrs::{G1Affine, G2Affine};
+use group::prime::PrimeCurveAffine;
+
+/// Encodes a point of G1 as in EIP-2537: each coordinate is padded to 64 bytes in big endian,
+/// and the identity is all zeroes.
+pub(crate) fn encode_g1(point: &G1Affine) -> [u8; 128] {
+    let mut bytes = [0u8; 128];
+    if !bool::from(point.is_identity()) {
+        let uncompressed = point.to_uncompressed();
+        bytes[16..64].copy_from_slice(&uncompressed[..48]);
+        bytes[80..].copy_from_slice(&uncompressed[48..]);
+    }
+    bytes
+}
+
+/// Encodes a point of G2 as in EIP-2537. Coefficients of Fp2 are ordered (c0, c1), while the
+/// uncompressed encoding of blstrs starts with c1.
+pub(crate) fn encode_g2(point: &G2Affine) -> [u8; 256] {
+    let mut bytes = [0u8; 256];
+    if !bool::from(point.is_identity()) {
+        let uncompressed = point.to_uncompressed();
+        for (i, coefficient) in [1, 0, 3, 2].into_iter().enumerate() {
+            bytes[64 * i + 16..64 * (i + 1)]
+                .copy_from_slice(&uncompressed[48 * coefficient..48 * (coefficient + 1)]);
+        }
+    }
+    bytes
+}
+
+/// Selector of `verify(bytes,uint256[])`, the entry point of the contract.
+pub(crate) fn verify_selector() -> [u8; 4] {
+    keccak256(b"verify(bytes,uint256[])")[..4]
+        .try_into()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodings() {
+        assert_eq!(encode_g1(&G1Affine::identity()), [0u8; 128]);
+        let bytes = encode_g1(&G1Affine::generator());
+        assert_eq!(bytes[..16], [0u8; 16]);
+        assert_eq!(bytes[16..64], G1Affine::generator().to_uncompressed()[..48]);
+
+        // The x coordinate of the generator of G2 starts with c0 = 0x024aa2b2...
+        let bytes = encode_g2(&G2Affine::generator());
+        assert_eq!(bytes[16..20], [0x02, 0x4a, 0xa2, 0xb2]);
+        assert_eq!(bytes[64 + 16..64 + 20], [0x13, 0xe0, 0x2b, 0x60]);
+    }
+}
@@ -0,0 +1,644 @@
+// Arithmetic expressions over the wires of a circuit, compiled to gates by
+// `PlonkCircuit::enforce` and `PlonkCircuit::compile`.
+//
+// An expression is reduced to a quadratic form m * x * y + sum_i m_i * w_i + k, with at most one
+// product, before any gate is created. Sums and multiplications by constants are folded into
+// the selectors of the gates that consume them, and a product is only given its own gate when
+// it is multiplied again, or added to another product. The last gate of the form takes the
+// product and the linear terms in x and y, and one more term in its c wire, so that for
+// example a * b - c is enforced with a single gate. Two wires that are enforced to be equal
+// are connected, without any gate.
+//
+// The `circuit!` macro builds a circuit from a sequence of statements, with a witness closure
+// that derives the trace from the values of the inputs.
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit};
+use crate::wire::Wire;
+use crate::witness::WitnessBuilder;
+use blstrs::Scalar;
+use ff::Field;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Arithmetic expression over the wires of a circuit, built with the operators `+`, `-` and
+/// `*`, e.g. `Expr::var(a) * b - c`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Const(Scalar),
+    Var(Wire),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn var(wire: Wire) -> Self {
+        Expr::Var(wire)
+    }
+
+    pub fn constant(value: impl Into<Scalar>) -> Self {
+        Expr::Const(value.into())
+    }
+
+    /// Value of the expression, with the values of its wires given by `value`.
+    pub fn evaluate(&self, value: &impl Fn(Wire) -> Scalar) -> Scalar {
+        match self {
+            Expr::Const(constant) => *constant,
+            Expr::Var(wire) => value(*wire),
+            Expr::Add(left, right) => left.evaluate(value) + right.evaluate(value),
+            Expr::Mul(left, right) => left.evaluate(value) * right.evaluate(value),
+        }
+    }
+}
+
+impl From<Wire> for Expr {
+    fn from(wire: Wire) -> Self {
+        Expr::Var(wire)
+    }
+}
+
+impl From<Scalar> for Expr {
+    fn from(value: Scalar) -> Self {
+        Expr::Const(value)
+    }
+}
+
+impl From<u64> for Expr {
+    fn from(value: u64) -> Self {
+        Expr::Const(Scalar::from(value))
+    }
+}
+
+impl<T: Into<Expr>> Add<T> for Expr {
+    type Output = Expr;
+
+    fn add(self, rhs: T) -> Expr {
+        Expr::Add(Box::new(self), Box::new(rhs.into()))
+    }
+}
+
+impl<T: Into<Expr>> Sub<T> for Expr {
+    type Output = Expr;
+
+    fn sub(self, rhs: T) -> Expr {
+        self + -rhs.into()
+    }
+}
+
+impl<T: Into<Expr>> Mul<T> for Expr {
+    type Output = Expr;
+
+    fn mul(self, rhs: T) -> Expr {
+        Expr::Mul(Box::new(self), Box::new(rhs.into()))
+    }
+}
+
+impl Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        Expr::Const(Scalar::one().neg()) * self
+    }
+}
+
+// Operators on wires, so that they can be used in expressions without `Expr::var`.
+impl<T: Into<Expr>> Add<T> for Wire {
+    type Output = Expr;
+
+    fn add(self, rhs: T) -> Expr {
+        Expr::Var(self) + rhs
+    }
+}
+
+impl<T: Into<Expr>> Sub<T> for Wire {
+    type Output = Expr;
+
+    fn sub(self, rhs: T) -> Expr {
+        Expr::Var(self) - rhs
+    }
+}
+
+impl<T: Into<Expr>> Mul<T> for Wire {
+    type Output = Expr;
+
+    fn mul(self, rhs: T) -> Expr {
+        Expr::Var(self) * rhs
+    }
+}
+
+impl Mul<Wire> for u64 {
+    type Output = Expr;
+
+    fn mul(self, rhs: Wire) -> Expr {
+        Expr::from(self) * rhs
+    }
+}
+
+impl Mul<Wire> for Scalar {
+    type Output = Expr;
+
+    fn mul(self, rhs: Wire) -> Expr {
+        Expr::from(self) * rhs
+    }
+}
+
+/// Trace of a circuit built with `circuit!`, from the values of its inputs, in the order in
+/// which they are declared.
+pub fn witness(
+    circuit: &PlonkCircuit,
+    inputs: &[Wire],
+    values: &[Scalar],
+) -> Result<ComputationTrace, PlonkError> {
+    if inputs.len() != values.len() {
+        return Err(PlonkError::InvalidLength {
+            expected: inputs.len(),
+            got: values.len(),
+        });
+    }
+    let mut builder = WitnessBuilder::new(circuit);
+    for (wire, value) in inputs.iter().zip(values) {
+        builder.assign(*wire, *value)?;
+    }
+    builder.build()
+}
+
+/// Builds a circuit from statements over its inputs, e.g.
+///
+/// ```
+/// use blstrs::Scalar;
+/// use dummy_plonk::circuit;
+///
+/// # fn main() -> Result<(), dummy_plonk::PlonkError> {
+/// let (circuit, witness) = circuit! {
+///     public(out);
+///     private(x);
+///     let t = x * x;
+///     let y = t + 5;
+///     assert_eq!(y, out);
+/// }?;
+/// let trace = witness(&circuit, &[Scalar::from(14)], &[Scalar::from(3)])?;
+/// assert!(circuit.is_satisfied(&trace).is_ok());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Both lists of inputs are required, and may be empty. Public inputs have a public input gate
+/// each, and private inputs an empty gate. Every `let`
+/// binds a wire to the value of its expression (with `PlonkCircuit::compile`), and every
+/// `assert_eq!` enforces that two expressions are equal (with `PlonkCircuit::enforce`). The
+/// result is the circuit and a closure computing its trace from the values of the public and
+/// the private inputs, or the first error of the statements.
+#[macro_export]
+macro_rules! circuit {
+    (
+        public($($public:ident),* $(,)?);
+        private($($private:ident),* $(,)?);
+        $($body:tt)*
+    ) => {
+        (|| -> Result<_, $crate::__private::PlonkError> {
+            let mut circuit = $crate::__private::PlonkCircuit::init();
+            let mut public_wires = $crate::__private::Vec::new();
+            let mut private_wires = $crate::__private::Vec::new();
+            $(
+                let $public = circuit.public_input();
+                public_wires.push($public);
+            )*
+            $(
+                let $private = circuit.gate($crate::__private::Gate::new()).0;
+                private_wires.push($private);
+            )*
+            $crate::circuit!(@body circuit; $($body)*);
+            let witness = move |circuit: &$crate::__private::PlonkCircuit,
+                                public: &[$crate::__private::Scalar],
+                                private: &[$crate::__private::Scalar]| {
+                if public.len() != public_wires.len() {
+                    return Err($crate::__private::PlonkError::PublicInputLength {
+                        expected: public_wires.len(),
+                        got: public.len(),
+                    });
+                }
+                let inputs = [public_wires.as_slice(), private_wires.as_slice()].concat();
+                $crate::__private::witness(circuit, &inputs, &[public, private].concat())
+            };
+            Ok((circuit, witness))
+        })()
+    };
+    (@body $circuit:ident;) => {};
+    (@body $circuit:ident; let $name:ident = $value:expr; $($rest:tt)*) => {
+        let $name = $circuit.compile(&$crate::__private::Expr::from($value))?;
+        $crate::circuit!(@body $circuit; $($rest)*);
+    };
+    (@body $circuit:ident; assert_eq!($left:expr, $right:expr); $($rest:tt)*) => {
+        $circuit.enforce(&($crate::__private::Expr::from($left) - $right))?;
+        $crate::circuit!(@body $circuit; $($rest)*);
+    };
+}
+
+// m * x * y + sum_i m_i * w_i + k. Linear terms have distinct wires and non zero coefficients.
+#[derive(Clone, Default)]
+struct Quadratic {
+    product: Option<(Wire, Wire, Scalar)>,
+    linear: Vec<(Wire, Scalar)>,
+    constant: Scalar,
+}
+
+impl Quadratic {
+    fn constant(value: Scalar) -> Self {
+        Quadratic {
+            constant: value,
+            ..Default::default()
+        }
+    }
+
+    fn is_constant(&self) -> bool {
+        self.product.is_none() && self.linear.is_empty()
+    }
+
+    fn add_term(&mut self, wire: Wire, coefficient: Scalar) {
+        match self.linear.iter().position(|(w, _)| *w == wire) {
+            Some(i) => {
+                self.linear[i].1 += coefficient;
+                if self.linear[i].1.is_zero_vartime() {
+                    self.linear.remove(i);
+                }
+            }
+            None if !coefficient.is_zero_vartime() => self.linear.push((wire, coefficient)),
+            None => {}
+        }
+    }
+
+    fn scale(&mut self, factor: Scalar) {
+        if factor.is_zero_vartime() {
+            *self = Quadratic::default();
+            return;
+        }
+        if let Some((_, _, m)) = self.product.as_mut() {
+            *m *= factor;
+        }
+        for (_, coefficient) in self.linear.iter_mut() {
+            *coefficient *= factor;
+        }
+        self.constant *= factor;
+    }
+}
+
+pub(crate) struct Compiler<'a> {
+    circuit: &'a mut PlonkCircuit,
+}
+
+impl<'a> Compiler<'a> {
+    pub(crate) fn new(circuit: &'a mut PlonkCircuit) -> Self {
+        Compiler { circuit }
+    }
+
+    // Creates a gate with the selectors of `gate`, connecting its wires to the given ones.
+    fn gate(
+        &mut self,
+        gate: Gate,
+        wires: [Option<Wire>; 3],
+    ) -> Result<(Wire, Wire, Wire), PlonkError> {
+        let (a, b, c) = self.circuit.gate(gate);
+        for (wire, connected) in [a, b, c].into_iter().zip(wires) {
+            if let Some(connected) = connected {
+                self.circuit.connect(connected, wire)?;
+            }
+        }
+        Ok((a, b, c))
+    }
+
+    fn quadratic(&mut self, expr: &Expr) -> Result<Quadratic, PlonkError> {
+        Ok(match expr {
+            Expr::Const(value) => Quadratic::constant(*value),
+            Expr::Var(wire) => {
+                if !self.circuit.permutations.contains_key(wire) {
+                    return Err(PlonkError::UnknownWire(*wire));
+                }
+                let mut quadratic = Quadratic::default();
+                quadratic.add_term(*wire, Scalar::one());
+                quadratic
+            }
+            Expr::Add(left, right) => {
+                let mut sum = self.quadratic(left)?;
+                let right = self.quadratic(right)?;
+                if let Some(product) = right.product {
+                    if sum.product.is_some() {
+                        let product = self.wire(Quadratic {
+                            product: Some(product),
+                            ..Default::default()
+                        })?;
+                        sum.add_term(product, Scalar::one());
+                    } else {
+                        sum.product = Some(product);
+                    }
+                }
+                for (wire, coefficient) in right.linear {
+                    sum.add_term(wire, coefficient);
+                }
+                sum.constant += right.constant;
+                sum
+            }
+            Expr::Mul(left, right) => {
+                let mut left = self.quadratic(left)?;
+                let mut right = self.quadratic(right)?;
+                if left.is_constant() {
+                    right.scale(left.constant);
+                    return Ok(right);
+                }
+                if right.is_constant() {
+                    left.scale(right.constant);
+                    return Ok(left);
+                }
+                // (m1 * x + k1) * (m2 * y + k2)
+                let (x, m1, k1) = self.term(left)?;
+                let (y, m2, k2) = self.term(right)?;
+                let mut product = Quadratic {
+                    product: Some((x, y, m1 * m2)),
+                    linear: Vec::new(),
+                    constant: k1 * k2,
+                };
+                product.add_term(x, m1 * k2);
+                product.add_term(y, k1 * m2);
+                product
+            }
+        })
+    }
+
+    // Writes `quadratic` as m * w + k, with a new wire w unless it already has this form.
+    fn term(&mut self, mut quadratic: Quadratic) -> Result<(Wire, Scalar, Scalar), PlonkError> {
+        if quadratic.product.is_none() && quadratic.linear.len() == 1 {
+            let (wire, coefficient) = quadratic.linear[0];
+            return Ok((wire, coefficient, quadratic.constant));
+        }
+        let constant = quadratic.constant;
+        quadratic.constant = Scalar::zero();
+        Ok((self.wire(quadratic)?, Scalar::one(), constant))
+    }
+
+    // Wire carrying the value of `quadratic`.
+    fn wire(&mut self, mut quadratic: Quadratic) -> Result<Wire, PlonkError> {
+        if let Some((x, y, m)) = quadratic.product.take() {
+            if quadratic.linear.len() > 2
+                || quadratic
+                    .linear
+                    .iter()
+                    .any(|(wire, _)| *wire != x && *wire != y)
+            {
+                // The product gets its own gate, and is then added to the other terms.
+                let product = self.wire(Quadratic {
+                    product: Some((x, y, m)),
+                    ..Default::default()
+                })?;
+                quadratic.add_term(product, Scalar::one());
+                return self.wire(quadratic);
+            }
+            let coefficient = |wire| {
+                quadratic
+                    .linear
+                    .iter()
+                    .find(|(w, _)| *w == wire)
+                    .map_or(Scalar::zero(), |(_, coefficient)| *coefficient)
+            };
+            let (ql, qr) = if x == y {
+                (coefficient(x), Scalar::zero())
+            } else {
+                (coefficient(x), coefficient(y))
+            };
+            let gate = Gate::new()
+                .mul(m)
+                .left(ql)
+                .right(qr)
+                .constant(quadratic.constant)
+                .output(-1);
+            let (_, _, c) = self.gate(gate, [Some(x), Some(y), None])?;
+            return Ok(c);
+        }
+
+        match quadratic.linear.as_slice() {
+            [(wire, coefficient)]
+                if *coefficient == Scalar::one() && quadratic.constant.is_zero_vartime() =>
+            {
+                return Ok(*wire)
+            }
+            [] => {
+                let gate = Gate::new().constant(quadratic.constant).output(-1);
+                let (_, _, c) = self.gate(gate, [None, None, None])?;
+                return Ok(c);
+            }
+            _ => {}
+        }
+        self.reduce(&mut quadratic.linear, 2)?;
+        let left = quadratic.linear[0];
+        let right = quadratic
+            .linear
+            .get(1)
+            .copied()
+            .map_or((None, Scalar::zero()), |(wire, coefficient)| {
+                (Some(wire), coefficient)
+            });
+        let gate = Gate::new()
+            .left(left.1)
+            .right(right.1)
+            .constant(quadratic.constant)
+            .output(-1);
+        let (_, _, c) = self.gate(gate, [Some(left.0), right.0, None])?;
+        Ok(c)
+    }
+
+    // Adds up linear terms until at most `max` are left, using a gate per extra term.
+    fn reduce(&mut self, linear: &mut Vec<(Wire, Scalar)>, max: usize) -> Result<(), PlonkError> {
+        while linear.len() > max {
+            let right = linear.pop().expect("There are at least two terms");
+            let left = linear.pop().expect("There are at least two terms");
+            let gate = Gate::new().left(left.1).right(right.1).output(-1);
+            let (_, _, sum) = self.gate(gate, [Some(left.0), Some(right.0), None])?;
+            linear.insert(0, (sum, Scalar::one()));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn compile(&mut self, expr: &Expr) -> Result<Wire, PlonkError> {
+        let quadratic = self.quadratic(expr)?;
+        self.wire(quadratic)
+    }
+
+    pub(crate) fn enforce(&mut self, expr: &Expr) -> Result<(), PlonkError> {
+        let mut quadratic = self.quadratic(expr)?;
+        let (x, y, m) = match quadratic.product.take() {
+            Some(product) => product,
+            None if quadratic.is_constant() && quadratic.constant.is_zero_vartime() => {
+                return Ok(())
+            }
+            None => {
+                if let [(x, m), (y, n)] = quadratic.linear.as_slice() {
+                    if *m == n.neg() && quadratic.constant.is_zero_vartime() {
+                        return self.circuit.connect(*x, *y);
+                    }
+                }
+                // q_L * a + q_R * b + q_O * c + q_C = 0.
+                self.reduce(&mut quadratic.linear, 3)?;
+                let term = |i: usize| {
+                    quadratic
+                        .linear
+                        .get(i)
+                        .map_or((None, Scalar::zero()), |(wire, coefficient)| {
+                            (Some(*wire), *coefficient)
+                        })
+                };
+                let (a, b, c) = (term(0), term(1), term(2));
+                let gate = Gate::new()
+                    .left(a.1)
+                    .right(b.1)
+                    .output(c.1)
+                    .constant(quadratic.constant);
+                self.gate(gate, [a.0, b.0, c.0])?;
+                return Ok(());
+            }
+        };
+
+        // q_M * x * y + q_L * x + q_R * y + q_O * c + q_C = 0, with every other term in c.
+        let mut coefficient =
+            |wire: Wire| match quadratic.linear.iter().position(|(w, _)| *w == wire) {
+                Some(i) => quadratic.linear.remove(i).1,
+                None => Scalar::zero(),
+            };
+        let ql = coefficient(x);
+        let qr = if x == y {
+            Scalar::zero()
+        } else {
+            coefficient(y)
+        };
+        let (c, qo) = match quadratic.linear.len() {
+            0 => (None, Scalar::zero()),
+            1 => (Some(quadratic.linear[0].0), quadratic.linear[0].1),
+            _ => {
+                let others = Quadratic {
+                    linear: quadratic.linear,
+                    ..Default::default()
+                };
+                (Some(self.wire(others)?), Scalar::one())
+            }
+        };
+        let gate = Gate::new()
+            .mul(m)
+            .left(ql)
+            .right(qr)
+            .output(qo)
+            .constant(quadratic.constant);
+        self.gate(gate, [Some(x), Some(y), c])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::ComputationTrace;
+    use crate::witness::WitnessBuilder;
+
+    // Assigns `inputs` and derives the other wires of the trace, which fails if a gate that
+    // enforces an expression does not hold.
+    fn trace(
+        circuit: &PlonkCircuit,
+        inputs: &[(Wire, u64)],
+    ) -> Result<ComputationTrace, PlonkError> {
+        let mut builder = WitnessBuilder::new(circuit);
+        for (wire, value) in inputs {
+            builder.assign(*wire, Scalar::from(*value))?;
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_enforce() {
+        // a * b - c takes a single gate.
+        let mut circuit = PlonkCircuit::init();
+        let (a, _, _) = circuit.gate(Gate::new());
+        let (b, _, _) = circuit.gate(Gate::new());
+        let (c, _, _) = circuit.gate(Gate::new());
+        circuit.enforce(&(Expr::var(a) * b - c)).unwrap();
+        assert_eq!(circuit.nr_constraints, 4);
+        let valid = trace(&circuit, &[(a, 3), (b, 4), (c, 12)]).unwrap();
+        assert!(circuit.is_satisfied(&valid).is_ok());
+        assert!(trace(&circuit, &[(a, 3), (b, 4), (c, 13)]).is_err());
+
+        // (a + 2) * (b + c) = 5 * c + 7 takes a gate for b + c and one for the rest.
+        let mut circuit = PlonkCircuit::init();
+        let (a, _, _) = circuit.gate(Gate::new());
+        let (b, _, _) = circuit.gate(Gate::new());
+        let (c, _, _) = circuit.gate(Gate::new());
+        let expr = (Expr::var(a) + 2) * (Expr::var(b) + c) - Expr::var(c) * 5 - 7;
+        circuit.enforce(&expr).unwrap();
+        assert_eq!(circuit.nr_constraints, 5);
+        // (1 + 2) * (3 + 1) = 12 = 5 * 1 + 7.
+        let values = |wire: Wire| Scalar::from([1, 3, 1][wire.row]);
+        assert!(expr.evaluate(&values).is_zero_vartime());
+        let valid = trace(&circuit, &[(a, 1), (b, 3), (c, 1)]).unwrap();
+        assert!(circuit.is_satisfied(&valid).is_ok());
+        assert!(trace(&circuit, &[(a, 1), (b, 3), (c, 2)]).is_err());
+
+        assert_eq!(
+            circuit.enforce(&Expr::var(Wire::left(10))),
+            Err(PlonkError::UnknownWire(Wire::left(10)))
+        );
+    }
+
+    #[test]
+    fn test_circuit_macro() {
+        let (circuit, witness) = circuit! {
+            public(out);
+            private(x);
+            let t = x * x;
+            let y = t + 5;
+            assert_eq!(y, out);
+        }
+        .unwrap();
+        // A public input, a private input, x * x and t + 5, with y connected to out.
+        assert_eq!(circuit.nr_constraints, 4);
+        let trace = witness(&circuit, &[Scalar::from(14)], &[Scalar::from(3)]).unwrap();
+        assert!(circuit.is_satisfied(&trace).is_ok());
+        assert!(witness(&circuit, &[Scalar::from(15)], &[Scalar::from(3)]).is_err());
+        assert_eq!(
+            witness(&circuit, &[], &[Scalar::from(3)]).err(),
+            Some(PlonkError::PublicInputLength {
+                expected: 1,
+                got: 0
+            })
+        );
+        assert_eq!(
+            witness(&circuit, &[Scalar::from(14)], &[]).err(),
+            Some(PlonkError::InvalidLength {
+                expected: 2,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_compile() {
+        // y = x^3 + x + 5 takes a gate for x^2 and one for x^2 * x + x + 5.
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.gate(Gate::new());
+        let y = circuit.compile(&(Expr::var(x) * x * x + x + 5)).unwrap();
+        assert_eq!(circuit.nr_constraints, 3);
+        let trace_x = trace(&circuit, &[(x, 3)]).unwrap();
+        assert_eq!(trace_x.value(y), Scalar::from(35));
+        assert!(circuit.is_satisfied(&trace_x).is_ok());
+
+        // A sum of four wires takes three gates, a wire none and a constant one.
+        let mut circuit = PlonkCircuit::init();
+        let wires = (0..4)
+            .map(|_| circuit.gate(Gate::new()).0)
+            .collect::<Vec<_>>();
+        let sum = wires
+            .iter()
+            .fold(Expr::constant(1), |sum, wire| sum + *wire);
+        let total = circuit.compile(&sum).unwrap();
+        assert_eq!(circuit.nr_constraints, 7);
+        assert_eq!(circuit.compile(&Expr::var(wires[0])).unwrap(), wires[0]);
+        let one = circuit.compile(&Expr::constant(1)).unwrap();
+        assert_eq!(circuit.nr_constraints, 8);
+        let inputs = wires.iter().map(|wire| (*wire, 2)).collect::<Vec<_>>();
+        let trace = trace(&circuit, &inputs).unwrap();
+        assert_eq!(trace.value(total), Scalar::from(9));
+        assert_eq!(trace.value(one), Scalar::one());
+        assert!(circuit.is_satisfied(&trace).is_ok());
+    }
+}
@@ -0,0 +1,261 @@
+// C interface, behind the `ffi` feature, for embedding the prover and the verifier in other
+// languages (Go with cgo, Python with ctypes or cffi, Swift, ...). The declarations are in
+// `include/plonk.h`. All inputs are byte buffers, in the encodings described in
+// `bindings.rs`, and functions return a status code:
+//
+// * `PLONK_OK` on success (or for a valid proof),
+// * `PLONK_INVALID_PROOF` when a well formed proof does not verify,
+// * `PLONK_ERROR` otherwise, in which case `plonk_last_error` describes the error.
+//
+// Buffers returned by the library are owned by the caller, who releases them with
+// `plonk_buffer_free`, and verifier keys loaded with `plonk_load_vk` are released with
+// `plonk_vk_free`. Panics are caught at the boundary and reported as errors.
+use crate::bindings;
+use crate::error::PlonkError;
+use crate::verifier::VerifierKey;
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+pub const PLONK_OK: i32 = 0;
+pub const PLONK_INVALID_PROOF: i32 = 1;
+pub const PLONK_ERROR: i32 = -1;
+
+/// Bytes allocated by the library.
+#[repr(C)]
+pub struct PlonkBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl PlonkBuffer {
+    fn new(bytes: Vec<u8>) -> Self {
+        let len = bytes.len();
+        let data = Box::into_raw(bytes.into_boxed_slice()) as *mut u8;
+        PlonkBuffer { data, len }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = message);
+}
+
+// Runs `f`, storing its error (or panic) as the last error. Nothing is used after a panic
+// but the message, so the closure does not need to be unwind safe.
+fn guard<F: FnOnce() -> Result<i32, PlonkError>>(f: F) -> i32 {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(status)) => status,
+        Ok(Err(PlonkError::VerificationFailed)) => PLONK_INVALID_PROOF,
+        Ok(Err(error)) => {
+            set_last_error(error.to_string());
+            PLONK_ERROR
+        }
+        Err(_) => {
+            set_last_error("The library panicked".to_string());
+            PLONK_ERROR
+        }
+    }
+}
+
+// Borrows `len` bytes at `data`, which may be null if `len` is zero.
+unsafe fn slice<'a>(data: *const u8, len: usize) -> Result<&'a [u8], PlonkError> {
+    match (data.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(PlonkError::Io("Null buffer".to_string())),
+        (false, _) => Ok(std::slice::from_raw_parts(data, len)),
+    }
+}
+
+/// Message of the last error of the calling thread. The string is valid until the next call
+/// to the library from the same thread.
+#[no_mangle]
+pub extern "C" fn plonk_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| error.borrow().as_ptr())
+}
+
+/// Computes the encoded prover and verifier keys of an encoded circuit into `pk_out` and
+/// `vk_out`, with the SRS of a Powers of Tau response file of `tau_powers_length` powers in G2.
+///
+/// # Safety
+///
+/// `circuit` and `srs` must point to `circuit_len` and `srs_len` readable bytes, and `pk_out`
+/// and `vk_out` to writable buffers.
+#[no_mangle]
+pub unsafe extern "C" fn plonk_setup(
+    circuit: *const u8,
+    circuit_len: usize,
+    srs: *const u8,
+    srs_len: usize,
+    tau_powers_length: usize,
+    pk_out: *mut PlonkBuffer,
+    vk_out: *mut PlonkBuffer,
+) -> i32 {
+    guard(|| {
+        let pk = bindings::prover_key(
+            slice(circuit, circuit_len)?,
+            slice(srs, srs_len)?,
+            tau_powers_length,
+        )?;
+        let vk = bindings::verifier_key(&pk)?;
+        *pk_out = PlonkBuffer::new(pk);
+        *vk_out = PlonkBuffer::new(vk);
+        Ok(PLONK_OK)
+    })
+}
+
+/// Proves that the encoded trace satisfies the circuit of the encoded prover key, writing the
+/// proof into `proof_out`.
+///
+/// # Safety
+///
+/// `pk` and `witness` must point to `pk_len` and `witness_len` readable bytes, and
+/// `proof_out` to a writable buffer.
+#[no_mangle]
+pub unsafe extern "C" fn plonk_prove(
+    pk: *const u8,
+    pk_len: usize,
+    witness: *const u8,
+    witness_len: usize,
+    proof_out: *mut PlonkBuffer,
+) -> i32 {
+    guard(|| {
+        let proof = bindings::prove(slice(pk, pk_len)?, slice(witness, witness_len)?)?;
+        *proof_out = PlonkBuffer::new(proof);
+        Ok(PLONK_OK)
+    })
+}
+
+/// Decodes a verifier key, so that it is only checked once for many proofs. Returns null on
+/// error.
+///
+/// # Safety
+///
+/// `vk` must point to `vk_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn plonk_load_vk(vk: *const u8, vk_len: usize) -> *mut VerifierKey {
+    let mut loaded = ptr::null_mut();
+    guard(|| {
+        let verifier_key = VerifierKey::deserialize(slice(vk, vk_len)?)?;
+        loaded = Box::into_raw(Box::new(verifier_key));
+        Ok(PLONK_OK)
+    });
+    loaded
+}
+
+/// Checks an encoded proof for the concatenated public inputs.
+///
+/// # Safety
+///
+/// `vk` must be a key returned by `plonk_load_vk` and not yet released, and `proof` and
+/// `public_inputs` must point to `proof_len` and `public_inputs_len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn plonk_verify(
+    vk: *const VerifierKey,
+    proof: *const u8,
+    proof_len: usize,
+    public_inputs: *const u8,
+    public_inputs_len: usize,
+) -> i32 {
+    guard(|| {
+        let vk = vk
+            .as_ref()
+            .ok_or_else(|| PlonkError::Io("Null verifier key".to_string()))?;
+        bindings::verify(
+            vk,
+            slice(proof, proof_len)?,
+            slice(public_inputs, public_inputs_len)?,
+        )?;
+        Ok(PLONK_OK)
+    })
+}
+
+/// Releases a verifier key returned by `plonk_load_vk`.
+///
+/// # Safety
+///
+/// `vk` must be null or a key returned by `plonk_load_vk`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn plonk_vk_free(vk: *mut VerifierKey) {
+    if !vk.is_null() {
+        drop(Box::from_raw(vk));
+    }
+}
+
+/// Releases a buffer returned by the library.
+///
+/// # Safety
+///
+/// `buffer` must have been returned by the library, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn plonk_buffer_free(buffer: PlonkBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::tests::{square_circuit, TAU_POWERS_LENGTH};
+    use blstrs::Scalar;
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_ffi() {
+        let (circuit, witness, srs) = square_circuit(3);
+        let empty = || PlonkBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+        let (mut pk, mut vk, mut proof) = (empty(), empty(), empty());
+        let nine = Scalar::from(9).to_bytes_be();
+        let four = Scalar::from(4).to_bytes_be();
+        unsafe {
+            let status = plonk_setup(
+                circuit.as_ptr(),
+                circuit.len(),
+                srs.as_ptr(),
+                srs.len(),
+                TAU_POWERS_LENGTH,
+                &mut pk,
+                &mut vk,
+            );
+            assert_eq!(status, PLONK_OK);
+            let status = plonk_prove(pk.data, pk.len, witness.as_ptr(), witness.len(), &mut proof);
+            assert_eq!(status, PLONK_OK);
+
+            let key = plonk_load_vk(vk.data, vk.len);
+            assert!(!key.is_null());
+            let verify = |public_inputs: &[u8]| {
+                plonk_verify(
+                    key,
+                    proof.data,
+                    proof.len,
+                    public_inputs.as_ptr(),
+                    public_inputs.len(),
+                )
+            };
+            assert_eq!(verify(&nine), PLONK_OK);
+            assert_eq!(verify(&four), PLONK_INVALID_PROOF);
+            assert_eq!(verify(&nine[..31]), PLONK_ERROR);
+            let message = CStr::from_ptr(plonk_last_error()).to_str().unwrap();
+            assert_eq!(message, "Expected 32 bytes, got 31");
+
+            assert!(plonk_load_vk(vk.data, vk.len - 1).is_null());
+            assert!(plonk_load_vk(ptr::null(), 10).is_null());
+            plonk_vk_free(key);
+            plonk_buffer_free(pk);
+            plonk_buffer_free(vk);
+            plonk_buffer_free(proof);
+        }
+    }
+}
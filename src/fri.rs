@@ -0,0 +1,706 @@
+// Commitments based on FRI, the fast Reed-Solomon interactive oracle proof of proximity. A
+// polynomial of less than n coefficients is committed as the Merkle root of its codeword, its
+// evaluations over a coset g * D of a domain of size N = BLOWUP * n. To open polynomials p_i at
+// z, the prover shows that q(X) = sum v^i * (p_i(X) - p_i(z)) / (X - z) has degree less than n,
+// which only holds if the claimed evaluations are right. Each round folds the codeword of q
+// with a challenge beta, f'(x^2) = (f(x) + f(-x)) / 2 + beta * (f(x) - f(-x)) / (2x), halving
+// the degree and the domain, until a constant is left. The verifier then checks the folding at
+// random positions, with Merkle paths into the codewords of the p_i (from which it computes q)
+// and of every folded layer. Only hashes are used, so there is no trusted setup and the scheme
+// is plausibly post-quantum, at the cost of proofs of tens of kilobytes.
+//
+// This is the transparent mode of the PLONK prover: keys set up with `Fri` need no SRS, and the
+// proofs open every polynomial at zeta, and those of the next row at zeta * omega. The queries
+// reveal values of the codewords, so these proofs are not zero-knowledge.
+use crate::commitment::{PlonkCommitment, PolynomialCommitment};
+use crate::error::PlonkError;
+use crate::kzg10::union_points;
+use crate::polynomial::{batch_invert, EvaluationDomain, Polynomial};
+use crate::proof::PlonkProof;
+#[cfg(feature = "std")]
+use crate::prover::{self, ProofPolynomials};
+use crate::transcript::Transcript;
+use crate::verifier::{Challenges, PlonkVerifier, VerifierKey};
+use alloc::vec;
+use alloc::vec::Vec;
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use blstrs::Scalar;
+use ff::{Field, PrimeField};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Ratio between the size of the codewords and the degree bound. Every query catches a codeword
+// far from the polynomials of low degree with probability about 1 - 1 / BLOWUP.
+const BLOWUP: usize = 8;
+// Number of positions at which the folding is checked, for about 120 bits of conjectured
+// security with the blowup above.
+const QUERIES: usize = 40;
+
+type Hash = [u8; 32];
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Fri {
+    pub size: usize, // degree bound of the polynomials, a power of two
+    pub domain: EvaluationDomain,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FriCommitment(pub(crate) Hash);
+
+/// A value of a codeword at position i and at its opposite position i + N / 2, with their
+/// Merkle paths.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FriDecommitment {
+    pub(crate) values: [Scalar; 2],
+    pub(crate) paths: [Vec<Hash>; 2],
+}
+
+/// The decommitments of a query, in the codewords of the opened polynomials and in every
+/// folded layer but the last one.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FriQuery {
+    pub(crate) inputs: Vec<FriDecommitment>,
+    pub(crate) layers: Vec<FriDecommitment>,
+}
+
+/// Opening of several polynomials at the same point: the claimed evaluations, the roots of the
+/// folded layers, the constant of the last one, and the queries.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FriOpening {
+    pub evaluations: Vec<Scalar>,
+    pub(crate) roots: Vec<Hash>,
+    pub(crate) last: Scalar,
+    pub(crate) queries: Vec<FriQuery>,
+}
+
+/// Opening of several polynomials, each at its own set of points, with an opening per point of
+/// all the polynomials evaluated there.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FriBatchOpening {
+    pub evaluations: Vec<Vec<Scalar>>,
+    pub(crate) openings: Vec<FriOpening>,
+}
+
+impl Fri {
+    /// Parameters for polynomials of up to `size` coefficients, rounded up to a power of two.
+    pub fn setup(size: usize) -> Result<Self, PlonkError> {
+        let size = size.max(1).next_power_of_two();
+        Ok(Self {
+            size,
+            domain: EvaluationDomain::new(size * BLOWUP)?,
+        })
+    }
+
+    pub fn commit(&self, polynomial: &Polynomial) -> FriCommitment {
+        FriCommitment(MerkleTree::new(&self.codeword(polynomial)).root())
+    }
+
+    /// Opens all `polynomials` at `point`, which must not be in the coset of the codewords.
+    pub fn open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        point: &Scalar,
+        transcript: &mut T,
+    ) -> FriOpening {
+        let codewords = polynomials
+            .iter()
+            .map(|poly| self.codeword(poly))
+            .collect::<Vec<_>>();
+        let trees = codewords
+            .iter()
+            .map(|codeword| MerkleTree::new(codeword))
+            .collect::<Vec<_>>();
+        let evaluations = polynomials
+            .iter()
+            .map(|poly| poly.eval(point))
+            .collect::<Vec<_>>();
+        let roots = trees.iter().map(MerkleTree::root).collect::<Vec<_>>();
+        let v = Self::opening_challenge(&roots, point, &evaluations, transcript);
+
+        // Codeword of q(X) = sum v^i * (p_i(X) - y_i) / (X - z).
+        let elements = self.domain.coset_elements();
+        let mut denominators = elements.iter().map(|x| *x - point).collect::<Vec<_>>();
+        batch_invert(&mut denominators).expect("The point is in the coset of the codewords");
+        let mut codeword = vec![Scalar::zero(); elements.len()];
+        let mut v_powers = Scalar::one();
+        for (values, y) in codewords.iter().zip(evaluations.iter()) {
+            for (q, value) in codeword.iter_mut().zip(values.iter()) {
+                *q += v_powers * (*value - y);
+            }
+            v_powers *= v;
+        }
+        for (q, denominator) in codeword.iter_mut().zip(denominators.iter()) {
+            *q *= denominator;
+        }
+
+        // Folding phase, keeping the trees of all layers but the last one, which is constant.
+        let mut layers = Vec::new();
+        let mut shift = Scalar::multiplicative_generator();
+        let mut generator = self.domain.generator;
+        for round in 0..self.rounds() {
+            let beta = transcript.challenge_scalar(b"fri beta");
+            codeword = fold(&codeword, &beta, &shift, &generator);
+            shift = shift.square();
+            generator = generator.square();
+            if round + 1 < self.rounds() {
+                let tree = MerkleTree::new(&codeword);
+                transcript.append_message(b"fri layer", &tree.root());
+                layers.push((codeword.clone(), tree));
+            }
+        }
+        let last = codeword[0];
+        transcript.append_scalar(b"fri last", &last);
+
+        let queries = self
+            .query_positions(transcript)
+            .into_iter()
+            .map(|position| FriQuery {
+                inputs: codewords
+                    .iter()
+                    .zip(trees.iter())
+                    .map(|(codeword, tree)| decommit(codeword, tree, position))
+                    .collect(),
+                layers: layers
+                    .iter()
+                    .map(|(codeword, tree)| decommit(codeword, tree, position))
+                    .collect(),
+            })
+            .collect();
+
+        FriOpening {
+            evaluations,
+            roots: layers.iter().map(|(_, tree)| tree.root()).collect(),
+            last,
+            queries,
+        }
+    }
+
+    /// Checks an opening produced by `open` of the polynomials committed in `commitments`.
+    pub fn check<T: Transcript>(
+        &self,
+        commitments: &[FriCommitment],
+        point: &Scalar,
+        opening: &FriOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        let rounds = self.rounds();
+        if commitments.len() != opening.evaluations.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: commitments.len(),
+                got: opening.evaluations.len(),
+            });
+        }
+        if opening.roots.len() != rounds.saturating_sub(1) {
+            return Err(PlonkError::InvalidLength {
+                expected: rounds.saturating_sub(1),
+                got: opening.roots.len(),
+            });
+        }
+        if opening.queries.len() != QUERIES {
+            return Err(PlonkError::InvalidLength {
+                expected: QUERIES,
+                got: opening.queries.len(),
+            });
+        }
+        let roots = commitments
+            .iter()
+            .map(|commitment| commitment.0)
+            .collect::<Vec<_>>();
+        let v = Self::opening_challenge(&roots, point, &opening.evaluations, transcript);
+        let mut betas = Vec::with_capacity(rounds);
+        for round in 0..rounds {
+            betas.push(transcript.challenge_scalar(b"fri beta"));
+            if let Some(root) = opening.roots.get(round) {
+                transcript.append_message(b"fri layer", root);
+            }
+        }
+        transcript.append_scalar(b"fri last", &opening.last);
+
+        let positions = self.query_positions(transcript);
+        for (position, query) in positions.into_iter().zip(opening.queries.iter()) {
+            if query.inputs.len() != commitments.len() || query.layers.len() != opening.roots.len()
+            {
+                return Err(PlonkError::VerificationFailed);
+            }
+
+            // The values of q at the two positions of the first layer.
+            let size = self.domain.size;
+            for (root, decommitment) in roots.iter().zip(query.inputs.iter()) {
+                check_decommitment(root, decommitment, position, size)?;
+            }
+            let mut values = [Scalar::zero(); 2];
+            for (half, value) in values.iter_mut().enumerate() {
+                let index = (position + half * size / 2) % size;
+                let x = Scalar::multiplicative_generator()
+                    * self.domain.generator.pow_vartime([index as u64]);
+                let denominator = Option::<Scalar>::from((x - point).invert())
+                    .ok_or(PlonkError::VerificationFailed)?;
+                let mut v_powers = Scalar::one();
+                for (decommitment, y) in query.inputs.iter().zip(opening.evaluations.iter()) {
+                    *value += v_powers * (decommitment.values[half] - y);
+                    v_powers *= v;
+                }
+                *value *= denominator;
+            }
+
+            // Every fold must give the value that the next layer opens.
+            let mut shift = Scalar::multiplicative_generator();
+            let mut generator = self.domain.generator;
+            let mut size = self.domain.size;
+            for (round, beta) in betas.iter().enumerate() {
+                let index = position % size;
+                let x = shift * generator.pow_vartime([(index % (size / 2)) as u64]);
+                let (low, high) = if index < size / 2 {
+                    (values[0], values[1])
+                } else {
+                    (values[1], values[0])
+                };
+                let folded = fold_pair(&low, &high, beta, &x)?;
+                shift = shift.square();
+                generator = generator.square();
+                size /= 2;
+
+                let expected = match query.layers.get(round) {
+                    Some(decommitment) => {
+                        check_decommitment(&opening.roots[round], decommitment, position, size)?;
+                        values = decommitment.values;
+                        decommitment.values[0]
+                    }
+                    None => opening.last,
+                };
+                if folded != expected {
+                    return Err(PlonkError::VerificationFailed);
+                }
+            }
+            if rounds == 0 && values != [opening.last; 2] {
+                return Err(PlonkError::VerificationFailed);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Opens the i-th polynomial at the points of `points[i]`, with an opening per point of
+    /// all the polynomials evaluated there.
+    pub fn batch_open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        points: &[Vec<Scalar>],
+        transcript: &mut T,
+    ) -> FriBatchOpening {
+        assert_eq!(
+            polynomials.len(),
+            points.len(),
+            "a set of points per polynomial"
+        );
+        let openings = union_points(points)
+            .iter()
+            .map(|point| {
+                let opened = polynomials
+                    .iter()
+                    .zip(points.iter())
+                    .filter(|(_, set)| set.contains(point))
+                    .map(|(poly, _)| poly.clone())
+                    .collect::<Vec<_>>();
+                self.open(&opened, point, transcript)
+            })
+            .collect();
+
+        FriBatchOpening {
+            evaluations: polynomials
+                .iter()
+                .zip(points.iter())
+                .map(|(poly, set)| set.iter().map(|point| poly.eval(point)).collect())
+                .collect(),
+            openings,
+        }
+    }
+
+    /// Checks an opening produced by `batch_open` of the polynomials committed in
+    /// `commitments`, at the same sets of points.
+    pub fn check_batch<T: Transcript>(
+        &self,
+        commitments: &[FriCommitment],
+        points: &[Vec<Scalar>],
+        opening: &FriBatchOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        if commitments.len() != points.len() || commitments.len() != opening.evaluations.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: commitments.len(),
+                got: points.len().min(opening.evaluations.len()),
+            });
+        }
+        let all_points = union_points(points);
+        if opening.openings.len() != all_points.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: all_points.len(),
+                got: opening.openings.len(),
+            });
+        }
+
+        for (point, point_opening) in all_points.iter().zip(opening.openings.iter()) {
+            let mut opened = Vec::new();
+            let mut evaluations = Vec::new();
+            for ((commitment, set), set_evaluations) in commitments
+                .iter()
+                .zip(points.iter())
+                .zip(opening.evaluations.iter())
+            {
+                if set.len() != set_evaluations.len() {
+                    return Err(PlonkError::InvalidLength {
+                        expected: set.len(),
+                        got: set_evaluations.len(),
+                    });
+                }
+                if let Some(index) = set.iter().position(|x| x == point) {
+                    opened.push(commitment.clone());
+                    evaluations.push(set_evaluations[index]);
+                }
+            }
+            // The claimed evaluations must be the ones that the opening proves.
+            if evaluations != point_opening.evaluations {
+                return Err(PlonkError::VerificationFailed);
+            }
+            self.check(&opened, point, point_opening, transcript)?;
+        }
+
+        Ok(())
+    }
+
+    // Number of folds, after which the codeword of a polynomial of degree less than `size` is
+    // constant.
+    fn rounds(&self) -> usize {
+        self.size.trailing_zeros() as usize
+    }
+
+    fn codeword(&self, polynomial: &Polynomial) -> Vec<Scalar> {
+        assert!(
+            polynomial.0.len() <= self.size,
+            "Polynomial degree not supported"
+        );
+        self.domain.coset_fft(polynomial)
+    }
+
+    fn opening_challenge<T: Transcript>(
+        roots: &[Hash],
+        point: &Scalar,
+        evaluations: &[Scalar],
+        transcript: &mut T,
+    ) -> Scalar {
+        for (root, evaluation) in roots.iter().zip(evaluations.iter()) {
+            transcript.append_message(b"fri commitment", root);
+            transcript.append_scalar(b"fri evaluation", evaluation);
+        }
+        transcript.append_scalar(b"fri point", point);
+        transcript.challenge_scalar(b"fri v")
+    }
+
+    // Positions in the first layer of the queries. The size of the domain is a power of two, so
+    // reducing 64 random bits modulo it is uniform.
+    fn query_positions<T: Transcript>(&self, transcript: &mut T) -> Vec<usize> {
+        (0..QUERIES)
+            .map(|_| {
+                let bytes = transcript.challenge_scalar(b"fri query").to_repr();
+                let mut position = [0u8; 8];
+                position.copy_from_slice(&bytes.as_ref()[..8]);
+                (u64::from_le_bytes(position) % self.domain.size as u64) as usize
+            })
+            .collect()
+    }
+}
+
+impl PolynomialCommitment for Fri {
+    type Commitment = FriCommitment;
+    type Opening = FriOpening;
+    type BatchOpening = FriBatchOpening;
+
+    fn max_size(&self) -> usize {
+        self.size
+    }
+
+    fn commit(&self, polynomial: &Polynomial) -> FriCommitment {
+        Fri::commit(self, polynomial)
+    }
+
+    fn open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        point: &Scalar,
+        transcript: &mut T,
+    ) -> FriOpening {
+        Fri::open(self, polynomials, point, transcript)
+    }
+
+    fn evaluations(opening: &FriOpening) -> &[Scalar] {
+        &opening.evaluations
+    }
+
+    fn check<T: Transcript>(
+        &self,
+        commitments: &[FriCommitment],
+        point: &Scalar,
+        opening: &FriOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        Fri::check(self, commitments, point, opening, transcript)
+    }
+
+    fn batch_open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        points: &[Vec<Scalar>],
+        transcript: &mut T,
+    ) -> FriBatchOpening {
+        Fri::batch_open(self, polynomials, points, transcript)
+    }
+
+    fn batch_evaluations(opening: &FriBatchOpening) -> &[Vec<Scalar>] {
+        &opening.evaluations
+    }
+
+    fn check_batch<T: Transcript>(
+        &self,
+        commitments: &[FriCommitment],
+        points: &[Vec<Scalar>],
+        opening: &FriBatchOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        Fri::check_batch(self, commitments, points, opening, transcript)
+    }
+}
+
+impl PlonkCommitment for Fri {
+    type LagrangeSet = ();
+    type ProofOpening = FriBatchOpening;
+
+    fn verifier_part(&self) -> Self {
+        self.clone()
+    }
+
+    fn lagrange_set(&self, _domain: &EvaluationDomain) -> Result<(), PlonkError> {
+        Ok(())
+    }
+
+    fn append_commitment<T: Transcript>(
+        transcript: &mut T,
+        label: &'static [u8],
+        commitment: &FriCommitment,
+    ) {
+        transcript.append_message(label, &commitment.0);
+    }
+
+    fn commitment_bytes(commitment: &FriCommitment) -> Vec<u8> {
+        commitment.0.to_vec()
+    }
+
+    // The domain of the codewords is determined by the degree bound.
+    fn verifier_bytes(&self) -> Vec<u8> {
+        (self.size as u64).to_le_bytes().to_vec()
+    }
+
+    #[cfg(feature = "std")]
+    fn open_proof<T: Transcript>(
+        polynomials: &ProofPolynomials<Self>,
+        transcript: &mut T,
+    ) -> FriBatchOpening {
+        prover::open_polynomials(polynomials, transcript)
+    }
+
+    fn check_proof<T: Transcript>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey<Self>,
+        proof: &PlonkProof<Self>,
+        challenges: &Challenges,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        PlonkVerifier::check_openings(
+            pub_in,
+            verifier_key,
+            proof,
+            &proof.opening,
+            challenges,
+            transcript,
+        )
+    }
+}
+
+// Folds the codeword of f over shift * <generator> into the one of f' over the squares.
+fn fold(codeword: &[Scalar], beta: &Scalar, shift: &Scalar, generator: &Scalar) -> Vec<Scalar> {
+    let half = codeword.len() / 2;
+    let mut x = *shift;
+    let mut folded = Vec::with_capacity(half);
+    for i in 0..half {
+        folded.push(fold_pair(&codeword[i], &codeword[i + half], beta, &x).unwrap());
+        x *= generator;
+    }
+    folded
+}
+
+// Value of f' at x^2 out of f(x) and f(-x).
+fn fold_pair(
+    value: &Scalar,
+    opposite: &Scalar,
+    beta: &Scalar,
+    x: &Scalar,
+) -> Result<Scalar, PlonkError> {
+    let two_inv = Scalar::from(2).invert().unwrap();
+    let x_inv = Option::<Scalar>::from(x.invert()).ok_or(PlonkError::NonInvertible)?;
+    Ok((*value + opposite) * two_inv + *beta * (*value - opposite) * two_inv * x_inv)
+}
+
+fn decommit(codeword: &[Scalar], tree: &MerkleTree, position: usize) -> FriDecommitment {
+    let size = codeword.len();
+    let indices = [position % size, (position + size / 2) % size];
+    FriDecommitment {
+        values: indices.map(|index| codeword[index]),
+        paths: indices.map(|index| tree.path(index)),
+    }
+}
+
+fn check_decommitment(
+    root: &Hash,
+    decommitment: &FriDecommitment,
+    position: usize,
+    size: usize,
+) -> Result<(), PlonkError> {
+    let indices = [position % size, (position + size / 2) % size];
+    for ((index, value), path) in indices
+        .iter()
+        .zip(decommitment.values.iter())
+        .zip(decommitment.paths.iter())
+    {
+        if !MerkleTree::verify(root, *index, value, path) {
+            return Err(PlonkError::VerificationFailed);
+        }
+    }
+    Ok(())
+}
+
+// Binary Merkle tree with Blake2b-256, over a power of two number of scalars. `layers[0]` are
+// the hashes of the leaves and the last layer is the root.
+struct MerkleTree {
+    layers: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    fn new(leaves: &[Scalar]) -> Self {
+        let mut layers = vec![leaves.iter().map(hash_leaf).collect::<Vec<_>>()];
+        while layers[layers.len() - 1].len() > 1 {
+            let layer = layers[layers.len() - 1]
+                .chunks(2)
+                .map(|pair| hash_node(&pair[0], &pair[1]))
+                .collect();
+            layers.push(layer);
+        }
+        Self { layers }
+    }
+
+    fn root(&self) -> Hash {
+        self.layers[self.layers.len() - 1][0]
+    }
+
+    // Siblings of the nodes from the leaf at `index` to the root.
+    fn path(&self, mut index: usize) -> Vec<Hash> {
+        let mut path = Vec::with_capacity(self.layers.len() - 1);
+        for layer in self.layers[..self.layers.len() - 1].iter() {
+            path.push(layer[index ^ 1]);
+            index /= 2;
+        }
+        path
+    }
+
+    fn verify(root: &Hash, mut index: usize, leaf: &Scalar, path: &[Hash]) -> bool {
+        let mut node = hash_leaf(leaf);
+        for sibling in path {
+            node = if index & 1 == 0 {
+                hash_node(&node, sibling)
+            } else {
+                hash_node(sibling, &node)
+            };
+            index /= 2;
+        }
+        index == 0 && node == *root
+    }
+}
+
+// Leaves and nodes are hashed with a different prefix, so that a node can't pass as a leaf.
+fn hash_leaf(leaf: &Scalar) -> Hash {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update([0u8]);
+    hasher.update(leaf.to_bytes_le());
+    hasher.finalize().into()
+}
+
+fn hash_node(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::commitment::tests::check_plonk;
+    use crate::commitment::tests::check_scheme;
+    use crate::transcript::Blake2bTranscript;
+
+    #[test]
+    fn test_fri() {
+        check_scheme(&Fri::setup(8).unwrap());
+        check_scheme(&Fri::setup(1).unwrap());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_fri_plonk() {
+        check_plonk(Fri::setup(32).unwrap());
+    }
+
+    #[test]
+    fn test_fri_tampered() {
+        let fri = Fri::setup(8).unwrap();
+        let polynomial = Polynomial((1..=8).map(Scalar::from).collect());
+        let commitment = fri.commit(&polynomial);
+        let point = Scalar::from(3);
+        let mut transcript = Blake2bTranscript::new(b"fri");
+        let opening = fri.open(&[polynomial], &point, &mut transcript);
+        let mut transcript = Blake2bTranscript::new(b"fri");
+        assert!(fri
+            .check(
+                core::slice::from_ref(&commitment),
+                &point,
+                &opening,
+                &mut transcript
+            )
+            .is_ok());
+
+        let mut wrong_evaluation = opening.clone();
+        wrong_evaluation.evaluations[0] += Scalar::one();
+        let mut wrong_last = opening.clone();
+        wrong_last.last += Scalar::one();
+        let mut wrong_value = opening.clone();
+        wrong_value.queries[0].layers[0].values[0] += Scalar::one();
+        for opening in [wrong_evaluation, wrong_last, wrong_value] {
+            let mut transcript = Blake2bTranscript::new(b"fri");
+            assert_eq!(
+                fri.check(
+                    core::slice::from_ref(&commitment),
+                    &point,
+                    &opening,
+                    &mut transcript
+                ),
+                Err(PlonkError::VerificationFailed)
+            );
+        }
+    }
+}
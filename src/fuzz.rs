@@ -0,0 +1,213 @@
+// Fuzzing of the decoders and of the verifier, which see bytes from untrusted peers. Each target
+// takes arbitrary bytes and must return an error for all that are not a valid encoding, without
+// panicking or allocating according to a length it read. The inputs are mutations of valid
+// encodings (bit flips, overwritten and swapped chunks, truncations, extensions), drawn from a
+// seeded ChaCha20, so that most of them get past the length checks and reach the point and
+// scalar decoding, and some decode and reach the verifier. A failure panics with the seed and
+// the iteration that reproduce it. The tests run a few hundred inputs per target, more with
+//
+// FUZZ_ITERATIONS=100000 cargo test --release fuzz_
+use crate::accumulation::Accumulator;
+use crate::circom::{read_r1cs, read_wtns};
+use crate::kzg10::Kzg10;
+use crate::plonk::{ComputationTrace, PlonkCircuit};
+use crate::proof::PlonkProof;
+use crate::prover::Prover;
+use crate::r1cs::r1cs_to_plonk;
+use crate::transcript::{Blake2bTranscript, Transcript};
+use crate::verifier::{PlonkVerifier, VerifierKey};
+use crate::witness::WitnessBuilder;
+use blstrs::Scalar;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{RngCore, SeedableRng};
+use std::panic::{self, AssertUnwindSafe};
+
+const SEED: [u8; 32] = [9u8; 32];
+const ITERATIONS: usize = 256;
+
+fn iterations() -> usize {
+    std::env::var("FUZZ_ITERATIONS")
+        .ok()
+        .and_then(|iterations| iterations.parse().ok())
+        .unwrap_or(ITERATIONS)
+}
+
+// Range of `0..bound`, for a non zero bound.
+fn below(rng: &mut ChaCha20Rng, bound: usize) -> usize {
+    (rng.next_u64() % bound as u64) as usize
+}
+
+// Applies one to four random mutations to `bytes`. Chunks of 32 and 48 bytes are the sizes of
+// the scalars and points of G1 of the encodings.
+fn mutate(rng: &mut ChaCha20Rng, bytes: &mut Vec<u8>) {
+    for _ in 0..1 + below(rng, 4) {
+        if bytes.is_empty() {
+            bytes.push(rng.next_u32() as u8);
+            continue;
+        }
+        let len = bytes.len();
+        let position = below(rng, len);
+        match below(rng, 7) {
+            0 => bytes[position] ^= 1 << below(rng, 8),
+            1 => bytes[position] = rng.next_u32() as u8,
+            2 => bytes.truncate(position),
+            3 => {
+                let mut extra = vec![0u8; 1 + below(rng, 64)];
+                rng.fill_bytes(&mut extra);
+                bytes.extend_from_slice(&extra);
+            }
+            4 => {
+                let end = len.min(position + [32, 48][below(rng, 2)]);
+                rng.fill_bytes(&mut bytes[position..end]);
+            }
+            5 => {
+                let size = [32, 48][below(rng, 2)];
+                if len >= 2 * size {
+                    let (i, j) = (below(rng, len / size), below(rng, len / size));
+                    for k in 0..size {
+                        bytes.swap(i * size + k, j * size + k);
+                    }
+                }
+            }
+            // Lengths and counts of the encodings are little endian u64.
+            _ => {
+                let end = len.min(position + 8);
+                bytes[position..end].copy_from_slice(&u64::MAX.to_le_bytes()[..end - position]);
+            }
+        }
+    }
+}
+
+// Runs `target` on mutations of `valid`, and on `valid` itself.
+fn fuzz(valid: &[u8], mut target: impl FnMut(&[u8])) {
+    let mut rng = ChaCha20Rng::from_seed(SEED);
+    target(valid);
+    for iteration in 0..iterations() {
+        let mut bytes = valid.to_vec();
+        mutate(&mut rng, &mut bytes);
+        if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| target(&bytes))) {
+            let message = panic
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| panic.downcast_ref::<&str>().copied())
+                .unwrap_or_default();
+            panic!("seed {SEED:?}, iteration {iteration}: {message}");
+        }
+    }
+}
+
+// Proof that the public input is x * x + x, for x = 3, with its verifier key.
+fn instance() -> (VerifierKey, PlonkProof, Vec<Scalar>) {
+    let mut circuit = PlonkCircuit::init();
+    let y = circuit.public_input();
+    let (x_l, x_r, square) = circuit.mult_gate();
+    let (square_copy, x_copy, sum) = circuit.add_gate();
+    circuit.connect(x_l, x_r).unwrap();
+    circuit.connect(x_l, x_copy).unwrap();
+    circuit.connect(square, square_copy).unwrap();
+    circuit.connect(sum, y).unwrap();
+    let srs = Kzg10::setup(16, &mut ChaCha20Rng::from_seed([0u8; 32]));
+    let (prover_key, verifier_key) = circuit.setup_with_srs(srs).unwrap();
+
+    let mut builder = WitnessBuilder::new(&circuit);
+    builder.assign(x_l, Scalar::from(3)).unwrap();
+    let trace = builder.build().unwrap();
+    let pub_in = vec![Scalar::from(12)];
+    let mut transcript = Blake2bTranscript::new(b"fuzz");
+    let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut transcript).unwrap();
+    (verifier_key, proof, pub_in)
+}
+
+fn verify(pub_in: &[Scalar], verifier_key: &VerifierKey, proof: &PlonkProof) -> bool {
+    let mut transcript = Blake2bTranscript::new(b"fuzz");
+    PlonkVerifier::verify(pub_in, verifier_key, proof, &mut transcript).is_ok()
+}
+
+#[test]
+fn fuzz_proof_from_bytes() {
+    let (verifier_key, proof, pub_in) = instance();
+    let valid = proof.to_bytes();
+    fuzz(&valid, |bytes| {
+        // Decoding is canonical, and only the original bytes give a valid proof.
+        if let Ok(decoded) = PlonkProof::from_bytes(bytes) {
+            assert_eq!(decoded.to_bytes(), bytes);
+            assert_eq!(verify(&pub_in, &verifier_key, &decoded), bytes == valid);
+        }
+    });
+}
+
+#[test]
+fn fuzz_verifier_key_deserialize() {
+    let (verifier_key, proof, pub_in) = instance();
+    let mut valid = Vec::new();
+    verifier_key.serialize(&mut valid).unwrap();
+    fuzz(&valid, |bytes| {
+        if let Ok(decoded) = VerifierKey::deserialize(bytes) {
+            let mut encoded = Vec::new();
+            decoded.serialize(&mut encoded).unwrap();
+            if !bytes.starts_with(&encoded) {
+                assert!(!verify(&pub_in, &decoded, &proof));
+            }
+        }
+    });
+}
+
+#[test]
+fn fuzz_verify_public_inputs() {
+    let (verifier_key, proof, _) = instance();
+    let mut rng = ChaCha20Rng::from_seed(SEED);
+    for _ in 0..iterations().min(32) {
+        let pub_in = (0..below(&mut rng, 4))
+            .map(|_| Scalar::from(rng.next_u64()))
+            .collect::<Vec<_>>();
+        assert!(!verify(&pub_in, &verifier_key, &proof));
+    }
+}
+
+#[test]
+fn fuzz_other_decoders() {
+    fuzz(&Accumulator::new().to_bytes(), |bytes| {
+        let _ = Accumulator::from_bytes(bytes);
+    });
+
+    let mut circuit = PlonkCircuit::init();
+    let _ = circuit.public_input();
+    let (left, right, _) = circuit.mult_gate();
+    circuit.connect(left, right).unwrap();
+    let mut valid = Vec::new();
+    circuit.serialize(&mut valid).unwrap();
+    fuzz(&valid, |bytes| {
+        let _ = PlonkCircuit::deserialize(bytes);
+    });
+
+    let trace = ComputationTrace {
+        a: vec![Scalar::from(1)],
+        b: vec![Scalar::from(2)],
+        c: vec![Scalar::from(3)],
+    };
+    let mut valid = Vec::new();
+    trace.serialize(&mut valid).unwrap();
+    fuzz(&valid, |bytes| {
+        let _ = ComputationTrace::deserialize(bytes);
+    });
+}
+
+#[test]
+fn fuzz_circom_files() {
+    let (r1cs_file, wtns_file) = crate::circom::tests::example_files();
+    // The counts of a decoded constraint system are bounded by the size of the file, and so is
+    // the circuit it converts to.
+    fuzz(&r1cs_file, |bytes| {
+        if let Ok(r1cs) = read_r1cs(bytes) {
+            let mut circuit = PlonkCircuit::init();
+            if r1cs_to_plonk(&mut circuit, &r1cs).is_ok() {
+                assert!(circuit.nr_constraints <= bytes.len());
+            }
+        }
+    });
+    fuzz(&wtns_file, |bytes| {
+        if let Ok(z) = read_wtns(bytes) {
+            assert!(32 * z.len() <= bytes.len());
+        }
+    });
+}
@@ -0,0 +1,306 @@
+// Backend shared by the gadgets. A gadget is written once over `Arithmetic`, which either adds
+// gates to the circuit (with wires as values), or fills the rows of these gates in the trace
+// (with scalars as values), so that both are always laid out in the same order.
+//
+// Values are combined linearly with `Combination`, so that constants and coefficients are
+// folded into the selectors of the gates that consume them instead of requiring their own.
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::{Field, PrimeField};
+use std::slice::Iter;
+
+pub(crate) trait Arithmetic {
+    type Value: Copy;
+
+    // Output of a gate with the selectors of `gate` and q_O = -1, that is
+    // q_L * l + q_R * r + q_M * l * r + q_C.
+    fn gate(
+        &mut self,
+        gate: Gate,
+        left: Self::Value,
+        right: Self::Value,
+    ) -> Result<Self::Value, PlonkError>;
+
+    // Left wire of the next gate, for a gate with the selectors of `gate` and q_N = -1, that is
+    // q_L * l + q_R * r + q_O * o + q_M * l * r + q_C. It is only available to the gate created
+    // right after this one, which must take it as its left input.
+    fn chain(
+        &mut self,
+        gate: Gate,
+        left: Self::Value,
+        right: Self::Value,
+        output: Self::Value,
+    ) -> Result<Self::Value, PlonkError>;
+
+    fn constant(&mut self, value: Scalar) -> Result<Self::Value, PlonkError>;
+
+    // Bit of `x` at `position`, constrained to be boolean, but not related to `x`.
+    fn bit(&mut self, x: Self::Value, position: usize) -> Result<Self::Value, PlonkError>;
+
+    fn assert_equal(&mut self, x: Self::Value, y: Self::Value) -> Result<(), PlonkError>;
+
+    // Quotient q such that q * (m * y + k) = x. If m * y + k is zero, the quotient is assigned
+    // zero, and the gate is only satisfied if x is zero as well.
+    fn divide(
+        &mut self,
+        x: Self::Value,
+        y: (Self::Value, Scalar),
+        constant: Scalar,
+    ) -> Result<Self::Value, PlonkError>;
+}
+
+pub(crate) struct Builder<'a> {
+    pub(crate) circuit: &'a mut PlonkCircuit,
+    pub(crate) rows: Vec<usize>, // rows of the gates, in the order in which they were created
+}
+
+impl<'a> Builder<'a> {
+    pub(crate) fn new(circuit: &'a mut PlonkCircuit) -> Self {
+        Builder {
+            circuit,
+            rows: Vec::new(),
+        }
+    }
+
+    fn new_gate(&mut self, gate: Gate) -> (Wire, Wire, Wire) {
+        let wires = self.circuit.gate(gate);
+        self.rows.push(wires.0.row);
+        wires
+    }
+}
+
+impl Arithmetic for Builder<'_> {
+    type Value = Wire;
+
+    fn gate(&mut self, gate: Gate, left: Wire, right: Wire) -> Result<Wire, PlonkError> {
+        let (a, b, c) = self.new_gate(gate.output(-1));
+        self.circuit.connect(left, a)?;
+        self.circuit.connect(right, b)?;
+        Ok(c)
+    }
+
+    fn chain(
+        &mut self,
+        gate: Gate,
+        left: Wire,
+        right: Wire,
+        output: Wire,
+    ) -> Result<Wire, PlonkError> {
+        let (a, b, c) = self.new_gate(gate.next(-1));
+        self.circuit.connect(left, a)?;
+        self.circuit.connect(right, b)?;
+        self.circuit.connect(output, c)?;
+        Ok(Wire::left(a.row + 1))
+    }
+
+    // The constant is the output of its gate, so that `WitnessBuilder` derives it.
+    fn constant(&mut self, value: Scalar) -> Result<Wire, PlonkError> {
+        let (_, _, c) = self.new_gate(Gate::new().constant(value).output(-1));
+        Ok(c)
+    }
+
+    fn bit(&mut self, _: Wire, _: usize) -> Result<Wire, PlonkError> {
+        let (a, b, _) = self.new_gate(Gate::new().mul(1).left(-1));
+        self.circuit.connect(a, b)?;
+        Ok(a)
+    }
+
+    fn assert_equal(&mut self, x: Wire, y: Wire) -> Result<(), PlonkError> {
+        self.circuit.connect(x, y)
+    }
+
+    fn divide(&mut self, x: Wire, y: (Wire, Scalar), constant: Scalar) -> Result<Wire, PlonkError> {
+        let (a, b, c) = self.new_gate(Gate::new().mul(y.1).left(constant).output(-1));
+        self.circuit.connect(y.0, b)?;
+        self.circuit.connect(x, c)?;
+        Ok(a)
+    }
+}
+
+pub(crate) struct Assigner<'a> {
+    trace: &'a mut ComputationTrace,
+    rows: Iter<'a, usize>,
+}
+
+impl<'a> Assigner<'a> {
+    pub(crate) fn new(trace: &'a mut ComputationTrace, rows: &'a [usize]) -> Self {
+        Assigner {
+            trace,
+            rows: rows.iter(),
+        }
+    }
+
+    fn assign(&mut self, a: Scalar, b: Scalar, c: Scalar) {
+        let row = *self.rows.next().expect("The gadget has no rows left");
+        self.trace.set(Wire::left(row), a);
+        self.trace.set(Wire::right(row), b);
+        self.trace.set(Wire::output(row), c);
+    }
+}
+
+impl Arithmetic for Assigner<'_> {
+    type Value = Scalar;
+
+    fn gate(&mut self, gate: Gate, left: Scalar, right: Scalar) -> Result<Scalar, PlonkError> {
+        let c = gate.ql * left + gate.qr * right + gate.qm * left * right + gate.qc;
+        self.assign(left, right, c);
+        Ok(c)
+    }
+
+    fn chain(
+        &mut self,
+        gate: Gate,
+        left: Scalar,
+        right: Scalar,
+        output: Scalar,
+    ) -> Result<Scalar, PlonkError> {
+        self.assign(left, right, output);
+        Ok(gate.ql * left + gate.qr * right + gate.qo * output + gate.qm * left * right + gate.qc)
+    }
+
+    fn constant(&mut self, value: Scalar) -> Result<Scalar, PlonkError> {
+        self.assign(Scalar::zero(), Scalar::zero(), value);
+        Ok(value)
+    }
+
+    fn bit(&mut self, x: Scalar, position: usize) -> Result<Scalar, PlonkError> {
+        let bytes = x.to_bytes_le();
+        let bit = Scalar::from(((bytes[position / 8] >> (position % 8)) & 1) as u64);
+        self.assign(bit, bit, Scalar::zero());
+        Ok(bit)
+    }
+
+    // Values that are not equal make the trace fail the copy constraint.
+    fn assert_equal(&mut self, _: Scalar, _: Scalar) -> Result<(), PlonkError> {
+        Ok(())
+    }
+
+    fn divide(
+        &mut self,
+        x: Scalar,
+        y: (Scalar, Scalar),
+        constant: Scalar,
+    ) -> Result<Scalar, PlonkError> {
+        let denominator = y.0 * y.1 + constant;
+        let quotient = x * denominator.invert().unwrap_or(Scalar::zero());
+        self.assign(quotient, y.0, x);
+        Ok(quotient)
+    }
+}
+
+// Sum of m * x over its terms, plus a constant.
+#[derive(Clone)]
+pub(crate) struct Combination<V> {
+    pub(crate) terms: Vec<(V, Scalar)>,
+    pub(crate) constant: Scalar,
+}
+
+impl<V: Copy> Combination<V> {
+    pub(crate) fn constant(value: Scalar) -> Self {
+        Combination {
+            terms: Vec::new(),
+            constant: value,
+        }
+    }
+
+    pub(crate) fn value(x: V) -> Self {
+        Combination {
+            terms: vec![(x, Scalar::one())],
+            constant: Scalar::zero(),
+        }
+    }
+
+    // Adds `other` multiplied by `factor`.
+    pub(crate) fn add(&mut self, other: &Combination<V>, factor: Scalar) {
+        self.terms.extend(
+            other
+                .terms
+                .iter()
+                .map(|(x, coefficient)| (*x, factor * coefficient)),
+        );
+        self.constant += factor * other.constant;
+    }
+
+    // Adds up the terms until there is at most one left, using a gate per extra term.
+    pub(crate) fn reduce<A: Arithmetic<Value = V>>(
+        &mut self,
+        ops: &mut A,
+    ) -> Result<(), PlonkError> {
+        while self.terms.len() > 1 {
+            let right = self.terms.pop().expect("There are at least two terms");
+            let left = self.terms.pop().expect("There are at least two terms");
+            let sum = ops.gate(Gate::new().left(left.1).right(right.1), left.0, right.0)?;
+            self.terms.push((sum, Scalar::one()));
+        }
+        Ok(())
+    }
+
+    // Single value equal to the combination.
+    pub(crate) fn evaluate<A: Arithmetic<Value = V>>(
+        mut self,
+        ops: &mut A,
+    ) -> Result<V, PlonkError> {
+        self.reduce(ops)?;
+        match self.terms.pop() {
+            None => ops.constant(self.constant),
+            Some((x, m)) if m == Scalar::one() && self.constant.is_zero_vartime() => Ok(x),
+            Some((x, m)) => ops.gate(Gate::new().left(m).constant(self.constant), x, x),
+        }
+    }
+}
+
+// Little-endian bits of `x`, which is constrained to be smaller than 2^num_bits. The bits are
+// recomposed with a linear combination, so their order is free, unlike those of
+// `PlonkCircuit::range_check`, whose running sum starts from the most significant bit, which it
+// returns first.
+pub(crate) fn decompose<A: Arithmetic>(
+    ops: &mut A,
+    x: A::Value,
+    num_bits: usize,
+) -> Result<Vec<A::Value>, PlonkError> {
+    let bits = (0..num_bits)
+        .map(|position| ops.bit(x, position))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut recomposition = Combination::constant(Scalar::zero());
+    let mut power = Scalar::one();
+    for bit in bits.iter() {
+        recomposition.terms.push((*bit, power));
+        power = power.double();
+    }
+    let recomposition = recomposition.evaluate(ops)?;
+    ops.assert_equal(recomposition, x)?;
+
+    Ok(bits)
+}
+
+// Little-endian bits of `x`, with all of Scalar::NUM_BITS bits, constrained to be the canonical
+// decomposition of `x`: as 2^255 > p, the recomposition could also match x + p, so the bits
+// are compared with those of p - 1 from the most significant one. `run` is one while the bits
+// match those of p - 1 so far, and a one bit where p - 1 has a zero must come after a
+// mismatch, i.e. bit * run = 0.
+pub(crate) fn decompose_canonical<A: Arithmetic>(
+    ops: &mut A,
+    x: A::Value,
+) -> Result<Vec<A::Value>, PlonkError> {
+    let bits = decompose(ops, x, Scalar::NUM_BITS as usize)?;
+    let max = (-Scalar::one()).to_bytes_le();
+    let zero = ops.constant(Scalar::zero())?;
+
+    let mut run = None;
+    for (position, bit) in bits.iter().enumerate().rev() {
+        let max_bit = (max[position / 8] >> (position % 8)) & 1 == 1;
+        match (max_bit, run) {
+            (true, None) => run = Some(*bit),
+            (true, Some(previous)) => run = Some(ops.gate(Gate::new().mul(1), previous, *bit)?),
+            (false, None) => ops.assert_equal(*bit, zero)?,
+            (false, Some(previous)) => {
+                let product = ops.gate(Gate::new().mul(1), previous, *bit)?;
+                ops.assert_equal(product, zero)?;
+            }
+        }
+    }
+
+    Ok(bits)
+}
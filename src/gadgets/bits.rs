@@ -0,0 +1,261 @@
+// Bit and byte decompositions of a wire, little-endian, written over the `Arithmetic` backend.
+// The bits are boolean-constrained and recomposed into the input. Up to 254 bits the
+// recomposition is smaller than the modulus, so the decomposition is unique. With all 255 bits
+// it could also be the one of x + p, so it is then constrained to be canonical, that is
+// smaller than p, at the cost of a gate per bit. Bytes recompose groups of eight bits, the
+// last byte of a full-width decomposition having seven.
+use super::arithmetic::{
+    decompose, decompose_canonical, Arithmetic, Assigner, Builder, Combination,
+};
+use super::Gadget;
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::{Field, PrimeField};
+
+/// Wires created by `to_bits_le` or `to_bytes_le`, used to fill their rows in the trace.
+#[derive(Clone, Debug)]
+pub struct Decomposition {
+    outputs: Vec<Wire>,
+    num_bits: usize,
+    bytes: bool,
+    rows: Vec<usize>, // rows of the gates, in the order in which they were created
+}
+
+impl Decomposition {
+    /// Wires of the bits, or of the bytes, least significant first.
+    pub fn outputs(&self) -> &[Wire] {
+        &self.outputs
+    }
+
+    /// Assigns the rows of the gadget for the input `value`, and returns the values of the
+    /// outputs. The trace must already contain the input. If `value` does not fit, the rows
+    /// are still assigned, but the trace does not satisfy the circuit.
+    pub fn assign(&self, trace: &mut ComputationTrace, value: &Scalar) -> Vec<u8> {
+        let mut assigner = Assigner::new(trace, &self.rows);
+        decomposition(&mut assigner, *value, self.num_bits, self.bytes)
+            .expect("Assigning the trace cannot fail")
+            .iter()
+            .map(|output| output.to_bytes_le()[0])
+            .collect()
+    }
+}
+
+/// Constrains the outputs of the returned gadget to be the `num_bits` least significant bits
+/// of `wire`, which is constrained to be smaller than 2^num_bits. With 255 bits, the full width
+/// of the field, the bits are the canonical ones.
+pub fn to_bits_le(
+    circuit: &mut PlonkCircuit,
+    wire: Wire,
+    num_bits: usize,
+) -> Result<Decomposition, PlonkError> {
+    gadget(circuit, wire, num_bits, false)
+}
+
+/// Constrains the outputs of the returned gadget to be the `num_bytes` least significant bytes
+/// of `wire`, which is constrained to be smaller than 2^(8 * num_bytes). With 32 bytes, the
+/// decomposition is the canonical one.
+pub fn to_bytes_le(
+    circuit: &mut PlonkCircuit,
+    wire: Wire,
+    num_bytes: usize,
+) -> Result<Decomposition, PlonkError> {
+    if num_bytes > 32 {
+        return Err(PlonkError::RangeTooLarge {
+            num_bits: 8 * num_bytes,
+            max: 256,
+        });
+    }
+    gadget(
+        circuit,
+        wire,
+        (8 * num_bytes).min(Scalar::NUM_BITS as usize),
+        true,
+    )
+}
+
+/// Little-endian bits of every input, as a `Gadget` with `num_bits` outputs per input, which
+/// are constrained as by `to_bits_le`. The bits are a hint, assigned by the prover as those of
+/// `Range`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Bits {
+    pub num_bits: usize,
+}
+
+impl Gadget for Bits {
+    fn synthesize(
+        &self,
+        circuit: &mut PlonkCircuit,
+        inputs: &[Wire],
+    ) -> Result<Vec<Wire>, PlonkError> {
+        let mut bits = Vec::with_capacity(inputs.len() * self.num_bits);
+        for input in inputs {
+            bits.extend_from_slice(to_bits_le(circuit, *input, self.num_bits)?.outputs());
+        }
+        Ok(bits)
+    }
+}
+
+fn gadget(
+    circuit: &mut PlonkCircuit,
+    wire: Wire,
+    num_bits: usize,
+    bytes: bool,
+) -> Result<Decomposition, PlonkError> {
+    let max = Scalar::NUM_BITS as usize;
+    if num_bits > max {
+        return Err(PlonkError::RangeTooLarge { num_bits, max });
+    }
+    let mut builder = Builder::new(circuit);
+    let outputs = decomposition(&mut builder, wire, num_bits, bytes)?;
+
+    Ok(Decomposition {
+        outputs,
+        num_bits,
+        bytes,
+        rows: builder.rows,
+    })
+}
+
+fn decomposition<A: Arithmetic>(
+    ops: &mut A,
+    x: A::Value,
+    num_bits: usize,
+    bytes: bool,
+) -> Result<Vec<A::Value>, PlonkError> {
+    let bits = if num_bits == Scalar::NUM_BITS as usize {
+        decompose_canonical(ops, x)?
+    } else {
+        decompose(ops, x, num_bits)?
+    };
+    if !bytes {
+        return Ok(bits);
+    }
+
+    bits.chunks(8)
+        .map(|byte| {
+            let mut value = Combination::constant(Scalar::zero());
+            let mut power = Scalar::one();
+            for bit in byte.iter() {
+                value.terms.push((*bit, power));
+                power = power.double();
+            }
+            value.evaluate(ops)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::WitnessBuilder;
+
+    // Trace of `gadget` for the input `value`, and whether it satisfies the circuit.
+    fn check(
+        gadget: impl Fn(&mut PlonkCircuit, Wire) -> Result<Decomposition, PlonkError>,
+        value: Scalar,
+    ) -> (Vec<u8>, bool) {
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.add_gate();
+        let decomposition = gadget(&mut circuit, x).unwrap();
+        let mut trace = ComputationTrace::new(&circuit);
+        trace.set(x, value);
+        trace.set(Wire::output(x.row), value);
+        let outputs = decomposition.assign(&mut trace, &value);
+        for (wire, output) in decomposition.outputs().iter().zip(outputs.iter()) {
+            assert_eq!(trace.value(*wire), Scalar::from(*output as u64));
+        }
+        (outputs, circuit.is_satisfied(&trace).is_ok())
+    }
+
+    #[test]
+    fn test_decomposition() {
+        let bits = |circuit: &mut PlonkCircuit, x| to_bits_le(circuit, x, 4);
+        assert_eq!(check(bits, Scalar::from(11)), (vec![1, 1, 0, 1], true));
+        assert!(!check(bits, Scalar::from(16)).1);
+        let bytes = |circuit: &mut PlonkCircuit, x| to_bytes_le(circuit, x, 2);
+        assert_eq!(check(bytes, Scalar::from(0x1234)), (vec![0x34, 0x12], true));
+        assert!(!check(bytes, Scalar::from(0x10000)).1);
+
+        // Full width decompositions of the largest scalar.
+        let max = -Scalar::one();
+        let full = |circuit: &mut PlonkCircuit, x| to_bytes_le(circuit, x, 32);
+        let (outputs, satisfied) = check(full, max);
+        assert_eq!(outputs, max.to_bytes_le()[..32]);
+        assert!(satisfied);
+        let full = |circuit: &mut PlonkCircuit, x| to_bits_le(circuit, x, 255);
+        assert!(check(full, max).1);
+
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.add_gate();
+        assert_eq!(
+            to_bits_le(&mut circuit, x, 256).err(),
+            Some(PlonkError::RangeTooLarge {
+                num_bits: 256,
+                max: 255
+            })
+        );
+    }
+
+    #[test]
+    fn test_bits_gadget() {
+        let mut circuit = PlonkCircuit::init();
+        let (x, y, _) = circuit.add_gate();
+        let bits = Bits { num_bits: 3 }
+            .synthesize(&mut circuit, &[x, y])
+            .unwrap();
+        assert_eq!(bits.len(), 6);
+
+        let trace = |values: [u64; 2], decompositions: [u64; 2]| {
+            let mut builder = WitnessBuilder::new(&circuit);
+            builder.assign(x, Scalar::from(values[0]))?;
+            builder.assign(y, Scalar::from(values[1]))?;
+            for (i, bit) in bits.iter().enumerate() {
+                let decomposition = decompositions[i / 3];
+                builder.assign(*bit, Scalar::from((decomposition >> (i % 3)) & 1))?;
+            }
+            builder.build()
+        };
+        assert!(circuit
+            .is_satisfied(&trace([6, 1], [6, 1]).unwrap())
+            .is_ok());
+        assert!(trace([6, 1], [6, 3]).is_err());
+    }
+
+    // The bits of p + 1 recompose to 1, but are not canonical.
+    #[test]
+    fn test_non_canonical_bits() {
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.add_gate();
+        let decomposition = to_bits_le(&mut circuit, x, 255).unwrap();
+        // The bits are a hint, and the other wires are derived from them, if they can be.
+        let valid = |bits: &[u8]| {
+            let mut builder = WitnessBuilder::new(&circuit);
+            builder.assign(x, Scalar::one()).unwrap();
+            for (wire, bit) in decomposition.outputs().iter().zip(bits.iter()) {
+                builder.assign(*wire, Scalar::from(*bit as u64)).unwrap();
+            }
+            builder
+                .build()
+                .is_ok_and(|trace| circuit.is_satisfied(&trace).is_ok())
+        };
+        let mut bits = vec![0u8; 255];
+        bits[0] = 1;
+        assert!(valid(&bits));
+
+        // p + 1 = (p - 1) + 2, that is the bits of p - 1 plus one at position 1.
+        let mut bits = (-Scalar::one())
+            .to_bytes_le()
+            .iter()
+            .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1))
+            .take(255)
+            .collect::<Vec<_>>();
+        let mut position = 1;
+        while bits[position] == 1 {
+            bits[position] = 0;
+            position += 1;
+        }
+        bits[position] = 1;
+        assert!(!valid(&bits));
+    }
+}
@@ -0,0 +1,113 @@
+// Boolean gadgets. `Boolean::Assert` constrains each input to be a bit with the gate
+// x * x - x = 0, and the other operations assume that their inputs are bits, with a single gate
+// each: NOT x = 1 - x, x AND y = x * y, x OR y = x + y - x * y, x XOR y = x + y - 2 * x * y and
+// x NAND y = 1 - x * y.
+use super::Gadget;
+use crate::error::PlonkError;
+use crate::plonk::{Gate, PlonkCircuit, Wire};
+
+/// Operation on bits, as a `Gadget`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Boolean {
+    /// Constrains every input to be a bit, and returns them.
+    Assert,
+    /// Negation of every input.
+    Not,
+    And,
+    Or,
+    Xor,
+    Nand,
+}
+
+impl Gadget for Boolean {
+    fn synthesize(
+        &self,
+        circuit: &mut PlonkCircuit,
+        inputs: &[Wire],
+    ) -> Result<Vec<Wire>, PlonkError> {
+        let gate = match self {
+            Boolean::Assert => {
+                for input in inputs {
+                    let (a, b, _) = circuit.gate(Gate::new().mul(1).left(-1));
+                    circuit.connect(*input, a)?;
+                    circuit.connect(*input, b)?;
+                }
+                return Ok(inputs.to_vec());
+            }
+            Boolean::Not => {
+                return inputs
+                    .iter()
+                    .map(|input| {
+                        let (a, _, c) = circuit.gate(Gate::new().left(-1).constant(1).output(-1));
+                        circuit.connect(*input, a)?;
+                        Ok(c)
+                    })
+                    .collect();
+            }
+            Boolean::And => Gate::new().mul(1),
+            Boolean::Or => Gate::new().left(1).right(1).mul(-1),
+            Boolean::Xor => Gate::new().left(1).right(1).mul(-2),
+            Boolean::Nand => Gate::new().mul(-1).constant(1),
+        };
+        let [x, y] = inputs else {
+            return Err(PlonkError::InvalidLength {
+                expected: 2,
+                got: inputs.len(),
+            });
+        };
+        let (a, b, c) = circuit.gate(gate.output(-1));
+        circuit.connect(*x, a)?;
+        circuit.connect(*y, b)?;
+        Ok(vec![c])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::WitnessBuilder;
+    use blstrs::Scalar;
+
+    #[test]
+    fn test_boolean() {
+        for (gadget, expected) in [
+            (Boolean::And, [0, 0, 0, 1]),
+            (Boolean::Or, [0, 1, 1, 1]),
+            (Boolean::Xor, [0, 1, 1, 0]),
+            (Boolean::Nand, [1, 1, 1, 0]),
+        ] {
+            let mut circuit = PlonkCircuit::init();
+            let (x, _, _) = circuit.gate(Gate::new());
+            let (y, _, _) = circuit.gate(Gate::new());
+            let inputs = Boolean::Assert.synthesize(&mut circuit, &[x, y]).unwrap();
+            let output = gadget.synthesize(&mut circuit, &inputs).unwrap()[0];
+            let not = Boolean::Not.synthesize(&mut circuit, &[output]).unwrap()[0];
+            for (i, expected) in expected.iter().enumerate() {
+                let mut builder = WitnessBuilder::new(&circuit);
+                builder.assign(x, Scalar::from(i as u64 / 2)).unwrap();
+                builder.assign(y, Scalar::from(i as u64 % 2)).unwrap();
+                let trace = builder.build().unwrap();
+                assert_eq!(trace.value(output), Scalar::from(*expected));
+                assert_eq!(trace.value(not), Scalar::from(1 - *expected));
+                assert!(circuit.is_satisfied(&trace).is_ok());
+            }
+
+            let mut builder = WitnessBuilder::new(&circuit);
+            builder.assign(x, Scalar::from(2)).unwrap();
+            assert!(matches!(
+                builder.build(),
+                Err(PlonkError::GateFailed { .. })
+            ));
+        }
+
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.gate(Gate::new());
+        assert_eq!(
+            Boolean::And.synthesize(&mut circuit, &[x]),
+            Err(PlonkError::InvalidLength {
+                expected: 2,
+                got: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// Conditional selection, out = cond * a + (1 - cond) * b, written over the `Arithmetic` backend.
+// The condition is constrained to be boolean with a single gate, shared by every value that
+// is selected with it, and each value uses three gates: t = a - b, p = cond * t, and p + b.
+use super::arithmetic::{Arithmetic, Assigner, Builder};
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use blstrs::Scalar;
+
+/// Builder of `if cond { then } else { otherwise }` over several pairs of values, e.g.
+/// `If::new(cond).then_else(x1, y1).then_else(x2, y2).build(&mut circuit)`.
+#[derive(Clone, Debug)]
+pub struct If {
+    cond: Wire,
+    branches: Vec<(Wire, Wire)>,
+}
+
+impl If {
+    pub fn new(cond: Wire) -> Self {
+        If {
+            cond,
+            branches: Vec::new(),
+        }
+    }
+
+    /// Adds an output, which is `then` if the condition is one, and `otherwise` if it is zero.
+    pub fn then_else(mut self, then: Wire, otherwise: Wire) -> Self {
+        self.branches.push((then, otherwise));
+        self
+    }
+
+    /// Adds the gates of the conditional to `circuit`.
+    pub fn build(self, circuit: &mut PlonkCircuit) -> Result<Conditional, PlonkError> {
+        let mut builder = Builder::new(circuit);
+        let outputs = conditional(&mut builder, self.cond, &self.branches)?;
+        Ok(Conditional {
+            outputs,
+            rows: builder.rows,
+        })
+    }
+}
+
+/// Wires created by `If::build` or `select`, used to fill their rows in the trace.
+#[derive(Clone, Debug)]
+pub struct Conditional {
+    outputs: Vec<Wire>,
+    rows: Vec<usize>,
+}
+
+impl Conditional {
+    /// Wires that carry the selected values, in the order of the branches.
+    pub fn outputs(&self) -> &[Wire] {
+        &self.outputs
+    }
+
+    /// Assigns the rows of the gadget for the condition `cond` and the values of the branches,
+    /// and returns the selected values.
+    pub fn assign(
+        &self,
+        trace: &mut ComputationTrace,
+        cond: &Scalar,
+        branches: &[(Scalar, Scalar)],
+    ) -> Vec<Scalar> {
+        assert_eq!(
+            branches.len(),
+            self.outputs.len(),
+            "Wrong number of branches"
+        );
+        let mut assigner = Assigner::new(trace, &self.rows);
+        conditional(&mut assigner, *cond, branches).expect("Assigning the trace cannot fail")
+    }
+}
+
+/// Constrains `cond` to be boolean, and the output of the returned gadget to be `a` if `cond`
+/// is one, and `b` if it is zero.
+pub fn select(
+    circuit: &mut PlonkCircuit,
+    cond: Wire,
+    a: Wire,
+    b: Wire,
+) -> Result<Conditional, PlonkError> {
+    If::new(cond).then_else(a, b).build(circuit)
+}
+
+fn conditional<A: Arithmetic>(
+    ops: &mut A,
+    cond: A::Value,
+    branches: &[(A::Value, A::Value)],
+) -> Result<Vec<A::Value>, PlonkError> {
+    let bit = ops.bit(cond, 0)?;
+    ops.assert_equal(bit, cond)?;
+
+    branches
+        .iter()
+        .map(|(then, otherwise)| {
+            let difference = ops.gate(Gate::new().left(1).right(-1), *then, *otherwise)?;
+            let product = ops.gate(Gate::new().mul(1), cond, difference)?;
+            ops.gate(Gate::new().left(1).right(1), product, *otherwise)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+
+    #[test]
+    fn test_conditional() {
+        let mut circuit = PlonkCircuit::init();
+        let (cond, _, _) = circuit.add_gate();
+        let (x1, y1, _) = circuit.add_gate();
+        let (x2, y2, _) = circuit.add_gate();
+        let conditional = If::new(cond)
+            .then_else(x1, y1)
+            .then_else(x2, y2)
+            .build(&mut circuit)
+            .unwrap();
+
+        let branches = [
+            (Scalar::from(3), Scalar::from(4)),
+            (Scalar::from(5), Scalar::from(6)),
+        ];
+        let assign = |cond: Scalar| {
+            let mut trace = ComputationTrace::new(&circuit);
+            let inputs = [(cond, Scalar::zero()), branches[0], branches[1]];
+            for (row, (left, right)) in inputs.into_iter().enumerate() {
+                trace.set(Wire::left(row), left);
+                trace.set(Wire::right(row), right);
+                trace.set(Wire::output(row), left + right);
+            }
+            let outputs = conditional.assign(&mut trace, &cond, &branches);
+            (trace, outputs)
+        };
+
+        let (trace, outputs) = assign(Scalar::one());
+        assert!(circuit.is_satisfied(&trace).is_ok());
+        assert_eq!(outputs, vec![Scalar::from(3), Scalar::from(5)]);
+
+        let (trace, outputs) = assign(Scalar::zero());
+        assert!(circuit.is_satisfied(&trace).is_ok());
+        assert_eq!(outputs, vec![Scalar::from(4), Scalar::from(6)]);
+        for (wire, value) in conditional.outputs().iter().zip(outputs) {
+            assert_eq!(trace.c[wire.row], value);
+        }
+
+        // With cond = 2 the output would be 2 * a - b, but the condition is not boolean.
+        let (trace, _) = assign(Scalar::from(2));
+        assert!(circuit.is_satisfied(&trace).is_err());
+    }
+}
@@ -0,0 +1,423 @@
+// Arithmetic of Jubjub in the circuit, written over the `Arithmetic` backend. Points are given
+// by the wires of their affine coordinates. The addition follows the complete formula
+//
+// (u1, v1) + (u2, v2) = ((u1 v2 + v1 u2) / (1 + d u1 u2 v1 v2), (v1 v2 + u1 u2) / (1 - d u1 u2 v1 v2)),
+//
+// with five products, two sums, and each coordinate proven with a single gate as the quotient
+// by the denominator, for a total of nine gates. As the formula is complete, doubling is an
+// addition of a point with itself.
+//
+// The variable-base scalar multiplication is double and add, starting from the most
+// significant bit, where the point that is added is selected by the bit with two gates,
+// (b * u, b * (v - 1) + 1), which is the identity (0, 1) if the bit is zero.
+//
+// The fixed-base scalar multiplication uses windows of three bits. As the base is known, the
+// multiples j * 8^i * B for j < 8 of each window i are precomputed, and the coordinates of the
+// multiple selected by the bits (b0, b1, b2) of the window are evaluated as the multilinear
+// polynomial in the bits that interpolates the table. This uses four gates for the products
+// of the bits, and linear combinations for the coordinates, so that the product only needs
+// an addition per window instead of two additions per bit.
+use super::arithmetic::{decompose, Arithmetic, Assigner, Builder, Combination};
+use crate::error::PlonkError;
+use crate::jubjub::{edwards_d, JubjubPoint};
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::Field;
+
+/// Point of Jubjub in the circuit, by the values of its coordinates (usually wires).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Point<V> {
+    pub u: V,
+    pub v: V,
+}
+
+impl From<JubjubPoint> for Point<Scalar> {
+    fn from(point: JubjubPoint) -> Self {
+        Point {
+            u: point.u,
+            v: point.v,
+        }
+    }
+}
+
+impl From<Point<Scalar>> for JubjubPoint {
+    fn from(point: Point<Scalar>) -> Self {
+        JubjubPoint {
+            u: point.u,
+            v: point.v,
+        }
+    }
+}
+
+/// Wires created by `point_addition`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct PointAddition {
+    output: Point<Wire>,
+    rows: Vec<usize>,
+}
+
+impl PointAddition {
+    /// Wires that carry the sum.
+    pub fn output(&self) -> Point<Wire> {
+        self.output
+    }
+
+    /// Assigns the rows of the gadget for the sum of `p` and `q`, which is returned.
+    pub fn assign(
+        &self,
+        trace: &mut ComputationTrace,
+        p: &JubjubPoint,
+        q: &JubjubPoint,
+    ) -> JubjubPoint {
+        let mut assigner = Assigner::new(trace, &self.rows);
+        add(&mut assigner, (*p).into(), (*q).into())
+            .expect("Assigning the trace cannot fail")
+            .into()
+    }
+}
+
+/// Constrains the output of the returned gadget to be the sum of `p` and `q`, which are
+/// expected to be points of the curve.
+pub fn point_addition(
+    circuit: &mut PlonkCircuit,
+    p: Point<Wire>,
+    q: Point<Wire>,
+) -> Result<PointAddition, PlonkError> {
+    let mut builder = Builder::new(circuit);
+    let output = add(&mut builder, p, q)?;
+    Ok(PointAddition {
+        output,
+        rows: builder.rows,
+    })
+}
+
+/// Wires created by `scalar_multiplication`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct ScalarMultiplication {
+    output: Point<Wire>,
+    num_bits: usize,
+    rows: Vec<usize>,
+}
+
+impl ScalarMultiplication {
+    /// Wires that carry the product.
+    pub fn output(&self) -> Point<Wire> {
+        self.output
+    }
+
+    /// Assigns the rows of the gadget for the product of `point` by `scalar`, which is
+    /// returned.
+    pub fn assign(
+        &self,
+        trace: &mut ComputationTrace,
+        scalar: &Scalar,
+        point: &JubjubPoint,
+    ) -> JubjubPoint {
+        let mut assigner = Assigner::new(trace, &self.rows);
+        let bits = decompose(&mut assigner, *scalar, self.num_bits)
+            .expect("Assigning the trace cannot fail");
+        mul(&mut assigner, &bits, (*point).into())
+            .expect("Assigning the trace cannot fail")
+            .into()
+    }
+}
+
+/// Constrains `scalar` to be smaller than 2^num_bits, and the output of the returned gadget to
+/// be the product of `point` by `scalar`. This uses 22 gates per bit of the scalar.
+pub fn scalar_multiplication(
+    circuit: &mut PlonkCircuit,
+    scalar: Wire,
+    point: Point<Wire>,
+    num_bits: usize,
+) -> Result<ScalarMultiplication, PlonkError> {
+    let mut builder = Builder::new(circuit);
+    let bits = decompose(&mut builder, scalar, num_bits)?;
+    let output = mul(&mut builder, &bits, point)?;
+    Ok(ScalarMultiplication {
+        output,
+        num_bits,
+        rows: builder.rows,
+    })
+}
+
+/// Wires created by `fixed_base_multiplication`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct FixedBaseMultiplication {
+    output: Point<Wire>,
+    base: JubjubPoint,
+    num_bits: usize,
+    rows: Vec<usize>,
+}
+
+impl FixedBaseMultiplication {
+    /// Wires that carry the product.
+    pub fn output(&self) -> Point<Wire> {
+        self.output
+    }
+
+    /// Assigns the rows of the gadget for the product of the base by `scalar`, which is
+    /// returned.
+    pub fn assign(&self, trace: &mut ComputationTrace, scalar: &Scalar) -> JubjubPoint {
+        let mut assigner = Assigner::new(trace, &self.rows);
+        let bits = decompose(&mut assigner, *scalar, self.num_bits)
+            .expect("Assigning the trace cannot fail");
+        fixed_base_mul(&mut assigner, &bits, &self.base)
+            .expect("Assigning the trace cannot fail")
+            .into()
+    }
+}
+
+/// Constrains `scalar` to be smaller than 2^num_bits, and the output of the returned gadget to
+/// be the product of the constant point `base` by `scalar`. This uses around 11 gates per bit
+/// of the scalar.
+pub fn fixed_base_multiplication(
+    circuit: &mut PlonkCircuit,
+    scalar: Wire,
+    base: &JubjubPoint,
+    num_bits: usize,
+) -> Result<FixedBaseMultiplication, PlonkError> {
+    let mut builder = Builder::new(circuit);
+    let bits = decompose(&mut builder, scalar, num_bits)?;
+    let output = fixed_base_mul(&mut builder, &bits, base)?;
+    Ok(FixedBaseMultiplication {
+        output,
+        base: *base,
+        num_bits,
+        rows: builder.rows,
+    })
+}
+
+pub(crate) fn add<A: Arithmetic>(
+    ops: &mut A,
+    p: Point<A::Value>,
+    q: Point<A::Value>,
+) -> Result<Point<A::Value>, PlonkError> {
+    let product = Gate::new().mul(1);
+    let sum = Gate::new().left(1).right(1);
+    let u1v2 = ops.gate(product, p.u, q.v)?;
+    let v1u2 = ops.gate(product, p.v, q.u)?;
+    let u1u2 = ops.gate(product, p.u, q.u)?;
+    let v1v2 = ops.gate(product, p.v, q.v)?;
+    let e = ops.gate(Gate::new().mul(edwards_d()), u1u2, v1v2)?;
+
+    let u_numerator = ops.gate(sum, u1v2, v1u2)?;
+    let v_numerator = ops.gate(sum, v1v2, u1u2)?;
+    Ok(Point {
+        u: ops.divide(u_numerator, (e, Scalar::one()), Scalar::one())?,
+        v: ops.divide(v_numerator, (e, -Scalar::one()), Scalar::one())?,
+    })
+}
+
+// The point if the bit is one, and the identity otherwise.
+fn select<A: Arithmetic>(
+    ops: &mut A,
+    bit: A::Value,
+    point: Point<A::Value>,
+) -> Result<Point<A::Value>, PlonkError> {
+    Ok(Point {
+        u: ops.gate(Gate::new().mul(1), bit, point.u)?,
+        v: ops.gate(Gate::new().mul(1).left(-1).constant(1), bit, point.v)?,
+    })
+}
+
+// Product of `point` by the little-endian `bits`.
+pub(crate) fn mul<A: Arithmetic>(
+    ops: &mut A,
+    bits: &[A::Value],
+    point: Point<A::Value>,
+) -> Result<Point<A::Value>, PlonkError> {
+    let (most_significant, rest) = match bits.split_last() {
+        Some(split) => split,
+        None => {
+            return Ok(Point {
+                u: ops.constant(Scalar::zero())?,
+                v: ops.constant(Scalar::one())?,
+            })
+        }
+    };
+    let mut result = select(ops, *most_significant, point)?;
+    for bit in rest.iter().rev() {
+        result = add(ops, result, result)?;
+        let selected = select(ops, *bit, point)?;
+        result = add(ops, result, selected)?;
+    }
+    Ok(result)
+}
+
+const WINDOW_BITS: usize = 3;
+
+// Coefficients of the multilinear polynomial in the bits of j that evaluates to values[j],
+// where coefficient m multiplies the product of the bits set in m.
+fn multilinear_coefficients(mut values: Vec<Scalar>) -> Vec<Scalar> {
+    for bit in 0..WINDOW_BITS {
+        for m in 0..values.len() {
+            if m & (1 << bit) != 0 {
+                values[m] = values[m] - values[m ^ (1 << bit)];
+            }
+        }
+    }
+    values
+}
+
+// Product of the constant `base` by the little-endian `bits`.
+pub(crate) fn fixed_base_mul<A: Arithmetic>(
+    ops: &mut A,
+    bits: &[A::Value],
+    base: &JubjubPoint,
+) -> Result<Point<A::Value>, PlonkError> {
+    let mut result: Option<Point<A::Value>> = None;
+    let mut window_base = *base;
+    for window in bits.chunks(WINDOW_BITS) {
+        let size = 1 << window.len();
+        let mut multiples = vec![JubjubPoint::identity()];
+        for j in 1..size {
+            multiples.push(multiples[j - 1].add(&window_base));
+        }
+        let u_coefficients = multilinear_coefficients(multiples.iter().map(|p| p.u).collect());
+        let v_coefficients = multilinear_coefficients(multiples.iter().map(|p| p.v).collect());
+
+        // Products of the bits set in m, built from the product without its highest bit.
+        let mut monomials = vec![None; size];
+        for m in 1..size {
+            let highest = usize::BITS - 1 - m.leading_zeros();
+            let rest = m ^ (1 << highest);
+            let bit = window[highest as usize];
+            monomials[m] = Some(match monomials[rest] {
+                None => bit,
+                Some(product) => ops.gate(Gate::new().mul(1), product, bit)?,
+            });
+        }
+
+        let mut u = Combination::constant(u_coefficients[0]);
+        let mut v = Combination::constant(v_coefficients[0]);
+        for m in 1..size {
+            let monomial = monomials[m].expect("Every monomial is set");
+            u.terms.push((monomial, u_coefficients[m]));
+            v.terms.push((monomial, v_coefficients[m]));
+        }
+        let selected = Point {
+            u: u.evaluate(ops)?,
+            v: v.evaluate(ops)?,
+        };
+
+        result = Some(match result {
+            None => selected,
+            Some(result) => add(ops, result, selected)?,
+        });
+        for _ in 0..WINDOW_BITS {
+            window_base = window_base.double();
+        }
+    }
+
+    match result {
+        Some(result) => Ok(result),
+        None => Ok(Point {
+            u: ops.constant(Scalar::zero())?,
+            v: ops.constant(Scalar::one())?,
+        }),
+    }
+}
+
+// Constrains -u^2 + v^2 = 1 + d * u^2 * v^2.
+pub(crate) fn assert_on_curve<A: Arithmetic>(
+    ops: &mut A,
+    point: Point<A::Value>,
+) -> Result<(), PlonkError> {
+    let u_square = ops.gate(Gate::new().mul(1), point.u, point.u)?;
+    let v_square = ops.gate(Gate::new().mul(1), point.v, point.v)?;
+    let left = ops.gate(Gate::new().left(-1).right(1), u_square, v_square)?;
+    let right = ops.gate(Gate::new().mul(edwards_d()).constant(1), u_square, v_square)?;
+    ops.assert_equal(left, right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jubjub::JubjubScalar;
+    use rand_core::OsRng;
+
+    fn point_wires(circuit: &mut PlonkCircuit) -> Point<Wire> {
+        let (u, v, _) = circuit.add_gate();
+        Point { u, v }
+    }
+
+    fn set_point(trace: &mut ComputationTrace, wires: &Point<Wire>, point: &JubjubPoint) {
+        trace.set(wires.u, point.u);
+        trace.set(wires.v, point.v);
+        trace.set(Wire::output(wires.u.row), point.u + point.v);
+    }
+
+    #[test]
+    fn test_point_addition() {
+        let generator = JubjubPoint::generator();
+        let p = generator.mul(&JubjubScalar::random(OsRng).to_bytes());
+        let mut circuit = PlonkCircuit::init();
+        let (p_wires, q_wires) = (point_wires(&mut circuit), point_wires(&mut circuit));
+        let sum = point_addition(&mut circuit, p_wires, q_wires).unwrap();
+
+        // Both distinct points and doubling, as the formula is complete.
+        for q in [generator, p] {
+            let mut trace = ComputationTrace::new(&circuit);
+            set_point(&mut trace, &p_wires, &p);
+            set_point(&mut trace, &q_wires, &q);
+            assert_eq!(sum.assign(&mut trace, &p, &q), p.add(&q));
+            assert!(circuit.is_satisfied(&trace).is_ok());
+
+            // The output cannot be another point.
+            trace.set(sum.output().u, generator.u);
+            assert!(circuit.is_satisfied(&trace).is_err());
+        }
+    }
+
+    #[test]
+    fn test_scalar_multiplication() {
+        let point = JubjubPoint::generator().mul(&[5]);
+        let mut circuit = PlonkCircuit::init();
+        let (scalar_wire, _, _) = circuit.add_gate();
+        let point_wires = point_wires(&mut circuit);
+        let product = scalar_multiplication(&mut circuit, scalar_wire, point_wires, 16).unwrap();
+
+        let scalar = Scalar::from(0xbeef);
+        let mut trace = ComputationTrace::new(&circuit);
+        trace.set(scalar_wire, scalar);
+        trace.set(Wire::output(scalar_wire.row), scalar);
+        set_point(&mut trace, &point_wires, &point);
+        let result = product.assign(&mut trace, &scalar, &point);
+        assert_eq!(result, point.mul(&0xbeefu64.to_le_bytes()));
+        assert!(circuit.is_satisfied(&trace).is_ok());
+
+        // The scalar has to fit in the number of bits.
+        let scalar = Scalar::from(0x1beef);
+        trace.set(scalar_wire, scalar);
+        trace.set(Wire::output(scalar_wire.row), scalar);
+        product.assign(&mut trace, &scalar, &point);
+        assert!(circuit.is_satisfied(&trace).is_err());
+    }
+
+    #[test]
+    fn test_fixed_base_multiplication() {
+        let base = JubjubPoint::generator();
+        // With a number of bits that is not a multiple of the window.
+        for (num_bits, scalar) in [
+            (252, JubjubScalar::random(OsRng).to_scalar()),
+            (8, Scalar::from(0xa5)),
+        ] {
+            let mut circuit = PlonkCircuit::init();
+            let (scalar_wire, _, _) = circuit.add_gate();
+            let product =
+                fixed_base_multiplication(&mut circuit, scalar_wire, &base, num_bits).unwrap();
+
+            let mut trace = ComputationTrace::new(&circuit);
+            trace.set(scalar_wire, scalar);
+            trace.set(Wire::output(scalar_wire.row), scalar);
+            let result = product.assign(&mut trace, &scalar);
+            assert_eq!(result, base.mul(&scalar.to_bytes_le()));
+            assert!(circuit.is_satisfied(&trace).is_ok());
+
+            let output = product.output();
+            let column = [&trace.a, &trace.b, &trace.c];
+            assert_eq!(column[output.u.column][output.u.row], result.u);
+            assert_eq!(column[output.v.column][output.v.row], result.v);
+        }
+    }
+}
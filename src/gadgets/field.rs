@@ -0,0 +1,138 @@
+// Gadgets over field elements that need a hint from the prover, the inverse of the input, which
+// the circuit cannot compute but only check.
+//
+// The inverse is a single gate inv * x - 1 = 0, which cannot be satisfied if x is zero. The
+// zero test uses two gates with the same hint (or zero if x is zero): out = 1 - x * inv, and
+// x * out = 0. If x is not zero, the second gate forces out = 0, and if x is zero, the first
+// one forces out = 1.
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::Field;
+
+/// Wires created by `inverse`, used to fill its row in the trace.
+#[derive(Clone, Debug)]
+pub struct Inverse {
+    output: Wire,
+}
+
+impl Inverse {
+    /// Wire that carries the inverse.
+    pub fn output(&self) -> Wire {
+        self.output
+    }
+
+    /// Assigns the row of the gadget for the inverse of `x`, which is returned. If `x` is zero,
+    /// the inverse is assigned zero, and the proof will fail.
+    pub fn assign(&self, trace: &mut ComputationTrace, x: &Scalar) -> Scalar {
+        let inverse = x.invert().unwrap_or(Scalar::zero());
+        trace.set(self.output, inverse);
+        trace.set(Wire::right(self.output.row), *x);
+        inverse
+    }
+}
+
+/// Constrains the output of the returned gadget to be the inverse of `x`, which is therefore
+/// constrained to be different from zero.
+pub fn inverse(circuit: &mut PlonkCircuit, x: Wire) -> Result<Inverse, PlonkError> {
+    let (inverse, x_copy, _) = circuit.gate(Gate::new().mul(1).constant(-1));
+    circuit.connect(x, x_copy)?;
+    Ok(Inverse { output: inverse })
+}
+
+/// Wires created by `is_zero`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct IsZero {
+    output: Wire,
+    check_row: usize, // row of x * out = 0
+}
+
+impl IsZero {
+    /// Wire that carries one if the input is zero, and zero otherwise.
+    pub fn output(&self) -> Wire {
+        self.output
+    }
+
+    /// Assigns the rows of the gadget for `x`, and returns whether it is zero, as a scalar.
+    pub fn assign(&self, trace: &mut ComputationTrace, x: &Scalar) -> Scalar {
+        let inverse = x.invert().unwrap_or(Scalar::zero());
+        let output = Scalar::one() - x * inverse;
+        trace.set(Wire::left(self.output.row), *x);
+        trace.set(Wire::right(self.output.row), inverse);
+        trace.set(self.output, output);
+        trace.set(Wire::left(self.check_row), *x);
+        trace.set(Wire::right(self.check_row), output);
+        output
+    }
+}
+
+/// Constrains the output of the returned gadget to be one if `x` is zero, and zero otherwise.
+pub fn is_zero(circuit: &mut PlonkCircuit, x: Wire) -> Result<IsZero, PlonkError> {
+    let (x_copy, _, output) = circuit.gate(Gate::new().mul(-1).output(-1).constant(1));
+    circuit.connect(x, x_copy)?;
+    let (x_check, output_check, _) = circuit.gate(Gate::new().mul(1));
+    circuit.connect(x, x_check)?;
+    circuit.connect(output, output_check)?;
+
+    Ok(IsZero {
+        output,
+        check_row: x_check.row,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input_trace(circuit: &PlonkCircuit, x: Wire, value: &Scalar) -> ComputationTrace {
+        let mut trace = ComputationTrace::new(circuit);
+        trace.set(x, *value);
+        trace.set(Wire::output(x.row), *value);
+        trace
+    }
+
+    #[test]
+    fn test_inverse() {
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.add_gate();
+        let inverse = inverse(&mut circuit, x).unwrap();
+
+        let mut trace = input_trace(&circuit, x, &Scalar::from(5));
+        let result = inverse.assign(&mut trace, &Scalar::from(5));
+        assert_eq!(result * Scalar::from(5), Scalar::one());
+        assert!(circuit.is_satisfied(&trace).is_ok());
+
+        let mut trace = input_trace(&circuit, x, &Scalar::zero());
+        inverse.assign(&mut trace, &Scalar::zero());
+        assert!(circuit.is_satisfied(&trace).is_err());
+    }
+
+    #[test]
+    fn test_is_zero() {
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.add_gate();
+        let is_zero = is_zero(&mut circuit, x).unwrap();
+
+        for (value, expected) in [
+            (Scalar::zero(), Scalar::one()),
+            (Scalar::from(7), Scalar::zero()),
+        ] {
+            let mut trace = input_trace(&circuit, x, &value);
+            assert_eq!(is_zero.assign(&mut trace, &value), expected);
+            assert!(circuit.is_satisfied(&trace).is_ok());
+
+            // The prover cannot claim the opposite, whatever the hint.
+            let output = is_zero.output();
+            trace.set(output, Scalar::one() - expected);
+            trace.set(Wire::right(is_zero.check_row), Scalar::one() - expected);
+            for hint in [
+                Scalar::zero(),
+                Scalar::one(),
+                Scalar::from(7).invert().unwrap(),
+            ] {
+                trace.set(Wire::right(output.row), hint);
+                assert!(circuit.is_satisfied(&trace).is_err());
+            }
+        }
+    }
+}
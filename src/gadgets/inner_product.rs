@@ -0,0 +1,260 @@
+// Inner products in the circuit, written over the `Arithmetic` backend, for the linear layers
+// of ML models and the evaluation of polynomials. A gate has a single product and three wires,
+// so a row cannot take two factors and a running sum in and give the new sum out, but it can
+// give it to the left wire of the next row through q_N. The terms of two wire vectors are
+// taken in pairs, with three rows per pair:
+//
+//   p = x_1 * y_1
+//   x_0 * y_0 + s = a'    (fused mul-add, the running sum s in the output wire)
+//   a' + p = s'
+//
+// that is around 3n / 2 rows for n terms, against 2n - 1 for the products and their sum.
+//
+// When one of the vectors is public, its coefficients are folded into the selectors, and the
+// sum runs along the rows as s + k_0 * w_0 + k_1 * w_1 = a', two terms per row, the first row
+// taking up to three and the last one giving the result in its output wire, that is n / 2 rows
+// rounded up. Up to three terms this is a plain `Combination`.
+use super::arithmetic::{Arithmetic, Assigner, Builder, Combination};
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::Field;
+
+/// Wires created by `inner_product`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct InnerProduct {
+    output: Wire,
+    len: usize,
+    rows: Vec<usize>, // rows of the gates, in the order in which they were created
+}
+
+impl InnerProduct {
+    /// Wire that carries the inner product.
+    pub fn output(&self) -> Wire {
+        self.output
+    }
+
+    /// Assigns the rows of the gadget for the inner product of `x` and `y`, which is returned.
+    /// The trace must already contain the inputs.
+    pub fn assign(&self, trace: &mut ComputationTrace, x: &[Scalar], y: &[Scalar]) -> Scalar {
+        assert!(
+            x.len() == self.len && y.len() == self.len,
+            "Wrong number of inputs"
+        );
+        let mut assigner = Assigner::new(trace, &self.rows);
+        product(&mut assigner, x, y).expect("Assigning the trace cannot fail")
+    }
+}
+
+/// Constrains the output wire of the returned gadget to be the sum of x_i * y_i. The vectors
+/// must have the same length.
+pub fn inner_product(
+    circuit: &mut PlonkCircuit,
+    x: &[Wire],
+    y: &[Wire],
+) -> Result<InnerProduct, PlonkError> {
+    if x.len() != y.len() {
+        return Err(PlonkError::InvalidLength {
+            expected: x.len(),
+            got: y.len(),
+        });
+    }
+    let mut builder = Builder::new(circuit);
+    let output = product(&mut builder, x, y)?;
+
+    Ok(InnerProduct {
+        output,
+        len: x.len(),
+        rows: builder.rows,
+    })
+}
+
+/// Wires created by `public_inner_product`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct PublicInnerProduct {
+    output: Wire,
+    coefficients: Vec<Scalar>,
+    rows: Vec<usize>, // rows of the gates, in the order in which they were created
+}
+
+impl PublicInnerProduct {
+    /// Wire that carries the inner product.
+    pub fn output(&self) -> Wire {
+        self.output
+    }
+
+    /// Assigns the rows of the gadget for the inner product of the coefficients and `x`, which
+    /// is returned. The trace must already contain the inputs.
+    pub fn assign(&self, trace: &mut ComputationTrace, x: &[Scalar]) -> Scalar {
+        assert_eq!(x.len(), self.coefficients.len(), "Wrong number of inputs");
+        let mut assigner = Assigner::new(trace, &self.rows);
+        public_product(&mut assigner, &self.coefficients, x)
+            .expect("Assigning the trace cannot fail")
+    }
+}
+
+/// Constrains the output wire of the returned gadget to be the sum of k_i * x_i, for the fixed
+/// `coefficients` k, which are part of the circuit. The vectors must have the same length.
+pub fn public_inner_product(
+    circuit: &mut PlonkCircuit,
+    coefficients: &[Scalar],
+    x: &[Wire],
+) -> Result<PublicInnerProduct, PlonkError> {
+    if x.len() != coefficients.len() {
+        return Err(PlonkError::InvalidLength {
+            expected: coefficients.len(),
+            got: x.len(),
+        });
+    }
+    let mut builder = Builder::new(circuit);
+    let output = public_product(&mut builder, coefficients, x)?;
+
+    Ok(PublicInnerProduct {
+        output,
+        coefficients: coefficients.to_vec(),
+        rows: builder.rows,
+    })
+}
+
+fn product<A: Arithmetic>(
+    ops: &mut A,
+    x: &[A::Value],
+    y: &[A::Value],
+) -> Result<A::Value, PlonkError> {
+    let terms = x.iter().copied().zip(y.iter().copied()).collect::<Vec<_>>();
+    let Some(((x_0, y_0), rest)) = terms.split_first() else {
+        return ops.constant(Scalar::zero());
+    };
+
+    let mut sum = ops.gate(Gate::new().mul(1), *x_0, *y_0)?;
+    for pair in rest.chunks(2) {
+        sum = match pair {
+            [(x_0, y_0), (x_1, y_1)] => {
+                let product = ops.gate(Gate::new().mul(1), *x_1, *y_1)?;
+                let next = ops.chain(Gate::new().mul(1).output(1), *x_0, *y_0, sum)?;
+                ops.gate(Gate::new().left(1).right(1), next, product)?
+            }
+            [(x, y)] => {
+                let product = ops.gate(Gate::new().mul(1), *x, *y)?;
+                ops.gate(Gate::new().left(1).right(1), sum, product)?
+            }
+            _ => unreachable!("Chunks have one or two terms"),
+        };
+    }
+    Ok(sum)
+}
+
+fn public_product<A: Arithmetic>(
+    ops: &mut A,
+    coefficients: &[Scalar],
+    x: &[A::Value],
+) -> Result<A::Value, PlonkError> {
+    let terms = x
+        .iter()
+        .copied()
+        .zip(coefficients.iter().copied())
+        .collect::<Vec<_>>();
+    if terms.len() <= 3 {
+        let combination = Combination {
+            terms,
+            constant: Scalar::zero(),
+        };
+        return combination.evaluate(ops);
+    }
+
+    // The first row takes three terms, or two if that leaves an even number of terms for the
+    // rows in the middle, its output wire then being a copy of its right one.
+    let (mut sum, rest) = match terms.as_slice() {
+        [(x_0, k_0), (x_1, k_1), rest @ ..] if terms.len() % 2 == 1 => {
+            let gate = Gate::new().left(*k_0).right(*k_1);
+            (ops.chain(gate, *x_0, *x_1, *x_1)?, rest)
+        }
+        [(x_0, k_0), (x_1, k_1), (x_2, k_2), rest @ ..] => {
+            let gate = Gate::new().left(*k_0).right(*k_1).output(*k_2);
+            (ops.chain(gate, *x_0, *x_1, *x_2)?, rest)
+        }
+        _ => unreachable!("There are more than three terms"),
+    };
+    let ((x_last, k_last), middle) = rest.split_last().expect("There is a last term");
+    for pair in middle.chunks_exact(2) {
+        let gate = Gate::new().left(1).right(pair[0].1).output(pair[1].1);
+        sum = ops.chain(gate, sum, pair[0].0, pair[1].0)?;
+    }
+    ops.gate(Gate::new().left(1).right(*k_last), sum, *x_last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Circuit of both inner products of `len` free inputs, the second vector of the first one
+    // being the public coefficients, with its trace for inputs x_i = i + 2 and y_i = 3i + 1.
+    fn circuit(len: usize) -> (PlonkCircuit, ComputationTrace, [Wire; 2], Scalar) {
+        let mut circuit = PlonkCircuit::init();
+        let mut inputs = || (0..len).map(|_| circuit.add_gate().0).collect::<Vec<_>>();
+        let (x_wires, y_wires) = (inputs(), inputs());
+        let x = (0..len as u64)
+            .map(|i| Scalar::from(i + 2))
+            .collect::<Vec<_>>();
+        let y = (0..len as u64)
+            .map(|i| Scalar::from(3 * i + 1))
+            .collect::<Vec<_>>();
+        let gadget = inner_product(&mut circuit, &x_wires, &y_wires).unwrap();
+        let public = public_inner_product(&mut circuit, &y, &x_wires).unwrap();
+
+        let mut trace = ComputationTrace::new(&circuit);
+        for (wire, value) in x_wires.iter().zip(&x).chain(y_wires.iter().zip(&y)) {
+            trace.set(*wire, *value);
+            trace.set(Wire::output(wire.row), *value);
+        }
+        let expected = x.iter().zip(&y).map(|(x, y)| x * y).sum::<Scalar>();
+        assert_eq!(gadget.assign(&mut trace, &x, &y), expected);
+        assert_eq!(public.assign(&mut trace, &x), expected);
+        assert_eq!(trace.value(gadget.output()), expected);
+        assert_eq!(trace.value(public.output()), expected);
+        (circuit, trace, [gadget.output(), public.output()], expected)
+    }
+
+    #[test]
+    fn test_inner_product() {
+        for len in 0..10 {
+            let (circuit, mut trace, outputs, expected) = circuit(len);
+            assert!(circuit.is_satisfied(&trace).is_ok(), "length {len}");
+
+            let rows = circuit.nr_constraints - 2 * len;
+            let wire_rows = if len == 0 { 1 } else { 3 * len / 2 };
+            let public_rows = match len {
+                0 => 1,
+                1..=3 => len - 1,
+                _ => len.div_ceil(2),
+            };
+            assert_eq!(rows, wire_rows + public_rows, "length {len}");
+
+            if len > 1 {
+                for output in outputs {
+                    trace.set(output, expected + Scalar::one());
+                    assert!(circuit.is_satisfied(&trace).is_err());
+                    trace.set(output, expected);
+                }
+            }
+            if len > 2 {
+                // The running sum of the first fused row, only held by the left wire of the
+                // row after it.
+                let next = Wire::left(2 * len + 3);
+                trace.set(next, trace.value(next) + Scalar::one());
+                assert!(circuit.is_satisfied(&trace).is_err());
+            }
+        }
+
+        let mut circuit = PlonkCircuit::init();
+        let (x, y, _) = circuit.add_gate();
+        assert_eq!(
+            inner_product(&mut circuit, &[x, y], &[x]).unwrap_err(),
+            PlonkError::InvalidLength {
+                expected: 2,
+                got: 1
+            }
+        );
+        assert!(public_inner_product(&mut circuit, &[Scalar::one()], &[]).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+// Gadgets are circuits for common primitives. Building a gadget adds its gates to a
+// `PlonkCircuit`, and returns a handle that assigns the rows of the gadget in the trace from
+// the values of its inputs, with the matching native computation.
+//
+// The `Gadget` trait is the common interface of the gadgets that map input wires to output
+// wires, so that they can be composed without knowing their layout, and implemented by other
+// crates. Their traces are derived with `WitnessBuilder`, from the values of the inputs, and
+// of the outputs of gadgets that need a hint from the prover (such as the bits of a range).
+//
+// The trait only returns wires, so it is not implemented by the gadgets whose rows
+// `WitnessBuilder` can't derive, which keep their handle with an `assign` method instead:
+// the integers of `uint`, the bytes of `bits`, `sha256` and `schnorr` take bit decompositions
+// that are not among their outputs, `ecc` proves its coordinates as quotients, which are
+// hints, and `inner_product` chains its running sums through the next row of gates that also
+// have an output, which `WitnessBuilder` evaluates before it knows the next row.
+use crate::error::PlonkError;
+use crate::plonk::PlonkCircuit;
+use crate::wire::Wire;
+
+pub(crate) mod arithmetic;
+pub mod bits;
+pub mod boolean;
+pub mod conditional;
+pub mod ecc;
+pub mod field;
+pub mod inner_product;
+pub mod poseidon;
+pub mod range;
+pub mod recursion;
+pub mod schnorr;
+pub mod sha256;
+pub mod subcircuit;
+pub mod uint;
+
+/// Circuit that constrains its output wires as a function of its input wires.
+pub trait Gadget {
+    /// Adds the gates of the gadget to `circuit`, connected to `inputs`, and returns the wires
+    /// of its outputs.
+    fn synthesize(
+        &self,
+        circuit: &mut PlonkCircuit,
+        inputs: &[Wire],
+    ) -> Result<Vec<Wire>, PlonkError>;
+}
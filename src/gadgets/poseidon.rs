@@ -0,0 +1,182 @@
+// Poseidon hash in the circuit, written over the `Arithmetic` backend. As the state is kept as
+// linear combinations, the round constants and the MDS matrix do not use gates of their own.
+// An S-box uses three gates, (m * x + k)^2, its square, and the product with m * x + k, and
+// reducing an element of the state to a single wire uses one gate per extra term.
+use super::arithmetic::{Arithmetic, Assigner, Builder, Combination};
+use super::Gadget;
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use crate::poseidon::{is_full_round, Poseidon, WIDTH};
+use blstrs::Scalar;
+use ff::Field;
+
+/// Wires created by `poseidon_hash`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct PoseidonHash {
+    output: Wire,
+    nr_inputs: usize,
+    rows: Vec<usize>, // rows of the gates, in the order in which they were created
+}
+
+impl PoseidonHash {
+    /// Wire that carries the hash.
+    pub fn output(&self) -> Wire {
+        self.output
+    }
+
+    /// Assigns the rows of the gadget for the hash of `inputs`, which is returned. The trace
+    /// must already contain the inputs, as the gadget only assigns its own gates.
+    pub fn assign(&self, trace: &mut ComputationTrace, inputs: &[Scalar]) -> Scalar {
+        assert_eq!(inputs.len(), self.nr_inputs, "Wrong number of inputs");
+        let mut assigner = Assigner::new(trace, &self.rows);
+        sponge(&mut assigner, inputs).expect("Assigning the trace cannot fail")
+    }
+}
+
+/// Constrains the output wire of the returned gadget to be the Poseidon hash of `inputs`, as
+/// computed natively by the sponge used for the transcripts: rate 2, with the number of inputs
+/// in the capacity element. A permutation uses around 630 gates.
+pub fn poseidon_hash(
+    circuit: &mut PlonkCircuit,
+    inputs: &[Wire],
+) -> Result<PoseidonHash, PlonkError> {
+    let mut builder = Builder::new(circuit);
+    let output = sponge(&mut builder, inputs)?;
+
+    Ok(PoseidonHash {
+        output,
+        nr_inputs: inputs.len(),
+        rows: builder.rows,
+    })
+}
+
+/// Poseidon hash of the inputs, as a `Gadget` with a single output.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PoseidonHasher;
+
+impl Gadget for PoseidonHasher {
+    fn synthesize(
+        &self,
+        circuit: &mut PlonkCircuit,
+        inputs: &[Wire],
+    ) -> Result<Vec<Wire>, PlonkError> {
+        Ok(vec![poseidon_hash(circuit, inputs)?.output()])
+    }
+}
+
+fn sbox<A: Arithmetic>(ops: &mut A, element: &mut Combination<A::Value>) -> Result<(), PlonkError> {
+    element.reduce(ops)?;
+    match element.terms.pop() {
+        None => element.constant = element.constant.pow_vartime([5]),
+        Some((x, m)) => {
+            // With l = r = x, m^2 * l * r + 2 * m * k * l + k^2 = (m * x + k)^2, and the fifth
+            // power is the product of the fourth with m * x + k.
+            let k = element.constant;
+            let square = ops.gate(
+                Gate::new()
+                    .mul(m.square())
+                    .left(m * k.double())
+                    .constant(k.square()),
+                x,
+                x,
+            )?;
+            let fourth = ops.gate(Gate::new().mul(1), square, square)?;
+            let fifth = ops.gate(Gate::new().mul(m).left(k), fourth, x)?;
+            *element = Combination::value(fifth);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn permute<A: Arithmetic>(
+    ops: &mut A,
+    poseidon: &Poseidon,
+    state: &mut [Combination<A::Value>; WIDTH],
+) -> Result<(), PlonkError> {
+    for (round, constants) in poseidon.round_constants.iter().enumerate() {
+        for (element, constant) in state.iter_mut().zip(constants.iter()) {
+            element.constant += constant;
+        }
+
+        if is_full_round(round) {
+            for element in state.iter_mut() {
+                sbox(ops, element)?;
+            }
+        } else {
+            sbox(ops, &mut state[0])?;
+        }
+
+        let mut result: [Combination<A::Value>; WIDTH] =
+            std::array::from_fn(|_| Combination::constant(Scalar::zero()));
+        for (value, row) in result.iter_mut().zip(poseidon.mds.iter()) {
+            for (m, element) in row.iter().zip(state.iter()) {
+                value.add(element, *m);
+            }
+            value.reduce(ops)?;
+        }
+        *state = result;
+    }
+    Ok(())
+}
+
+// Same sponge as `Poseidon::hash`.
+pub(crate) fn sponge<A: Arithmetic>(
+    ops: &mut A,
+    inputs: &[A::Value],
+) -> Result<A::Value, PlonkError> {
+    let poseidon = Poseidon::new();
+    let mut state: [Combination<A::Value>; WIDTH] =
+        std::array::from_fn(|_| Combination::constant(Scalar::zero()));
+    state[0].constant = Scalar::from(inputs.len() as u64);
+
+    for chunk in inputs.chunks(WIDTH - 1) {
+        for (element, input) in state[1..].iter_mut().zip(chunk) {
+            element.terms.push((*input, Scalar::one()));
+        }
+        permute(ops, &poseidon, &mut state)?;
+    }
+    if inputs.is_empty() {
+        permute(ops, &poseidon, &mut state)?;
+    }
+
+    let [_, output, _] = state;
+    output.evaluate(ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::WitnessBuilder;
+
+    #[test]
+    fn test_poseidon_hash() {
+        let poseidon = Poseidon::new();
+        for nr_inputs in 0..4 {
+            let mut circuit = PlonkCircuit::init();
+            let inputs: Vec<Wire> = (0..nr_inputs).map(|_| circuit.public_input()).collect();
+            let gadget = poseidon_hash(&mut circuit, &inputs).unwrap();
+
+            let values: Vec<Scalar> = (0..nr_inputs).map(|i| Scalar::from(i as u64 + 7)).collect();
+            let mut trace = ComputationTrace::new(&circuit);
+            for (wire, value) in inputs.iter().zip(values.iter()) {
+                trace.set(*wire, *value);
+            }
+            let hash = gadget.assign(&mut trace, &values);
+            assert_eq!(hash, poseidon.hash(&values));
+
+            let output = gadget.output();
+            let column = [&trace.a, &trace.b, &trace.c][output.column];
+            assert_eq!(column[output.row], hash);
+
+            // Without hints, the trace of the gadget is derived from the inputs.
+            let mut circuit = PlonkCircuit::init();
+            let inputs: Vec<Wire> = (0..nr_inputs).map(|_| circuit.public_input()).collect();
+            let output = PoseidonHasher.synthesize(&mut circuit, &inputs).unwrap()[0];
+            let mut builder = WitnessBuilder::new(&circuit);
+            for (wire, value) in inputs.iter().zip(values.iter()) {
+                builder.assign(*wire, *value).unwrap();
+            }
+            assert_eq!(builder.build().unwrap().value(output), hash);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+// Range checks as a `Gadget`, on top of `PlonkCircuit::range_check`. The outputs are the bits
+// of the input, most significant first, which the prover assigns as a hint: the running sum
+// that recomposes them is then derived by `WitnessBuilder`, and checked against the input.
+use super::Gadget;
+use crate::error::PlonkError;
+use crate::plonk::{PlonkCircuit, Wire};
+
+/// Constrains every input to be smaller than 2^bits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Range {
+    pub bits: usize,
+}
+
+impl Gadget for Range {
+    fn synthesize(
+        &self,
+        circuit: &mut PlonkCircuit,
+        inputs: &[Wire],
+    ) -> Result<Vec<Wire>, PlonkError> {
+        let mut bits = Vec::with_capacity(inputs.len() * self.bits);
+        for input in inputs {
+            bits.extend_from_slice(circuit.range_check(*input, self.bits)?.bits());
+        }
+        Ok(bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::Gate;
+    use crate::witness::WitnessBuilder;
+    use blstrs::Scalar;
+
+    #[test]
+    fn test_range() {
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.gate(Gate::new());
+        let bits = Range { bits: 4 }.synthesize(&mut circuit, &[x]).unwrap();
+        assert_eq!(bits.len(), 4);
+
+        let trace = |value: u64, decomposition: u64| {
+            let mut builder = WitnessBuilder::new(&circuit);
+            builder.assign(x, Scalar::from(value))?;
+            for (i, bit) in bits.iter().enumerate() {
+                builder.assign(*bit, Scalar::from((decomposition >> (3 - i)) & 1))?;
+            }
+            builder.build()
+        };
+        assert!(circuit.is_satisfied(&trace(11, 11).unwrap()).is_ok());
+        // The running sum of the bits of 3 does not match 11.
+        assert!(trace(11, 3).is_err());
+    }
+}
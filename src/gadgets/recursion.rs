@@ -0,0 +1,642 @@
+// Verification of a proof of this system in the circuit, for two layers of recursion: an outer
+// proof shows that an inner proof, created with `PoseidonTranscript`, passes every step of the
+// verifier but the last one. The gadget replays the transcript with the Poseidon permutation,
+// and computes the scalars of the final check (see `DeferredCheck`), which become outputs of
+// the circuit. The multi-scalar multiplication and the pairing are deferred to whoever checks
+// the outer proof, as the commitments are points of the very curve whose scalar field the
+// circuit works over.
+//
+// The commitments of the inner proof are absorbed as the transcript does, as two chunks of
+// their compressed encoding (31 and 17 bytes), which the circuit does not decompress. The
+// deferred check is only meaningful if the outer verifier derives these chunks from the points
+// that it uses in the multi-scalar multiplication, with `proof_inputs`. The verifier key and
+// the label of the transcript are constants of the circuit.
+//
+// The Lagrange polynomials are evaluated with a division, which assigns zero instead of one if
+// zeta is in the domain, an event of negligible probability. The gadget uses around 45k gates,
+// almost all of them in the permutations of the transcript.
+use super::arithmetic::{Arithmetic, Assigner, Builder, Combination};
+use super::poseidon::permute;
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use crate::poseidon::{Poseidon, WIDTH};
+use crate::proof::PlonkProof;
+use crate::verifier::{DeferredCheck, VerifierKey};
+use crate::wire::{K1, K2};
+use blstrs::Scalar;
+use ff::Field;
+
+/// Inputs of the gadget for each commitment of the proof.
+const POINT_CHUNKS: usize = 2;
+
+const NR_COMMITMENTS: usize = 13;
+
+const NR_EVALUATIONS: usize = 14;
+
+/// Wires created by `proof_verification`, used to fill its rows in the trace.
+#[derive(Clone)]
+pub struct ProofVerification {
+    outputs: Vec<Wire>,
+    verifier_key: VerifierKey,
+    label: &'static [u8],
+    rows: Vec<usize>,
+}
+
+impl ProofVerification {
+    /// Wires that carry the challenge u, followed by the coefficients of the deferred check.
+    pub fn outputs(&self) -> &[Wire] {
+        &self.outputs
+    }
+
+    /// Assigns the rows of the gadget for the inputs `inputs`, as given by `proof_inputs`, and
+    /// returns the deferred check. The trace must already contain the inputs, as the gadget
+    /// only assigns its own gates.
+    pub fn assign(&self, trace: &mut ComputationTrace, inputs: &[Scalar]) -> DeferredCheck {
+        let mut assigner = Assigner::new(trace, &self.rows);
+        let outputs = verification(&mut assigner, &self.verifier_key, self.label, inputs)
+            .expect("Assigning the trace cannot fail");
+        DeferredCheck {
+            u: outputs[0],
+            coefficients: outputs[1..].try_into().expect("There are 30 outputs"),
+        }
+    }
+}
+
+/// Inputs of the gadget for `proof` with public inputs `pub_in`: the public inputs, the
+/// chunks of the commitments in the order of the encoding of the proof, and the evaluations.
+pub fn proof_inputs(proof: &PlonkProof, pub_in: &[Scalar]) -> Vec<Scalar> {
+    let mut inputs = pub_in.to_vec();
+    for commitment in proof.commitments() {
+        inputs.extend(point_chunks(&commitment.to_bytes()));
+    }
+    inputs.extend(proof.evaluations().into_iter().copied());
+    inputs
+}
+
+/// Constrains the outputs of the returned gadget to be the deferred check of the proof given
+/// by `inputs` (see `proof_inputs`), for a transcript created with `label`. The circuit does
+/// not perform the check: the inner proof is only valid if `DeferredCheck::verify` passes for
+/// the outputs.
+pub fn proof_verification(
+    circuit: &mut PlonkCircuit,
+    verifier_key: &VerifierKey,
+    label: &'static [u8],
+    inputs: &[Wire],
+) -> Result<ProofVerification, PlonkError> {
+    let mut builder = Builder::new(circuit);
+    let outputs = verification(&mut builder, verifier_key, label, inputs)?;
+
+    Ok(ProofVerification {
+        outputs,
+        verifier_key: verifier_key.clone(),
+        label,
+        rows: builder.rows,
+    })
+}
+
+// Little-endian scalars of the chunks of 31 bytes of an encoding, as in `PoseidonTranscript`.
+fn point_chunks(bytes: &[u8; 48]) -> [Scalar; POINT_CHUNKS] {
+    let mut chunks = bytes.chunks(31).map(|chunk| {
+        let mut scalar_bytes = [0u8; 32];
+        scalar_bytes[..chunk.len()].copy_from_slice(chunk);
+        Scalar::from_bytes_le(&scalar_bytes).unwrap()
+    });
+    [chunks.next().unwrap(), chunks.next().unwrap()]
+}
+
+// Same transcript as `PoseidonTranscript`, with the elements absorbed as linear combinations.
+struct Sponge<V> {
+    poseidon: Poseidon,
+    state: [Combination<V>; WIDTH],
+    pos: usize,
+}
+
+impl<V: Copy> Sponge<V> {
+    fn new<A: Arithmetic<Value = V>>(ops: &mut A, label: &[u8]) -> Result<Self, PlonkError> {
+        let mut sponge = Sponge {
+            poseidon: Poseidon::new(),
+            state: std::array::from_fn(|_| Combination::constant(Scalar::zero())),
+            pos: 0,
+        };
+        sponge.absorb_bytes(ops, b"dom-sep")?;
+        sponge.absorb_bytes(ops, label)?;
+        Ok(sponge)
+    }
+
+    fn absorb<A: Arithmetic<Value = V>>(
+        &mut self,
+        ops: &mut A,
+        element: &Combination<V>,
+    ) -> Result<(), PlonkError> {
+        self.state[1 + self.pos].add(element, Scalar::one());
+        self.pos += 1;
+        if self.pos == WIDTH - 1 {
+            permute(ops, &self.poseidon, &mut self.state)?;
+            self.pos = 0;
+        }
+        Ok(())
+    }
+
+    fn absorb_bytes<A: Arithmetic<Value = V>>(
+        &mut self,
+        ops: &mut A,
+        bytes: &[u8],
+    ) -> Result<(), PlonkError> {
+        self.absorb(
+            ops,
+            &Combination::constant(Scalar::from(bytes.len() as u64)),
+        )?;
+        for chunk in bytes.chunks(31) {
+            let mut scalar_bytes = [0u8; 32];
+            scalar_bytes[..chunk.len()].copy_from_slice(chunk);
+            let chunk = Scalar::from_bytes_le(&scalar_bytes).unwrap();
+            self.absorb(ops, &Combination::constant(chunk))?;
+        }
+        Ok(())
+    }
+
+    fn append_scalar<A: Arithmetic<Value = V>>(
+        &mut self,
+        ops: &mut A,
+        label: &[u8],
+        scalar: V,
+    ) -> Result<(), PlonkError> {
+        self.absorb_bytes(ops, label)?;
+        self.absorb(ops, &Combination::value(scalar))
+    }
+
+    // The chunks of a point are absorbed as those of any other message of 48 bytes.
+    fn append_point<A: Arithmetic<Value = V>>(
+        &mut self,
+        ops: &mut A,
+        label: &[u8],
+        chunks: &[V],
+    ) -> Result<(), PlonkError> {
+        self.absorb_bytes(ops, label)?;
+        self.absorb(ops, &Combination::constant(Scalar::from(48)))?;
+        for chunk in chunks {
+            self.absorb(ops, &Combination::value(*chunk))?;
+        }
+        Ok(())
+    }
+
+    fn challenge<A: Arithmetic<Value = V>>(
+        &mut self,
+        ops: &mut A,
+        label: &[u8],
+    ) -> Result<Combination<V>, PlonkError> {
+        self.absorb_bytes(ops, label)?;
+        permute(ops, &self.poseidon, &mut self.state)?;
+        self.pos = 0;
+        let challenge = self.state[1].clone().evaluate(ops)?;
+        self.state[1] = Combination::value(challenge);
+        Ok(Combination::value(challenge))
+    }
+}
+
+// Product of two combinations, with a single gate if neither is constant.
+fn mul<A: Arithmetic>(
+    ops: &mut A,
+    x: &Combination<A::Value>,
+    y: &Combination<A::Value>,
+) -> Result<Combination<A::Value>, PlonkError> {
+    let (mut x, mut y) = (x.clone(), y.clone());
+    x.reduce(ops)?;
+    y.reduce(ops)?;
+    Ok(match (x.terms.first(), y.terms.first()) {
+        (Some(&(l, ml)), Some(&(r, mr))) => Combination::value(
+            ops.gate(
+                Gate::new()
+                    .mul(ml * mr)
+                    .left(ml * y.constant)
+                    .right(x.constant * mr)
+                    .constant(x.constant * y.constant),
+                l,
+                r,
+            )?,
+        ),
+        (None, _) => scale(&y, x.constant),
+        (_, None) => scale(&x, y.constant),
+    })
+}
+
+fn scale<V: Copy>(x: &Combination<V>, factor: Scalar) -> Combination<V> {
+    linear(&[(x, factor)], Scalar::zero())
+}
+
+// Sum of m * x over `terms`, plus `constant`.
+fn linear<V: Copy>(terms: &[(&Combination<V>, Scalar)], constant: Scalar) -> Combination<V> {
+    let mut result = Combination::constant(constant);
+    for (x, m) in terms {
+        result.add(x, *m);
+    }
+    result
+}
+
+fn one<V: Copy>() -> Combination<V> {
+    Combination::constant(Scalar::one())
+}
+
+// Powers x, x^2, ..., x^count.
+fn powers<A: Arithmetic>(
+    ops: &mut A,
+    x: &Combination<A::Value>,
+    count: usize,
+) -> Result<Vec<Combination<A::Value>>, PlonkError> {
+    let mut powers = vec![x.clone()];
+    while powers.len() < count {
+        let power = mul(ops, &powers[powers.len() - 1], x)?;
+        powers.push(power);
+    }
+    Ok(powers)
+}
+
+// Same computation as `PlonkVerifier::deferred_check`, returning u and the coefficients.
+fn verification<A: Arithmetic>(
+    ops: &mut A,
+    verifier_key: &VerifierKey,
+    label: &'static [u8],
+    inputs: &[A::Value],
+) -> Result<Vec<A::Value>, PlonkError> {
+    let nr_public_inputs = verifier_key.public_inputs.len();
+    let expected = nr_public_inputs + NR_COMMITMENTS * POINT_CHUNKS + NR_EVALUATIONS;
+    if inputs.len() != expected {
+        return Err(PlonkError::InvalidLength {
+            expected,
+            got: inputs.len(),
+        });
+    }
+    let (pub_in, inputs) = inputs.split_at(nr_public_inputs);
+    let (points, evaluations) = inputs.split_at(NR_COMMITMENTS * POINT_CHUNKS);
+    let point = |index: usize| &points[POINT_CHUNKS * index..POINT_CHUNKS * (index + 1)];
+
+    let mut transcript = Sponge::new(ops, label)?;
+    transcript.absorb_bytes(ops, b"verifier key")?;
+    transcript.absorb_bytes(ops, &verifier_key.fingerprint())?;
+    for input in pub_in {
+        transcript.append_scalar(ops, b"public input", *input)?;
+    }
+
+    let labels: [&[u8]; 3] = [b"commitment a", b"commitment b", b"commitment c"];
+    for (index, label) in labels.into_iter().enumerate() {
+        transcript.append_point(ops, label, point(index))?;
+    }
+    let eta = transcript.challenge(ops, b"eta")?;
+
+    let labels: [&[u8]; 3] = [b"commitment f", b"commitment h1", b"commitment h2"];
+    for (index, label) in labels.into_iter().enumerate() {
+        transcript.append_point(ops, label, point(3 + index))?;
+    }
+    let beta = transcript.challenge(ops, b"beta")?;
+    let gamma = transcript.challenge(ops, b"gamma")?;
+    let delta = transcript.challenge(ops, b"delta")?;
+    let epsilon = transcript.challenge(ops, b"epsilon")?;
+
+    transcript.append_point(ops, b"Permutation polynomial", point(6))?;
+    transcript.append_point(ops, b"Lookup polynomial", point(7))?;
+    let alpha = transcript.challenge(ops, b"alpha")?;
+
+    transcript.append_point(ops, b"Quotient low polynomial", point(8))?;
+    transcript.append_point(ops, b"Quotient mid polynomial", point(9))?;
+    transcript.append_point(ops, b"Quotient high polynomial", point(10))?;
+    let zeta = transcript.challenge(ops, b"zeta")?;
+
+    let labels: [&[u8]; NR_EVALUATIONS] = [
+        b"Append a_eval.",
+        b"Append b_eval.",
+        b"Append c_eval.",
+        b"Append s_sig1.",
+        b"Append s_sig2.",
+        b"Append z_omega.",
+        b"Append f_eval.",
+        b"Append qt_eval.",
+        b"Append table_eval.",
+        b"Append h2_eval.",
+        b"Append table_omega.",
+        b"Append h1_omega.",
+        b"Append z_lookup_omega.",
+        b"Append a_omega.",
+    ];
+    for (label, evaluation) in labels.into_iter().zip(evaluations) {
+        transcript.append_scalar(ops, label, *evaluation)?;
+    }
+    let v = transcript.challenge(ops, b"v")?;
+
+    transcript.append_point(ops, b"w_omega comm", point(11))?;
+    transcript.append_point(ops, b"w_omega_zeta comm", point(12))?;
+    let u = transcript.challenge(ops, b"u")?;
+
+    let [a_eval, b_eval, c_eval, s_sig1, s_sig2, z_omega, f_eval, qt_eval, table_eval, h2_eval, table_omega, h1_omega, z_lookup_omega, a_omega] =
+        core::array::from_fn(|index| Combination::value(evaluations[index]));
+
+    // zeta^n, by squaring, as n is a power of two.
+    let domain = &verifier_key.domain;
+    let mut zeta_n = zeta.clone();
+    for _ in 0..domain.size.trailing_zeros() {
+        zeta_n = mul(ops, &zeta_n, &zeta_n)?;
+    }
+    let zero_poly_eval = linear(&[(&zeta_n, Scalar::one())], -Scalar::one());
+
+    // L_i(zeta) = zh / (n * omega^-i * zeta - n).
+    let size = Scalar::from(domain.size as u64);
+    let zh = zero_poly_eval.clone().evaluate(ops)?;
+    let zeta_value = zeta.clone().evaluate(ops)?;
+    let lagrange = |ops: &mut A, row: usize| {
+        let root_inv = domain.generator_inv.pow_vartime([row as u64]);
+        Ok::<_, PlonkError>(Combination::value(ops.divide(
+            zh,
+            (zeta_value, size * root_inv),
+            -size,
+        )?))
+    };
+
+    let mut pi_eval = Combination::constant(Scalar::zero());
+    for (input, row) in pub_in.iter().zip(verifier_key.public_inputs.iter()) {
+        let lagrange_eval = lagrange(ops, *row)?;
+        let term = mul(ops, &Combination::value(*input), &lagrange_eval)?;
+        pi_eval.add(&term, -Scalar::one());
+    }
+    let l0_eval = lagrange(ops, 0)?;
+
+    let alpha_powers = powers(ops, &alpha, 5)?;
+    let v_powers = powers(ops, &v, 9)?;
+    let v_power = |exponent: usize| &v_powers[exponent - 1];
+
+    let one_plus_delta = linear(&[(&delta, Scalar::one())], Scalar::one());
+    let epsilon_one_plus_delta = mul(ops, &epsilon, &one_plus_delta)?;
+    let delta_h1_omega = mul(ops, &delta, &h1_omega)?;
+    let h1_factor = linear(
+        &[
+            (&epsilon_one_plus_delta, Scalar::one()),
+            (&h2_eval, Scalar::one()),
+            (&delta_h1_omega, Scalar::one()),
+        ],
+        Scalar::zero(),
+    );
+    let h1_factor = mul(ops, &h1_factor, &z_lookup_omega)?;
+    let h1_coefficient = mul(ops, &h1_factor, &alpha_powers[4])?;
+
+    // Factors of the permutation argument, a + beta * s_sig1 + gamma and b + beta * s_sig2 +
+    // gamma.
+    let beta_s_sig1 = mul(ops, &beta, &s_sig1)?;
+    let beta_s_sig2 = mul(ops, &beta, &s_sig2)?;
+    let sigma1 = linear(
+        &[
+            (&a_eval, Scalar::one()),
+            (&beta_s_sig1, Scalar::one()),
+            (&gamma, Scalar::one()),
+        ],
+        Scalar::zero(),
+    );
+    let sigma2 = linear(
+        &[
+            (&b_eval, Scalar::one()),
+            (&beta_s_sig2, Scalar::one()),
+            (&gamma, Scalar::one()),
+        ],
+        Scalar::zero(),
+    );
+    let sigma12 = mul(ops, &sigma1, &sigma2)?;
+    let alpha_z_omega = mul(ops, &alpha, &z_omega)?;
+    let sigma_alpha_z_omega = mul(ops, &sigma12, &alpha_z_omega)?;
+
+    let l0_alpha2 = mul(ops, &l0_eval, &alpha_powers[1])?;
+    let l0_alpha4 = mul(ops, &l0_eval, &alpha_powers[3])?;
+    let c_gamma = linear(
+        &[(&c_eval, Scalar::one()), (&gamma, Scalar::one())],
+        Scalar::zero(),
+    );
+    let permutation_constant = mul(ops, &sigma_alpha_z_omega, &c_gamma)?;
+    let delta_h2 = mul(ops, &delta, &h2_eval)?;
+    let lookup_constant = linear(
+        &[
+            (&epsilon_one_plus_delta, Scalar::one()),
+            (&delta_h2, Scalar::one()),
+        ],
+        Scalar::zero(),
+    );
+    let lookup_constant = mul(ops, &lookup_constant, &h1_coefficient)?;
+    let r0 = linear(
+        &[
+            (&pi_eval, Scalar::one()),
+            (&l0_alpha2, -Scalar::one()),
+            (&permutation_constant, -Scalar::one()),
+            (&l0_alpha4, -Scalar::one()),
+            (&lookup_constant, -Scalar::one()),
+        ],
+        Scalar::zero(),
+    );
+
+    let s_sig3_coefficient = mul(ops, &sigma_alpha_z_omega, &beta)?;
+    let s_sig3_coefficient = scale(&s_sig3_coefficient, -Scalar::one());
+
+    let beta_zeta = mul(ops, &beta, &zeta)?;
+    let mut z_coefficient = alpha.clone();
+    for (wire, k) in [(&a_eval, Scalar::one()), (&b_eval, K1()), (&c_eval, K2())] {
+        let factor = linear(
+            &[
+                (wire, Scalar::one()),
+                (&beta_zeta, k),
+                (&gamma, Scalar::one()),
+            ],
+            Scalar::zero(),
+        );
+        z_coefficient = mul(ops, &z_coefficient, &factor)?;
+    }
+    let z_coefficient = linear(
+        &[
+            (&z_coefficient, Scalar::one()),
+            (&l0_alpha2, Scalar::one()),
+            (&u, Scalar::one()),
+        ],
+        Scalar::zero(),
+    );
+
+    let epsilon_f = linear(
+        &[(&epsilon, Scalar::one()), (&f_eval, Scalar::one())],
+        Scalar::zero(),
+    );
+    let delta_table_omega = mul(ops, &delta, &table_omega)?;
+    let table_factor = linear(
+        &[
+            (&epsilon_one_plus_delta, Scalar::one()),
+            (&table_eval, Scalar::one()),
+            (&delta_table_omega, Scalar::one()),
+        ],
+        Scalar::zero(),
+    );
+    let z_lookup_coefficient = mul(ops, &one_plus_delta, &epsilon_f)?;
+    let z_lookup_coefficient = mul(ops, &z_lookup_coefficient, &table_factor)?;
+    let z_lookup_coefficient = mul(ops, &z_lookup_coefficient, &alpha_powers[4])?;
+    let u_v = mul(ops, &u, &v)?;
+    let z_lookup_coefficient = linear(
+        &[
+            (&z_lookup_coefficient, Scalar::one()),
+            (&l0_alpha4, Scalar::one()),
+            (&u_v, Scalar::one()),
+        ],
+        Scalar::zero(),
+    );
+
+    // compress([a, b, c, qt], eta) = a + eta * (b + eta * (c + eta * qt)).
+    let mut compressed = qt_eval.clone();
+    for eval in [&c_eval, &b_eval, &a_eval] {
+        compressed = mul(ops, &compressed, &eta)?;
+        compressed.add(eval, Scalar::one());
+    }
+    let qk_coefficient = linear(
+        &[(&compressed, Scalar::one()), (&f_eval, -Scalar::one())],
+        Scalar::zero(),
+    );
+    let qk_coefficient = mul(ops, &qk_coefficient, &alpha_powers[2])?;
+
+    let u_v2 = mul(ops, &u, v_power(2))?;
+    let table_coefficient = linear(
+        &[(v_power(7), Scalar::one()), (&u_v2, Scalar::one())],
+        Scalar::zero(),
+    );
+    let eta_powers = powers(ops, &eta, 3)?;
+    let mut table_coefficients = vec![table_coefficient.clone()];
+    for eta_power in eta_powers.iter() {
+        table_coefficients.push(mul(ops, &table_coefficient, eta_power)?);
+    }
+
+    let u_v3 = mul(ops, &u, v_power(3))?;
+    let u_v4 = mul(ops, &u, v_power(4))?;
+    let zero_poly_zeta_n = mul(ops, &zero_poly_eval, &zeta_n)?;
+    let zero_poly_zeta_2n = mul(ops, &zero_poly_zeta_n, &zeta_n)?;
+    let u_zeta = mul(ops, &u, &zeta)?;
+
+    // Batched evaluation, encoded on g1.
+    let mut batch_eval = s_sig2.clone();
+    for eval in [&s_sig1, &c_eval, &b_eval, &a_eval] {
+        batch_eval = mul(ops, &batch_eval, &v)?;
+        batch_eval.add(eval, Scalar::one());
+    }
+    let batch_eval = mul(ops, &batch_eval, &v)?;
+    let mut batch_eval_omega = a_omega.clone();
+    for eval in [&h1_omega, &table_omega, &z_lookup_omega, &z_omega] {
+        batch_eval_omega = mul(ops, &batch_eval_omega, &v)?;
+        batch_eval_omega.add(eval, Scalar::one());
+    }
+    let batch_eval_omega = mul(ops, &batch_eval_omega, &u)?;
+    let mut g1_coefficient = linear(
+        &[
+            (&r0, Scalar::one()),
+            (&batch_eval, -Scalar::one()),
+            (&batch_eval_omega, -Scalar::one()),
+        ],
+        Scalar::zero(),
+    );
+    for (exponent, eval) in [(6, &f_eval), (7, &table_eval), (8, &h2_eval), (9, &qt_eval)] {
+        let term = mul(ops, v_power(exponent), eval)?;
+        g1_coefficient.add(&term, -Scalar::one());
+    }
+
+    let a_b = mul(ops, &a_eval, &b_eval)?;
+    let coefficients = [
+        a_b,
+        a_eval.clone(),
+        b_eval.clone(),
+        c_eval.clone(),
+        one(),
+        v_power(4).clone(),
+        v_power(5).clone(),
+        s_sig3_coefficient,
+        qk_coefficient,
+        table_coefficients[0].clone(),
+        table_coefficients[1].clone(),
+        table_coefficients[2].clone(),
+        table_coefficients[3].clone(),
+        v_power(9).clone(),
+        a_omega.clone(),
+        linear(
+            &[(&v, Scalar::one()), (&u_v4, Scalar::one())],
+            Scalar::zero(),
+        ),
+        v_power(2).clone(),
+        v_power(3).clone(),
+        v_power(6).clone(),
+        linear(
+            &[(&u_v3, Scalar::one()), (&h1_coefficient, -Scalar::one())],
+            Scalar::zero(),
+        ),
+        v_power(8).clone(),
+        z_coefficient,
+        z_lookup_coefficient,
+        scale(&zero_poly_eval, -Scalar::one()),
+        scale(&zero_poly_zeta_n, -Scalar::one()),
+        scale(&zero_poly_zeta_2n, -Scalar::one()),
+        zeta,
+        scale(&u_zeta, domain.generator),
+        g1_coefficient,
+    ];
+
+    core::iter::once(u)
+        .chain(coefficients)
+        .map(|coefficient| coefficient.evaluate(ops))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::Prover;
+    use crate::transcript::{PoseidonTranscript, Transcript};
+    use crate::verifier::PlonkVerifier;
+    use crate::witness::WitnessBuilder;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_proof_verification() {
+        // Inner circuit: x * x = y, with y public.
+        let mut inner = PlonkCircuit::init();
+        let y = inner.public_input();
+        let (x_l, x_r, square) = inner.mult_gate();
+        inner.connect(x_l, x_r).unwrap();
+        inner.connect(square, y).unwrap();
+        inner.pad_next_power_of_two();
+        let (prover_key, verifier_key) = inner.setup(&mut OsRng).unwrap();
+        let mut builder = WitnessBuilder::new(&inner);
+        builder.assign(x_l, Scalar::from(3)).unwrap();
+        let trace = builder.build().unwrap();
+        let pub_in = vec![Scalar::from(9)];
+        let mut transcript = PoseidonTranscript::new(b"inner");
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut transcript).unwrap();
+
+        let mut transcript = PoseidonTranscript::new(b"inner");
+        let expected =
+            PlonkVerifier::deferred_check(&pub_in, &verifier_key, &proof, &mut transcript).unwrap();
+        assert!(expected.verify(&verifier_key, &proof).is_ok());
+
+        let values = proof_inputs(&proof, &pub_in);
+        let mut outer = PlonkCircuit::init();
+        let inputs = values
+            .iter()
+            .map(|_| outer.public_input())
+            .collect::<Vec<_>>();
+        assert!(proof_verification(&mut outer, &verifier_key, b"inner", &inputs[1..]).is_err());
+        let gadget = proof_verification(&mut outer, &verifier_key, b"inner", &inputs).unwrap();
+        assert_eq!(gadget.outputs().len(), 30);
+
+        let assign = |values: &[Scalar]| {
+            let mut trace = ComputationTrace::new(&outer);
+            for (wire, value) in inputs.iter().zip(values) {
+                trace.set(*wire, *value);
+            }
+            let check = gadget.assign(&mut trace, values);
+            (trace, check)
+        };
+        let (trace, check) = assign(&values);
+        assert!(outer.is_satisfied(&trace).is_ok());
+        assert_eq!(check, expected);
+        assert_eq!(trace.value(gadget.outputs()[0]), expected.u);
+
+        // Another public input changes the challenges, and the check no longer passes.
+        let mut wrong_values = values;
+        wrong_values[0] = Scalar::from(4);
+        let (trace, check) = assign(&wrong_values);
+        assert!(outer.is_satisfied(&trace).is_ok());
+        assert!(check.verify(&verifier_key, &proof).is_err());
+    }
+}
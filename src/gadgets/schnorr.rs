@@ -0,0 +1,170 @@
+// Schnorr signatures over Jubjub, with the Poseidon hash as challenge, and their verification
+// in the circuit. A signature of a message m (a scalar) under the public key P = x * G is a
+// pair (R, s) with R = k * G for a random k, and s = k + e * x modulo the order of G, where
+// e = Poseidon(R_u, R_v, P_u, P_v, m). It is valid if s * G = R + e * P.
+//
+// The circuit checks that R and P are points of the curve, but not that they belong to the
+// subgroup of prime order: this is left to whoever provides the public key. The challenge is
+// multiplied with its 255 bits, which must be its canonical ones (otherwise they could be
+// those of e + p), and s is constrained to fit in 252 bits, as the order of G.
+// The product s * G uses the fixed-base multiplication, and verifying a signature uses around
+// 10k gates.
+use super::arithmetic::{decompose, decompose_canonical, Arithmetic, Assigner, Builder};
+use super::ecc::{add, assert_on_curve, fixed_base_mul, mul, Point};
+use super::poseidon::sponge;
+use crate::error::PlonkError;
+use crate::jubjub::{JubjubPoint, JubjubScalar};
+use crate::plonk::{ComputationTrace, PlonkCircuit, Wire};
+use crate::poseidon::Poseidon;
+use blstrs::Scalar;
+use rand_core::RngCore;
+
+/// Bits of the response s, as the order of the subgroup is smaller than 2^252.
+const RESPONSE_BITS: usize = 252;
+
+/// Schnorr signature.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Signature {
+    pub r: JubjubPoint,
+    pub s: JubjubScalar,
+}
+
+/// Public key of the secret key `secret`.
+pub fn public_key(secret: &JubjubScalar) -> JubjubPoint {
+    JubjubPoint::generator().mul(&secret.to_bytes())
+}
+
+fn challenge(r: &JubjubPoint, public_key: &JubjubPoint, message: &Scalar) -> Scalar {
+    Poseidon::new().hash(&[r.u, r.v, public_key.u, public_key.v, *message])
+}
+
+/// Signs `message` with the secret key `secret`.
+pub fn sign(secret: &JubjubScalar, message: &Scalar, rng: impl RngCore) -> Signature {
+    let nonce = JubjubScalar::random(rng);
+    let r = JubjubPoint::generator().mul(&nonce.to_bytes());
+    let e = JubjubScalar::from_scalar(&challenge(&r, &public_key(secret), message));
+    Signature {
+        r,
+        s: nonce.add(&e.mul(secret)),
+    }
+}
+
+/// Native verification of `signature`, with the same equation as the circuit.
+pub fn verify(public_key: &JubjubPoint, message: &Scalar, signature: &Signature) -> bool {
+    let e = challenge(&signature.r, public_key, message);
+    JubjubPoint::generator().mul(&signature.s.to_bytes())
+        == signature.r.add(&public_key.mul(&e.to_bytes_le()))
+}
+
+/// Wires created by `schnorr_verification`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct SchnorrVerification {
+    rows: Vec<usize>,
+}
+
+impl SchnorrVerification {
+    /// Assigns the rows of the gadget for the verification of `signature`. The trace must
+    /// already contain the inputs, as the gadget only assigns its own gates.
+    pub fn assign(
+        &self,
+        trace: &mut ComputationTrace,
+        public_key: &JubjubPoint,
+        message: &Scalar,
+        signature: &Signature,
+    ) {
+        let mut assigner = Assigner::new(trace, &self.rows);
+        verification(
+            &mut assigner,
+            (*public_key).into(),
+            *message,
+            signature.r.into(),
+            signature.s.to_scalar(),
+        )
+        .expect("Assigning the trace cannot fail");
+    }
+}
+
+/// Constrains (`r`, `s`) to be a valid signature of `message` under `public_key`.
+pub fn schnorr_verification(
+    circuit: &mut PlonkCircuit,
+    public_key: Point<Wire>,
+    message: Wire,
+    r: Point<Wire>,
+    s: Wire,
+) -> Result<SchnorrVerification, PlonkError> {
+    let mut builder = Builder::new(circuit);
+    verification(&mut builder, public_key, message, r, s)?;
+    Ok(SchnorrVerification { rows: builder.rows })
+}
+
+fn verification<A: Arithmetic>(
+    ops: &mut A,
+    public_key: Point<A::Value>,
+    message: A::Value,
+    r: Point<A::Value>,
+    s: A::Value,
+) -> Result<(), PlonkError> {
+    assert_on_curve(ops, public_key)?;
+    assert_on_curve(ops, r)?;
+
+    let e = sponge(ops, &[r.u, r.v, public_key.u, public_key.v, message])?;
+    let e_bits = decompose_canonical(ops, e)?;
+    let s_bits = decompose(ops, s, RESPONSE_BITS)?;
+
+    let left = fixed_base_mul(ops, &s_bits, &JubjubPoint::generator())?;
+    let e_public_key = mul(ops, &e_bits, public_key)?;
+    let right = add(ops, r, e_public_key)?;
+
+    ops.assert_equal(left.u, right.u)?;
+    ops.assert_equal(left.v, right.v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_schnorr_verification() {
+        let secret = JubjubScalar::random(OsRng);
+        let public = public_key(&secret);
+        let message = Scalar::from(42);
+        let signature = sign(&secret, &message, OsRng);
+        assert!(verify(&public, &message, &signature));
+        assert!(!verify(&public, &Scalar::from(43), &signature));
+
+        let mut circuit = PlonkCircuit::init();
+        let (public_u, public_v, _) = circuit.add_gate();
+        let (r_u, r_v, _) = circuit.add_gate();
+        let (message_wire, s_wire, _) = circuit.add_gate();
+        let gadget = schnorr_verification(
+            &mut circuit,
+            Point {
+                u: public_u,
+                v: public_v,
+            },
+            message_wire,
+            Point { u: r_u, v: r_v },
+            s_wire,
+        )
+        .unwrap();
+
+        let assign = |message: Scalar| {
+            let mut trace = ComputationTrace::new(&circuit);
+            let inputs = [
+                (public.u, public.v),
+                (signature.r.u, signature.r.v),
+                (message, signature.s.to_scalar()),
+            ];
+            for (row, (left, right)) in inputs.into_iter().enumerate() {
+                trace.set(Wire::left(row), left);
+                trace.set(Wire::right(row), right);
+                trace.set(Wire::output(row), left + right);
+            }
+            gadget.assign(&mut trace, &public, &message, &signature);
+            trace
+        };
+        assert!(circuit.is_satisfied(&assign(message)).is_ok());
+        assert!(circuit.is_satisfied(&assign(Scalar::from(43))).is_err());
+    }
+}
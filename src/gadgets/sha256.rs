@@ -0,0 +1,495 @@
+// SHA-256 in the circuit, written over the `Arithmetic` backend. Words are decomposed into
+// boolean-constrained bits, and kept both as their bits and as their value. Rotations and
+// shifts only reorder the bits, XOR uses a gate per bit, a + b - 2ab, and the additions
+// modulo 2^32 are linear combinations of values, which are decomposed again to drop the
+// carry. The choice and majority functions are linear combinations of products of bits:
+//
+// Ch(e, f, g) = e * (f - g) + g, and Maj(a, b, c) = b * c + a * (b XOR c).
+//
+// A compression uses around 50k gates.
+use super::arithmetic::{decompose, Arithmetic, Assigner, Builder, Combination};
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::Field;
+
+/// Initial state of SHA-256.
+pub const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Pads `message` as specified by SHA-256 (a one bit, zeroes, and the length in bits), and
+/// splits it into blocks of sixteen big-endian words.
+pub fn pad_message(message: &[u8]) -> Vec<[u32; 16]> {
+    let mut bytes = message.to_vec();
+    bytes.push(0x80);
+    while bytes.len() % 64 != 56 {
+        bytes.push(0);
+    }
+    bytes.extend_from_slice(&((message.len() as u64) * 8).to_be_bytes());
+
+    bytes
+        .chunks(64)
+        .map(|chunk| {
+            let mut block = [0u32; 16];
+            for (word, bytes) in block.iter_mut().zip(chunk.chunks(4)) {
+                *word = u32::from_be_bytes(bytes.try_into().expect("Chunks have four bytes"));
+            }
+            block
+        })
+        .collect()
+}
+
+/// Native compression function, which updates `state` with `block`.
+pub fn compress(state: &mut [u32; 8], block: &[u32; 16]) {
+    let mut schedule = [0u32; 64];
+    schedule[..16].copy_from_slice(block);
+    for t in 16..64 {
+        let w15 = schedule[t - 15];
+        let w2 = schedule[t - 2];
+        let s0 = w15.rotate_right(7) ^ w15.rotate_right(18) ^ (w15 >> 3);
+        let s1 = w2.rotate_right(17) ^ w2.rotate_right(19) ^ (w2 >> 10);
+        schedule[t] = s1
+            .wrapping_add(schedule[t - 7])
+            .wrapping_add(s0)
+            .wrapping_add(schedule[t - 16]);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for t in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[t])
+            .wrapping_add(schedule[t]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (word, new) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(new);
+    }
+}
+
+/// Native SHA-256 of `message`.
+pub fn hash(message: &[u8]) -> [u8; 32] {
+    let mut state = IV;
+    for block in pad_message(message).iter() {
+        compress(&mut state, block);
+    }
+
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(state.iter()) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Wires created by `sha256` or `sha256_compression`, used to fill their rows in the trace.
+#[derive(Clone, Debug)]
+pub struct Sha256 {
+    output: [Wire; 8],
+    from_iv: bool,
+    nr_blocks: usize,
+    rows: Vec<usize>, // rows of the gates, in the order in which they were created
+}
+
+impl Sha256 {
+    /// Wires that carry the state after the last block, as 32-bit words.
+    pub fn output(&self) -> [Wire; 8] {
+        self.output
+    }
+
+    /// Assigns the rows of the gadget for the compression of `blocks` starting from `state`,
+    /// and returns the resulting state. For a gadget created by `sha256`, `state` must be
+    /// `IV`. The trace must already contain the inputs, as the gadget only assigns its own
+    /// gates.
+    pub fn assign(
+        &self,
+        trace: &mut ComputationTrace,
+        state: &[u32; 8],
+        blocks: &[[u32; 16]],
+    ) -> [u32; 8] {
+        assert_eq!(blocks.len(), self.nr_blocks, "Wrong number of blocks");
+        assert!(!self.from_iv || *state == IV, "The gadget starts from IV");
+
+        let to_scalars = |words: &[u32]| -> Vec<Scalar> {
+            words
+                .iter()
+                .map(|word| Scalar::from(*word as u64))
+                .collect()
+        };
+        let state = to_scalars(state);
+        let blocks: Vec<Vec<Scalar>> = blocks.iter().map(|block| to_scalars(block)).collect();
+
+        let mut assigner = Assigner::new(trace, &self.rows);
+        let output = chain(
+            &mut assigner,
+            (!self.from_iv).then_some(&state[..]),
+            &blocks,
+        )
+        .expect("Assigning the trace cannot fail");
+
+        let mut result = [0u32; 8];
+        for (word, value) in result.iter_mut().zip(output.iter()) {
+            let bytes = value.to_bytes_le();
+            *word = u32::from_le_bytes(bytes[..4].try_into().expect("Words have four bytes"));
+        }
+        result
+    }
+}
+
+/// Constrains the output wires of the returned gadget to be the SHA-256 state after
+/// absorbing `blocks`, starting from `IV`. The blocks are words assumed to be already padded
+/// as by `pad_message`, and each of them is constrained to be smaller than 2^32.
+pub fn sha256(circuit: &mut PlonkCircuit, blocks: &[[Wire; 16]]) -> Result<Sha256, PlonkError> {
+    let blocks: Vec<Vec<Wire>> = blocks.iter().map(|block| block.to_vec()).collect();
+    let mut builder = Builder::new(circuit);
+    let output = chain(&mut builder, None, &blocks)?;
+
+    Ok(Sha256 {
+        output: output.try_into().expect("The state has eight words"),
+        from_iv: true,
+        nr_blocks: blocks.len(),
+        rows: builder.rows,
+    })
+}
+
+/// Constrains the output wires of the returned gadget to be the compression of `block`
+/// starting from `state`. Every input is constrained to be smaller than 2^32.
+pub fn sha256_compression(
+    circuit: &mut PlonkCircuit,
+    state: &[Wire; 8],
+    block: &[Wire; 16],
+) -> Result<Sha256, PlonkError> {
+    let mut builder = Builder::new(circuit);
+    let output = chain(&mut builder, Some(&state[..]), &[block.to_vec()])?;
+
+    Ok(Sha256 {
+        output: output.try_into().expect("The state has eight words"),
+        from_iv: false,
+        nr_blocks: 1,
+        rows: builder.rows,
+    })
+}
+
+// 32-bit word, with its little-endian bits.
+#[derive(Clone)]
+struct Word<V> {
+    bits: Vec<V>,
+    value: Combination<V>,
+}
+
+fn word<A: Arithmetic>(ops: &mut A, x: A::Value) -> Result<Word<A::Value>, PlonkError> {
+    Ok(Word {
+        bits: decompose(ops, x, 32)?,
+        value: Combination::value(x),
+    })
+}
+
+// Reduction modulo 2^32 of `sum`, which is smaller than 2^(32 + carry_bits).
+fn reduce_modulo<A: Arithmetic>(
+    ops: &mut A,
+    sum: Combination<A::Value>,
+    carry_bits: usize,
+) -> Result<Word<A::Value>, PlonkError> {
+    let sum = sum.evaluate(ops)?;
+    let mut bits = decompose(ops, sum, 32 + carry_bits)?;
+    let carry = bits.split_off(32);
+
+    let mut value = Combination::value(sum);
+    let mut power = Scalar::from(1u64 << 32);
+    for bit in carry {
+        value.terms.push((bit, -power));
+        power = power.double();
+    }
+    Ok(Word { bits, value })
+}
+
+fn rotate<V: Copy>(bits: &[V], n: usize) -> Vec<Option<V>> {
+    (0..32).map(|i| Some(bits[(i + n) % 32])).collect()
+}
+
+fn shift<V: Copy>(bits: &[V], n: usize) -> Vec<Option<V>> {
+    (0..32).map(|i| bits.get(i + n).copied()).collect()
+}
+
+// Bits set to `None` are zero.
+fn xor<A: Arithmetic>(
+    ops: &mut A,
+    x: Option<A::Value>,
+    y: Option<A::Value>,
+) -> Result<Option<A::Value>, PlonkError> {
+    match (x, y) {
+        (Some(x), Some(y)) => Ok(Some(ops.gate(
+            Gate::new().left(1).right(1).mul(-2),
+            x,
+            y,
+        )?)),
+        (x, None) | (None, x) => Ok(x),
+    }
+}
+
+// Value of the bitwise XOR of three words.
+fn xor3<A: Arithmetic>(
+    ops: &mut A,
+    x: &[Option<A::Value>],
+    y: &[Option<A::Value>],
+    z: &[Option<A::Value>],
+) -> Result<Combination<A::Value>, PlonkError> {
+    let mut result = Combination::constant(Scalar::zero());
+    let mut power = Scalar::one();
+    for i in 0..32 {
+        let xy = xor(ops, x[i], y[i])?;
+        if let Some(bit) = xor(ops, xy, z[i])? {
+            result.terms.push((bit, power));
+        }
+        power = power.double();
+    }
+    Ok(result)
+}
+
+fn ch<A: Arithmetic>(
+    ops: &mut A,
+    e: &Word<A::Value>,
+    f: &Word<A::Value>,
+    g: &Word<A::Value>,
+) -> Result<Combination<A::Value>, PlonkError> {
+    let mut result = g.value.clone();
+    let mut power = Scalar::one();
+    for i in 0..32 {
+        let difference = ops.gate(Gate::new().left(1).right(-1), f.bits[i], g.bits[i])?;
+        let product = ops.gate(Gate::new().mul(1), e.bits[i], difference)?;
+        result.terms.push((product, power));
+        power = power.double();
+    }
+    Ok(result)
+}
+
+fn maj<A: Arithmetic>(
+    ops: &mut A,
+    a: &Word<A::Value>,
+    b: &Word<A::Value>,
+    c: &Word<A::Value>,
+) -> Result<Combination<A::Value>, PlonkError> {
+    let mut result = Combination::constant(Scalar::zero());
+    let mut power = Scalar::one();
+    for i in 0..32 {
+        let bc = ops.gate(Gate::new().mul(1), b.bits[i], c.bits[i])?;
+        let b_xor_c = xor(ops, Some(b.bits[i]), Some(c.bits[i]))?.expect("Both bits are set");
+        let product = ops.gate(Gate::new().mul(1), a.bits[i], b_xor_c)?;
+        result.terms.push((bc, power));
+        result.terms.push((product, power));
+        power = power.double();
+    }
+    Ok(result)
+}
+
+fn compression<A: Arithmetic>(
+    ops: &mut A,
+    state: &[Word<A::Value>],
+    block: &[A::Value],
+) -> Result<Vec<Word<A::Value>>, PlonkError> {
+    let mut schedule = block
+        .iter()
+        .map(|x| word(ops, *x))
+        .collect::<Result<Vec<_>, _>>()?;
+    for t in 16..64 {
+        let w15 = &schedule[t - 15].bits;
+        let mut sum = xor3(ops, &rotate(w15, 7), &rotate(w15, 18), &shift(w15, 3))?;
+        let w2 = &schedule[t - 2].bits;
+        let s1 = xor3(ops, &rotate(w2, 17), &rotate(w2, 19), &shift(w2, 10))?;
+        sum.add(&s1, Scalar::one());
+        sum.add(&schedule[t - 7].value, Scalar::one());
+        sum.add(&schedule[t - 16].value, Scalar::one());
+        let new_word = reduce_modulo(ops, sum, 2)?;
+        schedule.push(new_word);
+    }
+
+    let mut working = state.to_vec();
+    for (t, w) in schedule.iter().enumerate() {
+        let (a, e) = (&working[0], &working[4]);
+
+        let mut t1 = working[7].value.clone();
+        let s1 = xor3(
+            ops,
+            &rotate(&e.bits, 6),
+            &rotate(&e.bits, 11),
+            &rotate(&e.bits, 25),
+        )?;
+        t1.add(&s1, Scalar::one());
+        t1.add(&ch(ops, e, &working[5], &working[6])?, Scalar::one());
+        t1.add(&w.value, Scalar::one());
+        t1.constant += Scalar::from(K[t] as u64);
+        // T1 is used twice, so that it is cheaper to reduce it to a single wire first.
+        let t1 = Combination::value(t1.evaluate(ops)?);
+
+        let mut t2 = xor3(
+            ops,
+            &rotate(&a.bits, 2),
+            &rotate(&a.bits, 13),
+            &rotate(&a.bits, 22),
+        )?;
+        t2.add(&maj(ops, a, &working[1], &working[2])?, Scalar::one());
+
+        let mut new_e = working[3].value.clone();
+        new_e.add(&t1, Scalar::one());
+        let mut new_a = t1;
+        new_a.add(&t2, Scalar::one());
+
+        let new_e = reduce_modulo(ops, new_e, 3)?;
+        let new_a = reduce_modulo(ops, new_a, 3)?;
+        working.rotate_right(1);
+        working[0] = new_a;
+        working[4] = new_e;
+    }
+
+    state
+        .iter()
+        .zip(working.iter())
+        .map(|(initial, new)| {
+            let mut sum = initial.value.clone();
+            sum.add(&new.value, Scalar::one());
+            reduce_modulo(ops, sum, 1)
+        })
+        .collect()
+}
+
+// Compression of every block, starting from `state`, or from `IV` if it is not given.
+fn chain<A: Arithmetic>(
+    ops: &mut A,
+    state: Option<&[A::Value]>,
+    blocks: &[Vec<A::Value>],
+) -> Result<Vec<A::Value>, PlonkError> {
+    let initial = match state {
+        Some(state) => state.to_vec(),
+        None => IV
+            .iter()
+            .map(|word| ops.constant(Scalar::from(*word as u64)))
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+    let mut state = initial
+        .iter()
+        .map(|x| word(ops, *x))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for block in blocks.iter() {
+        state = compression(ops, &state, block)?;
+    }
+
+    state
+        .into_iter()
+        .map(|word| word.value.evaluate(ops))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_native_hash() {
+        let vectors: [(&[u8], &str); 3] = [
+            (
+                b"",
+                "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            ),
+            (
+                b"abc",
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+        ];
+        for (message, expected) in vectors {
+            let digest: String = hash(message).iter().map(|b| format!("{:02x}", b)).collect();
+            assert_eq!(digest, expected);
+        }
+        assert_eq!(pad_message(&[0u8; 55]).len(), 1);
+        assert_eq!(pad_message(&[0u8; 56]).len(), 2);
+    }
+
+    #[test]
+    fn test_sha256_gadget() {
+        for message in [
+            &b"abc"[..],
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+        ] {
+            let blocks = pad_message(message);
+            let mut circuit = PlonkCircuit::init();
+            let block_wires: Vec<[Wire; 16]> = blocks
+                .iter()
+                .map(|_| [(); 16].map(|_| circuit.add_gate().0))
+                .collect();
+            let gadget = sha256(&mut circuit, &block_wires).unwrap();
+
+            let mut trace = ComputationTrace::new(&circuit);
+            for (wires, block) in block_wires.iter().zip(blocks.iter()) {
+                for (wire, word) in wires.iter().zip(block.iter()) {
+                    trace.set(*wire, Scalar::from(*word as u64));
+                    trace.set(Wire::output(wire.row), Scalar::from(*word as u64));
+                }
+            }
+            let state = gadget.assign(&mut trace, &IV, &blocks);
+            assert!(circuit.is_satisfied(&trace).is_ok());
+
+            let mut native = IV;
+            for block in blocks.iter() {
+                compress(&mut native, block);
+            }
+            assert_eq!(state, native);
+            for (wire, word) in gadget.output().iter().zip(state.iter()) {
+                assert_eq!(trace.c[wire.row], Scalar::from(*word as u64));
+            }
+        }
+
+        // A single compression from an arbitrary state.
+        let mut circuit = PlonkCircuit::init();
+        let state_wires = [(); 8].map(|_| circuit.add_gate().0);
+        let block_wires = [(); 16].map(|_| circuit.add_gate().0);
+        let gadget = sha256_compression(&mut circuit, &state_wires, &block_wires).unwrap();
+
+        let state = [7u32; 8];
+        let block = [u32::MAX; 16];
+        let mut trace = ComputationTrace::new(&circuit);
+        for (wire, word) in state_wires
+            .iter()
+            .chain(block_wires.iter())
+            .zip(state.iter().chain(block.iter()))
+        {
+            trace.set(*wire, Scalar::from(*word as u64));
+            trace.set(Wire::output(wire.row), Scalar::from(*word as u64));
+        }
+        let output = gadget.assign(&mut trace, &state, &[block]);
+        assert!(circuit.is_satisfied(&trace).is_ok());
+
+        let mut native = state;
+        compress(&mut native, &block);
+        assert_eq!(output, native);
+    }
+}
@@ -0,0 +1,120 @@
+// Subcircuits are circuits built once, with the wires of their inputs and outputs, that are
+// appended to a parent circuit each time they are used (see `PlonkCircuit::append`). The rows
+// of an instance are those of the subcircuit shifted after the rows of the parent, and its
+// inputs are connected to the wires given to `synthesize`.
+use super::Gadget;
+use crate::error::PlonkError;
+use crate::plonk::{PlonkCircuit, Wire};
+
+/// Circuit with an interface of input and output wires, as a `Gadget`.
+#[derive(Clone)]
+pub struct Subcircuit {
+    circuit: PlonkCircuit,
+    inputs: Vec<Wire>,
+    outputs: Vec<Wire>,
+}
+
+impl Subcircuit {
+    /// Creates a subcircuit from `circuit`, whose inputs and outputs are wires of its gates.
+    pub fn new(
+        circuit: PlonkCircuit,
+        inputs: Vec<Wire>,
+        outputs: Vec<Wire>,
+    ) -> Result<Self, PlonkError> {
+        if let Some(wire) = inputs
+            .iter()
+            .chain(outputs.iter())
+            .find(|wire| !circuit.permutations.contains_key(wire))
+        {
+            return Err(PlonkError::UnknownWire(*wire));
+        }
+        Ok(Subcircuit {
+            circuit,
+            inputs,
+            outputs,
+        })
+    }
+
+    /// Circuit of a single instance.
+    pub fn circuit(&self) -> &PlonkCircuit {
+        &self.circuit
+    }
+}
+
+impl Gadget for Subcircuit {
+    fn synthesize(
+        &self,
+        circuit: &mut PlonkCircuit,
+        inputs: &[Wire],
+    ) -> Result<Vec<Wire>, PlonkError> {
+        if inputs.len() != self.inputs.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: self.inputs.len(),
+                got: inputs.len(),
+            });
+        }
+        let offset = circuit.append(&self.circuit);
+        let shift = |wire: &Wire| Wire {
+            row: wire.row + offset,
+            column: wire.column,
+        };
+        for (input, wire) in inputs.iter().zip(self.inputs.iter()) {
+            circuit.connect(*input, shift(wire))?;
+        }
+        Ok(self.outputs.iter().map(shift).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::Gate;
+    use crate::witness::WitnessBuilder;
+    use blstrs::Scalar;
+
+    #[test]
+    fn test_subcircuit() {
+        // f(x) = (x * x + x) XOR 1, on two bits.
+        let mut inner = PlonkCircuit::init();
+        let (x_l, x_r, square) = inner.mult_gate();
+        let (square_copy, x_copy, sum) = inner.add_gate_labeled("sum");
+        inner.connect(x_l, x_r).unwrap();
+        inner.connect(x_l, x_copy).unwrap();
+        inner.connect(square, square_copy).unwrap();
+        let one = inner.gate(Gate::new().constant(1).output(-1)).2;
+        let output = inner.xor(sum, one, 2).unwrap();
+        let f = Subcircuit::new(inner, vec![x_l], vec![output]).unwrap();
+
+        // f(f(x)), with x = 1: f(1) = 2 XOR 1 = 3, f(3) = 12 XOR 1, which is not on two bits,
+        // and x = 0: f(0) = 1, f(1) = 3.
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.gate(Gate::new());
+        let y = f.synthesize(&mut circuit, &[x]).unwrap();
+        let z = f.synthesize(&mut circuit, &y).unwrap()[0];
+        assert_eq!(circuit.nr_constraints, 1 + 2 * f.circuit().nr_constraints);
+        assert_eq!(circuit.lookup_tables.len(), 1);
+        assert_eq!(circuit.gate_labels[&2], "sum");
+        assert_eq!(
+            circuit.gate_labels[&(2 + f.circuit().nr_constraints)],
+            "sum"
+        );
+
+        let trace = |value: u64| {
+            let mut builder = WitnessBuilder::new(&circuit);
+            builder.assign(x, Scalar::from(value))?;
+            builder.build()
+        };
+        let valid = trace(0).unwrap();
+        assert_eq!(valid.value(z), Scalar::from(3));
+        assert!(circuit.is_satisfied(&valid).is_ok());
+        assert!(trace(1).is_err());
+
+        assert_eq!(
+            f.synthesize(&mut circuit, &[]),
+            Err(PlonkError::InvalidLength {
+                expected: 1,
+                got: 0
+            })
+        );
+    }
+}
@@ -0,0 +1,428 @@
+// Machine integers in the circuit, written over the `Arithmetic` backend. Every input is
+// decomposed into boolean-constrained bits, which bounds it by 2^w for a width w of 8, 16, 32
+// or 64 bits. The field is larger than 2^128, so sums and products of two integers never wrap
+// around the modulus, and each operation computes the exact result on the values, and then
+// bounds it the same way:
+//
+// - checked operations decompose the result in w bits, so that the trace is only valid if it
+//   does not overflow,
+// - the other ones decompose it in w + 1 bits (additions) or 2w bits (products), and recompose
+//   the low w bits, the top bit of an addition being its carry.
+//
+// An operation costs around 2w gates per input, and 2w (checked) to 4w gates for the result.
+//
+// The inputs of `uint` are plain wires, so they are decomposed by every operation. The typed
+// integers `UInt8`, `UInt16`, `UInt32` and `UInt64` carry the wires of their bits instead:
+// they are decomposed once by `UInt::constrain`, or come out of an operation with the bits of
+// its result, and `UInt::operation` only decomposes the result.
+use super::arithmetic::{decompose, Arithmetic, Assigner, Builder, Combination};
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::Field;
+
+/// Width of an unsigned integer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Width {
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl Width {
+    pub fn bits(self) -> usize {
+        match self {
+            Width::U8 => 8,
+            Width::U16 => 16,
+            Width::U32 => 32,
+            Width::U64 => 64,
+        }
+    }
+
+    // x modulo 2^bits, as a u128 so that products of two integers fit.
+    fn wrap(self, x: u128) -> u64 {
+        (x & ((1u128 << self.bits()) - 1)) as u64
+    }
+}
+
+/// Operation on two unsigned integers of the same width.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UintOp {
+    /// x + y, which must not overflow.
+    CheckedAdd,
+    /// x + y modulo 2^w.
+    WrappingAdd,
+    /// x + y modulo 2^w, and the carry bit.
+    AddWithCarry,
+    /// x * y, which must not overflow.
+    CheckedMul,
+    /// x * y modulo 2^w.
+    WrappingMul,
+}
+
+impl UintOp {
+    /// Native result of the operation on `x` and `y`, which must fit `width`, in the order
+    /// of the outputs of the gadget. Checked operations return `None` if they overflow.
+    pub fn apply(self, width: Width, x: u64, y: u64) -> Option<Vec<u64>> {
+        let (sum, product) = (x as u128 + y as u128, x as u128 * y as u128);
+        let fits = |z: u128| z >> width.bits() == 0;
+        match self {
+            UintOp::CheckedAdd => fits(sum).then(|| vec![sum as u64]),
+            UintOp::WrappingAdd => Some(vec![width.wrap(sum)]),
+            UintOp::AddWithCarry => Some(vec![width.wrap(sum), (sum >> width.bits()) as u64]),
+            UintOp::CheckedMul => fits(product).then(|| vec![product as u64]),
+            UintOp::WrappingMul => Some(vec![width.wrap(product)]),
+        }
+    }
+}
+
+/// Wires created by `uint` or `UInt::operation`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct Uint {
+    outputs: Vec<Wire>,
+    width: Width,
+    op: UintOp,
+    typed: bool, // whether the inputs are typed integers, which are not decomposed again
+    rows: Vec<usize>, // rows of the gates, in the order in which they were created
+}
+
+impl Uint {
+    /// Wires that carry the result, and then the carry for `AddWithCarry`.
+    pub fn outputs(&self) -> &[Wire] {
+        &self.outputs
+    }
+
+    /// Assigns the rows of the gadget for the inputs `x` and `y`, and returns the result as
+    /// `UintOp::apply`. The trace must already contain the inputs. If a checked operation
+    /// overflows, the rows are still assigned, but the trace does not satisfy the circuit.
+    pub fn assign(&self, trace: &mut ComputationTrace, x: u64, y: u64) -> Option<Vec<u64>> {
+        let mut assigner = Assigner::new(trace, &self.rows);
+        operation(
+            &mut assigner,
+            self.width,
+            self.op,
+            [Scalar::from(x), Scalar::from(y)].map(|value| Input::new(value, self.typed)),
+        )
+        .expect("Assigning the trace cannot fail");
+        self.op.apply(self.width, x, y)
+    }
+}
+
+/// Constrains the outputs of the returned gadget to be `op` applied to `x` and `y`, which are
+/// constrained to be integers of `width`.
+pub fn uint(
+    circuit: &mut PlonkCircuit,
+    width: Width,
+    op: UintOp,
+    x: Wire,
+    y: Wire,
+) -> Result<Uint, PlonkError> {
+    let mut builder = Builder::new(circuit);
+    let (outputs, _) = operation(&mut builder, width, op, [x, y].map(Input::Wire))?;
+
+    Ok(Uint {
+        outputs,
+        width,
+        op,
+        typed: false,
+        rows: builder.rows,
+    })
+}
+
+/// Integer of `BITS` bits in the circuit, which is 8, 16, 32 or 64: a wire constrained to fit
+/// the width, with the wires of its little-endian bits.
+#[derive(Clone, Debug)]
+pub struct UInt<const BITS: usize> {
+    value: Wire,
+    bits: Vec<Wire>,
+    rows: Vec<usize>, // rows of the decomposition by `constrain`, if any
+}
+
+pub type UInt8 = UInt<8>;
+pub type UInt16 = UInt<16>;
+pub type UInt32 = UInt<32>;
+pub type UInt64 = UInt<64>;
+
+impl<const BITS: usize> UInt<BITS> {
+    const WIDTH: Width = match BITS {
+        8 => Width::U8,
+        16 => Width::U16,
+        32 => Width::U32,
+        64 => Width::U64,
+        _ => panic!("Unsupported width"),
+    };
+
+    /// Decomposes `wire`, which is then constrained to be an integer of `BITS` bits.
+    pub fn constrain(circuit: &mut PlonkCircuit, wire: Wire) -> Result<Self, PlonkError> {
+        let mut builder = Builder::new(circuit);
+        let bits = decompose(&mut builder, wire, Self::WIDTH.bits())?;
+        Ok(UInt {
+            value: wire,
+            bits,
+            rows: builder.rows,
+        })
+    }
+
+    /// Assigns the decomposition created by `constrain` for the value `x`, which the trace
+    /// must already contain. Integers returned by `operation` have no rows of their own.
+    pub fn assign(&self, trace: &mut ComputationTrace, x: u64) {
+        let mut assigner = Assigner::new(trace, &self.rows);
+        decompose(&mut assigner, Scalar::from(x), Self::WIDTH.bits())
+            .expect("Assigning the trace cannot fail");
+    }
+
+    /// Wire of the value.
+    pub fn value(&self) -> Wire {
+        self.value
+    }
+
+    /// Wires of the bits, from the least significant one.
+    pub fn bits(&self) -> &[Wire] {
+        &self.bits
+    }
+
+    /// Applies `op` to this integer and `other`, which are not decomposed again, and returns
+    /// the result, with its bits, and the gadget that assigns the operation, whose outputs
+    /// also contain the carry for `AddWithCarry`.
+    pub fn operation(
+        &self,
+        circuit: &mut PlonkCircuit,
+        op: UintOp,
+        other: &Self,
+    ) -> Result<(Self, Uint), PlonkError> {
+        let mut builder = Builder::new(circuit);
+        let inputs = [self, other].map(|x| Input::Typed(x.value, x.bits.clone()));
+        let (outputs, bits) = operation(&mut builder, Self::WIDTH, op, inputs)?;
+
+        let result = UInt {
+            value: outputs[0],
+            bits,
+            rows: Vec::new(),
+        };
+        let gadget = Uint {
+            outputs,
+            width: Self::WIDTH,
+            op,
+            typed: true,
+            rows: builder.rows,
+        };
+        Ok((result, gadget))
+    }
+}
+
+// Input of an operation, which is decomposed unless its bits are known.
+enum Input<V> {
+    Wire(V),
+    Typed(V, Vec<V>),
+}
+
+impl<V> Input<V> {
+    // The assignment does not use the bits of typed inputs, only that they are not decomposed.
+    fn new(value: V, typed: bool) -> Self {
+        if typed {
+            Input::Typed(value, Vec::new())
+        } else {
+            Input::Wire(value)
+        }
+    }
+}
+
+// Low `width` bits of the little-endian `bits`, as a single value.
+fn recompose<A: Arithmetic>(
+    ops: &mut A,
+    bits: &[A::Value],
+    width: Width,
+) -> Result<A::Value, PlonkError> {
+    let mut value = Combination::constant(Scalar::zero());
+    let mut power = Scalar::one();
+    for bit in bits[..width.bits()].iter() {
+        value.terms.push((*bit, power));
+        power = power.double();
+    }
+    value.evaluate(ops)
+}
+
+// Outputs of an operation, and the bits of its result.
+type Outputs<V> = (Vec<V>, Vec<V>);
+
+fn operation<A: Arithmetic>(
+    ops: &mut A,
+    width: Width,
+    op: UintOp,
+    inputs: [Input<A::Value>; 2],
+) -> Result<Outputs<A::Value>, PlonkError> {
+    let w = width.bits();
+    let [x, y] = inputs.map(|input| match input {
+        Input::Wire(x) => decompose(ops, x, w).map(|_| x),
+        Input::Typed(x, _) => Ok(x),
+    });
+    let (x, y) = (x?, y?);
+
+    let result = match op {
+        UintOp::CheckedAdd | UintOp::WrappingAdd | UintOp::AddWithCarry => {
+            ops.gate(Gate::new().left(1).right(1), x, y)?
+        }
+        UintOp::CheckedMul | UintOp::WrappingMul => ops.gate(Gate::new().mul(1), x, y)?,
+    };
+    match op {
+        UintOp::CheckedAdd | UintOp::CheckedMul => {
+            let bits = decompose(ops, result, w)?;
+            Ok((vec![result], bits))
+        }
+        UintOp::WrappingAdd => {
+            let mut bits = decompose(ops, result, w + 1)?;
+            bits.truncate(w);
+            Ok((vec![recompose(ops, &bits, width)?], bits))
+        }
+        UintOp::AddWithCarry => {
+            let mut bits = decompose(ops, result, w + 1)?;
+            let carry = bits.pop().expect("The sum has w + 1 bits");
+            Ok((vec![recompose(ops, &bits, width)?, carry], bits))
+        }
+        UintOp::WrappingMul => {
+            let mut bits = decompose(ops, result, 2 * w)?;
+            bits.truncate(w);
+            Ok((vec![recompose(ops, &bits, width)?], bits))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Circuit of `op` over two free inputs, and whether its trace for `x` and `y` is valid,
+    // checking the outputs in the trace against the native result.
+    fn prove(width: Width, op: UintOp, x: u64, y: u64) -> bool {
+        let mut circuit = PlonkCircuit::init();
+        let (x_wire, _, _) = circuit.add_gate();
+        let (y_wire, _, _) = circuit.add_gate();
+        let gadget = uint(&mut circuit, width, op, x_wire, y_wire).unwrap();
+
+        let mut trace = ComputationTrace::new(&circuit);
+        for (wire, value) in [(x_wire, x), (y_wire, y)] {
+            trace.set(wire, Scalar::from(value));
+            trace.set(Wire::output(wire.row), Scalar::from(value));
+        }
+        let result = gadget.assign(&mut trace, x, y);
+        if let Some(result) = result.as_ref() {
+            for (wire, value) in gadget.outputs().iter().zip(result.iter()) {
+                assert_eq!(trace.value(*wire), Scalar::from(*value));
+            }
+        }
+        let satisfied = circuit.is_satisfied(&trace).is_ok();
+        assert!(!satisfied || result.is_some());
+        satisfied
+    }
+
+    #[test]
+    fn test_uint() {
+        let max = u64::MAX;
+        for (width, op, x, y, expected) in [
+            (Width::U8, UintOp::CheckedAdd, 100, 155, Some(vec![255])),
+            (Width::U8, UintOp::WrappingAdd, 200, 100, Some(vec![44])),
+            (Width::U8, UintOp::AddWithCarry, 200, 100, Some(vec![44, 1])),
+            (Width::U8, UintOp::CheckedMul, 15, 17, Some(vec![255])),
+            (Width::U8, UintOp::WrappingMul, 16, 17, Some(vec![16])),
+            (Width::U16, UintOp::WrappingAdd, 65_535, 1, Some(vec![0])),
+            (
+                Width::U32,
+                UintOp::AddWithCarry,
+                1 << 31,
+                1 << 31,
+                Some(vec![0, 1]),
+            ),
+            (
+                Width::U64,
+                UintOp::AddWithCarry,
+                max,
+                max,
+                Some(vec![max - 1, 1]),
+            ),
+            (Width::U64, UintOp::WrappingMul, max, max, Some(vec![1])),
+            (
+                Width::U64,
+                UintOp::CheckedMul,
+                1 << 32,
+                1 << 31,
+                Some(vec![1 << 63]),
+            ),
+        ] {
+            assert_eq!(op.apply(width, x, y), expected);
+            assert!(prove(width, op, x, y), "{op:?} of {x} and {y} as {width:?}");
+        }
+
+        // Overflows of checked operations, and inputs that do not fit the width.
+        assert!(!prove(Width::U8, UintOp::CheckedAdd, 100, 156));
+        assert!(!prove(Width::U32, UintOp::CheckedMul, 1 << 16, 1 << 16));
+        assert!(!prove(Width::U64, UintOp::CheckedAdd, max, 1));
+        assert!(!prove(Width::U8, UintOp::WrappingAdd, 256, 0));
+    }
+
+    #[test]
+    fn test_typed_uint() {
+        // (x + y) * x modulo 2^16, and the carry of (x + y) + y.
+        let (x, y) = (50_000u64, 30_000u64);
+        let mut circuit = PlonkCircuit::init();
+        let (x_wire, _, _) = circuit.add_gate();
+        let (y_wire, _, _) = circuit.add_gate();
+        let x_int = UInt16::constrain(&mut circuit, x_wire).unwrap();
+        let y_int = UInt16::constrain(&mut circuit, y_wire).unwrap();
+        let (sum, add) = x_int
+            .operation(&mut circuit, UintOp::WrappingAdd, &y_int)
+            .unwrap();
+        let (product, mul) = sum
+            .operation(&mut circuit, UintOp::WrappingMul, &x_int)
+            .unwrap();
+        let (_, carry) = sum
+            .operation(&mut circuit, UintOp::AddWithCarry, &y_int)
+            .unwrap();
+        assert_eq!(sum.bits().len(), 16);
+        assert_eq!(product.bits().len(), 16);
+
+        // Typed inputs are not decomposed again.
+        let mut untyped = PlonkCircuit::init();
+        let (a, _, _) = untyped.add_gate();
+        let (b, _, _) = untyped.add_gate();
+        let gadget = uint(&mut untyped, Width::U16, UintOp::WrappingAdd, a, b).unwrap();
+        assert_eq!(gadget.rows.len(), add.rows.len() + 2 * x_int.rows.len());
+
+        let mut trace = ComputationTrace::new(&circuit);
+        for (wire, value) in [(x_wire, x), (y_wire, y)] {
+            trace.set(wire, Scalar::from(value));
+            trace.set(Wire::output(wire.row), Scalar::from(value));
+        }
+        x_int.assign(&mut trace, x);
+        y_int.assign(&mut trace, y);
+        let sum_value = add.assign(&mut trace, x, y).unwrap()[0];
+        assert_eq!(sum_value, 14_464);
+        assert_eq!(
+            mul.assign(&mut trace, sum_value, x),
+            Some(vec![(sum_value * x) % (1 << 16)])
+        );
+        assert_eq!(
+            carry.assign(&mut trace, sum_value, y),
+            Some(vec![44_464, 0])
+        );
+        assert_eq!(trace.value(product.value()), Scalar::from(10_240));
+        assert_eq!(
+            trace.value(sum.bits()[4]),
+            Scalar::from((sum_value >> 4) & 1)
+        );
+        assert!(circuit.is_satisfied(&trace).is_ok());
+
+        // A typed input that does not fit its width fails its own decomposition.
+        let mut trace = ComputationTrace::new(&circuit);
+        for (wire, value) in [(x_wire, 1 << 16), (y_wire, y)] {
+            trace.set(wire, Scalar::from(value));
+            trace.set(Wire::output(wire.row), Scalar::from(value));
+        }
+        x_int.assign(&mut trace, 1 << 16);
+        y_int.assign(&mut trace, y);
+        let sum_value = add.assign(&mut trace, 1 << 16, y).unwrap()[0];
+        mul.assign(&mut trace, sum_value, 1 << 16);
+        carry.assign(&mut trace, sum_value, y);
+        assert!(circuit.is_satisfied(&trace).is_err());
+    }
+}
@@ -0,0 +1,539 @@
+// Inner product argument (IPA) commitments, as in Bulletproofs and Halo. A polynomial of up to
+// n coefficients is committed as sum p_i * G_i, with generators G_i obtained by hashing to the
+// curve, so that nobody knows a relation between them and there is no trusted setup. The
+// evaluation p(z) is the inner product <p, (1, z, ..., z^{n-1})>, which the prover shows in
+// log n rounds: each round splits both vectors in halves, sends the two cross terms L and R,
+// and folds the vectors and the generators with a challenge x. The price of transparency is an
+// opening of 2 log n points instead of one, and a verifier that computes an MSM of size n.
+//
+// PLONK proofs with IPA open every polynomial at zeta, and those of the next row at
+// zeta * omega, instead of linearising the quotient. The openings are not hiding, so these
+// proofs are not zero-knowledge.
+use crate::commitment::{PlonkCommitment, PolynomialCommitment};
+use crate::error::PlonkError;
+use crate::kzg10::{msm, powers, union_points};
+use crate::polynomial::{batch_invert, EvaluationDomain, Polynomial};
+use crate::proof::PlonkProof;
+#[cfg(feature = "std")]
+use crate::prover::{self, ProofPolynomials};
+use crate::transcript::Transcript;
+use crate::verifier::{Challenges, PlonkVerifier, VerifierKey};
+use alloc::vec;
+use alloc::vec::Vec;
+use blstrs::{G1Affine, G1Projective, Scalar};
+use ff::Field;
+use group::{Curve, Group};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Domain separation tag of the generators.
+const GENERATORS_DST: &[u8] = b"DUMMY-PLONK-IPA-GENERATORS";
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Ipa {
+    pub generators: Vec<G1Affine>, // a power of two number of them
+    pub u: G1Affine,               // generator of the inner product
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IpaCommitment(pub(crate) G1Affine);
+
+/// Opening of several polynomials at the same point: the claimed evaluations, the cross terms
+/// of every round, and the single coefficient left after folding the random linear combination
+/// of the polynomials.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IpaOpening {
+    pub evaluations: Vec<Scalar>,
+    pub(crate) l: Vec<G1Affine>,
+    pub(crate) r: Vec<G1Affine>,
+    pub(crate) a: Scalar,
+}
+
+/// Opening of several polynomials, each at its own set of points. There is an opening of the
+/// polynomials of every point, in the order in which the points first appear.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IpaBatchOpening {
+    pub evaluations: Vec<Vec<Scalar>>,
+    pub(crate) openings: Vec<IpaOpening>,
+}
+
+impl Ipa {
+    /// Generates the generators for polynomials of up to `size` coefficients, rounded up to a
+    /// power of two.
+    pub fn setup(size: usize) -> Self {
+        let size = size.max(1).next_power_of_two();
+        let generators = (0..size as u64)
+            .map(|i| G1Projective::hash_to_curve(&i.to_le_bytes(), GENERATORS_DST, &[]))
+            .collect::<Vec<_>>();
+        let mut affine = vec![G1Affine::default(); size];
+        G1Projective::batch_normalize(&generators, &mut affine);
+
+        Self {
+            generators: affine,
+            u: G1Projective::hash_to_curve(b"u", GENERATORS_DST, &[]).to_affine(),
+        }
+    }
+
+    pub fn commit(&self, polynomial: &Polynomial) -> IpaCommitment {
+        assert!(
+            polynomial.0.len() <= self.generators.len(),
+            "Polynomial degree not supported"
+        );
+        IpaCommitment(msm(&self.generators, &polynomial.0).to_affine())
+    }
+
+    /// Opens all `polynomials` at `point`, proving the evaluation of their combination
+    /// sum v^i * p_i(X) with a challenge v.
+    pub fn open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        point: &Scalar,
+        transcript: &mut T,
+    ) -> IpaOpening {
+        let commitments = polynomials
+            .iter()
+            .map(|poly| self.commit(poly))
+            .collect::<Vec<_>>();
+        let evaluations = polynomials
+            .iter()
+            .map(|poly| poly.eval(point))
+            .collect::<Vec<_>>();
+        let (v, u) = self.opening_challenges(&commitments, point, &evaluations, transcript);
+
+        let size = self.generators.len();
+        let mut a = vec![Scalar::zero(); size];
+        let mut v_powers = Scalar::one();
+        for poly in polynomials {
+            for (a, coefficient) in a.iter_mut().zip(poly.0.iter()) {
+                *a += v_powers * coefficient;
+            }
+            v_powers *= v;
+        }
+        let mut b = powers(point, size);
+        let mut generators = self
+            .generators
+            .iter()
+            .map(G1Projective::from)
+            .collect::<Vec<_>>();
+
+        let (mut l, mut r) = (Vec::new(), Vec::new());
+        while a.len() > 1 {
+            let half = a.len() / 2;
+            let (a_lo, a_hi) = a.split_at(half);
+            let (b_lo, b_hi) = b.split_at(half);
+            let (g_lo, g_hi) = generators.split_at(half);
+            let l_i = (inner_product_g1(a_lo, g_hi) + u * inner_product(a_lo, b_hi)).to_affine();
+            let r_i = (inner_product_g1(a_hi, g_lo) + u * inner_product(a_hi, b_lo)).to_affine();
+            transcript.append_point(b"ipa l", &l_i);
+            transcript.append_point(b"ipa r", &r_i);
+            let x = transcript.challenge_scalar(b"ipa round");
+            let x_inv = x.invert().unwrap();
+
+            a = fold(a_lo, a_hi, &x, &x_inv);
+            b = fold(b_lo, b_hi, &x_inv, &x);
+            generators = g_lo
+                .iter()
+                .zip(g_hi.iter())
+                .map(|(lo, hi)| lo * x_inv + hi * x)
+                .collect();
+            l.push(l_i);
+            r.push(r_i);
+        }
+
+        IpaOpening {
+            evaluations,
+            l,
+            r,
+            a: a[0],
+        }
+    }
+
+    /// Checks an opening produced by `open` of the polynomials committed in `commitments`.
+    pub fn check<T: Transcript>(
+        &self,
+        commitments: &[IpaCommitment],
+        point: &Scalar,
+        opening: &IpaOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        let rounds = self.generators.len().trailing_zeros() as usize;
+        if commitments.len() != opening.evaluations.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: commitments.len(),
+                got: opening.evaluations.len(),
+            });
+        }
+        if opening.l.len() != rounds || opening.r.len() != rounds {
+            return Err(PlonkError::InvalidLength {
+                expected: rounds,
+                got: opening.l.len().min(opening.r.len()),
+            });
+        }
+        let (v, u) = self.opening_challenges(commitments, point, &opening.evaluations, transcript);
+
+        // P = sum v^i * C_i + (sum v^i * y_i) * U' is the statement of the first round.
+        let mut p = G1Projective::identity();
+        let mut evaluation = Scalar::zero();
+        let mut v_powers = Scalar::one();
+        for (commitment, y) in commitments.iter().zip(opening.evaluations.iter()) {
+            p += commitment.0 * v_powers;
+            evaluation += v_powers * y;
+            v_powers *= v;
+        }
+        p += u * evaluation;
+
+        let mut challenges = Vec::with_capacity(rounds);
+        for (l, r) in opening.l.iter().zip(opening.r.iter()) {
+            transcript.append_point(b"ipa l", l);
+            transcript.append_point(b"ipa r", r);
+            challenges.push(transcript.challenge_scalar(b"ipa round"));
+        }
+        let mut inverses = challenges.clone();
+        batch_invert(&mut inverses)?;
+        for ((l, r), (x, x_inv)) in opening
+            .l
+            .iter()
+            .zip(opening.r.iter())
+            .zip(challenges.iter().zip(inverses.iter()))
+        {
+            p += l * x.square() + r * x_inv.square();
+        }
+
+        // The folded generator and vector b are sum s_i * G_i and sum s_i * z^i, where s_i is
+        // the product of the challenges of the rounds in which i was in the upper half, and of
+        // the inverses of the others.
+        let mut s = vec![Scalar::one(); self.generators.len()];
+        for (round, (x, x_inv)) in challenges.iter().zip(inverses.iter()).enumerate() {
+            let bit = 1 << (rounds - 1 - round);
+            for (i, s) in s.iter_mut().enumerate() {
+                *s *= if i & bit == 0 { x_inv } else { x };
+            }
+        }
+        let b = s
+            .iter()
+            .zip(powers(point, s.len()))
+            .fold(Scalar::zero(), |acc, (s, power)| acc + *s * power);
+        let folded = msm(&self.generators, &s);
+
+        if p == folded * opening.a + u * (opening.a * b) {
+            Ok(())
+        } else {
+            Err(PlonkError::VerificationFailed)
+        }
+    }
+
+    /// Opens the i-th polynomial at the points of `points[i]`, with an opening per point of
+    /// all the polynomials evaluated there.
+    pub fn batch_open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        points: &[Vec<Scalar>],
+        transcript: &mut T,
+    ) -> IpaBatchOpening {
+        assert_eq!(
+            polynomials.len(),
+            points.len(),
+            "a set of points per polynomial"
+        );
+        let openings = union_points(points)
+            .iter()
+            .map(|point| {
+                let opened = polynomials
+                    .iter()
+                    .zip(points.iter())
+                    .filter(|(_, set)| set.contains(point))
+                    .map(|(poly, _)| poly.clone())
+                    .collect::<Vec<_>>();
+                self.open(&opened, point, transcript)
+            })
+            .collect();
+
+        IpaBatchOpening {
+            evaluations: polynomials
+                .iter()
+                .zip(points.iter())
+                .map(|(poly, set)| set.iter().map(|point| poly.eval(point)).collect())
+                .collect(),
+            openings,
+        }
+    }
+
+    /// Checks an opening produced by `batch_open` of the polynomials committed in
+    /// `commitments`, at the same sets of points.
+    pub fn check_batch<T: Transcript>(
+        &self,
+        commitments: &[IpaCommitment],
+        points: &[Vec<Scalar>],
+        opening: &IpaBatchOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        if commitments.len() != points.len() || commitments.len() != opening.evaluations.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: commitments.len(),
+                got: points.len().min(opening.evaluations.len()),
+            });
+        }
+        let all_points = union_points(points);
+        if opening.openings.len() != all_points.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: all_points.len(),
+                got: opening.openings.len(),
+            });
+        }
+
+        for (point, point_opening) in all_points.iter().zip(opening.openings.iter()) {
+            let mut opened = Vec::new();
+            let mut evaluations = Vec::new();
+            for ((commitment, set), set_evaluations) in commitments
+                .iter()
+                .zip(points.iter())
+                .zip(opening.evaluations.iter())
+            {
+                if set.len() != set_evaluations.len() {
+                    return Err(PlonkError::InvalidLength {
+                        expected: set.len(),
+                        got: set_evaluations.len(),
+                    });
+                }
+                if let Some(index) = set.iter().position(|x| x == point) {
+                    opened.push(commitment.clone());
+                    evaluations.push(set_evaluations[index]);
+                }
+            }
+            // The claimed evaluations must be the ones that the opening proves.
+            if evaluations != point_opening.evaluations {
+                return Err(PlonkError::VerificationFailed);
+            }
+            self.check(&opened, point, point_opening, transcript)?;
+        }
+
+        Ok(())
+    }
+
+    // Absorbs the statement and draws the challenge v combining the polynomials, and the
+    // generator U' = xi * U of the inner product.
+    fn opening_challenges<T: Transcript>(
+        &self,
+        commitments: &[IpaCommitment],
+        point: &Scalar,
+        evaluations: &[Scalar],
+        transcript: &mut T,
+    ) -> (Scalar, G1Projective) {
+        for (commitment, evaluation) in commitments.iter().zip(evaluations.iter()) {
+            transcript.append_point(b"ipa commitment", &commitment.0);
+            transcript.append_scalar(b"ipa evaluation", evaluation);
+        }
+        transcript.append_scalar(b"ipa point", point);
+        let v = transcript.challenge_scalar(b"ipa v");
+        let xi = transcript.challenge_scalar(b"ipa xi");
+
+        (v, self.u * xi)
+    }
+}
+
+impl PolynomialCommitment for Ipa {
+    type Commitment = IpaCommitment;
+    type Opening = IpaOpening;
+    type BatchOpening = IpaBatchOpening;
+
+    fn max_size(&self) -> usize {
+        self.generators.len()
+    }
+
+    fn commit(&self, polynomial: &Polynomial) -> IpaCommitment {
+        Ipa::commit(self, polynomial)
+    }
+
+    fn open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        point: &Scalar,
+        transcript: &mut T,
+    ) -> IpaOpening {
+        Ipa::open(self, polynomials, point, transcript)
+    }
+
+    fn evaluations(opening: &IpaOpening) -> &[Scalar] {
+        &opening.evaluations
+    }
+
+    fn check<T: Transcript>(
+        &self,
+        commitments: &[IpaCommitment],
+        point: &Scalar,
+        opening: &IpaOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        Ipa::check(self, commitments, point, opening, transcript)
+    }
+
+    fn batch_open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        points: &[Vec<Scalar>],
+        transcript: &mut T,
+    ) -> IpaBatchOpening {
+        Ipa::batch_open(self, polynomials, points, transcript)
+    }
+
+    fn batch_evaluations(opening: &IpaBatchOpening) -> &[Vec<Scalar>] {
+        &opening.evaluations
+    }
+
+    fn check_batch<T: Transcript>(
+        &self,
+        commitments: &[IpaCommitment],
+        points: &[Vec<Scalar>],
+        opening: &IpaBatchOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        Ipa::check_batch(self, commitments, points, opening, transcript)
+    }
+}
+
+impl PlonkCommitment for Ipa {
+    type LagrangeSet = ();
+    type ProofOpening = IpaBatchOpening;
+
+    // The verifier needs all the generators for the MSM of the last round.
+    fn verifier_part(&self) -> Self {
+        self.clone()
+    }
+
+    fn lagrange_set(&self, _domain: &EvaluationDomain) -> Result<(), PlonkError> {
+        Ok(())
+    }
+
+    fn append_commitment<T: Transcript>(
+        transcript: &mut T,
+        label: &'static [u8],
+        commitment: &IpaCommitment,
+    ) {
+        transcript.append_point(label, &commitment.0);
+    }
+
+    fn commitment_bytes(commitment: &IpaCommitment) -> Vec<u8> {
+        commitment.0.to_compressed().to_vec()
+    }
+
+    fn verifier_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.u.to_compressed().to_vec();
+        for point in self.generators.iter() {
+            bytes.extend_from_slice(&point.to_compressed());
+        }
+        bytes
+    }
+
+    #[cfg(feature = "std")]
+    fn open_proof<T: Transcript>(
+        polynomials: &ProofPolynomials<Self>,
+        transcript: &mut T,
+    ) -> IpaBatchOpening {
+        prover::open_polynomials(polynomials, transcript)
+    }
+
+    fn check_proof<T: Transcript>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey<Self>,
+        proof: &PlonkProof<Self>,
+        challenges: &Challenges,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        PlonkVerifier::check_openings(
+            pub_in,
+            verifier_key,
+            proof,
+            &proof.opening,
+            challenges,
+            transcript,
+        )
+    }
+}
+
+fn inner_product(a: &[Scalar], b: &[Scalar]) -> Scalar {
+    a.iter()
+        .zip(b.iter())
+        .fold(Scalar::zero(), |acc, (a, b)| acc + *a * b)
+}
+
+fn inner_product_g1(a: &[Scalar], points: &[G1Projective]) -> G1Projective {
+    a.iter()
+        .zip(points.iter())
+        .map(|(a, point)| point * a)
+        .sum()
+}
+
+// lo * x + hi * y, pointwise.
+fn fold(lo: &[Scalar], hi: &[Scalar], x: &Scalar, y: &Scalar) -> Vec<Scalar> {
+    lo.iter()
+        .zip(hi.iter())
+        .map(|(lo, hi)| *lo * x + *hi * y)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use crate::commitment::tests::check_plonk;
+    use crate::commitment::tests::check_scheme;
+    use crate::transcript::Blake2bTranscript;
+
+    #[test]
+    fn test_ipa() {
+        check_scheme(&Ipa::setup(8));
+        // Generators are public and deterministic, and a single coefficient needs no round.
+        assert_eq!(Ipa::setup(5).generators, Ipa::setup(8).generators);
+        check_scheme(&Ipa::setup(1));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_ipa_plonk() {
+        check_plonk(Ipa::setup(32));
+    }
+
+    #[test]
+    fn test_ipa_tampered() {
+        let ipa = Ipa::setup(8);
+        let polynomial = Polynomial((1..=8).map(Scalar::from).collect());
+        let commitment = ipa.commit(&polynomial);
+        let point = Scalar::from(3);
+        let mut transcript = Blake2bTranscript::new(b"ipa");
+        let opening = ipa.open(&[polynomial], &point, &mut transcript);
+
+        let mut wrong_evaluation = opening.clone();
+        wrong_evaluation.evaluations[0] += Scalar::one();
+        let mut wrong_round = opening.clone();
+        wrong_round.l.swap(0, 1);
+        let mut wrong_coefficient = opening.clone();
+        wrong_coefficient.a += Scalar::one();
+        for opening in [wrong_evaluation, wrong_round, wrong_coefficient] {
+            let mut transcript = Blake2bTranscript::new(b"ipa");
+            assert_eq!(
+                ipa.check(
+                    core::slice::from_ref(&commitment),
+                    &point,
+                    &opening,
+                    &mut transcript
+                ),
+                Err(PlonkError::VerificationFailed)
+            );
+        }
+
+        let mut missing_round = opening;
+        missing_round.r.pop();
+        let mut transcript = Blake2bTranscript::new(b"ipa");
+        assert_eq!(
+            ipa.check(&[commitment], &point, &missing_round, &mut transcript),
+            Err(PlonkError::InvalidLength {
+                expected: 3,
+                got: 2
+            })
+        );
+    }
+}
@@ -0,0 +1,292 @@
+// Minimal JSON values, with a writer and a parser, used to export and import circuits. Numbers
+// are limited to unsigned integers, which is all the schema of the circuits needs (scalars are
+// written as hexadecimal strings).
+use crate::error::PlonkError;
+use std::fmt;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>), // in the order of the document
+}
+
+impl Json {
+    pub(crate) fn object(fields: Vec<(&str, Json)>) -> Self {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Result<&Json, PlonkError> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == key)
+                .map(|(_, value)| value)
+                .ok_or_else(|| PlonkError::InvalidJson(format!("Missing field \"{key}\""))),
+            _ => Err(PlonkError::InvalidJson(format!(
+                "Expected an object with field \"{key}\""
+            ))),
+        }
+    }
+
+    pub(crate) fn as_u64(&self) -> Result<u64, PlonkError> {
+        match self {
+            Json::Number(value) => Ok(*value),
+            _ => Err(PlonkError::InvalidJson("Expected a number".to_string())),
+        }
+    }
+
+    pub(crate) fn as_usize(&self) -> Result<usize, PlonkError> {
+        usize::try_from(self.as_u64()?).map_err(|error| PlonkError::InvalidJson(error.to_string()))
+    }
+
+    pub(crate) fn as_str(&self) -> Result<&str, PlonkError> {
+        match self {
+            Json::String(value) => Ok(value),
+            _ => Err(PlonkError::InvalidJson("Expected a string".to_string())),
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Result<&[Json], PlonkError> {
+        match self {
+            Json::Array(values) => Ok(values),
+            _ => Err(PlonkError::InvalidJson("Expected an array".to_string())),
+        }
+    }
+
+    pub(crate) fn parse(input: &str) -> Result<Self, PlonkError> {
+        let mut parser = Parser {
+            input: input.as_bytes(),
+            position: 0,
+        };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.position != parser.input.len() {
+            return Err(parser.error("Unexpected data after the document"));
+        }
+        Ok(value)
+    }
+}
+
+// Compact encoding, without any whitespace.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            Json::Number(value) => write!(f, "{value}"),
+            Json::String(value) => {
+                write!(f, "\"")?;
+                for char in value.chars() {
+                    match char {
+                        '"' => write!(f, "\\\"")?,
+                        '\\' => write!(f, "\\\\")?,
+                        '\n' => write!(f, "\\n")?,
+                        char if (char as u32) < 0x20 => write!(f, "\\u{:04x}", char as u32)?,
+                        char => write!(f, "{char}")?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{separator}{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { "," };
+                    write!(f, "{separator}{}:{value}", Json::String(key.clone()))?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> PlonkError {
+        PlonkError::InvalidJson(format!("{message} at byte {}", self.position))
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.input.get(self.position) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), PlonkError> {
+        if self.input[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected {literal}")))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, PlonkError> {
+        self.whitespace();
+        match self.input.get(self.position) {
+            Some(b'n') => self.expect("null").map(|_| Json::Null),
+            Some(b't') => self.expect("true").map(|_| Json::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Json::Bool(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[') => {
+                self.position += 1;
+                let mut values = Vec::new();
+                self.whitespace();
+                if self.input.get(self.position) == Some(&b']') {
+                    self.position += 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    self.whitespace();
+                    match self.input.get(self.position) {
+                        Some(b',') => self.position += 1,
+                        Some(b']') => {
+                            self.position += 1;
+                            return Ok(Json::Array(values));
+                        }
+                        _ => return Err(self.error("Expected , or ]")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.position += 1;
+                let mut fields = Vec::new();
+                self.whitespace();
+                if self.input.get(self.position) == Some(&b'}') {
+                    self.position += 1;
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.whitespace();
+                    let key = self.string()?;
+                    self.whitespace();
+                    self.expect(":")?;
+                    fields.push((key, self.value()?));
+                    self.whitespace();
+                    match self.input.get(self.position) {
+                        Some(b',') => self.position += 1,
+                        Some(b'}') => {
+                            self.position += 1;
+                            return Ok(Json::Object(fields));
+                        }
+                        _ => return Err(self.error("Expected , or }")),
+                    }
+                }
+            }
+            Some(b'0'..=b'9') => {
+                let start = self.position;
+                while let Some(b'0'..=b'9') = self.input.get(self.position) {
+                    self.position += 1;
+                }
+                std::str::from_utf8(&self.input[start..self.position])
+                    .expect("Digits are valid UTF-8")
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| self.error("Number too large"))
+            }
+            _ => Err(self.error("Unexpected character")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, PlonkError> {
+        self.expect("\"")?;
+        let mut bytes = Vec::new();
+        loop {
+            let byte = *self
+                .input
+                .get(self.position)
+                .ok_or_else(|| self.error("Unterminated string"))?;
+            self.position += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = *self
+                        .input
+                        .get(self.position)
+                        .ok_or_else(|| self.error("Unterminated string"))?;
+                    self.position += 1;
+                    let char = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'n' => '\n',
+                        b't' => '\t',
+                        b'r' => '\r',
+                        b'u' => {
+                            let code = self
+                                .input
+                                .get(self.position..self.position + 4)
+                                .and_then(|hex| std::str::from_utf8(hex).ok())
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("Invalid unicode escape"))?;
+                            self.position += 4;
+                            code
+                        }
+                        _ => return Err(self.error("Invalid escape")),
+                    };
+                    bytes.extend_from_slice(char.encode_utf8(&mut [0u8; 4]).as_bytes());
+                }
+                byte => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("Invalid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        let value = Json::object(vec![
+            ("name", Json::String("x \"squared\"\n".to_string())),
+            (
+                "values",
+                Json::Array(vec![Json::Number(3), Json::Bool(true), Json::Null]),
+            ),
+            ("empty", Json::Object(vec![])),
+        ]);
+        let encoded = value.to_string();
+        assert_eq!(
+            encoded,
+            r#"{"name":"x \"squared\"\n","values":[3,true,null],"empty":{}}"#
+        );
+        assert_eq!(Json::parse(&encoded).unwrap(), value);
+        assert_eq!(
+            Json::parse(" { \"a\" : [ 1 , 2 ] , \"b\" : \"\\u00e9\" } ").unwrap(),
+            Json::object(vec![
+                ("a", Json::Array(vec![Json::Number(1), Json::Number(2)])),
+                ("b", Json::String("é".to_string()))
+            ])
+        );
+
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{\"a\": 1} x").is_err());
+        assert!(Json::parse("-1").is_err());
+        assert_eq!(value.get("values").unwrap().as_array().unwrap().len(), 3);
+        assert!(value.get("missing").is_err());
+    }
+}
@@ -0,0 +1,212 @@
+// Jubjub, the twisted Edwards curve -u^2 + v^2 = 1 + d * u^2 * v^2 with d = -10240/10241, defined
+// over the scalar field of BLS12-381. This makes it an embedded curve for our circuits: its
+// group operations are field operations over the scalars of the proof system, and its points
+// can be represented by two wires. As d is not a square, the addition formula is complete.
+//
+// The curve has order 8 * r, and we work in the subgroup of prime order r.
+use blstrs::Scalar;
+use ff::Field;
+use rand_core::RngCore;
+
+/// Parameter d of the curve equation.
+pub(crate) fn edwards_d() -> Scalar {
+    -(Scalar::from(10240) * Scalar::from(10241).invert().unwrap())
+}
+
+/// Order r of the prime order subgroup, as little-endian limbs.
+const ORDER: [u64; 4] = [
+    0xd0970e5ed6f72cb7,
+    0xa6682093ccc81082,
+    0x06673b0101343b00,
+    0x0e7db4ea6533afa9,
+];
+
+/// Point of Jubjub, in affine coordinates.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct JubjubPoint {
+    pub u: Scalar,
+    pub v: Scalar,
+}
+
+impl JubjubPoint {
+    pub fn identity() -> Self {
+        JubjubPoint {
+            u: Scalar::zero(),
+            v: Scalar::one(),
+        }
+    }
+
+    /// Generator of the prime order subgroup: the first point with u = 1, 2, ... multiplied
+    /// by the cofactor.
+    pub fn generator() -> Self {
+        let d = edwards_d();
+        let mut u = Scalar::one();
+        loop {
+            // v^2 = (1 + u^2) / (1 - d * u^2)
+            let u_square = u.square();
+            let v_square =
+                (Scalar::one() + u_square) * (Scalar::one() - d * u_square).invert().unwrap();
+            if let Some(v) = Option::<Scalar>::from(v_square.sqrt()) {
+                let point = JubjubPoint { u, v }.double().double().double();
+                if point != Self::identity() {
+                    return point;
+                }
+            }
+            u += Scalar::one();
+        }
+    }
+
+    pub fn is_on_curve(&self) -> bool {
+        let (u_square, v_square) = (self.u.square(), self.v.square());
+        v_square - u_square == Scalar::one() + edwards_d() * u_square * v_square
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        let (a, b) = (self.u * other.v, self.v * other.u);
+        let (c, d) = (self.u * other.u, self.v * other.v);
+        let e = edwards_d() * c * d;
+        JubjubPoint {
+            u: (a + b) * (Scalar::one() + e).invert().unwrap(),
+            v: (c + d) * (Scalar::one() - e).invert().unwrap(),
+        }
+    }
+
+    pub fn double(&self) -> Self {
+        self.add(self)
+    }
+
+    /// Multiplication by the little-endian integer `scalar`, with double and add.
+    pub fn mul(&self, scalar: &[u8]) -> Self {
+        let mut result = Self::identity();
+        for byte in scalar.iter().rev() {
+            for i in (0..8).rev() {
+                result = result.double();
+                if (byte >> i) & 1 == 1 {
+                    result = result.add(self);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Scalar of the prime order subgroup of Jubjub, reduced modulo r.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct JubjubScalar([u64; 4]);
+
+impl JubjubScalar {
+    pub fn random(mut rng: impl RngCore) -> Self {
+        let mut bytes = [0u8; 64];
+        rng.fill_bytes(&mut bytes);
+        Self::from_bytes_wide(&bytes)
+    }
+
+    /// Reduction of the little-endian integer `bytes`.
+    pub fn from_bytes_wide(bytes: &[u8; 64]) -> Self {
+        let limbs: Vec<u64> = bytes
+            .chunks(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("Chunks have eight bytes")))
+            .collect();
+        JubjubScalar(reduce(&limbs))
+    }
+
+    /// Reduction of the canonical representative of `scalar`.
+    pub fn from_scalar(scalar: &Scalar) -> Self {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&scalar.to_bytes_le());
+        Self::from_bytes_wide(&bytes)
+    }
+
+    /// Embedding in the scalars of BLS12-381, which is possible as r is smaller.
+    pub fn to_scalar(self) -> Scalar {
+        Scalar::from_bytes_le(&self.to_bytes()).unwrap()
+    }
+
+    pub fn to_bytes(self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        for (chunk, limb) in bytes.chunks_mut(8).zip(self.0.iter()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        // Both are smaller than r < 2^252, so that the sum fits in four limbs.
+        let mut sum = [0u64; 4];
+        let mut carry = 0;
+        for (result, (a, b)) in sum.iter_mut().zip(self.0.iter().zip(other.0.iter())) {
+            let total = *a as u128 + *b as u128 + carry;
+            *result = total as u64;
+            carry = total >> 64;
+        }
+        JubjubScalar(reduce(&sum))
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        let mut product = [0u64; 8];
+        for (i, a) in self.0.iter().enumerate() {
+            let mut carry = 0;
+            for (j, b) in other.0.iter().enumerate() {
+                let total = *a as u128 * *b as u128 + product[i + j] as u128 + carry;
+                product[i + j] = total as u64;
+                carry = total >> 64;
+            }
+            product[i + 4] = carry as u64;
+        }
+        JubjubScalar(reduce(&product))
+    }
+}
+
+// Remainder of the division of the little-endian integer `limbs` by r, bit by bit.
+fn reduce(limbs: &[u64]) -> [u64; 4] {
+    let mut remainder = [0u64; 4];
+    for limb in limbs.iter().rev() {
+        for i in (0..64).rev() {
+            // The remainder is smaller than 2^252, so that doubling it does not overflow.
+            let mut carry = (limb >> i) & 1;
+            for r in remainder.iter_mut() {
+                let next_carry = *r >> 63;
+                *r = (*r << 1) | carry;
+                carry = next_carry;
+            }
+
+            if remainder.iter().rev().cmp(ORDER.iter().rev()) != std::cmp::Ordering::Less {
+                let mut borrow = 0;
+                for (r, m) in remainder.iter_mut().zip(ORDER.iter()) {
+                    let (difference, overflow_1) = r.overflowing_sub(*m);
+                    let (difference, overflow_2) = difference.overflowing_sub(borrow);
+                    *r = difference;
+                    borrow = (overflow_1 || overflow_2) as u64;
+                }
+            }
+        }
+    }
+    remainder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_group() {
+        let generator = JubjubPoint::generator();
+        assert!(generator.is_on_curve());
+        assert_ne!(generator, JubjubPoint::identity());
+
+        let mut order = [0u8; 32];
+        for (chunk, limb) in order.chunks_mut(8).zip(ORDER.iter()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        assert_eq!(generator.mul(&order), JubjubPoint::identity());
+
+        // The scalar multiplication is compatible with the arithmetic modulo r.
+        let (a, b) = (JubjubScalar::random(OsRng), JubjubScalar::random(OsRng));
+        let a_g = generator.mul(&a.to_bytes());
+        let b_g = generator.mul(&b.to_bytes());
+        assert_eq!(a_g.add(&b_g), generator.mul(&a.add(&b).to_bytes()));
+        assert_eq!(a_g.mul(&b.to_bytes()), generator.mul(&a.mul(&b).to_bytes()));
+        assert_eq!(JubjubScalar::from_scalar(&a.to_scalar()), a);
+    }
+}
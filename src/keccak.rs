@@ -0,0 +1,148 @@
+// Keccak-f[1600] permutation, shared by STROBE (for Merlin transcripts) and by Keccak-256,
+// the hash of the EVM, which the `KeccakTranscript` uses so that a Solidity contract can
+// recompute its challenges.
+
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+const ROTATIONS: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+const PERMUTATION: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+pub(crate) fn keccak_f1600(lanes: &mut [u64; 25]) {
+    for round_constant in ROUND_CONSTANTS {
+        // theta
+        let mut columns = [0u64; 5];
+        for x in 0..5 {
+            columns[x] = lanes[x] ^ lanes[x + 5] ^ lanes[x + 10] ^ lanes[x + 15] ^ lanes[x + 20];
+        }
+        for x in 0..5 {
+            let d = columns[(x + 4) % 5] ^ columns[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                lanes[5 * y + x] ^= d;
+            }
+        }
+
+        // rho and pi
+        let mut last = lanes[1];
+        for (rotation, position) in ROTATIONS.iter().zip(PERMUTATION.iter()) {
+            let temp = lanes[*position];
+            lanes[*position] = last.rotate_left(*rotation);
+            last = temp;
+        }
+
+        // chi
+        for y in 0..5 {
+            let mut row = [0u64; 5];
+            row.copy_from_slice(&lanes[5 * y..5 * y + 5]);
+            for x in 0..5 {
+                lanes[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // iota
+        lanes[0] ^= round_constant;
+    }
+}
+
+pub(crate) fn keccak_f1600_bytes(state: &mut [u8; 200]) {
+    let mut lanes = [0u64; 25];
+    for (lane, bytes) in lanes.iter_mut().zip(state.chunks_exact(8)) {
+        *lane = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+    keccak_f1600(&mut lanes);
+    for (lane, bytes) in lanes.iter().zip(state.chunks_exact_mut(8)) {
+        bytes.copy_from_slice(&lane.to_le_bytes());
+    }
+}
+
+const KECCAK_256_RATE: usize = 136;
+
+/// Keccak-256 as used by Ethereum, which is SHA3-256 with the original padding 0x01 instead
+/// of 0x06.
+pub(crate) fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut state = [0u8; 200];
+    let mut chunks = data.chunks_exact(KECCAK_256_RATE);
+    for chunk in &mut chunks {
+        for (byte, value) in state.iter_mut().zip(chunk) {
+            *byte ^= value;
+        }
+        keccak_f1600_bytes(&mut state);
+    }
+    let remainder = chunks.remainder();
+    for (byte, value) in state.iter_mut().zip(remainder) {
+        *byte ^= value;
+    }
+    state[remainder.len()] ^= 0x01;
+    state[KECCAK_256_RATE - 1] ^= 0x80;
+    keccak_f1600_bytes(&mut state);
+
+    state[..32].try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keccak() {
+        // SHA3-256 of the empty string, which is a single permutation of the padded state.
+        let mut state = [0u8; 200];
+        state[0] ^= 0x06;
+        state[135] ^= 0x80;
+        keccak_f1600_bytes(&mut state);
+
+        let expected = [
+            0xa7, 0xff, 0xc6, 0xf8, 0xbf, 0x1e, 0xd7, 0x66, 0x51, 0xc1, 0x47, 0x56, 0xa0, 0x61,
+            0xd6, 0x62, 0xf5, 0x80, 0xff, 0x4d, 0xe4, 0x3b, 0x49, 0xfa, 0x82, 0xd8, 0x0a, 0x4b,
+            0x80, 0xf8, 0x43, 0x4a,
+        ];
+        assert_eq!(state[..32], expected);
+    }
+
+    #[test]
+    fn test_keccak256() {
+        let hex =
+            |bytes: [u8; 32]| -> String { bytes.iter().map(|b| format!("{:02x}", b)).collect() };
+        assert_eq!(
+            hex(keccak256(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            hex(keccak256(b"hello")),
+            "1c8aff950685c2ed4bc3174f3472287b56d9517b9c948127319a09a7a36deac8"
+        );
+        // Inputs longer than the rate absorb several blocks, and 135 bytes fill a block with
+        // both padding bytes.
+        assert_ne!(keccak256(&[0u8; 136]), keccak256(&[0u8; 137]));
+        assert_ne!(keccak256(&[0u8; 135]), keccak256(&[0u8; 134]));
+    }
+}
@@ -1,208 +1,1015 @@
 #![allow(non_snake_case)]
-use crate::polynomial::Polynomial;
-use crate::transcript::Transcript;
+use crate::error::PlonkError;
+use crate::parallel;
+use crate::polynomial::{
+    fft_in_place, EvaluationDomain, Evaluations, Polynomial, PolynomialEvaluationPoints,
+    SparsePolynomial,
+};
+use crate::transcript::{Blake2bTranscript, Transcript};
 use crate::{define_add_variants, define_mul_variants};
+use alloc::vec;
+use alloc::vec::Vec;
 use blstrs::*;
-use ff::Field;
+use core::cmp::min;
+use core::ops::{Add, Mul, Neg};
+use ff::{Field, PrimeField};
 use group::prime::PrimeCurveAffine;
-use group::Curve;
-use rand_chacha::ChaCha20Rng;
-use rand_core::SeedableRng;
-use std::ops::{Add, Mul, Neg};
-
-pub struct Kzg10<const MAX_GATES: usize> {
-    pub powers_x_g1: [G1Affine; MAX_GATES], // This will have as size the max number of gates allowed.
-    pub powers_x_g2: [G2Affine; 2],         // we only have power 0 and 1
+use group::{Curve, Group};
+use pairing::{MillerLoopResult, MultiMillerLoop};
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::io::Read;
+use zeroize::Zeroize;
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Kzg10 {
+    pub powers_x_g1: Vec<G1Affine>, // This will have as size the max number of coefficients allowed.
+    pub powers_x_g2: [G2Affine; 2], // we only have power 0 and 1
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Kzg10Commitment(pub(crate) G1Affine);
 
-pub struct Kzg10BatchProof(Kzg10Commitment, Kzg10Commitment);
+/// Opening of several polynomials at the same point. It contains the claimed evaluations, and
+/// a single witness for their random linear combination.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Kzg10Opening {
+    pub evaluations: Vec<Scalar>,
+    pub witness: Kzg10Commitment,
+}
 
-impl<const MAX_GATES: usize> Kzg10<MAX_GATES> {
-    pub fn setup() -> Self {
-        let toxic_waste = Scalar::random(&mut ChaCha20Rng::from_seed([0u8; 32]));
-        let mut powers_x_g1 = [G1Affine::default(); MAX_GATES];
+/// Openings of two groups of polynomials, the first at one point and the second at another,
+/// checked with a single pairing equation. This is the form of the openings of PLONK, at zeta
+/// and at zeta * omega.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TwoPointOpening {
+    pub openings: [Kzg10Opening; 2],
+}
+
+// Coefficients of the right-hand side of the check of a `TwoPointOpening`, for the challenge u:
+// those of the commitments of each group, of g1 and of the two witnesses.
+pub(crate) struct TwoPointCheck {
+    pub(crate) u: Scalar,
+    pub(crate) commitments: [Vec<Scalar>; 2],
+    pub(crate) generator: Scalar,
+    pub(crate) witnesses: [Scalar; 2],
+}
+
+/// Proof that an SRS was obtained from the previous one by multiplying its toxic waste by a
+/// secret s, which the contributor knows. It contains s * g1 and s * g2, and a Schnorr proof of
+/// knowledge of the discrete logarithm of s * g1.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Kzg10Contribution {
+    pub secret_g1: G1Affine,
+    pub secret_g2: G2Affine,
+    pub nonce_g1: G1Affine,
+    pub response: Scalar,
+}
+
+/// Encoding of the points in a Powers of Tau file of the Zcash ceremony. Challenge files
+/// store uncompressed points, while response files store them compressed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PowersOfTauFormat {
+    Challenge,
+    Response,
+}
+
+#[cfg(feature = "std")]
+impl PowersOfTauFormat {
+    fn g1_size(&self) -> usize {
+        match self {
+            PowersOfTauFormat::Challenge => 96,
+            PowersOfTauFormat::Response => 48,
+        }
+    }
+
+    fn g2_size(&self) -> usize {
+        match self {
+            PowersOfTauFormat::Challenge => 192,
+            PowersOfTauFormat::Response => 96,
+        }
+    }
+
+    fn read_g1<R: Read>(&self, reader: &mut R) -> Result<G1Affine, PlonkError> {
+        let point = match self {
+            PowersOfTauFormat::Challenge => {
+                let mut bytes = [0u8; 96];
+                reader.read_exact(&mut bytes)?;
+                G1Affine::from_uncompressed(&bytes)
+            }
+            PowersOfTauFormat::Response => {
+                let mut bytes = [0u8; 48];
+                reader.read_exact(&mut bytes)?;
+                G1Affine::from_compressed(&bytes)
+            }
+        };
+        Option::from(point).ok_or(PlonkError::InvalidPoint)
+    }
+
+    fn read_g2<R: Read>(&self, reader: &mut R) -> Result<G2Affine, PlonkError> {
+        let point = match self {
+            PowersOfTauFormat::Challenge => {
+                let mut bytes = [0u8; 192];
+                reader.read_exact(&mut bytes)?;
+                G2Affine::from_uncompressed(&bytes)
+            }
+            PowersOfTauFormat::Response => {
+                let mut bytes = [0u8; 96];
+                reader.read_exact(&mut bytes)?;
+                G2Affine::from_compressed(&bytes)
+            }
+        };
+        Option::from(point).ok_or(PlonkError::InvalidPoint)
+    }
+}
+
+impl Kzg10 {
+    /// Generates an SRS supporting polynomials with up to `size` coefficients, with a toxic
+    /// waste drawn from `rng`. Whoever knows the toxic waste can forge proofs, so the randomness
+    /// must not be reproducible by anyone else (a seeded RNG is only for tests).
+    pub fn setup<R: RngCore + CryptoRng>(size: usize, rng: &mut R) -> Self {
+        Self::from_toxic_waste(size, &Scalar::random(rng))
+    }
+
+    pub(crate) fn from_toxic_waste(size: usize, toxic_waste: &Scalar) -> Self {
         let mut powers_x_g2 = [G2Affine::default(); 2];
 
         powers_x_g2[0] = G2Affine::generator();
         powers_x_g2[1] = (G2Affine::generator() * toxic_waste).to_affine();
 
-        let mut temp_g1 = G1Affine::generator();
+        let powers_x_g1 = fixed_base_mul(&G1Affine::generator(), &powers(toxic_waste, size));
 
-        for powers in powers_x_g1.iter_mut() {
-            *powers = temp_g1;
-            temp_g1 = (temp_g1 * toxic_waste).to_affine(); // Doing unnecessary addition at last iteration, who cares?
+        Self {
+            powers_x_g1,
+            powers_x_g2,
         }
+    }
 
-        Self {
+    /// Loads the SRS from a Powers of Tau file of the Zcash ceremony (or of the Perpetual Powers
+    /// of Tau over BLS12-381). The file starts with a 64 byte hash, followed by the accumulator:
+    /// `2 * tau_powers_length - 1` powers of tau in G1, `tau_powers_length` powers of tau in G2,
+    /// and then the alpha and beta powers, which we don't need. The ceremony of Zcash used
+    /// `tau_powers_length = 2^21`. We only load the first `size` powers in G1.
+    #[cfg(feature = "std")]
+    pub fn from_powers_of_tau<R: Read>(
+        mut reader: R,
+        format: PowersOfTauFormat,
+        tau_powers_length: usize,
+        size: usize,
+    ) -> Result<Self, PlonkError> {
+        let tau_powers_g1_length = 2 * tau_powers_length - 1;
+        if size > tau_powers_g1_length || size == 0 || tau_powers_length < 2 {
+            return Err(PlonkError::InvalidSrs);
+        }
+
+        let mut hash = [0u8; 64];
+        reader.read_exact(&mut hash)?;
+
+        let mut powers_x_g1 = Vec::with_capacity(size);
+        for _ in 0..size {
+            powers_x_g1.push(format.read_g1(&mut reader)?);
+        }
+
+        // We skip the powers in G1 that we don't need.
+        let skip = ((tau_powers_g1_length - size) * format.g1_size()) as u64;
+        let skipped = std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())?;
+        if skipped != skip {
+            return Err(PlonkError::Io("Unexpected end of file".to_string()));
+        }
+
+        let powers_x_g2 = [format.read_g2(&mut reader)?, format.read_g2(&mut reader)?];
+
+        let kzg10 = Self {
             powers_x_g1,
             powers_x_g2,
+        };
+        kzg10.validate()?;
+
+        Ok(kzg10)
+    }
+
+    /// Checks that the SRS is of the form (g1, x * g1, x^2 * g1, ...), (g2, x * g2), as it should
+    /// be done for SRS loaded from files of third parties. Instead of checking
+    /// e(x^{i+1} * g1, g2) = e(x^i * g1, x * g2) for every power, we check the combination of all
+    /// of them with the powers of a scalar r derived from the SRS, which only takes two MSMs and
+    /// two pairings. An SRS that is not well formed passes with probability at most size / |F|.
+    pub fn validate(&self) -> Result<(), PlonkError> {
+        if self.powers_x_g1.first() != Some(&G1Affine::generator())
+            || self.powers_x_g2[0] != G2Affine::generator()
+            || bool::from(self.powers_x_g2[1].is_identity())
+        {
+            return Err(PlonkError::InvalidSrs);
         }
+
+        let mut transcript = Blake2bTranscript::new(b"kzg10 validation");
+        for power in self.powers_x_g1.iter() {
+            transcript.append_point(b"x^i * g1", power);
+        }
+        transcript.append_message(b"x * g2", &self.powers_x_g2[1].to_compressed());
+        let r = transcript.challenge_scalar(b"r");
+
+        let last = self.powers_x_g1.len() - 1;
+        let r_powers = powers(&r, last);
+        let shifted = msm(&self.powers_x_g1[1..], &r_powers).to_affine();
+        let unshifted = msm(&self.powers_x_g1[..last], &r_powers).to_affine();
+        pairing_check(&self.powers_x_g2, &unshifted, &shifted).or(Err(PlonkError::InvalidSrs))
+    }
+
+    /// Updates the SRS of toxic waste x to the one of toxic waste `secret` * x, so that the SRS
+    /// is sound as long as a single contributor of a ceremony discards their secret. The
+    /// returned proof lets anyone check the update with `verify_contribution`. A zero secret
+    /// is rejected, and leaves the SRS unchanged.
+    pub fn contribute(&mut self, secret: Scalar) -> Result<Kzg10Contribution, PlonkError> {
+        if bool::from(secret.is_zero()) {
+            return Err(PlonkError::ZeroSecret);
+        }
+        let secret_powers = powers(&secret, self.powers_x_g1.len());
+        let updated = parallel::map(&self.powers_x_g1, |i, power| power * secret_powers[i]);
+        G1Projective::batch_normalize(&updated, &mut self.powers_x_g1);
+        self.powers_x_g2[1] = (self.powers_x_g2[1] * secret).to_affine();
+
+        let secret_g1 = (G1Affine::generator() * secret).to_affine();
+        let secret_g2 = (G2Affine::generator() * secret).to_affine();
+        // The nonce is derived from the secret and the updated SRS, so that we never reuse it
+        // for two different challenges.
+        let mut nonce_transcript = Blake2bTranscript::new(b"kzg10 contribution nonce");
+        nonce_transcript.append_scalar(b"secret", &secret);
+        nonce_transcript.append_point(b"x * g1", &self.powers_x_g1[1]);
+        let nonce = nonce_transcript.challenge_scalar(b"nonce");
+        let nonce_g1 = (G1Affine::generator() * nonce).to_affine();
+        let challenge = Self::contribution_challenge(&self.powers_x_g1[1], &secret_g1, &nonce_g1);
+
+        Ok(Kzg10Contribution {
+            secret_g1,
+            secret_g2,
+            nonce_g1,
+            response: nonce + challenge * secret,
+        })
+    }
+
+    /// Checks that the SRS is well formed and that it is the update of `previous` by the
+    /// contribution of `proof`.
+    pub fn verify_contribution(
+        &self,
+        previous: &Kzg10,
+        proof: &Kzg10Contribution,
+    ) -> Result<(), PlonkError> {
+        if self.powers_x_g1.len() != previous.powers_x_g1.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: previous.powers_x_g1.len(),
+                got: self.powers_x_g1.len(),
+            });
+        }
+        self.validate()?;
+
+        // The contributor knows s, s * g1 and s * g2 have the same discrete logarithm, and the
+        // toxic waste was multiplied by s.
+        let challenge =
+            Self::contribution_challenge(&self.powers_x_g1[1], &proof.secret_g1, &proof.nonce_g1);
+        let g1 = G1Affine::generator();
+        let g2 = G2Affine::generator();
+        if g1 * proof.response != proof.nonce_g1 + proof.secret_g1 * challenge
+            || bool::from(proof.secret_g1.is_identity())
+            || pairing(&proof.secret_g1, &g2) != pairing(&g1, &proof.secret_g2)
+            || pairing(&self.powers_x_g1[1], &g2)
+                != pairing(&previous.powers_x_g1[1], &proof.secret_g2)
+        {
+            return Err(PlonkError::VerificationFailed);
+        }
+
+        Ok(())
+    }
+
+    fn contribution_challenge(
+        x_g1: &G1Affine,
+        secret_g1: &G1Affine,
+        nonce_g1: &G1Affine,
+    ) -> Scalar {
+        let mut transcript = Blake2bTranscript::new(b"kzg10 contribution");
+        transcript.append_point(b"x * g1", x_g1);
+        transcript.append_point(b"s * g1", secret_g1);
+        transcript.append_point(b"nonce", nonce_g1);
+        transcript.challenge_scalar(b"challenge")
     }
 
     pub fn commit(&self, polynomial: &Polynomial) -> Kzg10Commitment {
         assert!(
-            polynomial.0.len() <= MAX_GATES,
+            polynomial.0.len() <= self.powers_x_g1.len(),
             "Polynomial degree not supported"
         );
-        let mut commitment = G1Affine::identity();
-        for (srs, coefficient) in self.powers_x_g1.iter().zip(polynomial.0.iter()) {
-            commitment = (commitment + srs * coefficient).to_affine();
+        // The terms are summed in one batch per thread.
+        let batch = polynomial.0.len().div_ceil(parallel::threads()).max(1);
+        let coefficients = polynomial.0.chunks(batch).collect::<Vec<_>>();
+        let commitment = parallel::map(&coefficients, |index, coefficients| {
+            msm(&self.powers_x_g1[index * batch..], coefficients)
+        })
+        .into_iter()
+        .sum::<G1Projective>();
+
+        Kzg10Commitment(commitment.to_affine())
+    }
+}
+
+impl Kzg10 {
+    /// Opens all `polynomials` at `point` with a single group element. The polynomials are
+    /// combined as sum v^i * p_i(X), with the challenge v drawn from the transcript after
+    /// absorbing the commitments, the point and the evaluations.
+    pub fn open_batch<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        point: &Scalar,
+        transcript: &mut T,
+    ) -> Kzg10Opening {
+        let commitments = polynomials
+            .iter()
+            .map(|poly| self.commit(poly))
+            .collect::<Vec<_>>();
+        let evaluations = polynomials
+            .iter()
+            .map(|poly| poly.eval(point))
+            .collect::<Vec<_>>();
+
+        let v = Self::batch_challenge(&commitments, point, &evaluations, transcript);
+
+        let mut h_x = Polynomial::zero(self.powers_x_g1.len());
+        let mut v_powers = Scalar::one();
+        for (poly, evaluation) in polynomials.iter().zip(evaluations.iter()) {
+            let mut temp_poly = poly.clone();
+            temp_poly.0[0] -= evaluation;
+            h_x += &(temp_poly / Polynomial(vec![point.neg(), Scalar::one()])) * v_powers;
+            v_powers *= v;
         }
 
-        Kzg10Commitment(commitment)
+        Kzg10Opening {
+            evaluations,
+            witness: self.commit(&h_x),
+        }
     }
 
-    /// We simplify this function as is described in the paper. The open protocol for multiple evaluation points assumes
-    /// that there are only two distinct evaluation points.
-    #[allow(clippy::too_many_arguments)]
-    pub fn batch_prove(
+    /// Checks an opening produced by `open_batch` of the polynomials committed in `commitments`.
+    pub fn check_batch<T: Transcript>(
         &self,
-        polynomials_a: &[Polynomial],
-        polynomials_b: &[Polynomial],
-        commitments_a: &[Kzg10Commitment],
-        commitments_b: &[Kzg10Commitment],
-        eval_a: &Scalar,
-        eval_b: &Scalar,
-        output_a: &[Scalar],
-        output_b: &[Scalar],
-        transcript: &mut Transcript,
-    ) -> Kzg10BatchProof {
-        assert!(
-            polynomials_a.iter().any(|poly| poly.0.len() <= MAX_GATES),
-            "Polynomial degree not supported"
-        );
-        assert!(
-            polynomials_b.iter().any(|poly| poly.0.len() <= MAX_GATES),
-            "Polynomial degree not supported"
-        );
+        commitments: &[Kzg10Commitment],
+        point: &Scalar,
+        opening: &Kzg10Opening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        if commitments.len() != opening.evaluations.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: commitments.len(),
+                got: opening.evaluations.len(),
+            });
+        }
+
+        let v = Self::batch_challenge(commitments, point, &opening.evaluations, transcript);
+
+        let mut F = G1Projective::identity();
+        let mut v_powers = Scalar::one();
+        for (commitment, evaluation) in commitments.iter().zip(opening.evaluations.iter()) {
+            F += commitment.0 * v_powers - G1Affine::generator() * (v_powers * evaluation);
+            v_powers *= v;
+        }
+
+        // F = sum v^i * (C_i - y_i * g1), which should be the commitment of (X - point) * h(X).
+        let lhs_g1 = F + opening.witness.0 * point;
+        pairing_check(&self.powers_x_g2, &opening.witness.0, &lhs_g1.to_affine())
+    }
+
+    /// Opens `polynomials[0]` at `points[0]` and `polynomials[1]` at `points[1]`, with one
+    /// witness per group for sum v^j * (p_j(X) - p_j(point)) / (X - point). This is the opening
+    /// of PLONK, at zeta and zeta * omega, whose proofs contain the commitments and the
+    /// evaluations: unlike `open_batch`, they are not absorbed here, and must be bound to the
+    /// transcript before. The challenge v is drawn first, and the challenge u, that combines
+    /// the checks of both witnesses, after them.
+    pub fn open_two_points<T: Transcript>(
+        &self,
+        polynomials: [&[&Polynomial]; 2],
+        points: &[Scalar; 2],
+        transcript: &mut T,
+    ) -> TwoPointOpening {
+        let v = transcript.challenge_scalar(b"v");
+
+        let openings = [0, 1].map(|i| {
+            let evaluations = polynomials[i]
+                .iter()
+                .map(|poly| poly.eval(&points[i]))
+                .collect::<Vec<_>>();
+            let mut h_x = Polynomial::zero(self.powers_x_g1.len());
+            let mut v_powers = Scalar::one();
+            for (poly, evaluation) in polynomials[i].iter().zip(evaluations.iter()) {
+                h_x += *poly * v_powers;
+                h_x.0[0] -= v_powers * evaluation;
+                v_powers *= v;
+            }
+            h_x = h_x / Polynomial(vec![points[i].neg(), Scalar::one()]);
+            let witness = self.commit(&h_x);
+            // The polynomials of a proof depend on the witness, and so does their combination.
+            h_x.zeroize();
+            Kzg10Opening {
+                evaluations,
+                witness,
+            }
+        });
+        Self::two_points_challenge(&openings, transcript);
+
+        TwoPointOpening { openings }
+    }
+
+    /// Checks an opening produced by `open_two_points` of the polynomials committed in
+    /// `commitments`. With the witnesses W_0 and W_1, it checks e(W_0 + u * W_1, x * g2) =
+    /// e(R_0 + u * R_1, g2), with R_i = sum v^j * (C_ij - y_ij * g1) + points_i * W_i, which
+    /// only holds for both openings at once but with negligible probability.
+    pub fn check_two_points<T: Transcript>(
+        &self,
+        commitments: [&[Kzg10Commitment]; 2],
+        points: &[Scalar; 2],
+        opening: &TwoPointOpening,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        for (commitments, opening) in commitments.iter().zip(opening.openings.iter()) {
+            if commitments.len() != opening.evaluations.len() {
+                return Err(PlonkError::InvalidLength {
+                    expected: commitments.len(),
+                    got: opening.evaluations.len(),
+                });
+            }
+        }
+
+        let check = Self::two_points_check(points, opening, transcript);
+        let bases = commitments
+            .iter()
+            .flat_map(|commitments| commitments.iter().map(|commitment| commitment.0))
+            .chain([G1Affine::generator()])
+            .chain(opening.openings.iter().map(|opening| opening.witness.0))
+            .collect::<Vec<_>>();
+        let scalars = check
+            .commitments
+            .iter()
+            .flatten()
+            .copied()
+            .chain([check.generator])
+            .chain(check.witnesses)
+            .collect::<Vec<_>>();
+
+        let [witness_0, witness_1] = [0, 1].map(|i| opening.openings[i].witness.0);
+        let lhs = witness_0 + witness_1 * check.u;
+        pairing_check(
+            &self.powers_x_g2,
+            &lhs.to_affine(),
+            &msm(&bases, &scalars).to_affine(),
+        )
+    }
+
+    // Draws the challenges of `check_two_points`, and returns the coefficients of the points
+    // in R_0 + u * R_1. The PLONK verifier uses them with the commitment to the linearisation,
+    // which it never computes, expanded.
+    pub(crate) fn two_points_check<T: Transcript>(
+        points: &[Scalar; 2],
+        opening: &TwoPointOpening,
+        transcript: &mut T,
+    ) -> TwoPointCheck {
+        let v = transcript.challenge_scalar(b"v");
+        let u = Self::two_points_challenge(&opening.openings, transcript);
 
-        let len_a = commitments_a.len();
-        let len_b = commitments_b.len();
-        // First we use the transcript to generate two scalars.
-        // The code here will panic if not all vectors are the same, but that's fine here,
-        // as we really need to enforce that.
-        // NOTE: This is also insecure. In a real-world scenario we would need to change the label of each
-        // commitment. But it is a bit of a pain to do (see here ark-plonk/somewhere).
-        transcript.append_scalar(b"eval group 1", eval_a);
-        for i in 0..len_a {
-            transcript.append_point(b"commitments group 1", &commitments_a[i].0);
-            transcript.append_scalar(b"output group 1", &output_a[i]);
+        let mut generator = Scalar::zero();
+        let mut u_power = Scalar::one();
+        let commitments = [0, 1].map(|i| {
+            let mut coefficient = u_power;
+            let coefficients = opening.openings[i]
+                .evaluations
+                .iter()
+                .map(|evaluation| {
+                    generator -= coefficient * evaluation;
+                    let current = coefficient;
+                    coefficient *= v;
+                    current
+                })
+                .collect::<Vec<_>>();
+            u_power *= u;
+            coefficients
+        });
+
+        TwoPointCheck {
+            u,
+            commitments,
+            generator,
+            witnesses: [points[0], u * points[1]],
         }
+    }
 
-        transcript.append_scalar(b"eval group 1", eval_b);
-        for j in 0..len_b {
-            transcript.append_point(b"commitments group 1", &commitments_b[j].0);
-            transcript.append_scalar(b"output group 1", &output_b[j]);
+    // The challenge that combines the two openings, drawn after both witnesses, with the
+    // labels of the witnesses of PLONK.
+    fn two_points_challenge<T: Transcript>(
+        openings: &[Kzg10Opening; 2],
+        transcript: &mut T,
+    ) -> Scalar {
+        transcript.append_point(b"w_omega comm", &openings[0].witness.0);
+        transcript.append_point(b"w_omega_zeta comm", &openings[1].witness.0);
+        transcript.challenge_scalar(b"u")
+    }
+
+    fn batch_challenge<T: Transcript>(
+        commitments: &[Kzg10Commitment],
+        point: &Scalar,
+        evaluations: &[Scalar],
+        transcript: &mut T,
+    ) -> Scalar {
+        transcript.append_scalar(b"opening point", point);
+        for (commitment, evaluation) in commitments.iter().zip(evaluations.iter()) {
+            transcript.append_point(b"opened commitment", &commitment.0);
+            transcript.append_scalar(b"opened evaluation", evaluation);
         }
 
-        let gamma = transcript.challenge_scalar(b"gamma");
-        let gammaprime = transcript.challenge_scalar(b"gammaprime");
+        transcript.challenge_scalar(b"opening challenge")
+    }
+}
+
+/// SHPLONK opening of several polynomials, each at its own set of points, with two group
+/// elements regardless of the number of polynomials and points. `evaluations[i][j]` is the
+/// evaluation of the i-th polynomial at its j-th point.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MultiOpen {
+    pub evaluations: Vec<Vec<Scalar>>,
+    pub(crate) w: Kzg10Commitment,
+    pub(crate) w_prime: Kzg10Commitment,
+}
 
-        // Now we compute h(X) and h'(X) polynomials
-        let mut h_x = Polynomial::zero(MAX_GATES);
+impl Kzg10 {
+    /// Opens each polynomial f_i at the (distinct) points S_i, following the SHPLONK paper (BDFG20). Let
+    /// T be the union of all S_i. The prover commits to h(X) = sum gamma^i * (f_i - r_i) / Z_{S_i},
+    /// where r_i interpolates f_i over S_i, and then proves that the linearisation
+    /// L(X) = sum gamma^i * Z_{T \ S_i}(z) * (f_i - r_i(z)) - Z_T(z) * h(X) vanishes at a random z.
+    pub fn multi_open<T: Transcript>(
+        &self,
+        polynomials: &[Polynomial],
+        points: &[Vec<Scalar>],
+        transcript: &mut T,
+    ) -> MultiOpen {
+        assert_eq!(
+            polynomials.len(),
+            points.len(),
+            "a set of points per polynomial"
+        );
+
+        let commitments = polynomials
+            .iter()
+            .map(|poly| self.commit(poly))
+            .collect::<Vec<_>>();
+        let evaluations = polynomials
+            .iter()
+            .zip(points.iter())
+            .map(|(poly, set)| set.iter().map(|point| poly.eval(point)).collect())
+            .collect::<Vec<Vec<_>>>();
+
+        let gamma = Self::multi_open_commitments(&commitments, points, &evaluations, transcript);
+
+        let mut h_x = Polynomial::zero(self.powers_x_g1.len());
+        let mut remainders = Vec::with_capacity(polynomials.len());
         let mut gamma_powers = Scalar::one();
-        for poly in polynomials_a {
-            let mut temp_poly = poly.clone();
-            // we subtract the polynomial evaluated at the evaluation point
-            temp_poly.0[0] -= poly.eval(eval_a);
-            // we divide by the monomial X - eval_a
-            let poly_division = temp_poly / Polynomial(vec![eval_a.neg(), Scalar::one()]);
-            h_x += &poly_division * gamma_powers;
+        for (poly, set) in polynomials.iter().zip(points.iter()) {
+            let (quotient, remainder) = poly.divide_with_remainder(&Polynomial::vanishing(set));
+            h_x += &quotient * gamma_powers;
+            remainders.push(remainder);
             gamma_powers *= gamma;
         }
+        let w = self.commit(&h_x);
 
-        let mut h_prime_x = Polynomial::zero(MAX_GATES);
+        transcript.append_point(b"multi open w", &w.0);
+        let z = transcript.challenge_scalar(b"multi open z");
 
-        let mut gammaprime_powers = Scalar::one();
-        for poly in polynomials_b {
-            let mut temp_poly = poly.clone();
-            // we subtract the polynomial evaluated at the evaluation point
-            temp_poly.0[0] -= poly.eval(eval_b);
-            // we divide by the monomial X - eval_b
-            let poly_division = temp_poly / Polynomial(vec![eval_b.neg(), Scalar::one()]);
-            h_prime_x += &poly_division * gammaprime_powers;
-
-            // we update the power of gamma
-            gammaprime_powers *= gammaprime;
+        let all_points = union_points(points);
+        let mut l_x = &h_x * Polynomial::vanishing(&all_points).eval(&z).neg();
+        let mut gamma_powers = Scalar::one();
+        for ((poly, set), remainder) in polynomials.iter().zip(points.iter()).zip(remainders) {
+            let scalar = gamma_powers * vanishing_complement(&all_points, set, &z);
+            l_x += &(poly + remainder.eval(&z).neg()) * scalar;
+            gamma_powers *= gamma;
         }
+        let (quotient, _) = l_x.divide_with_remainder(&Polynomial(vec![z.neg(), Scalar::one()]));
 
-        Kzg10BatchProof(self.commit(&h_x), self.commit(&h_prime_x))
+        MultiOpen {
+            evaluations,
+            w,
+            w_prime: self.commit(&quotient),
+        }
     }
 
-    /// We simplify this function as is described in the paper. The open protocol for multiple evaluation points assumes
-    /// that there are only two distinct evaluation points.
-    #[allow(clippy::too_many_arguments)]
-    pub fn batch_verify(
+    /// Checks an opening produced by `multi_open` of the polynomials committed in
+    /// `commitments`, at the same sets of points.
+    pub fn check_multi_open<T: Transcript>(
         &self,
-        proof: &Kzg10BatchProof,
-        commitments_a: &[Kzg10Commitment],
-        commitments_b: &[Kzg10Commitment],
-        eval_a: &Scalar,
-        eval_b: &Scalar,
-        output_a: &[Scalar],
-        output_b: &[Scalar],
-        transcript: &mut Transcript,
-    ) -> Result<(), ()> {
-        let len_a = commitments_a.len();
-        let len_b = commitments_b.len();
-        // First we use the transcript to generate two scalars.
-        // The code here will panic if not all vectors are the same, but that's fine here,
-        // as we really need to enforce that.
-        // NOTE: This is also insecure. In a real-world scenario we would need to change the label of each
-        // commitment. But it is a bit of a pain to do (see here ark-plonk/somewhere).
-        transcript.append_scalar(b"eval group 1", eval_a);
-        for i in 0..len_a {
-            transcript.append_point(b"commitments group 1", &commitments_a[i].0);
-            transcript.append_scalar(b"output group 1", &output_a[i]);
-        }
-
-        transcript.append_scalar(b"eval group 1", eval_b);
-        for j in 0..len_b {
-            transcript.append_point(b"commitments group 1", &commitments_b[j].0);
-            transcript.append_scalar(b"output group 1", &output_b[j]);
-        }
-
-        let gamma = transcript.challenge_scalar(b"gamma");
-        let gammaprime = transcript.challenge_scalar(b"gammaprime");
-
-        let rprime = transcript.challenge_scalar(b"rprime");
-
-        let mut F = G1Affine::identity();
+        commitments: &[Kzg10Commitment],
+        points: &[Vec<Scalar>],
+        proof: &MultiOpen,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        if commitments.len() != points.len() || commitments.len() != proof.evaluations.len() {
+            return Err(PlonkError::InvalidLength {
+                expected: commitments.len(),
+                got: min(points.len(), proof.evaluations.len()),
+            });
+        }
+        for (set, evaluations) in points.iter().zip(proof.evaluations.iter()) {
+            if set.len() != evaluations.len() {
+                return Err(PlonkError::InvalidLength {
+                    expected: set.len(),
+                    got: evaluations.len(),
+                });
+            }
+        }
+
+        let gamma =
+            Self::multi_open_commitments(commitments, points, &proof.evaluations, transcript);
+        transcript.append_point(b"multi open w", &proof.w.0);
+        let z = transcript.challenge_scalar(b"multi open z");
 
+        // F = sum gamma^i * Z_{T \ S_i}(z) * (C_i - r_i(z) * g1) - Z_T(z) * W, which should be
+        // the commitment of (X - z) * W'(X).
+        let all_points = union_points(points);
+        let mut F = proof.w.0 * Polynomial::vanishing(&all_points).eval(&z).neg();
         let mut gamma_powers = Scalar::one();
-        for i in 0..len_a {
-            F = (F + commitments_a[i].0 * gamma_powers
-                - G1Affine::generator() * (gamma_powers * output_a[i]))
-                .to_affine();
+        for ((commitment, set), evaluations) in commitments
+            .iter()
+            .zip(points.iter())
+            .zip(proof.evaluations.iter())
+        {
+            let interpolation = PolynomialEvaluationPoints(
+                set.iter()
+                    .copied()
+                    .zip(evaluations.iter().copied())
+                    .collect(),
+            )
+            .interpolate();
+            let scalar = gamma_powers * vanishing_complement(&all_points, set, &z);
+            F += (commitment.0 - G1Affine::generator() * interpolation.eval(&z)) * scalar;
             gamma_powers *= gamma;
         }
 
-        let mut gammaprime_powers = Scalar::one();
-        for j in 0..len_b {
-            F = (F + commitments_b[j].0 * (rprime * gammaprime_powers)
-                - G1Affine::generator() * (rprime * gammaprime_powers * output_b[j]))
-                .to_affine();
-            gammaprime_powers *= gammaprime;
+        let lhs_g1 = F + proof.w_prime.0 * z;
+
+        pairing_check(&self.powers_x_g2, &proof.w_prime.0, &lhs_g1.to_affine())
+    }
+
+    fn multi_open_commitments<T: Transcript>(
+        commitments: &[Kzg10Commitment],
+        points: &[Vec<Scalar>],
+        evaluations: &[Vec<Scalar>],
+        transcript: &mut T,
+    ) -> Scalar {
+        for ((commitment, set), evals) in commitments
+            .iter()
+            .zip(points.iter())
+            .zip(evaluations.iter())
+        {
+            transcript.append_point(b"opened commitment", &commitment.0);
+            for (point, evaluation) in set.iter().zip(evals.iter()) {
+                transcript.append_scalar(b"opening point", point);
+                transcript.append_scalar(b"opened evaluation", evaluation);
+            }
+        }
+
+        transcript.challenge_scalar(b"multi open gamma")
+    }
+}
+
+/// Hiding variant of the commitments, as in the PolyCommit_Ped scheme of the KZG paper. The SRS
+/// has a second sequence of powers of tau over a generator h, with an unknown discrete log in
+/// base g, and a polynomial p(X) is committed together with a random polynomial r(X) of the
+/// same degree as p(X) * g + r(X) * h (evaluated at tau). The commitment is then statistically
+/// hiding, instead of revealing p when it comes from a small set, which matters for protocols
+/// that compose these commitments with other zero knowledge arguments. Openings reveal r(z)
+/// next to p(z).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HidingKzg10 {
+    pub kzg: Kzg10,
+    pub powers_x_h1: Vec<G1Affine>,
+}
+
+/// Opening of a hiding commitment at a point, with the evaluations of the polynomial and of
+/// its blinding polynomial.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HidingOpening {
+    pub evaluation: Scalar,
+    pub blinding_evaluation: Scalar,
+    pub witness: Kzg10Commitment,
+}
+
+impl HidingKzg10 {
+    /// Generates the SRS of `Kzg10::setup`, extended with the powers of tau over a point h of
+    /// unknown discrete logarithm, all drawn from `rng`.
+    pub fn setup<R: RngCore + CryptoRng>(size: usize, rng: &mut R) -> Self {
+        let toxic_waste = Scalar::random(&mut *rng);
+        let kzg = Kzg10::from_toxic_waste(size, &toxic_waste);
+        let h1 = (G1Affine::generator() * Scalar::random(rng)).to_affine();
+        let powers_x_h1 = fixed_base_mul(&h1, &powers(&toxic_waste, size));
+
+        Self { kzg, powers_x_h1 }
+    }
+
+    /// Commits to `polynomial`, returning the commitment and the blinding polynomial, which is
+    /// needed to open it.
+    pub fn commit<R: RngCore + CryptoRng>(
+        &self,
+        polynomial: &Polynomial,
+        rng: &mut R,
+    ) -> (Kzg10Commitment, Polynomial) {
+        let blinding = Polynomial(
+            (0..polynomial.0.len())
+                .map(|_| Scalar::random(&mut *rng))
+                .collect(),
+        );
+        (self.commit_with_blinding(polynomial, &blinding), blinding)
+    }
+
+    fn commit_with_blinding(
+        &self,
+        polynomial: &Polynomial,
+        blinding: &Polynomial,
+    ) -> Kzg10Commitment {
+        assert!(
+            blinding.0.len() <= self.powers_x_h1.len(),
+            "Polynomial degree not supported"
+        );
+        Kzg10Commitment(
+            (self.kzg.commit(polynomial).0 + msm(&self.powers_x_h1, &blinding.0)).to_affine(),
+        )
+    }
+
+    /// Opens a commitment of `polynomial` with `blinding` at `point`. The witness is the
+    /// commitment of the quotients of both polynomials by X - point.
+    pub fn open(
+        &self,
+        polynomial: &Polynomial,
+        blinding: &Polynomial,
+        point: &Scalar,
+    ) -> HidingOpening {
+        let divisor = Polynomial(vec![point.neg(), Scalar::one()]);
+        let quotient = |poly: &Polynomial, evaluation: &Scalar| {
+            let mut temp_poly = poly.clone();
+            if let Some(constant) = temp_poly.0.first_mut() {
+                *constant -= evaluation;
+            }
+            temp_poly / divisor.clone()
+        };
+        let evaluation = polynomial.eval(point);
+        let blinding_evaluation = blinding.eval(point);
+
+        HidingOpening {
+            evaluation,
+            blinding_evaluation,
+            witness: self.commit_with_blinding(
+                &quotient(polynomial, &evaluation),
+                &quotient(blinding, &blinding_evaluation),
+            ),
+        }
+    }
+
+    /// Checks that e(C - p(z) * g - r(z) * h + z * W, g2) = e(W, tau * g2).
+    pub fn check(
+        &self,
+        commitment: &Kzg10Commitment,
+        point: &Scalar,
+        opening: &HidingOpening,
+    ) -> Result<(), PlonkError> {
+        let lhs_g1 = commitment.0
+            - G1Affine::generator() * opening.evaluation
+            - self.powers_x_h1[0] * opening.blinding_evaluation
+            + opening.witness.0 * point;
+
+        pairing_check(
+            &self.kzg.powers_x_g2,
+            &opening.witness.0,
+            &lhs_g1.to_affine(),
+        )
+    }
+}
+
+/// Checks e(lhs, x * g2) = e(rhs, g2), the equation of every opening, as e(lhs, x * g2) *
+/// e(-rhs, g2) = 1, with a single multi-Miller loop and final exponentiation instead of two
+/// full pairings.
+pub(crate) fn pairing_check(
+    powers_x_g2: &[G2Affine; 2],
+    lhs: &G1Affine,
+    rhs: &G1Affine,
+) -> Result<(), PlonkError> {
+    let powers_x_g2 = powers_x_g2.map(G2Prepared::from);
+    let result = Bls12::multi_miller_loop(&[(lhs, &powers_x_g2[1]), (&rhs.neg(), &powers_x_g2[0])])
+        .final_exponentiation();
+
+    if bool::from(result.is_identity()) {
+        Ok(())
+    } else {
+        Err(PlonkError::VerificationFailed)
+    }
+}
+
+/// Multi-scalar multiplication sum_i scalars[i] * bases[i], with Pippenger's bucket method.
+/// Scalars are split in windows of c bits. For each window, every base is added to the bucket
+/// of its digit, and the buckets are combined as sum_d d * B_d with two running sums, so that
+/// the cost is about (255 / c) * (n + 2^c) additions instead of 255 * n doublings and additions.
+/// Extra bases are ignored.
+pub(crate) fn msm(bases: &[G1Affine], scalars: &[Scalar]) -> G1Projective {
+    if scalars.is_empty() {
+        return G1Projective::identity();
+    }
+    // The optimal window is about ln(n) bits.
+    let c = (scalars.len().ilog2() as usize * 69 / 100).max(3);
+    let scalars = scalars
+        .iter()
+        .map(|scalar| scalar.to_bytes_le())
+        .collect::<Vec<_>>();
+
+    let mut result = G1Projective::identity();
+    for window in (0..(Scalar::NUM_BITS as usize).div_ceil(c)).rev() {
+        for _ in 0..c {
+            result = result.double();
+        }
+
+        let mut buckets = vec![G1Projective::identity(); (1 << c) - 1];
+        for (base, scalar) in bases.iter().zip(scalars.iter()) {
+            let digit = digit(scalar, window * c, c);
+            if digit != 0 {
+                buckets[digit - 1] += base;
+            }
+        }
+
+        let mut running_sum = G1Projective::identity();
+        for bucket in buckets.iter().rev() {
+            running_sum += bucket;
+            result += running_sum;
+        }
+    }
+
+    result
+}
+
+/// SRS in the Lagrange basis of a domain H, (L_0(x) * g1, ..., L_{n-1}(x) * g1). A polynomial
+/// given by its evaluations over H is committed as the MSM of its evaluations with these
+/// points, which is the commitment of `Kzg10::commit` without interpolating the polynomial.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LagrangeKzg10 {
+    pub domain: EvaluationDomain,
+    pub powers_lagrange_g1: Vec<G1Affine>,
+}
+
+impl Kzg10 {
+    /// Computes the SRS in the Lagrange basis of `domain`. As L_i(X) = (1/n) sum_j
+    /// omega^{-ij} X^j, this is the inverse FFT of the first n powers in G1.
+    pub fn lagrange_basis(&self, domain: &EvaluationDomain) -> Result<LagrangeKzg10, PlonkError> {
+        if self.powers_x_g1.len() < domain.size {
+            return Err(PlonkError::InvalidLength {
+                expected: domain.size,
+                got: self.powers_x_g1.len(),
+            });
+        }
+        let mut points = self.powers_x_g1[..domain.size]
+            .iter()
+            .map(G1Projective::from)
+            .collect::<Vec<_>>();
+        fft_in_place(&mut points, &domain.generator_inv);
+        for point in points.iter_mut() {
+            *point *= domain.size_inv;
+        }
+        let mut powers_lagrange_g1 = vec![G1Affine::identity(); domain.size];
+        G1Projective::batch_normalize(&points, &mut powers_lagrange_g1);
+
+        Ok(LagrangeKzg10 {
+            domain: domain.clone(),
+            powers_lagrange_g1,
+        })
+    }
+
+    /// Commits to a polynomial with few terms, only multiplying the powers of its degrees.
+    pub fn commit_sparse(&self, polynomial: &SparsePolynomial) -> Kzg10Commitment {
+        assert!(
+            polynomial.degree() < self.powers_x_g1.len(),
+            "Polynomial degree not supported"
+        );
+        let (bases, scalars): (Vec<_>, Vec<_>) = polynomial
+            .0
+            .iter()
+            .map(|(degree, coefficient)| (self.powers_x_g1[*degree], *coefficient))
+            .unzip();
+        Kzg10Commitment(msm(&bases, &scalars).to_affine())
+    }
+}
+
+impl LagrangeKzg10 {
+    /// Commits to the polynomial with evaluations `evals` over the domain of the SRS.
+    pub fn commit_evals(&self, evals: &Evaluations) -> Kzg10Commitment {
+        assert!(
+            evals.domain == self.domain && evals.shift == Scalar::one(),
+            "Evaluations over a different domain than the SRS"
+        );
+        let batch = evals.evals.len().div_ceil(parallel::threads()).max(1);
+        let chunks = evals.evals.chunks(batch).collect::<Vec<_>>();
+        let commitment = parallel::map(&chunks, |index, evals| {
+            msm(&self.powers_lagrange_g1[index * batch..], evals)
+        })
+        .into_iter()
+        .sum::<G1Projective>();
+
+        Kzg10Commitment(commitment.to_affine())
+    }
+}
+
+// Maximum bits of the windows of `fixed_base_mul`.
+const FIXED_BASE_WINDOW: usize = 8;
+
+// The first `size` powers 1, x, x^2, ... of x.
+pub(crate) fn powers(x: &Scalar, size: usize) -> Vec<Scalar> {
+    let mut powers = Vec::with_capacity(size);
+    let mut power = Scalar::one();
+    for _ in 0..size {
+        powers.push(power);
+        power *= x;
+    }
+    powers
+}
+
+/// Multiplies `base` by every scalar. The multiples d * 2^(c * w) * base of every window w of c
+/// bits are computed once, so that each product only takes 256 / c additions, which is what
+/// makes the setup of large circuits practical. The table has 2^c points per window, so that
+/// small windows are better for few scalars.
+pub(crate) fn fixed_base_mul(base: &G1Affine, scalars: &[Scalar]) -> Vec<G1Affine> {
+    let c = (scalars.len().max(2).ilog2() as usize).min(FIXED_BASE_WINDOW);
+    let windows = (Scalar::NUM_BITS as usize).div_ceil(c);
+    let mut table = vec![G1Projective::identity(); windows << c];
+    let mut window_base = G1Projective::from(base);
+    for window in table.chunks_mut(1 << c) {
+        for d in 1..window.len() {
+            window[d] = window[d - 1] + window_base;
         }
+        for _ in 0..c {
+            window_base = window_base.double();
+        }
+    }
+    let mut affine_table = vec![G1Affine::identity(); table.len()];
+    G1Projective::batch_normalize(&table, &mut affine_table);
 
-        let lhs_g1 = F + proof.0 .0 * eval_a + proof.1 .0 * (rprime * eval_b);
-        let rhs_g1 = proof.0 .0 + (proof.1 .0 * rprime);
+    let products = parallel::map(scalars, |_, scalar| {
+        let bytes = scalar.to_bytes_le();
+        (0..windows).fold(G1Projective::identity(), |acc, window| {
+            acc + affine_table[(window << c) + digit(&bytes, window * c, c)]
+        })
+    });
+    let mut result = vec![G1Affine::identity(); products.len()];
+    G1Projective::batch_normalize(&products, &mut result);
+    result
+}
 
-        let lhs_pairing = pairing(&lhs_g1.to_affine(), &G2Affine::generator());
-        let rhs_pairing = pairing(&rhs_g1.to_affine(), &self.powers_x_g2[1]);
+// The `c` bits of the little endian `bytes` starting at bit `start`.
+fn digit(bytes: &[u8; 32], start: usize, c: usize) -> usize {
+    (start..(start + c).min(256))
+        .map(|bit| ((bytes[bit / 8] >> (bit % 8)) & 1) as usize)
+        .rev()
+        .fold(0, |digit, bit| (digit << 1) | bit)
+}
 
-        if lhs_pairing == rhs_pairing {
-            Ok(())
-        } else {
-            Err(())
+// Union of all the opening sets, in order of first appearance.
+pub(crate) fn union_points(points: &[Vec<Scalar>]) -> Vec<Scalar> {
+    let mut all_points: Vec<Scalar> = Vec::new();
+    for point in points.iter().flatten() {
+        if !all_points.contains(point) {
+            all_points.push(*point);
         }
     }
+    all_points
+}
+
+// Evaluation at z of the vanishing polynomial of the points of T that are not in S.
+fn vanishing_complement(all_points: &[Scalar], set: &[Scalar], z: &Scalar) -> Scalar {
+    all_points
+        .iter()
+        .filter(|point| !set.contains(point))
+        .fold(Scalar::one(), |acc, point| acc * (z - point))
 }
 
-impl<'a, 'b> Add<&'b Kzg10Commitment> for &'a Kzg10Commitment {
+impl Kzg10Commitment {
+    /// Compressed encoding of the commitment.
+    pub fn to_bytes(&self) -> [u8; 48] {
+        self.0.to_compressed()
+    }
+
+    /// Decodes a compressed commitment, checking that it is a point of the prime order subgroup.
+    pub fn from_bytes(bytes: &[u8; 48]) -> Result<Self, PlonkError> {
+        Option::from(G1Affine::from_compressed(bytes))
+            .map(Kzg10Commitment)
+            .ok_or(PlonkError::InvalidPoint)
+    }
+}
+
+impl<'b> Add<&'b Kzg10Commitment> for &Kzg10Commitment {
     type Output = Kzg10Commitment;
 
     fn add(self, rhs: &'b Kzg10Commitment) -> Self::Output {
@@ -216,7 +1023,7 @@ define_add_variants!(
     Output = Kzg10Commitment
 );
 
-impl<'a, 'b> Mul<&'b Scalar> for &'a Kzg10Commitment {
+impl<'b> Mul<&'b Scalar> for &Kzg10Commitment {
     type Output = Kzg10Commitment;
 
     fn mul(self, rhs: &'b Scalar) -> Self::Output {
@@ -230,7 +1037,7 @@ define_mul_variants!(
     Output = Kzg10Commitment
 );
 
-impl<'a, 'b> Mul<&'b Kzg10Commitment> for &'a Scalar {
+impl<'b> Mul<&'b Kzg10Commitment> for &Scalar {
     type Output = Kzg10Commitment;
 
     fn mul(self, rhs: &'b Kzg10Commitment) -> Self::Output {
@@ -245,13 +1052,20 @@ define_mul_variants!(
 );
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
 
     const SIZE: usize = 10;
+
+    // SRS of the toxic waste drawn from the seed 0, which the tests recompute.
+    fn setup(size: usize) -> Kzg10 {
+        Kzg10::setup(size, &mut ChaCha20Rng::from_seed([0u8; 32]))
+    }
     #[test]
     fn test_setup() {
-        let kzg10 = Kzg10::<SIZE>::setup();
+        let kzg10 = setup(SIZE);
         let toxic_waste = Scalar::random(&mut ChaCha20Rng::from_seed([0u8; 32]));
 
         assert_eq!(
@@ -269,11 +1083,249 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pairing_check() {
+        let kzg10 = setup(SIZE);
+        let [g1, x_g1] = [kzg10.powers_x_g1[0], kzg10.powers_x_g1[1]];
+        assert!(pairing_check(&kzg10.powers_x_g2, &g1, &x_g1).is_ok());
+        assert!(pairing_check(&kzg10.powers_x_g2, &x_g1, &x_g1).is_err());
+        assert!(pairing_check(&kzg10.powers_x_g2, &x_g1, &g1).is_err());
+        assert!(pairing_check(
+            &kzg10.powers_x_g2,
+            &G1Affine::identity(),
+            &G1Affine::identity()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        let kzg10 = setup(SIZE);
+        assert!(kzg10.validate().is_ok());
+        assert!(setup(1).validate().is_ok());
+
+        // A single wrong power, two swapped powers, or powers of another toxic waste in G2.
+        let mut wrong_power = kzg10.clone();
+        wrong_power.powers_x_g1[SIZE - 1] =
+            (wrong_power.powers_x_g1[SIZE - 1] * Scalar::from(2)).to_affine();
+        let mut swapped = kzg10.clone();
+        swapped.powers_x_g1.swap(2, 3);
+        let mut wrong_g2 = kzg10.clone();
+        wrong_g2.powers_x_g2[1] = (G2Affine::generator() * Scalar::from(3)).to_affine();
+        let mut wrong_generator = kzg10.clone();
+        wrong_generator.powers_x_g1[0] = kzg10.powers_x_g1[1];
+        for srs in [wrong_power, swapped, wrong_g2, wrong_generator] {
+            assert_eq!(srs.validate(), Err(PlonkError::InvalidSrs));
+        }
+    }
+
+    #[test]
+    fn test_lagrange_basis() {
+        let kzg10 = setup(SIZE);
+        let domain = EvaluationDomain::new(8).unwrap();
+        let lagrange = kzg10.lagrange_basis(&domain).unwrap();
+        for (i, point) in lagrange.powers_lagrange_g1.iter().enumerate() {
+            assert_eq!(*point, kzg10.commit(&domain.lagrange_basis(i)).0);
+        }
+
+        let mut rng = ChaCha20Rng::from_seed([3u8; 32]);
+        let values = (0..8).map(|_| Scalar::random(&mut rng)).collect::<Vec<_>>();
+        assert_eq!(
+            lagrange
+                .commit_evals(&Evaluations::new(values.clone(), &domain))
+                .0,
+            kzg10.commit(&domain.ifft(&values)).0
+        );
+        let sparse = SparsePolynomial::new(vec![(0, Scalar::from(3)), (9, Scalar::from(5))]);
+        assert_eq!(
+            kzg10.commit_sparse(&sparse).0,
+            kzg10.commit(&sparse.to_dense()).0
+        );
+
+        assert_eq!(
+            kzg10
+                .lagrange_basis(&EvaluationDomain::new(16).unwrap())
+                .err(),
+            Some(PlonkError::InvalidLength {
+                expected: 16,
+                got: SIZE
+            })
+        );
+    }
+
+    #[test]
+    fn test_contribute() {
+        // Two contributions on top of the deterministic setup.
+        let setup = setup(SIZE);
+        let mut first = setup.clone();
+        let first_proof = first.contribute(Scalar::from(5)).unwrap();
+        let mut second = first.clone();
+        let second_proof = second.contribute(Scalar::from(7)).unwrap();
+        assert!(first.verify_contribution(&setup, &first_proof).is_ok());
+        assert!(second.verify_contribution(&first, &second_proof).is_ok());
+
+        let toxic_waste = Scalar::random(&mut ChaCha20Rng::from_seed([0u8; 32])) * Scalar::from(35);
+        assert_eq!(
+            second.powers_x_g1,
+            fixed_base_mul(&G1Affine::generator(), &powers(&toxic_waste, SIZE))
+        );
+        assert_eq!(
+            second.powers_x_g2[1],
+            (G2Affine::generator() * toxic_waste).to_affine()
+        );
+
+        // A proof does not apply to another update, and the proof of knowledge can't be forged.
+        assert_eq!(
+            second.verify_contribution(&first, &first_proof),
+            Err(PlonkError::VerificationFailed)
+        );
+        assert_eq!(
+            second.verify_contribution(&setup, &second_proof),
+            Err(PlonkError::VerificationFailed)
+        );
+        let mut forged = second_proof.clone();
+        forged.response += Scalar::one();
+        assert_eq!(
+            second.verify_contribution(&first, &forged),
+            Err(PlonkError::VerificationFailed)
+        );
+
+        // An update that breaks the structure of the SRS is rejected, whatever the proof.
+        let mut broken = second.clone();
+        broken.powers_x_g1[3] = G1Affine::generator();
+        assert_eq!(
+            broken.verify_contribution(&first, &second_proof),
+            Err(PlonkError::InvalidSrs)
+        );
+
+        // A zero secret would erase the toxic waste.
+        let mut zero = second.clone();
+        assert_eq!(zero.contribute(Scalar::zero()), Err(PlonkError::ZeroSecret));
+        assert_eq!(zero.powers_x_g1, second.powers_x_g1);
+    }
+
+    // Writes the powers of tau of `toxic_waste` in the format of the Zcash ceremony. We leave the
+    // alpha and beta powers out, as we never read them.
+    pub(crate) fn write_powers_of_tau(
+        toxic_waste: Scalar,
+        format: PowersOfTauFormat,
+        tau_powers_length: usize,
+    ) -> Vec<u8> {
+        let mut bytes = vec![0u8; 64];
+        let mut power = G1Affine::generator();
+        for _ in 0..(2 * tau_powers_length - 1) {
+            match format {
+                PowersOfTauFormat::Challenge => bytes.extend_from_slice(&power.to_uncompressed()),
+                PowersOfTauFormat::Response => bytes.extend_from_slice(&power.to_compressed()),
+            }
+            power = (power * toxic_waste).to_affine();
+        }
+        let mut power = G2Affine::generator();
+        for _ in 0..tau_powers_length {
+            match format {
+                PowersOfTauFormat::Challenge => bytes.extend_from_slice(&power.to_uncompressed()),
+                PowersOfTauFormat::Response => bytes.extend_from_slice(&power.to_compressed()),
+            }
+            power = (power * toxic_waste).to_affine();
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_fixed_base_mul() {
+        let base = (G1Affine::generator() * Scalar::from(5)).to_affine();
+        let scalars = [
+            Scalar::zero(),
+            Scalar::one(),
+            Scalar::from(255),
+            Scalar::from(256),
+            Scalar::one().neg(),
+            Scalar::random(&mut ChaCha20Rng::from_seed([3u8; 32])),
+        ];
+        assert!(fixed_base_mul(&base, &scalars)
+            .iter()
+            .zip(scalars.iter())
+            .all(|(product, scalar)| *product == (base * scalar).to_affine()));
+    }
+
+    #[test]
+    fn test_msm() {
+        let mut rng = ChaCha20Rng::from_seed([1u8; 32]);
+        let kzg10 = setup(300);
+        for size in [0, 1, 7, 300] {
+            let scalars = (0..size)
+                .map(|_| Scalar::random(&mut rng))
+                .collect::<Vec<_>>();
+            let expected = kzg10
+                .powers_x_g1
+                .iter()
+                .zip(scalars.iter())
+                .fold(G1Projective::identity(), |acc, (base, scalar)| {
+                    acc + base * scalar
+                });
+            assert_eq!(msm(&kzg10.powers_x_g1, &scalars), expected);
+        }
+
+        // Digits of zero, one, and of the largest scalar.
+        let scalars = [Scalar::zero(), Scalar::one(), -Scalar::one()];
+        let expected = G1Projective::from(kzg10.powers_x_g1[1]) - kzg10.powers_x_g1[2];
+        assert_eq!(msm(&kzg10.powers_x_g1, &scalars), expected);
+    }
+
+    #[test]
+    fn test_powers_of_tau() {
+        let toxic_waste = Scalar::random(&mut ChaCha20Rng::from_seed([0u8; 32]));
+        let kzg10 = setup(SIZE);
+
+        for format in [PowersOfTauFormat::Challenge, PowersOfTauFormat::Response] {
+            let bytes = write_powers_of_tau(toxic_waste, format, 8);
+            let loaded = Kzg10::from_powers_of_tau(&bytes[..], format, 8, SIZE).unwrap();
+
+            assert_eq!(loaded.powers_x_g1, kzg10.powers_x_g1);
+            assert_eq!(loaded.powers_x_g2, kzg10.powers_x_g2);
+        }
+
+        // Not enough powers in the file
+        let bytes = write_powers_of_tau(toxic_waste, PowersOfTauFormat::Response, 4);
+        assert_eq!(
+            Kzg10::from_powers_of_tau(&bytes[..], PowersOfTauFormat::Response, 4, SIZE).err(),
+            Some(PlonkError::InvalidSrs)
+        );
+
+        // Truncated file
+        let bytes = write_powers_of_tau(toxic_waste, PowersOfTauFormat::Response, 8);
+        assert!(matches!(
+            Kzg10::from_powers_of_tau(&bytes[..500], PowersOfTauFormat::Response, 8, SIZE),
+            Err(PlonkError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_powers_of_tau_inconsistent() {
+        let toxic_waste = Scalar::random(&mut ChaCha20Rng::from_seed([0u8; 32]));
+        let mut bytes = write_powers_of_tau(toxic_waste, PowersOfTauFormat::Response, 8);
+
+        // We replace the fourth power of tau by a different (valid) point.
+        let offset = 64 + 3 * 48;
+        bytes[offset..offset + 48].copy_from_slice(&G1Affine::generator().to_compressed());
+        assert_eq!(
+            Kzg10::from_powers_of_tau(&bytes[..], PowersOfTauFormat::Response, 8, SIZE).err(),
+            Some(PlonkError::InvalidSrs)
+        );
+
+        // And by something which is not a point.
+        bytes[offset..offset + 48].copy_from_slice(&[0xffu8; 48]);
+        assert_eq!(
+            Kzg10::from_powers_of_tau(&bytes[..], PowersOfTauFormat::Response, 8, SIZE).err(),
+            Some(PlonkError::InvalidPoint)
+        );
+    }
+
     #[test]
     fn test_kzg() {
-        let kzg10 = Kzg10::<3>::setup();
-        let mut transcript = Transcript::new(b"Testing KZG10");
-        let mut transcript_verifier = Transcript::new(b"Testing KZG10");
+        let kzg10 = setup(3);
+        let mut transcript = Blake2bTranscript::new(b"Testing KZG10");
+        let mut transcript_verifier = Blake2bTranscript::new(b"Testing KZG10");
 
         let polynomial1 = Polynomial(vec![Scalar::from(1), Scalar::from(5), Scalar::from(2)]);
 
@@ -281,35 +1333,256 @@ mod tests {
 
         let eval_point1 = Scalar::from(3);
         let eval_point2 = Scalar::from(7);
-        let result1 = polynomial1.eval(&eval_point1);
-        let result2 = polynomial2.eval(&eval_point2);
 
         let commitment1 = kzg10.commit(&polynomial1);
         let commitment2 = kzg10.commit(&polynomial2);
 
-        let proof = kzg10.batch_prove(
-            &[polynomial1],
-            &[polynomial2],
-            &[commitment1.clone()],
-            &[commitment2.clone()],
-            &eval_point1,
-            &eval_point2,
-            &[result1],
-            &[result2],
+        let proof = kzg10.open_two_points(
+            [&[&polynomial1], &[&polynomial2]],
+            &[eval_point1, eval_point2],
             &mut transcript,
         );
+        assert_eq!(
+            proof.openings.clone().map(|opening| opening.evaluations),
+            [
+                vec![polynomial1.eval(&eval_point1)],
+                vec![polynomial2.eval(&eval_point2)]
+            ]
+        );
 
         assert!(kzg10
-            .batch_verify(
+            .check_two_points(
+                [&[commitment1], &[commitment2]],
+                &[eval_point1, eval_point2],
                 &proof,
-                &[commitment1],
-                &[commitment2],
-                &eval_point1,
-                &eval_point2,
-                &[result1],
-                &[result2],
                 &mut transcript_verifier
             )
             .is_ok())
     }
+
+    #[test]
+    fn test_open_two_points() {
+        let kzg10 = setup(4);
+        let mut transcript = Blake2bTranscript::new(b"Testing KZG10");
+        let mut transcript_verifier = Blake2bTranscript::new(b"Testing KZG10");
+
+        let polynomial1 = Polynomial(vec![Scalar::from(1), Scalar::from(5), Scalar::from(2)]);
+        let polynomial2 = Polynomial(vec![Scalar::from(4), Scalar::from(5), Scalar::from(3)]);
+        let polynomial3 = Polynomial(vec![Scalar::from(6), Scalar::from(0), Scalar::from(1)]);
+        let points = [Scalar::from(3), Scalar::from(7)];
+
+        let commitment1 = kzg10.commit(&polynomial1);
+        let commitment2 = kzg10.commit(&polynomial2);
+        let commitment3 = kzg10.commit(&polynomial3);
+
+        let opening = kzg10.open_two_points(
+            [&[&polynomial1], &[&polynomial2, &polynomial3]],
+            &points,
+            &mut transcript,
+        );
+        assert_eq!(
+            opening.openings[0].evaluations,
+            vec![polynomial1.eval(&points[0])]
+        );
+        assert_eq!(
+            opening.openings[1].evaluations[1],
+            polynomial3.eval(&points[1])
+        );
+
+        let commitments = [
+            std::slice::from_ref(&commitment1),
+            &[commitment2.clone(), commitment3.clone()][..],
+        ];
+        assert!(kzg10
+            .check_two_points(
+                commitments,
+                &points,
+                &opening,
+                &mut transcript_verifier.clone()
+            )
+            .is_ok());
+        // Any evaluation, at either point, makes the single check fail.
+        for (i, j) in [(0, 0), (1, 0), (1, 1)] {
+            let mut wrong = opening.clone();
+            wrong.openings[i].evaluations[j] += Scalar::one();
+            assert_eq!(
+                kzg10.check_two_points(
+                    commitments,
+                    &points,
+                    &wrong,
+                    &mut transcript_verifier.clone()
+                ),
+                Err(PlonkError::VerificationFailed)
+            );
+        }
+        assert!(kzg10
+            .check_two_points(
+                commitments,
+                &[points[1], points[0]],
+                &opening,
+                &mut transcript_verifier.clone()
+            )
+            .is_err());
+        assert_eq!(
+            kzg10.check_two_points(
+                [commitments[0], &[commitment2]],
+                &points,
+                &opening,
+                &mut transcript_verifier
+            ),
+            Err(PlonkError::InvalidLength {
+                expected: 1,
+                got: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_hiding_commitment() {
+        let kzg10 = HidingKzg10::setup(4, &mut ChaCha20Rng::from_seed([0u8; 32]));
+        let mut rng = ChaCha20Rng::from_seed([2u8; 32]);
+        let polynomial = Polynomial((1..=4).map(Scalar::from).collect::<Vec<_>>());
+        let point = Scalar::from(9);
+
+        let (commitment, blinding) = kzg10.commit(&polynomial, &mut rng);
+        let opening = kzg10.open(&polynomial, &blinding, &point);
+        assert_eq!(opening.evaluation, polynomial.eval(&point));
+        assert!(kzg10.check(&commitment, &point, &opening).is_ok());
+
+        // The same polynomial has unrelated commitments, none of them the plain commitment.
+        let (other_commitment, _) = kzg10.commit(&polynomial, &mut rng);
+        assert_ne!(commitment.0, other_commitment.0);
+        assert_ne!(commitment.0, kzg10.kzg.commit(&polynomial).0);
+
+        let mut wrong = opening.clone();
+        wrong.evaluation += Scalar::one();
+        assert_eq!(
+            kzg10.check(&commitment, &point, &wrong),
+            Err(PlonkError::VerificationFailed)
+        );
+        let mut wrong = opening;
+        wrong.blinding_evaluation += Scalar::one();
+        assert!(kzg10.check(&commitment, &point, &wrong).is_err());
+    }
+
+    #[test]
+    fn test_open_batch() {
+        let kzg10 = setup(4);
+        let mut transcript = Blake2bTranscript::new(b"Testing KZG10");
+        let mut transcript_verifier = Blake2bTranscript::new(b"Testing KZG10");
+
+        let polynomials = vec![
+            Polynomial(vec![Scalar::from(1), Scalar::from(5), Scalar::from(2)]),
+            Polynomial(vec![
+                Scalar::from(4),
+                Scalar::from(5),
+                Scalar::from(3),
+                Scalar::from(9),
+            ]),
+            Polynomial(vec![Scalar::from(8)]),
+        ];
+        let commitments = polynomials
+            .iter()
+            .map(|poly| kzg10.commit(poly))
+            .collect::<Vec<_>>();
+        let point = Scalar::from(3);
+
+        let opening = kzg10.open_batch(&polynomials, &point, &mut transcript);
+        assert_eq!(opening.evaluations[0], Scalar::from(34));
+
+        assert!(kzg10
+            .check_batch(
+                &commitments,
+                &point,
+                &opening,
+                &mut transcript_verifier.clone()
+            )
+            .is_ok());
+
+        let mut wrong_opening = opening.clone();
+        wrong_opening.evaluations[2] = Scalar::from(9);
+        assert_eq!(
+            kzg10.check_batch(
+                &commitments,
+                &point,
+                &wrong_opening,
+                &mut transcript_verifier.clone()
+            ),
+            Err(PlonkError::VerificationFailed)
+        );
+
+        assert_eq!(
+            kzg10.check_batch(
+                &commitments[..2],
+                &point,
+                &opening,
+                &mut transcript_verifier
+            ),
+            Err(PlonkError::InvalidLength {
+                expected: 2,
+                got: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_multi_open() {
+        let kzg10 = setup(4);
+        let mut transcript = Blake2bTranscript::new(b"Testing KZG10");
+        let mut transcript_verifier = Blake2bTranscript::new(b"Testing KZG10");
+
+        let polynomials = vec![
+            Polynomial(vec![Scalar::from(1), Scalar::from(5), Scalar::from(2)]),
+            Polynomial(vec![
+                Scalar::from(4),
+                Scalar::from(5),
+                Scalar::from(3),
+                Scalar::from(9),
+            ]),
+            Polynomial(vec![Scalar::from(8), Scalar::from(1)]),
+        ];
+        let commitments = polynomials
+            .iter()
+            .map(|poly| kzg10.commit(poly))
+            .collect::<Vec<_>>();
+        let zeta = Scalar::from(3);
+        let zeta_omega = Scalar::from(11);
+        let points = vec![vec![zeta], vec![zeta, zeta_omega], vec![zeta_omega]];
+
+        let proof = kzg10.multi_open(&polynomials, &points, &mut transcript);
+        assert_eq!(proof.evaluations[0], vec![Scalar::from(34)]);
+        assert_eq!(proof.evaluations[2], vec![Scalar::from(19)]);
+
+        assert!(kzg10
+            .check_multi_open(
+                &commitments,
+                &points,
+                &proof,
+                &mut transcript_verifier.clone()
+            )
+            .is_ok());
+
+        let mut wrong_proof = proof.clone();
+        wrong_proof.evaluations[1][1] = Scalar::from(9);
+        assert_eq!(
+            kzg10.check_multi_open(
+                &commitments,
+                &points,
+                &wrong_proof,
+                &mut transcript_verifier.clone()
+            ),
+            Err(PlonkError::VerificationFailed)
+        );
+
+        let wrong_points = vec![vec![zeta], vec![zeta, Scalar::from(12)], vec![zeta_omega]];
+        assert_eq!(
+            kzg10.check_multi_open(
+                &commitments,
+                &wrong_points,
+                &proof,
+                &mut transcript_verifier
+            ),
+            Err(PlonkError::VerificationFailed)
+        );
+    }
 }
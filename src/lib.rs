@@ -1,11 +1,129 @@
 #![allow(dead_code)]
+// Without the `std` feature, only the verifier and what it depends on are built, on top of
+// `core` and `alloc`.
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+mod accumulation;
+#[cfg(feature = "std")]
+pub mod acir;
+#[cfg(feature = "arkworks")]
+pub mod arkworks;
+#[cfg(feature = "std")]
+pub mod bellman;
+#[cfg(feature = "std")]
+mod bindings;
+#[cfg(feature = "std")]
+mod circom;
+mod commitment;
+mod error;
+mod evm;
+#[cfg(feature = "std")]
+mod expr;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod fri;
+#[cfg(all(test, feature = "std"))]
+mod fuzz;
+#[cfg(feature = "std")]
+pub mod gadgets;
+mod ipa;
+#[cfg(feature = "std")]
+mod json;
+#[cfg(feature = "std")]
+mod jubjub;
+mod keccak;
 mod kzg10;
+mod lookup;
+#[cfg(feature = "std")]
+mod mock;
+#[cfg(feature = "std")]
+mod optimizer;
+mod parallel;
+#[cfg(feature = "std")]
 mod plonk;
 mod polynomial;
+mod poseidon;
+mod proof;
+#[cfg(feature = "merlin")]
+mod strobe;
 mod transcript;
+#[cfg(all(test, feature = "std"))]
+mod vectors;
 #[macro_use]
 mod macros;
 
+#[cfg(feature = "std")]
 mod prover;
+#[cfg(feature = "std")]
+mod r1cs;
+#[cfg(feature = "std")]
+mod solidity;
 mod verifier;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod wire;
+#[cfg(feature = "std")]
+mod witness;
+
+// Public interface, for the applications and the benchmarks outside of the crate.
+pub use crate::accumulation::Accumulator;
+#[cfg(feature = "std")]
+pub use crate::acir::{acir_to_plonk, AcirCircuit, AcirProgram};
+#[cfg(feature = "arkworks")]
+pub use crate::arkworks::ArkworksCircuit;
+#[cfg(feature = "std")]
+pub use crate::circom::{read_r1cs, read_wtns};
+pub use crate::commitment::{PlonkCommitment, PolynomialCommitment};
+pub use crate::error::PlonkError;
+#[cfg(feature = "std")]
+pub use crate::expr::Expr;
+pub use crate::fri::{Fri, FriBatchOpening, FriCommitment};
+pub use crate::ipa::{Ipa, IpaBatchOpening, IpaCommitment};
+#[cfg(feature = "std")]
+pub use crate::jubjub::{JubjubPoint, JubjubScalar};
+pub use crate::kzg10::{HidingKzg10, HidingOpening, Kzg10, Kzg10Commitment, PowersOfTauFormat};
+pub use crate::lookup::LookupTable;
+#[cfg(feature = "std")]
+pub use crate::mock::{Failure, LabelledWire, MockProver};
+#[cfg(feature = "std")]
+pub use crate::optimizer::Optimization;
+#[cfg(feature = "std")]
+pub use crate::plonk::{
+    CircuitStats, ComputationTrace, Gate, PlonkCircuit, ProverKey, UnsatisfiedGate,
+};
+pub use crate::polynomial::{EvaluationDomain, Polynomial};
+pub use crate::proof::{LinearisedOpening, PlonkProof};
+#[cfg(feature = "std")]
+pub use crate::prover::{ProofPolynomials, Prover};
+#[cfg(feature = "std")]
+pub use crate::r1cs::{r1cs_to_plonk, LinearCombination, R1cs, R1csCircuit};
+#[cfg(feature = "std")]
+pub use crate::solidity::solidity_verifier;
+#[cfg(feature = "merlin")]
+pub use crate::transcript::MerlinTranscript;
+pub use crate::transcript::{Blake2bTranscript, KeccakTranscript, PoseidonTranscript, Transcript};
+pub use crate::verifier::{Challenges, DeferredCheck, PlonkVerifier, VerifierKey};
+pub use crate::wire::Wire;
+#[cfg(feature = "std")]
+pub use crate::witness::WitnessBuilder;
+
+// Kernels of the prover, only public for the benchmarks in `benches/`.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod kernels {
+    pub use crate::lookup::lookup_product;
+    pub use crate::prover::permutation_product;
+}
+
+// Paths used by the expansion of `circuit!` in other crates, which may not depend on `blstrs`.
+#[cfg(feature = "std")]
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::error::PlonkError;
+    pub use crate::expr::{witness, Expr};
+    pub use crate::plonk::{Gate, PlonkCircuit};
+    pub use alloc::vec::Vec;
+    pub use blstrs::Scalar;
+}
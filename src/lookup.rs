@@ -0,0 +1,254 @@
+// Lookup argument, following plookup (Gabizon and Williamson, 2020) in the variant of PlonKup,
+// where the sorted vector is split in alternating halves so that everything fits in the
+// domain H of the circuit.
+//
+// A circuit may have several tables, with rows (t_1, t_2, t_3). They are concatenated in a
+// single table whose rows are tagged with the identifier t_4 of their table. Gates with q_K = 1
+// require their wires (a, b, c) to be a row of the table with identifier q_T. With a challenge
+// eta, both are compressed into single values t = t_1 + eta * t_2 + eta^2 * t_3 + eta^3 * t_4 and
+// f = a + eta * b + eta^2 * c + eta^3 * q_T. Rows without a lookup get as query the first element
+// of the table, so that every query has to be in the table.
+//
+// The table is padded to n rows by repeating its last row, and s = (f, t) is sorted in the
+// order of t, and split in h_1 = (s_0, s_2, ...) and h_2 = (s_1, s_3, ...). Then f is contained in
+// t if and only if the running product
+//
+// Z(w^{i+1}) = Z(w^i) * (1 + delta)(epsilon + f_i)(epsilon(1 + delta) + t_i + delta * t_{i+1}) /
+//          ((epsilon(1 + delta) + h_1_i + delta * h_2_i)(epsilon(1 + delta) + h_2_i + delta * h_1_{i+1}))
+//
+// with Z(1) = 1 wraps around to one.
+use crate::error::PlonkError;
+use crate::polynomial::batch_invert;
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+use blstrs::Scalar;
+use ff::Field;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Rows (a, b, c) against which the wires of lookup gates are checked.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LookupTable(pub(crate) Vec<[Scalar; 3]>);
+
+impl LookupTable {
+    /// Table with arbitrary rows of at most three values, e.g. pairs (x, S(x)) of an S-box.
+    /// Rows with less than three values are padded with zeroes, so the wires of the lookup
+    /// gate that are not used must be zero. Rows of more than three values are rejected at
+    /// compile time:
+    ///
+    /// ```compile_fail
+    /// use blstrs::Scalar;
+    /// use dummy_plonk::LookupTable;
+    ///
+    /// LookupTable::new(vec![[Scalar::from(1); 4]]);
+    /// ```
+    pub fn new<const W: usize>(rows: Vec<[Scalar; W]>) -> Self {
+        const { assert!(W <= 3, "Lookup tables have at most three columns") };
+        LookupTable(
+            rows.into_iter()
+                .map(|row| {
+                    let mut padded = [Scalar::zero(); 3];
+                    padded[..W].copy_from_slice(&row);
+                    padded
+                })
+                .collect(),
+        )
+    }
+
+    /// XOR of every pair of `bits`-bit values, as rows (a, b, a ^ b). The table has 4^bits rows.
+    pub fn xor(bits: u32) -> Self {
+        Self::bitwise(bits, |a, b| a ^ b)
+    }
+
+    /// AND of every pair of `bits`-bit values, as rows (a, b, a & b). The table has 4^bits rows.
+    pub fn and(bits: u32) -> Self {
+        Self::bitwise(bits, |a, b| a & b)
+    }
+
+    /// XOR of every pair of bytes.
+    pub fn xor_u8() -> Self {
+        Self::xor(8)
+    }
+
+    /// AND of every pair of bytes.
+    pub fn and_u8() -> Self {
+        Self::and(8)
+    }
+
+    fn bitwise(bits: u32, operation: impl Fn(u64, u64) -> u64) -> Self {
+        let mut rows = Vec::with_capacity(1 << (2 * bits));
+        for a in 0..(1u64 << bits) {
+            for b in 0..(1u64 << bits) {
+                rows.push([
+                    Scalar::from(a),
+                    Scalar::from(b),
+                    Scalar::from(operation(a, b)),
+                ]);
+            }
+        }
+        LookupTable(rows)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Concatenates `tables`, tagging the rows of the i-th table with identifier i + 1, and pads
+/// the result to exactly `size` rows by repeating the last row. Without tables, the result is
+/// the single row (0, 0, 0, 0).
+pub(crate) fn tagged_rows(tables: &[LookupTable], size: usize) -> Vec<[Scalar; 4]> {
+    let mut rows = Vec::with_capacity(size);
+    for (i, table) in tables.iter().enumerate() {
+        let id = Scalar::from(i as u64 + 1);
+        rows.extend(table.0.iter().map(|row| [row[0], row[1], row[2], id]));
+    }
+    let last = rows.last().copied().unwrap_or([Scalar::zero(); 4]);
+    rows.resize(size, last);
+    rows
+}
+
+/// Compresses a row into a single value, as row_0 + eta * row_1 + eta^2 * row_2 + ...
+pub(crate) fn compress(row: &[Scalar], eta: &Scalar) -> Scalar {
+    row.iter()
+        .rev()
+        .fold(Scalar::zero(), |acc, value| acc * eta + value)
+}
+
+/// Sorts the queries in the order of the table, and splits the result in its even and odd
+/// positions. Fails with the index of the first query that is not in the table.
+pub(crate) fn sorted_halves(
+    queries: &[Scalar],
+    table: &[Scalar],
+) -> Result<(Vec<Scalar>, Vec<Scalar>), PlonkError> {
+    let mut positions = BTreeMap::new();
+    for (i, value) in table.iter().enumerate().rev() {
+        positions.insert(value.to_bytes_le(), i);
+    }
+
+    let mut counts = vec![0usize; table.len()];
+    for (row, query) in queries.iter().enumerate() {
+        let position = positions
+            .get(&query.to_bytes_le())
+            .ok_or(PlonkError::LookupFailed { row })?;
+        counts[*position] += 1;
+    }
+
+    let mut sorted = Vec::with_capacity(queries.len() + table.len());
+    for (value, count) in table.iter().zip(counts) {
+        sorted.extend(core::iter::repeat_n(*value, count + 1));
+    }
+
+    Ok((
+        sorted.iter().step_by(2).copied().collect(),
+        sorted.iter().skip(1).step_by(2).copied().collect(),
+    ))
+}
+
+/// Evaluations over H of the running product of the lookup argument.
+pub fn lookup_product(
+    queries: &[Scalar],
+    table: &[Scalar],
+    h_1: &[Scalar],
+    h_2: &[Scalar],
+    delta: &Scalar,
+    epsilon: &Scalar,
+) -> Result<Vec<Scalar>, PlonkError> {
+    let n = queries.len();
+    let one_plus_delta = Scalar::one() + delta;
+    let epsilon_one_plus_delta = epsilon * one_plus_delta;
+
+    let mut denominators = (0..n)
+        .map(|i| {
+            (epsilon_one_plus_delta + h_1[i] + delta * h_2[i])
+                * (epsilon_one_plus_delta + h_2[i] + delta * h_1[(i + 1) % n])
+        })
+        .collect::<Vec<Scalar>>();
+    batch_invert(&mut denominators)?;
+
+    let mut evals = Vec::with_capacity(n);
+    let mut factor = Scalar::one();
+    for (i, denominator_inv) in denominators.iter().enumerate() {
+        evals.push(factor);
+        let numerator = one_plus_delta
+            * (epsilon + queries[i])
+            * (epsilon_one_plus_delta + table[i] + delta * table[(i + 1) % n]);
+        factor *= numerator * denominator_inv;
+    }
+
+    Ok(evals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    #[test]
+    fn test_tables() {
+        let xor = LookupTable::xor(2);
+        assert_eq!(xor.len(), 16);
+        assert!(xor
+            .0
+            .contains(&[Scalar::from(2), Scalar::from(3), Scalar::from(1)]));
+        assert!(LookupTable::and(2).0.contains(&[
+            Scalar::from(2),
+            Scalar::from(3),
+            Scalar::from(2)
+        ]));
+
+        let pairs = LookupTable::new(vec![[Scalar::from(1), Scalar::from(2)]]);
+        assert_eq!(
+            pairs.0,
+            vec![[Scalar::from(1), Scalar::from(2), Scalar::zero()]]
+        );
+
+        let rows = tagged_rows(&[xor, LookupTable::and(1)], 32);
+        assert_eq!(rows.len(), 32);
+        assert_eq!(rows[0][3], Scalar::from(1));
+        assert_eq!(rows[16][3], Scalar::from(2));
+        assert_eq!(
+            rows[31],
+            [Scalar::one(), Scalar::one(), Scalar::one(), Scalar::from(2)]
+        );
+        assert_eq!(tagged_rows(&[], 4), vec![[Scalar::zero(); 4]; 4]);
+    }
+
+    #[test]
+    fn test_lookup_product() {
+        let mut rng = ChaCha20Rng::from_seed([0u8; 32]);
+        let table = (1..=4).map(Scalar::from).collect::<Vec<_>>();
+        let queries = [3, 1, 3, 4].map(Scalar::from);
+        let (delta, epsilon) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+
+        let (h_1, h_2) = sorted_halves(&queries, &table).unwrap();
+        assert_eq!(h_1, [1, 2, 3, 4].map(Scalar::from));
+        assert_eq!(h_2, [1, 3, 3, 4].map(Scalar::from));
+
+        // The product wraps around to one.
+        let evals = lookup_product(&queries, &table, &h_1, &h_2, &delta, &epsilon).unwrap();
+        let last = evals.len() - 1;
+        let one_plus_delta = Scalar::one() + delta;
+        let closing = evals[last]
+            * one_plus_delta
+            * (epsilon + queries[last])
+            * (epsilon * one_plus_delta + table[last] + delta * table[0])
+            * ((epsilon * one_plus_delta + h_1[last] + delta * h_2[last])
+                * (epsilon * one_plus_delta + h_2[last] + delta * h_1[0]))
+                .invert()
+                .unwrap();
+        assert_eq!(closing, Scalar::one());
+
+        assert_eq!(
+            sorted_halves(&[Scalar::from(1), Scalar::from(5)], &table),
+            Err(PlonkError::LookupFailed { row: 1 })
+        );
+    }
+}
@@ -9,7 +9,8 @@
 // - isis agora lovecruft <isis@patternsinthevoid.net>
 // - Henry de Valence <hdevalence@hdevalence.ca>
 
-//! Internal macros.
+//! Internal macros. The `Generic` variants take a type parameter with its bound, e.g.
+//! `Generic = F: PrimeField, LHS = Polynomial<F>, ...`.
 
 /// Define borrow and non-borrow variants of `Add`.
 #[macro_export]
@@ -36,6 +37,28 @@ macro_rules! define_add_variants {
             }
         }
     };
+    (Generic = $g:ident: $bound:path, LHS = $lhs:ty, RHS = $rhs:ty, Output = $out:ty) => {
+        impl<'b, $g: $bound> Add<&'b $rhs> for $lhs {
+            type Output = $out;
+            fn add(self, rhs: &'b $rhs) -> $out {
+                &self + rhs
+            }
+        }
+
+        impl<'a, $g: $bound> Add<$rhs> for &'a $lhs {
+            type Output = $out;
+            fn add(self, rhs: $rhs) -> $out {
+                self + &rhs
+            }
+        }
+
+        impl<$g: $bound> Add<$rhs> for $lhs {
+            type Output = $out;
+            fn add(self, rhs: $rhs) -> $out {
+                &self + &rhs
+            }
+        }
+    };
 }
 
 /// Define non-borrow variants of `AddAssign`.
@@ -48,6 +71,13 @@ macro_rules! define_add_assign_variants {
             }
         }
     };
+    (Generic = $g:ident: $bound:path, LHS = $lhs:ty, RHS = $rhs:ty) => {
+        impl<$g: $bound> AddAssign<$rhs> for $lhs {
+            fn add_assign(&mut self, rhs: $rhs) {
+                *self += &rhs;
+            }
+        }
+    };
 }
 
 /// Define borrow and non-borrow variants of `Sub`.
@@ -75,6 +105,28 @@ macro_rules! define_sub_variants {
             }
         }
     };
+    (Generic = $g:ident: $bound:path, LHS = $lhs:ty, RHS = $rhs:ty, Output = $out:ty) => {
+        impl<'b, $g: $bound> Sub<&'b $rhs> for $lhs {
+            type Output = $out;
+            fn sub(self, rhs: &'b $rhs) -> $out {
+                &self - rhs
+            }
+        }
+
+        impl<'a, $g: $bound> Sub<$rhs> for &'a $lhs {
+            type Output = $out;
+            fn sub(self, rhs: $rhs) -> $out {
+                self - &rhs
+            }
+        }
+
+        impl<$g: $bound> Sub<$rhs> for $lhs {
+            type Output = $out;
+            fn sub(self, rhs: $rhs) -> $out {
+                &self - &rhs
+            }
+        }
+    };
 }
 
 /// Define non-borrow variants of `SubAssign`.
@@ -87,6 +139,13 @@ macro_rules! define_sub_assign_variants {
             }
         }
     };
+    (Generic = $g:ident: $bound:path, LHS = $lhs:ty, RHS = $rhs:ty) => {
+        impl<$g: $bound> SubAssign<$rhs> for $lhs {
+            fn sub_assign(&mut self, rhs: $rhs) {
+                *self -= &rhs;
+            }
+        }
+    };
 }
 
 /// Define borrow and non-borrow variants of `Mul`.
@@ -114,6 +173,28 @@ macro_rules! define_mul_variants {
             }
         }
     };
+    (Generic = $g:ident: $bound:path, LHS = $lhs:ty, RHS = $rhs:ty, Output = $out:ty) => {
+        impl<'b, $g: $bound> Mul<&'b $rhs> for $lhs {
+            type Output = $out;
+            fn mul(self, rhs: &'b $rhs) -> $out {
+                &self * rhs
+            }
+        }
+
+        impl<'a, $g: $bound> Mul<$rhs> for &'a $lhs {
+            type Output = $out;
+            fn mul(self, rhs: $rhs) -> $out {
+                self * &rhs
+            }
+        }
+
+        impl<$g: $bound> Mul<$rhs> for $lhs {
+            type Output = $out;
+            fn mul(self, rhs: $rhs) -> $out {
+                &self * &rhs
+            }
+        }
+    };
 }
 
 /// Define non-borrow variants of `MulAssign`.
@@ -126,4 +207,11 @@ macro_rules! define_mul_assign_variants {
             }
         }
     };
+    (Generic = $g:ident: $bound:path, LHS = $lhs:ty, RHS = $rhs:ty) => {
+        impl<$g: $bound> MulAssign<$rhs> for $lhs {
+            fn mul_assign(&mut self, rhs: $rhs) {
+                *self *= &rhs;
+            }
+        }
+    };
 }
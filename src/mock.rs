@@ -0,0 +1,327 @@
+// Debugging harness for circuits. The mock prover checks the same relations as the prover, the
+// gates (with the public inputs), the copy constraints and the lookups, but directly over the
+// rows of the trace instead of over committed polynomials. So instead of a proof that fails to
+// verify, it reports every constraint that the trace violates, with the labels of its gates and
+// wires (see `PlonkCircuit::label_gate` and `PlonkCircuit::label`).
+use crate::plonk::{ComputationTrace, PlonkCircuit, UnsatisfiedGate, Wire};
+use blstrs::Scalar;
+use std::fmt;
+
+/// Wire involved in a failure, with its label if it has one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LabelledWire {
+    pub wire: Wire,
+    pub label: Option<String>,
+}
+
+impl fmt::Display for LabelledWire {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let column = ["a", "b", "c"][self.wire.column];
+        match &self.label {
+            Some(label) => write!(f, "{label} ({column}[{}])", self.wire.row),
+            None => write!(f, "{column}[{}]", self.wire.row),
+        }
+    }
+}
+
+/// Constraint violated by the trace given to `MockProver::verify`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Failure {
+    /// The number of public values does not match the public inputs of the circuit.
+    PublicInputLength { expected: usize, got: usize },
+    /// The wire of the `index`-th public input does not carry its public value.
+    PublicInput {
+        index: usize,
+        wire: LabelledWire,
+        expected: Scalar,
+        got: Scalar,
+    },
+    /// Two connected wires have different values.
+    CopyConstraint {
+        wire: LabelledWire,
+        connected: LabelledWire,
+        values: (Scalar, Scalar),
+    },
+    /// The gate at `row` does not hold for the values of its wires (a, b, c).
+    Gate {
+        row: usize,
+        label: Option<String>,
+        wires: [LabelledWire; 3],
+        values: [Scalar; 3],
+    },
+    /// The wires (a, b, c) of the lookup gate at `row` are not a row of its table.
+    Lookup {
+        row: usize,
+        label: Option<String>,
+        wires: [LabelledWire; 3],
+        values: [Scalar; 3],
+    },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::PublicInputLength { expected, got } => {
+                write!(f, "Expected {expected} public inputs, got {got}")
+            }
+            Failure::PublicInput {
+                index,
+                wire,
+                expected,
+                got,
+            } => write!(
+                f,
+                "Public input {index} at {wire} should be {expected:?}, but is {got:?}"
+            ),
+            Failure::CopyConstraint {
+                wire,
+                connected,
+                values,
+            } => write!(
+                f,
+                "Copy constraint between {wire} = {:?} and {connected} = {:?}",
+                values.0, values.1
+            ),
+            Failure::Gate {
+                row,
+                label,
+                wires,
+                values,
+            } => {
+                write!(f, "Gate {}at row {row} with", name(label))?;
+                write_wires(f, wires, values)
+            }
+            Failure::Lookup {
+                row,
+                label,
+                wires,
+                values,
+            } => {
+                write!(f, "Lookup {}at row {row} with", name(label))?;
+                write_wires(f, wires, values)
+            }
+        }
+    }
+}
+
+fn name(label: &Option<String>) -> String {
+    label
+        .as_ref()
+        .map_or(String::new(), |label| format!("{label} "))
+}
+
+fn write_wires(
+    f: &mut fmt::Formatter<'_>,
+    wires: &[LabelledWire; 3],
+    values: &[Scalar; 3],
+) -> fmt::Result {
+    for (i, (wire, value)) in wires.iter().zip(values).enumerate() {
+        let separator = if i == 0 { "" } else { "," };
+        write!(f, "{separator} {wire} = {value:?}")?;
+    }
+    Ok(())
+}
+
+/// Prover that only checks the trace, without producing a proof.
+///
+/// ```
+/// use blstrs::Scalar;
+/// use dummy_plonk::{ComputationTrace, Failure, MockProver, PlonkCircuit};
+///
+/// let mut circuit = PlonkCircuit::init();
+/// let (x_l, x_r, x_sq) = circuit.mult_gate();
+/// circuit.connect(x_l, x_r).unwrap();
+/// circuit.label_gate(0, "square");
+///
+/// let mut trace = ComputationTrace::new(&circuit);
+/// trace.set(x_l, Scalar::from(3));
+/// trace.set(x_r, Scalar::from(3));
+/// trace.set(x_sq, Scalar::from(10));
+/// let failures = MockProver::verify(&[], &circuit, &trace).unwrap_err();
+/// assert!(matches!(
+///     failures.as_slice(),
+///     [Failure::Gate { row: 0, label: Some(label), .. }] if label == "square"
+/// ));
+/// ```
+pub struct MockProver;
+
+impl MockProver {
+    /// Checks that `trace`, with the public values `public_in`, satisfies `circuit`, and
+    /// returns every constraint that it violates otherwise.
+    pub fn verify(
+        public_in: &[Scalar],
+        circuit: &PlonkCircuit,
+        trace: &ComputationTrace,
+    ) -> Result<(), Vec<Failure>> {
+        let label = |wire: Wire| LabelledWire {
+            wire,
+            label: circuit.labels.get(&wire).cloned(),
+        };
+        let row_wires = |row: usize| {
+            [
+                label(Wire::left(row)),
+                label(Wire::right(row)),
+                label(Wire::output(row)),
+            ]
+        };
+
+        let mut failures = Vec::new();
+        if public_in.len() != circuit.public_inputs.len() {
+            failures.push(Failure::PublicInputLength {
+                expected: circuit.public_inputs.len(),
+                got: public_in.len(),
+            });
+        }
+        // Public input gates only have q_L = 1, so that the row holds if a = x_i.
+        for (index, (row, expected)) in circuit.public_inputs.iter().zip(public_in).enumerate() {
+            let got = trace.value(Wire::left(*row));
+            if got != *expected {
+                failures.push(Failure::PublicInput {
+                    index,
+                    wire: label(Wire::left(*row)),
+                    expected: *expected,
+                    got,
+                });
+            }
+        }
+
+        failures.extend(
+            circuit
+                .unsatisfied(trace)
+                .map(|constraint| match constraint {
+                    UnsatisfiedGate::CopyConstraint {
+                        wire,
+                        connected,
+                        values,
+                    } => Failure::CopyConstraint {
+                        wire: label(wire),
+                        connected: label(connected),
+                        values,
+                    },
+                    UnsatisfiedGate::Gate {
+                        row,
+                        label,
+                        a,
+                        b,
+                        c,
+                    } => Failure::Gate {
+                        row,
+                        label,
+                        wires: row_wires(row),
+                        values: [a, b, c],
+                    },
+                    UnsatisfiedGate::Lookup {
+                        row,
+                        label,
+                        a,
+                        b,
+                        c,
+                    } => Failure::Lookup {
+                        row,
+                        label,
+                        wires: row_wires(row),
+                        values: [a, b, c],
+                    },
+                }),
+        );
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_prover() {
+        // x * x = y, with y public, and y + x = z.
+        let mut circuit = PlonkCircuit::init();
+        let pi = circuit.public_input();
+        let (x_l, x_r, y) = circuit.mult_gate();
+        let (y_copy, x_copy, z) = circuit.add_gate_labeled("sum");
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(x_l, x_copy).unwrap();
+        circuit.connect(pi, y).unwrap();
+        circuit.connect(y, y_copy).unwrap();
+        circuit.label(x_l, "x");
+        circuit.label(y, "y");
+        circuit.label(z, "z");
+
+        let assign = |x: u64, y: u64, z: u64| {
+            let mut trace = ComputationTrace::new(&circuit);
+            let (x, y, z) = (Scalar::from(x), Scalar::from(y), Scalar::from(z));
+            for (wire, value) in [
+                (pi, y),
+                (x_l, x),
+                (x_r, x),
+                (Wire::output(1), y),
+                (y_copy, y),
+                (x_copy, x),
+                (Wire::output(2), z),
+            ] {
+                trace.set(wire, value);
+            }
+            trace
+        };
+        let public_in = [Scalar::from(9)];
+        assert_eq!(
+            MockProver::verify(&public_in, &circuit, &assign(3, 9, 12)),
+            Ok(())
+        );
+
+        let failures = MockProver::verify(&public_in, &circuit, &assign(3, 9, 13)).unwrap_err();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(
+            failures[0].to_string(),
+            format!(
+                "Gate sum at row 2 with a[2] = {:?}, b[2] = {:?}, z (c[2]) = {:?}",
+                Scalar::from(9),
+                Scalar::from(3),
+                Scalar::from(13)
+            )
+        );
+
+        // 4 * 4 = 16 is a valid product, but not the public value.
+        let failures = MockProver::verify(&public_in, &circuit, &assign(4, 16, 20)).unwrap_err();
+        assert_eq!(
+            failures,
+            vec![Failure::PublicInput {
+                index: 0,
+                wire: LabelledWire {
+                    wire: pi,
+                    label: None
+                },
+                expected: Scalar::from(9),
+                got: Scalar::from(16)
+            }]
+        );
+
+        let mut trace = assign(3, 9, 12);
+        trace.set(x_r, Scalar::from(4));
+        let failures = MockProver::verify(&[], &circuit, &trace).unwrap_err();
+        assert_eq!(
+            failures[0],
+            Failure::PublicInputLength {
+                expected: 1,
+                got: 0
+            }
+        );
+        assert!(failures.iter().any(|failure| matches!(
+            failure,
+            Failure::CopyConstraint { wire, .. } if wire.wire == x_r
+        )));
+        assert!(failures.iter().any(|failure| matches!(
+            failure,
+            Failure::Gate { row: 1, wires, .. } if wires[0].label.as_deref() == Some("x")
+        )));
+        // The row of the public input is only checked against the public value.
+        assert!(!failures
+            .iter()
+            .any(|failure| matches!(failure, Failure::Gate { row: 0, .. })));
+    }
+}
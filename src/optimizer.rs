@@ -0,0 +1,616 @@
+// Optimization pass over a built circuit, run by `PlonkCircuit::optimize`.
+//
+// Constants are the values of the wires connected to the left wire of a constant gate (see
+// `PlonkCircuit::assert_constant`), or to the output of a gate that only has q_O and q_C. A
+// constant wire is folded into the selectors of the other gates that use it, e.g. q_L * a with
+// a = v becomes q_C += q_L * v and q_M * a * b becomes q_R += q_M * v, and is then disconnected.
+//
+// A gate is dead if it cannot constrain the rest of the circuit: either its output is not
+// connected and q_O != 0, so that any a and b have a matching c, or none of the wires that it
+// uses is connected and it has a solution. Dead gates are removed along with their wires,
+// which may leave other gates dead, until there are none left. Public input gates, lookup
+// gates, and gates that use the next row (along with that row, so that they stay adjacent) are
+// never folded or removed, nor are the wires to keep and their gates.
+//
+// Packing, run by `PlonkCircuit::pack`, merges a gate into the only gate that uses its output,
+// when the gate equation of the second one, with the output of the first substituted, still
+// fits in a row: at most one product and three values, such as t = 2 * x + 1 feeding
+// z = t * w, which becomes z = 2 * x * w + w, or t = x * w feeding z = t + x. The merged gate
+// takes the row of the second gate, and keeps its output in the c wire, so that the trace is
+// still derived row after row. The first gate may not have wires to keep, and those of the
+// second one must keep their values.
+use crate::error::PlonkError;
+use crate::plonk::PlonkCircuit;
+use crate::wire::{Wire, WIDTH};
+use blstrs::Scalar;
+use ff::Field;
+use std::collections::{HashMap, HashSet};
+
+/// Result of `PlonkCircuit::optimize`, with the new row of every row of the original circuit.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Optimization {
+    pub gates_removed: usize,
+    pub constants_folded: usize,
+    pub gates_merged: usize,
+    pub rows: Vec<Option<usize>>, // new row of each original row, if it was kept
+}
+
+impl Optimization {
+    /// Wire of the optimized circuit for a wire of the original one, if its gate was kept.
+    pub fn wire(&self, wire: Wire) -> Option<Wire> {
+        self.rows.get(wire.row).copied().flatten().map(|row| Wire {
+            row,
+            column: wire.column,
+        })
+    }
+}
+
+struct Optimizer<'a> {
+    circuit: &'a mut PlonkCircuit,
+    alive: Vec<bool>,
+    fixed: Vec<bool>, // public input and lookup rows, and the rows chained by q_N
+    keep: HashSet<Wire>,
+    constants_folded: usize,
+    gates_merged: usize,
+}
+
+// Polynomial in the values of the cycles of the permutation, each given by its smallest wire,
+// as monomials with their coefficients.
+type Polynomial = Vec<(Vec<Wire>, Scalar)>;
+
+fn add_monomial(polynomial: &mut Polynomial, mut monomial: Vec<Wire>, coefficient: Scalar) {
+    monomial.sort();
+    match polynomial.iter().position(|(m, _)| *m == monomial) {
+        Some(i) => polynomial[i].1 += coefficient,
+        None => polynomial.push((monomial, coefficient)),
+    }
+}
+
+impl<'a> Optimizer<'a> {
+    fn new(circuit: &'a mut PlonkCircuit, keep: &[Wire]) -> Result<Self, PlonkError> {
+        if let Some(wire) = keep
+            .iter()
+            .find(|wire| !circuit.permutations.contains_key(wire))
+        {
+            return Err(PlonkError::UnknownWire(*wire));
+        }
+        let mut fixed = circuit
+            .constraints
+            .qk
+            .iter()
+            .map(|qk| *qk == Scalar::one())
+            .collect::<Vec<_>>();
+        for row in circuit.public_inputs.iter() {
+            fixed[*row] = true;
+        }
+        let n = circuit.nr_constraints;
+        for row in 0..n {
+            if circuit.constraints.qn[row] != Scalar::zero() {
+                fixed[row] = true;
+                fixed[(row + 1) % n] = true;
+            }
+        }
+
+        Ok(Optimizer {
+            alive: vec![true; circuit.nr_constraints],
+            fixed,
+            keep: keep.iter().copied().collect(),
+            circuit,
+            constants_folded: 0,
+            gates_merged: 0,
+        })
+    }
+
+    fn selectors(&self, row: usize) -> [Scalar; 5] {
+        let q = &self.circuit.constraints;
+        [q.ql[row], q.qr[row], q.qo[row], q.qm[row], q.qc[row]]
+    }
+
+    // Whether the gate at `row` has a term in the wire of `column`.
+    fn uses(&self, row: usize, column: usize) -> bool {
+        let [ql, qr, qo, qm, _] = self.selectors(row);
+        let q = &self.circuit.constraints;
+        let previous = (row + q.qn.len() - 1) % q.qn.len();
+        q.qk[row] == Scalar::one()
+            || self.circuit.public_inputs.contains(&row) && column == 0
+            || q.qn[previous] != Scalar::zero() && column == 0
+            || match column {
+                0 => !ql.is_zero_vartime() || !qm.is_zero_vartime(),
+                1 => !qr.is_zero_vartime() || !qm.is_zero_vartime(),
+                _ => !qo.is_zero_vartime(),
+            }
+    }
+
+    fn connected(&self, wire: Wire) -> bool {
+        self.circuit.permutations[&wire] != wire
+    }
+
+    // Removes `wire` from its cycle of the permutation.
+    fn disconnect(&mut self, wire: Wire) {
+        let next = self.circuit.permutations[&wire];
+        if next == wire {
+            return;
+        }
+        let mut previous = next;
+        while self.circuit.permutations[&previous] != wire {
+            previous = self.circuit.permutations[&previous];
+        }
+        self.circuit.permutations.insert(previous, next);
+        self.circuit.permutations.insert(wire, wire);
+    }
+
+    fn cycle(&self, wire: Wire) -> Vec<Wire> {
+        let mut cycle = vec![wire];
+        let mut current = self.circuit.permutations[&wire];
+        while current != wire {
+            cycle.push(current);
+            current = self.circuit.permutations[&current];
+        }
+        cycle
+    }
+
+    // Each wire of a constant cycle, with its value and the wire of the gate that defines it,
+    // which is not folded.
+    fn constants(&self) -> HashMap<Wire, (Scalar, Wire)> {
+        let mut definitions = self
+            .circuit
+            .constants
+            .iter()
+            .filter(|(row, _)| self.alive[*row] && self.uses(*row, 0))
+            .map(|(row, value)| (Wire::left(*row), *value))
+            .collect::<Vec<_>>();
+        for row in 0..self.circuit.nr_constraints {
+            let [ql, qr, qo, qm, qc] = self.selectors(row);
+            let q = &self.circuit.constraints;
+            if self.alive[row]
+                && q.qk[row] != Scalar::one()
+                && !self.circuit.public_inputs.contains(&row)
+                && ql.is_zero_vartime()
+                && qr.is_zero_vartime()
+                && qm.is_zero_vartime()
+            {
+                if let Some(inverse) = Option::<Scalar>::from(qo.invert()) {
+                    definitions.push((Wire::output(row), -qc * inverse));
+                }
+            }
+        }
+
+        let mut constants = HashMap::new();
+        for (definition, value) in definitions {
+            if constants.contains_key(&definition) {
+                continue;
+            }
+            for wire in self.cycle(definition) {
+                constants.insert(wire, (value, definition));
+            }
+        }
+        constants
+    }
+
+    fn variable(&self, wire: Wire) -> Wire {
+        self.cycle(wire)
+            .into_iter()
+            .min()
+            .expect("Cycles are not empty")
+    }
+
+    // Gate equation of `row` with the given selectors, where the wire of `substitution` is
+    // replaced by a polynomial.
+    fn polynomial(
+        &self,
+        row: usize,
+        [ql, qr, qo, qm, qc]: [Scalar; 5],
+        substitution: Option<(Wire, &Polynomial)>,
+    ) -> Polynomial {
+        let value = |column: usize| match substitution {
+            Some((wire, polynomial)) if wire == (Wire { row, column }) => polynomial.clone(),
+            _ => vec![(vec![self.variable(Wire { row, column })], Scalar::one())],
+        };
+        let mut polynomial = Vec::new();
+        for (column, selector) in [(0, ql), (1, qr), (2, qo)] {
+            if !selector.is_zero_vartime() {
+                for (monomial, coefficient) in value(column) {
+                    add_monomial(&mut polynomial, monomial, coefficient * selector);
+                }
+            }
+        }
+        if !qm.is_zero_vartime() {
+            for (left, l) in value(0) {
+                for (right, r) in value(1) {
+                    add_monomial(&mut polynomial, [left.clone(), right].concat(), l * r * qm);
+                }
+            }
+        }
+        add_monomial(&mut polynomial, Vec::new(), qc);
+        polynomial.retain(|(_, coefficient)| !coefficient.is_zero_vartime());
+        polynomial
+    }
+
+    // Merges the gate at `row` into the gate that uses its output, if it fits in a row.
+    fn merge(&mut self, row: usize) -> Result<bool, PlonkError> {
+        let output = Wire::output(row);
+        let cycle = self.cycle(output);
+        let [ql, qr, qo, qm, qc] = self.selectors(row);
+        let (Some(inverse), [_, used]) = (Option::<Scalar>::from(qo.invert()), cycle.as_slice())
+        else {
+            return Ok(false);
+        };
+        let next = used.row;
+        let movable = |row: usize| {
+            self.alive[row]
+                && !self.fixed[row]
+                && !self.circuit.constants.iter().any(|(r, _)| *r == row)
+        };
+        let kept = (0..WIDTH).any(|column| self.keep.contains(&Wire { row, column }));
+        if next <= row || kept || !movable(row) || !movable(next) || !self.uses(next, used.column) {
+            return Ok(false);
+        }
+
+        // t = -(q_L * a + q_R * b + q_M * a * b + q_C) / q_O
+        let mut t = self.polynomial(row, [ql, qr, Scalar::zero(), qm, qc], None);
+        for (_, coefficient) in t.iter_mut() {
+            *coefficient *= -inverse;
+        }
+        let merged = self.polynomial(next, self.selectors(next), Some((*used, &t)));
+        if merged.iter().any(|(monomial, _)| monomial.len() > 2) {
+            return Ok(false);
+        }
+        let mut products = merged.iter().filter(|(monomial, _)| monomial.len() == 2);
+        let product = products.next();
+        if products.next().is_some() {
+            return Ok(false);
+        }
+        let linear = merged
+            .iter()
+            .filter(|(monomial, _)| monomial.len() == 1)
+            .map(|(monomial, _)| monomial[0])
+            .collect::<Vec<_>>();
+        let out =
+            (self.uses(next, 2) && used.column != 2).then(|| self.variable(Wire::output(next)));
+
+        // Values of the wires a, b and c of the merged gate.
+        let columns: [Option<Wire>; 3] = match product {
+            Some((pair, _)) => {
+                let others = linear
+                    .iter()
+                    .filter(|wire| !pair.contains(wire))
+                    .collect::<Vec<_>>();
+                if others.len() > 1 || out.is_some() && others.first().copied() != out.as_ref() {
+                    return Ok(false);
+                }
+                [
+                    Some(pair[0]),
+                    Some(pair[1]),
+                    others.first().map(|wire| **wire),
+                ]
+            }
+            None => {
+                if linear.len() > 3 || out.is_some_and(|out| !linear.contains(&out)) {
+                    return Ok(false);
+                }
+                let mut inputs = linear.iter().filter(|wire| Some(**wire) != out);
+                let c = out.or_else(|| linear.get(2).copied());
+                [inputs.next().copied(), inputs.next().copied(), c]
+            }
+        };
+        // Wires to keep stay where they are, with the same value.
+        for (column, variable) in columns.iter().enumerate() {
+            let wire = Wire { row: next, column };
+            if self.keep.contains(&wire) && *variable != Some(self.variable(wire)) {
+                return Ok(false);
+            }
+        }
+        let coefficient = |variable: Option<Wire>| {
+            merged
+                .iter()
+                .find(|(monomial, _)| variable.is_some_and(|v| *monomial == [v]))
+                .map_or(Scalar::zero(), |(_, coefficient)| *coefficient)
+        };
+        let q = &mut self.circuit.constraints;
+        q.ql[next] = coefficient(columns[0]);
+        q.qr[next] = if columns[1] == columns[0] {
+            Scalar::zero()
+        } else {
+            coefficient(columns[1])
+        };
+        q.qo[next] = coefficient(columns[2]);
+        q.qm[next] = product.map_or(Scalar::zero(), |(_, coefficient)| *coefficient);
+        q.qc[next] = merged
+            .iter()
+            .find(|(monomial, _)| monomial.is_empty())
+            .map_or(Scalar::zero(), |(_, coefficient)| *coefficient);
+        for selector in [&mut q.ql, &mut q.qr, &mut q.qo, &mut q.qm, &mut q.qc] {
+            selector[row] = Scalar::zero();
+        }
+
+        // Each value is connected to a wire outside of the two gates, if it has one.
+        let outside = columns.map(|variable| {
+            variable.and_then(|variable| {
+                self.cycle(variable)
+                    .into_iter()
+                    .find(|wire| wire.row != row && wire.row != next)
+            })
+        });
+        for column in 0..WIDTH {
+            self.disconnect(Wire { row, column });
+            self.disconnect(Wire { row: next, column });
+        }
+        for column in 0..WIDTH {
+            let wire = Wire { row: next, column };
+            let placed =
+                (0..column).find(|c| columns[*c].is_some() && columns[*c] == columns[column]);
+            match (outside[column], placed) {
+                (Some(outside), _) => self.circuit.connect(outside, wire)?,
+                (None, Some(placed)) => self.circuit.connect(
+                    Wire {
+                        row: next,
+                        column: placed,
+                    },
+                    wire,
+                )?,
+                (None, None) => {}
+            }
+        }
+        self.alive[row] = false;
+        self.circuit.gate_labels.remove(&row);
+        self.gates_merged += 1;
+        Ok(true)
+    }
+
+    // One round of folding and removal, returning whether the circuit changed.
+    fn round(&mut self) -> bool {
+        let mut changed = false;
+
+        // Wires that the gates do not use are only connected by mistake.
+        for row in 0..self.circuit.nr_constraints {
+            if !self.alive[row] {
+                continue;
+            }
+            for column in 0..WIDTH {
+                let wire = Wire { row, column };
+                if !self.uses(row, column) && !self.keep.contains(&wire) && self.connected(wire) {
+                    self.disconnect(wire);
+                    changed = true;
+                }
+            }
+        }
+
+        let constants = self.constants();
+        for row in 0..self.circuit.nr_constraints {
+            if !self.alive[row] || self.fixed[row] {
+                continue;
+            }
+            for column in 0..WIDTH {
+                let wire = Wire { row, column };
+                let Some((value, definition)) = constants.get(&wire) else {
+                    continue;
+                };
+                if *definition == wire || !self.uses(row, column) || self.keep.contains(&wire) {
+                    continue;
+                }
+                let q = &mut self.circuit.constraints;
+                match column {
+                    0 => {
+                        q.qc[row] += q.ql[row] * value;
+                        q.qr[row] += q.qm[row] * value;
+                        q.ql[row] = Scalar::zero();
+                        q.qm[row] = Scalar::zero();
+                    }
+                    1 => {
+                        q.qc[row] += q.qr[row] * value;
+                        q.ql[row] += q.qm[row] * value;
+                        q.qr[row] = Scalar::zero();
+                        q.qm[row] = Scalar::zero();
+                    }
+                    _ => {
+                        q.qc[row] += q.qo[row] * value;
+                        q.qo[row] = Scalar::zero();
+                    }
+                }
+                self.disconnect(wire);
+                self.circuit
+                    .constants
+                    .retain(|(r, _)| *r != row || column != 0);
+                self.constants_folded += 1;
+                changed = true;
+            }
+        }
+
+        for row in 0..self.circuit.nr_constraints {
+            let kept = (0..WIDTH).any(|column| self.keep.contains(&Wire { row, column }));
+            if !self.alive[row] || self.fixed[row] || kept {
+                continue;
+            }
+            let used = (0..WIDTH)
+                .filter(|column| self.uses(row, *column))
+                .map(|column| Wire { row, column })
+                .collect::<Vec<_>>();
+            let [ql, qr, qo, qm, qc] = self.selectors(row);
+            let free_output = !qo.is_zero_vartime() && !self.connected(Wire::output(row));
+            let unsatisfiable =
+                [ql, qr, qo, qm].iter().all(|q| q.is_zero_vartime()) && !qc.is_zero_vartime();
+            let isolated = used.iter().all(|wire| !self.connected(*wire));
+            if free_output || (isolated && !unsatisfiable) {
+                for column in 0..WIDTH {
+                    self.disconnect(Wire { row, column });
+                }
+                self.alive[row] = false;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    // Drops the removed rows, and renumbers the others.
+    fn compact(self) -> Optimization {
+        let circuit = self.circuit;
+        let mut rows = Vec::with_capacity(circuit.nr_constraints);
+        let mut next = 0;
+        for alive in self.alive.iter() {
+            rows.push(alive.then(|| {
+                next += 1;
+                next - 1
+            }));
+        }
+        let shift = |wire: &Wire| {
+            rows[wire.row].map(|row| Wire {
+                row,
+                column: wire.column,
+            })
+        };
+
+        let q = &mut circuit.constraints;
+        for selector in [
+            &mut q.ql, &mut q.qr, &mut q.qo, &mut q.qm, &mut q.qc, &mut q.qk, &mut q.qt, &mut q.qn,
+        ] {
+            let mut alive = self.alive.iter();
+            selector.retain(|_| *alive.next().unwrap());
+        }
+        circuit.permutations = circuit
+            .permutations
+            .iter()
+            .filter_map(|(wire, next)| Some((shift(wire)?, shift(next)?)))
+            .collect();
+        circuit.public_inputs = circuit
+            .public_inputs
+            .iter()
+            .filter_map(|row| rows[*row])
+            .collect();
+        circuit.constants = circuit
+            .constants
+            .iter()
+            .filter_map(|(row, value)| Some((rows[*row]?, *value)))
+            .collect();
+        circuit.labels = circuit
+            .labels
+            .iter()
+            .filter_map(|(wire, label)| Some((shift(wire)?, label.clone())))
+            .collect();
+        circuit.gate_labels = circuit
+            .gate_labels
+            .iter()
+            .filter_map(|(row, label)| Some((rows[*row]?, label.clone())))
+            .collect();
+        circuit.nr_constraints = next;
+        circuit.nr_wires = WIDTH * next;
+        circuit.extended_h_subgroup.clear();
+
+        Optimization {
+            gates_removed: rows.len() - next,
+            constants_folded: self.constants_folded,
+            gates_merged: self.gates_merged,
+            rows,
+        }
+    }
+}
+
+pub(crate) fn optimize(
+    circuit: &mut PlonkCircuit,
+    keep: &[Wire],
+) -> Result<Optimization, PlonkError> {
+    let mut optimizer = Optimizer::new(circuit, keep)?;
+    while optimizer.round() {}
+    Ok(optimizer.compact())
+}
+
+pub(crate) fn pack(circuit: &mut PlonkCircuit, keep: &[Wire]) -> Result<Optimization, PlonkError> {
+    let mut optimizer = Optimizer::new(circuit, keep)?;
+    let mut merged = true;
+    while merged {
+        merged = false;
+        for row in 0..optimizer.circuit.nr_constraints {
+            merged |= optimizer.merge(row)?;
+        }
+    }
+    Ok(optimizer.compact())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::Gate;
+    use crate::witness::WitnessBuilder;
+
+    #[test]
+    fn test_optimize() {
+        // y = (x * k) + (k + 2) with k = 3, and a dead gate x + x.
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.gate(Gate::new());
+        let (k, _, _) = circuit.gate(Gate::new());
+        circuit.assert_constant(k, Scalar::from(3)).unwrap();
+        let (x_l, k_r, product) = circuit.mult_gate();
+        let (k_l, _, k_plus_two) = circuit.gate(Gate::new().left(1).constant(2).output(-1));
+        let (product_l, sum_r, y) = circuit.add_gate();
+        let (dead_l, dead_r, _) = circuit.add_gate();
+        circuit.connect_all(&[x, x_l, dead_l, dead_r]).unwrap();
+        circuit.connect_all(&[k, k_r, k_l]).unwrap();
+        circuit.connect(product, product_l).unwrap();
+        circuit.connect(k_plus_two, sum_r).unwrap();
+        circuit.label(y, "y");
+        circuit.pad_next_power_of_two();
+
+        let optimization = circuit.optimize(&[x, y]).unwrap();
+        // The gates of x, x * 3 and the sum 3 * x + 5 are kept.
+        assert_eq!(circuit.nr_constraints, 3);
+        assert_eq!(optimization.gates_removed, 5);
+        assert_eq!(optimization.constants_folded, 3);
+        assert_eq!(optimization.gates_merged, 0);
+        let (x, y) = (optimization.wire(x).unwrap(), optimization.wire(y).unwrap());
+        assert_eq!(optimization.wire(dead_l), None);
+        assert_eq!(circuit.labels[&y], "y");
+        assert!(circuit.constants.is_empty());
+
+        let mut builder = WitnessBuilder::new(&circuit);
+        builder.assign(x, Scalar::from(4)).unwrap();
+        let trace = builder.build().unwrap();
+        assert_eq!(trace.value(y), Scalar::from(17));
+        assert!(circuit.is_satisfied(&trace).is_ok());
+
+        let mut trace = trace;
+        trace.set(y, Scalar::from(18));
+        assert!(circuit.is_satisfied(&trace).is_err());
+        assert_eq!(
+            circuit.optimize(&[Wire::left(3)]),
+            Err(PlonkError::UnknownWire(Wire::left(3)))
+        );
+    }
+
+    #[test]
+    fn test_pack() {
+        // z = (2 * x + 1) * w and s = x * w + x merge, but not n = 1 - x feeding n * n, as
+        // the output of n is connected to two wires.
+        let mut circuit = PlonkCircuit::init();
+        let x = circuit.public_input();
+        let (w, _, _) = circuit.gate(Gate::new());
+        let (x_1, _, t) = circuit.gate(Gate::new().left(2).constant(1).output(-1));
+        let (t_l, w_1, z) = circuit.mult_gate();
+        let (x_2, w_2, p) = circuit.mult_gate();
+        let (p_l, x_3, s) = circuit.add_gate();
+        let (x_4, _, not) = circuit.gate(Gate::new().left(-1).constant(1).output(-1));
+        let (not_l, not_r, square) = circuit.mult_gate();
+        circuit.connect_all(&[x, x_1, x_2, x_3, x_4]).unwrap();
+        circuit.connect_all(&[w, w_1, w_2]).unwrap();
+        circuit.connect(t, t_l).unwrap();
+        circuit.connect(p, p_l).unwrap();
+        circuit.connect_all(&[not, not_l, not_r]).unwrap();
+
+        let packing = circuit.pack(&[x, w, z, s, square]).unwrap();
+        assert_eq!(packing.gates_merged, 2);
+        assert_eq!(circuit.nr_constraints, 6);
+        let wires = [x, w, z, s, square].map(|wire| packing.wire(wire).unwrap());
+
+        let mut builder = WitnessBuilder::new(&circuit);
+        builder.assign(wires[0], Scalar::from(3)).unwrap();
+        builder.assign(wires[1], Scalar::from(5)).unwrap();
+        let trace = builder.build().unwrap();
+        assert_eq!(trace.value(wires[2]), Scalar::from(35));
+        assert_eq!(trace.value(wires[3]), Scalar::from(18));
+        assert_eq!(trace.value(wires[4]), Scalar::from(4));
+        assert!(circuit.is_satisfied(&trace).is_ok());
+
+        let mut trace = trace;
+        trace.set(wires[2], Scalar::from(36));
+        assert!(circuit.is_satisfied(&trace).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+// Data parallel helpers of the prover. With the `parallel` feature, work is split in one
+// contiguous batch per available core, run with scoped threads. Helpers called from a worker
+// thread run sequentially, so that nested calls (a commitment inside a parallel setup, an FFT
+// inside a parallel map) do not oversubscribe the cores. Without the feature every helper is a
+// plain sequential loop, so results never depend on the feature.
+use alloc::vec::Vec;
+#[cfg(feature = "parallel")]
+use std::cell::Cell;
+#[cfg(feature = "parallel")]
+use std::thread;
+
+// Slices shorter than this are not worth a thread in `for_each_chunk_mut`.
+#[cfg(feature = "parallel")]
+const MIN_PARALLEL_LEN: usize = 1 << 10;
+
+#[cfg(feature = "parallel")]
+thread_local! {
+    static WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Number of batches in which the helpers split their work.
+#[cfg(feature = "parallel")]
+pub(crate) fn threads() -> usize {
+    if WORKER.with(Cell::get) {
+        return 1;
+    }
+    thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+/// Number of batches in which the helpers split their work.
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn threads() -> usize {
+    1
+}
+
+/// Maps `f` over the items and their indices, preserving the order.
+pub(crate) fn map<T: Sync, R: Send, F: Fn(usize, &T) -> R + Sync>(items: &[T], f: F) -> Vec<R> {
+    #[cfg(feature = "parallel")]
+    if threads() > 1 && items.len() > 1 {
+        let batch = items.len().div_ceil(threads());
+        let f = &f;
+        return thread::scope(|scope| {
+            let handles = items
+                .chunks(batch)
+                .enumerate()
+                .map(|(index, items)| {
+                    scope.spawn(move || {
+                        WORKER.with(|worker| worker.set(true));
+                        items
+                            .iter()
+                            .enumerate()
+                            .map(|(i, item)| f(index * batch + i, item))
+                            .collect::<Vec<R>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+    }
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| f(i, item))
+        .collect()
+}
+
+/// Same as `map`, over an array.
+pub(crate) fn map_array<T: Sync, R: Send, F: Fn(usize, &T) -> R + Sync, const N: usize>(
+    items: &[T; N],
+    f: F,
+) -> [R; N] {
+    let mut results = map(items, f).into_iter();
+    core::array::from_fn(|_| results.next().unwrap())
+}
+
+/// Calls `f` on each chunk of `chunk_size` values (the last one may be shorter), together with
+/// the index of the chunk.
+pub(crate) fn for_each_chunk_mut<T: Send, F: Fn(usize, &mut [T]) + Sync>(
+    values: &mut [T],
+    chunk_size: usize,
+    f: F,
+) {
+    #[cfg(feature = "parallel")]
+    if threads() > 1 && values.len() >= MIN_PARALLEL_LEN.max(2 * chunk_size) {
+        // Each thread gets a whole number of chunks.
+        let batch = values.len().div_ceil(chunk_size).div_ceil(threads());
+        let f = &f;
+        thread::scope(|scope| {
+            for (index, values) in values.chunks_mut(batch * chunk_size).enumerate() {
+                scope.spawn(move || {
+                    WORKER.with(|worker| worker.set(true));
+                    for (i, chunk) in values.chunks_mut(chunk_size).enumerate() {
+                        f(index * batch + i, chunk);
+                    }
+                });
+            }
+        });
+        return;
+    }
+
+    for (i, chunk) in values.chunks_mut(chunk_size).enumerate() {
+        f(i, chunk);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map() {
+        let items = (0..1000u64).collect::<Vec<_>>();
+        let squares = map(&items, |i, item| (i as u64, item * item));
+        assert!(squares
+            .iter()
+            .enumerate()
+            .all(|(i, (index, square))| *index == i as u64 && *square == (i * i) as u64));
+        assert_eq!(map_array(&[1, 2, 3], |i, item| i + item), [1, 3, 5]);
+        assert!(map(&[] as &[u64], |_, item| *item).is_empty());
+    }
+
+    #[test]
+    fn test_for_each_chunk_mut() {
+        let mut values = vec![0usize; 5000];
+        for_each_chunk_mut(&mut values, 7, |index, chunk| {
+            for (i, value) in chunk.iter_mut().enumerate() {
+                *value = 7 * index + i;
+            }
+        });
+        assert!(values.iter().enumerate().all(|(i, value)| *value == i));
+    }
+}
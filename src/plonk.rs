@@ -24,25 +24,53 @@
 //
 // The trace is the left, right and output values of each constraint.
 //
+// A gate may also use the left wire of the next row, through a selector q_N that adds
+// q_N * a(omega * X) to the gate equation, so that a chain of rows (such as the rounds of a
+// hash, or a running sum) is constrained without copying each intermediate value to the next
+// row. The next row of the last one is the first, as omega^n = 1, so the last gate of a padded
+// circuit should not use it.
+//
+// Lookup gates have a selector q_K = 1 (and all other selectors set to zero), and require
+// their wires (a, b, c) to be one of the rows of the lookup table selected by q_T. This is
+// proven with the lookup argument described in `lookup.rs`.
+//
+// Public inputs are exposed through dedicated rows with q_L = 1 and all other
+// selectors set to zero. The claimed value x_i is then added to the gate equation
+// through the public input polynomial PI(X) = - sum_i x_i * L_i(X), where L_i is the
+// lagrange basis of the row of the i-th public input. Hence the row is only satisfied
+// if its left wire equals x_i.
+//
 // We also need to declare the connections between the wires. For that, we require
-// the circuit designer to specify which two wires are connected. For sake
-// of simplicity, we are only exposing an addition and a multiplication gate. Each
-// one of these gates increases the total number of wires by 3, so it should be easy
-// to keep the count in our examples.
+// the circuit designer to specify which two wires are connected. Besides the addition
+// and multiplication gates, we expose gates with arbitrary selectors (see `Gate`), and
+// constant gates that pin a wire to a known value. Each one of these gates increases the
+// total number of wires by 3, and returns a `Wire` handle for each of them, so that
+// connections can be expressed without computing indices by hand.
 #![allow(non_snake_case)]
-use crate::kzg10::Kzg10;
-use crate::polynomial::Polynomial;
-use blstrs::Scalar;
+use crate::commitment::PlonkCommitment;
+use crate::error::PlonkError;
+use crate::expr::{Compiler, Expr};
+use crate::json::Json;
+use crate::kzg10::{Kzg10, Kzg10Commitment};
+use crate::lookup::{tagged_rows, LookupTable};
+use crate::optimizer::{self, Optimization};
+use crate::parallel;
+use crate::polynomial::{zeroize_scalars, EvaluationDomain, Polynomial, SparsePolynomial};
+use crate::proof::PlonkProof;
+use crate::verifier::VerifierKey;
+pub use crate::wire::Wire;
+use crate::wire::{K, WIDTH};
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use blstrs::{G1Affine, G2Affine, Scalar};
 use ff::{Field, PrimeField};
-use std::collections::HashMap;
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{Read, Write};
 use std::ops::Neg;
-
-pub(crate) fn K1() -> Scalar {
-    Scalar::from(7_u64)
-}
-pub(crate) fn K2() -> Scalar {
-    Scalar::from(13_u64)
-}
+use zeroize::Zeroize;
 
 pub struct ComputationTrace {
     pub(crate) a: Vec<Scalar>,
@@ -51,8 +79,80 @@ pub struct ComputationTrace {
 }
 
 impl ComputationTrace {
+    /// Creates an all-zero trace with a row per constraint of `circuit`, where the rows of
+    /// the constant gates are already assigned.
+    pub fn new(circuit: &PlonkCircuit) -> Self {
+        let mut trace = ComputationTrace {
+            a: vec![Scalar::zero(); circuit.nr_constraints],
+            b: vec![Scalar::zero(); circuit.nr_constraints],
+            c: vec![Scalar::zero(); circuit.nr_constraints],
+        };
+        for (row, value) in circuit.constants.iter() {
+            trace.set(Wire::left(*row), *value);
+        }
+
+        trace
+    }
+
+    /// Assigns `value` to `wire`.
+    pub fn set(&mut self, wire: Wire, value: Scalar) {
+        let column = match wire.column {
+            0 => &mut self.a,
+            1 => &mut self.b,
+            _ => &mut self.c,
+        };
+        column[wire.row] = value;
+    }
+
+    /// Value of `wire`, where rows beyond the end of the trace are zero, as when it is padded.
+    pub fn value(&self, wire: Wire) -> Scalar {
+        [&self.a, &self.b, &self.c][wire.column]
+            .get(wire.row)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Values of the wires (a, b, c) of `row`.
+    pub(crate) fn row(&self, row: usize) -> (Scalar, Scalar, Scalar) {
+        (
+            self.value(Wire::left(row)),
+            self.value(Wire::right(row)),
+            self.value(Wire::output(row)),
+        )
+    }
+
+    /// Writes the trace as its three columns a, b and c, each as a u64 length in little endian
+    /// followed by the values in big endian.
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), PlonkError> {
+        for column in [&self.a, &self.b, &self.c] {
+            write_scalars(&mut writer, column)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a trace written with `serialize`, checking that the columns have the same length.
+    pub fn deserialize<R: Read>(mut reader: R) -> Result<Self, PlonkError> {
+        let a = read_scalars(&mut reader)?;
+        let b = read_scalars(&mut reader)?;
+        let c = read_scalars(&mut reader)?;
+        for column in [&b, &c] {
+            if column.len() != a.len() {
+                return Err(PlonkError::InvalidLength {
+                    expected: a.len(),
+                    got: column.len(),
+                });
+            }
+        }
+        Ok(ComputationTrace { a, b, c })
+    }
+
     pub(crate) fn pad_next_power_two(&self) -> Self {
-        let new_size = self.a.len().next_power_of_two();
+        self.pad(self.a.len().next_power_of_two())
+    }
+
+    // Pads the columns with zeros up to `new_size` rows, which is what the padding gates of
+    // `PlonkCircuit::pad_next_power_of_two` expect.
+    pub(crate) fn pad(&self, new_size: usize) -> Self {
         let mut result = ComputationTrace {
             a: vec![Scalar::zero(); new_size],
             b: vec![Scalar::zero(); new_size],
@@ -67,29 +167,261 @@ impl ComputationTrace {
     }
 }
 
+// The trace is the witness of the proof, so it can be wiped once it is not needed.
+impl Zeroize for ComputationTrace {
+    fn zeroize(&mut self) {
+        for column in [&mut self.a, &mut self.b, &mut self.c] {
+            zeroize_scalars(column);
+        }
+    }
+}
+
+/// Selectors of a gate, which enforces
+/// q_L * a + q_R * b + q_O * c + q_M * a * b + q_C + q_N * a' = 0 over the wires of its row and
+/// the left wire a' of the next row. Every selector starts at zero, so that a gate can be built
+/// by only setting the terms that it needs, e.g. `Gate::new().left(3).right(2).output(-1).constant(5)`
+/// for 3a + 2b - c + 5 = 0, or `Gate::new().left(1).right(1).next(-1)` for a' = a + b.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Gate {
+    pub ql: Scalar,
+    pub qr: Scalar,
+    pub qo: Scalar,
+    pub qm: Scalar,
+    pub qc: Scalar,
+    pub qn: Scalar,
+}
+
+impl Gate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn left(mut self, ql: impl Into<Coefficient>) -> Self {
+        self.ql = ql.into().0;
+        self
+    }
+
+    pub fn right(mut self, qr: impl Into<Coefficient>) -> Self {
+        self.qr = qr.into().0;
+        self
+    }
+
+    pub fn output(mut self, qo: impl Into<Coefficient>) -> Self {
+        self.qo = qo.into().0;
+        self
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn mul(mut self, qm: impl Into<Coefficient>) -> Self {
+        self.qm = qm.into().0;
+        self
+    }
+
+    pub fn constant(mut self, qc: impl Into<Coefficient>) -> Self {
+        self.qc = qc.into().0;
+        self
+    }
+
+    /// Coefficient of the left wire of the next row.
+    pub fn next(mut self, qn: impl Into<Coefficient>) -> Self {
+        self.qn = qn.into().0;
+        self
+    }
+}
+
+/// Selector value of a `Gate`, which can be given either as a scalar or as a small signed
+/// integer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Coefficient(Scalar);
+
+impl From<Scalar> for Coefficient {
+    fn from(value: Scalar) -> Self {
+        Coefficient(value)
+    }
+}
+
+impl From<i64> for Coefficient {
+    fn from(value: i64) -> Self {
+        let scalar = Scalar::from(value.unsigned_abs());
+        if value < 0 {
+            Coefficient(scalar.neg())
+        } else {
+            Coefficient(scalar)
+        }
+    }
+}
+
+/// Wires created by `PlonkCircuit::range_check`, used to fill its rows in the trace.
+#[derive(Clone, Debug)]
+pub struct RangeCheck {
+    bits: Vec<Wire>,         // left wire of the boolean gates, most significant bit first
+    accumulators: Vec<Wire>, // output wire of the running sum gates
+}
+
+impl RangeCheck {
+    /// Wires of the bits, from the most significant one, which is the reverse of the order of
+    /// `gadgets::bits::to_bits_le`.
+    pub fn bits(&self) -> &[Wire] {
+        &self.bits
+    }
+
+    /// Assigns the decomposition of `value` to the rows of the range check. If `value` does
+    /// not fit in the range, the decomposition is truncated, and the proof will fail.
+    pub fn assign(&self, trace: &mut ComputationTrace, value: &Scalar) {
+        let bytes = value.to_bytes_le();
+        let num_bits = self.bits.len();
+        let mut accumulator = Scalar::zero();
+        for (i, wire) in self.bits.iter().enumerate() {
+            let position = num_bits - 1 - i;
+            let bit = Scalar::from(((bytes[position / 8] >> (position % 8)) & 1) as u64);
+            trace.set(*wire, bit);
+            trace.set(Wire::right(wire.row), bit);
+
+            if i > 0 {
+                let sum_row = self.accumulators[i - 1].row;
+                trace.set(Wire::left(sum_row), accumulator);
+                trace.set(Wire::right(sum_row), bit);
+                accumulator = accumulator.double() + bit;
+                trace.set(Wire::output(sum_row), accumulator);
+            } else {
+                accumulator = bit;
+            }
+        }
+    }
+}
+
+/// First constraint of a circuit that a computation trace violates, with the values that
+/// violate it, as returned by `PlonkCircuit::is_satisfied`. Gates carry their label, if they
+/// were given one with `PlonkCircuit::add_gate_labeled` or `PlonkCircuit::label_gate`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UnsatisfiedGate {
+    /// Two connected wires have different values.
+    CopyConstraint {
+        wire: Wire,
+        connected: Wire,
+        values: (Scalar, Scalar),
+    },
+    /// The gate at `row` does not hold for the values (a, b, c) of its wires.
+    Gate {
+        row: usize,
+        label: Option<String>,
+        a: Scalar,
+        b: Scalar,
+        c: Scalar,
+    },
+    /// The wires (a, b, c) of the lookup gate at `row` are not a row of its table.
+    Lookup {
+        row: usize,
+        label: Option<String>,
+        a: Scalar,
+        b: Scalar,
+        c: Scalar,
+    },
+}
+
+impl fmt::Display for UnsatisfiedGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnsatisfiedGate::CopyConstraint {
+                wire,
+                connected,
+                values,
+            } => write!(
+                f,
+                "Wires {wire:?} and {connected:?} are connected but have values {:?} and {:?}",
+                values.0, values.1
+            ),
+            UnsatisfiedGate::Gate { row, label, a, b, c } => write!(
+                f,
+                "The gate{} at row {row} is not satisfied by a = {a:?}, b = {b:?}, c = {c:?}",
+                gate_name(label)
+            ),
+            UnsatisfiedGate::Lookup { row, label, a, b, c } => write!(
+                f,
+                "The lookup gate{} at row {row} has wires a = {a:?}, b = {b:?}, c = {c:?}, which are not in its table",
+                gate_name(label)
+            ),
+        }
+    }
+}
+
+// Label of a gate in error messages, after the word "gate".
+fn gate_name(label: &Option<String>) -> String {
+    label
+        .as_ref()
+        .map_or(String::new(), |label| format!(" \"{label}\""))
+}
+
+/// Size of a circuit and estimates of the cost of proving it, as returned by
+/// `PlonkCircuit::stats`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CircuitStats {
+    pub additions: usize,
+    pub multiplications: usize,
+    pub public_inputs: usize,
+    pub constants: usize,
+    pub lookups: usize,
+    pub custom: usize,
+    pub empty: usize, // gates without any selector
+    pub nr_wires: usize,
+    pub copy_constraints: usize,
+    pub lookup_rows: usize,
+    pub domain_size: usize,         // number of rows after padding
+    pub proof_size: usize,          // bytes
+    pub fft_multiplications: usize, // field multiplications of the FFTs of the prover
+    pub msm_points: usize, // points of the multi-scalar multiplications of the prover, at most
+}
+
 #[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Constraints {
     pub qm: Vec<Scalar>,
     pub ql: Vec<Scalar>,
     pub qr: Vec<Scalar>,
     pub qo: Vec<Scalar>,
     pub qc: Vec<Scalar>,
+    pub qk: Vec<Scalar>,
+    pub qt: Vec<Scalar>,
+    pub qn: Vec<Scalar>, // coefficient of the left wire of the next row
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct PlonkCircuit {
     pub extended_h_subgroup: Vec<Scalar>,
     pub constraints: Constraints,
-    pub permutations: HashMap<usize, usize>,
+    #[cfg_attr(feature = "serde", serde(with = "wire_map_serde"))]
+    pub permutations: HashMap<Wire, Wire>,
     pub nr_wires: usize,
     pub nr_constraints: usize,
+    pub public_inputs: Vec<usize>, // rows of the public input gates, in order
+    pub constants: Vec<(usize, Scalar)>, // rows of the constant gates, with their values
+    pub lookup_tables: Vec<LookupTable>, // the table with identifier i is at position i - 1
+    pub named_tables: HashMap<String, usize>, // identifiers of the tables used by gadgets
+    #[cfg_attr(feature = "serde", serde(with = "wire_map_serde"))]
+    pub labels: HashMap<Wire, String>, // names of wires, only used to report failures
+    pub gate_labels: HashMap<usize, String>, // names of gates by row, only used to report failures
 }
 
 pub struct PlonkConstraintSystem(ComputationTrace, PlonkCircuit);
 
-pub struct PreprocessedInput {
-    pub kzg_set: Kzg10<128>, // We could make this generic, but it's only going to complicate the code.
-    pub blinder_polynomial: Polynomial,
+/// Preprocessed input of the prover: the commitment scheme (KZG by default, with its SRS) and
+/// the selector and permutation polynomials.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P: Serialize, P::Commitment: Serialize, P::LagrangeSet: Serialize",
+        deserialize = "P: Deserialize<'de>, P::Commitment: Deserialize<'de>, \
+                       P::LagrangeSet: Deserialize<'de>"
+    ))
+)]
+pub struct ProverKey<P: PlonkCommitment = Kzg10> {
+    pub verifier_key: VerifierKey<P>,
+    pub scheme: P,
+    pub lagrange_set: P::LagrangeSet, // scheme in the lagrange basis of the domain, to commit to the wires
+    pub domain: EvaluationDomain,
+    pub blinder_polynomial: SparsePolynomial,
     pub constraints: PlonkCircuit,
     pub sigma_star: HashMap<usize, Scalar>,
     pub qm_x: Polynomial,
@@ -100,6 +432,275 @@ pub struct PreprocessedInput {
     pub qs1_x: Polynomial,
     pub qs2_x: Polynomial,
     pub qs3_x: Polynomial,
+    pub qk_x: Polynomial,
+    pub table1_x: Polynomial,
+    pub table2_x: Polynomial,
+    pub table3_x: Polynomial,
+    pub table4_x: Polynomial,
+    pub qt_x: Polynomial,
+    pub qn_x: Polynomial,
+    pub l0_x: Polynomial, // lagrange polynomial of the first row, used by every proof
+    pub lookup_table: Vec<[Scalar; 4]>, // tagged rows, padded to the size of the domain
+}
+
+impl VerifierKey {
+    /// Writes the verifier key in a binary format: the size of the domain and the rows of the
+    /// public inputs as u64 in little endian, followed by the compressed points of the SRS and
+    /// the commitments, in the order in which they are absorbed by the transcript.
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), PlonkError> {
+        write_u64(&mut writer, self.domain.size)?;
+        write_u64(&mut writer, self.public_inputs.len())?;
+        for row in self.public_inputs.iter() {
+            write_u64(&mut writer, *row)?;
+        }
+        writer.write_all(&self.scheme.verifier_bytes())?;
+        for commitment in self.commitments() {
+            writer.write_all(&commitment.to_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a verifier key written with `serialize`, checking that all points are in the
+    /// prime order subgroup and that all public inputs are rows of the domain.
+    pub fn deserialize<R: Read>(mut reader: R) -> Result<Self, PlonkError> {
+        let domain = EvaluationDomain::new(read_u64(&mut reader)?)?;
+        let public_inputs = (0..read_u64(&mut reader)?)
+            .map(|_| match read_u64(&mut reader)? {
+                row if row < domain.size => Ok(row),
+                row => Err(PlonkError::WireOutOfBounds {
+                    wire: row,
+                    nr_wires: domain.size,
+                }),
+            })
+            .collect::<Result<Vec<_>, PlonkError>>()?;
+
+        let mut g1 = [0u8; 48];
+        reader.read_exact(&mut g1)?;
+        let g1 = Option::from(G1Affine::from_compressed(&g1)).ok_or(PlonkError::InvalidPoint)?;
+        let mut g2 = || -> Result<G2Affine, PlonkError> {
+            let mut bytes = [0u8; 96];
+            reader.read_exact(&mut bytes)?;
+            Option::from(G2Affine::from_compressed(&bytes)).ok_or(PlonkError::InvalidPoint)
+        };
+        let powers_x_g2 = [g2()?, g2()?];
+        let mut commitment = || -> Result<Kzg10Commitment, PlonkError> {
+            let mut bytes = [0u8; 48];
+            reader.read_exact(&mut bytes)?;
+            Kzg10Commitment::from_bytes(&bytes)
+        };
+
+        Ok(VerifierKey {
+            domain,
+            public_inputs,
+            scheme: Kzg10 {
+                powers_x_g1: vec![g1],
+                powers_x_g2,
+            },
+            qm_comm: commitment()?,
+            ql_comm: commitment()?,
+            qr_comm: commitment()?,
+            qo_comm: commitment()?,
+            qc_comm: commitment()?,
+            qs1_comm: commitment()?,
+            qs2_comm: commitment()?,
+            qs3_comm: commitment()?,
+            qk_comm: commitment()?,
+            table1_comm: commitment()?,
+            table2_comm: commitment()?,
+            table3_comm: commitment()?,
+            table4_comm: commitment()?,
+            qt_comm: commitment()?,
+            qn_comm: commitment()?,
+        })
+    }
+}
+
+// First bytes of a file written by `ProverKey::serialize`, and version of its format.
+const PROVER_KEY_MAGIC: &[u8; 8] = b"PLONKPK\0";
+const PROVER_KEY_VERSION: usize = 1;
+
+impl ProverKey {
+    // Polynomials in the order of the encoding.
+    fn polynomials(&self) -> [&Polynomial; 16] {
+        [
+            &self.qm_x,
+            &self.ql_x,
+            &self.qr_x,
+            &self.qo_x,
+            &self.qc_x,
+            &self.qs1_x,
+            &self.qs2_x,
+            &self.qs3_x,
+            &self.qk_x,
+            &self.table1_x,
+            &self.table2_x,
+            &self.table3_x,
+            &self.table4_x,
+            &self.qt_x,
+            &self.qn_x,
+            &self.l0_x,
+        ]
+    }
+
+    /// Writes the prover key, so that it can be loaded with `deserialize` instead of running
+    /// the setup again. The file starts with a magic string and the version of the format,
+    /// followed by the circuit (as with `PlonkCircuit::serialize`), the verifier key (as with
+    /// `VerifierKey::serialize`), the compressed powers of the SRS in G1, sigma* in the order of
+    /// the extended witness, the selector, permutation, table and first lagrange polynomials,
+    /// and the rows of the lookup table. It ends with the Blake2b-256 hash of everything
+    /// before it. The SRS in the lagrange basis is recomputed when loading the key.
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), PlonkError> {
+        let mut bytes = PROVER_KEY_MAGIC.to_vec();
+        write_u64(&mut bytes, PROVER_KEY_VERSION)?;
+        self.constraints.serialize(&mut bytes)?;
+        self.verifier_key.serialize(&mut bytes)?;
+        write_u64(&mut bytes, self.scheme.powers_x_g1.len())?;
+        for point in self.scheme.powers_x_g1.iter() {
+            bytes.extend_from_slice(&point.to_compressed());
+        }
+        let sigma_star = (0..self.sigma_star.len())
+            .map(|index| self.sigma_star[&index])
+            .collect::<Vec<_>>();
+        write_scalars(&mut bytes, &sigma_star)?;
+        for polynomial in self.polynomials() {
+            write_scalars(&mut bytes, &polynomial.0)?;
+        }
+        write_scalars(&mut bytes, &self.lookup_table.concat())?;
+
+        let hash: [u8; 32] = Blake2b::<U32>::digest(&bytes).into();
+        writer.write_all(&bytes)?;
+        Ok(writer.write_all(&hash)?)
+    }
+
+    /// Reads a prover key written with `serialize`. Besides the checks of the circuit and the
+    /// verifier key, the hash must match the contents, the version must be known, and the
+    /// keys must agree with each other on the domain and the SRS.
+    pub fn deserialize<R: Read>(mut reader: R) -> Result<Self, PlonkError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let invalid = |message: &str| PlonkError::InvalidFormat(message.to_string());
+        if bytes.len() < PROVER_KEY_MAGIC.len() + 8 + 32 || !bytes.starts_with(PROVER_KEY_MAGIC) {
+            return Err(invalid("Not a prover key"));
+        }
+        let (contents, hash) = bytes.split_at(bytes.len() - 32);
+        if Blake2b::<U32>::digest(contents).as_slice() != hash {
+            return Err(invalid(
+                "The hash of the prover key does not match its contents",
+            ));
+        }
+        let mut reader = &contents[PROVER_KEY_MAGIC.len()..];
+        let version = read_u64(&mut reader)?;
+        if version != PROVER_KEY_VERSION {
+            return Err(PlonkError::InvalidFormat(format!(
+                "Unsupported prover key version {version}"
+            )));
+        }
+
+        let constraints = PlonkCircuit::deserialize(&mut reader)?;
+        let verifier_key = VerifierKey::deserialize(&mut reader)?;
+        let n = constraints.nr_constraints;
+        if verifier_key.domain.size != n || constraints.extended_h_subgroup.len() != WIDTH * n {
+            return Err(invalid("The circuit was not set up"));
+        }
+        let powers_x_g1 = (0..read_u64(&mut reader)?)
+            .map(|_| {
+                let mut point = [0u8; 48];
+                reader.read_exact(&mut point)?;
+                Option::from(G1Affine::from_compressed(&point)).ok_or(PlonkError::InvalidPoint)
+            })
+            .collect::<Result<Vec<_>, PlonkError>>()?;
+        if powers_x_g1.first() != verifier_key.scheme.powers_x_g1.first()
+            || powers_x_g1.len() < n + 7
+        {
+            return Err(invalid("The SRS does not match the verifier key"));
+        }
+        let scheme = Kzg10 {
+            powers_x_g1,
+            powers_x_g2: verifier_key.scheme.powers_x_g2,
+        };
+
+        let sigma_star = read_scalars(&mut reader)?;
+        if sigma_star.len() != WIDTH * n {
+            return Err(PlonkError::InvalidLength {
+                expected: WIDTH * n,
+                got: sigma_star.len(),
+            });
+        }
+        let mut polynomial = || -> Result<Polynomial, PlonkError> {
+            let coefficients = read_scalars(&mut reader)?;
+            if coefficients.len() > n {
+                return Err(PlonkError::InvalidLength {
+                    expected: n,
+                    got: coefficients.len(),
+                });
+            }
+            Ok(Polynomial(coefficients))
+        };
+        let [qm_x, ql_x, qr_x, qo_x, qc_x, qs1_x, qs2_x, qs3_x, qk_x, table1_x, table2_x, table3_x, table4_x, qt_x, qn_x, l0_x] =
+            [(); 16].map(|_| polynomial());
+        let lookup_table = read_scalars(&mut reader)?;
+        if lookup_table.len() != 4 * n {
+            return Err(PlonkError::InvalidLength {
+                expected: 4 * n,
+                got: lookup_table.len(),
+            });
+        }
+        if !reader.is_empty() {
+            return Err(invalid("Unexpected data after the prover key"));
+        }
+
+        let domain = EvaluationDomain::new(n)?;
+        Ok(ProverKey {
+            lagrange_set: scheme.lagrange_basis(&domain)?,
+            domain,
+            blinder_polynomial: SparsePolynomial::vanishing(n),
+            sigma_star: sigma_star.into_iter().enumerate().collect(),
+            qm_x: qm_x?,
+            ql_x: ql_x?,
+            qr_x: qr_x?,
+            qo_x: qo_x?,
+            qc_x: qc_x?,
+            qs1_x: qs1_x?,
+            qs2_x: qs2_x?,
+            qs3_x: qs3_x?,
+            qk_x: qk_x?,
+            table1_x: table1_x?,
+            table2_x: table2_x?,
+            table3_x: table3_x?,
+            table4_x: table4_x?,
+            qt_x: qt_x?,
+            qn_x: qn_x?,
+            l0_x: l0_x?,
+            lookup_table: lookup_table
+                .chunks_exact(4)
+                .map(|row| [row[0], row[1], row[2], row[3]])
+                .collect(),
+            verifier_key,
+            scheme,
+            constraints,
+        })
+    }
+}
+
+impl<P: PlonkCommitment> ProverKey<P> {
+    /// Computes PI(X) = - sum_i x_i * L_i(X) for the public values `x_i`.
+    pub fn public_input_polynomial(&self, public_in: &[Scalar]) -> Result<Polynomial, PlonkError> {
+        if public_in.len() != self.constraints.public_inputs.len() {
+            return Err(PlonkError::PublicInputLength {
+                expected: self.constraints.public_inputs.len(),
+                got: public_in.len(),
+            });
+        }
+        // PI is interpolated at once from its values over H, instead of adding up one lagrange
+        // polynomial per public input.
+        let mut evals = vec![Scalar::zero(); self.domain.size];
+        for (row, input) in self.constraints.public_inputs.iter().zip(public_in.iter()) {
+            evals[*row] -= input;
+        }
+
+        Ok(self.domain.ifft(&evals))
+    }
 }
 
 impl PlonkCircuit {
@@ -110,169 +711,1253 @@ impl PlonkCircuit {
             nr_wires: 0,
             nr_constraints: 0,
             extended_h_subgroup: Default::default(),
+            public_inputs: Default::default(),
+            constants: Default::default(),
+            lookup_tables: Default::default(),
+            named_tables: Default::default(),
+            labels: Default::default(),
+            gate_labels: Default::default(),
         }
     }
-    pub fn add_gate(&mut self) {
-        self.constraints.ql.push(Scalar::one());
-        self.constraints.qr.push(Scalar::one());
-        self.constraints.qo.push(Scalar::one().neg());
-        self.constraints.qm.push(Scalar::zero());
-        self.constraints.qc.push(Scalar::zero());
+    pub fn add_gate(&mut self) -> (Wire, Wire, Wire) {
+        self.gate(Gate::new().left(1).right(1).output(-1))
+    }
 
-        // we extend the permutation with the identity permutation
-        self.permutations.insert(self.nr_wires, self.nr_wires);
-        self.permutations
-            .insert(self.nr_wires + 1, self.nr_wires + 1);
-        self.permutations
-            .insert(self.nr_wires + 2, self.nr_wires + 2);
+    /// Adds the gates that enforce `expr` = 0, e.g. `Expr::var(a) * b - c` for a * b = c,
+    /// connected to the wires of the expression. Products and linear terms are folded into as
+    /// few gates as possible (see `expr.rs`).
+    pub fn enforce(&mut self, expr: &Expr) -> Result<(), PlonkError> {
+        Compiler::new(self).enforce(expr)
+    }
 
-        self.nr_wires += 3;
-        self.nr_constraints += 1;
+    /// Adds the gates that compute `expr`, and returns the wire that carries its value.
+    pub fn compile(&mut self, expr: &Expr) -> Result<Wire, PlonkError> {
+        Compiler::new(self).compile(expr)
     }
 
-    pub fn mult_gate(&mut self) {
-        self.constraints.qm.push(Scalar::one());
-        self.constraints.qo.push(Scalar::one().neg());
-        self.constraints.ql.push(Scalar::zero());
-        self.constraints.qr.push(Scalar::zero());
-        self.constraints.qc.push(Scalar::zero());
+    /// Same as `add_gate`, naming the gate as with `label_gate`.
+    pub fn add_gate_labeled(&mut self, label: impl Into<String>) -> (Wire, Wire, Wire) {
+        let wires = self.add_gate();
+        self.label_gate(wires.0.row, label);
+        wires
+    }
 
-        // we extend the permutation with the identity permutation
-        self.permutations.insert(self.nr_wires, self.nr_wires);
-        self.permutations
-            .insert(self.nr_wires + 1, self.nr_wires + 1);
-        self.permutations
-            .insert(self.nr_wires + 2, self.nr_wires + 2);
+    pub fn mult_gate(&mut self) -> (Wire, Wire, Wire) {
+        self.gate(Gate::new().mul(1).output(-1))
+    }
 
-        self.nr_wires += 3;
-        self.nr_constraints += 1;
+    /// Creates a gate with arbitrary selectors, enforcing
+    /// ql * a + qr * b + qo * c + qm * a * b + qc = 0.
+    pub fn custom_gate(
+        &mut self,
+        ql: Scalar,
+        qr: Scalar,
+        qo: Scalar,
+        qm: Scalar,
+        qc: Scalar,
+    ) -> (Wire, Wire, Wire) {
+        self.gate(Gate {
+            ql,
+            qr,
+            qo,
+            qm,
+            qc,
+            qn: Scalar::zero(),
+        })
+    }
+
+    /// Creates a gate with the selectors of `gate`.
+    pub fn gate(&mut self, gate: Gate) -> (Wire, Wire, Wire) {
+        self.constraints.ql.push(gate.ql);
+        self.constraints.qr.push(gate.qr);
+        self.constraints.qo.push(gate.qo);
+        self.constraints.qm.push(gate.qm);
+        self.constraints.qc.push(gate.qc);
+        self.constraints.qk.push(Scalar::zero());
+        self.constraints.qt.push(Scalar::zero());
+        self.constraints.qn.push(gate.qn);
+
+        self.new_row()
+    }
+
+    /// Appends the rows of `other` after those of the circuit, with their copy constraints,
+    /// constants, public inputs and labels, and returns the row at which they start. Lookup
+    /// tables of `other` are only added if the circuit does not have them yet, so that a
+    /// circuit appended several times shares its tables.
+    pub fn append(&mut self, other: &PlonkCircuit) -> usize {
+        let offset = self.nr_constraints;
+        let shift = |wire: &Wire| Wire {
+            row: wire.row + offset,
+            column: wire.column,
+        };
+
+        let table_ids = other
+            .lookup_tables
+            .iter()
+            .map(|table| {
+                match self
+                    .lookup_tables
+                    .iter()
+                    .position(|existing| existing == table)
+                {
+                    Some(index) => index + 1,
+                    None => self.add_lookup_table(table.clone()),
+                }
+            })
+            .collect::<Vec<_>>();
+        for (name, id) in other.named_tables.iter() {
+            self.named_tables
+                .entry(name.clone())
+                .or_insert(table_ids[id - 1]);
+        }
+
+        let q = &other.constraints;
+        for row in 0..other.nr_constraints {
+            self.constraints.ql.push(q.ql[row]);
+            self.constraints.qr.push(q.qr[row]);
+            self.constraints.qo.push(q.qo[row]);
+            self.constraints.qm.push(q.qm[row]);
+            self.constraints.qc.push(q.qc[row]);
+            self.constraints.qk.push(q.qk[row]);
+            let table = (1..=table_ids.len()).find(|id| Scalar::from(*id as u64) == q.qt[row]);
+            self.constraints.qt.push(match table {
+                Some(id) => Scalar::from(table_ids[id - 1] as u64),
+                None => q.qt[row],
+            });
+            self.constraints.qn.push(q.qn[row]);
+        }
+        self.permutations.extend(
+            other
+                .permutations
+                .iter()
+                .map(|(wire, next)| (shift(wire), shift(next))),
+        );
+        self.nr_wires += other.nr_wires;
+        self.nr_constraints += other.nr_constraints;
+
+        self.public_inputs
+            .extend(other.public_inputs.iter().map(|row| row + offset));
+        self.constants.extend(
+            other
+                .constants
+                .iter()
+                .map(|(row, value)| (row + offset, *value)),
+        );
+        self.labels.extend(
+            other
+                .labels
+                .iter()
+                .map(|(wire, label)| (shift(wire), label.clone())),
+        );
+        self.gate_labels.extend(
+            other
+                .gate_labels
+                .iter()
+                .map(|(row, label)| (row + offset, label.clone())),
+        );
+
+        offset
+    }
+
+    /// Folds constant wires into the selectors of the gates that use them, and removes the
+    /// gates that do not constrain anything, along with their wires (see `optimizer.rs`). The
+    /// wires in `keep`, such as the inputs assigned by the prover and the outputs read from
+    /// the trace, are left as they are. Rows are renumbered, and the returned `Optimization`
+    /// maps the wires of the original circuit to the new ones. The setup must run again.
+    pub fn optimize(&mut self, keep: &[Wire]) -> Result<Optimization, PlonkError> {
+        optimizer::optimize(self, keep)
+    }
+
+    /// Merges gates into the only gate that uses their output, when the merged equation still
+    /// fits in a row (see `optimizer.rs`), e.g. an addition feeding a multiplication. Wires are
+    /// kept and mapped as with `optimize`.
+    pub fn pack(&mut self, keep: &[Wire]) -> Result<Optimization, PlonkError> {
+        optimizer::pack(self, keep)
+    }
+
+    /// Adds a lookup table to the circuit, and returns its identifier.
+    pub fn add_lookup_table(&mut self, table: LookupTable) -> usize {
+        self.lookup_tables.push(table);
+        self.lookup_tables.len()
+    }
+
+    /// Creates a lookup gate, which requires its wires (a, b, c) to be a row of the lookup
+    /// table with identifier `table_id`.
+    pub fn lookup_gate(&mut self, table_id: usize) -> Result<(Wire, Wire, Wire), PlonkError> {
+        if table_id == 0 || table_id > self.lookup_tables.len() {
+            return Err(PlonkError::UnknownLookupTable(table_id));
+        }
+        let wires = self.gate(Gate::new());
+        *self.constraints.qk.last_mut().unwrap() = Scalar::one();
+        *self.constraints.qt.last_mut().unwrap() = Scalar::from(table_id as u64);
+        Ok(wires)
+    }
+
+    /// Requires `wires` (at most three) to be a row of the table with identifier `table_id`,
+    /// by connecting them to the wires of a new lookup gate, which are returned. Wires of the
+    /// gate that are not connected must be assigned zero, to match the padding of the table.
+    pub fn lookup(
+        &mut self,
+        table_id: usize,
+        wires: &[Wire],
+    ) -> Result<(Wire, Wire, Wire), PlonkError> {
+        if wires.len() > 3 {
+            return Err(PlonkError::InvalidLength {
+                expected: 3,
+                got: wires.len(),
+            });
+        }
+
+        let gate = self.lookup_gate(table_id)?;
+        for (wire, gate_wire) in wires.iter().zip([gate.0, gate.1, gate.2]) {
+            self.connect(*wire, gate_wire)?;
+        }
+        Ok(gate)
+    }
+
+    /// XOR of two bytes, with a lookup in the 8-bit XOR table. The lookup also constrains
+    /// both inputs to be bytes.
+    pub fn xor_u8(&mut self, left: Wire, right: Wire) -> Result<Wire, PlonkError> {
+        self.bitwise("xor", LookupTable::xor, 8, left, right)
+    }
+
+    /// AND of two bytes, with a lookup in the 8-bit AND table. The lookup also constrains
+    /// both inputs to be bytes.
+    pub fn and_u8(&mut self, left: Wire, right: Wire) -> Result<Wire, PlonkError> {
+        self.bitwise("and", LookupTable::and, 8, left, right)
+    }
+
+    /// XOR of two `bits`-bit values.
+    pub fn xor(&mut self, left: Wire, right: Wire, bits: u32) -> Result<Wire, PlonkError> {
+        self.bitwise("xor", LookupTable::xor, bits, left, right)
+    }
+
+    /// AND of two `bits`-bit values.
+    pub fn and(&mut self, left: Wire, right: Wire, bits: u32) -> Result<Wire, PlonkError> {
+        self.bitwise("and", LookupTable::and, bits, left, right)
+    }
+
+    // The table of each operation and width is only added once to the circuit.
+    fn bitwise(
+        &mut self,
+        operation: &str,
+        table: fn(u32) -> LookupTable,
+        bits: u32,
+        left: Wire,
+        right: Wire,
+    ) -> Result<Wire, PlonkError> {
+        let name = format!("{operation}/{bits}");
+        let table_id = match self.named_tables.get(&name) {
+            Some(id) => *id,
+            None => {
+                let id = self.add_lookup_table(table(bits));
+                self.named_tables.insert(name, id);
+                id
+            }
+        };
+
+        let (_, _, output) = self.lookup(table_id, &[left, right])?;
+        Ok(output)
+    }
+
+    /// Creates a public input gate, and returns the wire that carries the public value.
+    /// Public values are given to the prover and verifier in the order in which they were
+    /// created.
+    pub fn public_input(&mut self) -> Wire {
+        self.public_inputs.push(self.nr_constraints);
+        let (input, _, _) = self.gate(Gate::new().left(1));
+        input
     }
 
-    pub fn prepare_pi(&mut self) {
-        self.constraints.ql.push(Scalar::one());
-        self.constraints.qr.push(Scalar::zero());
-        self.constraints.qo.push(Scalar::zero());
-        self.constraints.qm.push(Scalar::zero());
-        self.constraints.qc.push(Scalar::zero());
+    /// Pins `wire` to `value`, with a gate q_L = 1, q_C = -value whose left wire is connected
+    /// to `wire`. The left wire of the gate is filled by `ComputationTrace::new`.
+    pub fn assert_constant(&mut self, wire: Wire, value: Scalar) -> Result<(), PlonkError> {
+        self.constants.push((self.nr_constraints, value));
+        let (constant, _, _) = self.gate(Gate::new().left(1).constant(value.neg()));
+        self.connect(wire, constant)
+    }
+
+    /// Constrains two wires to carry the same value, with a copy constraint (see `connect`),
+    /// which costs no gate.
+    pub fn assert_equal(&mut self, left: Wire, right: Wire) -> Result<(), PlonkError> {
+        self.connect(left, right)
+    }
+
+    /// Constrains `wire` to carry `value`, with a constant gate (see `assert_constant`).
+    pub fn assert_equal_const(&mut self, wire: Wire, value: Scalar) -> Result<(), PlonkError> {
+        self.assert_constant(wire, value)
+    }
+
+    /// Constrains `wire` to be smaller than 2^num_bits. The value is decomposed into bits, each
+    /// bit is constrained to be boolean with a gate bit * bit - bit = 0, and the bits are
+    /// recomposed from the most significant one with a running sum acc' = 2 * acc + bit, whose
+    /// last value is connected to `wire`. This uses 2 * num_bits - 1 gates.
+    ///
+    /// The bits of the returned `RangeCheck` are in the order of the running sum, from the most
+    /// significant one. The gadgets of `gadgets::bits` and `gadgets::uint` decompose with a
+    /// linear combination instead, and their bits are little-endian, from the least significant
+    /// one.
+    pub fn range_check(&mut self, wire: Wire, num_bits: usize) -> Result<RangeCheck, PlonkError> {
+        // With 255 bits the recomposition could wrap around the modulus.
+        let max = Scalar::NUM_BITS as usize - 1;
+        if num_bits > max {
+            return Err(PlonkError::RangeTooLarge { num_bits, max });
+        }
+        if num_bits == 0 {
+            self.assert_constant(wire, Scalar::zero())?;
+            return Ok(RangeCheck {
+                bits: vec![],
+                accumulators: vec![],
+            });
+        }
+
+        let mut bits = Vec::with_capacity(num_bits);
+        for _ in 0..num_bits {
+            let (bit_l, bit_r, _) = self.gate(Gate::new().mul(1).left(-1));
+            self.connect(bit_l, bit_r)?;
+            bits.push(bit_l);
+        }
+
+        let mut accumulators = Vec::with_capacity(num_bits - 1);
+        let mut accumulator = bits[0];
+        for bit in bits.iter().skip(1) {
+            let (acc_l, acc_r, acc_o) = self.gate(Gate::new().left(2).right(1).output(-1));
+            self.connect(accumulator, acc_l)?;
+            self.connect(*bit, acc_r)?;
+            accumulators.push(acc_o);
+            accumulator = acc_o;
+        }
+        self.connect(accumulator, wire)?;
+
+        Ok(RangeCheck { bits, accumulators })
+    }
+
+    // Registers the wires of the row whose selectors were just pushed.
+    fn new_row(&mut self) -> (Wire, Wire, Wire) {
+        let row = self.nr_constraints;
+        let wires = (Wire::left(row), Wire::right(row), Wire::output(row));
 
         // we extend the permutation with the identity permutation
-        self.permutations.insert(self.nr_wires, self.nr_wires);
-        self.permutations
-            .insert(self.nr_wires + 1, self.nr_wires + 1);
-        self.permutations
-            .insert(self.nr_wires + 2, self.nr_wires + 2);
+        for column in 0..WIDTH {
+            let wire = Wire::new(row, column);
+            self.permutations.insert(wire, wire);
+        }
 
-        self.nr_wires += 3;
+        self.nr_wires += WIDTH;
         self.nr_constraints += 1;
+
+        wires
+    }
+
+    // Total number of rows of the lookup tables.
+    fn lookup_rows(&self) -> usize {
+        self.lookup_tables.iter().map(LookupTable::len).sum()
     }
 
     // Pad to the next power of two
     pub fn pad_next_power_of_two(&mut self) {
-        // we first pad the number of constraints to the next power of two. we do so by adding zero constraints
-        while (self.nr_constraints & (self.nr_constraints - 1)) != 0 {
+        // we first pad the number of constraints to the next power of two. we do so by adding zero constraints.
+        // The lookup table needs to fit in the domain as well.
+        while !self.nr_constraints.is_power_of_two() || self.nr_constraints < self.lookup_rows() {
             self.add_gate();
         }
     }
 
-    // This should always be called after creating the gates.
-    pub fn connect_wires(&mut self, in_wire: &usize, out_wire: &usize) {
-        assert!(*in_wire < self.nr_wires && *out_wire < self.nr_wires, "The circuit does not have enough wires for these two. Max {0}, got {in_wire} and {out_wire}", self.nr_wires);
-        let in_rel = self.permutations.get(in_wire).unwrap().clone(); // we know each key is populated
-        let out_rel = self.permutations.get(out_wire).unwrap().clone(); // we know each key is populated
-        self.permutations.insert(*in_wire, out_rel);
-        self.permutations.insert(*out_wire, in_rel);
+    /// Counts the gates of the circuit by type, and estimates the cost of proving it once it
+    /// is padded, without running the setup.
+    pub fn stats(&self) -> CircuitStats {
+        let q = &self.constraints;
+        let public_inputs = self.public_inputs.iter().copied().collect::<HashSet<_>>();
+        let constants = self
+            .constants
+            .iter()
+            .map(|(row, _)| *row)
+            .collect::<HashSet<_>>();
+        let (one, minus_one) = (Scalar::one(), Scalar::one().neg());
+        let mut stats = CircuitStats::default();
+        for row in 0..self.nr_constraints {
+            let gate = Gate {
+                ql: q.ql[row],
+                qr: q.qr[row],
+                qo: q.qo[row],
+                qm: q.qm[row],
+                qc: q.qc[row],
+                qn: q.qn[row],
+            };
+            let count = if q.qk[row] == one {
+                &mut stats.lookups
+            } else if public_inputs.contains(&row) {
+                &mut stats.public_inputs
+            } else if constants.contains(&row) {
+                &mut stats.constants
+            } else if gate == Gate::new().left(one).right(one).output(minus_one) {
+                &mut stats.additions
+            } else if gate == Gate::new().mul(one).output(minus_one) {
+                &mut stats.multiplications
+            } else if gate == Gate::new() {
+                &mut stats.empty
+            } else {
+                &mut stats.custom
+            };
+            *count += 1;
+        }
+
+        // A cycle of k connected wires amounts to k - 1 copy constraints.
+        let mut visited = HashSet::new();
+        for wire in self.permutations.keys() {
+            let mut current = *wire;
+            while visited.insert(current) {
+                current = self.permutations[&current];
+                if current != *wire {
+                    stats.copy_constraints += 1;
+                }
+            }
+        }
+
+        stats.nr_wires = self.nr_wires;
+        stats.lookup_rows = self.lookup_rows();
+        let n = self
+            .nr_constraints
+            .max(stats.lookup_rows)
+            .max(1)
+            .next_power_of_two();
+        stats.domain_size = n;
+        stats.proof_size = PlonkProof::SIZE;
+
+        // The prover interpolates 8 polynomials over the domain, and computes the quotient with
+        // 27 FFTs and an inverse FFT over a coset of a domain with at least 4n + 7 elements.
+        // Each of the 13 points of the proof is a commitment to a polynomial of degree < n + 7.
+        let fft = |size: usize| size / 2 * size.trailing_zeros() as usize;
+        stats.fft_multiplications = 8 * fft(n) + 28 * fft((4 * n + 7).next_power_of_two());
+        stats.msm_points = 13 * (n + 7);
+
+        stats
+    }
+
+    /// Renders the wiring of the circuit in the DOT format of Graphviz, with a node per gate
+    /// showing its label and its selectors, and an edge per copy constraint between the ports (a, b, c) of
+    /// the gates, labelled with the names of the wires if they have one.
+    pub fn to_dot(&self) -> String {
+        let q = &self.constraints;
+        let selector = |value: &Scalar| {
+            if *value == Scalar::one().neg() {
+                "-1".to_string()
+            } else if let Some(small) = (0..16u64).find(|i| Scalar::from(*i) == *value) {
+                small.to_string()
+            } else {
+                format!("{value:?}")
+            }
+        };
+        let port = |wire: &Wire| format!("g{}:{}", wire.row, ["a", "b", "c"][wire.column]);
+        // Labels are escaped, as they are part of the record of the gate.
+        let escape = |label: Option<&String>| {
+            let label = label.map(String::as_str).unwrap_or("");
+            label.chars().fold(String::new(), |mut name, char| {
+                if "{}|<>\"\\".contains(char) {
+                    name.push('\\');
+                }
+                name.push(char);
+                name
+            })
+        };
+        let name = |wire: &Wire| escape(self.labels.get(wire));
+
+        let mut dot = String::from("digraph circuit {\n    node [shape=record];\n");
+        for row in 0..self.nr_constraints {
+            let kind = if q.qk[row] == Scalar::one() {
+                format!("lookup {}", selector(&q.qt[row]))
+            } else if self.public_inputs.contains(&row) {
+                "public input".to_string()
+            } else {
+                let next = if q.qn[row] == Scalar::zero() {
+                    String::new()
+                } else {
+                    format!(" qN={}", selector(&q.qn[row]))
+                };
+                format!(
+                    "qL={} qR={} qO={} qM={} qC={}{next}",
+                    selector(&q.ql[row]),
+                    selector(&q.qr[row]),
+                    selector(&q.qo[row]),
+                    selector(&q.qm[row]),
+                    selector(&q.qc[row])
+                )
+            };
+            let kind = match self.gate_labels.get(&row) {
+                Some(label) => format!("{} ({kind})", escape(Some(label))),
+                None => kind,
+            };
+            dot.push_str(&format!(
+                "    g{row} [label=\"{{{row}: {kind}|{{<a> a {}|<b> b {}|<c> c {}}}}}\"];\n",
+                name(&Wire::left(row)),
+                name(&Wire::right(row)),
+                name(&Wire::output(row))
+            ));
+        }
+
+        // Each cycle of the permutation is drawn as a chain of edges, in order of the wires.
+        let mut visited = HashSet::new();
+        let mut wires = self.permutations.keys().collect::<Vec<_>>();
+        wires.sort();
+        for wire in wires {
+            let mut cycle = Vec::new();
+            let mut current = *wire;
+            while visited.insert(current) {
+                cycle.push(current);
+                current = self.permutations[&current];
+            }
+            cycle.sort();
+            for pair in cycle.windows(2) {
+                dot.push_str(&format!(
+                    "    {} -> {} [dir=none];\n",
+                    port(&pair[0]),
+                    port(&pair[1])
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
     }
 
-    pub fn lagrange_basis(&self, index: usize) -> Polynomial {
-        let mut lb = Polynomial(vec![Scalar::from(1)]);
-        for j in 0..self.nr_constraints {
-            if index == j {
-                continue;
+    /// Writes the circuit in a binary format, so that it can be stored and loaded with
+    /// `deserialize` instead of being built again. Integers are encoded as u64 in little endian
+    /// and scalars in big endian, and the permutation as the image of every wire in the order of
+    /// the extended witness.
+    pub fn serialize<W: Write>(&self, mut writer: W) -> Result<(), PlonkError> {
+        write_u64(&mut writer, self.nr_constraints)?;
+        let q = &self.constraints;
+        for selector in [&q.qm, &q.ql, &q.qr, &q.qo, &q.qc, &q.qk, &q.qt, &q.qn] {
+            for value in selector.iter() {
+                writer.write_all(&value.to_bytes_be())?;
             }
+        }
+        for index in 0..self.nr_wires {
+            let wire = Wire::from_index(index, self.nr_constraints);
+            write_u64(
+                &mut writer,
+                self.permutations[&wire].index(self.nr_constraints),
+            )?;
+        }
+
+        write_scalars(&mut writer, &self.extended_h_subgroup)?;
+        write_u64(&mut writer, self.public_inputs.len())?;
+        for row in self.public_inputs.iter() {
+            write_u64(&mut writer, *row)?;
+        }
+        write_u64(&mut writer, self.constants.len())?;
+        for (row, value) in self.constants.iter() {
+            write_u64(&mut writer, *row)?;
+            writer.write_all(&value.to_bytes_be())?;
+        }
+        write_u64(&mut writer, self.lookup_tables.len())?;
+        for table in self.lookup_tables.iter() {
+            write_scalars(&mut writer, &table.0.concat())?;
+        }
 
-            lb *= &Polynomial(vec![self.extended_h_subgroup[j].neg(), Scalar::one()])
-                * (self.extended_h_subgroup[index] - self.extended_h_subgroup[j])
-                    .invert()
-                    .unwrap();
+        let mut named_tables = self.named_tables.iter().collect::<Vec<_>>();
+        named_tables.sort();
+        write_u64(&mut writer, named_tables.len())?;
+        for (name, id) in named_tables {
+            write_string(&mut writer, name)?;
+            write_u64(&mut writer, *id)?;
         }
-        lb
+        let mut labels = self.labels.iter().collect::<Vec<_>>();
+        labels.sort();
+        write_u64(&mut writer, labels.len())?;
+        for (wire, label) in labels {
+            write_u64(&mut writer, wire.index(self.nr_constraints))?;
+            write_string(&mut writer, label)?;
+        }
+        let mut gate_labels = self.gate_labels.iter().collect::<Vec<_>>();
+        gate_labels.sort();
+        write_u64(&mut writer, gate_labels.len())?;
+        for (row, label) in gate_labels {
+            write_u64(&mut writer, *row)?;
+            write_string(&mut writer, label)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a circuit written with `serialize`, checking that all scalars are canonical and
+    /// that all wires and rows are part of the circuit.
+    pub fn deserialize<R: Read>(mut reader: R) -> Result<Self, PlonkError> {
+        let nr_constraints = read_u64(&mut reader)?;
+        let nr_wires = nr_constraints
+            .checked_mul(WIDTH)
+            .ok_or(PlonkError::CircuitTooLarge {
+                nr_constraints,
+                max: usize::MAX / WIDTH,
+            })?;
+        let row = |row: usize| {
+            if row < nr_constraints {
+                Ok(row)
+            } else {
+                Err(PlonkError::WireOutOfBounds {
+                    wire: row,
+                    nr_wires: nr_constraints,
+                })
+            }
+        };
+        let wire = |index: usize| {
+            if index < nr_wires {
+                Ok(Wire::from_index(index, nr_constraints))
+            } else {
+                Err(PlonkError::WireOutOfBounds {
+                    wire: index,
+                    nr_wires,
+                })
+            }
+        };
+
+        let mut selector = || {
+            (0..nr_constraints)
+                .map(|_| read_scalar(&mut reader))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let constraints = Constraints {
+            qm: selector()?,
+            ql: selector()?,
+            qr: selector()?,
+            qo: selector()?,
+            qc: selector()?,
+            qk: selector()?,
+            qt: selector()?,
+            qn: selector()?,
+        };
+        // As the lengths of `read_scalars`, the number of wires is not trusted to allocate.
+        let mut permutations = HashMap::new();
+        for index in 0..nr_wires {
+            permutations.insert(wire(index)?, wire(read_u64(&mut reader)?)?);
+        }
+
+        let extended_h_subgroup = read_scalars(&mut reader)?;
+        let public_inputs = (0..read_u64(&mut reader)?)
+            .map(|_| row(read_u64(&mut reader)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        let constants = (0..read_u64(&mut reader)?)
+            .map(|_| Ok((row(read_u64(&mut reader)?)?, read_scalar(&mut reader)?)))
+            .collect::<Result<Vec<_>, PlonkError>>()?;
+        let lookup_tables = (0..read_u64(&mut reader)?)
+            .map(|_| {
+                let values = read_scalars(&mut reader)?;
+                if values.len() % 3 != 0 {
+                    return Err(PlonkError::InvalidLength {
+                        expected: values.len() / 3 * 3,
+                        got: values.len(),
+                    });
+                }
+                Ok(LookupTable(
+                    values
+                        .chunks_exact(3)
+                        .map(|row| [row[0], row[1], row[2]])
+                        .collect(),
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let named_tables = (0..read_u64(&mut reader)?)
+            .map(|_| Ok((read_string(&mut reader)?, read_u64(&mut reader)?)))
+            .collect::<Result<HashMap<_, _>, PlonkError>>()?;
+        if let Some(id) = named_tables
+            .values()
+            .find(|id| **id == 0 || **id > lookup_tables.len())
+        {
+            return Err(PlonkError::UnknownLookupTable(*id));
+        }
+        let labels = (0..read_u64(&mut reader)?)
+            .map(|_| Ok((wire(read_u64(&mut reader)?)?, read_string(&mut reader)?)))
+            .collect::<Result<HashMap<_, _>, PlonkError>>()?;
+        let gate_labels = (0..read_u64(&mut reader)?)
+            .map(|_| match read_u64(&mut reader)? {
+                row if row < nr_constraints => Ok((row, read_string(&mut reader)?)),
+                row => Err(PlonkError::UnknownWire(Wire::left(row))),
+            })
+            .collect::<Result<HashMap<_, _>, PlonkError>>()?;
+
+        Ok(PlonkCircuit {
+            extended_h_subgroup,
+            constraints,
+            permutations,
+            nr_wires,
+            nr_constraints,
+            public_inputs,
+            constants,
+            lookup_tables,
+            named_tables,
+            labels,
+            gate_labels,
+        })
+    }
+
+    /// Exports the constraint system as JSON, for external tools. The document is an object
+    /// with the number of constraints `nr_constraints`, the selectors of every row in `gates`
+    /// (`ql`, `qr`, `qo`, `qm`, `qc`, `qk` and `qt`, and `qn` if it is not zero), the non-trivial cycles of the permutation
+    /// in `permutation_cycles` (with wires as `{"row": 0, "column": "a"}`), the rows of the
+    /// `public_inputs`, the `constants`, the rows of the `lookup_tables`, the `named_tables`
+    /// and the `labels` of the wires and `gate_labels` of the gates. Scalars are hexadecimal strings in big endian, such as
+    /// `"0x1"`. The result of the setup is not exported, as it is computed again.
+    pub fn to_json(&self) -> String {
+        let q = &self.constraints;
+        let gates = (0..self.nr_constraints)
+            .map(|row| {
+                let mut selectors = vec![
+                    ("ql", scalar_to_json(&q.ql[row])),
+                    ("qr", scalar_to_json(&q.qr[row])),
+                    ("qo", scalar_to_json(&q.qo[row])),
+                    ("qm", scalar_to_json(&q.qm[row])),
+                    ("qc", scalar_to_json(&q.qc[row])),
+                    ("qk", scalar_to_json(&q.qk[row])),
+                    ("qt", scalar_to_json(&q.qt[row])),
+                ];
+                if q.qn[row] != Scalar::zero() {
+                    selectors.push(("qn", scalar_to_json(&q.qn[row])));
+                }
+                Json::object(selectors)
+            })
+            .collect();
+
+        let mut visited = HashSet::new();
+        let mut wires = self.permutations.keys().copied().collect::<Vec<_>>();
+        wires.sort();
+        let mut cycles = Vec::new();
+        for wire in wires {
+            let mut cycle = Vec::new();
+            let mut current = wire;
+            while visited.insert(current) {
+                cycle.push(wire_to_json(&current));
+                current = self.permutations[&current];
+            }
+            if cycle.len() > 1 {
+                cycles.push(Json::Array(cycle));
+            }
+        }
+
+        let mut named_tables = self.named_tables.iter().collect::<Vec<_>>();
+        named_tables.sort();
+        let mut labels = self.labels.iter().collect::<Vec<_>>();
+        labels.sort();
+        let mut gate_labels = self.gate_labels.iter().collect::<Vec<_>>();
+        gate_labels.sort();
+
+        let numbers = |rows: &mut dyn Iterator<Item = usize>| {
+            Json::Array(rows.map(|row| Json::Number(row as u64)).collect())
+        };
+        Json::object(vec![
+            ("nr_constraints", Json::Number(self.nr_constraints as u64)),
+            ("gates", Json::Array(gates)),
+            ("permutation_cycles", Json::Array(cycles)),
+            (
+                "public_inputs",
+                numbers(&mut self.public_inputs.iter().copied()),
+            ),
+            (
+                "constants",
+                Json::Array(
+                    self.constants
+                        .iter()
+                        .map(|(row, value)| {
+                            Json::object(vec![
+                                ("row", Json::Number(*row as u64)),
+                                ("value", scalar_to_json(value)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "lookup_tables",
+                Json::Array(
+                    self.lookup_tables
+                        .iter()
+                        .map(|table| {
+                            Json::Array(
+                                table
+                                    .0
+                                    .iter()
+                                    .map(|row| {
+                                        Json::Array(row.iter().map(scalar_to_json).collect())
+                                    })
+                                    .collect(),
+                            )
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "named_tables",
+                Json::Array(
+                    named_tables
+                        .into_iter()
+                        .map(|(name, id)| {
+                            Json::object(vec![
+                                ("name", Json::String(name.clone())),
+                                ("id", Json::Number(*id as u64)),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "labels",
+                Json::Array(
+                    labels
+                        .into_iter()
+                        .map(|(wire, label)| {
+                            Json::object(vec![
+                                ("wire", wire_to_json(wire)),
+                                ("label", Json::String(label.clone())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "gate_labels",
+                Json::Array(
+                    gate_labels
+                        .into_iter()
+                        .map(|(row, label)| {
+                            Json::object(vec![
+                                ("row", Json::Number(*row as u64)),
+                                ("label", Json::String(label.clone())),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ),
+        ])
+        .to_string()
+    }
+
+    /// Imports a constraint system exported with `to_json`. Wires that do not appear in any
+    /// cycle are only connected to themselves.
+    pub fn from_json(json: &str) -> Result<Self, PlonkError> {
+        let document = Json::parse(json)?;
+        let mut circuit = PlonkCircuit::init();
+
+        let nr_constraints = document.get("nr_constraints")?.as_usize()?;
+        let gates = document.get("gates")?.as_array()?;
+        if gates.len() != nr_constraints {
+            return Err(PlonkError::InvalidJson(format!(
+                "Expected {nr_constraints} gates, got {}",
+                gates.len()
+            )));
+        }
+        for gate in gates {
+            let selector = |name: &str| scalar_from_json(gate.get(name)?);
+            circuit.gate(Gate {
+                ql: selector("ql")?,
+                qr: selector("qr")?,
+                qo: selector("qo")?,
+                qm: selector("qm")?,
+                qc: selector("qc")?,
+                qn: match gate.get("qn") {
+                    Ok(qn) => scalar_from_json(qn)?,
+                    Err(_) => Scalar::zero(),
+                },
+            });
+            *circuit.constraints.qk.last_mut().unwrap() = selector("qk")?;
+            *circuit.constraints.qt.last_mut().unwrap() = selector("qt")?;
+        }
+
+        let wire = |value: &Json| {
+            let wire = wire_from_json(value)?;
+            if wire.row < nr_constraints {
+                Ok(wire)
+            } else {
+                Err(PlonkError::UnknownWire(wire))
+            }
+        };
+        let mut connected = HashSet::new();
+        for cycle in document.get("permutation_cycles")?.as_array()? {
+            let cycle = cycle
+                .as_array()?
+                .iter()
+                .map(wire)
+                .collect::<Result<Vec<_>, _>>()?;
+            for (i, current) in cycle.iter().enumerate() {
+                if !connected.insert(*current) {
+                    return Err(PlonkError::InvalidJson(format!(
+                        "Wire {current:?} is in several cycles"
+                    )));
+                }
+                circuit
+                    .permutations
+                    .insert(*current, cycle[(i + 1) % cycle.len()]);
+            }
+        }
+
+        let row = |value: &Json| {
+            let row = value.as_usize()?;
+            if row < nr_constraints {
+                Ok(row)
+            } else {
+                Err(PlonkError::UnknownWire(Wire::left(row)))
+            }
+        };
+        circuit.public_inputs = document
+            .get("public_inputs")?
+            .as_array()?
+            .iter()
+            .map(row)
+            .collect::<Result<_, _>>()?;
+        circuit.constants = document
+            .get("constants")?
+            .as_array()?
+            .iter()
+            .map(|constant| {
+                Ok((
+                    row(constant.get("row")?)?,
+                    scalar_from_json(constant.get("value")?)?,
+                ))
+            })
+            .collect::<Result<_, PlonkError>>()?;
+        for table in document.get("lookup_tables")?.as_array()? {
+            let rows = table
+                .as_array()?
+                .iter()
+                .map(|values| match values.as_array()? {
+                    [a, b, c] => Ok([
+                        scalar_from_json(a)?,
+                        scalar_from_json(b)?,
+                        scalar_from_json(c)?,
+                    ]),
+                    _ => Err(PlonkError::InvalidJson(
+                        "Rows of lookup tables have three values".to_string(),
+                    )),
+                })
+                .collect::<Result<_, _>>()?;
+            circuit.add_lookup_table(LookupTable(rows));
+        }
+        for named_table in document.get("named_tables")?.as_array()? {
+            let id = named_table.get("id")?.as_usize()?;
+            if id == 0 || id > circuit.lookup_tables.len() {
+                return Err(PlonkError::UnknownLookupTable(id));
+            }
+            let name = named_table.get("name")?.as_str()?;
+            circuit.named_tables.insert(name.to_string(), id);
+        }
+        for label in document.get("labels")?.as_array()? {
+            circuit.label(wire(label.get("wire")?)?, label.get("label")?.as_str()?);
+        }
+        for label in document.get("gate_labels")?.as_array()? {
+            circuit.label_gate(row(label.get("row")?)?, label.get("label")?.as_str()?);
+        }
+
+        Ok(circuit)
+    }
+
+    /// Names `wire`, so that the constraints that involve it are easier to identify when they
+    /// are not satisfied (see `MockProver`).
+    pub fn label(&mut self, wire: Wire, label: impl Into<String>) {
+        self.labels.insert(wire, label.into());
+    }
+
+    /// Names the gate at `row`, so that it is reported by its name when it is not satisfied,
+    /// and shown in `to_dot`.
+    pub fn label_gate(&mut self, row: usize, label: impl Into<String>) {
+        self.gate_labels.insert(row, label.into());
+    }
+
+    /// Adds a copy constraint between two wires. Each set of connected wires is a cycle of the
+    /// permutation, and swapping the successors of two wires merges their cycles. Wires that
+    /// are already connected are left as they are, as swapping would split their cycle.
+    pub fn connect(&mut self, in_wire: Wire, out_wire: Wire) -> Result<(), PlonkError> {
+        if self.equivalence_class(in_wire)?.contains(&out_wire) {
+            return Ok(());
+        }
+        let in_rel = self.permutations[&in_wire];
+        let out_rel = *self
+            .permutations
+            .get(&out_wire)
+            .ok_or(PlonkError::UnknownWire(out_wire))?;
+        self.permutations.insert(in_wire, out_rel);
+        self.permutations.insert(out_wire, in_rel);
+
+        Ok(())
+    }
+
+    /// Connects all the wires with each other.
+    pub fn connect_all(&mut self, wires: &[Wire]) -> Result<(), PlonkError> {
+        for pair in wires.windows(2) {
+            self.connect(pair[0], pair[1])?;
+        }
+        Ok(())
+    }
+
+    /// Wires connected to `wire`, starting with itself, in the order of their cycle.
+    pub fn equivalence_class(&self, wire: Wire) -> Result<Vec<Wire>, PlonkError> {
+        let mut class = vec![wire];
+        let mut current = *self
+            .permutations
+            .get(&wire)
+            .ok_or(PlonkError::UnknownWire(wire))?;
+        while current != wire {
+            class.push(current);
+            current = self.permutations[&current];
+        }
+        Ok(class)
+    }
+
+    // Same as `connect`, but with wires given by their position in the extended witness. As
+    // these depend on the number of constraints, this should always be called after padding.
+    pub fn connect_wires(&mut self, in_wire: &usize, out_wire: &usize) -> Result<(), PlonkError> {
+        for wire in [in_wire, out_wire] {
+            if *wire >= self.nr_wires {
+                return Err(PlonkError::WireOutOfBounds {
+                    wire: *wire,
+                    nr_wires: self.nr_wires,
+                });
+            }
+        }
+        self.connect(
+            Wire::from_index(*in_wire, self.nr_constraints),
+            Wire::from_index(*out_wire, self.nr_constraints),
+        )
+    }
+
+    /// Checks that `trace` satisfies every copy constraint, gate and lookup of the circuit, in
+    /// this order, and returns the first one that it violates. Rows missing from the trace are
+    /// taken as zero, as when it is padded. The rows of the public inputs are not checked, as
+    /// their values are only given to the prover.
+    // The error is only built for a failing trace, so its size does not matter.
+    #[allow(clippy::result_large_err)]
+    pub fn is_satisfied(&self, trace: &ComputationTrace) -> Result<(), UnsatisfiedGate> {
+        match self.unsatisfied(trace).next() {
+            Some(constraint) => Err(constraint),
+            None => Ok(()),
+        }
+    }
+
+    // Every constraint violated by `trace`, in the order of `is_satisfied`.
+    pub(crate) fn unsatisfied<'a>(
+        &'a self,
+        trace: &'a ComputationTrace,
+    ) -> impl Iterator<Item = UnsatisfiedGate> + 'a {
+        let mut permutations = self.permutations.iter().collect::<Vec<_>>();
+        permutations.sort();
+        let copies = permutations
+            .into_iter()
+            .filter_map(move |(wire, connected)| {
+                let values = (trace.value(*wire), trace.value(*connected));
+                (values.0 != values.1).then_some(UnsatisfiedGate::CopyConstraint {
+                    wire: *wire,
+                    connected: *connected,
+                    values,
+                })
+            });
+
+        let q = &self.constraints;
+        let public_inputs = self.public_inputs.iter().copied().collect::<HashSet<_>>();
+        let gates = (0..self.nr_constraints)
+            .filter(move |row| !public_inputs.contains(row))
+            .filter_map(move |row| {
+                let (a, b, c) = trace.row(row);
+                let next = trace.value(Wire::left((row + 1) % self.nr_constraints));
+                let gate = q.ql[row] * a
+                    + q.qr[row] * b
+                    + q.qo[row] * c
+                    + q.qm[row] * a * b
+                    + q.qc[row]
+                    + q.qn[row] * next;
+                (!gate.is_zero_vartime()).then(|| UnsatisfiedGate::Gate {
+                    row,
+                    label: self.gate_labels.get(&row).cloned(),
+                    a,
+                    b,
+                    c,
+                })
+            });
+
+        let tables = self
+            .lookup_tables
+            .iter()
+            .map(|table| {
+                table
+                    .0
+                    .iter()
+                    .map(|row| row.map(|value| value.to_bytes_le()))
+                    .collect::<HashSet<_>>()
+            })
+            .collect::<Vec<_>>();
+        let lookups = (0..self.nr_constraints)
+            .filter(move |row| q.qk[*row] == Scalar::one())
+            .filter_map(move |row| {
+                let (a, b, c) = trace.row(row);
+                let query = [a, b, c].map(|value| value.to_bytes_le());
+                let found = tables
+                    .iter()
+                    .zip(1u64..)
+                    .any(|(table, id)| Scalar::from(id) == q.qt[row] && table.contains(&query));
+                (!found).then(|| UnsatisfiedGate::Lookup {
+                    row,
+                    label: self.gate_labels.get(&row).cloned(),
+                    a,
+                    b,
+                    c,
+                })
+            });
+
+        copies.chain(gates).chain(lookups)
+    }
+
+    // This should only be called once the circuit is padded to a power of two.
+    pub fn lagrange_basis(&self, index: usize) -> Polynomial {
+        EvaluationDomain::new(self.nr_constraints)
+            .expect("The circuit is not padded")
+            .lagrange_basis(index)
     }
 
     pub fn compute_sigma_star(&self) -> HashMap<usize, Scalar> {
         self.permutations
             .iter()
-            .map(|(index, value)| (*index, self.extended_h_subgroup[*value]))
+            .map(|(wire, value)| {
+                (
+                    wire.index(self.nr_constraints),
+                    self.extended_h_subgroup[value.index(self.nr_constraints)],
+                )
+            })
             .collect::<HashMap<usize, Scalar>>()
     }
 
-    pub fn setup(&mut self) -> PreprocessedInput {
-        // For simplicity, we begin computing our extended subgroup H'. We need an nth root of unity with
-        // n being the number of constraints. We compute this root of unity out of the 2^32nd
-        // root of unity, g, which is provided as a constant in the underlying library. We do so
-        // by calculating omega = g^{2^32/ n}.
-        let omega = Scalar::root_of_unity().pow_vartime([
-            (1u64 << 32) / self.nr_constraints as u64,
-            0,
-            0,
-            0,
-        ]);
+    /// Computes the keys of the circuit, with a new SRS for its size whose toxic waste is drawn
+    /// from `rng`. The SRS is only as secure as `rng`: production keys are set up with `OsRng`,
+    /// or from the SRS of a ceremony with `setup_with_srs`, and seeded RNGs are only meant for
+    /// reproducible tests.
+    pub fn setup<R: RngCore + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+    ) -> Result<(ProverKey, VerifierKey), PlonkError> {
+        self.pad_next_power_of_two();
+        // The largest polynomial we commit to is the high part of the quotient, with n + 7
+        // coefficients.
+        self.setup_with_srs(Kzg10::setup(self.nr_constraints + 7, rng))
+    }
 
-        assert_eq!(
-            omega.pow_vartime([self.nr_constraints as u64, 0, 0, 0]),
-            Scalar::one()
-        );
+    /// Computes the keys of the circuit with the commitment `scheme`, which must commit to
+    /// polynomials of n + 7 coefficients for a padded circuit of n gates: for KZG, an SRS with
+    /// at least n + 7 powers, e.g. loaded from a Powers of Tau file, and without trusted setup,
+    /// `Ipa::setup(n + 7)` or `Fri::setup(n + 7)`.
+    pub fn setup_with_srs<P: PlonkCommitment>(
+        &mut self,
+        scheme: P,
+    ) -> Result<(ProverKey<P>, VerifierKey<P>), PlonkError> {
+        // The domain must be a power of two, so circuits of any size are padded with gates that
+        // a zero row satisfies. This does nothing to circuits that are already padded.
+        self.pad_next_power_of_two();
+        if scheme.max_size() < self.nr_constraints + 7 {
+            return Err(PlonkError::CircuitTooLarge {
+                nr_constraints: self.nr_constraints,
+                max: scheme.max_size().saturating_sub(7),
+            });
+        }
 
-        self.extended_h_subgroup = vec![Scalar::zero(); self.nr_constraints * 3];
-        self.extended_h_subgroup[0] = omega;
-        self.extended_h_subgroup[self.nr_constraints] = K1() * omega;
-        self.extended_h_subgroup[self.nr_constraints * 2] = K2() * omega;
+        // For simplicity, we begin computing our extended subgroup H'. We need an nth root of unity with
+        // n being the number of constraints, which defines the subgroup H, and which is
+        // root_of_unity^(2^32 / n). Row i of the circuit corresponds to omega^i.
+        let domain = EvaluationDomain::new(self.nr_constraints)?;
+        let elements = domain.elements();
 
-        for index in 1..self.nr_constraints {
-            self.extended_h_subgroup[index] = self.extended_h_subgroup[index - 1] * omega;
-            self.extended_h_subgroup[index + self.nr_constraints] =
-                self.extended_h_subgroup[index] * K1();
-            self.extended_h_subgroup[index + self.nr_constraints * 2] =
-                self.extended_h_subgroup[index] * K2();
+        self.extended_h_subgroup = Vec::with_capacity(self.nr_constraints * WIDTH);
+        for column in 0..WIDTH {
+            let k = K(column);
+            self.extended_h_subgroup
+                .extend(elements.iter().map(|element| element * k));
         }
 
         // Next, we define the \sigma*
         let sigma_star = self.compute_sigma_star();
 
         // Now we create the permutation polynomials qs1, qs2 and qs3, and the
-        // selector polynomials ql_x, qr_x, qc_x, qo_x and qm_x.
-        let mut qs1_x = Polynomial::zero(self.nr_constraints);
-        let mut qs2_x = Polynomial::zero(self.nr_constraints);
-        let mut qs3_x = Polynomial::zero(self.nr_constraints);
-
-        let mut ql_x = Polynomial::zero(self.nr_constraints);
-        let mut qr_x = Polynomial::zero(self.nr_constraints);
-        let mut qc_x = Polynomial::zero(self.nr_constraints);
-        let mut qo_x = Polynomial::zero(self.nr_constraints);
-        let mut qm_x = Polynomial::zero(self.nr_constraints);
-
-        for i in 0..self.nr_constraints {
-            let lp = self.lagrange_basis(i);
-            qs1_x += &lp * sigma_star.get(&i).unwrap();
-            qs2_x += &lp * sigma_star.get(&(self.nr_constraints + i)).unwrap();
-            qs3_x += &lp * sigma_star.get(&(self.nr_constraints * 2 + i)).unwrap();
-
-            ql_x += &lp * self.constraints.ql[i];
-            qr_x += &lp * self.constraints.qr[i];
-            qc_x += &lp * self.constraints.qc[i];
-            qo_x += &lp * self.constraints.qo[i];
-            qm_x += &lp * self.constraints.qm[i];
-        }
-
-        let mut blinder_vec = vec![Scalar::zero(); self.nr_constraints + 1];
-        blinder_vec[0] = Scalar::one().neg();
-        blinder_vec[self.nr_constraints] = Scalar::one();
-        let blinder_polynomial = Polynomial(blinder_vec);
+        // selector polynomials ql_x, qr_x, qc_x, qo_x, qm_x and qn_x, by interpolating their
+        // values over H.
+        let sigma_column = |column: usize| {
+            (0..self.nr_constraints)
+                .map(|row| {
+                    *sigma_star
+                        .get(&(column * self.nr_constraints + row))
+                        .unwrap()
+                })
+                .collect::<Vec<Scalar>>()
+        };
+        let columns: [usize; WIDTH] = core::array::from_fn(|column| column);
+        let [qs1_x, qs2_x, qs3_x] =
+            parallel::map_array(&columns, |_, column| domain.ifft(&sigma_column(*column)));
+
+        let constraints = &self.constraints;
+        let [ql_x, qr_x, qc_x, qo_x, qm_x, qk_x, qt_x, qn_x] = parallel::map_array(
+            &[
+                &constraints.ql,
+                &constraints.qr,
+                &constraints.qc,
+                &constraints.qo,
+                &constraints.qm,
+                &constraints.qk,
+                &constraints.qt,
+                &constraints.qn,
+            ],
+            |_, evals| domain.ifft(evals),
+        );
+
+        // The lookup table is interpolated column by column.
+        if self.lookup_rows() > self.nr_constraints {
+            return Err(PlonkError::LookupTableTooLarge {
+                table_size: self.lookup_rows(),
+                nr_constraints: self.nr_constraints,
+            });
+        }
+        let lookup_table = tagged_rows(&self.lookup_tables, self.nr_constraints);
+        let [table1_x, table2_x, table3_x, table4_x] =
+            parallel::map_array(&[0, 1, 2, 3], |_, column| {
+                domain.ifft(
+                    &lookup_table
+                        .iter()
+                        .map(|row| row[*column])
+                        .collect::<Vec<_>>(),
+                )
+            });
+
+        let blinder_polynomial = SparsePolynomial::vanishing(self.nr_constraints);
         assert!(self.extended_h_subgroup[..self.nr_constraints]
             .iter()
             .all(|val| blinder_polynomial.eval(val) == Scalar::zero()));
 
-        PreprocessedInput {
-            kzg_set: Kzg10::setup(),
+        let [qm_comm, ql_comm, qr_comm, qo_comm, qc_comm, qs1_comm, qs2_comm, qs3_comm, qk_comm, table1_comm, table2_comm, table3_comm, table4_comm, qt_comm, qn_comm] =
+            parallel::map_array(
+                &[
+                    &qm_x, &ql_x, &qr_x, &qo_x, &qc_x, &qs1_x, &qs2_x, &qs3_x, &qk_x, &table1_x,
+                    &table2_x, &table3_x, &table4_x, &qt_x, &qn_x,
+                ],
+                |_, polynomial| scheme.commit(polynomial),
+            );
+
+        let verifier_key = VerifierKey {
+            domain: domain.clone(),
+            public_inputs: self.public_inputs.clone(),
+            scheme: scheme.verifier_part(),
+            qm_comm,
+            ql_comm,
+            qr_comm,
+            qo_comm,
+            qc_comm,
+            qs1_comm,
+            qs2_comm,
+            qs3_comm,
+            qk_comm,
+            table1_comm,
+            table2_comm,
+            table3_comm,
+            table4_comm,
+            qt_comm,
+            qn_comm,
+        };
+
+        let l0_x = domain.lagrange_basis(0);
+        let prover_key = ProverKey {
+            verifier_key: verifier_key.clone(),
+            lagrange_set: scheme.lagrange_set(&domain)?,
+            scheme,
+            domain,
             blinder_polynomial,
             sigma_star,
             qm_x,
@@ -283,7 +1968,724 @@ impl PlonkCircuit {
             qs1_x,
             qs2_x,
             qs3_x,
+            qk_x,
+            table1_x,
+            table2_x,
+            table3_x,
+            table4_x,
+            qt_x,
+            qn_x,
+            l0_x,
+            lookup_table,
             constraints: self.clone(),
+        };
+
+        Ok((prover_key, verifier_key))
+    }
+}
+
+fn scalar_to_json(value: &Scalar) -> Json {
+    let hex = value
+        .to_bytes_be()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    let digits = hex.trim_start_matches('0');
+    Json::String(format!(
+        "0x{}",
+        if digits.is_empty() { "0" } else { digits }
+    ))
+}
+
+fn scalar_from_json(value: &Json) -> Result<Scalar, PlonkError> {
+    let digits = value
+        .as_str()?
+        .strip_prefix("0x")
+        .filter(|digits| !digits.is_empty() && digits.len() <= 64)
+        .ok_or(PlonkError::InvalidScalar)?;
+    let padded = format!("{digits:0>64}");
+    let mut bytes = [0u8; 32];
+    for (byte, pair) in bytes.iter_mut().zip(padded.as_bytes().chunks_exact(2)) {
+        let pair = std::str::from_utf8(pair).map_err(|_| PlonkError::InvalidScalar)?;
+        *byte = u8::from_str_radix(pair, 16).map_err(|_| PlonkError::InvalidScalar)?;
+    }
+    Option::from(Scalar::from_bytes_be(&bytes)).ok_or(PlonkError::InvalidScalar)
+}
+
+fn wire_to_json(wire: &Wire) -> Json {
+    Json::object(vec![
+        ("row", Json::Number(wire.row as u64)),
+        (
+            "column",
+            Json::String(["a", "b", "c"][wire.column].to_string()),
+        ),
+    ])
+}
+
+fn wire_from_json(value: &Json) -> Result<Wire, PlonkError> {
+    let row = value.get("row")?.as_usize()?;
+    match value.get("column")?.as_str()? {
+        "a" => Ok(Wire::left(row)),
+        "b" => Ok(Wire::right(row)),
+        "c" => Ok(Wire::output(row)),
+        column => Err(PlonkError::InvalidJson(format!("Unknown column {column}"))),
+    }
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: usize) -> Result<(), PlonkError> {
+    Ok(writer.write_all(&(value as u64).to_le_bytes())?)
+}
+
+fn write_scalars<W: Write>(writer: &mut W, values: &[Scalar]) -> Result<(), PlonkError> {
+    write_u64(writer, values.len())?;
+    for value in values.iter() {
+        writer.write_all(&value.to_bytes_be())?;
+    }
+    Ok(())
+}
+
+fn write_string<W: Write>(writer: &mut W, value: &str) -> Result<(), PlonkError> {
+    write_u64(writer, value.len())?;
+    Ok(writer.write_all(value.as_bytes())?)
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<usize, PlonkError> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    usize::try_from(u64::from_le_bytes(bytes)).map_err(|error| PlonkError::Io(error.to_string()))
+}
+
+fn read_scalar<R: Read>(reader: &mut R) -> Result<Scalar, PlonkError> {
+    let mut bytes = [0u8; 32];
+    reader.read_exact(&mut bytes)?;
+    Option::from(Scalar::from_bytes_be(&bytes)).ok_or(PlonkError::InvalidScalar)
+}
+
+// The length is not trusted to allocate, so that a corrupted one fails with an I/O error when
+// the data runs out.
+fn read_scalars<R: Read>(reader: &mut R) -> Result<Vec<Scalar>, PlonkError> {
+    (0..read_u64(reader)?)
+        .map(|_| read_scalar(reader))
+        .collect()
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, PlonkError> {
+    let length = read_u64(reader)? as u64;
+    let mut bytes = Vec::new();
+    if reader.take(length).read_to_end(&mut bytes)? as u64 != length {
+        return Err(PlonkError::Io("Unexpected end of file".to_string()));
+    }
+    String::from_utf8(bytes).map_err(|error| PlonkError::Io(error.to_string()))
+}
+
+#[cfg(feature = "serde")]
+mod wire_map_serde {
+    use super::Wire;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    // Wires are not valid keys in most human readable formats, so maps with wires as keys (the
+    // permutation and the labels) are serialized as a list of pairs, sorted to make the
+    // encoding deterministic.
+    pub fn serialize<V: Clone + Ord + Serialize, S: Serializer>(
+        map: &HashMap<Wire, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut pairs = map
+            .iter()
+            .map(|(wire, value)| (*wire, value.clone()))
+            .collect::<Vec<(Wire, V)>>();
+        pairs.sort();
+        pairs.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, V: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<Wire, V>, D::Error> {
+        Ok(Vec::<(Wire, V)>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::Prover;
+    use crate::transcript::{Blake2bTranscript, Transcript};
+    use crate::verifier::PlonkVerifier;
+    use crate::witness::WitnessBuilder;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::{OsRng, SeedableRng};
+
+    #[test]
+    fn test_wire_handles() {
+        let mut circuit = PlonkCircuit::init();
+        let (_, _, x_sq) = circuit.mult_gate();
+        let (sum_l, _, _) = circuit.add_gate();
+        circuit.pad_next_power_of_two();
+
+        let mut raw_circuit = circuit.clone();
+        circuit.connect(x_sq, sum_l).unwrap();
+        raw_circuit.connect_wires(&4, &1).unwrap();
+
+        assert_eq!(x_sq.index(circuit.nr_constraints), 4);
+        assert_eq!(sum_l.index(circuit.nr_constraints), 1);
+        assert_eq!(circuit.permutations, raw_circuit.permutations);
+        assert_eq!(circuit.permutations[&x_sq], sum_l);
+        assert_eq!(circuit.permutations[&sum_l], x_sq);
+
+        assert_eq!(
+            circuit.connect_wires(&1, &6),
+            Err(PlonkError::WireOutOfBounds {
+                wire: 6,
+                nr_wires: 6
+            })
+        );
+        assert_eq!(
+            circuit.connect(x_sq, Wire::left(2)),
+            Err(PlonkError::UnknownWire(Wire::left(2)))
+        );
+    }
+
+    #[test]
+    fn test_assert_equal() {
+        let mut circuit = PlonkCircuit::init();
+        let (x_l, x_r, square) = circuit.mult_gate();
+        circuit.assert_equal(x_l, x_r).unwrap();
+        assert_eq!(circuit.nr_constraints, 1);
+        circuit.assert_equal_const(square, Scalar::from(9)).unwrap();
+        assert_eq!(circuit.nr_constraints, 2);
+
+        let trace = |x: u64| {
+            let mut builder = WitnessBuilder::new(&circuit);
+            builder.assign(x_l, Scalar::from(x))?;
+            builder.build()
+        };
+        assert!(circuit.is_satisfied(&trace(3).unwrap()).is_ok());
+        assert!(trace(4).is_err());
+        assert_eq!(
+            circuit.assert_equal(x_l, Wire::left(2)),
+            Err(PlonkError::UnknownWire(Wire::left(2)))
+        );
+    }
+
+    #[test]
+    fn test_connect_all() {
+        let mut circuit = PlonkCircuit::init();
+        let (a, b, c) = circuit.add_gate();
+        let (d, e, _) = circuit.add_gate();
+        circuit.connect_all(&[a, b, c, d]).unwrap();
+        // Connecting wires of the same class again does not split it.
+        circuit.connect(d, a).unwrap();
+        circuit.connect(b, c).unwrap();
+
+        let mut class = circuit.equivalence_class(c).unwrap();
+        assert_eq!(class[0], c);
+        class.sort();
+        assert_eq!(class, vec![a, b, c, d]);
+        assert_eq!(circuit.equivalence_class(e).unwrap(), vec![e]);
+
+        // The classes do not depend on the order of the connections.
+        let mut other = PlonkCircuit::init();
+        other.add_gate();
+        other.add_gate();
+        other.connect_all(&[d, c]).unwrap();
+        other.connect_all(&[b, a, d]).unwrap();
+        let mut other_class = other.equivalence_class(a).unwrap();
+        other_class.sort();
+        assert_eq!(other_class, class);
+        assert_eq!(
+            circuit.equivalence_class(Wire::left(2)),
+            Err(PlonkError::UnknownWire(Wire::left(2)))
+        );
+    }
+
+    #[test]
+    fn test_custom_gate() {
+        let mut circuit = PlonkCircuit::init();
+        circuit.add_gate();
+        circuit.custom_gate(
+            Scalar::one(),
+            Scalar::one(),
+            Scalar::one().neg(),
+            Scalar::zero(),
+            Scalar::zero(),
+        );
+        circuit.gate(Gate::new().left(3).right(2).output(-1).constant(5));
+
+        assert_eq!(circuit.constraints.ql[0], circuit.constraints.ql[1]);
+        assert_eq!(circuit.constraints.qo[0], circuit.constraints.qo[1]);
+        assert_eq!(circuit.constraints.ql[2], Scalar::from(3));
+        assert_eq!(circuit.constraints.qr[2], Scalar::from(2));
+        assert_eq!(circuit.constraints.qo[2], Scalar::one().neg());
+        assert_eq!(circuit.constraints.qm[2], Scalar::zero());
+        assert_eq!(circuit.constraints.qc[2], Scalar::from(5));
+        assert_eq!(circuit.nr_constraints, 3);
+    }
+
+    #[test]
+    fn test_is_satisfied() {
+        // x * x = y, and (x, y) is a row of a table of squares.
+        let mut circuit = PlonkCircuit::init();
+        let table = circuit.add_lookup_table(LookupTable::new(vec![
+            [Scalar::from(2), Scalar::from(4)],
+            [Scalar::from(3), Scalar::from(9)],
+        ]));
+        let pi = circuit.public_input();
+        let (x_l, x_r, y) = circuit.mult_gate();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(pi, y).unwrap();
+        circuit.lookup(table, &[x_l, y]).unwrap();
+        circuit.label_gate(2, "squares");
+
+        let assign = |x: u64, y: u64| {
+            let mut trace = ComputationTrace::new(&circuit);
+            let (x, y) = (Scalar::from(x), Scalar::from(y));
+            for wire in [Wire::left(1), Wire::right(1), Wire::left(2)] {
+                trace.set(wire, x);
+            }
+            for wire in [Wire::left(0), Wire::output(1), Wire::right(2)] {
+                trace.set(wire, y);
+            }
+            trace
+        };
+        assert_eq!(circuit.is_satisfied(&assign(3, 9)), Ok(()));
+        assert_eq!(
+            circuit.is_satisfied(&assign(3, 10)),
+            Err(UnsatisfiedGate::Gate {
+                row: 1,
+                label: None,
+                a: Scalar::from(3),
+                b: Scalar::from(3),
+                c: Scalar::from(10)
+            })
+        );
+        assert_eq!(
+            circuit.is_satisfied(&assign(4, 16)),
+            Err(UnsatisfiedGate::Lookup {
+                row: 2,
+                label: Some("squares".to_string()),
+                a: Scalar::from(4),
+                b: Scalar::from(16),
+                c: Scalar::zero()
+            })
+        );
+        assert!(circuit
+            .is_satisfied(&assign(4, 16))
+            .unwrap_err()
+            .to_string()
+            .starts_with("The lookup gate \"squares\" at row 2 has wires"));
+
+        let mut trace = assign(3, 9);
+        trace.set(Wire::right(1), Scalar::from(4));
+        assert_eq!(
+            circuit.is_satisfied(&trace),
+            Err(UnsatisfiedGate::CopyConstraint {
+                wire: Wire::right(1),
+                connected: Wire::left(1),
+                values: (Scalar::from(4), Scalar::from(3))
+            })
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut circuit = PlonkCircuit::init();
+        let pi = circuit.public_input();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        let (sum_l, _, sum) = circuit.add_gate();
+        circuit.gate(Gate::new().left(2).output(-1));
+        circuit.assert_constant(sum, Scalar::from(5)).unwrap();
+        circuit.xor(x_l, x_sq, 2).unwrap();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(pi, x_sq).unwrap();
+        circuit.connect(x_sq, sum_l).unwrap();
+
+        let stats = circuit.stats();
+        assert_eq!(
+            (
+                stats.public_inputs,
+                stats.multiplications,
+                stats.additions,
+                stats.custom,
+                stats.constants,
+                stats.lookups,
+                stats.empty
+            ),
+            (1, 1, 1, 1, 1, 1, 0)
+        );
+        assert_eq!(stats.nr_wires, 18);
+        // x_l = x_r = a_xor, pi = x_sq = sum_l = b_xor, and sum = a_constant.
+        assert_eq!(stats.copy_constraints, 6);
+        assert_eq!(stats.lookup_rows, 16);
+        assert_eq!(stats.domain_size, 16);
+        assert_eq!(stats.proof_size, PlonkProof::SIZE);
+
+        circuit.pad_next_power_of_two();
+        let padded = circuit.stats();
+        assert_eq!(padded.domain_size, stats.domain_size);
+        assert_eq!(padded.additions, 11);
+        assert_eq!(padded.fft_multiplications, stats.fft_multiplications);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let mut circuit = PlonkCircuit::init();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        let (sum_l, _, _) = circuit.add_gate_labeled("sum<x>");
+        let pi = circuit.public_input();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(x_sq, sum_l).unwrap();
+        circuit.connect(x_sq, pi).unwrap();
+        circuit.label(x_l, "x");
+        circuit.label(x_sq, "x|x");
+
+        let dot = circuit.to_dot();
+        assert!(dot.starts_with("digraph circuit {"));
+        assert!(dot.contains(
+            "g0 [label=\"{0: qL=0 qR=0 qO=-1 qM=1 qC=0|{<a> a x|<b> b |<c> c x\\|x}}\"];"
+        ));
+        assert!(dot.contains("g1 [label=\"{1: sum\\<x\\> (qL=1 qR=1 qO=-1 qM=0 qC=0)|"));
+        assert!(dot.contains("g2 [label=\"{2: public input|"));
+        assert!(dot.contains("g0:a -> g0:b [dir=none];"));
+        // The cycle x_sq -> sum_l -> pi is drawn as two edges.
+        assert!(dot.contains("g1:a -> g2:a [dir=none];"));
+        assert!(dot.contains("g0:c -> g1:a [dir=none];"));
+        assert_eq!(dot.matches("->").count(), 3);
+    }
+
+    #[test]
+    fn test_serialize_circuit() {
+        let mut circuit = PlonkCircuit::init();
+        let pi = circuit.public_input();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        circuit.assert_constant(x_l, Scalar::from(3)).unwrap();
+        circuit.xor(x_l, x_sq, 2).unwrap();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(pi, x_sq).unwrap();
+        circuit.label(x_sq, "x²");
+        circuit.label_gate(x_sq.row, "square");
+        circuit.named_tables.insert("xor 2".to_string(), 1);
+        circuit.pad_next_power_of_two();
+        circuit.setup(&mut OsRng).unwrap();
+
+        let mut bytes = Vec::new();
+        circuit.serialize(&mut bytes).unwrap();
+        let decoded = PlonkCircuit::deserialize(bytes.as_slice()).unwrap();
+        assert_eq!(decoded.permutations, circuit.permutations);
+        assert_eq!(decoded.constraints.qt, circuit.constraints.qt);
+        assert_eq!(decoded.constants, circuit.constants);
+        assert_eq!(decoded.lookup_tables, circuit.lookup_tables);
+        assert_eq!(decoded.named_tables, circuit.named_tables);
+        assert_eq!(decoded.labels, circuit.labels);
+        assert_eq!(decoded.gate_labels, circuit.gate_labels);
+        let mut encoded = Vec::new();
+        decoded.serialize(&mut encoded).unwrap();
+        assert_eq!(encoded, bytes);
+
+        assert!(matches!(
+            PlonkCircuit::deserialize(&bytes[..bytes.len() - 1]),
+            Err(PlonkError::Io(_))
+        ));
+        // The image of the first wire is out of bounds.
+        let mut invalid_wire = bytes.clone();
+        let offset = 8 + 8 * 16 * 32;
+        invalid_wire[offset..offset + 8].copy_from_slice(&48u64.to_le_bytes());
+        assert_eq!(
+            PlonkCircuit::deserialize(invalid_wire.as_slice()).err(),
+            Some(PlonkError::WireOutOfBounds {
+                wire: 48,
+                nr_wires: 48
+            })
+        );
+        let mut too_large = bytes.clone();
+        too_large[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(
+            PlonkCircuit::deserialize(too_large.as_slice()),
+            Err(PlonkError::CircuitTooLarge { .. })
+        ));
+        let mut invalid_scalar = bytes;
+        invalid_scalar[8..40].copy_from_slice(&[0xffu8; 32]);
+        assert_eq!(
+            PlonkCircuit::deserialize(invalid_scalar.as_slice()).err(),
+            Some(PlonkError::InvalidScalar)
+        );
+    }
+
+    #[test]
+    fn test_public_input_polynomial() {
+        let mut circuit = PlonkCircuit::init();
+        circuit.add_gate();
+        let pi = [circuit.public_input(), circuit.public_input()];
+        circuit.pad_next_power_of_two();
+        let (prover_key, _) = circuit.setup(&mut OsRng).unwrap();
+
+        let inputs = [Scalar::from(5), Scalar::from(7)];
+        let polynomial = prover_key.public_input_polynomial(&inputs).unwrap();
+        let expected = &prover_key.domain.lagrange_basis(pi[0].row) * -inputs[0]
+            + &prover_key.domain.lagrange_basis(pi[1].row) * -inputs[1];
+        assert_eq!(polynomial, expected);
+        assert_eq!(
+            prover_key.public_input_polynomial(&inputs[..1]),
+            Err(PlonkError::PublicInputLength {
+                expected: 2,
+                got: 1
+            })
+        );
+        assert_eq!(prover_key.l0_x, prover_key.domain.lagrange_basis(0));
+    }
+
+    #[test]
+    fn test_setup_pads_circuit() {
+        // x^2 + y^2 = z over three gates, which the setup pads to four.
+        let mut circuit = PlonkCircuit::init();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        let (y_l, y_r, y_sq) = circuit.mult_gate();
+        let (left, right, _) = circuit.add_gate();
+        for (wire, other) in [(x_l, x_r), (y_l, y_r), (x_sq, left), (y_sq, right)] {
+            circuit.connect(wire, other).unwrap();
         }
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+        assert_eq!(prover_key.domain.size, 4);
+        assert_eq!(
+            prover_key.domain.generator,
+            Scalar::root_of_unity().pow_vartime([1u64 << 30])
+        );
+
+        // The trace only has the rows of the gates.
+        let values = |values: [u64; 3]| values.map(Scalar::from).to_vec();
+        let trace = ComputationTrace {
+            a: values([3, 4, 9]),
+            b: values([3, 4, 16]),
+            c: values([9, 16, 25]),
+        };
+
+        let mut transcript = Blake2bTranscript::new(b"padding");
+        let proof = Prover::prove(&[], &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"padding");
+        assert!(PlonkVerifier::verify(&[], &verifier_key, &proof, &mut transcript).is_ok());
+    }
+
+    #[test]
+    fn test_setup_rng() {
+        let mut circuit = PlonkCircuit::init();
+        let (x_l, x_r, _) = circuit.mult_gate();
+        circuit.connect(x_l, x_r).unwrap();
+
+        // A seeded setup is reproducible, and a random one gives other keys.
+        let seeded = || ChaCha20Rng::from_seed([0u8; 32]);
+        let fingerprint = circuit.setup(&mut seeded()).unwrap().1.fingerprint();
+        assert_eq!(
+            circuit.setup(&mut seeded()).unwrap().1.fingerprint(),
+            fingerprint
+        );
+        let (_, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+        assert_ne!(verifier_key.fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn test_setup_with_srs() {
+        let mut circuit = PlonkCircuit::init();
+        let (x_l, x_r, _) = circuit.mult_gate();
+        circuit.connect(x_l, x_r).unwrap();
+        assert_eq!(
+            circuit.setup_with_srs(Kzg10::setup(6, &mut OsRng)).err(),
+            Some(PlonkError::CircuitTooLarge {
+                nr_constraints: 1,
+                max: 0
+            })
+        );
+
+        // The quotient is split in three parts of degree at most n + 6, so n + 7 powers are
+        // enough, and a larger SRS than needed works as well.
+        assert!(circuit.setup_with_srs(Kzg10::setup(8, &mut OsRng)).is_ok());
+        let (prover_key, verifier_key) = circuit
+            .setup_with_srs(Kzg10::setup(16, &mut OsRng))
+            .unwrap();
+        let values = |value: u64| vec![Scalar::from(value)];
+        let trace = ComputationTrace {
+            a: values(3),
+            b: values(3),
+            c: values(9),
+        };
+        let mut transcript = Blake2bTranscript::new(b"srs");
+        let proof = Prover::prove(&[], &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"srs");
+        assert!(PlonkVerifier::verify(&[], &verifier_key, &proof, &mut transcript).is_ok());
+    }
+
+    #[test]
+    fn test_serialize_prover_key() {
+        // x * x = y with y public, and a lookup table so that every part of the key is used.
+        let mut circuit = PlonkCircuit::init();
+        let y = circuit.public_input();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        circuit.add_lookup_table(LookupTable::xor(2));
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(y, x_sq).unwrap();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let mut bytes = Vec::new();
+        prover_key.serialize(&mut bytes).unwrap();
+        let decoded = ProverKey::deserialize(bytes.as_slice()).unwrap();
+        assert_eq!(decoded.sigma_star, prover_key.sigma_star);
+        assert_eq!(decoded.lookup_table, prover_key.lookup_table);
+        assert_eq!(
+            decoded.verifier_key.fingerprint(),
+            verifier_key.fingerprint()
+        );
+        let mut encoded = Vec::new();
+        decoded.serialize(&mut encoded).unwrap();
+        assert_eq!(encoded, bytes);
+
+        // The decoded key proves for the verifier key of the setup.
+        let mut trace = ComputationTrace::new(&decoded.constraints);
+        for (wire, value) in [(y, 9), (x_l, 3), (x_r, 3), (x_sq, 9)] {
+            trace.set(wire, Scalar::from(value));
+        }
+        let pub_in = [Scalar::from(9)];
+        let mut transcript = Blake2bTranscript::new(b"prover key");
+        let proof = Prover::prove(&pub_in, &decoded, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"prover key");
+        assert!(PlonkVerifier::verify(&pub_in, &verifier_key, &proof, &mut transcript).is_ok());
+
+        // Any change is caught by the hash, and other versions are rejected.
+        let mut corrupted = bytes.clone();
+        corrupted[100] ^= 1;
+        assert!(matches!(
+            ProverKey::deserialize(corrupted.as_slice()),
+            Err(PlonkError::InvalidFormat(_))
+        ));
+        let mut other_version = bytes[..bytes.len() - 32].to_vec();
+        other_version[8] = 2;
+        let hash: [u8; 32] = Blake2b::<U32>::digest(&other_version).into();
+        other_version.extend_from_slice(&hash);
+        assert_eq!(
+            ProverKey::deserialize(other_version.as_slice()).err(),
+            Some(PlonkError::InvalidFormat(
+                "Unsupported prover key version 2".to_string()
+            ))
+        );
+        assert!(matches!(
+            ProverKey::deserialize(&bytes[8..]),
+            Err(PlonkError::InvalidFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_zeroize_trace() {
+        let mut trace = ComputationTrace {
+            a: vec![Scalar::from(3)],
+            b: vec![Scalar::from(3)],
+            c: vec![Scalar::from(9)],
+        };
+        trace.zeroize();
+        assert!(trace.a.is_empty() && trace.b.is_empty() && trace.c.is_empty());
+        assert_eq!(trace.a.capacity(), 0);
+    }
+
+    #[test]
+    fn test_serialize_keys_and_trace() {
+        let mut circuit = PlonkCircuit::init();
+        let pi = circuit.public_input();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(pi, x_sq).unwrap();
+        circuit.pad_next_power_of_two();
+        let (_, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let mut bytes = Vec::new();
+        verifier_key.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 3 * 8 + 48 + 2 * 96 + 15 * 48);
+        let decoded = VerifierKey::deserialize(bytes.as_slice()).unwrap();
+        let mut encoded = Vec::new();
+        decoded.serialize(&mut encoded).unwrap();
+        assert_eq!(encoded, bytes);
+        assert_eq!(decoded.domain.generator, verifier_key.domain.generator);
+        assert!(matches!(
+            VerifierKey::deserialize(&bytes[..bytes.len() - 1]),
+            Err(PlonkError::Io(_))
+        ));
+        let mut invalid_row = bytes;
+        invalid_row[16..24].copy_from_slice(&2u64.to_le_bytes());
+        assert_eq!(
+            VerifierKey::deserialize(invalid_row.as_slice()).err(),
+            Some(PlonkError::WireOutOfBounds {
+                wire: 2,
+                nr_wires: 2
+            })
+        );
+
+        let mut trace = ComputationTrace::new(&circuit);
+        trace.set(x_l, Scalar::from(3));
+        let mut bytes = Vec::new();
+        trace.serialize(&mut bytes).unwrap();
+        let decoded = ComputationTrace::deserialize(bytes.as_slice()).unwrap();
+        assert_eq!(
+            (decoded.a, decoded.b, decoded.c),
+            (trace.a, trace.b, trace.c)
+        );
+        // The column b is one value shorter than a.
+        let mut short = Vec::new();
+        write_scalars(&mut short, &[Scalar::one(); 2]).unwrap();
+        write_scalars(&mut short, &[Scalar::one()]).unwrap();
+        write_scalars(&mut short, &[Scalar::one(); 2]).unwrap();
+        assert_eq!(
+            ComputationTrace::deserialize(short.as_slice()).err(),
+            Some(PlonkError::InvalidLength {
+                expected: 2,
+                got: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_circuit() {
+        let mut circuit = PlonkCircuit::init();
+        let pi = circuit.public_input();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        circuit.assert_constant(x_l, Scalar::from(3)).unwrap();
+        circuit.xor(x_l, x_sq, 1).unwrap();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(pi, x_sq).unwrap();
+        circuit.label(x_sq, "x\"squared\"");
+        circuit.label_gate(x_sq.row, "square");
+        circuit.named_tables.insert("xor 1".to_string(), 1);
+
+        let json = circuit.to_json();
+        assert!(json.starts_with(
+            r#"{"nr_constraints":4,"gates":[{"ql":"0x1","qr":"0x0","qo":"0x0","qm":"0x0","qc":"0x0","qk":"0x0","qt":"0x0"},"#
+        ));
+        assert!(json.contains(r#""public_inputs":[0],"constants":[{"row":2,"value":"0x3"}]"#));
+        assert!(json
+            .contains(r#"[{"row":0,"column":"a"},{"row":3,"column":"b"},{"row":1,"column":"c"}]"#));
+
+        let decoded = PlonkCircuit::from_json(&json).unwrap();
+        assert_eq!(decoded.permutations, circuit.permutations);
+        assert_eq!(decoded.constraints.qc, circuit.constraints.qc);
+        assert_eq!(decoded.constraints.qt, circuit.constraints.qt);
+        assert_eq!(decoded.public_inputs, circuit.public_inputs);
+        assert_eq!(decoded.constants, circuit.constants);
+        assert_eq!(decoded.lookup_tables, circuit.lookup_tables);
+        assert_eq!(decoded.named_tables, circuit.named_tables);
+        assert_eq!(decoded.labels, circuit.labels);
+        assert_eq!(decoded.gate_labels, circuit.gate_labels);
+        assert_eq!(decoded.to_json(), json);
+
+        let wrong_row = json.replace(r#"{"row":3,"column":"b"}"#, r#"{"row":4,"column":"b"}"#);
+        assert_eq!(
+            PlonkCircuit::from_json(&wrong_row).err(),
+            Some(PlonkError::UnknownWire(Wire::right(4)))
+        );
+        let wrong_scalar = json.replacen("0x1", "0x1g", 1);
+        assert_eq!(
+            PlonkCircuit::from_json(&wrong_scalar).err(),
+            Some(PlonkError::InvalidScalar)
+        );
+        assert!(matches!(
+            PlonkCircuit::from_json(&json.replace("\"gates\"", "\"rows\"")),
+            Err(PlonkError::InvalidJson(_))
+        ));
     }
 }
@@ -1,18 +1,28 @@
+use crate::error::PlonkError;
+use crate::parallel;
 use crate::*;
+use alloc::vec;
+use alloc::vec::Vec;
 use blstrs::Scalar;
-use ff::Field;
-use std::cmp::{max, min};
-use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub};
+use core::cmp::{max, min};
+use core::ops::{Add, AddAssign, Div, Index, Mul, MulAssign, Sub};
+use ff::PrimeField;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
 
 // Polynomial written as p(x) = a0 + x * a1 + .. + x^{MAX_DEGREE} * a_{MAX_DEGREE}, where we always pad with zeroes.
+// Polynomials are generic over the field of their coefficients, so that they can be tested
+// over small fields, but the rest of the crate uses the scalar field of BLS12-381.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Polynomial(pub(crate) Vec<Scalar>);
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Polynomial<F = Scalar>(pub(crate) Vec<F>);
 
-impl Polynomial {
+impl<F: PrimeField> Polynomial<F> {
     /// Evaluate a polynomial
-    pub fn eval(&self, value: &Scalar) -> Scalar {
-        let mut result = Scalar::zero();
-        let mut power = Scalar::one();
+    pub fn eval(&self, value: &F) -> F {
+        let mut result = F::zero();
+        let mut power = F::one();
         for coefficient in self.0.iter() {
             result += power * coefficient;
             power *= value;
@@ -23,13 +33,13 @@ impl Polynomial {
 
     /// Create the zero polynomial
     pub fn zero(degree: usize) -> Self {
-        Self(vec![Scalar::zero(); degree])
+        Self(vec![F::zero(); degree])
     }
 
     /// Scale by constant. i.e. compute f(X * c)
-    pub fn scale(&self, val: Scalar) -> Self {
+    pub fn scale(&self, val: F) -> Self {
         let mut result = self.clone();
-        let mut power = Scalar::one();
+        let mut power = F::one();
         for coeff in result.0.iter_mut() {
             *coeff *= power;
             power *= val; // unnecessary mult at end, but well, who cares?
@@ -40,19 +50,103 @@ impl Polynomial {
     pub fn remove_zeros(&mut self) {
         let mut cut = 0;
         for &coeff in self.0.iter().rev() {
-            if coeff != Scalar::zero() {
+            if coeff != F::zero() {
                 break;
             }
             cut += 1;
         }
         self.0 = self.0[..self.0.len() - cut].to_vec();
     }
+
+    /// Vanishing polynomial of a set of points, i.e. the product of (X - point).
+    pub fn vanishing(points: &[F]) -> Self {
+        let mut result = Polynomial(vec![F::one()]);
+        for point in points {
+            result *= &Polynomial(vec![point.neg(), F::one()]);
+        }
+        result
+    }
+
+    /// Long division by an arbitrary non-zero polynomial. Returns the quotient and the
+    /// remainder, so that self = quotient * divisor + remainder.
+    pub fn divide_with_remainder(&self, divisor: &Polynomial<F>) -> (Polynomial<F>, Polynomial<F>) {
+        let mut divisor = divisor.clone();
+        divisor.remove_zeros();
+        assert!(!divisor.0.is_empty(), "division by the zero polynomial");
+
+        let mut remainder = self.clone();
+        remainder.remove_zeros();
+        if remainder.0.len() < divisor.0.len() {
+            return (Polynomial(vec![]), remainder);
+        }
+
+        let leading_inv = divisor.0[divisor.0.len() - 1].invert().unwrap();
+        let mut quotient = Polynomial::zero(remainder.0.len() - divisor.0.len() + 1);
+        for i in (0..quotient.0.len()).rev() {
+            let coefficient = remainder.0[i + divisor.0.len() - 1] * leading_inv;
+            for (j, divisor_coeff) in divisor.0.iter().enumerate() {
+                remainder.0[i + j] -= coefficient * divisor_coeff;
+            }
+            quotient.0[i] = coefficient;
+        }
+        remainder.remove_zeros();
+
+        (quotient, remainder)
+    }
+
+    /// Division by the vanishing polynomial X^n - 1 of a domain of size n, in linear time. The
+    /// coefficient of X^(i + n) of the quotient is the one of X^(i + n) of `self` plus the one
+    /// of X^(i + 2n) of the quotient, and the low coefficients of `self` plus the ones of the
+    /// quotient form the remainder. Fails if the remainder is not zero.
+    pub fn divide_by_vanishing(&self, n: usize) -> Result<Polynomial<F>, PlonkError> {
+        assert!(n > 0, "division by the zero polynomial");
+        if self.0.len() <= n {
+            return match self.0.iter().all(|coeff| *coeff == F::zero()) {
+                true => Ok(Polynomial(vec![])),
+                false => Err(PlonkError::NotDivisible),
+            };
+        }
+
+        let mut quotient = Polynomial::zero(self.0.len() - n);
+        for i in (0..quotient.0.len()).rev() {
+            quotient.0[i] = self.0[i + n];
+            if i + n < quotient.0.len() {
+                let carry = quotient.0[i + n];
+                quotient.0[i] += carry;
+            }
+        }
+        if (0..n).any(|i| self.0[i] + quotient.0.get(i).copied().unwrap_or(F::zero()) != F::zero())
+        {
+            return Err(PlonkError::NotDivisible);
+        }
+
+        Ok(quotient)
+    }
 }
 
-impl<'a, 'b> Add<&'b Polynomial> for &'a Polynomial {
-    type Output = Polynomial;
+/// Overwrites the values with zeros, in a way that the compiler does not optimise away, and
+/// releases their memory. This is what `Zeroize` does for vectors of types that it supports,
+/// which excludes the scalars of other crates.
+pub(crate) fn zeroize_scalars<F: PrimeField>(values: &mut Vec<F>) {
+    for value in values.iter_mut() {
+        // Safety: `value` is a valid, aligned and exclusive reference.
+        unsafe { core::ptr::write_volatile(value, F::zero()) };
+    }
+    core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    values.clear();
+    values.shrink_to_fit();
+}
+
+impl<F: PrimeField> Zeroize for Polynomial<F> {
+    fn zeroize(&mut self) {
+        zeroize_scalars(&mut self.0);
+    }
+}
+
+impl<'b, F: PrimeField> Add<&'b Polynomial<F>> for &Polynomial<F> {
+    type Output = Polynomial<F>;
 
-    fn add(self, rhs: &'b Polynomial) -> Self::Output {
+    fn add(self, rhs: &'b Polynomial<F>) -> Self::Output {
         let min_degree = min(self.0.len(), rhs.0.len());
         let mut result = if self.0.len() > rhs.0.len() {
             self.clone()
@@ -67,99 +161,104 @@ impl<'a, 'b> Add<&'b Polynomial> for &'a Polynomial {
     }
 }
 
-define_add_variants!(LHS = Polynomial, RHS = Polynomial, Output = Polynomial);
+define_add_variants!(Generic = F: PrimeField, LHS = Polynomial<F>, RHS = Polynomial<F>, Output = Polynomial<F>);
 
-impl<'a, 'b> Sub<&'b Polynomial> for &'a Polynomial {
-    type Output = Polynomial;
+impl<'b, F: PrimeField> Sub<&'b Polynomial<F>> for &Polynomial<F> {
+    type Output = Polynomial<F>;
 
-    fn sub(self, rhs: &'b Polynomial) -> Self::Output {
+    fn sub(self, rhs: &'b Polynomial<F>) -> Self::Output {
         let max_degree = max(self.0.len(), rhs.0.len());
         let mut result = if self.0.len() > rhs.0.len() {
             self.clone()
         } else {
             let diff_deg = rhs.0.len() - self.0.len();
             let mut coeffs = self.0.clone();
-            coeffs.extend_from_slice(&vec![Scalar::zero(); diff_deg]);
+            coeffs.extend_from_slice(&vec![F::zero(); diff_deg]);
             Polynomial(coeffs)
         };
         for index in 0..max_degree {
-            result.0[index] = self.0.get(index).unwrap_or(&Scalar::zero())
-                - rhs.0.get(index).unwrap_or(&Scalar::zero())
+            result.0[index] =
+                *self.0.get(index).unwrap_or(&F::zero()) - rhs.0.get(index).unwrap_or(&F::zero())
         }
 
         result
     }
 }
 
-define_sub_variants!(LHS = Polynomial, RHS = Polynomial, Output = Polynomial);
+define_sub_variants!(Generic = F: PrimeField, LHS = Polynomial<F>, RHS = Polynomial<F>, Output = Polynomial<F>);
 
-impl<'b> AddAssign<&'b Polynomial> for Polynomial {
+impl<'b, F: PrimeField> AddAssign<&'b Polynomial<F>> for Polynomial<F> {
     fn add_assign(&mut self, rhs: &'b Self) {
         *self = self.clone() + rhs;
     }
 }
 
-define_add_assign_variants!(LHS = Polynomial, RHS = Polynomial);
+define_add_assign_variants!(Generic = F: PrimeField, LHS = Polynomial<F>, RHS = Polynomial<F>);
 
-impl<'a, 'b> Add<&'b Scalar> for &'a Polynomial {
-    type Output = Polynomial;
+impl<'b, F: PrimeField> Add<&'b F> for &Polynomial<F> {
+    type Output = Polynomial<F>;
 
-    fn add(self, rhs: &'b Scalar) -> Self::Output {
+    fn add(self, rhs: &'b F) -> Self::Output {
         let mut vec = self.0.clone();
         vec[0] += rhs;
         Polynomial(vec)
     }
 }
 
-define_add_variants!(LHS = Polynomial, RHS = Scalar, Output = Polynomial);
+define_add_variants!(Generic = F: PrimeField, LHS = Polynomial<F>, RHS = F, Output = Polynomial<F>);
 
-impl<'a, 'b> Mul<&'b Scalar> for &'a Polynomial {
-    type Output = Polynomial;
+impl<'b, F: PrimeField> Mul<&'b F> for &Polynomial<F> {
+    type Output = Polynomial<F>;
 
-    fn mul(self, rhs: &'b Scalar) -> Self::Output {
-        Polynomial(
-            self.0
-                .iter()
-                .map(|coeff| coeff * rhs)
-                .collect::<Vec<Scalar>>(),
-        )
+    fn mul(self, rhs: &'b F) -> Self::Output {
+        Polynomial(self.0.iter().map(|coeff| *coeff * rhs).collect::<Vec<F>>())
     }
 }
 
-define_mul_variants!(LHS = Polynomial, RHS = Scalar, Output = Polynomial);
+define_mul_variants!(Generic = F: PrimeField, LHS = Polynomial<F>, RHS = F, Output = Polynomial<F>);
 
-impl<'a, 'b> Mul<&'b Polynomial> for &'a Polynomial {
-    type Output = Polynomial;
+impl<'b, F: PrimeField> Mul<&'b Polynomial<F>> for &Polynomial<F> {
+    type Output = Polynomial<F>;
 
-    fn mul(self, rhs: &'b Polynomial) -> Self::Output {
-        let mut out = vec![Scalar::zero(); self.0.len() + rhs.0.len() - 1];
-        for (idx_a, coeff_a) in self.0.iter().enumerate() {
-            for (idx_b, coeff_b) in rhs.0.iter().enumerate() {
-                out[idx_a + idx_b] += coeff_a * coeff_b;
-            }
+    fn mul(self, rhs: &'b Polynomial<F>) -> Self::Output {
+        // Each coefficient of the product is computed on its own, so that they can be computed
+        // in parallel.
+        let mut out = vec![F::zero(); self.0.len() + rhs.0.len() - 1];
+        if self.0.is_empty() || rhs.0.is_empty() {
+            return Polynomial(out);
         }
+        let chunk_size = out.len().div_ceil(parallel::threads());
+        parallel::for_each_chunk_mut(&mut out, chunk_size, |index, chunk| {
+            for (i, coeff) in chunk.iter_mut().enumerate() {
+                let idx = index * chunk_size + i;
+                let start = idx.saturating_sub(rhs.0.len() - 1);
+                for idx_a in start..self.0.len().min(idx + 1) {
+                    *coeff += self.0[idx_a] * rhs.0[idx - idx_a];
+                }
+            }
+        });
 
         Polynomial(out)
     }
 }
 
-define_mul_variants!(LHS = Polynomial, RHS = Polynomial, Output = Polynomial);
+define_mul_variants!(Generic = F: PrimeField, LHS = Polynomial<F>, RHS = Polynomial<F>, Output = Polynomial<F>);
 
-impl<'b> MulAssign<&'b Polynomial> for Polynomial {
+impl<'b, F: PrimeField> MulAssign<&'b Polynomial<F>> for Polynomial<F> {
     fn mul_assign(&mut self, rhs: &'b Self) {
         *self = &self.clone() * rhs;
     }
 }
 
-define_mul_assign_variants!(LHS = Polynomial, RHS = Polynomial);
+define_mul_assign_variants!(Generic = F: PrimeField, LHS = Polynomial<F>, RHS = Polynomial<F>);
 
 // The following division algorithm is not generic. It's a simplification given that we
 // know we are only going to use this with a monomial of the form X^n - 1 as a denominator
 // which is a divisor.
-impl Div<Polynomial> for Polynomial {
-    type Output = Polynomial;
+impl<F: PrimeField> Div<Polynomial<F>> for Polynomial<F> {
+    type Output = Polynomial<F>;
 
-    fn div(self, rhs: Polynomial) -> Self::Output {
+    fn div(self, rhs: Polynomial<F>) -> Self::Output {
         let mut copy_rhs = rhs.clone();
         let mut copy_self = self.clone();
         copy_rhs.remove_zeros();
@@ -167,11 +266,11 @@ impl Div<Polynomial> for Polynomial {
 
         assert_eq!(
             copy_rhs.0[copy_rhs.0.len() - 1],
-            Scalar::one(),
+            F::one(),
             "unexpected denominator"
         );
 
-        let mut result = Polynomial::zero(copy_self.0.len());
+        let mut result = Polynomial::<F>::zero(copy_self.0.len());
         for i in (1..copy_self.0.len()).rev() {
             result.0[i - (copy_rhs.0.len() - 1)] = self.0[i] + -(result.0[i] * rhs.0[0]);
 
@@ -185,24 +284,129 @@ impl Div<Polynomial> for Polynomial {
     }
 }
 
+// Polynomial represented by its non-zero terms (degree, coefficient), sorted by degree. This is
+// the representation of the vanishing polynomial X^n - 1 of the domain, which only has two
+// terms, so that multiplying or dividing by it is linear in the size of the dense operand.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SparsePolynomial<F = Scalar>(pub(crate) Vec<(usize, F)>);
+
+impl<F: PrimeField> SparsePolynomial<F> {
+    /// Creates the polynomial from its terms, adding up the coefficients of repeated degrees.
+    pub fn new(mut terms: Vec<(usize, F)>) -> Self {
+        terms.sort_by_key(|(degree, _)| *degree);
+        let mut merged: Vec<(usize, F)> = Vec::with_capacity(terms.len());
+        for (degree, coefficient) in terms {
+            match merged.last_mut() {
+                Some(last) if last.0 == degree => last.1 += coefficient,
+                _ => merged.push((degree, coefficient)),
+            }
+        }
+        merged.retain(|(_, coefficient)| *coefficient != F::zero());
+        Self(merged)
+    }
+
+    /// Vanishing polynomial X^n - 1 of the domain of size n.
+    pub fn vanishing(n: usize) -> Self {
+        Self::new(vec![(0, F::one().neg()), (n, F::one())])
+    }
+
+    /// Degree of the polynomial, which is zero for the zero polynomial.
+    pub fn degree(&self) -> usize {
+        self.0.last().map_or(0, |(degree, _)| *degree)
+    }
+
+    pub fn eval(&self, value: &F) -> F {
+        self.0.iter().fold(F::zero(), |acc, (degree, coefficient)| {
+            acc + value.pow_vartime([*degree as u64]) * coefficient
+        })
+    }
+
+    pub fn to_dense(&self) -> Polynomial<F> {
+        let mut dense = Polynomial::zero(self.0.last().map_or(0, |(degree, _)| degree + 1));
+        for (degree, coefficient) in self.0.iter() {
+            dense.0[*degree] = *coefficient;
+        }
+        dense
+    }
+}
+
+impl<'b, F: PrimeField> Mul<&'b SparsePolynomial<F>> for &Polynomial<F> {
+    type Output = Polynomial<F>;
+
+    fn mul(self, rhs: &'b SparsePolynomial<F>) -> Self::Output {
+        if self.0.is_empty() || rhs.0.is_empty() {
+            return Polynomial(vec![]);
+        }
+        let mut out = Polynomial::zero(self.0.len() + rhs.degree());
+        for (degree, coefficient) in rhs.0.iter() {
+            for (out_coeff, coeff) in out.0[*degree..].iter_mut().zip(self.0.iter()) {
+                *out_coeff += *coeff * coefficient;
+            }
+        }
+
+        out
+    }
+}
+
+define_mul_variants!(Generic = F: PrimeField, LHS = Polynomial<F>, RHS = SparsePolynomial<F>, Output = Polynomial<F>);
+
+impl<F: PrimeField> Polynomial<F> {
+    /// Long division by a sparse non-zero polynomial, with a cost linear in the size of `self`
+    /// for each term of the divisor. Returns the quotient and the remainder.
+    pub fn divide_by_sparse(
+        &self,
+        divisor: &SparsePolynomial<F>,
+    ) -> (Polynomial<F>, Polynomial<F>) {
+        let (leading_degree, leading) = *divisor.0.last().expect("division by the zero polynomial");
+        let mut remainder = self.clone();
+        remainder.remove_zeros();
+        if remainder.0.len() <= leading_degree {
+            return (Polynomial(vec![]), remainder);
+        }
+
+        let leading_inv = leading.invert().unwrap();
+        let mut quotient = Polynomial::zero(remainder.0.len() - leading_degree);
+        for i in (0..quotient.0.len()).rev() {
+            let coefficient = remainder.0[i + leading_degree] * leading_inv;
+            for (degree, divisor_coeff) in divisor.0.iter() {
+                remainder.0[i + degree] -= coefficient * divisor_coeff;
+            }
+            quotient.0[i] = coefficient;
+        }
+        remainder.remove_zeros();
+
+        (quotient, remainder)
+    }
+}
+
 // Polynomial represented as Evaluation points (I don't think we'll implement
 // the Fast Fourier Transform, as the goal here is not efficiency, but rather
 // simplicity).
 // We are assuming multiplicative subgroup of order `n`, meaning that each power
 // of the generator `g` will be different.
-pub struct PolynomialEvaluationPoints(pub(crate) Vec<(Scalar, Scalar)>);
+pub struct PolynomialEvaluationPoints<F = Scalar>(pub(crate) Vec<(F, F)>);
+
+impl<F: PrimeField> PolynomialEvaluationPoints<F> {
+    pub fn interpolate(&self) -> Polynomial<F> {
+        // The denominators prod_{j != i} (x_i - x_j) of the lagrange basis are inverted at once.
+        let mut denominators = (0..self.0.len())
+            .map(|i| {
+                (0..self.0.len())
+                    .filter(|j| *j != i)
+                    .fold(F::one(), |acc, j| acc * (self.0[i].0 - self.0[j].0))
+            })
+            .collect::<Vec<F>>();
+        batch_invert(&mut denominators).expect("repeated evaluation points");
 
-impl PolynomialEvaluationPoints {
-    pub fn interpolate(&self) -> Polynomial {
         let mut polynomial = Polynomial::zero(self.0.len());
-        for i in 0..self.0.len() {
-            let mut lb = Polynomial(vec![Scalar::from(1)]);
+        for (i, denominator_inv) in denominators.iter().enumerate() {
+            let mut lb = Polynomial(vec![*denominator_inv]);
             for j in 0..self.0.len() {
                 if i == j {
                     continue;
                 }
-                lb *= &Polynomial(vec![self.0[j].0.neg(), Scalar::one()])
-                    * (self.0[i].0 - self.0[j].0).invert().unwrap();
+                lb *= &Polynomial(vec![self.0[j].0.neg(), F::one()]);
             }
 
             polynomial += &lb * self.0[i].1;
@@ -212,9 +416,516 @@ impl PolynomialEvaluationPoints {
     }
 }
 
+// Multiplicative subgroup H of order `size` (which must be a power of two), generated by
+// an n-th root of unity omega. This allows us to move between coefficient form and evaluations
+// over H using radix-2 FFTs in O(n log n), instead of the O(n^2) interpolation above.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EvaluationDomain<F = Scalar> {
+    pub(crate) size: usize,
+    pub(crate) generator: F,
+    pub(crate) generator_inv: F,
+    pub(crate) size_inv: F,
+}
+
+impl<F: PrimeField> EvaluationDomain<F> {
+    /// Create the domain of order `size`.
+    pub fn new(size: usize) -> Result<Self, PlonkError> {
+        if !size.is_power_of_two() || size.trailing_zeros() > F::S {
+            return Err(PlonkError::InvalidDomainSize(size));
+        }
+        let log_size = size.trailing_zeros();
+
+        // We compute the root of unity out of the 2^S-th root of unity, g, which is provided as
+        // a constant of the field (S = 32 for BLS12-381). We do so by calculating
+        // omega = g^{2^S / n}.
+        let generator = F::root_of_unity().pow_vartime([1u64 << (F::S - log_size)]);
+        assert_eq!(generator.pow_vartime([size as u64]), F::one());
+
+        Ok(Self {
+            size,
+            generator,
+            generator_inv: generator.invert().unwrap(),
+            size_inv: F::from(size as u64).invert().unwrap(),
+        })
+    }
+
+    /// Elements of the domain, 1, omega, omega^2, ..., omega^{n-1}.
+    pub fn elements(&self) -> Vec<F> {
+        let mut elements = Vec::with_capacity(self.size);
+        let mut power = F::one();
+        for _ in 0..self.size {
+            elements.push(power);
+            power *= self.generator;
+        }
+        elements
+    }
+
+    /// Evaluate `poly` over all elements of the domain.
+    pub fn fft(&self, poly: &Polynomial<F>) -> Vec<F> {
+        assert!(
+            poly.0.len() <= self.size,
+            "Polynomial too large for the domain"
+        );
+        let mut values = poly.0.clone();
+        values.resize(self.size, F::zero());
+        fft_in_place(&mut values, &self.generator);
+        values
+    }
+
+    /// Interpolate the polynomial that takes the values `evals` over the elements of the domain.
+    pub fn ifft(&self, evals: &[F]) -> Polynomial<F> {
+        assert_eq!(
+            evals.len(),
+            self.size,
+            "Expected {} evaluations, got {}",
+            self.size,
+            evals.len()
+        );
+        let mut values = evals.to_vec();
+        fft_in_place(&mut values, &self.generator_inv);
+        for value in values.iter_mut() {
+            *value *= self.size_inv;
+        }
+        Polynomial(values)
+    }
+
+    /// Evaluate `poly` over the coset g * H, where g is the multiplicative generator of the
+    /// field. As g is not in H, the vanishing polynomial of H is never zero over the coset.
+    pub fn coset_fft(&self, poly: &Polynomial<F>) -> Vec<F> {
+        self.fft(&poly.scale(F::multiplicative_generator()))
+    }
+
+    /// Interpolate the polynomial that takes the values `evals` over the coset g * H.
+    pub fn coset_ifft(&self, evals: &[F]) -> Polynomial<F> {
+        self.ifft(evals)
+            .scale(F::multiplicative_generator().invert().unwrap())
+    }
+
+    /// Evaluate `poly` over the coset shift * H. Larger polynomials than the domain are first
+    /// reduced modulo X^n - shift^n, which does not change their values over the coset, so that
+    /// a polynomial of any degree can be evaluated over cosets of a small domain.
+    pub fn shifted_fft(&self, poly: &Polynomial<F>, shift: &F) -> Vec<F> {
+        let mut values = vec![F::zero(); self.size];
+        let mut power = F::one();
+        for (i, coeff) in poly.0.iter().enumerate() {
+            values[i % self.size] += *coeff * power;
+            power *= shift;
+        }
+        fft_in_place(&mut values, &self.generator);
+        values
+    }
+
+    /// Elements of the coset g * H, in the same order as the evaluations of `coset_fft`.
+    pub fn coset_elements(&self) -> Vec<F> {
+        let shift = F::multiplicative_generator();
+        self.elements()
+            .iter()
+            .map(|element| *element * shift)
+            .collect()
+    }
+
+    /// Lagrange polynomial which is one at omega^index and zero in the rest of the domain.
+    pub fn lagrange_basis(&self, index: usize) -> Polynomial<F> {
+        let mut evals = vec![F::zero(); self.size];
+        evals[index] = F::one();
+        self.ifft(&evals)
+    }
+
+    /// Evaluates the lagrange polynomial of omega^index at `point` with the barycentric formula
+    /// L_i(X) = omega^i (X^n - 1) / (n (X - omega^i)), so that it does not need to be
+    /// interpolated.
+    pub fn evaluate_lagrange(&self, index: usize, point: &F) -> F {
+        let root = self.generator.pow_vartime([index as u64]);
+        if *point == root {
+            return F::one();
+        }
+        root * (point.pow_vartime([self.size as u64]) - F::one())
+            * self.size_inv
+            * (*point - root).invert().unwrap()
+    }
+}
+
+// Polynomial represented by its evaluations over a domain H, or over a coset shift * H. Adding
+// and multiplying polynomials in this form is pointwise, so it takes linear time, as long as
+// the result fits the domain (the product of two polynomials of degree n needs a domain of
+// size at least 2n + 1).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Evaluations<F = Scalar> {
+    pub(crate) evals: Vec<F>,
+    pub(crate) domain: EvaluationDomain<F>,
+    pub(crate) shift: F,
+}
+
+impl<F: PrimeField> Evaluations<F> {
+    /// Polynomial with the values `evals` over the elements of the domain.
+    pub fn new(evals: Vec<F>, domain: &EvaluationDomain<F>) -> Self {
+        assert_eq!(
+            evals.len(),
+            domain.size,
+            "Expected {} evaluations, got {}",
+            domain.size,
+            evals.len()
+        );
+        Self {
+            evals,
+            domain: domain.clone(),
+            shift: F::one(),
+        }
+    }
+
+    /// Evaluations of `poly` over the elements of the domain.
+    pub fn from_polynomial(poly: &Polynomial<F>, domain: &EvaluationDomain<F>) -> Self {
+        Self {
+            evals: domain.fft(poly),
+            domain: domain.clone(),
+            shift: F::one(),
+        }
+    }
+
+    /// Evaluations of `poly` over the coset g * H of the domain.
+    pub fn from_polynomial_coset(poly: &Polynomial<F>, domain: &EvaluationDomain<F>) -> Self {
+        Self {
+            evals: domain.coset_fft(poly),
+            domain: domain.clone(),
+            shift: F::multiplicative_generator(),
+        }
+    }
+
+    /// Evaluations of `poly` over the coset shift * H of the domain, where `poly` may be larger
+    /// than the domain (see `EvaluationDomain::shifted_fft`).
+    pub fn from_polynomial_shifted(
+        poly: &Polynomial<F>,
+        domain: &EvaluationDomain<F>,
+        shift: &F,
+    ) -> Self {
+        Self {
+            evals: domain.shifted_fft(poly, shift),
+            domain: domain.clone(),
+            shift: *shift,
+        }
+    }
+
+    /// Polynomial in coefficient form.
+    pub fn interpolate(&self) -> Polynomial<F> {
+        self.domain
+            .ifft(&self.evals)
+            .scale(self.shift.invert().unwrap())
+    }
+
+    // Applies `f` pointwise to the evaluations of both polynomials.
+    fn zip_with(&self, rhs: &Self, f: impl Fn(F, F) -> F) -> Self {
+        assert!(
+            self.domain == rhs.domain && self.shift == rhs.shift,
+            "Evaluations over different domains"
+        );
+        Self {
+            evals: self
+                .evals
+                .iter()
+                .zip(rhs.evals.iter())
+                .map(|(lhs, rhs)| f(*lhs, *rhs))
+                .collect(),
+            domain: self.domain.clone(),
+            shift: self.shift,
+        }
+    }
+}
+
+impl<F: PrimeField> Zeroize for Evaluations<F> {
+    fn zeroize(&mut self) {
+        zeroize_scalars(&mut self.evals);
+    }
+}
+
+impl<F: PrimeField> Index<usize> for Evaluations<F> {
+    type Output = F;
+
+    fn index(&self, index: usize) -> &F {
+        &self.evals[index]
+    }
+}
+
+impl<'b, F: PrimeField> Add<&'b Evaluations<F>> for &Evaluations<F> {
+    type Output = Evaluations<F>;
+
+    fn add(self, rhs: &'b Evaluations<F>) -> Self::Output {
+        self.zip_with(rhs, |lhs, rhs| lhs + rhs)
+    }
+}
+
+define_add_variants!(Generic = F: PrimeField, LHS = Evaluations<F>, RHS = Evaluations<F>, Output = Evaluations<F>);
+
+impl<'b, F: PrimeField> Sub<&'b Evaluations<F>> for &Evaluations<F> {
+    type Output = Evaluations<F>;
+
+    fn sub(self, rhs: &'b Evaluations<F>) -> Self::Output {
+        self.zip_with(rhs, |lhs, rhs| lhs - rhs)
+    }
+}
+
+define_sub_variants!(Generic = F: PrimeField, LHS = Evaluations<F>, RHS = Evaluations<F>, Output = Evaluations<F>);
+
+impl<'b, F: PrimeField> Mul<&'b Evaluations<F>> for &Evaluations<F> {
+    type Output = Evaluations<F>;
+
+    fn mul(self, rhs: &'b Evaluations<F>) -> Self::Output {
+        self.zip_with(rhs, |lhs, rhs| lhs * rhs)
+    }
+}
+
+define_mul_variants!(Generic = F: PrimeField, LHS = Evaluations<F>, RHS = Evaluations<F>, Output = Evaluations<F>);
+
+impl<'b, F: PrimeField> Mul<&'b F> for &Evaluations<F> {
+    type Output = Evaluations<F>;
+
+    fn mul(self, rhs: &'b F) -> Self::Output {
+        Evaluations {
+            evals: self.evals.iter().map(|eval| *eval * rhs).collect(),
+            domain: self.domain.clone(),
+            shift: self.shift,
+        }
+    }
+}
+
+define_mul_variants!(Generic = F: PrimeField, LHS = Evaluations<F>, RHS = F, Output = Evaluations<F>);
+
+/// Inverts all the values with a single field inversion (Montgomery's trick): we keep the
+/// prefix products v_0 * ... * v_{i-1}, invert the product of all values, and walk back
+/// peeling off one value at a time. Fails without modifying the values if one of them is zero.
+pub fn batch_invert<F: PrimeField>(values: &mut [F]) -> Result<(), PlonkError> {
+    let mut prefixes = Vec::with_capacity(values.len());
+    let mut product = F::one();
+    for value in values.iter() {
+        prefixes.push(product);
+        product *= value;
+    }
+
+    let mut inverse = Option::<F>::from(product.invert()).ok_or(PlonkError::NonInvertible)?;
+    for (value, prefix) in values.iter_mut().zip(prefixes).rev() {
+        let next = inverse * *value;
+        *value = inverse * prefix;
+        inverse = next;
+    }
+
+    Ok(())
+}
+
+// Iterative Cooley-Tukey FFT. The length of `values` must be the order of `omega`. The values
+// may be the elements of any group on which the field acts, which lets us move the SRS to the
+// Lagrange basis.
+pub(crate) fn fft_in_place<F, G>(values: &mut [G], omega: &F)
+where
+    F: PrimeField,
+    G: Copy + Send + Sync + AddAssign + Sub<Output = G> + Mul<F, Output = G>,
+{
+    let n = values.len();
+    let log_n = n.trailing_zeros();
+
+    for k in 0..n {
+        let rk = bitreverse(k, log_n);
+        if k < rk {
+            values.swap(k, rk);
+        }
+    }
+
+    let mut m = 1;
+    while m < n {
+        let omega_m = omega.pow_vartime([(n / (2 * m)) as u64]);
+        parallel::for_each_chunk_mut(values, 2 * m, |_, chunk| {
+            let (low, high) = chunk.split_at_mut(m);
+            let mut w = F::one();
+            for (low, high) in low.iter_mut().zip(high.iter_mut()) {
+                let t = *high * w;
+                *high = *low - t;
+                *low += t;
+                w *= omega_m;
+            }
+        });
+        m *= 2;
+    }
+}
+
+fn bitreverse(mut n: usize, bits: u32) -> usize {
+    let mut result = 0;
+    for _ in 0..bits {
+        result = (result << 1) | (n & 1);
+        n >>= 1;
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ff::Field;
+    use rand_core::RngCore;
+    use std::ops::{Neg, SubAssign};
+    use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+    // Field of order 97, small enough to check results by hand. 97 - 1 = 2^5 * 3, and 5
+    // generates the multiplicative group, so 5^3 = 28 is a 2^5-th root of unity.
+    #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+    struct F97(u64);
+
+    const P: u64 = 97;
+
+    impl From<u64> for F97 {
+        fn from(value: u64) -> Self {
+            F97(value % P)
+        }
+    }
+
+    impl ConditionallySelectable for F97 {
+        fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+            F97(u64::conditional_select(&a.0, &b.0, choice))
+        }
+    }
+
+    impl ConstantTimeEq for F97 {
+        fn ct_eq(&self, other: &Self) -> Choice {
+            self.0.ct_eq(&other.0)
+        }
+    }
+
+    impl Neg for F97 {
+        type Output = F97;
+
+        fn neg(self) -> F97 {
+            F97((P - self.0) % P)
+        }
+    }
+
+    macro_rules! f97_operation {
+        ($trait:ident, $method:ident, $assign:ident, $assign_method:ident, $op:expr) => {
+            impl $trait for F97 {
+                type Output = F97;
+
+                #[allow(clippy::suspicious_arithmetic_impl)]
+                fn $method(self, rhs: F97) -> F97 {
+                    F97($op(self.0, rhs.0) % P)
+                }
+            }
+
+            impl<'a> $trait<&'a F97> for F97 {
+                type Output = F97;
+
+                fn $method(self, rhs: &'a F97) -> F97 {
+                    self.$method(*rhs)
+                }
+            }
+
+            impl $assign for F97 {
+                fn $assign_method(&mut self, rhs: F97) {
+                    *self = self.$method(rhs);
+                }
+            }
+
+            impl<'a> $assign<&'a F97> for F97 {
+                fn $assign_method(&mut self, rhs: &'a F97) {
+                    *self = self.$method(*rhs);
+                }
+            }
+        };
+    }
+
+    f97_operation!(Add, add, AddAssign, add_assign, |a, b| a + b);
+    f97_operation!(Sub, sub, SubAssign, sub_assign, |a, b| a + P - b);
+    f97_operation!(Mul, mul, MulAssign, mul_assign, |a, b| a * b);
+
+    impl Field for F97 {
+        fn random(mut rng: impl RngCore) -> Self {
+            F97::from(rng.next_u64())
+        }
+
+        fn zero() -> Self {
+            F97(0)
+        }
+
+        fn one() -> Self {
+            F97(1)
+        }
+
+        fn square(&self) -> Self {
+            *self * self
+        }
+
+        fn double(&self) -> Self {
+            *self + self
+        }
+
+        fn invert(&self) -> CtOption<Self> {
+            CtOption::new(self.pow_vartime([P - 2]), !self.is_zero())
+        }
+
+        fn sqrt(&self) -> CtOption<Self> {
+            let root = (0..P).map(F97).find(|root| root.square() == *self);
+            CtOption::new(root.unwrap_or_default(), Choice::from(root.is_some() as u8))
+        }
+    }
+
+    impl PrimeField for F97 {
+        type Repr = [u8; 1];
+
+        fn from_repr(repr: [u8; 1]) -> CtOption<Self> {
+            CtOption::new(
+                F97(repr[0] as u64),
+                Choice::from(((repr[0] as u64) < P) as u8),
+            )
+        }
+
+        fn to_repr(&self) -> [u8; 1] {
+            [self.0 as u8]
+        }
+
+        fn is_odd(&self) -> Choice {
+            Choice::from((self.0 & 1) as u8)
+        }
+
+        const NUM_BITS: u32 = 7;
+        const CAPACITY: u32 = 6;
+        const S: u32 = 5;
+
+        fn multiplicative_generator() -> Self {
+            F97(5)
+        }
+
+        fn root_of_unity() -> Self {
+            F97(28)
+        }
+    }
+
+    #[test]
+    fn test_small_field() {
+        let f = |values: &[u64]| Polynomial(values.iter().map(|v| F97::from(*v)).collect());
+
+        // (3 + x) * (4 + 2x) = 12 + 10x + 2x^2, and 2 * 5^2 + 10 * 5 + 12 = 112 = 15 mod 97.
+        let product = &f(&[3, 1]) * &f(&[4, 2]);
+        assert_eq!(product, f(&[12, 10, 2]));
+        assert_eq!(product.eval(&F97(5)), F97(15));
+        let (quotient, remainder) = product.divide_with_remainder(&f(&[3, 1]));
+        assert_eq!((quotient, remainder), (f(&[4, 2]), f(&[])));
+
+        let domain = EvaluationDomain::<F97>::new(8).unwrap();
+        assert_eq!(domain.generator, F97(28).pow_vartime([4]));
+        let evals = domain.fft(&product);
+        for (element, eval) in domain.elements().iter().zip(evals.iter()) {
+            assert_eq!(product.eval(element), *eval);
+        }
+        let mut interpolated = domain.ifft(&evals);
+        interpolated.remove_zeros();
+        assert_eq!(interpolated, product);
+        let poly = f(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(domain.coset_ifft(&domain.coset_fft(&poly)), poly);
+
+        let points =
+            PolynomialEvaluationPoints((1..4).map(|i| (F97(i), product.eval(&F97(i)))).collect());
+        assert_eq!(points.interpolate(), product);
+        assert_eq!(
+            EvaluationDomain::<F97>::new(64).unwrap_err(),
+            PlonkError::InvalidDomainSize(64)
+        );
+    }
 
     #[test]
     fn eval() {
@@ -253,6 +964,30 @@ mod tests {
         assert_eq!(poly3.0, div.0);
     }
 
+    #[test]
+    fn test_division_with_remainder() {
+        let poly1 = Polynomial(vec![
+            Scalar::from(9),
+            Scalar::from(9),
+            Scalar::from(55).neg(),
+            Scalar::from(2),
+            Scalar::from(7),
+        ]);
+        let divisor = Polynomial::vanishing(&[Scalar::from(2), Scalar::from(5)]);
+
+        let (quotient, remainder) = poly1.divide_with_remainder(&divisor);
+        assert_eq!(quotient.0.len(), 3);
+        assert!(remainder.0.len() < divisor.0.len());
+
+        let mut recomposed = &(&quotient * &divisor) + &remainder;
+        recomposed.remove_zeros();
+        assert_eq!(recomposed, poly1);
+
+        // The remainder agrees with the dividend at the roots of the divisor.
+        let point = Scalar::from(5);
+        assert_eq!(remainder.eval(&point), poly1.eval(&point));
+    }
+
     #[test]
     fn test_addition() {
         let poly1 = Polynomial(vec![
@@ -353,4 +1088,194 @@ mod tests {
         assert_eq!(poly1 - &poly3, poly1m3);
         assert_eq!(poly2 - poly3, poly2m3);
     }
+
+    #[test]
+    fn test_fft() {
+        let poly1 = Polynomial(vec![
+            Scalar::from(180),
+            Scalar::from(3),
+            Scalar::from(2),
+            Scalar::from(7),
+            Scalar::from(11145),
+        ]);
+        let domain = EvaluationDomain::new(8).unwrap();
+
+        let evals = domain.fft(&poly1);
+        for (element, eval) in domain.elements().iter().zip(evals.iter()) {
+            assert_eq!(poly1.eval(element), *eval);
+        }
+
+        let mut interpolated = domain.ifft(&evals);
+        interpolated.remove_zeros();
+        assert_eq!(poly1, interpolated);
+
+        assert_eq!(
+            EvaluationDomain::<Scalar>::new(6).unwrap_err(),
+            PlonkError::InvalidDomainSize(6)
+        );
+    }
+
+    #[test]
+    fn test_coset_fft() {
+        let poly1 = Polynomial(vec![
+            Scalar::from(9),
+            Scalar::from(9),
+            Scalar::from(55).neg(),
+            Scalar::from(2),
+        ]);
+        let domain = EvaluationDomain::new(4).unwrap();
+
+        let evals = domain.coset_fft(&poly1);
+        for (element, eval) in domain.coset_elements().iter().zip(evals.iter()) {
+            assert_eq!(poly1.eval(element), *eval);
+        }
+        assert_eq!(poly1, domain.coset_ifft(&evals));
+    }
+
+    #[test]
+    fn test_lagrange_basis() {
+        let domain = EvaluationDomain::<Scalar>::new(4).unwrap();
+        let elements = domain.elements();
+        for i in 0..4 {
+            let lb = domain.lagrange_basis(i);
+            for (j, element) in elements.iter().enumerate() {
+                let expected = if i == j {
+                    Scalar::one()
+                } else {
+                    Scalar::zero()
+                };
+                assert_eq!(lb.eval(element), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_evaluate_lagrange() {
+        let domain = EvaluationDomain::<Scalar>::new(8).unwrap();
+        let point = Scalar::from(1234);
+        for i in 0..8 {
+            let lb = domain.lagrange_basis(i);
+            assert_eq!(domain.evaluate_lagrange(i, &point), lb.eval(&point));
+            for (j, element) in domain.elements().iter().enumerate() {
+                let expected = Scalar::from((i == j) as u64);
+                assert_eq!(domain.evaluate_lagrange(i, element), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sparse_polynomial() {
+        let vanishing = SparsePolynomial::<Scalar>::vanishing(4);
+        assert_eq!(
+            vanishing.to_dense(),
+            Polynomial(vec![
+                -Scalar::one(),
+                Scalar::zero(),
+                Scalar::zero(),
+                Scalar::zero(),
+                Scalar::one()
+            ])
+        );
+        let domain = EvaluationDomain::<Scalar>::new(4).unwrap();
+        assert!(domain
+            .elements()
+            .iter()
+            .all(|element| vanishing.eval(element) == Scalar::zero()));
+        assert_eq!(
+            SparsePolynomial::new(vec![
+                (3, Scalar::one()),
+                (0, Scalar::one()),
+                (3, -Scalar::one())
+            ]),
+            SparsePolynomial(vec![(0, Scalar::one())])
+        );
+
+        let dense = Polynomial((1..=7).map(Scalar::from).collect::<Vec<_>>());
+        let point = Scalar::from(11);
+        assert_eq!(&dense * &vanishing, &dense * &vanishing.to_dense());
+        assert_eq!(
+            (&dense * &vanishing).eval(&point),
+            dense.eval(&point) * vanishing.eval(&point)
+        );
+        assert_eq!(
+            dense.divide_by_sparse(&vanishing),
+            dense.divide_with_remainder(&vanishing.to_dense())
+        );
+        let (quotient, remainder) = (&dense * &vanishing).divide_by_sparse(&vanishing);
+        assert_eq!(quotient, dense);
+        assert!(remainder.0.is_empty());
+    }
+
+    #[test]
+    fn test_divide_by_vanishing() {
+        let dense = Polynomial((1..=10).map(Scalar::from).collect::<Vec<_>>());
+        for n in [1, 4, 8, 16] {
+            let vanishing = SparsePolynomial::vanishing(n);
+            let mut quotient = (&dense * &vanishing).divide_by_vanishing(n).unwrap();
+            quotient.remove_zeros();
+            assert_eq!(quotient, dense);
+            assert_eq!(
+                dense.divide_by_vanishing(n).is_ok(),
+                dense.divide_by_sparse(&vanishing).1 .0.is_empty()
+            );
+        }
+        assert_eq!(
+            (&dense * &SparsePolynomial::vanishing(4) + Scalar::one()).divide_by_vanishing(4),
+            Err(PlonkError::NotDivisible)
+        );
+        assert_eq!(
+            Polynomial::<Scalar>::zero(3).divide_by_vanishing(4),
+            Ok(Polynomial(vec![]))
+        );
+    }
+
+    #[test]
+    fn test_shifted_fft() {
+        // A polynomial larger than the domain is evaluated correctly over any of its cosets.
+        let domain = EvaluationDomain::<Scalar>::new(4).unwrap();
+        let poly = Polynomial((1..=11).map(Scalar::from).collect::<Vec<_>>());
+        let shift = Scalar::from(7);
+        let evals = domain.shifted_fft(&poly, &shift);
+        for (element, eval) in domain.elements().iter().zip(evals.iter()) {
+            assert_eq!(poly.eval(&(*element * shift)), *eval);
+        }
+        let small = Polynomial((1..=4).map(Scalar::from).collect::<Vec<_>>());
+        assert_eq!(
+            Evaluations::from_polynomial_shifted(&small, &domain, &shift).interpolate(),
+            small
+        );
+    }
+
+    #[test]
+    fn test_evaluations() {
+        let domain = EvaluationDomain::<Scalar>::new(8).unwrap();
+        let a = Polynomial((1..=3).map(Scalar::from).collect::<Vec<_>>());
+        let b = Polynomial((4..=7).map(Scalar::from).collect::<Vec<_>>());
+        for coset in [false, true] {
+            let evaluations = |poly: &Polynomial<Scalar>| match coset {
+                true => Evaluations::from_polynomial_coset(poly, &domain),
+                false => Evaluations::from_polynomial(poly, &domain),
+            };
+            let (a_evals, b_evals) = (evaluations(&a), evaluations(&b));
+            let mut expected = &a * &b + &a - &b * Scalar::from(2);
+            expected.0.resize(8, Scalar::zero());
+            let result =
+                (&a_evals * &b_evals + &a_evals - &b_evals * Scalar::from(2)).interpolate();
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_batch_invert() {
+        let mut values = (1..=10).map(Scalar::from).collect::<Vec<_>>();
+        batch_invert(&mut values).unwrap();
+        for (i, value) in values.iter().enumerate() {
+            assert_eq!(*value * Scalar::from(i as u64 + 1), Scalar::one());
+        }
+        batch_invert::<Scalar>(&mut []).unwrap();
+
+        let mut values = vec![Scalar::from(2), Scalar::zero(), Scalar::from(3)];
+        assert_eq!(batch_invert(&mut values), Err(PlonkError::NonInvertible));
+        assert_eq!(values[0], Scalar::from(2));
+    }
 }
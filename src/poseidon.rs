@@ -0,0 +1,129 @@
+use crate::transcript::scalar_from_wide_bytes;
+use alloc::vec::Vec;
+use blake2::{digest::consts::U64, Blake2b, Digest};
+use blstrs::Scalar;
+use ff::Field;
+
+/// Width of the permutation. We use a rate of 2 and a capacity of 1.
+pub(crate) const WIDTH: usize = 3;
+
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: usize = 57;
+
+/// Poseidon permutation over the scalar field of BLS12-381, with x^5 as S-box, and the number
+/// of rounds recommended for a 128 bit security level with width 3. The round constants are
+/// derived from Blake2b, and the MDS matrix is the Cauchy matrix 1 / (i + j + WIDTH).
+#[derive(Clone, Debug)]
+pub(crate) struct Poseidon {
+    pub(crate) round_constants: Vec<[Scalar; WIDTH]>,
+    pub(crate) mds: [[Scalar; WIDTH]; WIDTH],
+}
+
+impl Poseidon {
+    pub(crate) fn new() -> Self {
+        let round_constants = (0..FULL_ROUNDS + PARTIAL_ROUNDS)
+            .map(|round| {
+                let mut constants = [Scalar::zero(); WIDTH];
+                for (i, constant) in constants.iter_mut().enumerate() {
+                    let mut hasher = Blake2b::<U64>::new();
+                    hasher.update(b"Poseidon BLS12-381 round constants");
+                    hasher.update(((round * WIDTH + i) as u64).to_le_bytes());
+                    *constant = scalar_from_wide_bytes(&hasher.finalize().into());
+                }
+                constants
+            })
+            .collect();
+
+        let mut mds = [[Scalar::zero(); WIDTH]; WIDTH];
+        for (i, row) in mds.iter_mut().enumerate() {
+            for (j, entry) in row.iter_mut().enumerate() {
+                *entry = Scalar::from((i + j + WIDTH) as u64).invert().unwrap();
+            }
+        }
+
+        Poseidon {
+            round_constants,
+            mds,
+        }
+    }
+
+    pub(crate) fn permute(&self, state: &mut [Scalar; WIDTH]) {
+        for (round, constants) in self.round_constants.iter().enumerate() {
+            for (element, constant) in state.iter_mut().zip(constants.iter()) {
+                *element += constant;
+            }
+
+            if is_full_round(round) {
+                state.iter_mut().for_each(sbox);
+            } else {
+                sbox(&mut state[0]);
+            }
+
+            let mut result = [Scalar::zero(); WIDTH];
+            for (value, row) in result.iter_mut().zip(self.mds.iter()) {
+                *value = row
+                    .iter()
+                    .zip(state.iter())
+                    .fold(Scalar::zero(), |acc, (m, s)| acc + m * s);
+            }
+            *state = result;
+        }
+    }
+
+    /// Hash of `inputs`, with a sponge of rate 2 whose capacity element is initialised with
+    /// the number of inputs. The inputs are absorbed two at a time, with a permutation after
+    /// each pair (at least one), and the output is the first element of the rate.
+    pub(crate) fn hash(&self, inputs: &[Scalar]) -> Scalar {
+        let mut state = [Scalar::zero(); WIDTH];
+        state[0] = Scalar::from(inputs.len() as u64);
+        for chunk in inputs.chunks(WIDTH - 1) {
+            for (element, input) in state[1..].iter_mut().zip(chunk) {
+                *element += input;
+            }
+            self.permute(&mut state);
+        }
+        if inputs.is_empty() {
+            self.permute(&mut state);
+        }
+
+        state[1]
+    }
+}
+
+/// Full rounds are applied at the beginning and at the end, and the partial rounds only apply
+/// the S-box to the first element.
+pub(crate) fn is_full_round(round: usize) -> bool {
+    !(FULL_ROUNDS / 2..FULL_ROUNDS / 2 + PARTIAL_ROUNDS).contains(&round)
+}
+
+fn sbox(element: &mut Scalar) {
+    let square = element.square();
+    *element *= square.square();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permutation() {
+        let poseidon = Poseidon::new();
+        let mut state = [Scalar::zero(); WIDTH];
+        poseidon.permute(&mut state);
+        assert_ne!(state, [Scalar::zero(); WIDTH]);
+
+        // The permutation is deterministic, and changing any input changes every output.
+        let mut same_state = [Scalar::zero(); WIDTH];
+        poseidon.permute(&mut same_state);
+        assert_eq!(state, same_state);
+
+        let mut other_state = [Scalar::zero(), Scalar::zero(), Scalar::one()];
+        poseidon.permute(&mut other_state);
+        for (a, b) in state.iter().zip(other_state.iter()) {
+            assert_ne!(a, b);
+        }
+
+        // The length of the input is part of the hash.
+        assert_ne!(poseidon.hash(&[]), poseidon.hash(&[Scalar::zero()]));
+    }
+}
@@ -0,0 +1,214 @@
+// Proofs of the prover, and the encodings of those with KZG. They only depend on `core` and
+// `alloc`, so that proofs can be decoded by the verifier without the standard library.
+use crate::commitment::PlonkCommitment;
+use crate::error::PlonkError;
+use crate::evm::{encode_g1, verify_selector};
+use crate::kzg10::{Kzg10, Kzg10Commitment};
+use alloc::vec::Vec;
+use blstrs::Scalar;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Proof of the prover, with the commitments and the evaluations of its polynomials, and the
+/// opening of the evaluations by the commitment scheme (KZG by default).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P::Commitment: Serialize, P::ProofOpening: Serialize",
+        deserialize = "P::Commitment: Deserialize<'de>, P::ProofOpening: Deserialize<'de>"
+    ))
+)]
+pub struct PlonkProof<P: PlonkCommitment = Kzg10> {
+    pub commitment_a: P::Commitment,
+    pub commitment_b: P::Commitment,
+    pub commitment_c: P::Commitment,
+    pub commitment_f: P::Commitment,
+    pub commitment_h1: P::Commitment,
+    pub commitment_h2: P::Commitment,
+    pub commitment_z: P::Commitment,
+    pub commitment_z_lookup: P::Commitment,
+    pub t_low: P::Commitment,
+    pub t_mid: P::Commitment,
+    pub t_high: P::Commitment,
+    pub a_eval: Scalar,
+    pub b_eval: Scalar,
+    pub c_eval: Scalar,
+    pub s_sig1: Scalar,
+    pub s_sig2: Scalar,
+    pub z_omega: Scalar,
+    pub f_eval: Scalar,
+    pub qt_eval: Scalar,
+    pub table_eval: Scalar,
+    pub h2_eval: Scalar,
+    pub table_omega: Scalar,
+    pub h1_omega: Scalar,
+    pub z_lookup_omega: Scalar,
+    pub a_omega: Scalar,
+    pub opening: P::ProofOpening,
+}
+
+/// Opening of the evaluations of a proof with KZG: the commitments to the quotients by X - zeta
+/// and X - zeta * omega of the combinations, with the powers of a challenge v, of the
+/// polynomials opened at each point, the linearisation polynomial being one of those at zeta.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LinearisedOpening {
+    pub w_omega: Kzg10Commitment,
+    pub w_omega_zeta: Kzg10Commitment,
+}
+
+impl<P: PlonkCommitment> PlonkProof<P> {
+    // Evaluations in the order of the encodings, which is also the one of the transcript.
+    pub(crate) fn evaluations(&self) -> [&Scalar; 14] {
+        [
+            &self.a_eval,
+            &self.b_eval,
+            &self.c_eval,
+            &self.s_sig1,
+            &self.s_sig2,
+            &self.z_omega,
+            &self.f_eval,
+            &self.qt_eval,
+            &self.table_eval,
+            &self.h2_eval,
+            &self.table_omega,
+            &self.h1_omega,
+            &self.z_lookup_omega,
+            &self.a_omega,
+        ]
+    }
+}
+
+impl PlonkProof {
+    /// Size of the encoding of a proof: thirteen compressed G1 points followed by fourteen
+    /// scalars.
+    pub const SIZE: usize = 13 * 48 + 14 * 32;
+
+    // Commitments in the order of the encodings.
+    pub(crate) fn commitments(&self) -> [&Kzg10Commitment; 13] {
+        [
+            &self.commitment_a,
+            &self.commitment_b,
+            &self.commitment_c,
+            &self.commitment_f,
+            &self.commitment_h1,
+            &self.commitment_h2,
+            &self.commitment_z,
+            &self.commitment_z_lookup,
+            &self.t_low,
+            &self.t_mid,
+            &self.t_high,
+            &self.opening.w_omega,
+            &self.opening.w_omega_zeta,
+        ]
+    }
+
+    /// Encodes the proof with a fixed layout: the compressed commitments, followed by those of
+    /// the opening, and by the evaluations in big endian, in the order in which they appear in
+    /// the structs.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        for commitment in self.commitments() {
+            bytes.extend_from_slice(&commitment.to_bytes());
+        }
+        for scalar in self.evaluations() {
+            bytes.extend_from_slice(&scalar.to_bytes_be());
+        }
+        bytes
+    }
+
+    /// Size of the encoding of a proof for the Solidity verifier.
+    pub const EVM_SIZE: usize = 13 * 128 + 14 * 32;
+
+    /// Encodes the proof as expected by the contract of `solidity_verifier`: the same layout as
+    /// `to_bytes`, with points in the uncompressed encoding of EIP-2537.
+    pub fn to_evm_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::EVM_SIZE);
+        for commitment in self.commitments() {
+            bytes.extend_from_slice(&encode_g1(&commitment.0));
+        }
+        bytes.extend_from_slice(&self.to_bytes()[13 * 48..]);
+        bytes
+    }
+
+    /// ABI encoding of the call `verify(proof, publicInputs)` to the contract of
+    /// `solidity_verifier`, starting with the function selector. Public inputs are big endian
+    /// words, as any `uint256[]`.
+    pub fn to_evm_calldata(&self, pub_in: &[Scalar]) -> Vec<u8> {
+        let word = |value: usize| {
+            let mut bytes = [0u8; 32];
+            bytes[24..].copy_from_slice(&(value as u64).to_be_bytes());
+            bytes
+        };
+        // The encoding of the proof is a whole number of words, so it needs no padding.
+        let proof = self.to_evm_bytes();
+        let mut calldata = Vec::with_capacity(4 + 32 * (4 + pub_in.len()) + proof.len());
+        calldata.extend_from_slice(&verify_selector());
+        calldata.extend_from_slice(&word(2 * 32));
+        calldata.extend_from_slice(&word(3 * 32 + proof.len()));
+        calldata.extend_from_slice(&word(proof.len()));
+        calldata.extend_from_slice(&proof);
+        calldata.extend_from_slice(&word(pub_in.len()));
+        for input in pub_in {
+            calldata.extend_from_slice(&input.to_bytes_be());
+        }
+        calldata
+    }
+
+    /// Decodes a proof encoded with `to_bytes`, checking that all points are in the prime order
+    /// subgroup and that all scalars are canonical.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, PlonkError> {
+        if bytes.len() != Self::SIZE {
+            return Err(PlonkError::InvalidLength {
+                expected: Self::SIZE,
+                got: bytes.len(),
+            });
+        }
+
+        let (points, scalars) = bytes.split_at(13 * 48);
+        let mut points = points
+            .chunks_exact(48)
+            .map(|chunk| Kzg10Commitment::from_bytes(chunk.try_into().unwrap()));
+        let mut scalars = scalars.chunks_exact(32).map(|chunk| {
+            Option::from(Scalar::from_bytes_be(chunk.try_into().unwrap()))
+                .ok_or(PlonkError::InvalidScalar)
+        });
+
+        // The iterators have exactly the number of elements we need.
+        let mut point = || points.next().unwrap();
+        let mut scalar = || scalars.next().unwrap();
+
+        Ok(PlonkProof {
+            commitment_a: point()?,
+            commitment_b: point()?,
+            commitment_c: point()?,
+            commitment_f: point()?,
+            commitment_h1: point()?,
+            commitment_h2: point()?,
+            commitment_z: point()?,
+            commitment_z_lookup: point()?,
+            t_low: point()?,
+            t_mid: point()?,
+            t_high: point()?,
+            opening: LinearisedOpening {
+                w_omega: point()?,
+                w_omega_zeta: point()?,
+            },
+            a_eval: scalar()?,
+            b_eval: scalar()?,
+            c_eval: scalar()?,
+            s_sig1: scalar()?,
+            s_sig2: scalar()?,
+            z_omega: scalar()?,
+            f_eval: scalar()?,
+            qt_eval: scalar()?,
+            table_eval: scalar()?,
+            h2_eval: scalar()?,
+            table_omega: scalar()?,
+            h1_omega: scalar()?,
+            z_lookup_omega: scalar()?,
+            a_omega: scalar()?,
+        })
+    }
+}
@@ -1,247 +1,567 @@
-use crate::kzg10::Kzg10Commitment;
-use crate::plonk::{ComputationTrace, PreprocessedInput, K1, K2};
-use crate::polynomial::Polynomial;
+use crate::commitment::PlonkCommitment;
+use crate::error::PlonkError;
+use crate::kzg10::{Kzg10, Kzg10Commitment};
+use crate::lookup::{compress, lookup_product, sorted_halves};
+use crate::parallel;
+use crate::plonk::{ComputationTrace, ProverKey};
+use crate::polynomial::{
+    batch_invert, zeroize_scalars, EvaluationDomain, Evaluations, Polynomial, SparsePolynomial,
+};
+use crate::proof::{LinearisedOpening, PlonkProof};
 use crate::transcript::Transcript;
+use crate::verifier::{opening_points, Challenges};
+use crate::wire::{K1, K2};
 use blstrs::Scalar;
-use ff::Field;
-use rand_core::OsRng;
+use ff::{Field, PrimeField};
+use rand_chacha::ChaCha20Rng;
+use rand_core::{CryptoRng, OsRng, RngCore, SeedableRng};
 use std::ops::Neg;
+use zeroize::Zeroize;
 
 pub struct Prover;
 
-pub struct PlonkProof {
-    pub commitment_a: Kzg10Commitment,
-    pub commitment_b: Kzg10Commitment,
-    pub commitment_c: Kzg10Commitment,
-    pub commitment_z: Kzg10Commitment,
-    pub t_low: Kzg10Commitment,
-    pub t_mid: Kzg10Commitment,
-    pub t_high: Kzg10Commitment,
-    pub w_omega: Kzg10Commitment,
-    pub w_omega_zeta: Kzg10Commitment,
-    pub a_eval: Scalar,
-    pub b_eval: Scalar,
-    pub c_eval: Scalar,
-    pub s_sig1: Scalar,
-    pub s_sig2: Scalar,
-    pub z_omega: Scalar,
+// Evaluations over H of the permutation polynomial, computed as a running product. All the
+// denominators are inverted at once.
+pub fn permutation_product<P: PlonkCommitment>(
+    prover_key: &ProverKey<P>,
+    trace: &ComputationTrace,
+    beta: &Scalar,
+    gamma: &Scalar,
+) -> Result<Vec<Scalar>, PlonkError> {
+    let n = prover_key.domain.size;
+    let h = &prover_key.constraints.extended_h_subgroup;
+    let sigma = |index: usize| *prover_key.sigma_star.get(&index).unwrap();
+    let mut denominators = (0..n)
+        .map(|j| {
+            (trace.a[j] + sigma(j) * beta + gamma)
+                * (trace.b[j] + sigma(j + n) * beta + gamma)
+                * (trace.c[j] + sigma(j + 2 * n) * beta + gamma)
+        })
+        .collect::<Vec<Scalar>>();
+    batch_invert(&mut denominators)?;
+
+    let mut z_evals = Vec::with_capacity(n);
+    let mut factor = Scalar::one();
+    for (j, denominator_inv) in denominators.iter().enumerate() {
+        z_evals.push(factor);
+        let numerator = (trace.a[j] + beta * h[j] + gamma)
+            * (trace.b[j] + beta * K1() * h[j] + gamma)
+            * (trace.c[j] + beta * K2() * h[j] + gamma);
+        factor *= numerator * denominator_inv;
+    }
+    // The denominators depend on the witness.
+    zeroize_scalars(&mut denominators);
+    Ok(z_evals)
+}
+
+// Commitment of the wire polynomial that takes the `values` over the domain, blinded with the
+// polynomial of coefficients `blinding` times Z_H(X).
+pub(crate) fn commit_wire(
+    prover_key: &ProverKey,
+    values: &[Scalar],
+    blinding: &[Scalar],
+) -> Kzg10Commitment {
+    let blinding_terms = prover_key
+        .blinder_polynomial
+        .0
+        .iter()
+        .flat_map(|(degree, coefficient)| {
+            blinding
+                .iter()
+                .enumerate()
+                .map(move |(i, blinding)| (degree + i, *coefficient * blinding))
+        })
+        .collect();
+    let mut evals = Evaluations::new(values.to_vec(), &prover_key.domain);
+    let commitment = prover_key.lagrange_set.commit_evals(&evals);
+    evals.zeroize();
+    commitment
+        + prover_key
+            .scheme
+            .commit_sparse(&SparsePolynomial::new(blinding_terms))
+}
+
+// Evaluations over a coset of the polynomials in the numerator of the quotient.
+#[derive(Zeroize)]
+struct QuotientTables {
+    gate: Evaluations,
+    a: Evaluations,
+    b: Evaluations,
+    c: Evaluations,
+    z: Evaluations,
+    z_omega: Evaluations,
+    qs1: Evaluations,
+    qs2: Evaluations,
+    qs3: Evaluations,
+    l0: Evaluations,
+    qk: Evaluations,
+    qt: Evaluations,
+    f: Evaluations,
+    table: Evaluations,
+    table_omega: Evaluations,
+    h1: Evaluations,
+    h1_omega: Evaluations,
+    h2: Evaluations,
+    z_lookup: Evaluations,
+    z_lookup_omega: Evaluations,
 }
 
 impl Prover {
-    pub fn prove(
+    pub fn prove<P: PlonkCommitment, T: Transcript>(
+        public_in: &[Scalar],
+        prover_key: &ProverKey<P>,
+        trace: &ComputationTrace,
+        transcript: &mut T,
+    ) -> Result<PlonkProof<P>, PlonkError> {
+        Self::prove_with_mode(public_in, prover_key, trace, transcript, false, &mut OsRng)
+    }
+
+    /// Same as `prove`, drawing the blinding scalars from `rng`, for targets without an
+    /// operating system RNG.
+    pub fn prove_with_rng<P: PlonkCommitment, T: Transcript, R: RngCore + CryptoRng>(
+        public_in: &[Scalar],
+        prover_key: &ProverKey<P>,
+        trace: &ComputationTrace,
+        transcript: &mut T,
+        rng: &mut R,
+    ) -> Result<PlonkProof<P>, PlonkError> {
+        Self::prove_with_mode(public_in, prover_key, trace, transcript, false, rng)
+    }
+
+    /// Same as `prove`, but with a lower peak memory for large circuits. The quotient is
+    /// computed over the cosets of the domain of the circuit one at a time, instead of over a
+    /// domain eight times larger, so that the evaluations of its twenty polynomials are only
+    /// held for the size of the circuit. The proofs are the same.
+    pub fn prove_streaming<P: PlonkCommitment, T: Transcript>(
+        public_in: &[Scalar],
+        prover_key: &ProverKey<P>,
+        trace: &ComputationTrace,
+        transcript: &mut T,
+    ) -> Result<PlonkProof<P>, PlonkError> {
+        Self::prove_with_mode(public_in, prover_key, trace, transcript, true, &mut OsRng)
+    }
+
+    /// Same as `prove`, with all the blinding scalars derived from `seed`, so that the proof
+    /// only depends on its inputs. This is meant for snapshot tests and test vectors shared
+    /// with other implementations: two proofs with the same seed and different witnesses are
+    /// blinded alike, and together leak relations between the witnesses, so a seed must never
+    /// be used with a secret witness.
+    pub fn prove_deterministic<P: PlonkCommitment, T: Transcript>(
+        public_in: &[Scalar],
+        prover_key: &ProverKey<P>,
+        trace: &ComputationTrace,
+        transcript: &mut T,
+        seed: [u8; 32],
+    ) -> Result<PlonkProof<P>, PlonkError> {
+        let mut rng = ChaCha20Rng::from_seed(seed);
+        Self::prove_with_mode(public_in, prover_key, trace, transcript, false, &mut rng)
+    }
+
+    /// Same as `prove_streaming`, drawing the blinding scalars from `rng`.
+    pub fn prove_streaming_with_rng<P: PlonkCommitment, T: Transcript, R: RngCore + CryptoRng>(
+        public_in: &[Scalar],
+        prover_key: &ProverKey<P>,
+        trace: &ComputationTrace,
+        transcript: &mut T,
+        rng: &mut R,
+    ) -> Result<PlonkProof<P>, PlonkError> {
+        Self::prove_with_mode(public_in, prover_key, trace, transcript, true, rng)
+    }
+
+    fn prove_with_mode<P: PlonkCommitment, T: Transcript, R: RngCore + CryptoRng>(
         public_in: &[Scalar],
-        pre_in: &PreprocessedInput,
-        prover_key: &ComputationTrace,
-        transcript: &mut Transcript,
-    ) -> PlonkProof {
+        prover_key: &ProverKey<P>,
+        trace: &ComputationTrace,
+        transcript: &mut T,
+        streaming: bool,
+        rng: &mut R,
+    ) -> Result<PlonkProof<P>, PlonkError> {
         // We begin by computing the public polynomial
-        let mut pi = Polynomial::zero(public_in.len());
-        for (i, input) in public_in.iter().enumerate() {
-            let lp = pre_in.constraints.lagrange_basis(i);
-            pi += lp * input;
+        let pi = prover_key.public_input_polynomial(public_in)?;
+
+        // The transcript first absorbs the circuit and the public inputs, so that challenges
+        // depend on the statement being proven.
+        prover_key.verifier_key.append_to_transcript(transcript);
+        for input in public_in.iter() {
+            transcript.append_scalar(b"public input", input);
         }
 
-        // We first compute the random scalars, that we don't compute randomly for debugging.
+        // We first draw the blinding scalars b1, ..., b9 of the paper. The wire polynomials are
+        // blinded with random multiples of degree 1 of Z_H, and the permutation polynomial with
+        // one of degree 2, which is enough for their openings (one per polynomial, and two for
+        // the permutation polynomial) to be uniformly random whatever the witness. The left wire
+        // is also opened at zeta * omega, for the gates that use the next row, so it gets one
+        // more blinding scalar b_next.
         let (b1, b2, b3, b4, b5, b6, b7, b8, b9) = (
-            Scalar::random(&mut OsRng),
-            Scalar::random(&mut OsRng),
-            Scalar::random(&mut OsRng),
-            Scalar::random(&mut OsRng),
-            Scalar::random(&mut OsRng),
-            Scalar::random(&mut OsRng),
-            Scalar::random(&mut OsRng),
-            Scalar::random(&mut OsRng),
-            Scalar::random(&mut OsRng),
+            Scalar::random(&mut *rng),
+            Scalar::random(&mut *rng),
+            Scalar::random(&mut *rng),
+            Scalar::random(&mut *rng),
+            Scalar::random(&mut *rng),
+            Scalar::random(&mut *rng),
+            Scalar::random(&mut *rng),
+            Scalar::random(&mut *rng),
+            Scalar::random(&mut *rng),
         );
+        let b_next = Scalar::random(&mut *rng);
 
-        let prover_key = prover_key.pad_next_power_two();
-
-        // First we check that indeed the permutation is correct:
-        let mut extended_witness = Vec::with_capacity(prover_key.a.len() * 3);
-        extended_witness.extend_from_slice(&prover_key.a);
-        extended_witness.extend_from_slice(&prover_key.b);
-        extended_witness.extend_from_slice(&prover_key.c);
-
-        for (&key, &value) in pre_in.constraints.permutations.iter() {
-            assert_eq!(
-                extended_witness[key], extended_witness[value],
-                "Failed in key {key} and value {value}."
-            );
+        // The trace may have been created before the padding of the circuit in the setup, but
+        // all its columns must have the same length.
+        let n = prover_key.constraints.nr_constraints;
+        if trace.b.len() != trace.a.len() || trace.c.len() != trace.a.len() {
+            return Err(PlonkError::TraceShape {
+                a: trace.a.len(),
+                b: trace.b.len(),
+                c: trace.c.len(),
+            });
         }
+        if trace.a.len() > n {
+            return Err(PlonkError::InvalidLength {
+                expected: n,
+                got: trace.a.len(),
+            });
+        }
+        let mut trace = trace.pad(n);
+
+        // First we check that the trace satisfies the circuit, as otherwise we would only
+        // notice it when the quotient is not a polynomial.
+        prover_key.constraints.is_satisfied(&trace)?;
 
         // Now we compute the wire scalar:
-        let mut a_poly = Polynomial(vec![b2, b1]) * &pre_in.blinder_polynomial;
-        let mut b_poly = Polynomial(vec![b4, b3]) * &pre_in.blinder_polynomial;
-        let mut c_poly = Polynomial(vec![b6, b5]) * &pre_in.blinder_polynomial;
-
-        for index in 0..pre_in.constraints.nr_constraints {
-            let lb = pre_in.constraints.lagrange_basis(index);
-            a_poly += &lb * prover_key.a[index];
-            b_poly += &lb * prover_key.b[index];
-            c_poly += &lb * prover_key.c[index];
-        }
+        let mut a_poly = Polynomial(vec![b2, b1, b_next]) * &prover_key.blinder_polynomial
+            + prover_key.domain.ifft(&trace.a);
+        let mut b_poly = Polynomial(vec![b4, b3]) * &prover_key.blinder_polynomial
+            + prover_key.domain.ifft(&trace.b);
+        let mut c_poly = Polynomial(vec![b6, b5]) * &prover_key.blinder_polynomial
+            + prover_key.domain.ifft(&trace.c);
 
-        let commitment_a = pre_in.kzg_set.commit(&a_poly);
-        let commitment_b = pre_in.kzg_set.commit(&b_poly);
-        let commitment_c = pre_in.kzg_set.commit(&c_poly);
+        let commitment_a = P::commit_wire(prover_key, &trace.a, &[b2, b1, b_next], &a_poly);
+        let commitment_b = P::commit_wire(prover_key, &trace.b, &[b4, b3], &b_poly);
+        let commitment_c = P::commit_wire(prover_key, &trace.c, &[b6, b5], &c_poly);
 
-        transcript.append_point(b"commitment a", &commitment_a.0);
-        transcript.append_point(b"commitment b", &commitment_b.0);
-        transcript.append_point(b"commitment c", &commitment_c.0);
+        P::append_commitment(transcript, b"commitment a", &commitment_a);
+        P::append_commitment(transcript, b"commitment b", &commitment_b);
+        P::append_commitment(transcript, b"commitment c", &commitment_c);
 
         // This is the end of Round 1
 
+        // We now compute the polynomials of the lookup argument. We compress the table and the
+        // wires of each row with a challenge eta, and sort the queries in the order of the table.
+        let eta = transcript.challenge_scalar(b"eta");
+        let table_evals = prover_key
+            .lookup_table
+            .iter()
+            .map(|row| compress(row, &eta))
+            .collect::<Vec<_>>();
+        let mut f_evals = (0..n)
+            .map(|j| {
+                if prover_key.constraints.constraints.qk[j] == Scalar::zero() {
+                    table_evals[0]
+                } else {
+                    let table_id = prover_key.constraints.constraints.qt[j];
+                    compress(&[trace.a[j], trace.b[j], trace.c[j], table_id], &eta)
+                }
+            })
+            .collect::<Vec<_>>();
+        let (mut h1_evals, mut h2_evals) = sorted_halves(&f_evals, &table_evals)?;
+
+        let mut random = || Scalar::random(&mut *rng);
+        let mut f_poly = Polynomial(vec![random(), random()]) * &prover_key.blinder_polynomial
+            + prover_key.domain.ifft(&f_evals);
+        let mut h1_poly = Polynomial(vec![random(), random()]) * &prover_key.blinder_polynomial
+            + prover_key.domain.ifft(&h1_evals);
+        let mut h2_poly = Polynomial(vec![random(), random()]) * &prover_key.blinder_polynomial
+            + prover_key.domain.ifft(&h2_evals);
+        let table_poly = &prover_key.table1_x
+            + &(&prover_key.table2_x + &(&prover_key.table3_x + &prover_key.table4_x * eta) * eta)
+                * eta;
+
+        let commitment_f = prover_key.scheme.commit(&f_poly);
+        let commitment_h1 = prover_key.scheme.commit(&h1_poly);
+        let commitment_h2 = prover_key.scheme.commit(&h2_poly);
+
+        P::append_commitment(transcript, b"commitment f", &commitment_f);
+        P::append_commitment(transcript, b"commitment h1", &commitment_h1);
+        P::append_commitment(transcript, b"commitment h2", &commitment_h2);
+
         // We begin round 2 by computing permutation challenges
         let beta = transcript.challenge_scalar(b"beta");
         let gamma = transcript.challenge_scalar(b"gamma");
+        let delta = transcript.challenge_scalar(b"delta");
+        let epsilon = transcript.challenge_scalar(b"epsilon");
 
-        // We now compute the permutation polynomial
-        let mut permutation_polynomial = Polynomial(vec![b9, b8, b7]) * &pre_in.blinder_polynomial
-            + pre_in.constraints.lagrange_basis(0);
-        for i in 1..pre_in.constraints.nr_constraints {
-            let mut factor = Scalar::one();
-            for j in 0..i {
-                let numerator =
-                    (prover_key.a[j] + beta * pre_in.constraints.extended_h_subgroup[j] + gamma)
-                        * (prover_key.b[j]
-                            + beta * K1() * pre_in.constraints.extended_h_subgroup[j]
-                            + gamma)
-                        * (prover_key.c[j]
-                            + beta * K2() * pre_in.constraints.extended_h_subgroup[j]
-                            + gamma);
-                let denominator =
-                    (prover_key.a[j] + pre_in.sigma_star.get(&j).unwrap() * beta + gamma)
-                        * (prover_key.b[j]
-                            + pre_in
-                                .sigma_star
-                                .get(&(j + pre_in.constraints.nr_constraints))
-                                .unwrap()
-                                * beta
-                            + gamma)
-                        * (prover_key.c[j]
-                            + pre_in
-                                .sigma_star
-                                .get(&(j + 2 * pre_in.constraints.nr_constraints))
-                                .unwrap()
-                                * beta
-                            + gamma);
-                factor *= numerator * denominator.invert().unwrap();
-            }
-            permutation_polynomial += pre_in.constraints.lagrange_basis(i) * factor;
-        }
+        let mut z_evals = permutation_product(prover_key, &trace, &beta, &gamma)?;
+        let mut permutation_polynomial = Polynomial(vec![b9, b8, b7])
+            * &prover_key.blinder_polynomial
+            + prover_key.domain.ifft(&z_evals);
+
+        let commitment_z = prover_key.scheme.commit(&permutation_polynomial);
 
-        let commitment_z = pre_in.kzg_set.commit(&permutation_polynomial);
+        let mut z_lookup_evals = lookup_product(
+            &f_evals,
+            &table_evals,
+            &h1_evals,
+            &h2_evals,
+            &delta,
+            &epsilon,
+        )?;
+        let mut lookup_polynomial = Polynomial(vec![random(), random(), random()])
+            * &prover_key.blinder_polynomial
+            + prover_key.domain.ifft(&z_lookup_evals);
 
-        transcript.append_point(b"Permutation polynomial", &commitment_z.0);
+        let commitment_z_lookup = prover_key.scheme.commit(&lookup_polynomial);
+
+        P::append_commitment(transcript, b"Permutation polynomial", &commitment_z);
+        P::append_commitment(transcript, b"Lookup polynomial", &commitment_z_lookup);
+
+        // The evaluations over H are not needed anymore, and they depend on the witness.
+        trace.zeroize();
+        for evals in [
+            &mut f_evals,
+            &mut h1_evals,
+            &mut h2_evals,
+            &mut z_evals,
+            &mut z_lookup_evals,
+        ] {
+            zeroize_scalars(evals);
+        }
 
         // Round 2 is over
 
         // We begin round 3 by computing the challenge
         let alpha = transcript.challenge_scalar(b"alpha");
 
-        // We now compute the quotient polynomial. For simplicity of the example we are not using public
-        // inputs.
-        let first = &a_poly * &b_poly * &pre_in.qm_x
-            + &a_poly * &pre_in.ql_x
-            + &b_poly * &pre_in.qr_x
-            + &c_poly * &pre_in.qo_x
-            + &pi
-            + &pre_in.qc_x;
-        assert!(check_subrgoup_zero(
-            &pre_in.constraints.extended_h_subgroup[..pre_in.constraints.nr_constraints],
-            &first
-        ));
+        // We now compute the quotient polynomial. Its numerator has degree at most 4n + 6, as the
+        // left wire has degree n + 2, so we compute it over a coset of a domain with at least
+        // 4n + 7 elements, where we can divide pointwise by the vanishing polynomial of H (which
+        // never vanishes over the coset).
+        let quotient_domain = EvaluationDomain::new((4 * n + 7).next_power_of_two())?;
 
-        let second = (&a_poly + Polynomial(vec![gamma, beta]))
-            * (&b_poly + Polynomial(vec![gamma, beta * K1()]))
-            * (&c_poly + Polynomial(vec![gamma, beta * K2()]))
-            * &permutation_polynomial
-            * alpha;
-
-        let third = (&a_poly + &pre_in.qs1_x * beta + gamma)
-            * (&b_poly + &pre_in.qs2_x * beta + gamma)
-            * (&c_poly + &pre_in.qs3_x * beta + gamma)
-            * &permutation_polynomial.scale(pre_in.constraints.extended_h_subgroup[0])
-            * alpha;
-        assert!(check_subrgoup_zero(
-            &pre_in.constraints.extended_h_subgroup[..pre_in.constraints.nr_constraints],
-            &(&second - &third)
-        ));
+        let mut z_omega_poly = permutation_polynomial.scale(prover_key.domain.generator);
+        let mut a_omega_poly = a_poly.scale(prover_key.domain.generator);
+        let table_omega_poly = table_poly.scale(prover_key.domain.generator);
+        let mut h1_omega_poly = h1_poly.scale(prover_key.domain.generator);
+        let mut z_lookup_omega_poly = lookup_polynomial.scale(prover_key.domain.generator);
 
-        let fourth = (&permutation_polynomial + Scalar::one().neg())
-            * &pre_in.constraints.lagrange_basis(0)
-            * alpha
-            * alpha;
-        assert!(check_subrgoup_zero(
-            &pre_in.constraints.extended_h_subgroup[..pre_in.constraints.nr_constraints],
-            &fourth
-        ));
+        // Evaluations of the polynomials of the numerator over the coset shift * domain. The gate
+        // constraint is computed in evaluation form, as pointwise sums and products of the wires
+        // and the selectors.
+        let tables = |domain: &EvaluationDomain, shift: &Scalar| {
+            let evaluations =
+                |poly: &Polynomial| Evaluations::from_polynomial_shifted(poly, domain, shift);
+            let (a, b, c) = (
+                evaluations(&a_poly),
+                evaluations(&b_poly),
+                evaluations(&c_poly),
+            );
+            let gate = &a * &b * evaluations(&prover_key.qm_x)
+                + &a * evaluations(&prover_key.ql_x)
+                + &b * evaluations(&prover_key.qr_x)
+                + &c * evaluations(&prover_key.qo_x)
+                + evaluations(&a_omega_poly) * evaluations(&prover_key.qn_x)
+                + evaluations(&pi)
+                + evaluations(&prover_key.qc_x);
+            QuotientTables {
+                gate,
+                a,
+                b,
+                c,
+                z: evaluations(&permutation_polynomial),
+                z_omega: evaluations(&z_omega_poly),
+                qs1: evaluations(&prover_key.qs1_x),
+                qs2: evaluations(&prover_key.qs2_x),
+                qs3: evaluations(&prover_key.qs3_x),
+                l0: evaluations(&prover_key.l0_x),
+                qk: evaluations(&prover_key.qk_x),
+                qt: evaluations(&prover_key.qt_x),
+                f: evaluations(&f_poly),
+                table: evaluations(&table_poly),
+                table_omega: evaluations(&table_omega_poly),
+                h1: evaluations(&h1_poly),
+                h1_omega: evaluations(&h1_omega_poly),
+                h2: evaluations(&h2_poly),
+                z_lookup: evaluations(&lookup_polynomial),
+                z_lookup_omega: evaluations(&z_lookup_omega_poly),
+            }
+        };
+
+        let alpha_powers = [
+            alpha,
+            alpha.square(),
+            alpha.pow_vartime([3]),
+            alpha.pow_vartime([4]),
+            alpha.pow_vartime([5]),
+        ];
+        let one_plus_delta = Scalar::one() + delta;
+        let epsilon_one_plus_delta = epsilon * one_plus_delta;
+
+        // Numerator of the quotient at the point x, which is the j-th point of the tables.
+        let numerator = |t: &QuotientTables, j: usize, x: &Scalar| {
+            let first = t.gate[j];
+
+            let second = (t.a[j] + beta * x + gamma)
+                * (t.b[j] + beta * K1() * x + gamma)
+                * (t.c[j] + beta * K2() * x + gamma)
+                * t.z[j]
+                * alpha;
+
+            let third = (t.a[j] + beta * t.qs1[j] + gamma)
+                * (t.b[j] + beta * t.qs2[j] + gamma)
+                * (t.c[j] + beta * t.qs3[j] + gamma)
+                * t.z_omega[j]
+                * alpha;
+
+            let fourth = (t.z[j] - Scalar::one()) * t.l0[j] * alpha_powers[1];
 
-        let quotient_poly =
-            (&first + &second - &third + &fourth) / pre_in.blinder_polynomial.clone();
+            // The lookup gates, the start of the lookup product, and the lookup product.
+            let fifth = t.qk[j]
+                * (compress(&[t.a[j], t.b[j], t.c[j], t.qt[j]], &eta) - t.f[j])
+                * alpha_powers[2];
 
-        // Now we need to split the polynomial into three polynomials of degree at most n + 5.
-        let (b10, b11) = (Scalar::random(&mut OsRng), Scalar::random(&mut OsRng));
+            let sixth = (t.z_lookup[j] - Scalar::one()) * t.l0[j] * alpha_powers[3];
 
-        let mut randomisation_poly_degree_n =
-            Polynomial(vec![Scalar::zero(); pre_in.constraints.nr_constraints + 1]);
-        randomisation_poly_degree_n.0[pre_in.constraints.nr_constraints] = b10;
-        let quotient_low =
-            Polynomial(quotient_poly.0[..pre_in.constraints.nr_constraints].to_vec())
+            let seventh = (t.z_lookup[j]
+                * one_plus_delta
+                * (epsilon + t.f[j])
+                * (epsilon_one_plus_delta + t.table[j] + delta * t.table_omega[j])
+                - t.z_lookup_omega[j]
+                    * (epsilon_one_plus_delta + t.h1[j] + delta * t.h2[j])
+                    * (epsilon_one_plus_delta + t.h2[j] + delta * t.h1_omega[j]))
+                * alpha_powers[4];
+
+            first + second - third + fourth + fifth + sixth + seventh
+        };
+
+        let shift = Scalar::multiplicative_generator();
+        let mut quotient_evals = if streaming {
+            // The coset g * H' of the quotient domain H' is the union of the cosets
+            // g * w'^k * H of the domain H of the circuit, for k below |H'| / |H|, where w' is
+            // the generator of H'. We compute the numerator over one of them at a time, so that
+            // only tables of the size of the circuit are in memory. The vanishing polynomial of
+            // H is constant over each of them.
+            let pieces = quotient_domain.size / prover_key.domain.size;
+            let elements = prover_key.domain.elements();
+            let mut quotient_evals = vec![Scalar::zero(); quotient_domain.size];
+            let mut piece_shift = shift;
+            for k in 0..pieces {
+                let mut tables = tables(&prover_key.domain, &piece_shift);
+                let vanishing_inv = Option::<Scalar>::from(
+                    (piece_shift.pow_vartime([n as u64]) - Scalar::one()).invert(),
+                )
+                .ok_or(PlonkError::NonInvertible)?;
+                let mut evals = parallel::map(&elements, |i, element| {
+                    numerator(&tables, i, &(*element * piece_shift)) * vanishing_inv
+                });
+                for (i, eval) in evals.iter().enumerate() {
+                    quotient_evals[i * pieces + k] = *eval;
+                }
+                zeroize_scalars(&mut evals);
+                tables.zeroize();
+                piece_shift *= quotient_domain.generator;
+            }
+            quotient_evals
+        } else {
+            // The vanishing polynomial of H never vanishes over the coset, so its inverses are
+            // computed at once.
+            let mut tables = tables(&quotient_domain, &shift);
+            let coset_elements = quotient_domain.coset_elements();
+            let mut vanishing_inv = coset_elements
+                .iter()
+                .map(|x| x.pow_vartime([n as u64]) - Scalar::one())
+                .collect::<Vec<Scalar>>();
+            batch_invert(&mut vanishing_inv)?;
+
+            let quotient_evals = parallel::map(&coset_elements, |j, x| {
+                numerator(&tables, j, x) * vanishing_inv[j]
+            });
+            tables.zeroize();
+            quotient_evals
+        };
+
+        let mut quotient_poly = quotient_domain.coset_ifft(&quotient_evals);
+        quotient_poly.remove_zeros();
+
+        // If the numerator is not divisible by the vanishing polynomial (meaning that the witness
+        // does not satisfy the circuit), the interpolated "quotient" has a much higher degree.
+        if quotient_poly.0.len() > 3 * n + 7 {
+            return Err(PlonkError::PublicInputsUnsatisfied);
+        }
+
+        // Now we need to split the polynomial into three polynomials of degree at most n + 6.
+        let (b10, b11) = (Scalar::random(&mut *rng), Scalar::random(&mut *rng));
+
+        let mut randomisation_poly_degree_n = Polynomial(vec![
+            Scalar::zero();
+            prover_key.constraints.nr_constraints
+                + 1
+        ]);
+        randomisation_poly_degree_n.0[prover_key.constraints.nr_constraints] = b10;
+        let mut quotient_low =
+            Polynomial(quotient_poly.0[..prover_key.constraints.nr_constraints].to_vec())
                 + &randomisation_poly_degree_n;
 
         randomisation_poly_degree_n.0[0] = b10.neg();
-        randomisation_poly_degree_n.0[pre_in.constraints.nr_constraints] = b11;
-        let (quotient_mid, quotient_high) = match (quotient_poly.0.len() - 1)
-            / pre_in.constraints.nr_constraints
-        {
-            0 => (
-                Polynomial::zero(pre_in.constraints.nr_constraints) + randomisation_poly_degree_n,
-                Polynomial::zero(pre_in.constraints.nr_constraints) + b11.neg(),
-            ),
-            1 => (
-                Polynomial(quotient_poly.0[pre_in.constraints.nr_constraints..].to_vec())
-                    + randomisation_poly_degree_n,
-                Polynomial::zero(pre_in.constraints.nr_constraints) + b11.neg(),
-            ),
-            _ => {
-                if (quotient_poly.0.len() - 1) > 3 * pre_in.constraints.nr_constraints + 5 {
-                    panic!(
-                        "Unexpected quotient_poly length: {:?} with {:?} constraints",
-                        quotient_poly.0.len(),
-                        pre_in.constraints.nr_constraints
+        randomisation_poly_degree_n.0[prover_key.constraints.nr_constraints] = b11;
+        let (mut quotient_mid, mut quotient_high) =
+            match (quotient_poly.0.len() - 1) / prover_key.constraints.nr_constraints {
+                0 => (
+                    Polynomial::zero(prover_key.constraints.nr_constraints)
+                        + randomisation_poly_degree_n,
+                    Polynomial::zero(prover_key.constraints.nr_constraints) + b11.neg(),
+                ),
+                1 => (
+                    Polynomial(quotient_poly.0[prover_key.constraints.nr_constraints..].to_vec())
+                        + randomisation_poly_degree_n,
+                    Polynomial::zero(prover_key.constraints.nr_constraints) + b11.neg(),
+                ),
+                _ => {
+                    if (quotient_poly.0.len() - 1) > 3 * prover_key.constraints.nr_constraints + 6 {
+                        panic!(
+                            "Unexpected quotient_poly length: {:?} with {:?} constraints",
+                            quotient_poly.0.len(),
+                            prover_key.constraints.nr_constraints
+                        )
+                    }
+                    (
+                        Polynomial(
+                            quotient_poly.0[prover_key.constraints.nr_constraints
+                                ..2 * prover_key.constraints.nr_constraints]
+                                .to_vec(),
+                        ) + randomisation_poly_degree_n,
+                        Polynomial(
+                            quotient_poly.0[2 * prover_key.constraints.nr_constraints..].to_vec(),
+                        ) + b11.neg(),
                     )
                 }
-                (
-                    Polynomial(
-                        quotient_poly.0[pre_in.constraints.nr_constraints
-                            ..2 * pre_in.constraints.nr_constraints]
-                            .to_vec(),
-                    ) + randomisation_poly_degree_n,
-                    Polynomial(quotient_poly.0[2 * pre_in.constraints.nr_constraints..].to_vec())
-                        + b11.neg(),
-                )
-            }
-        };
+            };
 
-        let quotient_low_comm = pre_in.kzg_set.commit(&quotient_low);
-        let quotient_mid_comm = pre_in.kzg_set.commit(&quotient_mid);
-        let quotient_high_comm = pre_in.kzg_set.commit(&quotient_high);
+        let quotient_low_comm = prover_key.scheme.commit(&quotient_low);
+        let quotient_mid_comm = prover_key.scheme.commit(&quotient_mid);
+        let quotient_high_comm = prover_key.scheme.commit(&quotient_high);
 
-        transcript.append_point(b"Quotient low polynomial", &quotient_low_comm.0);
-        transcript.append_point(b"Quotient mid polynomial", &quotient_mid_comm.0);
-        transcript.append_point(b"Quotient high polynomial", &quotient_high_comm.0);
+        P::append_commitment(transcript, b"Quotient low polynomial", &quotient_low_comm);
+        P::append_commitment(transcript, b"Quotient mid polynomial", &quotient_mid_comm);
+        P::append_commitment(transcript, b"Quotient high polynomial", &quotient_high_comm);
 
         // Now we proceed in computing opening evaluations
         let zeta = transcript.challenge_scalar(b"zeta");
         let a_eval = a_poly.eval(&zeta);
         let b_eval = b_poly.eval(&zeta);
         let c_eval = c_poly.eval(&zeta);
-        let s_sig1 = pre_in.qs1_x.eval(&zeta);
-        let s_sig2 = pre_in.qs2_x.eval(&zeta);
+        let s_sig1 = prover_key.qs1_x.eval(&zeta);
+        let s_sig2 = prover_key.qs2_x.eval(&zeta);
         let z_omega = permutation_polynomial
-            .scale(pre_in.constraints.extended_h_subgroup[0])
+            .scale(prover_key.domain.generator)
             .eval(&zeta);
+        let zeta_omega = zeta * prover_key.domain.generator;
+        let f_eval = f_poly.eval(&zeta);
+        let qt_eval = prover_key.qt_x.eval(&zeta);
+        let table_eval = table_poly.eval(&zeta);
+        let h2_eval = h2_poly.eval(&zeta);
+        let table_omega = table_poly.eval(&zeta_omega);
+        let h1_omega = h1_poly.eval(&zeta_omega);
+        let z_lookup_omega = lookup_polynomial.eval(&zeta_omega);
+        let a_omega = a_poly.eval(&zeta_omega);
 
         transcript.append_scalar(b"Append a_eval.", &a_eval);
         transcript.append_scalar(b"Append b_eval.", &b_eval);
@@ -249,105 +569,338 @@ impl Prover {
         transcript.append_scalar(b"Append s_sig1.", &s_sig1);
         transcript.append_scalar(b"Append s_sig2.", &s_sig2);
         transcript.append_scalar(b"Append z_omega.", &z_omega);
+        transcript.append_scalar(b"Append f_eval.", &f_eval);
+        transcript.append_scalar(b"Append qt_eval.", &qt_eval);
+        transcript.append_scalar(b"Append table_eval.", &table_eval);
+        transcript.append_scalar(b"Append h2_eval.", &h2_eval);
+        transcript.append_scalar(b"Append table_omega.", &table_omega);
+        transcript.append_scalar(b"Append h1_omega.", &h1_omega);
+        transcript.append_scalar(b"Append z_lookup_omega.", &z_lookup_omega);
+        transcript.append_scalar(b"Append a_omega.", &a_omega);
 
-        // Now we proceed with the final phase, were we compute the linearisation polynomial, and the proof opening.
-        let v = transcript.challenge_scalar(b"v");
-
-        let mut linearisation_poly = Polynomial::zero(pre_in.constraints.nr_constraints);
-
-        linearisation_poly += &pre_in.qm_x * a_eval * b_eval
-            + &pre_in.ql_x * a_eval
-            + &pre_in.qr_x * b_eval
-            + &pre_in.qo_x * c_eval
-            + pi.eval(&zeta)
-            + &pre_in.qc_x;
-        linearisation_poly += (&permutation_polynomial
-            * (a_eval + beta * zeta + gamma)
-            * (b_eval + beta * zeta * K1() + gamma)
-            * (c_eval + beta * zeta * K2() + gamma)
-            - (&pre_in.qs3_x * beta + gamma + c_eval)
-                * (a_eval + beta * s_sig1 + gamma)
-                * (b_eval + beta * s_sig2 + gamma)
-                * z_omega)
-            * alpha;
-        linearisation_poly += (&permutation_polynomial + Scalar::one().neg())
-            * pre_in.constraints.lagrange_basis(0).eval(&zeta)
-            * alpha
-            * alpha;
-        linearisation_poly = &linearisation_poly
-            - (quotient_low
-                + quotient_mid
-                    * zeta.pow_vartime([pre_in.constraints.nr_constraints as u64, 0, 0, 0])
-                + quotient_high
-                    * zeta.pow_vartime([2 * pre_in.constraints.nr_constraints as u64, 0, 0, 0]))
-                * pre_in.blinder_polynomial.eval(&zeta);
-
-        // Now we compute the opening proof polynomial:
-        let mut w_omega = linearisation_poly.clone();
-        w_omega += (a_poly + a_eval.neg()) * v;
-        w_omega += (b_poly + b_eval.neg()) * v * v;
-        w_omega += (c_poly + c_eval.neg()) * v * v * v;
-        w_omega += (&pre_in.qs1_x + s_sig1.neg()) * v * v * v * v;
-        w_omega += (&pre_in.qs2_x + s_sig2.neg()) * v * v * v * v * v;
-
-        assert_eq!(w_omega.eval(&zeta), Scalar::zero());
-
-        w_omega = w_omega / Polynomial(vec![zeta.neg(), Scalar::one()]);
-
-        let mut w_omega_zeta = permutation_polynomial + z_omega.neg();
-
-        assert_eq!(
-            w_omega_zeta.eval(&(zeta * pre_in.constraints.extended_h_subgroup[0])),
-            Scalar::zero()
+        // The scheme proves the evaluations, with the polynomials they come from.
+        let opening = P::open_proof(
+            &ProofPolynomials {
+                prover_key,
+                pi: &pi,
+                a: &a_poly,
+                b: &b_poly,
+                c: &c_poly,
+                f: &f_poly,
+                h1: &h1_poly,
+                h2: &h2_poly,
+                z: &permutation_polynomial,
+                z_lookup: &lookup_polynomial,
+                table: &table_poly,
+                quotient: [&quotient_low, &quotient_mid, &quotient_high],
+                challenges: Challenges {
+                    eta,
+                    beta,
+                    gamma,
+                    delta,
+                    epsilon,
+                    alpha,
+                    zeta,
+                },
+                evaluations: [
+                    a_eval,
+                    b_eval,
+                    c_eval,
+                    s_sig1,
+                    s_sig2,
+                    z_omega,
+                    f_eval,
+                    qt_eval,
+                    table_eval,
+                    h2_eval,
+                    table_omega,
+                    h1_omega,
+                    z_lookup_omega,
+                    a_omega,
+                ],
+            },
+            transcript,
         );
 
-        w_omega_zeta = w_omega_zeta
-            / Polynomial(vec![
-                (zeta * pre_in.constraints.extended_h_subgroup[0]).neg(),
-                Scalar::one(),
-            ]);
-
-        let w_omega_comm = pre_in.kzg_set.commit(&w_omega);
-        let w_omega_zeta_comm = pre_in.kzg_set.commit(&w_omega_zeta);
-
-        transcript.append_point(b"w_omega comm", &w_omega_comm.0);
-        transcript.append_point(b"w_omega_zeta comm", &w_omega_zeta_comm.0);
+        // Every polynomial computed from the witness is wiped before returning the proof.
+        for poly in [
+            &mut a_poly,
+            &mut b_poly,
+            &mut c_poly,
+            &mut f_poly,
+            &mut h1_poly,
+            &mut h2_poly,
+            &mut permutation_polynomial,
+            &mut lookup_polynomial,
+            &mut z_omega_poly,
+            &mut a_omega_poly,
+            &mut h1_omega_poly,
+            &mut z_lookup_omega_poly,
+            &mut quotient_poly,
+            &mut quotient_low,
+            &mut quotient_mid,
+            &mut quotient_high,
+        ] {
+            poly.zeroize();
+        }
+        zeroize_scalars(&mut quotient_evals);
 
-        PlonkProof {
+        Ok(PlonkProof {
             commitment_a,
             commitment_b,
             commitment_c,
+            commitment_f,
+            commitment_h1,
+            commitment_h2,
             commitment_z,
+            commitment_z_lookup,
             t_low: quotient_low_comm,
             t_mid: quotient_mid_comm,
             t_high: quotient_high_comm,
-            w_omega: w_omega_comm,
-            w_omega_zeta: w_omega_zeta_comm,
             a_eval,
             b_eval,
             c_eval,
             s_sig1,
             s_sig2,
             z_omega,
-        }
+            f_eval,
+            qt_eval,
+            table_eval,
+            h2_eval,
+            table_omega,
+            h1_omega,
+            z_lookup_omega,
+            a_omega,
+            opening,
+        })
     }
 }
 
-// We use this function to check that a polynomial is zero in all the set H.
-fn check_subrgoup_zero(h_subgroup: &[Scalar], poly: &Polynomial) -> bool {
-    h_subgroup
-        .iter()
-        .all(|val| poly.eval(val) == Scalar::zero())
+/// Polynomials of a proof once their evaluations are absorbed by the transcript, with the
+/// challenges drawn so far, from which the scheme proves the evaluations.
+pub struct ProofPolynomials<'a, P: PlonkCommitment> {
+    pub(crate) prover_key: &'a ProverKey<P>,
+    pub(crate) pi: &'a Polynomial,
+    pub(crate) a: &'a Polynomial,
+    pub(crate) b: &'a Polynomial,
+    pub(crate) c: &'a Polynomial,
+    pub(crate) f: &'a Polynomial,
+    pub(crate) h1: &'a Polynomial,
+    pub(crate) h2: &'a Polynomial,
+    pub(crate) z: &'a Polynomial,
+    pub(crate) z_lookup: &'a Polynomial,
+    pub(crate) table: &'a Polynomial,
+    pub(crate) quotient: [&'a Polynomial; 3], // low, mid and high parts
+    pub(crate) challenges: Challenges,
+    pub(crate) evaluations: [Scalar; 14], // in the order of `PlonkProof::evaluations`
+}
+
+// Opening of the evaluations with KZG, from the linearisation polynomial. Every term of the
+// numerator of the quotient is linear in one polynomial once the others are replaced by their
+// openings at zeta, so the verifier rebuilds the commitment to r(x) from the commitments it
+// has, and none of q_M, q_L, q_R, q_O, q_C, q_K, q_N or s_sig3 is opened. The openings left
+// are those of the products of two committed polynomials: the wires, s_sig1 and s_sig2, q_T
+// (multiplied by q_K), the lookup polynomials and the shifted ones.
+pub(crate) fn linearised_opening<T: Transcript>(
+    polynomials: &ProofPolynomials<Kzg10>,
+    transcript: &mut T,
+) -> LinearisedOpening {
+    let &ProofPolynomials {
+        prover_key,
+        pi,
+        a: a_poly,
+        b: b_poly,
+        c: c_poly,
+        f: f_poly,
+        h1: h1_poly,
+        h2: h2_poly,
+        z: permutation_polynomial,
+        z_lookup: lookup_polynomial,
+        table: table_poly,
+        quotient: [quotient_low, quotient_mid, quotient_high],
+        challenges,
+        evaluations,
+    } = polynomials;
+    let Challenges {
+        eta,
+        beta,
+        gamma,
+        delta,
+        epsilon,
+        alpha,
+        zeta,
+    } = challenges;
+    let [a_eval, b_eval, c_eval, s_sig1, s_sig2, z_omega, f_eval, qt_eval, table_eval, h2_eval, table_omega, h1_omega, z_lookup_omega, a_omega] =
+        evaluations;
+    let alpha_powers = [
+        alpha,
+        alpha.square(),
+        alpha.pow_vartime([3]),
+        alpha.pow_vartime([4]),
+        alpha.pow_vartime([5]),
+    ];
+    let one_plus_delta = Scalar::one() + delta;
+    let epsilon_one_plus_delta = epsilon * one_plus_delta;
+
+    let mut linearisation_poly = Polynomial::zero(prover_key.constraints.nr_constraints);
+
+    linearisation_poly += &prover_key.qm_x * a_eval * b_eval
+        + &prover_key.ql_x * a_eval
+        + &prover_key.qr_x * b_eval
+        + &prover_key.qo_x * c_eval
+        + &prover_key.qn_x * a_omega
+        + pi.eval(&zeta)
+        + &prover_key.qc_x;
+    linearisation_poly += (permutation_polynomial
+        * (a_eval + beta * zeta + gamma)
+        * (b_eval + beta * zeta * K1() + gamma)
+        * (c_eval + beta * zeta * K2() + gamma)
+        - (&prover_key.qs3_x * beta + gamma + c_eval)
+            * (a_eval + beta * s_sig1 + gamma)
+            * (b_eval + beta * s_sig2 + gamma)
+            * z_omega)
+        * alpha;
+    let l0_eval = prover_key.domain.evaluate_lagrange(0, &zeta);
+    linearisation_poly +=
+        (permutation_polynomial + Scalar::one().neg()) * l0_eval * alpha_powers[1];
+    linearisation_poly += &prover_key.qk_x
+        * (compress(&[a_eval, b_eval, c_eval, qt_eval], &eta) - f_eval)
+        * alpha_powers[2];
+    linearisation_poly += (lookup_polynomial + Scalar::one().neg()) * l0_eval * alpha_powers[3];
+    linearisation_poly += (lookup_polynomial
+        * one_plus_delta
+        * (epsilon + f_eval)
+        * (epsilon_one_plus_delta + table_eval + delta * table_omega)
+        - (h1_poly + epsilon_one_plus_delta + delta * h2_eval)
+            * (epsilon_one_plus_delta + h2_eval + delta * h1_omega)
+            * z_lookup_omega)
+        * alpha_powers[4];
+    linearisation_poly = &linearisation_poly
+        - (quotient_low
+            + quotient_mid
+                * zeta.pow_vartime([prover_key.constraints.nr_constraints as u64, 0, 0, 0])
+            + quotient_high
+                * zeta.pow_vartime([2 * prover_key.constraints.nr_constraints as u64, 0, 0, 0]))
+            * prover_key.blinder_polynomial.eval(&zeta);
+
+    // The linearisation is opened at zeta with the polynomials evaluated there, and its
+    // evaluation is zero: its commitment, which the verifier computes from the others, is
+    // then that of a polynomial that vanishes at zeta.
+    let opening = prover_key.scheme.open_two_points(
+        [
+            &[
+                &linearisation_poly,
+                a_poly,
+                b_poly,
+                c_poly,
+                &prover_key.qs1_x,
+                &prover_key.qs2_x,
+                f_poly,
+                table_poly,
+                h2_poly,
+                &prover_key.qt_x,
+            ],
+            &[
+                permutation_polynomial,
+                lookup_polynomial,
+                table_poly,
+                h1_poly,
+                a_poly,
+            ],
+        ],
+        &[zeta, zeta * prover_key.domain.generator],
+        transcript,
+    );
+    assert_eq!(opening.openings[0].evaluations[0], Scalar::zero());
+
+    // This polynomial depends on the witness.
+    linearisation_poly.zeroize();
+
+    let [w_omega, w_omega_zeta] = opening.openings.map(|opening| opening.witness);
+    LinearisedOpening {
+        w_omega,
+        w_omega_zeta,
+    }
+}
+
+// Opening of the evaluations with the schemes without linearisation: every polynomial is
+// opened at zeta, and those of the next row at zeta * omega as well, in the order in which the
+// verifier takes their commitments.
+pub(crate) fn open_polynomials<P: PlonkCommitment, T: Transcript>(
+    polynomials: &ProofPolynomials<P>,
+    transcript: &mut T,
+) -> P::BatchOpening {
+    let &ProofPolynomials {
+        prover_key: key,
+        a,
+        b,
+        c,
+        f,
+        h1,
+        h2,
+        z,
+        z_lookup,
+        quotient: [t_low, t_mid, t_high],
+        challenges,
+        ..
+    } = polynomials;
+    let mut opened = [
+        a,
+        b,
+        c,
+        &key.qs1_x,
+        &key.qs2_x,
+        &key.qs3_x,
+        &key.ql_x,
+        &key.qr_x,
+        &key.qm_x,
+        &key.qo_x,
+        &key.qc_x,
+        &key.qn_x,
+        &key.qk_x,
+        &key.qt_x,
+        &key.table1_x,
+        &key.table2_x,
+        &key.table3_x,
+        &key.table4_x,
+        f,
+        h1,
+        h2,
+        z,
+        z_lookup,
+        t_low,
+        t_mid,
+        t_high,
+    ]
+    .map(Clone::clone);
+    let points = opening_points(&challenges.zeta, &key.domain.generator);
+    let opening = key.scheme.batch_open(&opened, &points, transcript);
+    for poly in opened.iter_mut() {
+        poly.zeroize();
+    }
+    opening
 }
 
 #[cfg(test)]
 mod test {
-    use crate::plonk::{ComputationTrace, PlonkCircuit, PreprocessedInput};
+    use crate::error::PlonkError;
+    use crate::plonk::{ComputationTrace, PlonkCircuit, ProverKey};
+    use crate::proof::PlonkProof;
     use crate::prover::Prover;
-    use crate::transcript::Transcript;
+    use crate::transcript::{Blake2bTranscript, Transcript};
+    use crate::verifier::PlonkVerifier;
+    use blake2::{digest::consts::U32, Blake2b, Digest};
     use blstrs::Scalar;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::SeedableRng;
+
+    // Blake2b-256 hash of the proof of `test_prove_deterministic`.
+    const SNAPSHOT: [u8; 32] = [
+        87, 44, 72, 115, 54, 212, 230, 20, 18, 211, 155, 32, 225, 40, 231, 176, 244, 229, 209, 101,
+        104, 45, 66, 97, 116, 42, 77, 178, 167, 60, 29, 130,
+    ];
 
-    fn create_dummy_circuit_and_prover_key() -> (PreprocessedInput, ComputationTrace, Vec<Scalar>) {
+    fn create_dummy_circuit_and_prover_key() -> (ProverKey, ComputationTrace, Vec<Scalar>) {
         // We are going to begin with a simple proof, showing that I know the value of
         // a pythagorean triplet. i.e., three values such that x^2 + y^2 = z^2;
         let mut circuit = PlonkCircuit::init();
@@ -362,14 +915,16 @@ mod test {
         // Gates are finished, so here we pad to the next power of two
         circuit.pad_next_power_of_two();
 
-        circuit.connect_wires(&0, &4);
-        circuit.connect_wires(&3, &8);
-        circuit.connect_wires(&1, &5);
-        circuit.connect_wires(&7, &9);
-        circuit.connect_wires(&2, &6);
-        circuit.connect_wires(&10, &11);
+        circuit.connect_wires(&0, &4).unwrap();
+        circuit.connect_wires(&3, &8).unwrap();
+        circuit.connect_wires(&1, &5).unwrap();
+        circuit.connect_wires(&7, &9).unwrap();
+        circuit.connect_wires(&2, &6).unwrap();
+        circuit.connect_wires(&10, &11).unwrap();
 
-        let setup = circuit.setup();
+        let (setup, _) = circuit
+            .setup(&mut ChaCha20Rng::from_seed([0u8; 32]))
+            .unwrap();
 
         // as a computation trace, we'll create the proof for the values (3,4,5)
         let computation_trace = ComputationTrace {
@@ -398,8 +953,173 @@ mod test {
     }
     #[test]
     fn test_prover() {
-        let mut transcript = Transcript::new(b"testing the prover");
+        let mut transcript = Blake2bTranscript::new(b"testing the prover");
+        let (pre_in, trace, pub_in) = create_dummy_circuit_and_prover_key();
+        let _proof = Prover::prove(&pub_in, &pre_in, &trace, &mut transcript).unwrap();
+    }
+
+    #[test]
+    fn test_prover_streaming() {
+        let (pre_in, trace, pub_in) = create_dummy_circuit_and_prover_key();
+        let mut transcript = Blake2bTranscript::new(b"testing the prover");
+        let proof = Prover::prove_streaming(&pub_in, &pre_in, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"testing the prover");
+        assert!(
+            PlonkVerifier::verify(&pub_in, &pre_in.verifier_key, &proof, &mut transcript).is_ok()
+        );
+
+        // With the same blinders, both provers create the same proof.
+        let prove = |streaming: bool| {
+            let mut transcript = Blake2bTranscript::new(b"testing the prover");
+            let mut rng = ChaCha20Rng::from_seed([1u8; 32]);
+            let prove = if streaming {
+                Prover::prove_streaming_with_rng
+            } else {
+                Prover::prove_with_rng
+            };
+            prove(&pub_in, &pre_in, &trace, &mut transcript, &mut rng)
+                .unwrap()
+                .to_bytes()
+        };
+        assert_eq!(prove(true), prove(false));
+    }
+
+    #[test]
+    fn test_prove_deterministic() {
+        let (pre_in, trace, pub_in) = create_dummy_circuit_and_prover_key();
+        let prove = |seed: u8| {
+            let mut transcript = Blake2bTranscript::new(b"testing the prover");
+            Prover::prove_deterministic(&pub_in, &pre_in, &trace, &mut transcript, [seed; 32])
+                .unwrap()
+        };
+        let proof = prove(1);
+        let mut transcript = Blake2bTranscript::new(b"testing the prover");
+        assert!(
+            PlonkVerifier::verify(&pub_in, &pre_in.verifier_key, &proof, &mut transcript).is_ok()
+        );
+        assert_ne!(proof.to_bytes(), prove(2).to_bytes());
+
+        // Test vector: the keys of `setup` are deterministic as well, so the proof stays the
+        // same across runs and platforms.
+        let digest: [u8; 32] = Blake2b::<U32>::digest(proof.to_bytes()).into();
+        assert_eq!(digest, SNAPSHOT);
+    }
+
+    #[test]
+    fn test_blinding() {
         let (pre_in, trace, pub_in) = create_dummy_circuit_and_prover_key();
-        let _proof = Prover::prove(&pub_in, &pre_in, &trace, &mut transcript);
+        let prove = |trace: &ComputationTrace, seed: u8| {
+            let mut transcript = Blake2bTranscript::new(b"testing the prover");
+            let mut rng = ChaCha20Rng::from_seed([seed; 32]);
+            Prover::prove_with_rng(&pub_in, &pre_in, trace, &mut transcript, &mut rng).unwrap()
+        };
+
+        // The proof only depends on the witness through the blinded polynomials, so with
+        // different blinders every commitment and every opening of them changes.
+        let (proof, other_proof) = (prove(&trace, 1), prove(&trace, 2));
+        assert_eq!(proof.to_bytes(), prove(&trace, 1).to_bytes());
+        assert!(proof
+            .commitments()
+            .iter()
+            .zip(other_proof.commitments().iter())
+            .all(|(lhs, rhs)| lhs.0 != rhs.0));
+        let openings = |proof: &PlonkProof| {
+            [
+                proof.a_eval,
+                proof.b_eval,
+                proof.c_eval,
+                proof.z_omega,
+                proof.f_eval,
+                proof.h2_eval,
+                proof.h1_omega,
+                proof.z_lookup_omega,
+            ]
+        };
+        assert!(openings(&proof)
+            .iter()
+            .zip(openings(&other_proof).iter())
+            .all(|(lhs, rhs)| lhs != rhs));
+
+        // The proofs of another witness, 6^2 + 8^2 = 10^2, are valid proofs of the same circuit.
+        let values = |values: [u64; 4]| values.map(Scalar::from).to_vec();
+        let other_trace = ComputationTrace {
+            a: values([6, 8, 10, 36]),
+            b: values([6, 8, 10, 64]),
+            c: values([36, 64, 100, 100]),
+        }
+        .pad_next_power_two();
+        let other_proof = prove(&other_trace, 1);
+        let mut transcript = Blake2bTranscript::new(b"testing the prover");
+        assert!(PlonkVerifier::verify(
+            &pub_in,
+            &pre_in.verifier_key,
+            &other_proof,
+            &mut transcript
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_proof_bytes() {
+        let mut transcript = Blake2bTranscript::new(b"testing the prover");
+        let (pre_in, trace, pub_in) = create_dummy_circuit_and_prover_key();
+        let proof = Prover::prove(&pub_in, &pre_in, &trace, &mut transcript).unwrap();
+
+        let bytes = proof.to_bytes();
+        assert_eq!(bytes.len(), PlonkProof::SIZE);
+        assert_eq!(PlonkProof::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+
+        assert_eq!(
+            PlonkProof::from_bytes(&bytes[1..]).err(),
+            Some(PlonkError::InvalidLength {
+                expected: PlonkProof::SIZE,
+                got: PlonkProof::SIZE - 1
+            })
+        );
+
+        let mut invalid_point = bytes.clone();
+        invalid_point[48..96].copy_from_slice(&[0xffu8; 48]);
+        assert_eq!(
+            PlonkProof::from_bytes(&invalid_point).err(),
+            Some(PlonkError::InvalidPoint)
+        );
+
+        let mut invalid_scalar = bytes;
+        invalid_scalar[PlonkProof::SIZE - 32..].copy_from_slice(&[0xffu8; 32]);
+        assert_eq!(
+            PlonkProof::from_bytes(&invalid_scalar).err(),
+            Some(PlonkError::InvalidScalar)
+        );
+    }
+
+    #[test]
+    fn test_prover_invalid_trace() {
+        let mut transcript = Blake2bTranscript::new(b"testing the prover");
+        let (pre_in, mut trace, pub_in) = create_dummy_circuit_and_prover_key();
+
+        // We claim that 3 * 3 = 10, and propagate the value through the copy constraints.
+        trace.c[0] = Scalar::from(10);
+        trace.a[3] = Scalar::from(10);
+        assert_eq!(
+            Prover::prove(&pub_in, &pre_in, &trace, &mut transcript).err(),
+            Some(PlonkError::GateFailed {
+                row: 0,
+                label: None
+            })
+        );
+
+        // If we don't propagate it, it is the copy constraint that fails.
+        trace.a[3] = Scalar::from(9);
+        assert!(matches!(
+            Prover::prove(&pub_in, &pre_in, &trace, &mut transcript),
+            Err(PlonkError::CopyConstraintUnsatisfied { .. })
+        ));
+
+        // Columns of different lengths are rejected before they are padded.
+        trace.c.push(Scalar::from(0));
+        assert!(matches!(
+            Prover::prove(&pub_in, &pre_in, &trace, &mut transcript),
+            Err(PlonkError::TraceShape { .. })
+        ));
     }
 }
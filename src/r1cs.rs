@@ -0,0 +1,256 @@
+// Conversion of rank-1 constraint systems, as used by Groth16 toolchains, into PLONK gates. An
+// R1CS over the vector z = (1, public inputs, private variables) is a list of constraints
+// <A_i, z> * <B_i, z> = <C_i, z>, where A_i, B_i and C_i are sparse linear combinations.
+//
+// Every variable has a wire: public variables are public inputs, and private ones are laid out
+// three per row of a gate without selectors. The constant variable z_0 = 1 has no wire, and is
+// folded in the constants of the gates. Each linear combination is reduced to a single term
+// m * x + k with a gate per extra term, and the constraint becomes a single gate over the
+// reduced terms of A_i and B_i, whose output is connected to the reduced term of C_i:
+//
+// (m_A * x + k_A) * (m_B * y + k_B) / m_C - k_C / m_C = z
+//
+// A constraint with n terms over its three combinations uses about n - 2 gates.
+use crate::error::PlonkError;
+use crate::gadgets::arithmetic::{Arithmetic, Assigner, Builder, Combination};
+use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::Field;
+
+/// Sparse linear combination, as pairs (index of the variable, coefficient).
+pub type LinearCombination = Vec<(usize, Scalar)>;
+
+/// Rank-1 constraint system over z = (1, public inputs, private variables).
+#[derive(Clone, Debug, Default)]
+pub struct R1cs {
+    pub nr_variables: usize, // including the constant variable z_0 = 1
+    pub nr_public: usize,    // public inputs are z_1, ..., z_nr_public
+    pub constraints: Vec<(LinearCombination, LinearCombination, LinearCombination)>,
+}
+
+impl R1cs {
+    /// Whether `z` (starting with the constant one) satisfies every constraint.
+    pub fn is_satisfied(&self, z: &[Scalar]) -> bool {
+        let evaluate = |combination: &LinearCombination| {
+            combination
+                .iter()
+                .map(|(variable, coefficient)| {
+                    z.get(*variable).copied().unwrap_or_default() * coefficient
+                })
+                .sum::<Scalar>()
+        };
+        z.len() == self.nr_variables
+            && self
+                .constraints
+                .iter()
+                .all(|(a, b, c)| evaluate(a) * evaluate(b) == evaluate(c))
+    }
+}
+
+/// Wires created by `r1cs_to_plonk`, used to fill the rows of the constraints in the trace.
+#[derive(Clone, Debug)]
+pub struct R1csCircuit {
+    variables: Vec<Wire>, // wires of z_1, z_2, ...
+    nr_public: usize,
+    rows: Vec<usize>,
+}
+
+impl R1csCircuit {
+    /// Wire of the variable z_i, for i > 0.
+    pub fn variable(&self, index: usize) -> Wire {
+        self.variables[index - 1]
+    }
+
+    /// Public values of the proof for the assignment `z`.
+    pub fn public_inputs(&self, z: &[Scalar]) -> Vec<Scalar> {
+        z[1..=self.nr_public].to_vec()
+    }
+
+    /// Assigns the wires of the variables and the rows of the constraints for the assignment
+    /// `z`, whose first value is the constant one.
+    pub fn assign(
+        &self,
+        trace: &mut ComputationTrace,
+        r1cs: &R1cs,
+        z: &[Scalar],
+    ) -> Result<(), PlonkError> {
+        if z.len() != self.variables.len() + 1 {
+            return Err(PlonkError::InvalidLength {
+                expected: self.variables.len() + 1,
+                got: z.len(),
+            });
+        }
+        for (wire, value) in self.variables.iter().zip(&z[1..]) {
+            trace.set(*wire, *value);
+        }
+        let mut assigner = Assigner::new(trace, &self.rows);
+        constraints(&mut assigner, r1cs, &z[1..])
+    }
+}
+
+/// Adds the constraints of `r1cs` to `circuit`, with a public input per public variable.
+pub fn r1cs_to_plonk(circuit: &mut PlonkCircuit, r1cs: &R1cs) -> Result<R1csCircuit, PlonkError> {
+    // The constant variable and the public ones come first, so they must all exist.
+    if r1cs.nr_public >= r1cs.nr_variables {
+        return Err(PlonkError::UnknownVariable {
+            variable: r1cs.nr_public,
+            nr_variables: r1cs.nr_variables,
+        });
+    }
+    for (variable, _) in r1cs
+        .constraints
+        .iter()
+        .flat_map(|(a, b, c)| a.iter().chain(b).chain(c))
+    {
+        if *variable >= r1cs.nr_variables {
+            return Err(PlonkError::UnknownVariable {
+                variable: *variable,
+                nr_variables: r1cs.nr_variables,
+            });
+        }
+    }
+
+    let nr_private = r1cs.nr_variables - r1cs.nr_public - 1;
+    let mut variables = (0..r1cs.nr_public)
+        .map(|_| circuit.public_input())
+        .collect::<Vec<_>>();
+    for _ in 0..nr_private.div_ceil(3) {
+        let (a, b, c) = circuit.gate(Gate::new());
+        variables.extend([a, b, c]);
+    }
+    variables.truncate(r1cs.nr_variables - 1);
+
+    let mut builder = Builder::new(circuit);
+    constraints(&mut builder, r1cs, &variables)?;
+    Ok(R1csCircuit {
+        variables,
+        nr_public: r1cs.nr_public,
+        rows: builder.rows,
+    })
+}
+
+// Constraints of `r1cs` over the values of z_1, z_2, ...
+fn constraints<A: Arithmetic>(
+    ops: &mut A,
+    r1cs: &R1cs,
+    variables: &[A::Value],
+) -> Result<(), PlonkError> {
+    let reduce = |ops: &mut A, combination: &LinearCombination| {
+        let mut reduced = Combination::constant(Scalar::zero());
+        for (variable, coefficient) in combination.iter() {
+            match variable {
+                0 => reduced.constant += coefficient,
+                _ if coefficient.is_zero_vartime() => {}
+                _ => reduced.terms.push((variables[variable - 1], *coefficient)),
+            }
+        }
+        reduced.reduce(ops)?;
+        Ok::<_, PlonkError>(reduced)
+    };
+
+    for (a, b, c) in r1cs.constraints.iter() {
+        let (a, b, c) = (reduce(ops, a)?, reduce(ops, b)?, reduce(ops, c)?);
+        // A combination without terms is a constant, which we multiply with any value.
+        let term =
+            |combination: &Combination<A::Value>, ops: &mut A| match combination.terms.first() {
+                Some(term) => Ok(*term),
+                None => Ok::<_, PlonkError>((ops.constant(Scalar::zero())?, Scalar::zero())),
+            };
+        let ((x, m_a), (y, m_b)) = (term(&a, ops)?, term(&b, ops)?);
+        let (k_a, k_b) = (a.constant, b.constant);
+
+        let (z, m_c) = term(&c, ops)?;
+        let scale = if m_c.is_zero_vartime() {
+            Scalar::one()
+        } else {
+            m_c.invert().unwrap()
+        };
+        let product = ops.gate(
+            Gate::new()
+                .mul(m_a * m_b * scale)
+                .left(m_a * k_b * scale)
+                .right(k_a * m_b * scale)
+                .constant((k_a * k_b - c.constant) * scale),
+            x,
+            y,
+        )?;
+        ops.assert_equal(product, z)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover::Prover;
+    use crate::transcript::{Blake2bTranscript, Transcript};
+    use crate::verifier::PlonkVerifier;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_r1cs_to_plonk() {
+        // x^3 + x + 5 = out, over z = (1, out, x, x^2, x^3, x^3 + x).
+        let one = Scalar::one();
+        let r1cs = R1cs {
+            nr_variables: 6,
+            nr_public: 1,
+            constraints: vec![
+                (vec![(2, one)], vec![(2, one)], vec![(3, one)]),
+                (vec![(3, one)], vec![(2, one)], vec![(4, one)]),
+                (vec![(4, one), (2, one)], vec![(0, one)], vec![(5, one)]),
+                (
+                    vec![(5, one), (0, Scalar::from(5))],
+                    vec![(0, one)],
+                    vec![(1, one)],
+                ),
+            ],
+        };
+        let z = [1u64, 35, 3, 9, 27, 30].map(Scalar::from);
+        assert!(r1cs.is_satisfied(&z));
+
+        let mut circuit = PlonkCircuit::init();
+        let gadget = r1cs_to_plonk(&mut circuit, &r1cs).unwrap();
+        assert_eq!(gadget.variable(1), Wire::left(0));
+        circuit.pad_next_power_of_two();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        gadget.assign(&mut trace, &r1cs, &z).unwrap();
+        assert_eq!(prover_key.constraints.is_satisfied(&trace), Ok(()));
+        let public_inputs = gadget.public_inputs(&z);
+        let mut transcript = Blake2bTranscript::new(b"testing r1cs");
+        let proof = Prover::prove(&public_inputs, &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"testing r1cs");
+        assert!(
+            PlonkVerifier::verify(&public_inputs, &verifier_key, &proof, &mut transcript).is_ok()
+        );
+
+        let wrong = [1u64, 35, 3, 9, 27, 31].map(Scalar::from);
+        assert!(!r1cs.is_satisfied(&wrong));
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        gadget.assign(&mut trace, &r1cs, &wrong).unwrap();
+        assert!(prover_key.constraints.is_satisfied(&trace).is_err());
+
+        let mut invalid = r1cs.clone();
+        invalid.constraints[0].0[0].0 = 6;
+        assert_eq!(
+            r1cs_to_plonk(&mut PlonkCircuit::init(), &invalid).err(),
+            Some(PlonkError::UnknownVariable {
+                variable: 6,
+                nr_variables: 6
+            })
+        );
+
+        // More public variables than variables.
+        let mut invalid = r1cs;
+        invalid.nr_public = 6;
+        assert_eq!(
+            r1cs_to_plonk(&mut PlonkCircuit::init(), &invalid).err(),
+            Some(PlonkError::UnknownVariable {
+                variable: 6,
+                nr_variables: 6
+            })
+        );
+    }
+}
@@ -0,0 +1,801 @@
+// Generator of Solidity verifiers. The contract is specialised to a single `VerifierKey`: the
+// domain, the rows of the public inputs and the commitments of the circuit are constants, and
+// so is the state of the transcript after absorbing the verifier key, which the contract
+// continues with keccak256 exactly as `KeccakTranscript` does.
+//
+// Group operations use the BLS12-381 precompiles of EIP-2537 (available since Prague), and
+// the final check is written as a pairing check of two pairs:
+//
+//   e(W + u * W', x * g2) * e(-rhs, g2) = 1,
+//
+// where both G1 points are computed with a single multi-scalar multiplication each, by
+// expanding the commitments that the native verifier combines into one term per point. The
+// negation of rhs is folded into its scalars, so that the contract never negates a point.
+//
+// Proofs are passed to the contract as returned by `PlonkProof::to_evm_bytes`, and a whole
+// call is encoded by `PlonkProof::to_evm_calldata`.
+use crate::circom::modulus;
+use crate::evm::{encode_g1, encode_g2};
+use crate::transcript::{KeccakTranscript, Transcript};
+use crate::verifier::VerifierKey;
+use crate::wire::{K1, K2};
+use blstrs::Scalar;
+use ff::Field;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn uint(scalar: &Scalar) -> String {
+    format!("0x{}", hex(&scalar.to_bytes_be()))
+}
+
+/// Emits the source of a Solidity contract `PlonkVerifier` that verifies proofs of the
+/// circuit of `verifier_key`, created with a `KeccakTranscript` labelled `label`.
+pub fn solidity_verifier(verifier_key: &VerifierKey, label: &'static [u8]) -> String {
+    let mut transcript = KeccakTranscript::new(label);
+    verifier_key.append_to_transcript(&mut transcript);
+
+    let mut r = modulus();
+    r.reverse();
+    let two_64 = Scalar::from(u64::MAX) + Scalar::one();
+    let domain = &verifier_key.domain;
+    let roots = verifier_key
+        .public_inputs
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let root = domain.generator.pow_vartime([*row as u64]);
+            format!("        roots[{}] = {};\n", i, uint(&root))
+        })
+        .collect::<String>();
+
+    let mut source = TEMPLATE
+        .replace("{{LABEL}}", &label.escape_ascii().to_string())
+        .replace("{{R}}", &format!("0x{}", hex(&r)))
+        .replace("{{TWO_256}}", &uint(&two_64.pow_vartime([4])))
+        .replace("{{N}}", &domain.size.to_string())
+        .replace("{{N_INV}}", &uint(&domain.size_inv))
+        .replace("{{OMEGA}}", &uint(&domain.generator))
+        .replace("{{K1}}", &uint(&K1()))
+        .replace("{{K2}}", &uint(&K2()))
+        .replace(
+            "{{INITIAL_STATE}}",
+            &format!("0x{}", hex(&transcript.state)),
+        )
+        .replace(
+            "{{NR_PUBLIC_INPUTS}}",
+            &verifier_key.public_inputs.len().to_string(),
+        )
+        .replace("{{ROOTS}}", &roots)
+        .replace(
+            "{{G1}}",
+            &hex(&encode_g1(&verifier_key.scheme.powers_x_g1[0])),
+        )
+        .replace(
+            "{{G2}}",
+            &hex(&encode_g2(&verifier_key.scheme.powers_x_g2[0])),
+        )
+        .replace(
+            "{{X_G2}}",
+            &hex(&encode_g2(&verifier_key.scheme.powers_x_g2[1])),
+        );
+    for (name, commitment) in [
+        ("QM", &verifier_key.qm_comm),
+        ("QL", &verifier_key.ql_comm),
+        ("QR", &verifier_key.qr_comm),
+        ("QO", &verifier_key.qo_comm),
+        ("QC", &verifier_key.qc_comm),
+        ("QS1", &verifier_key.qs1_comm),
+        ("QS2", &verifier_key.qs2_comm),
+        ("QS3", &verifier_key.qs3_comm),
+        ("QK", &verifier_key.qk_comm),
+        ("TABLE1", &verifier_key.table1_comm),
+        ("TABLE2", &verifier_key.table2_comm),
+        ("TABLE3", &verifier_key.table3_comm),
+        ("TABLE4", &verifier_key.table4_comm),
+        ("QT", &verifier_key.qt_comm),
+        ("QN", &verifier_key.qn_comm),
+    ] {
+        source = source.replace(
+            &format!("{{{{{}_COMM}}}}", name),
+            &hex(&encode_g1(&commitment.0)),
+        );
+    }
+    source
+}
+
+const TEMPLATE: &str = r#"// SPDX-License-Identifier: MIT
+// Generated by dummy-plonk. Verifies the proofs of a single circuit, created with a
+// KeccakTranscript labelled "{{LABEL}}", with the BLS12-381 precompiles of EIP-2537.
+pragma solidity ^0.8.24;
+
+contract PlonkVerifier {
+    // Order of the scalar field, and 2^256 modulo it.
+    uint256 constant R = {{R}};
+    uint256 constant TWO_256 = {{TWO_256}};
+
+    // Domain of the circuit, and cosets of the permutation argument.
+    uint256 constant N = {{N}};
+    uint256 constant N_INV = {{N_INV}};
+    uint256 constant OMEGA = {{OMEGA}};
+    uint256 constant K1 = {{K1}};
+    uint256 constant K2 = {{K2}};
+
+    // State of the transcript after absorbing the verifier key.
+    bytes32 constant INITIAL_STATE = {{INITIAL_STATE}};
+    uint256 constant NR_PUBLIC_INPUTS = {{NR_PUBLIC_INPUTS}};
+
+    // Points of the verifier key, in the encoding of EIP-2537.
+    bytes constant G1 = hex"{{G1}}";
+    bytes constant G2 = hex"{{G2}}";
+    bytes constant X_G2 = hex"{{X_G2}}";
+    bytes constant QM = hex"{{QM_COMM}}";
+    bytes constant QL = hex"{{QL_COMM}}";
+    bytes constant QR = hex"{{QR_COMM}}";
+    bytes constant QO = hex"{{QO_COMM}}";
+    bytes constant QC = hex"{{QC_COMM}}";
+    bytes constant QS1 = hex"{{QS1_COMM}}";
+    bytes constant QS2 = hex"{{QS2_COMM}}";
+    bytes constant QS3 = hex"{{QS3_COMM}}";
+    bytes constant QK = hex"{{QK_COMM}}";
+    bytes constant TABLE1 = hex"{{TABLE1_COMM}}";
+    bytes constant TABLE2 = hex"{{TABLE2_COMM}}";
+    bytes constant TABLE3 = hex"{{TABLE3_COMM}}";
+    bytes constant TABLE4 = hex"{{TABLE4_COMM}}";
+    bytes constant QT = hex"{{QT_COMM}}";
+    bytes constant QN = hex"{{QN_COMM}}";
+
+    // Layout of a proof: thirteen points of 128 bytes followed by fourteen scalars.
+    uint256 constant PROOF_SIZE = 13 * 128 + 14 * 32;
+    uint256 constant EVALUATIONS = 13 * 128;
+
+    uint256 constant A = 0;
+    uint256 constant B = 1;
+    uint256 constant C = 2;
+    uint256 constant F = 3;
+    uint256 constant H1 = 4;
+    uint256 constant H2 = 5;
+    uint256 constant Z = 6;
+    uint256 constant Z_LOOKUP = 7;
+    uint256 constant T_LOW = 8;
+    uint256 constant T_MID = 9;
+    uint256 constant T_HIGH = 10;
+    uint256 constant W_OMEGA = 11;
+    uint256 constant W_OMEGA_ZETA = 12;
+
+    uint256 constant A_EVAL = 0;
+    uint256 constant B_EVAL = 1;
+    uint256 constant C_EVAL = 2;
+    uint256 constant S_SIG1 = 3;
+    uint256 constant S_SIG2 = 4;
+    uint256 constant Z_OMEGA = 5;
+    uint256 constant F_EVAL = 6;
+    uint256 constant QT_EVAL = 7;
+    uint256 constant TABLE_EVAL = 8;
+    uint256 constant H2_EVAL = 9;
+    uint256 constant TABLE_OMEGA = 10;
+    uint256 constant H1_OMEGA = 11;
+    uint256 constant Z_LOOKUP_OMEGA = 12;
+    uint256 constant A_OMEGA = 13;
+
+    struct Challenges {
+        uint256 eta;
+        uint256 beta;
+        uint256 gamma;
+        uint256 delta;
+        uint256 epsilon;
+        uint256 alpha;
+        uint256 zeta;
+        uint256 v;
+        uint256 u;
+    }
+
+    // Values derived from the challenges, shared by the terms of the final check.
+    struct Derived {
+        uint256 zn; // zeta^N
+        uint256 zh; // zeta^N - 1
+        uint256 l0;
+        uint256 h1;
+        uint256 r0;
+        uint256[6] alpha; // powers of alpha
+        uint256[10] v; // powers of v
+    }
+
+    /// Checks `proof` for the given public inputs.
+    function verify(bytes calldata proof, uint256[] calldata publicInputs) external view returns (bool) {
+        require(proof.length == PROOF_SIZE, "invalid proof length");
+        require(publicInputs.length == NR_PUBLIC_INPUTS, "invalid number of public inputs");
+        for (uint256 i = 0; i < 14; i++) {
+            require(evaluation(proof, i) < R, "non canonical evaluation");
+        }
+        for (uint256 i = 0; i < publicInputs.length; i++) {
+            require(publicInputs[i] < R, "non canonical public input");
+        }
+
+        Challenges memory c = challenges(proof, publicInputs);
+        Derived memory d = derive(proof, publicInputs, c);
+
+        bytes memory lhs = new bytes(2 * 160);
+        setTerm(lhs, 0, point(proof, W_OMEGA), 1);
+        setTerm(lhs, 1, point(proof, W_OMEGA_ZETA), c.u);
+
+        return pairingCheck(abi.encodePacked(msm(lhs), X_G2, msm(negatedRhs(proof, c, d)), G2));
+    }
+
+    function point(bytes calldata proof, uint256 i) internal pure returns (bytes memory) {
+        return proof[128 * i:128 * (i + 1)];
+    }
+
+    function evaluation(bytes calldata proof, uint256 i) internal pure returns (uint256) {
+        return uint256(bytes32(proof[EVALUATIONS + 32 * i:EVALUATIONS + 32 * (i + 1)]));
+    }
+
+    function publicInputRoots() internal pure returns (uint256[] memory roots) {
+        roots = new uint256[](NR_PUBLIC_INPUTS);
+{{ROOTS}}    }
+
+    // Transcript, as `KeccakTranscript`.
+
+    function absorb(bytes32 state, bytes memory label, bytes memory message) internal pure returns (bytes32) {
+        return keccak256(abi.encodePacked(state, label, uint32(message.length), message));
+    }
+
+    function squeeze(bytes32 state, bytes memory label) internal pure returns (bytes32, uint256) {
+        state = keccak256(abi.encodePacked(state, label));
+        uint256 high = uint256(keccak256(abi.encodePacked(state, uint8(0))));
+        uint256 low = uint256(keccak256(abi.encodePacked(state, uint8(1))));
+        return (state, addmod(mulmod(high, TWO_256, R), low, R));
+    }
+
+    function challenges(bytes calldata proof, uint256[] calldata publicInputs)
+        internal
+        pure
+        returns (Challenges memory c)
+    {
+        bytes32 state = INITIAL_STATE;
+        for (uint256 i = 0; i < publicInputs.length; i++) {
+            state = absorb(state, "public input", abi.encodePacked(publicInputs[i]));
+        }
+
+        state = absorb(state, "commitment a", point(proof, A));
+        state = absorb(state, "commitment b", point(proof, B));
+        state = absorb(state, "commitment c", point(proof, C));
+        (state, c.eta) = squeeze(state, "eta");
+
+        state = absorb(state, "commitment f", point(proof, F));
+        state = absorb(state, "commitment h1", point(proof, H1));
+        state = absorb(state, "commitment h2", point(proof, H2));
+        (state, c.beta) = squeeze(state, "beta");
+        (state, c.gamma) = squeeze(state, "gamma");
+        (state, c.delta) = squeeze(state, "delta");
+        (state, c.epsilon) = squeeze(state, "epsilon");
+
+        state = absorb(state, "Permutation polynomial", point(proof, Z));
+        state = absorb(state, "Lookup polynomial", point(proof, Z_LOOKUP));
+        (state, c.alpha) = squeeze(state, "alpha");
+
+        state = absorb(state, "Quotient low polynomial", point(proof, T_LOW));
+        state = absorb(state, "Quotient mid polynomial", point(proof, T_MID));
+        state = absorb(state, "Quotient high polynomial", point(proof, T_HIGH));
+        (state, c.zeta) = squeeze(state, "zeta");
+
+        state = absorb(state, "Append a_eval.", abi.encodePacked(evaluation(proof, A_EVAL)));
+        state = absorb(state, "Append b_eval.", abi.encodePacked(evaluation(proof, B_EVAL)));
+        state = absorb(state, "Append c_eval.", abi.encodePacked(evaluation(proof, C_EVAL)));
+        state = absorb(state, "Append s_sig1.", abi.encodePacked(evaluation(proof, S_SIG1)));
+        state = absorb(state, "Append s_sig2.", abi.encodePacked(evaluation(proof, S_SIG2)));
+        state = absorb(state, "Append z_omega.", abi.encodePacked(evaluation(proof, Z_OMEGA)));
+        state = absorb(state, "Append f_eval.", abi.encodePacked(evaluation(proof, F_EVAL)));
+        state = absorb(state, "Append qt_eval.", abi.encodePacked(evaluation(proof, QT_EVAL)));
+        state = absorb(state, "Append table_eval.", abi.encodePacked(evaluation(proof, TABLE_EVAL)));
+        state = absorb(state, "Append h2_eval.", abi.encodePacked(evaluation(proof, H2_EVAL)));
+        state = absorb(state, "Append table_omega.", abi.encodePacked(evaluation(proof, TABLE_OMEGA)));
+        state = absorb(state, "Append h1_omega.", abi.encodePacked(evaluation(proof, H1_OMEGA)));
+        state = absorb(state, "Append z_lookup_omega.", abi.encodePacked(evaluation(proof, Z_LOOKUP_OMEGA)));
+        state = absorb(state, "Append a_omega.", abi.encodePacked(evaluation(proof, A_OMEGA)));
+        (state, c.v) = squeeze(state, "v");
+
+        state = absorb(state, "w_omega comm", point(proof, W_OMEGA));
+        state = absorb(state, "w_omega_zeta comm", point(proof, W_OMEGA_ZETA));
+        (, c.u) = squeeze(state, "u");
+    }
+
+    // Arithmetic modulo R.
+
+    function neg(uint256 x) internal pure returns (uint256) {
+        return (R - x) % R;
+    }
+
+    function expmod(uint256 base, uint256 exponent) internal view returns (uint256) {
+        (bool ok, bytes memory output) =
+            address(0x05).staticcall(abi.encode(uint256(32), uint256(32), uint256(32), base, exponent, R));
+        require(ok, "modexp failed");
+        return abi.decode(output, (uint256));
+    }
+
+    // L_i(zeta) = omega^i * (zeta^N - 1) / (N * (zeta - omega^i)).
+    function lagrange(uint256 root, uint256 zeta, uint256 zh) internal view returns (uint256) {
+        uint256 denominator = expmod(addmod(zeta, R - root, R), R - 2);
+        return mulmod(mulmod(root, mulmod(zh, N_INV, R), R), denominator, R);
+    }
+
+    function derive(bytes calldata proof, uint256[] calldata publicInputs, Challenges memory c)
+        internal
+        view
+        returns (Derived memory d)
+    {
+        d.zn = expmod(c.zeta, N);
+        d.zh = addmod(d.zn, R - 1, R);
+        d.l0 = lagrange(1, c.zeta, d.zh);
+        d.alpha[0] = 1;
+        d.v[0] = 1;
+        for (uint256 i = 1; i < 6; i++) {
+            d.alpha[i] = mulmod(d.alpha[i - 1], c.alpha, R);
+        }
+        for (uint256 i = 1; i < 10; i++) {
+            d.v[i] = mulmod(d.v[i - 1], c.v, R);
+        }
+
+        uint256 pi = 0;
+        uint256[] memory roots = publicInputRoots();
+        for (uint256 i = 0; i < roots.length; i++) {
+            pi = addmod(pi, mulmod(neg(publicInputs[i]), lagrange(roots[i], c.zeta, d.zh), R), R);
+        }
+
+        uint256 epsilonOnePlusDelta = mulmod(c.epsilon, addmod(1, c.delta, R), R);
+        d.h1 = mulmod(
+            mulmod(
+                addmod(
+                    addmod(epsilonOnePlusDelta, evaluation(proof, H2_EVAL), R),
+                    mulmod(c.delta, evaluation(proof, H1_OMEGA), R),
+                    R
+                ),
+                evaluation(proof, Z_LOOKUP_OMEGA),
+                R
+            ),
+            d.alpha[5],
+            R
+        );
+
+        uint256 permutation = mulmod(
+            mulmod(
+                addmod(addmod(evaluation(proof, A_EVAL), mulmod(c.beta, evaluation(proof, S_SIG1), R), R), c.gamma, R),
+                addmod(addmod(evaluation(proof, B_EVAL), mulmod(c.beta, evaluation(proof, S_SIG2), R), R), c.gamma, R),
+                R
+            ),
+            mulmod(addmod(evaluation(proof, C_EVAL), c.gamma, R), evaluation(proof, Z_OMEGA), R),
+            R
+        );
+        d.r0 = addmod(pi, neg(mulmod(d.l0, addmod(d.alpha[2], d.alpha[4], R), R)), R);
+        d.r0 = addmod(d.r0, neg(mulmod(c.alpha, permutation, R)), R);
+        d.r0 = addmod(
+            d.r0,
+            neg(mulmod(addmod(epsilonOnePlusDelta, mulmod(c.delta, evaluation(proof, H2_EVAL), R), R), d.h1, R)),
+            R
+        );
+    }
+
+    // Terms of -rhs, where rhs = zeta * W + u * zeta * omega * W' + [batched commitments] - [batched
+    // evaluations] * g1, with every commitment of the batch expanded to its own term.
+    function negatedRhs(bytes calldata proof, Challenges memory c, Derived memory d)
+        internal
+        view
+        returns (bytes memory input)
+    {
+        input = new bytes(29 * 160);
+        uint256 a = evaluation(proof, A_EVAL);
+        uint256 b = evaluation(proof, B_EVAL);
+
+        setTerm(input, 0, point(proof, W_OMEGA), neg(c.zeta));
+        setTerm(input, 1, point(proof, W_OMEGA_ZETA), neg(mulmod(mulmod(c.u, c.zeta, R), OMEGA, R)));
+        setTerm(input, 2, QM, neg(mulmod(a, b, R)));
+        setTerm(input, 3, QL, neg(a));
+        setTerm(input, 4, QR, neg(b));
+        setTerm(input, 5, QO, neg(evaluation(proof, C_EVAL)));
+        setTerm(input, 6, QC, R - 1);
+        setTerm(input, 7, point(proof, Z), neg(zCoefficient(proof, c, d)));
+        setTerm(input, 8, QK, neg(mulmod(lookupQuery(proof, c), d.alpha[3], R)));
+        setTerm(input, 9, point(proof, Z_LOOKUP), neg(zLookupCoefficient(proof, c, d)));
+        setTerm(input, 10, point(proof, H1), neg(addmod(mulmod(c.u, d.v[3], R), neg(d.h1), R)));
+
+        uint256 table = addmod(d.v[7], mulmod(c.u, d.v[2], R), R);
+        setTerm(input, 11, TABLE1, neg(table));
+        table = mulmod(table, c.eta, R);
+        setTerm(input, 12, TABLE2, neg(table));
+        table = mulmod(table, c.eta, R);
+        setTerm(input, 13, TABLE3, neg(table));
+        table = mulmod(table, c.eta, R);
+        setTerm(input, 14, TABLE4, neg(table));
+
+        setTerm(input, 15, point(proof, F), neg(d.v[6]));
+        setTerm(input, 16, point(proof, H2), neg(d.v[8]));
+        setTerm(input, 17, QT, neg(d.v[9]));
+        setTerm(input, 18, QS3, sigma3Coefficient(proof, c));
+        setTerm(input, 19, point(proof, T_LOW), d.zh);
+        setTerm(input, 20, point(proof, T_MID), mulmod(d.zh, d.zn, R));
+        setTerm(input, 21, point(proof, T_HIGH), mulmod(mulmod(d.zh, d.zn, R), d.zn, R));
+        setTerm(input, 22, point(proof, A), neg(addmod(d.v[1], mulmod(c.u, d.v[4], R), R)));
+        setTerm(input, 23, point(proof, B), neg(d.v[2]));
+        setTerm(input, 24, point(proof, C), neg(d.v[3]));
+        setTerm(input, 25, QS1, neg(d.v[4]));
+        setTerm(input, 26, QS2, neg(d.v[5]));
+        setTerm(input, 27, QN, neg(evaluation(proof, A_OMEGA)));
+        setTerm(input, 28, G1, batchedEvaluation(proof, c, d));
+    }
+
+    function zCoefficient(bytes calldata proof, Challenges memory c, Derived memory d)
+        internal
+        pure
+        returns (uint256)
+    {
+        uint256 betaZeta = mulmod(c.beta, c.zeta, R);
+        uint256 product = mulmod(
+            addmod(addmod(evaluation(proof, A_EVAL), betaZeta, R), c.gamma, R),
+            addmod(addmod(evaluation(proof, B_EVAL), mulmod(betaZeta, K1, R), R), c.gamma, R),
+            R
+        );
+        product = mulmod(
+            product, addmod(addmod(evaluation(proof, C_EVAL), mulmod(betaZeta, K2, R), R), c.gamma, R), R
+        );
+        return addmod(addmod(mulmod(product, c.alpha, R), mulmod(d.l0, d.alpha[2], R), R), c.u, R);
+    }
+
+    // a + eta * b + eta^2 * c + eta^3 * qt - f.
+    function lookupQuery(bytes calldata proof, Challenges memory c) internal pure returns (uint256) {
+        uint256 query = evaluation(proof, QT_EVAL);
+        query = addmod(mulmod(query, c.eta, R), evaluation(proof, C_EVAL), R);
+        query = addmod(mulmod(query, c.eta, R), evaluation(proof, B_EVAL), R);
+        query = addmod(mulmod(query, c.eta, R), evaluation(proof, A_EVAL), R);
+        return addmod(query, neg(evaluation(proof, F_EVAL)), R);
+    }
+
+    function zLookupCoefficient(bytes calldata proof, Challenges memory c, Derived memory d)
+        internal
+        pure
+        returns (uint256)
+    {
+        uint256 onePlusDelta = addmod(1, c.delta, R);
+        uint256 product = mulmod(onePlusDelta, addmod(c.epsilon, evaluation(proof, F_EVAL), R), R);
+        uint256 table = addmod(
+            addmod(mulmod(c.epsilon, onePlusDelta, R), evaluation(proof, TABLE_EVAL), R),
+            mulmod(c.delta, evaluation(proof, TABLE_OMEGA), R),
+            R
+        );
+        product = mulmod(mulmod(product, table, R), d.alpha[5], R);
+        return addmod(addmod(product, mulmod(d.l0, d.alpha[4], R), R), mulmod(c.u, c.v, R), R);
+    }
+
+    // Coefficient of s_sig3 in -rhs, (a + beta * s_sig1 + gamma) * (b + beta * s_sig2 + gamma) *
+    // alpha * beta * z_omega.
+    function sigma3Coefficient(bytes calldata proof, Challenges memory c) internal pure returns (uint256) {
+        uint256 product = mulmod(
+            addmod(addmod(evaluation(proof, A_EVAL), mulmod(c.beta, evaluation(proof, S_SIG1), R), R), c.gamma, R),
+            addmod(addmod(evaluation(proof, B_EVAL), mulmod(c.beta, evaluation(proof, S_SIG2), R), R), c.gamma, R),
+            R
+        );
+        return mulmod(mulmod(mulmod(product, c.alpha, R), c.beta, R), evaluation(proof, Z_OMEGA), R);
+    }
+
+    function batchedEvaluation(bytes calldata proof, Challenges memory c, Derived memory d)
+        internal
+        pure
+        returns (uint256 sum)
+    {
+        sum = neg(d.r0);
+        uint256[10] memory evaluations = [
+            uint256(0),
+            evaluation(proof, A_EVAL),
+            evaluation(proof, B_EVAL),
+            evaluation(proof, C_EVAL),
+            evaluation(proof, S_SIG1),
+            evaluation(proof, S_SIG2),
+            evaluation(proof, F_EVAL),
+            evaluation(proof, TABLE_EVAL),
+            evaluation(proof, H2_EVAL),
+            evaluation(proof, QT_EVAL)
+        ];
+        for (uint256 i = 1; i < 10; i++) {
+            sum = addmod(sum, mulmod(d.v[i], evaluations[i], R), R);
+        }
+        uint256 shifted = addmod(
+            addmod(evaluation(proof, Z_OMEGA), mulmod(d.v[1], evaluation(proof, Z_LOOKUP_OMEGA), R), R),
+            addmod(
+                mulmod(d.v[2], evaluation(proof, TABLE_OMEGA), R), mulmod(d.v[3], evaluation(proof, H1_OMEGA), R), R
+            ),
+            R
+        );
+        shifted = addmod(shifted, mulmod(d.v[4], evaluation(proof, A_OMEGA), R), R);
+        sum = addmod(sum, mulmod(c.u, shifted, R), R);
+    }
+
+    // Precompiles.
+
+    function setTerm(bytes memory input, uint256 i, bytes memory g, uint256 scalar) internal pure {
+        assembly ("memory-safe") {
+            let dst := add(add(input, 32), mul(i, 160))
+            let src := add(g, 32)
+            mstore(dst, mload(src))
+            mstore(add(dst, 32), mload(add(src, 32)))
+            mstore(add(dst, 64), mload(add(src, 64)))
+            mstore(add(dst, 96), mload(add(src, 96)))
+            mstore(add(dst, 128), scalar)
+        }
+    }
+
+    function msm(bytes memory input) internal view returns (bytes memory) {
+        (bool ok, bytes memory output) = address(0x0c).staticcall(input);
+        require(ok && output.length == 128, "G1 MSM failed");
+        return output;
+    }
+
+    function pairingCheck(bytes memory input) internal view returns (bool) {
+        (bool ok, bytes memory output) = address(0x0f).staticcall(input);
+        require(ok && output.length == 32, "pairing check failed");
+        return abi.decode(output, (uint256)) == 1;
+    }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::verify_selector;
+    use crate::keccak::keccak256;
+    use crate::lookup::compress;
+    use crate::plonk::{ComputationTrace, PlonkCircuit, ProverKey};
+    use crate::proof::PlonkProof;
+    use crate::prover::Prover;
+    use crate::verifier::PlonkVerifier;
+    use blstrs::{pairing, G1Projective};
+    use group::{Curve, Group};
+    use rand_core::OsRng;
+    use std::ops::Neg;
+
+    // Challenges as recomputed by the contract, from the transcript state it starts with.
+    fn challenges(state: [u8; 32], pub_in: &[Scalar], proof: &PlonkProof) -> [Scalar; 9] {
+        let mut transcript = KeccakTranscript { state };
+        for input in pub_in {
+            transcript.append_scalar(b"public input", input);
+        }
+        let bytes = proof.to_evm_bytes();
+        let (points, scalars) = bytes.split_at(13 * 128);
+        let mut points = points.chunks_exact(128);
+        let mut scalars = scalars.chunks_exact(32);
+        let mut absorb = |transcript: &mut KeccakTranscript, labels: &[&'static [u8]]| {
+            for label in labels {
+                transcript.append_message(label, points.next().unwrap());
+            }
+        };
+        absorb(
+            &mut transcript,
+            &[b"commitment a", b"commitment b", b"commitment c"],
+        );
+        let eta = transcript.challenge_scalar(b"eta");
+        absorb(
+            &mut transcript,
+            &[b"commitment f", b"commitment h1", b"commitment h2"],
+        );
+        let beta = transcript.challenge_scalar(b"beta");
+        let gamma = transcript.challenge_scalar(b"gamma");
+        let delta = transcript.challenge_scalar(b"delta");
+        let epsilon = transcript.challenge_scalar(b"epsilon");
+        absorb(
+            &mut transcript,
+            &[b"Permutation polynomial", b"Lookup polynomial"],
+        );
+        let alpha = transcript.challenge_scalar(b"alpha");
+        absorb(
+            &mut transcript,
+            &[
+                b"Quotient low polynomial",
+                b"Quotient mid polynomial",
+                b"Quotient high polynomial",
+            ],
+        );
+        let zeta = transcript.challenge_scalar(b"zeta");
+        for label in [
+            &b"Append a_eval."[..],
+            b"Append b_eval.",
+            b"Append c_eval.",
+            b"Append s_sig1.",
+            b"Append s_sig2.",
+            b"Append z_omega.",
+            b"Append f_eval.",
+            b"Append qt_eval.",
+            b"Append table_eval.",
+            b"Append h2_eval.",
+            b"Append table_omega.",
+            b"Append h1_omega.",
+            b"Append z_lookup_omega.",
+            b"Append a_omega.",
+        ] {
+            transcript.append_message(label, scalars.next().unwrap());
+        }
+        let v = transcript.challenge_scalar(b"v");
+        absorb(&mut transcript, &[b"w_omega comm", b"w_omega_zeta comm"]);
+        let u = transcript.challenge_scalar(b"u");
+        [eta, beta, gamma, delta, epsilon, alpha, zeta, v, u]
+    }
+
+    // Final check of the contract, with the terms of both multi-scalar multiplications.
+    fn contract_check(verifier_key: &VerifierKey, pub_in: &[Scalar], proof: &PlonkProof) -> bool {
+        let mut transcript = KeccakTranscript::new(b"testing solidity");
+        verifier_key.append_to_transcript(&mut transcript);
+        let [eta, beta, gamma, delta, epsilon, alpha, zeta, v, u] =
+            challenges(transcript.state, pub_in, proof);
+        let p = proof;
+        let domain = &verifier_key.domain;
+        let zn = zeta.pow_vartime([domain.size as u64]);
+        let zh = zn - Scalar::one();
+        let lagrange = |root: Scalar| root * zh * domain.size_inv * (zeta - root).invert().unwrap();
+        let l0 = lagrange(Scalar::one());
+        let alphas: Vec<_> = (0..6).map(|i| alpha.pow_vartime([i])).collect();
+        let vs: Vec<_> = (0..10).map(|i| v.pow_vartime([i])).collect();
+        let pi: Scalar = pub_in
+            .iter()
+            .zip(verifier_key.public_inputs.iter())
+            .map(|(x, row)| x.neg() * lagrange(domain.generator.pow_vartime([*row as u64])))
+            .sum();
+        let eod = epsilon * (Scalar::one() + delta);
+        let h1 = (eod + p.h2_eval + delta * p.h1_omega) * p.z_lookup_omega * alphas[5];
+        let permutation = (p.a_eval + beta * p.s_sig1 + gamma)
+            * (p.b_eval + beta * p.s_sig2 + gamma)
+            * (p.c_eval + gamma)
+            * p.z_omega;
+        let r0 = pi
+            - l0 * (alphas[2] + alphas[4])
+            - alpha * permutation
+            - (eod + delta * p.h2_eval) * h1;
+        let table = vs[7] + u * vs[2];
+        let batched = -r0
+            + [
+                p.a_eval,
+                p.b_eval,
+                p.c_eval,
+                p.s_sig1,
+                p.s_sig2,
+                p.f_eval,
+                p.table_eval,
+                p.h2_eval,
+                p.qt_eval,
+            ]
+            .iter()
+            .zip(&vs[1..])
+            .map(|(eval, power)| eval * power)
+            .sum::<Scalar>()
+            + u * (p.z_omega
+                + v * p.z_lookup_omega
+                + vs[2] * p.table_omega
+                + vs[3] * p.h1_omega
+                + vs[4] * p.a_omega);
+        let z = (p.a_eval + beta * zeta + gamma)
+            * (p.b_eval + beta * K1() * zeta + gamma)
+            * (p.c_eval + beta * K2() * zeta + gamma)
+            * alpha
+            + l0 * alphas[2]
+            + u;
+        let z_lookup = (Scalar::one() + delta)
+            * (epsilon + p.f_eval)
+            * (eod + p.table_eval + delta * p.table_omega)
+            * alphas[5]
+            + l0 * alphas[4]
+            + u * v;
+        let query = compress(&[p.a_eval, p.b_eval, p.c_eval, p.qt_eval], &eta) - p.f_eval;
+        let sigma3 = (p.a_eval + beta * p.s_sig1 + gamma)
+            * (p.b_eval + beta * p.s_sig2 + gamma)
+            * alpha
+            * beta
+            * p.z_omega;
+
+        let vk = verifier_key;
+        let negated_rhs = [
+            (p.opening.w_omega.0, -zeta),
+            (p.opening.w_omega_zeta.0, -(u * zeta * domain.generator)),
+            (vk.qm_comm.0, -(p.a_eval * p.b_eval)),
+            (vk.ql_comm.0, -p.a_eval),
+            (vk.qr_comm.0, -p.b_eval),
+            (vk.qo_comm.0, -p.c_eval),
+            (vk.qc_comm.0, -Scalar::one()),
+            (p.commitment_z.0, -z),
+            (vk.qk_comm.0, -(query * alphas[3])),
+            (p.commitment_z_lookup.0, -z_lookup),
+            (p.commitment_h1.0, -(u * vs[3] - h1)),
+            (vk.table1_comm.0, -table),
+            (vk.table2_comm.0, -(table * eta)),
+            (vk.table3_comm.0, -(table * eta.square())),
+            (vk.table4_comm.0, -(table * eta.pow_vartime([3]))),
+            (p.commitment_f.0, -vs[6]),
+            (p.commitment_h2.0, -vs[8]),
+            (vk.qt_comm.0, -vs[9]),
+            (vk.qs3_comm.0, sigma3),
+            (p.t_low.0, zh),
+            (p.t_mid.0, zh * zn),
+            (p.t_high.0, zh * zn * zn),
+            (p.commitment_a.0, -(vs[1] + u * vs[4])),
+            (p.commitment_b.0, -vs[2]),
+            (p.commitment_c.0, -vs[3]),
+            (vk.qs1_comm.0, -vs[4]),
+            (vk.qs2_comm.0, -vs[5]),
+            (vk.qn_comm.0, -p.a_omega),
+            (vk.scheme.powers_x_g1[0], batched),
+        ]
+        .iter()
+        .fold(G1Projective::identity(), |acc, (point, scalar)| {
+            acc + point * scalar
+        });
+        let lhs = p.opening.w_omega.0 + p.opening.w_omega_zeta.0 * u;
+
+        pairing(&lhs.to_affine(), &vk.scheme.powers_x_g2[1])
+            + pairing(&negated_rhs.to_affine(), &vk.scheme.powers_x_g2[0])
+            == blstrs::Gt::identity()
+    }
+
+    // x * x = y with y public, for x = 3.
+    fn square_circuit() -> (ProverKey, VerifierKey, ComputationTrace, Vec<Scalar>) {
+        let mut circuit = PlonkCircuit::init();
+        let y = circuit.public_input();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        circuit.pad_next_power_of_two();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(y, x_sq).unwrap();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        trace.set(y, Scalar::from(9));
+        trace.set(x_l, Scalar::from(3));
+        trace.set(x_r, Scalar::from(3));
+        trace.set(x_sq, Scalar::from(9));
+        (prover_key, verifier_key, trace, vec![Scalar::from(9)])
+    }
+
+    #[test]
+    fn test_solidity_verifier() {
+        let (prover_key, verifier_key, trace, pub_in) = square_circuit();
+        let mut transcript = KeccakTranscript::new(b"testing solidity");
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut transcript).unwrap();
+        let mut transcript = KeccakTranscript::new(b"testing solidity");
+        assert!(PlonkVerifier::verify(&pub_in, &verifier_key, &proof, &mut transcript).is_ok());
+
+        // The terms of the contract agree with the native verifier.
+        assert!(contract_check(&verifier_key, &pub_in, &proof));
+        assert!(!contract_check(&verifier_key, &[Scalar::from(4)], &proof));
+        assert_eq!(proof.to_evm_bytes().len(), PlonkProof::EVM_SIZE);
+
+        let source = solidity_verifier(&verifier_key, b"testing solidity");
+        let mut state = KeccakTranscript::new(b"testing solidity");
+        verifier_key.append_to_transcript(&mut state);
+        assert!(source.contains(&format!("INITIAL_STATE = 0x{};", hex(&state.state))));
+        assert!(source.contains(&format!("N = {};", verifier_key.domain.size)));
+        assert!(source.contains(&format!(
+            "QM = hex\"{}\";",
+            hex(&encode_g1(&verifier_key.qm_comm.0))
+        )));
+        assert!(source.contains("roots[0] = 0x"));
+        assert!(!source.contains("{{"));
+    }
+
+    #[test]
+    fn test_calldata() {
+        // Selector of transfer(address,uint256) in ERC-20.
+        assert_eq!(
+            keccak256(b"transfer(address,uint256)")[..4],
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+
+        let (prover_key, _, trace, pub_in) = square_circuit();
+        let mut transcript = KeccakTranscript::new(b"testing solidity");
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut transcript).unwrap();
+        let calldata = proof.to_evm_calldata(&pub_in);
+        let word = |i: usize| &calldata[4 + 32 * i..4 + 32 * (i + 1)];
+        let size = PlonkProof::EVM_SIZE;
+        assert_eq!(calldata.len(), 4 + 32 * 4 + size + 32 * pub_in.len());
+        assert_eq!(calldata[..4], verify_selector());
+        assert_eq!(word(0)[31], 0x40);
+        assert_eq!(word(1)[30..], ((96 + size) as u16).to_be_bytes());
+        assert_eq!(word(2)[30..], (size as u16).to_be_bytes());
+        assert_eq!(&calldata[4 + 96..4 + 96 + size], proof.to_evm_bytes());
+        assert_eq!(word(3 + size / 32)[31], 1);
+        assert_eq!(word(4 + size / 32), pub_in[0].to_bytes_be());
+    }
+}
@@ -1,56 +1,229 @@
-use blake2::{Blake2b, Digest, digest::consts::U32};
+use crate::evm::encode_g1;
+use crate::keccak::keccak256;
+use crate::poseidon::{Poseidon, WIDTH};
+#[cfg(feature = "merlin")]
+use crate::strobe::Strobe128;
+use blake2::{digest::consts::U32, Blake2b, Digest};
 use blstrs::{G1Affine, Scalar};
+use ff::Field;
 
+/// Fiat-Shamir transcript. Every element is absorbed together with a label, so that the
+/// challenges are bound to the role that each element plays in the protocol.
+pub trait Transcript {
+    /// Creates a transcript for the protocol identified by `label`.
+    fn new(label: &'static [u8]) -> Self
+    where
+        Self: Sized;
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]);
+
+    fn append_point(&mut self, label: &'static [u8], message: &G1Affine) {
+        self.append_message(label, &message.to_compressed());
+    }
+
+    fn append_scalar(&mut self, label: &'static [u8], message: &Scalar) {
+        self.append_message(label, &message.to_bytes_be());
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar;
+}
+
+/// Reduces 64 little endian bytes modulo the group order, so that the bias is negligible.
+pub(crate) fn scalar_from_wide_bytes(bytes: &[u8; 64]) -> Scalar {
+    let two_64 = Scalar::from(u64::MAX) + Scalar::from(1);
+    bytes
+        .chunks_exact(8)
+        .rev()
+        .fold(Scalar::from(0), |acc, limb| {
+            acc * two_64 + Scalar::from(u64::from_le_bytes(limb.try_into().unwrap()))
+        })
+}
+
+/// Default transcript, built on top of Blake2b.
 #[derive(Clone)]
-pub struct Transcript(Blake2b<U32>);
+pub struct Blake2bTranscript(Blake2b<U32>);
 
-impl Transcript {
-    pub fn new(label: &'static [u8]) -> Transcript {
+impl Transcript for Blake2bTranscript {
+    fn new(label: &'static [u8]) -> Self {
         let mut transcript = Blake2b::new();
         transcript.update(b"FS transcript");
         transcript.update(b"dom-sep");
         transcript.update(label);
 
-        Transcript(transcript)
+        Blake2bTranscript(transcript)
     }
 
-    pub fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
         let data_len = (message.len() as u32).to_le_bytes();
         self.0.update(label);
         self.0.update(data_len);
         self.0.update(message);
     }
 
-    pub fn append_point(&mut self, label: &'static [u8], message: &G1Affine) {
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
+        let mut scalar_bytes = [0u8; 32];
         self.0.update(label);
-        self.0.update(message.to_compressed());
+        scalar_bytes[1..].copy_from_slice(&self.clone().0.finalize().to_vec()[1..32]);
+        Scalar::from_bytes_be(&scalar_bytes).unwrap()
     }
+}
 
-    pub fn append_scalar(&mut self, label: &'static [u8], message: &Scalar) {
-        self.0.update(label);
-        self.0.update(message.to_bytes_be());
+/// Transcript compatible with the merlin crate, so that challenges can be shared with other
+/// Merlin based protocols.
+#[cfg(feature = "merlin")]
+#[derive(Clone)]
+pub struct MerlinTranscript {
+    strobe: Strobe128,
+}
+
+#[cfg(feature = "merlin")]
+impl MerlinTranscript {
+    const PROTOCOL_LABEL: &'static [u8] = b"Merlin v1.0";
+
+    /// Fills `dest` with challenge bytes, exactly as `merlin::Transcript::challenge_bytes`.
+    pub fn challenge_bytes(&mut self, label: &'static [u8], dest: &mut [u8]) {
+        let data_len = (dest.len() as u32).to_le_bytes();
+        self.strobe.meta_ad(label, false);
+        self.strobe.meta_ad(&data_len, true);
+        self.strobe.prf(dest, false);
+    }
+}
+
+#[cfg(feature = "merlin")]
+impl Transcript for MerlinTranscript {
+    fn new(label: &'static [u8]) -> Self {
+        let mut transcript = MerlinTranscript {
+            strobe: Strobe128::new(Self::PROTOCOL_LABEL),
+        };
+        transcript.append_message(b"dom-sep", label);
+
+        transcript
     }
 
-    pub fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
-        let mut scalar_bytes = [0u8; 32];
-        self.0.update(label);
-        scalar_bytes[1..].copy_from_slice(&self.clone().0.finalize().to_vec()[1..32]);
-        Scalar::from_bytes_be(&scalar_bytes).unwrap()
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        let data_len = (message.len() as u32).to_le_bytes();
+        self.strobe.meta_ad(label, false);
+        self.strobe.meta_ad(&data_len, true);
+        self.strobe.ad(message, false);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
+        let mut bytes = [0u8; 64];
+        self.challenge_bytes(label, &mut bytes);
+        scalar_from_wide_bytes(&bytes)
+    }
+}
+
+/// Transcript built on Keccak-256, designed to be cheap to recompute in the EVM. The state is
+/// a single hash, which absorbs each message as keccak256(state || label || len || message),
+/// with the length as a big endian u32. Points are absorbed in the uncompressed encoding of
+/// EIP-2537, as they appear in the calldata of the Solidity verifier, and challenges are two
+/// hashes of the new state reduced modulo the group order.
+#[derive(Clone)]
+pub struct KeccakTranscript {
+    pub(crate) state: [u8; 32],
+}
+
+impl Transcript for KeccakTranscript {
+    fn new(label: &'static [u8]) -> Self {
+        let mut transcript = KeccakTranscript { state: [0u8; 32] };
+        transcript.append_message(b"dom-sep", label);
+
+        transcript
+    }
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        let data_len = (message.len() as u32).to_be_bytes();
+        self.state = keccak256(&[&self.state[..], label, &data_len, message].concat());
+    }
+
+    fn append_point(&mut self, label: &'static [u8], message: &G1Affine) {
+        self.append_message(label, &encode_g1(message));
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
+        self.state = keccak256(&[&self.state[..], label].concat());
+        let mut bytes = [0u8; 64];
+        for (half, index) in bytes.chunks_exact_mut(32).zip([1u8, 0]) {
+            half.copy_from_slice(&keccak256(&[&self.state[..], &[index]].concat()));
+            half.reverse();
+        }
+        scalar_from_wide_bytes(&bytes)
+    }
+}
+
+/// Algebraic transcript, built as a duplex sponge over the Poseidon permutation with rate 2.
+/// Scalars are absorbed as field elements, and byte strings are absorbed in chunks of 31
+/// bytes, prefixed with their length. This is the transcript to use if the proof is later
+/// verified inside another circuit.
+#[derive(Clone)]
+pub struct PoseidonTranscript {
+    poseidon: Poseidon,
+    state: [Scalar; WIDTH],
+    pos: usize,
+}
+
+impl PoseidonTranscript {
+    fn absorb(&mut self, element: Scalar) {
+        self.state[1 + self.pos] += element;
+        self.pos += 1;
+        if self.pos == WIDTH - 1 {
+            self.poseidon.permute(&mut self.state);
+            self.pos = 0;
+        }
+    }
+
+    fn absorb_bytes(&mut self, bytes: &[u8]) {
+        self.absorb(Scalar::from(bytes.len() as u64));
+        for chunk in bytes.chunks(31) {
+            let mut scalar_bytes = [0u8; 32];
+            scalar_bytes[..chunk.len()].copy_from_slice(chunk);
+            self.absorb(Scalar::from_bytes_le(&scalar_bytes).unwrap());
+        }
+    }
+}
+
+impl Transcript for PoseidonTranscript {
+    fn new(label: &'static [u8]) -> Self {
+        let mut transcript = PoseidonTranscript {
+            poseidon: Poseidon::new(),
+            state: [Scalar::zero(); WIDTH],
+            pos: 0,
+        };
+        transcript.absorb_bytes(b"dom-sep");
+        transcript.absorb_bytes(label);
+
+        transcript
+    }
+
+    fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.absorb_bytes(label);
+        self.absorb_bytes(message);
+    }
+
+    fn append_scalar(&mut self, label: &'static [u8], message: &Scalar) {
+        self.absorb_bytes(label);
+        self.absorb(*message);
+    }
+
+    fn challenge_scalar(&mut self, label: &'static [u8]) -> Scalar {
+        self.absorb_bytes(label);
+        self.poseidon.permute(&mut self.state);
+        self.pos = 0;
+        self.state[1]
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::transcript::Transcript;
-    use ff::Field;
     use group::prime::PrimeCurveAffine;
     use rand_chacha::ChaCha20Rng;
     use rand_core::SeedableRng;
 
     #[test]
     fn test_transcript() {
-        let mut transcript = Transcript::new(b"Test transcript");
+        let mut transcript = Blake2bTranscript::new(b"Test transcript");
         transcript.append_message(b"First message", b"Hello world");
         transcript.append_point(b"Second message", &G1Affine::identity());
         transcript.append_scalar(
@@ -60,4 +233,73 @@ mod tests {
 
         let _result = transcript.challenge_scalar(b"End");
     }
+
+    #[test]
+    fn test_domain_separation() {
+        let mut transcript = Blake2bTranscript::new(b"Test transcript");
+        let mut other_protocol = Blake2bTranscript::new(b"Other transcript");
+        let mut other_label = Blake2bTranscript::new(b"Test transcript");
+        transcript.append_message(b"message", b"Hello world");
+        other_protocol.append_message(b"message", b"Hello world");
+        other_label.append_message(b"other message", b"Hello world");
+
+        let challenge = transcript.challenge_scalar(b"challenge");
+        assert_ne!(challenge, other_protocol.challenge_scalar(b"challenge"));
+        assert_ne!(challenge, other_label.challenge_scalar(b"challenge"));
+
+        // Consecutive challenges are different
+        assert_ne!(challenge, transcript.challenge_scalar(b"challenge"));
+    }
+
+    #[test]
+    fn test_poseidon_transcript() {
+        let mut transcript = PoseidonTranscript::new(b"Test transcript");
+        let mut other_scalar = PoseidonTranscript::new(b"Test transcript");
+        transcript.append_point(b"point", &G1Affine::identity());
+        other_scalar.append_point(b"point", &G1Affine::identity());
+        transcript.append_scalar(b"scalar", &Scalar::one());
+        other_scalar.append_scalar(b"scalar", &Scalar::from(2));
+
+        let challenge = transcript.challenge_scalar(b"challenge");
+        assert_ne!(challenge, other_scalar.challenge_scalar(b"challenge"));
+        assert_ne!(challenge, transcript.challenge_scalar(b"challenge"));
+    }
+
+    #[test]
+    fn test_keccak_transcript() {
+        let mut transcript = KeccakTranscript::new(b"Test transcript");
+        let mut other_point = KeccakTranscript::new(b"Test transcript");
+        transcript.append_point(b"point", &G1Affine::identity());
+        other_point.append_point(b"point", &G1Affine::generator());
+
+        // The state is a plain chain of hashes, as recomputed by the Solidity verifier.
+        let label = b"Test transcript";
+        let initial = keccak256(&[&[0u8; 32][..], b"dom-sep", &[0, 0, 0, 15], label].concat());
+        let expected = keccak256(&[&initial[..], b"point", &[0, 0, 0, 128], &[0u8; 128]].concat());
+        assert_eq!(transcript.state, expected);
+
+        let challenge = transcript.challenge_scalar(b"challenge");
+        assert_ne!(challenge, other_point.challenge_scalar(b"challenge"));
+        assert_ne!(challenge, transcript.challenge_scalar(b"challenge"));
+    }
+
+    #[cfg(feature = "merlin")]
+    #[test]
+    fn test_merlin_transcript() {
+        // Test vector of the merlin crate.
+        let mut transcript = MerlinTranscript::new(b"test protocol");
+        transcript.append_message(b"some label", b"some data");
+
+        let mut challenge = [0u8; 32];
+        transcript.challenge_bytes(b"challenge", &mut challenge);
+        let expected = [
+            0xd5, 0xa2, 0x19, 0x72, 0xd0, 0xd5, 0xfe, 0x32, 0x0c, 0x0d, 0x26, 0x3f, 0xac, 0x7f,
+            0xff, 0xb8, 0x14, 0x5a, 0xa6, 0x40, 0xaf, 0x6e, 0x9b, 0xca, 0x17, 0x7c, 0x03, 0xc7,
+            0xef, 0xcf, 0x06, 0x15,
+        ];
+        assert_eq!(challenge, expected);
+
+        let first = transcript.challenge_scalar(b"challenge");
+        assert_ne!(first, transcript.challenge_scalar(b"challenge"));
+    }
 }
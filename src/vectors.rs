@@ -0,0 +1,137 @@
+// Test vectors of a reference circuit, to compare the arithmetization with independent PLONK
+// implementations. Everything that is random in a real run is fixed: the toxic waste of the SRS
+// is a known scalar, the blinding scalars are derived from a seed, and the transcript is a
+// Blake2b one with a fixed label. The circuit is the usual x^3 + x + 5 = y, with y public, laid
+// out as
+//
+//   row 0: public input y
+//   row 1: x * x = x^2
+//   row 2: x^2 * x = x^3
+//   row 3: x^3 + x + 5 = y
+//
+// and padded to the next power of two. A vector is a text file of `name hex` lines, points of
+// G1 compressed and scalars big endian. The entries before `proof` only depend on the SRS, the
+// circuit and the witness, so another implementation with the same layout and SRS produces the
+// same values, and the checks below only compare the entries that both files have. The proof
+// itself depends on the transcript, and is a regression vector of this implementation.
+//
+// Vectors of other implementations go next to `reference.txt` in `vectors/`, without the proof.
+// After an intended change of the encoding or of the prover, the reference vector is
+// regenerated with
+//
+// UPDATE_VECTORS=1 cargo test test_vectors
+use crate::kzg10::Kzg10;
+use crate::plonk::{Gate, PlonkCircuit};
+use crate::prover::Prover;
+use crate::transcript::{Blake2bTranscript, Transcript};
+use crate::verifier::PlonkVerifier;
+use crate::witness::WitnessBuilder;
+use blstrs::Scalar;
+use std::path::PathBuf;
+
+const TOXIC_WASTE: u64 = 0x5eed;
+const SEED: [u8; 32] = [7u8; 32];
+const LABEL: &[u8] = b"plonk test vectors";
+
+// Names of the commitments of the verifier key, in the order of `VerifierKey::commitments`.
+const SELECTORS: [&str; 15] = [
+    "qm", "ql", "qr", "qo", "qc", "qs1", "qs2", "qs3", "qk", "table1", "table2", "table3",
+    "table4", "qt", "qn",
+];
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Entries of the vector of the reference circuit, with x = 3.
+fn reference() -> Vec<(String, String)> {
+    let mut circuit = PlonkCircuit::init();
+    let y = circuit.public_input();
+    let (x, x_copy, square) = circuit.mult_gate();
+    let (square_copy, x_cube_copy, cube) = circuit.mult_gate();
+    let (cube_copy, x_sum_copy, sum) =
+        circuit.gate(Gate::new().left(1).right(1).output(-1).constant(5));
+    circuit
+        .connect_all(&[x, x_copy, x_cube_copy, x_sum_copy])
+        .unwrap();
+    circuit.connect(square, square_copy).unwrap();
+    circuit.connect(cube, cube_copy).unwrap();
+    circuit.connect(sum, y).unwrap();
+    let srs = Kzg10::from_toxic_waste(64, &Scalar::from(TOXIC_WASTE));
+    let (prover_key, verifier_key) = circuit.setup_with_srs(srs).unwrap();
+
+    let mut builder = WitnessBuilder::new(&circuit);
+    builder.assign(x, Scalar::from(3)).unwrap();
+    let trace = builder.build().unwrap();
+    let pub_in = [Scalar::from(35)];
+    let mut transcript = Blake2bTranscript::new(LABEL);
+    let proof =
+        Prover::prove_deterministic(&pub_in, &prover_key, &trace, &mut transcript, SEED).unwrap();
+    let mut transcript = Blake2bTranscript::new(LABEL);
+    assert!(PlonkVerifier::verify(&pub_in, &verifier_key, &proof, &mut transcript).is_ok());
+
+    let mut entries = vec![
+        (
+            "toxic_waste".to_string(),
+            hex(&Scalar::from(TOXIC_WASTE).to_bytes_be()),
+        ),
+        (
+            "n".to_string(),
+            format!("{:016x}", verifier_key.domain.size),
+        ),
+        ("public_input".to_string(), hex(&pub_in[0].to_bytes_be())),
+    ];
+    for (name, commitment) in SELECTORS.iter().zip(verifier_key.commitments()) {
+        entries.push((name.to_string(), hex(&commitment.to_bytes())));
+    }
+    // Wire polynomials before blinding.
+    for (name, column) in [("a", &trace.a), ("b", &trace.b), ("c", &trace.c)] {
+        let commitment = prover_key.scheme.commit(&prover_key.domain.ifft(column));
+        entries.push((name.to_string(), hex(&commitment.to_bytes())));
+    }
+    entries.push(("proof".to_string(), hex(&proof.to_bytes())));
+    entries
+}
+
+fn parse(vector: &str) -> Vec<(String, String)> {
+    vector
+        .lines()
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (name, value) = line.split_once(' ').expect("lines are `name hex`");
+            (name.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+fn vectors_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("vectors")
+}
+
+#[test]
+fn test_vectors() {
+    let entries = reference();
+    let path = vectors_dir().join("reference.txt");
+    if std::env::var("UPDATE_VECTORS").is_ok() {
+        let mut vector = "# x^3 + x + 5 = 35, generated by dummy-plonk\n".to_string();
+        for (name, value) in entries.iter() {
+            vector.push_str(&format!("{name} {value}\n"));
+        }
+        std::fs::write(&path, vector).unwrap();
+    }
+
+    let files = std::fs::read_dir(vectors_dir()).unwrap();
+    let mut checked = 0;
+    for file in files {
+        let path = file.unwrap().path();
+        let vector = parse(&std::fs::read_to_string(&path).unwrap());
+        for (name, value) in vector.iter() {
+            if let Some((_, expected)) = entries.iter().find(|(entry, _)| entry == name) {
+                assert_eq!(value, expected, "entry {name} of {}", path.display());
+                checked += 1;
+            }
+        }
+    }
+    // The reference vector at least has all the entries.
+    assert!(checked >= entries.len());
+}
@@ -1,44 +1,284 @@
-use crate::kzg10::Kzg10Commitment;
-use crate::plonk::{PreprocessedInput, K1, K2};
-use crate::prover::PlonkProof;
+// The verifier only depends on `core` and `alloc`, so that it can be built without the
+// standard library (see the `std` feature). The prover, the circuit builders and the file
+// formats stay behind `std`.
+use crate::commitment::PlonkCommitment;
+use crate::error::PlonkError;
+use crate::kzg10::{msm, pairing_check, Kzg10, Kzg10Opening, TwoPointOpening};
+use crate::lookup::compress;
+use crate::polynomial::EvaluationDomain;
+use crate::proof::PlonkProof;
 use crate::transcript::Transcript;
-use blstrs::{pairing, Scalar};
+use crate::wire::{K1, K2};
+use alloc::vec;
+use alloc::vec::Vec;
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use blstrs::{G1Affine, G1Projective, Scalar};
+use core::ops::Neg;
 use ff::Field;
-use group::Curve;
-use std::ops::Neg;
+use group::{Curve, Group};
+#[cfg(feature = "std")]
+use rand_core::OsRng;
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Preprocessed input of the verifier. It only contains the commitments to the selector and
+/// permutation polynomials, and what the verifier needs of the commitment scheme (KZG by
+/// default), e.g. the elements of the SRS needed for the pairing check.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "P: Serialize, P::Commitment: Serialize",
+        deserialize = "P: Deserialize<'de>, P::Commitment: Deserialize<'de>"
+    ))
+)]
+pub struct VerifierKey<P: PlonkCommitment = Kzg10> {
+    pub domain: EvaluationDomain,
+    pub public_inputs: Vec<usize>,
+    pub scheme: P,
+    pub qm_comm: P::Commitment,
+    pub ql_comm: P::Commitment,
+    pub qr_comm: P::Commitment,
+    pub qo_comm: P::Commitment,
+    pub qc_comm: P::Commitment,
+    pub qs1_comm: P::Commitment,
+    pub qs2_comm: P::Commitment,
+    pub qs3_comm: P::Commitment,
+    pub qk_comm: P::Commitment,
+    pub table1_comm: P::Commitment,
+    pub table2_comm: P::Commitment,
+    pub table3_comm: P::Commitment,
+    pub table4_comm: P::Commitment,
+    pub qt_comm: P::Commitment,
+    pub qn_comm: P::Commitment,
+}
+
+impl<P: PlonkCommitment> VerifierKey<P> {
+    /// Absorbs the fingerprint of the circuit into the transcript, so that the challenges (and
+    /// therefore the proof) are bound to it. Both the prover and the verifier call it before
+    /// absorbing the public inputs, and before any challenge is drawn.
+    pub fn append_to_transcript<T: Transcript>(&self, transcript: &mut T) {
+        transcript.append_message(b"verifier key", &self.fingerprint());
+    }
+
+    /// Blake2b-256 hash of the verifier key: the size of the domain, the rows of the public
+    /// inputs, the verifier part of the scheme and the commitments, which with KZG is the
+    /// encoding by `serialize`. Two keys have the same fingerprint only if they verify the same
+    /// circuit with the same SRS.
+    pub fn fingerprint(&self) -> [u8; 32] {
+        let mut hasher = Blake2b::<U32>::new();
+        hasher.update((self.domain.size as u64).to_le_bytes());
+        hasher.update((self.public_inputs.len() as u64).to_le_bytes());
+        for row in self.public_inputs.iter() {
+            hasher.update((*row as u64).to_le_bytes());
+        }
+        hasher.update(self.scheme.verifier_bytes());
+        for commitment in self.commitments() {
+            hasher.update(P::commitment_bytes(commitment));
+        }
+        hasher.finalize().into()
+    }
+
+    // Commitments in the order of the transcript.
+    pub(crate) fn commitments(&self) -> [&P::Commitment; 15] {
+        [
+            &self.qm_comm,
+            &self.ql_comm,
+            &self.qr_comm,
+            &self.qo_comm,
+            &self.qc_comm,
+            &self.qs1_comm,
+            &self.qs2_comm,
+            &self.qs3_comm,
+            &self.qk_comm,
+            &self.table1_comm,
+            &self.table2_comm,
+            &self.table3_comm,
+            &self.table4_comm,
+            &self.qt_comm,
+            &self.qn_comm,
+        ]
+    }
+}
+
+/// Final check of the verifier, e(lhs, x * g2) = e(rhs, g2), with lhs = w_omega + u *
+/// w_omega_zeta, and rhs the linear combination of the commitments of the verifier key, those
+/// of the proof, and g1, with `coefficients` (in this order, each in the order of its
+/// `commitments`). Everything else the verifier does only depends on scalars, so that a circuit
+/// can compute the check, and leave the multi-scalar multiplication and the pairing to whoever
+/// verifies the outer proof.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeferredCheck {
+    pub u: Scalar,
+    pub coefficients: [Scalar; 29],
+}
+
+impl DeferredCheck {
+    /// Points (lhs, rhs) of the check for the commitments of `verifier_key` and `proof`.
+    pub fn pairing_points(
+        &self,
+        verifier_key: &VerifierKey,
+        proof: &PlonkProof,
+    ) -> (G1Affine, G1Affine) {
+        let lhs = &proof.opening.w_omega + self.u * &proof.opening.w_omega_zeta;
+        let rhs = msm(&Self::bases(verifier_key, proof), &self.coefficients);
+        (lhs.0, rhs.to_affine())
+    }
+
+    // Points of rhs, in the order of the coefficients.
+    fn bases(verifier_key: &VerifierKey, proof: &PlonkProof) -> Vec<G1Affine> {
+        verifier_key
+            .commitments()
+            .into_iter()
+            .chain(proof.commitments())
+            .map(|commitment| commitment.0)
+            .chain([verifier_key.scheme.powers_x_g1[0]])
+            .collect()
+    }
+
+    /// Performs the check.
+    pub fn verify(&self, verifier_key: &VerifierKey, proof: &PlonkProof) -> Result<(), PlonkError> {
+        let (lhs, rhs) = self.pairing_points(verifier_key, proof);
+        pairing_check(&verifier_key.scheme.powers_x_g2, &lhs, &rhs)
+    }
+}
+
+/// Challenges of a proof up to zeta, in the order in which the transcript draws them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Challenges {
+    pub eta: Scalar,
+    pub beta: Scalar,
+    pub gamma: Scalar,
+    pub delta: Scalar,
+    pub epsilon: Scalar,
+    pub alpha: Scalar,
+    pub zeta: Scalar,
+}
 
 pub struct PlonkVerifier;
 
 impl PlonkVerifier {
-    pub fn verify(
+    pub fn verify<P: PlonkCommitment, T: Transcript>(
         pub_in: &[Scalar],
-        pre_in: &PreprocessedInput,
+        verifier_key: &VerifierKey<P>,
+        proof: &PlonkProof<P>,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        let challenges = Self::challenges(pub_in, verifier_key, proof, transcript)?;
+        P::check_proof(pub_in, verifier_key, proof, &challenges, transcript)
+    }
+
+    /// Verifies several proofs of the same circuit with a single multi-pairing. The pairing
+    /// checks of each proof are combined with random weights, so that a batch with an invalid
+    /// proof only passes with negligible probability. Each proof is checked against a fresh
+    /// transcript created with `label`.
+    #[cfg(feature = "std")]
+    pub fn verify_batch<T: Transcript>(
+        verifier_key: &VerifierKey,
+        proofs: &[(PlonkProof, Vec<Scalar>)],
+        label: &'static [u8],
+    ) -> Result<(), PlonkError> {
+        Self::verify_batch_with_rng::<T, _>(verifier_key, proofs, label, &mut OsRng)
+    }
+
+    /// Same as `verify_batch`, drawing the weights from `rng`, for targets without an
+    /// operating system RNG.
+    pub fn verify_batch_with_rng<T: Transcript, R: RngCore + CryptoRng>(
+        verifier_key: &VerifierKey,
+        proofs: &[(PlonkProof, Vec<Scalar>)],
+        label: &'static [u8],
+        rng: &mut R,
+    ) -> Result<(), PlonkError> {
+        let mut lhs_g1 = G1Projective::identity();
+        let mut rhs_g1 = G1Projective::identity();
+        for (proof, pub_in) in proofs {
+            let mut transcript = T::new(label);
+            let (proof_lhs, proof_rhs) =
+                Self::pairing_points(pub_in, verifier_key, proof, &mut transcript)?;
+
+            let weight = Scalar::random(&mut *rng);
+            lhs_g1 += proof_lhs * weight;
+            rhs_g1 += proof_rhs * weight;
+        }
+
+        pairing_check(
+            &verifier_key.scheme.powers_x_g2,
+            &lhs_g1.to_affine(),
+            &rhs_g1.to_affine(),
+        )
+    }
+
+    // Points (lhs, rhs) for which the proof is valid if e(lhs, x * g2) = e(rhs, g2).
+    fn pairing_points<T: Transcript>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey,
+        proof: &PlonkProof,
+        transcript: &mut T,
+    ) -> Result<(G1Affine, G1Affine), PlonkError> {
+        Ok(
+            Self::deferred_check(pub_in, verifier_key, proof, transcript)?
+                .pairing_points(verifier_key, proof),
+        )
+    }
+
+    /// Runs the verifier up to the final check, which is returned instead of performed. This
+    /// is the part of the verifier that the recursion gadget computes in the circuit.
+    pub fn deferred_check<T: Transcript>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey,
         proof: &PlonkProof,
-        transcript: &mut Transcript,
-    ) -> Result<(), ()> {
-        let qm_comm = pre_in.kzg_set.commit(&pre_in.qm_x);
-        let ql_comm = pre_in.kzg_set.commit(&pre_in.ql_x);
-        let qr_comm = pre_in.kzg_set.commit(&pre_in.qr_x);
-        let qo_comm = pre_in.kzg_set.commit(&pre_in.qo_x);
-        let qc_comm = pre_in.kzg_set.commit(&pre_in.qc_x);
-        let s_sig1 = pre_in.kzg_set.commit(&pre_in.qs1_x);
-        let s_sig2 = pre_in.kzg_set.commit(&pre_in.qs2_x);
-        let s_sig3 = pre_in.kzg_set.commit(&pre_in.qs3_x);
-
-        transcript.append_point(b"commitment a", &proof.commitment_a.0);
-        transcript.append_point(b"commitment b", &proof.commitment_b.0);
-        transcript.append_point(b"commitment c", &proof.commitment_c.0);
+        transcript: &mut T,
+    ) -> Result<DeferredCheck, PlonkError> {
+        let challenges = Self::challenges(pub_in, verifier_key, proof, transcript)?;
+        Self::linearised_check(pub_in, verifier_key, proof, &challenges, transcript)
+    }
+
+    // Absorbs the statement, the commitments and the evaluations of the proof, and returns the
+    // challenges drawn in between, which only depends on the scheme through the encoding of
+    // its commitments.
+    fn challenges<P: PlonkCommitment, T: Transcript>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey<P>,
+        proof: &PlonkProof<P>,
+        transcript: &mut T,
+    ) -> Result<Challenges, PlonkError> {
+        if pub_in.len() != verifier_key.public_inputs.len() {
+            return Err(PlonkError::PublicInputLength {
+                expected: verifier_key.public_inputs.len(),
+                got: pub_in.len(),
+            });
+        }
+
+        verifier_key.append_to_transcript(transcript);
+        for input in pub_in.iter() {
+            transcript.append_scalar(b"public input", input);
+        }
+
+        P::append_commitment(transcript, b"commitment a", &proof.commitment_a);
+        P::append_commitment(transcript, b"commitment b", &proof.commitment_b);
+        P::append_commitment(transcript, b"commitment c", &proof.commitment_c);
+
+        let eta = transcript.challenge_scalar(b"eta");
+
+        P::append_commitment(transcript, b"commitment f", &proof.commitment_f);
+        P::append_commitment(transcript, b"commitment h1", &proof.commitment_h1);
+        P::append_commitment(transcript, b"commitment h2", &proof.commitment_h2);
 
         let beta = transcript.challenge_scalar(b"beta");
         let gamma = transcript.challenge_scalar(b"gamma");
+        let delta = transcript.challenge_scalar(b"delta");
+        let epsilon = transcript.challenge_scalar(b"epsilon");
 
-        transcript.append_point(b"Permutation polynomial", &proof.commitment_z.0);
+        P::append_commitment(transcript, b"Permutation polynomial", &proof.commitment_z);
+        P::append_commitment(transcript, b"Lookup polynomial", &proof.commitment_z_lookup);
 
         let alpha = transcript.challenge_scalar(b"alpha");
 
-        transcript.append_point(b"Quotient low polynomial", &proof.t_low.0);
-        transcript.append_point(b"Quotient mid polynomial", &proof.t_mid.0);
-        transcript.append_point(b"Quotient high polynomial", &proof.t_high.0);
+        P::append_commitment(transcript, b"Quotient low polynomial", &proof.t_low);
+        P::append_commitment(transcript, b"Quotient mid polynomial", &proof.t_mid);
+        P::append_commitment(transcript, b"Quotient high polynomial", &proof.t_high);
 
         let zeta = transcript.challenge_scalar(b"zeta");
 
@@ -48,138 +288,415 @@ impl PlonkVerifier {
         transcript.append_scalar(b"Append s_sig1.", &proof.s_sig1);
         transcript.append_scalar(b"Append s_sig2.", &proof.s_sig2);
         transcript.append_scalar(b"Append z_omega.", &proof.z_omega);
+        transcript.append_scalar(b"Append f_eval.", &proof.f_eval);
+        transcript.append_scalar(b"Append qt_eval.", &proof.qt_eval);
+        transcript.append_scalar(b"Append table_eval.", &proof.table_eval);
+        transcript.append_scalar(b"Append h2_eval.", &proof.h2_eval);
+        transcript.append_scalar(b"Append table_omega.", &proof.table_omega);
+        transcript.append_scalar(b"Append h1_omega.", &proof.h1_omega);
+        transcript.append_scalar(b"Append z_lookup_omega.", &proof.z_lookup_omega);
+        transcript.append_scalar(b"Append a_omega.", &proof.a_omega);
 
-        let v = transcript.challenge_scalar(b"v");
+        Ok(Challenges {
+            eta,
+            beta,
+            gamma,
+            delta,
+            epsilon,
+            alpha,
+            zeta,
+        })
+    }
 
-        transcript.append_point(b"w_omega comm", &proof.w_omega.0);
-        transcript.append_point(b"w_omega_zeta comm", &proof.w_omega_zeta.0);
+    // We evaluate the public polynomial with the barycentric formula, so that the verifier
+    // never interpolates over the domain.
+    fn public_input_eval<P: PlonkCommitment>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey<P>,
+        zeta: &Scalar,
+    ) -> Scalar {
+        pub_in
+            .iter()
+            .zip(verifier_key.public_inputs.iter())
+            .map(|(s, row)| s.neg() * verifier_key.domain.evaluate_lagrange(*row, zeta))
+            .sum()
+    }
 
-        let u = transcript.challenge_scalar(b"u");
+    // Rest of the verifier of KZG proofs, once the evaluations are absorbed: the commitment to
+    // the linearisation polynomial is combined from those of the keys and the proof, and the
+    // openings at zeta and zeta * omega are checked with a single pairing equation.
+    pub(crate) fn linearised_check<T: Transcript>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey,
+        proof: &PlonkProof,
+        challenges: &Challenges,
+        transcript: &mut T,
+    ) -> Result<DeferredCheck, PlonkError> {
+        let Challenges {
+            eta,
+            beta,
+            gamma,
+            delta,
+            epsilon,
+            alpha,
+            zeta,
+        } = *challenges;
 
-        let zero_poly_eval = pre_in.blinder_polynomial.eval(&zeta);
+        // The polynomials opened by the prover, with the linearisation first, which vanishes at
+        // zeta.
+        let opening = TwoPointOpening {
+            openings: [
+                Kzg10Opening {
+                    evaluations: vec![
+                        Scalar::zero(),
+                        proof.a_eval,
+                        proof.b_eval,
+                        proof.c_eval,
+                        proof.s_sig1,
+                        proof.s_sig2,
+                        proof.f_eval,
+                        proof.table_eval,
+                        proof.h2_eval,
+                        proof.qt_eval,
+                    ],
+                    witness: proof.opening.w_omega.clone(),
+                },
+                Kzg10Opening {
+                    evaluations: vec![
+                        proof.z_omega,
+                        proof.z_lookup_omega,
+                        proof.table_omega,
+                        proof.h1_omega,
+                        proof.a_omega,
+                    ],
+                    witness: proof.opening.w_omega_zeta.clone(),
+                },
+            ],
+        };
+        let check = Kzg10::two_points_check(
+            &[zeta, zeta * verifier_key.domain.generator],
+            &opening,
+            transcript,
+        );
+        let u = check.u;
+        let [at_zeta, at_zeta_omega] = &check.commitments;
+        let linearisation = at_zeta[0];
 
-        // We compute the public polynomial
-        let pi_eval: Scalar = pub_in
-            .iter()
-            .enumerate()
-            .map(|(index, s)| s * pre_in.constraints.lagrange_basis(index).eval(&zeta))
-            .sum();
+        let zero_poly_eval = zeta.pow_vartime([verifier_key.domain.size as u64]) - Scalar::one();
+
+        let pi_eval = Self::public_input_eval(pub_in, verifier_key, &zeta);
+        let l0_eval = verifier_key.domain.evaluate_lagrange(0, &zeta);
+        let alpha_powers = [
+            alpha,
+            alpha.square(),
+            alpha.pow_vartime([3]),
+            alpha.pow_vartime([4]),
+            alpha.pow_vartime([5]),
+        ];
+        let one_plus_delta = Scalar::one() + delta;
+        let epsilon_one_plus_delta = epsilon * one_plus_delta;
+        let h1_coefficient = (epsilon_one_plus_delta + proof.h2_eval + delta * proof.h1_omega)
+            * proof.z_lookup_omega
+            * alpha_powers[4];
 
         // Now we split r into its constant and non-constant terms.
         let r0 = pi_eval
-            + pre_in.constraints.lagrange_basis(0).eval(&zeta).neg() * alpha * alpha
+            + l0_eval.neg() * alpha_powers[1]
             + alpha.neg()
                 * (proof.a_eval + beta * proof.s_sig1 + gamma)
                 * (proof.b_eval + beta * proof.s_sig2 + gamma)
                 * (proof.c_eval + gamma)
-                * proof.z_omega;
-
-        let batch_poly_commit_1 = proof.a_eval * proof.b_eval * qm_comm
-            + proof.a_eval * ql_comm
-            + proof.b_eval * qr_comm
-            + proof.c_eval * qo_comm
-            + qc_comm
-            + ((proof.a_eval + beta * zeta + gamma)
-                * (proof.b_eval + beta * K1() * zeta + gamma)
-                * (proof.c_eval + beta * K2() * zeta + gamma)
-                * alpha
-                + pre_in.constraints.lagrange_basis(0).eval(&zeta) * alpha * alpha
-                + u)
-                * &proof.commitment_z
-            + (proof.a_eval + beta * proof.s_sig1 + gamma).neg()
-                * (proof.b_eval + beta * proof.s_sig2 + gamma)
-                * alpha
-                * beta
                 * proof.z_omega
-                * s_sig3
-            + zero_poly_eval.neg()
-                * (&proof.t_low
-                    + &proof.t_mid
-                        * zeta.pow_vartime([pre_in.constraints.nr_constraints as u64, 0, 0, 0])
-                    + &proof.t_high
-                        * zeta.pow_vartime([
-                            2 * pre_in.constraints.nr_constraints as u64,
-                            0,
-                            0,
-                            0,
-                        ]));
-
-        let batch_poly_commit_full = batch_poly_commit_1
-            + v * (&proof.commitment_a
-                + v * (&proof.commitment_b
-                    + v * (&proof.commitment_c + v * (&s_sig1 + v * (&s_sig2)))));
-
-        let group_encoded_batch_eval = pre_in.kzg_set.powers_x_g1[0]
-            * (r0.neg()
-                + v * (proof.a_eval
-                    + v * (proof.b_eval
-                        + v * (proof.c_eval + v * (proof.s_sig1 + v * proof.s_sig2))))
-                + u * proof.z_omega);
-
-        let lhs_g1 = &proof.w_omega + u * &proof.w_omega_zeta;
-        let rhs_g2 = zeta * &proof.w_omega
-            + u * zeta * pre_in.constraints.extended_h_subgroup[0] * &proof.w_omega_zeta
-            + batch_poly_commit_full
-            + Kzg10Commitment(group_encoded_batch_eval.to_affine().neg());
-
-        let lhs_pairing = pairing(&lhs_g1.0, &pre_in.kzg_set.powers_x_g2[1]);
-        let rhs_pairing = pairing(&rhs_g2.0, &pre_in.kzg_set.powers_x_g2[0]);
-
-        if lhs_pairing == rhs_pairing {
-            Ok(())
-        } else {
-            Err(())
+            + l0_eval.neg() * alpha_powers[3]
+            + (epsilon_one_plus_delta + delta * proof.h2_eval).neg() * h1_coefficient;
+
+        let s_sig3_coefficient = (proof.a_eval + beta * proof.s_sig1 + gamma).neg()
+            * (proof.b_eval + beta * proof.s_sig2 + gamma)
+            * alpha
+            * beta
+            * proof.z_omega;
+        let z_coefficient = (proof.a_eval + beta * zeta + gamma)
+            * (proof.b_eval + beta * K1() * zeta + gamma)
+            * (proof.c_eval + beta * K2() * zeta + gamma)
+            * alpha
+            + l0_eval * alpha_powers[1];
+        let z_lookup_coefficient = one_plus_delta
+            * (epsilon + proof.f_eval)
+            * (epsilon_one_plus_delta + proof.table_eval + delta * proof.table_omega)
+            * alpha_powers[4]
+            + l0_eval * alpha_powers[3];
+        // The table is committed as t_1 + eta * t_2 + eta^2 * t_3 + eta^3 * t_4.
+        let table_coefficient = at_zeta[7] + at_zeta_omega[2];
+        let zeta_n = zeta.pow_vartime([verifier_key.domain.size as u64]);
+
+        Ok(DeferredCheck {
+            u,
+            coefficients: [
+                proof.a_eval * proof.b_eval * linearisation,
+                proof.a_eval * linearisation,
+                proof.b_eval * linearisation,
+                proof.c_eval * linearisation,
+                linearisation,
+                at_zeta[4],
+                at_zeta[5],
+                s_sig3_coefficient * linearisation,
+                (compress(
+                    &[proof.a_eval, proof.b_eval, proof.c_eval, proof.qt_eval],
+                    &eta,
+                ) - proof.f_eval)
+                    * alpha_powers[2]
+                    * linearisation,
+                table_coefficient,
+                table_coefficient * eta,
+                table_coefficient * eta.square(),
+                table_coefficient * eta.pow_vartime([3]),
+                at_zeta[9],
+                proof.a_omega * linearisation,
+                at_zeta[1] + at_zeta_omega[4],
+                at_zeta[2],
+                at_zeta[3],
+                at_zeta[6],
+                at_zeta_omega[3] - h1_coefficient * linearisation,
+                at_zeta[8],
+                z_coefficient * linearisation + at_zeta_omega[0],
+                z_lookup_coefficient * linearisation + at_zeta_omega[1],
+                zero_poly_eval.neg() * linearisation,
+                zero_poly_eval.neg() * zeta_n * linearisation,
+                zero_poly_eval.neg() * zeta_n.square() * linearisation,
+                check.witnesses[0],
+                check.witnesses[1],
+                r0 * linearisation + check.generator,
+            ],
+        })
+    }
+
+    // Verifier of the schemes without linearisation, once the evaluations are absorbed. Every
+    // polynomial is opened at zeta, and those of the next row at zeta * omega as well, so the
+    // verifier evaluates the numerator of the quotient, and checks that it is Z_H(zeta) times
+    // the quotient, before checking the opening.
+    pub(crate) fn check_openings<P: PlonkCommitment, T: Transcript>(
+        pub_in: &[Scalar],
+        verifier_key: &VerifierKey<P>,
+        proof: &PlonkProof<P>,
+        opening: &P::BatchOpening,
+        challenges: &Challenges,
+        transcript: &mut T,
+    ) -> Result<(), PlonkError> {
+        let Challenges {
+            eta,
+            beta,
+            gamma,
+            delta,
+            epsilon,
+            alpha,
+            zeta,
+        } = *challenges;
+        let points = opening_points(&zeta, &verifier_key.domain.generator);
+        let evaluations = P::batch_evaluations(opening);
+        if evaluations.len() != points.len()
+            || evaluations
+                .iter()
+                .zip(points.iter())
+                .any(|(evaluations, points)| evaluations.len() != points.len())
+        {
+            return Err(PlonkError::VerificationFailed);
+        }
+
+        let at_zeta = |i: usize| evaluations[i][0];
+        let at_zeta_omega = |i: usize| evaluations[i][1];
+        let [a, b, c, s_sig1, s_sig2, s_sig3, ql, qr, qm, qo, qc, qn, qk, qt] =
+            core::array::from_fn(at_zeta);
+        let table = compress(&[14, 15, 16, 17].map(at_zeta), &eta);
+        let table_omega = compress(&[14, 15, 16, 17].map(at_zeta_omega), &eta);
+        let [f, h1, h2, z, z_lookup, t_low, t_mid, t_high] =
+            core::array::from_fn(|i| at_zeta(18 + i));
+        let [a_omega, h1_omega, z_omega, z_lookup_omega] = [0, 19, 21, 22].map(at_zeta_omega);
+
+        // The evaluations of the proof must be the opened ones.
+        let opened = [
+            a,
+            b,
+            c,
+            s_sig1,
+            s_sig2,
+            z_omega,
+            f,
+            qt,
+            table,
+            h2,
+            table_omega,
+            h1_omega,
+            z_lookup_omega,
+            a_omega,
+        ];
+        if proof
+            .evaluations()
+            .into_iter()
+            .zip(opened.iter())
+            .any(|(claimed, opened)| claimed != opened)
+        {
+            return Err(PlonkError::VerificationFailed);
+        }
+
+        let pi_eval = Self::public_input_eval(pub_in, verifier_key, &zeta);
+        let l0_eval = verifier_key.domain.evaluate_lagrange(0, &zeta);
+        let alpha_powers = [
+            alpha,
+            alpha.square(),
+            alpha.pow_vartime([3]),
+            alpha.pow_vartime([4]),
+            alpha.pow_vartime([5]),
+        ];
+        let one_plus_delta = Scalar::one() + delta;
+        let epsilon_one_plus_delta = epsilon * one_plus_delta;
+
+        let gate = a * b * qm + a * ql + b * qr + c * qo + a_omega * qn + pi_eval + qc;
+        let permutation = ((a + beta * zeta + gamma)
+            * (b + beta * K1() * zeta + gamma)
+            * (c + beta * K2() * zeta + gamma)
+            * z
+            - (a + beta * s_sig1 + gamma)
+                * (b + beta * s_sig2 + gamma)
+                * (c + beta * s_sig3 + gamma)
+                * z_omega)
+            * alpha;
+        let lookup = qk * (compress(&[a, b, c, qt], &eta) - f) * alpha_powers[2]
+            + (z_lookup - Scalar::one()) * l0_eval * alpha_powers[3]
+            + (z_lookup
+                * one_plus_delta
+                * (epsilon + f)
+                * (epsilon_one_plus_delta + table + delta * table_omega)
+                - z_lookup_omega
+                    * (epsilon_one_plus_delta + h1 + delta * h2)
+                    * (epsilon_one_plus_delta + h2 + delta * h1_omega))
+                * alpha_powers[4];
+        let numerator =
+            gate + permutation + (z - Scalar::one()) * l0_eval * alpha_powers[1] + lookup;
+
+        let zeta_n = zeta.pow_vartime([verifier_key.domain.size as u64]);
+        let quotient = t_low + zeta_n * (t_mid + zeta_n * t_high);
+        if numerator != (zeta_n - Scalar::one()) * quotient {
+            return Err(PlonkError::VerificationFailed);
         }
+
+        let commitments = opened_commitments(verifier_key, proof);
+        verifier_key
+            .scheme
+            .check_batch(&commitments, &points, opening, transcript)
     }
 }
 
+// Points at which the schemes without linearisation open the polynomials of a proof, in the
+// order of `opened_commitments`. The left wire, the tables, h1 and the products of the
+// permutation and of the lookup are opened at zeta * omega as well.
+pub(crate) fn opening_points(zeta: &Scalar, omega: &Scalar) -> Vec<Vec<Scalar>> {
+    let both = [0, 14, 15, 16, 17, 19, 21, 22];
+    (0..26)
+        .map(|i| {
+            if both.contains(&i) {
+                vec![*zeta, *zeta * omega]
+            } else {
+                vec![*zeta]
+            }
+        })
+        .collect()
+}
+
+// Commitments opened by the schemes without linearisation: the wires, the permutation and the
+// selectors, the tables, f, h1 and h2, the products of the permutation and of the lookup, and
+// the parts of the quotient.
+fn opened_commitments<P: PlonkCommitment>(
+    verifier_key: &VerifierKey<P>,
+    proof: &PlonkProof<P>,
+) -> Vec<P::Commitment> {
+    [
+        &proof.commitment_a,
+        &proof.commitment_b,
+        &proof.commitment_c,
+        &verifier_key.qs1_comm,
+        &verifier_key.qs2_comm,
+        &verifier_key.qs3_comm,
+        &verifier_key.ql_comm,
+        &verifier_key.qr_comm,
+        &verifier_key.qm_comm,
+        &verifier_key.qo_comm,
+        &verifier_key.qc_comm,
+        &verifier_key.qn_comm,
+        &verifier_key.qk_comm,
+        &verifier_key.qt_comm,
+        &verifier_key.table1_comm,
+        &verifier_key.table2_comm,
+        &verifier_key.table3_comm,
+        &verifier_key.table4_comm,
+        &proof.commitment_f,
+        &proof.commitment_h1,
+        &proof.commitment_h2,
+        &proof.commitment_z,
+        &proof.commitment_z_lookup,
+        &proof.t_low,
+        &proof.t_mid,
+        &proof.t_high,
+    ]
+    .map(Clone::clone)
+    .to_vec()
+}
+
 #[cfg(test)]
 mod test {
-    use std::ops::Neg;
-    use crate::plonk::{ComputationTrace, PlonkCircuit, PreprocessedInput};
+    use crate::error::PlonkError;
+    use crate::gadgets::poseidon::poseidon_hash;
+    use crate::lookup::LookupTable;
+    use crate::plonk::{ComputationTrace, Gate, PlonkCircuit, ProverKey, UnsatisfiedGate, Wire};
     use crate::prover::Prover;
-    use crate::transcript::Transcript;
-    use crate::verifier::PlonkVerifier;
+    use crate::transcript::{Blake2bTranscript, PoseidonTranscript, Transcript};
+    use crate::verifier::{PlonkVerifier, VerifierKey};
+    use crate::witness::WitnessBuilder;
+    use blake2::{digest::consts::U32, Blake2b, Digest};
     use blstrs::Scalar;
     use ff::Field;
+    use rand_chacha::ChaCha20Rng;
+    use rand_core::{OsRng, SeedableRng};
 
-    fn create_dummy_circuit_and_prover_key() -> (PreprocessedInput, ComputationTrace, Vec<Scalar>) {
+    fn create_dummy_circuit_and_prover_key(
+    ) -> (ProverKey, VerifierKey, ComputationTrace, Vec<Scalar>) {
         // We are going to begin with a simple proof, showing that I know the value of
         // a pythagorean triplet. i.e., three values such that x^2 + y^2 = z^2;
         let mut circuit = PlonkCircuit::init();
 
-        circuit.prepare_pi(); // pub constraint
+        let pi = circuit.public_input(); // pub constraint
 
-        //                     | a | b | c |
-        //                      ----------
-        circuit.mult_gate(); // x * x = x^2
-        circuit.mult_gate(); // y * y = y^2
-        circuit.mult_gate(); // z * z = z^2
-        circuit.add_gate(); // x^2 + y^2 = z^2
-        circuit.mult_gate(); // z * y = v
-        circuit.add_gate(); // v + z^2 = res
+        //                                       | a | b | c |
+        //                                        ----------
+        let (x_l, x_r, x_sq) = circuit.mult_gate(); // x * x = x^2
+        let (y_l, y_r, y_sq) = circuit.mult_gate(); // y * y = y^2
+        let (z_l, z_r, z_sq) = circuit.mult_gate(); // z * z = z^2
+        let (sum_l, sum_r, sum_o) = circuit.add_gate(); // x^2 + y^2 = z^2
+        let (zy_l, zy_r, zy_o) = circuit.mult_gate(); // z * y = v
+        let (res_l, res_r, _) = circuit.add_gate(); // v + z^2 = res
 
         // Gates are finished, so here we pad to the next power of two
         circuit.pad_next_power_of_two();
 
         // We need to connect the wires with the padded trace:
-        circuit.connect_wires(&0, &17); // Connecting PI with x^2
-        circuit.connect_wires(&1, &9);
-        circuit.connect_wires(&17, &4);
-        circuit.connect_wires(&2, &10);
-        circuit.connect_wires(&18, &12);
-        circuit.connect_wires(&3, &11);
-        circuit.connect_wires(&19, &20);
-        circuit.connect_wires(&3, &5);
-        circuit.connect_wires(&2, &13);
-        circuit.connect_wires(&21, &6);
-        circuit.connect_wires(&19, &14);
+        circuit.connect(pi, x_sq).unwrap(); // Connecting PI with x^2
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.connect(x_sq, sum_l).unwrap();
+        circuit.connect(y_l, y_r).unwrap();
+        circuit.connect(y_sq, sum_r).unwrap();
+        circuit.connect(z_l, z_r).unwrap();
+        circuit.connect(z_sq, sum_o).unwrap();
+        circuit.connect(z_l, zy_l).unwrap();
+        circuit.connect(y_l, zy_r).unwrap();
+        circuit.connect(zy_o, res_l).unwrap();
+        circuit.connect(z_sq, res_r).unwrap();
 
         // Circuit is finished, so we set it up
-        let setup = circuit.setup();
+        let setup = circuit
+            .setup(&mut ChaCha20Rng::from_seed([0u8; 32]))
+            .unwrap();
 
         // We put as a public input that the first square (x^2) needs to be 9
-        let pub_in = vec![Scalar::from(9).neg()];
+        let pub_in = vec![Scalar::from(9)];
 
         // As a computation trace, we'll create the proof for the values (3,4,5)
         let computation_trace = ComputationTrace {
@@ -213,27 +730,574 @@ mod test {
         }
         .pad_next_power_two();
 
-        (setup, computation_trace, pub_in)
+        let (prover_key, verifier_key) = setup;
+        (prover_key, verifier_key, computation_trace, pub_in)
     }
     #[test]
     fn test_verifier() {
-        let mut prover_transcript = Transcript::new(b"testing the prover");
-        let mut verifier_transcript = Transcript::new(b"testing the prover");
+        let mut prover_transcript = Blake2bTranscript::new(b"testing the prover");
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing the prover");
+
+        let (prover_key, verifier_key, trace, pub_in) = create_dummy_circuit_and_prover_key();
+
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut prover_transcript).unwrap();
+
+        assert!(
+            PlonkVerifier::verify(&pub_in, &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_verifier_wrong_public_input() {
+        let mut prover_transcript = Blake2bTranscript::new(b"testing the prover");
+        let (prover_key, verifier_key, trace, pub_in) = create_dummy_circuit_and_prover_key();
+
+        // The gates hold on the trace alone, so only the quotient shows that the public input
+        // does not match it.
+        assert_eq!(
+            Prover::prove(
+                &[Scalar::from(16)],
+                &prover_key,
+                &trace,
+                &mut prover_transcript.clone()
+            )
+            .err(),
+            Some(PlonkError::PublicInputsUnsatisfied)
+        );
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut prover_transcript).unwrap();
+
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing the prover");
+        assert_eq!(
+            PlonkVerifier::verify(
+                &[Scalar::from(16)],
+                &verifier_key,
+                &proof,
+                &mut verifier_transcript
+            ),
+            Err(PlonkError::VerificationFailed)
+        );
+
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing the prover");
+        assert_eq!(
+            PlonkVerifier::verify(&[], &verifier_key, &proof, &mut verifier_transcript),
+            Err(PlonkError::PublicInputLength {
+                expected: 1,
+                got: 0
+            })
+        );
+    }
+    #[test]
+    fn test_verifier_wrong_transcript() {
+        let mut prover_transcript = Blake2bTranscript::new(b"testing the prover");
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing the verifier");
+
+        let (prover_key, verifier_key, trace, pub_in) = create_dummy_circuit_and_prover_key();
+
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut prover_transcript).unwrap();
+
+        assert_eq!(
+            PlonkVerifier::verify(&pub_in, &verifier_key, &proof, &mut verifier_transcript),
+            Err(PlonkError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let (prover_key, verifier_key, trace, pub_in) = create_dummy_circuit_and_prover_key();
+        let mut bytes = Vec::new();
+        verifier_key.serialize(&mut bytes).unwrap();
+        let hash: [u8; 32] = Blake2b::<U32>::digest(&bytes).into();
+        assert_eq!(verifier_key.fingerprint(), hash);
+        let (_, same_key, _, _) = create_dummy_circuit_and_prover_key();
+        assert_eq!(same_key.fingerprint(), hash);
+
+        // A proof does not verify against a circuit of the same size with other selectors.
+        let mut transcript = Blake2bTranscript::new(b"testing the prover");
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut transcript).unwrap();
+        let mut other_key = verifier_key.clone();
+        other_key.qc_comm = other_key.qm_comm.clone();
+        assert_ne!(other_key.fingerprint(), hash);
+        let mut transcript = Blake2bTranscript::new(b"testing the prover");
+        assert_eq!(
+            PlonkVerifier::verify(&pub_in, &other_key, &proof, &mut transcript),
+            Err(PlonkError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let (prover_key, verifier_key, trace, pub_in) = create_dummy_circuit_and_prover_key();
+
+        let proofs = (0..3)
+            .map(|_| {
+                let mut transcript = Blake2bTranscript::new(b"testing the prover");
+                let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut transcript).unwrap();
+                (proof, pub_in.clone())
+            })
+            .collect::<Vec<_>>();
 
-        let (pre_in, trace, pub_in) = create_dummy_circuit_and_prover_key();
+        assert!(PlonkVerifier::verify_batch::<Blake2bTranscript>(
+            &verifier_key,
+            &proofs,
+            b"testing the prover"
+        )
+        .is_ok());
+        // Weights from a seeded RNG, as on targets without an operating system.
+        assert!(
+            PlonkVerifier::verify_batch_with_rng::<Blake2bTranscript, _>(
+                &verifier_key,
+                &proofs,
+                b"testing the prover",
+                &mut ChaCha20Rng::from_seed([1u8; 32])
+            )
+            .is_ok()
+        );
 
-        let proof = Prover::prove(&pub_in, &pre_in, &trace, &mut prover_transcript);
+        let mut wrong_proofs = proofs;
+        wrong_proofs[1].1 = vec![Scalar::from(10)];
+        assert_eq!(
+            PlonkVerifier::verify_batch::<Blake2bTranscript>(
+                &verifier_key,
+                &wrong_proofs,
+                b"testing the prover"
+            ),
+            Err(PlonkError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn test_verifier_poseidon() {
+        let mut prover_transcript = PoseidonTranscript::new(b"testing the prover");
+        let mut verifier_transcript = PoseidonTranscript::new(b"testing the prover");
+
+        let (prover_key, verifier_key, trace, pub_in) = create_dummy_circuit_and_prover_key();
 
-        assert!(PlonkVerifier::verify(&pub_in, &pre_in, &proof, &mut verifier_transcript).is_ok());
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut prover_transcript).unwrap();
+
+        assert!(
+            PlonkVerifier::verify(&pub_in, &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+
+        // A proof is bound to the transcript it was created with.
+        let mut blake_transcript = Blake2bTranscript::new(b"testing the prover");
+        assert_eq!(
+            PlonkVerifier::verify(&pub_in, &verifier_key, &proof, &mut blake_transcript),
+            Err(PlonkError::VerificationFailed)
+        );
     }
+
+    #[cfg(feature = "merlin")]
+    #[test]
+    fn test_verifier_merlin() {
+        use crate::transcript::MerlinTranscript;
+
+        let mut prover_transcript = MerlinTranscript::new(b"testing the prover");
+        let mut verifier_transcript = MerlinTranscript::new(b"testing the prover");
+
+        let (prover_key, verifier_key, trace, pub_in) = create_dummy_circuit_and_prover_key();
+
+        let proof = Prover::prove(&pub_in, &prover_key, &trace, &mut prover_transcript).unwrap();
+
+        assert!(
+            PlonkVerifier::verify(&pub_in, &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_custom_gate() {
+        // 3a + 2b - c + 5 = 0 in a single row, followed by c * c = d.
+        let mut circuit = PlonkCircuit::init();
+        let (_, _, c) = circuit.gate(Gate::new().left(3).right(2).output(-1).constant(5));
+        let (c_l, c_r, _) = circuit.mult_gate();
+        circuit.pad_next_power_of_two();
+        circuit.connect(c, c_l).unwrap();
+        circuit.connect(c, c_r).unwrap();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let trace = ComputationTrace {
+            a: vec![Scalar::from(1), Scalar::from(12)],
+            b: vec![Scalar::from(2), Scalar::from(12)],
+            c: vec![Scalar::from(12), Scalar::from(144)],
+        };
+
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a custom gate");
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing a custom gate");
+        let proof = Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).unwrap();
+
+        assert!(
+            PlonkVerifier::verify(&[], &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_assert_constant() {
+        // x * x = y, with y pinned to 25.
+        let mut circuit = PlonkCircuit::init();
+        let (x_l, x_r, y) = circuit.mult_gate();
+        circuit.connect(x_l, x_r).unwrap();
+        circuit.assert_constant(y, Scalar::from(25)).unwrap();
+        circuit.pad_next_power_of_two();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        assert_eq!(trace.a[1], Scalar::from(25));
+        trace.set(x_l, Scalar::from(5));
+        trace.set(x_r, Scalar::from(5));
+        trace.set(y, Scalar::from(25));
+
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a constant");
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing a constant");
+        let proof = Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).unwrap();
+        assert!(
+            PlonkVerifier::verify(&[], &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+
+        // Any other square breaks the copy constraint with the constant row.
+        trace.set(x_l, Scalar::from(4));
+        trace.set(x_r, Scalar::from(4));
+        trace.set(y, Scalar::from(16));
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a constant");
+        assert!(matches!(
+            Prover::prove(&[], &prover_key, &trace, &mut prover_transcript),
+            Err(PlonkError::CopyConstraintUnsatisfied { .. })
+        ));
+    }
+
+    #[test]
+    fn test_range_check() {
+        // x + y = z, with z in [0, 2^8).
+        let mut circuit = PlonkCircuit::init();
+        let (x, y, z) = circuit.add_gate();
+        let range = circuit.range_check(z, 8).unwrap();
+        circuit.pad_next_power_of_two();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+        assert_eq!(prover_key.constraints.nr_constraints, 16);
+
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        trace.set(x, Scalar::from(100));
+        trace.set(y, Scalar::from(155));
+        trace.set(z, Scalar::from(255));
+        range.assign(&mut trace, &Scalar::from(255));
+
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a range check");
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing a range check");
+        let proof = Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).unwrap();
+        assert!(
+            PlonkVerifier::verify(&[], &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+
+        trace.set(y, Scalar::from(156));
+        trace.set(z, Scalar::from(256));
+        range.assign(&mut trace, &Scalar::from(256));
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a range check");
+        assert!(Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).is_err());
+
+        assert_eq!(
+            PlonkCircuit::init().range_check(x, 255).err(),
+            Some(PlonkError::RangeTooLarge {
+                num_bits: 255,
+                max: 254
+            })
+        );
+    }
+
+    #[test]
+    fn test_lookup() {
+        // A table with the XOR of every pair of 2-bit values, and a circuit showing that
+        // (x XOR y) + z = w.
+        let mut circuit = PlonkCircuit::init();
+        let xor_table = circuit.add_lookup_table(LookupTable::xor(2));
+        let (x, y, xor) = circuit.lookup_gate(xor_table).unwrap();
+        let (sum_l, z, w) = circuit.add_gate();
+        circuit.connect(xor, sum_l).unwrap();
+        circuit.pad_next_power_of_two();
+        assert_eq!(circuit.nr_constraints, 16);
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        trace.set(x, Scalar::from(2));
+        trace.set(y, Scalar::from(3));
+        trace.set(xor, Scalar::from(1));
+        trace.set(sum_l, Scalar::from(1));
+        trace.set(z, Scalar::from(7));
+        trace.set(w, Scalar::from(8));
+
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a lookup");
+        let proof = Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).unwrap();
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing a lookup");
+        assert!(
+            PlonkVerifier::verify(&[], &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+
+        let mut wrong_proof = proof;
+        wrong_proof.f_eval += Scalar::one();
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing a lookup");
+        assert_eq!(
+            PlonkVerifier::verify(&[], &verifier_key, &wrong_proof, &mut verifier_transcript),
+            Err(PlonkError::VerificationFailed)
+        );
+
+        // 2 XOR 3 is not 2.
+        trace.set(xor, Scalar::from(2));
+        trace.set(sum_l, Scalar::from(2));
+        trace.set(w, Scalar::from(9));
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a lookup");
+        assert_eq!(
+            Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).err(),
+            Some(PlonkError::LookupFailed { row: 0 })
+        );
+    }
+
+    #[test]
+    fn test_bitwise_gadgets() {
+        // (x XOR y) AND y, over 2-bit values.
+        let mut circuit = PlonkCircuit::init();
+        let (x, y, _) = circuit.add_gate();
+        let xor = circuit.xor(x, y, 2).unwrap();
+        let and = circuit.and(xor, y, 2).unwrap();
+        let second_xor = circuit.xor(and, x, 2).unwrap();
+        assert_eq!(circuit.lookup_tables.len(), 2);
+        circuit.pad_next_power_of_two();
+        assert_eq!(circuit.nr_constraints, 32);
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        let assign = |trace: &mut ComputationTrace, values: [u64; 5]| {
+            let [x_value, y_value, xor_value, and_value, second_value] = values.map(Scalar::from);
+            trace.set(x, x_value);
+            trace.set(y, y_value);
+            trace.set(Wire::output(0), x_value + y_value);
+            for (row, (left, right, output)) in [
+                (x_value, y_value, xor_value),
+                (xor_value, y_value, and_value),
+                (and_value, x_value, second_value),
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                trace.set(Wire::left(row + 1), left);
+                trace.set(Wire::right(row + 1), right);
+                trace.set(Wire::output(row + 1), output);
+            }
+        };
+
+        assign(&mut trace, [2, 3, 1, 1, 3]);
+        let mut prover_transcript = Blake2bTranscript::new(b"testing bitwise gadgets");
+        let proof = Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).unwrap();
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing bitwise gadgets");
+        assert!(
+            PlonkVerifier::verify(&[], &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+        assert_eq!(and, Wire::output(2));
+        assert_eq!(second_xor, Wire::output(3));
+
+        // 2 AND 3 is a row of the AND table, but not of the XOR table.
+        assign(&mut trace, [2, 3, 2, 2, 0]);
+        let mut prover_transcript = Blake2bTranscript::new(b"testing bitwise gadgets");
+        assert_eq!(
+            Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).err(),
+            Some(PlonkError::LookupFailed { row: 1 })
+        );
+
+        let mut circuit = PlonkCircuit::init();
+        let (x, y, _) = circuit.add_gate();
+        circuit.xor_u8(x, y).unwrap();
+        circuit.xor_u8(x, y).unwrap();
+        circuit.and_u8(x, y).unwrap();
+        assert_eq!(circuit.lookup_tables.len(), 2);
+        assert_eq!(circuit.lookup_tables[0].len(), 1 << 16);
+        assert_eq!(
+            circuit.lookup_gate(3).err(),
+            Some(PlonkError::UnknownLookupTable(3))
+        );
+    }
+
+    #[test]
+    fn test_custom_lookup_table() {
+        // A 3-bit S-box, and a circuit showing that S(x) * S(x) = y.
+        let sbox = [6u64, 4, 0, 3, 7, 1, 5, 2];
+        let mut circuit = PlonkCircuit::init();
+        let table = circuit.add_lookup_table(LookupTable::new(
+            sbox.iter()
+                .enumerate()
+                .map(|(x, s)| [Scalar::from(x as u64), Scalar::from(*s)])
+                .collect(),
+        ));
+        let (s_l, s_r, y) = circuit.mult_gate();
+        let (x, s, unused) = circuit.lookup(table, &[Wire::left(1), s_l]).unwrap();
+        assert_eq!(x, Wire::left(1));
+        circuit.connect(s_l, s_r).unwrap();
+        circuit.pad_next_power_of_two();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        for wire in [s_l, s_r, s] {
+            trace.set(wire, Scalar::from(sbox[5]));
+        }
+        trace.set(x, Scalar::from(5));
+        trace.set(y, Scalar::from(sbox[5] * sbox[5]));
+
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a custom table");
+        let proof = Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).unwrap();
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing a custom table");
+        assert!(
+            PlonkVerifier::verify(&[], &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+
+        // The unused column of the gate has to match the padding of the table.
+        trace.set(unused, Scalar::one());
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a custom table");
+        assert_eq!(
+            Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).err(),
+            Some(PlonkError::LookupFailed { row: 1 })
+        );
+
+        assert_eq!(
+            circuit.lookup(table, &[x, x, x, x]).err(),
+            Some(PlonkError::InvalidLength {
+                expected: 3,
+                got: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_poseidon_gadget() {
+        // Knowledge of a preimage (x, y) of a public Poseidon hash.
+        let mut circuit = PlonkCircuit::init();
+        let hash = circuit.public_input();
+        let (x, y, _) = circuit.add_gate();
+        let gadget = poseidon_hash(&mut circuit, &[x, y]).unwrap();
+        circuit.connect(gadget.output(), hash).unwrap();
+        circuit.pad_next_power_of_two();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let preimage = [Scalar::from(3), Scalar::from(4)];
+        let mut trace = ComputationTrace::new(&prover_key.constraints);
+        trace.set(x, preimage[0]);
+        trace.set(y, preimage[1]);
+        trace.set(Wire::output(x.row), preimage[0] + preimage[1]);
+        let public_value = gadget.assign(&mut trace, &preimage);
+        trace.set(hash, public_value);
+
+        let mut prover_transcript = Blake2bTranscript::new(b"testing poseidon");
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing poseidon");
+        let proof =
+            Prover::prove(&[public_value], &prover_key, &trace, &mut prover_transcript).unwrap();
+        assert!(PlonkVerifier::verify(
+            &[public_value],
+            &verifier_key,
+            &proof,
+            &mut verifier_transcript
+        )
+        .is_ok());
+
+        // A different preimage does not hash to the public value.
+        trace.set(y, Scalar::from(5));
+        trace.set(Wire::output(x.row), Scalar::from(8));
+        gadget.assign(&mut trace, &[Scalar::from(3), Scalar::from(5)]);
+        let mut prover_transcript = Blake2bTranscript::new(b"testing poseidon");
+        assert!(
+            Prover::prove(&[public_value], &prover_key, &trace, &mut prover_transcript).is_err()
+        );
+    }
+
+    #[test]
+    fn test_large_circuit() {
+        // A chain of doublings, x_{i+1} = x_i + x_i, with more constraints than the
+        // 128 powers of the SRS we used to hardcode.
+        let mut circuit = PlonkCircuit::init();
+        let gates = (0..130).map(|_| circuit.add_gate()).collect::<Vec<_>>();
+        circuit.pad_next_power_of_two();
+        for (i, (left, right, out)) in gates.iter().enumerate() {
+            circuit.connect(*left, *right).unwrap();
+            if let Some((next_left, _, _)) = gates.get(i + 1) {
+                circuit.connect(*out, *next_left).unwrap();
+            }
+        }
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+        assert_eq!(prover_key.constraints.nr_constraints, 256);
+
+        let mut value = Scalar::one();
+        let mut trace = ComputationTrace {
+            a: vec![],
+            b: vec![],
+            c: vec![],
+        };
+        for _ in 0..130 {
+            trace.a.push(value);
+            trace.b.push(value);
+            value = value.double();
+            trace.c.push(value);
+        }
+        let trace = trace.pad_next_power_two();
+
+        let mut prover_transcript = Blake2bTranscript::new(b"testing a large circuit");
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing a large circuit");
+        let proof = Prover::prove(&[], &prover_key, &trace, &mut prover_transcript).unwrap();
+
+        assert!(
+            PlonkVerifier::verify(&[], &verifier_key, &proof, &mut verifier_transcript).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_next_row() {
+        // A running sum 1 + 1 + 2 + 3 + 4 kept in the left wires, each row adding its right wire
+        // into the left wire of the next one, a' = a + b, without copy constraints.
+        let mut circuit = PlonkCircuit::init();
+        let total = circuit.public_input();
+        let steps = (0..4)
+            .map(|_| circuit.gate(Gate::new().left(1).right(1).next(-1)))
+            .collect::<Vec<_>>();
+        let (sum, _, _) = circuit.gate(Gate::new());
+        circuit.pad_next_power_of_two();
+        circuit.connect(total, sum).unwrap();
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        // The builder derives the left wire of each next row.
+        let mut builder = WitnessBuilder::new(&prover_key.constraints);
+        builder.assign(steps[0].0, Scalar::one()).unwrap();
+        for (i, (_, right, _)) in steps.iter().enumerate() {
+            builder.assign(*right, Scalar::from(i as u64 + 1)).unwrap();
+        }
+        let trace = builder.build().unwrap();
+        assert_eq!(trace.value(sum), Scalar::from(11));
+        assert!(prover_key.constraints.is_satisfied(&trace).is_ok());
+
+        let prove = |pub_in: &[Scalar]| {
+            let mut transcript = Blake2bTranscript::new(b"testing the next row");
+            Prover::prove(pub_in, &prover_key, &trace, &mut transcript)
+        };
+        let proof = prove(&[Scalar::from(11)]).unwrap();
+        let mut transcript = Blake2bTranscript::new(b"testing the next row");
+        assert!(
+            PlonkVerifier::verify(&[Scalar::from(11)], &verifier_key, &proof, &mut transcript)
+                .is_ok()
+        );
+        let mut transcript = Blake2bTranscript::new(b"testing the next row");
+        assert_eq!(
+            PlonkVerifier::verify(&[Scalar::from(10)], &verifier_key, &proof, &mut transcript),
+            Err(PlonkError::VerificationFailed)
+        );
+
+        // A wrong intermediate sum breaks the gates of both rows that use it.
+        let mut trace = trace;
+        trace.set(steps[2].0, Scalar::from(5));
+        assert!(matches!(
+            prover_key.constraints.is_satisfied(&trace),
+            Err(UnsatisfiedGate::Gate { row: 2, .. })
+        ));
+    }
+
     #[test]
     fn test_lb() {
         // initiate a plonk test
-        let (pre_in, _, _) = create_dummy_circuit_and_prover_key();
+        let (pre_in, _, _, _) = create_dummy_circuit_and_prover_key();
 
-        // get the generator of H
+        // get the element of H corresponding to the first row
         let w = pre_in.constraints.extended_h_subgroup[0];
-        println!("w: {:?}",w); // this w, the generator of H
+        println!("w: {:?}", w); // this w, omega^0
 
         // set a point for exaluation
         let x = Scalar::from(10);
@@ -248,7 +1312,6 @@ mod test {
         let den = Scalar::from(8) * (x - w);
         println!("using trick: {:?}", num * den.invert().unwrap());
 
-
         let full_calc = pre_in.constraints.lagrange_basis(0).eval(&x);
         println!("using constraints basis: {:?}", full_calc);
 
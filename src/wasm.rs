@@ -0,0 +1,80 @@
+// Bindings for JavaScript, behind the `wasm` feature, exported with `wasm-bindgen` to the
+// browser and to Node when built for `wasm32-unknown-unknown`, e.g. with
+//
+// wasm-pack build --target web -- --features wasm
+//
+// Every function takes and returns byte buffers (`Uint8Array` on the JavaScript side), and
+// errors as their message, which are thrown as exceptions. The encodings are described in
+// `bindings.rs`. The blinding scalars of the prover come from `crypto.getRandomValues`,
+// through the `js` feature of `getrandom`.
+use crate::bindings;
+use crate::error::PlonkError;
+use crate::verifier::VerifierKey;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Prover key of the circuit, as written by `ProverKey::serialize`, with the SRS of a Powers
+/// of Tau response file of `tau_powers_length` powers in G2.
+#[wasm_bindgen]
+pub fn prover_key(
+    circuit_bytes: &[u8],
+    srs_bytes: &[u8],
+    tau_powers_length: usize,
+) -> Result<Vec<u8>, String> {
+    bindings::prover_key(circuit_bytes, srs_bytes, tau_powers_length)
+        .map_err(|error| error.to_string())
+}
+
+/// Verifier key of the prover key, as written by `VerifierKey::serialize`.
+#[wasm_bindgen]
+pub fn verifier_key(pk_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    bindings::verifier_key(pk_bytes).map_err(|error| error.to_string())
+}
+
+/// Proves that the trace satisfies the circuit of the prover key. The public inputs are read
+/// from the trace, at the rows of the public inputs of the circuit.
+#[wasm_bindgen]
+pub fn prove(pk_bytes: &[u8], witness_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    bindings::prove(pk_bytes, witness_bytes).map_err(|error| error.to_string())
+}
+
+/// Checks a proof, returning whether it is valid. Malformed inputs are errors.
+#[wasm_bindgen]
+pub fn verify(vk_bytes: &[u8], proof_bytes: &[u8], public_inputs: &[u8]) -> Result<bool, String> {
+    let result = VerifierKey::deserialize(vk_bytes)
+        .and_then(|verifier_key| bindings::verify(&verifier_key, proof_bytes, public_inputs));
+    match result {
+        Ok(()) => Ok(true),
+        Err(PlonkError::VerificationFailed) => Ok(false),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::tests::{square_circuit, TAU_POWERS_LENGTH};
+    use blstrs::Scalar;
+
+    #[test]
+    fn test_wasm_bindings() {
+        let (circuit_bytes, witness_bytes, srs_bytes) = square_circuit(3);
+        let pk_bytes = prover_key(&circuit_bytes, &srs_bytes, TAU_POWERS_LENGTH).unwrap();
+        let vk_bytes = verifier_key(&pk_bytes).unwrap();
+        let proof = prove(&pk_bytes, &witness_bytes).unwrap();
+        let nine = Scalar::from(9).to_bytes_be();
+        assert_eq!(verify(&vk_bytes, &proof, &nine), Ok(true));
+        assert_eq!(
+            verify(&vk_bytes, &proof, &Scalar::from(4).to_bytes_be()),
+            Ok(false)
+        );
+        assert_eq!(
+            verify(&vk_bytes, &proof, &nine[..31]),
+            Err(PlonkError::InvalidLength {
+                expected: 32,
+                got: 31
+            }
+            .to_string())
+        );
+        assert!(verify(&vk_bytes[1..], &proof, &nine).is_err());
+    }
+}
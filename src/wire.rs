@@ -0,0 +1,106 @@
+// Wires of the circuit. The permutation argument identifies the wires of the WIDTH columns
+// with the cosets H, K1 * H, ..., K_{WIDTH - 1} * H of the evaluation domain H, so the
+// generators must lie in pairwise different cosets of H. Up to MAX_WIDTH generators are
+// defined, so that the permutation layer (wire indices, the extended subgroup and sigma*) is
+// independent of the number of columns; the gate equation, the prover and the verifier use
+// WIDTH = 3.
+#![allow(non_snake_case)]
+use blstrs::Scalar;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Number of wire columns of a gate.
+pub(crate) const WIDTH: usize = 3;
+
+/// Largest number of columns for which coset generators are defined.
+pub(crate) const MAX_WIDTH: usize = 8;
+
+// K_0 = 1 and small primes after it, checked to be in different cosets of the largest
+// 2-adic subgroup in the tests.
+const COSETS: [u64; MAX_WIDTH] = [1, 7, 13, 17, 19, 23, 29, 31];
+
+/// Generator of the coset of H holding the wires of `column`.
+pub(crate) fn K(column: usize) -> Scalar {
+    Scalar::from(COSETS[column])
+}
+pub(crate) fn K1() -> Scalar {
+    K(1)
+}
+pub(crate) fn K2() -> Scalar {
+    K(2)
+}
+
+/// Handle to one of the three wires (left, right or output) of a gate.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Wire {
+    pub(crate) row: usize,
+    pub(crate) column: usize,
+}
+
+impl Wire {
+    /// Left wire (`a`) of the gate at `row`.
+    pub fn left(row: usize) -> Self {
+        Self { row, column: 0 }
+    }
+
+    /// Right wire (`b`) of the gate at `row`.
+    pub fn right(row: usize) -> Self {
+        Self { row, column: 1 }
+    }
+
+    /// Output wire (`c`) of the gate at `row`.
+    pub fn output(row: usize) -> Self {
+        Self { row, column: 2 }
+    }
+
+    /// Wire of `column` of the gate at `row`.
+    pub(crate) fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+
+    /// Position of the wire in the extended witness (a || b || c), which depends on
+    /// the (padded) number of constraints.
+    pub(crate) fn index(&self, nr_constraints: usize) -> usize {
+        self.column * nr_constraints + self.row
+    }
+
+    pub(crate) fn from_index(index: usize, nr_constraints: usize) -> Self {
+        Self {
+            row: index % nr_constraints,
+            column: index / nr_constraints,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+
+    #[test]
+    fn test_cosets() {
+        // x is in the 2-adic subgroup of order 2^32, which contains every domain H, iff
+        // x^t = 1 with t = (r - 1) / 2^32, so the generators must have different t-th powers.
+        let t = [
+            0xfffe5bfeffffffff,
+            0x09a1d80553bda402,
+            0x299d7d483339d808,
+            0x0000000073eda753,
+        ];
+        let powers = (0..MAX_WIDTH)
+            .map(|column| K(column).pow_vartime(t))
+            .collect::<Vec<_>>();
+        assert_eq!(powers[0], Scalar::one());
+        for i in 0..MAX_WIDTH {
+            for j in 0..i {
+                assert_ne!(powers[i], powers[j], "K({}) and K({})", j, i);
+            }
+        }
+        assert_eq!((K1(), K2()), (Scalar::from(7), Scalar::from(13)));
+
+        let wire = Wire::new(5, 2);
+        assert_eq!(wire, Wire::output(5));
+        assert_eq!(Wire::from_index(wire.index(8), 8), wire);
+    }
+}
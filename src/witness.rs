@@ -0,0 +1,246 @@
+// Witness generation. Instead of filling every wire of the trace by hand, the prover only
+// assigns the free inputs of the circuit, and the remaining wires are derived from them: a
+// value is copied to every wire connected to it, and the gates are evaluated in order, each
+// one computing its output from its left and right wires.
+//
+// A gate with q_O != 0 determines its output as c = -(q_L * a + q_R * b + q_M * a * b + q_C) / q_O,
+// and a lookup gate takes as output the third column of the first row of its table that starts
+// with (a, b). Outputs that are already assigned are checked against the computed value, so
+// gates used backwards (e.g. a division q * y = x) need their free input assigned as well.
+// A gate with q_O = 0 that uses the next row (q_N != 0) determines its left wire a' instead,
+// as a' = -(q_L * a + q_R * b + q_M * a * b + q_C) / q_N, which chains rows such as the steps of
+// a running sum; when q_O != 0, a' must be known before the gate is evaluated. Other gates with
+// q_O = 0 (e.g. boolean checks) do not determine any wire, so all their wires must be assigned
+// or connected to assigned ones. Wires that are never assigned are zero, and the trace is
+// checked against the circuit before it is returned, so that a missing assignment fails there
+// rather than in the prover.
+use crate::error::PlonkError;
+use crate::plonk::{ComputationTrace, PlonkCircuit, Wire};
+use blstrs::Scalar;
+use ff::Field;
+use std::collections::HashMap;
+
+/// Builder of a computation trace from the free inputs of a circuit.
+pub struct WitnessBuilder<'a> {
+    circuit: &'a PlonkCircuit,
+    values: HashMap<Wire, Scalar>,
+}
+
+impl<'a> WitnessBuilder<'a> {
+    /// Creates a builder for `circuit` without any assigned wire.
+    pub fn new(circuit: &'a PlonkCircuit) -> Self {
+        WitnessBuilder {
+            circuit,
+            values: HashMap::new(),
+        }
+    }
+
+    /// Assigns `value` to `wire`, and to every wire connected to it. Fails if one of them
+    /// already has a different value.
+    pub fn assign(&mut self, wire: Wire, value: Scalar) -> Result<&mut Self, PlonkError> {
+        let mut current = wire;
+        loop {
+            match self.values.get(&current) {
+                Some(assigned) if *assigned != value => {
+                    return Err(PlonkError::CopyConstraintUnsatisfied {
+                        wire,
+                        connected: current,
+                    })
+                }
+                _ => {
+                    self.values.insert(current, value);
+                }
+            }
+            current = *self
+                .circuit
+                .permutations
+                .get(&current)
+                .ok_or(PlonkError::UnknownWire(current))?;
+            if current == wire {
+                return Ok(self);
+            }
+        }
+    }
+
+    /// Value of `wire`, if it was assigned or derived so far.
+    pub fn value(&self, wire: Wire) -> Option<Scalar> {
+        self.values.get(&wire).copied()
+    }
+
+    /// Assigns the constant gates, evaluates the gates in order, and returns the resulting
+    /// trace, once checked with `PlonkCircuit::is_satisfied`. Fails if a derived value
+    /// conflicts with an assigned one, if a lookup gate has no matching row, or if the trace
+    /// does not satisfy the circuit.
+    pub fn build(mut self) -> Result<ComputationTrace, PlonkError> {
+        for (row, value) in self.circuit.constants.iter() {
+            self.assign(Wire::left(*row), *value)?;
+        }
+
+        let q = &self.circuit.constraints;
+        for row in 0..self.circuit.nr_constraints {
+            let output = Wire::output(row);
+            let a = self.value(Wire::left(row)).unwrap_or(Scalar::zero());
+            let b = self.value(Wire::right(row)).unwrap_or(Scalar::zero());
+            let next = Wire::left((row + 1) % self.circuit.nr_constraints);
+
+            if q.qk[row] == Scalar::one() {
+                // Tables with three columns may have several rows starting with (a, b).
+                if self.values.contains_key(&output) {
+                    continue;
+                }
+                let c = self.lookup(row, a, b)?;
+                self.assign(output, c)?;
+            } else if let Some(inverse) = Option::<Scalar>::from(q.qo[row].invert()) {
+                let next = q.qn[row] * self.value(next).unwrap_or(Scalar::zero());
+                let c = -(q.ql[row] * a + q.qr[row] * b + q.qm[row] * a * b + q.qc[row] + next)
+                    * inverse;
+                self.assign(output, c)?;
+            } else if let Some(inverse) = Option::<Scalar>::from(q.qn[row].invert()) {
+                let value =
+                    -(q.ql[row] * a + q.qr[row] * b + q.qm[row] * a * b + q.qc[row]) * inverse;
+                self.assign(next, value)?;
+            }
+        }
+
+        let mut trace = ComputationTrace::new(self.circuit);
+        for (wire, value) in self.values {
+            trace.set(wire, value);
+        }
+        self.circuit.is_satisfied(&trace)?;
+        Ok(trace)
+    }
+
+    // Output of the first row of the table of the lookup gate at `row` that starts with (a, b).
+    fn lookup(&self, row: usize, a: Scalar, b: Scalar) -> Result<Scalar, PlonkError> {
+        let table_id = self.circuit.constraints.qt[row];
+        self.circuit
+            .lookup_tables
+            .iter()
+            .zip(1u64..)
+            .find(|(_, id)| Scalar::from(*id) == table_id)
+            .and_then(|(table, _)| table.0.iter().find(|t| t[0] == a && t[1] == b))
+            .map(|t| t[2])
+            .ok_or(PlonkError::LookupFailed { row })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plonk::Gate;
+    use crate::prover::Prover;
+    use crate::transcript::{Blake2bTranscript, Transcript};
+    use crate::verifier::PlonkVerifier;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_witness_builder() {
+        // x^2 + y^2 = z^2, with x^2 public, and z * y + z^2 as a last output.
+        let mut circuit = PlonkCircuit::init();
+        let pi = circuit.public_input();
+        let (x_l, x_r, x_sq) = circuit.mult_gate();
+        let (y_l, y_r, y_sq) = circuit.mult_gate();
+        let (z_l, z_r, z_sq) = circuit.mult_gate();
+        let (sum_l, sum_r, sum_o) = circuit.add_gate();
+        let (zy_l, zy_r, zy_o) = circuit.mult_gate();
+        let (res_l, res_r, res_o) = circuit.add_gate();
+        circuit.pad_next_power_of_two();
+        for (left, right) in [
+            (pi, x_sq),
+            (x_l, x_r),
+            (x_sq, sum_l),
+            (y_l, y_r),
+            (y_sq, sum_r),
+            (z_l, z_r),
+            (z_sq, sum_o),
+            (z_l, zy_l),
+            (y_l, zy_r),
+            (zy_o, res_l),
+            (z_sq, res_r),
+        ] {
+            circuit.connect(left, right).unwrap();
+        }
+        let (prover_key, verifier_key) = circuit.setup(&mut OsRng).unwrap();
+
+        let mut witness = WitnessBuilder::new(&prover_key.constraints);
+        witness
+            .assign(x_l, Scalar::from(3))
+            .unwrap()
+            .assign(y_l, Scalar::from(4))
+            .unwrap();
+        // The assignment of z is copied to the right wire of the product z * y.
+        witness.assign(z_r, Scalar::from(5)).unwrap();
+        assert_eq!(witness.value(zy_l), Some(Scalar::from(5)));
+        let trace = witness.build().unwrap();
+        assert_eq!(trace.c[res_o.row], Scalar::from(45));
+        assert_eq!(trace.a[pi.row], Scalar::from(9));
+
+        let mut prover_transcript = Blake2bTranscript::new(b"testing the witness builder");
+        let proof = Prover::prove(
+            &[Scalar::from(9)],
+            &prover_key,
+            &trace,
+            &mut prover_transcript,
+        )
+        .unwrap();
+        let mut verifier_transcript = Blake2bTranscript::new(b"testing the witness builder");
+        assert!(PlonkVerifier::verify(
+            &[Scalar::from(9)],
+            &verifier_key,
+            &proof,
+            &mut verifier_transcript
+        )
+        .is_ok());
+
+        // A public input that does not match x^2 conflicts with the output of the square.
+        let mut witness = WitnessBuilder::new(&prover_key.constraints);
+        witness.assign(pi, Scalar::from(10)).unwrap();
+        witness.assign(x_l, Scalar::from(3)).unwrap();
+        assert!(matches!(
+            witness.build().err(),
+            Some(PlonkError::CopyConstraintUnsatisfied { .. })
+        ));
+    }
+
+    #[test]
+    fn test_witness_unassigned() {
+        // A gate without output, x - 1 = 0, is not satisfied if x is left unassigned.
+        let mut circuit = PlonkCircuit::init();
+        let (x, _, _) = circuit.gate(Gate::new().left(1).constant(-1));
+        assert_eq!(
+            WitnessBuilder::new(&circuit).build().err(),
+            Some(PlonkError::GateFailed {
+                row: 0,
+                label: None
+            })
+        );
+        let mut witness = WitnessBuilder::new(&circuit);
+        witness.assign(x, Scalar::one()).unwrap();
+        assert!(witness.build().is_ok());
+    }
+
+    #[test]
+    fn test_witness_lookup() {
+        let mut circuit = PlonkCircuit::init();
+        let (x, y, _) = circuit.add_gate();
+        let xor = circuit.xor(x, y, 2).unwrap();
+        let (xor_copy, y_copy, sum) = circuit.add_gate();
+        circuit.connect(xor, xor_copy).unwrap();
+        circuit.connect(y, y_copy).unwrap();
+
+        let mut witness = WitnessBuilder::new(&circuit);
+        witness.assign(x, Scalar::from(2)).unwrap();
+        witness.assign(y, Scalar::from(3)).unwrap();
+        let trace = witness.build().unwrap();
+        assert_eq!(trace.c[xor.row], Scalar::from(1));
+        assert_eq!(trace.c[0], Scalar::from(5));
+        assert_eq!(trace.c[sum.row], Scalar::from(4));
+
+        let mut witness = WitnessBuilder::new(&circuit);
+        witness.assign(x, Scalar::from(4)).unwrap();
+        assert_eq!(
+            witness.build().err(),
+            Some(PlonkError::LookupFailed { row: 1 })
+        );
+    }
+}